@@ -0,0 +1,92 @@
+// cargo bench --bench handlers
+// whole requests through the router and its middleware, nothing listens on a port
+
+use std::hint::black_box;
+
+use axum::body::{to_bytes, Body};
+use axum::http::{header, Method, Request, StatusCode};
+use axum::Router;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rust_hello_http::responses::ResponseCache;
+use rust_hello_http::{generator, AppBuilder};
+use serde_json::json;
+use tower::ServiceExt;
+
+const SIZES: &[usize] = &[100, 10_000];
+// requests in flight at once for the concurrent ones
+const CONCURRENCY: usize = 16;
+
+// without the response cache, so every read does the work
+fn app(size: usize) -> Router {
+    let app = AppBuilder::new().response_cache(ResponseCache::off()).build();
+    generator::generate(&app.state, size, 1, |_| {});
+    app.router
+}
+
+async fn send(router: &Router, method: Method, uri: &str, body: Body) -> StatusCode {
+    let request = Request::builder().method(method).uri(uri).header(header::CONTENT_TYPE, "application/json").body(body).unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    black_box(to_bytes(response.into_body(), usize::MAX).await.unwrap());
+    status
+}
+
+fn handlers(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_multi_thread().worker_threads(4).enable_all().build().unwrap();
+    let mut group = c.benchmark_group("handlers");
+    for &size in SIZES {
+        let router = app(size);
+        let cached = {
+            let app = AppBuilder::new().build();
+            generator::generate(&app.state, size, 1, |_| {});
+            app.router
+        };
+        let middle = format!("/pokemon/{}", size / 2);
+        group.bench_with_input(BenchmarkId::new("get_pokemon", size), &size, |b, _| {
+            b.to_async(&runtime).iter(|| send(&router, Method::GET, &middle, Body::empty()))
+        });
+        // streamed past 1000
+        group.bench_with_input(BenchmarkId::new("list_pokemon", size), &size, |b, _| {
+            b.to_async(&runtime).iter(|| send(&router, Method::GET, "/pokemon", Body::empty()))
+        });
+        // the same from the response cache, the streamed ones are never kept
+        group.bench_with_input(BenchmarkId::new("list_pokemon_cached", size), &size, |b, _| {
+            b.to_async(&runtime).iter(|| send(&cached, Method::GET, "/pokemon", Body::empty()))
+        });
+        // looks at every one and finds none
+        group.bench_with_input(BenchmarkId::new("filter_pokemon", size), &size, |b, _| {
+            b.to_async(&runtime).iter(|| send(&router, Method::GET, "/pokemon?favorite=true", Body::empty()))
+        });
+        // a fresh app each time, or the team would keep growing
+        group.bench_with_input(BenchmarkId::new("create_pokemon", size), &size, |b, &size| {
+            let body = json!({ "name": "Pikachu", "level": 10, "poke_type": "Electric" }).to_string();
+            b.to_async(&runtime).iter_batched(|| app(size), |router| {
+                let body = body.clone();
+                async move { send(&router, Method::POST, "/pokemon", Body::from(body)).await }
+            }, criterion::BatchSize::LargeInput)
+        });
+        // reads and writes from many clients at once, all after the same locks
+        group.bench_with_input(BenchmarkId::new("concurrent_mixed", size), &size, |b, &size| {
+            b.to_async(&runtime).iter(|| async {
+                let requests: Vec<_> = (0..CONCURRENCY).map(|i| {
+                    let router = router.clone();
+                    tokio::spawn(async move {
+                        let id = i * size / CONCURRENCY + 1;
+                        if i % 4 == 0 {
+                            send(&router, Method::POST, &format!("/pokemon/{}/gain-exp", id), Body::from(json!({ "amount": 10 }).to_string())).await
+                        } else {
+                            send(&router, Method::GET, &format!("/pokemon/{}", id), Body::empty()).await
+                        }
+                    })
+                }).collect();
+                for request in requests {
+                    request.await.unwrap();
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, handlers);
+criterion_main!(benches);
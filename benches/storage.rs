@@ -0,0 +1,226 @@
+// cargo bench --bench storage
+// the collections are a Mutex<Vec<T>> each, these are what they'd be up against
+
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::sync::{Arc, Mutex, RwLock};
+
+use axum::body::to_bytes;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rust_hello_http::generator::random_pokemon;
+use rust_hello_http::models::Pokemon;
+use rust_hello_http::streaming;
+use tokio::sync::{mpsc, oneshot};
+
+const SIZES: &[usize] = &[100, 10_000];
+
+// tasks hammering the state at once, and how many operations each
+const TASKS: usize = 8;
+const OPS_PER_TASK: usize = 500;
+// one in this many is a write, about what the API sees
+const WRITE_EVERY: usize = 10;
+
+fn team(size: usize) -> Vec<Pokemon> {
+    let mut rng = StdRng::seed_from_u64(1);
+    (1..=size as u32).map(|id| random_pokemon(id, &mut rng)).collect()
+}
+
+fn lookup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lookup");
+    for &size in SIZES {
+        let vec = team(size);
+        let map: HashMap<u32, Pokemon> = vec.iter().map(|p| (p.id, p.clone())).collect();
+        let ids: Vec<u32> = (0..1000).map(|i| (i * 7919 % size + 1) as u32).collect();
+        // what the handlers do now
+        group.bench_with_input(BenchmarkId::new("vec_find", size), &size, |b, _| {
+            b.iter(|| ids.iter().filter_map(|id| vec.iter().find(|p| p.id == *id)).map(|p| p.level).sum::<u32>())
+        });
+        // the IDs only ever go up, so the Vec is already sorted by them
+        group.bench_with_input(BenchmarkId::new("vec_binary_search", size), &size, |b, _| {
+            b.iter(|| ids.iter().filter_map(|id| vec.binary_search_by_key(id, |p| p.id).ok()).map(|i| vec[i].level).sum::<u32>())
+        });
+        group.bench_with_input(BenchmarkId::new("hashmap_get", size), &size, |b, _| {
+            b.iter(|| ids.iter().filter_map(|id| map.get(id)).map(|p| p.level).sum::<u32>())
+        });
+    }
+    group.finish();
+}
+
+fn insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert");
+    for &size in SIZES {
+        let records = team(size);
+        group.bench_with_input(BenchmarkId::new("vec_push", size), &records, |b, records| {
+            b.iter(|| {
+                let mut vec = Vec::new();
+                for p in records {
+                    vec.push(p.clone());
+                }
+                vec
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("hashmap_insert", size), &records, |b, records| {
+            b.iter(|| {
+                let mut map = HashMap::new();
+                for p in records {
+                    map.insert(p.id, p.clone());
+                }
+                map
+            })
+        });
+    }
+    group.finish();
+}
+
+// the three ways to share the team between tasks, each answers a read with the level and a write with a level up
+trait Store: Clone + Send + Sync + 'static {
+    fn read(&self, id: u32) -> impl std::future::Future<Output = Option<u32>> + Send;
+    fn write(&self, id: u32) -> impl std::future::Future<Output = ()> + Send;
+}
+
+#[derive(Clone)]
+struct MutexStore(Arc<Mutex<Vec<Pokemon>>>);
+
+impl Store for MutexStore{
+    async fn read(&self, id: u32) -> Option<u32> {
+        self.0.lock().unwrap().iter().find(|p| p.id == id).map(|p| p.level)
+    }
+
+    async fn write(&self, id: u32) {
+        if let Some(p) = self.0.lock().unwrap().iter_mut().find(|p| p.id == id) {
+            p.level = (p.level % 100) + 1;
+        }
+    }
+}
+
+#[derive(Clone)]
+struct RwLockStore(Arc<RwLock<Vec<Pokemon>>>);
+
+impl Store for RwLockStore{
+    async fn read(&self, id: u32) -> Option<u32> {
+        self.0.read().unwrap().iter().find(|p| p.id == id).map(|p| p.level)
+    }
+
+    async fn write(&self, id: u32) {
+        if let Some(p) = self.0.write().unwrap().iter_mut().find(|p| p.id == id) {
+            p.level = (p.level % 100) + 1;
+        }
+    }
+}
+
+enum Message{
+    Read(u32, oneshot::Sender<Option<u32>>),
+    Write(u32, oneshot::Sender<()>),
+}
+
+// one task owns the team and the rest send it messages
+#[derive(Clone)]
+struct ActorStore(mpsc::Sender<Message>);
+
+impl ActorStore{
+    fn spawn(mut team: Vec<Pokemon>) -> ActorStore {
+        let (sender, mut inbox) = mpsc::channel(1024);
+        tokio::spawn(async move {
+            while let Some(message) = inbox.recv().await {
+                match message {
+                    Message::Read(id, reply) => {
+                        let _ = reply.send(team.iter().find(|p| p.id == id).map(|p| p.level));
+                    }
+                    Message::Write(id, reply) => {
+                        if let Some(p) = team.iter_mut().find(|p| p.id == id) {
+                            p.level = (p.level % 100) + 1;
+                        }
+                        let _ = reply.send(());
+                    }
+                }
+            }
+        });
+        ActorStore(sender)
+    }
+}
+
+impl Store for ActorStore{
+    async fn read(&self, id: u32) -> Option<u32> {
+        let (reply, answer) = oneshot::channel();
+        self.0.send(Message::Read(id, reply)).await.ok()?;
+        answer.await.ok().flatten()
+    }
+
+    async fn write(&self, id: u32) {
+        let (reply, answer) = oneshot::channel();
+        if self.0.send(Message::Write(id, reply)).await.is_ok() {
+            let _ = answer.await;
+        }
+    }
+}
+
+async fn load(store: impl Store, size: usize) {
+    let tasks: Vec<_> = (0..TASKS).map(|task| {
+        let store = store.clone();
+        tokio::spawn(async move {
+            let mut rng = StdRng::seed_from_u64(task as u64);
+            for op in 0..OPS_PER_TASK {
+                let id = rng.random_range(1..=size as u32);
+                if op % WRITE_EVERY == 0 {
+                    store.write(id).await;
+                } else {
+                    black_box(store.read(id).await);
+                }
+            }
+        })
+    }).collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+}
+
+fn shared_state(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_multi_thread().worker_threads(4).enable_all().build().unwrap();
+    let mut group = c.benchmark_group("shared_state");
+    for &size in SIZES {
+        let mutex = MutexStore(Arc::new(Mutex::new(team(size))));
+        let rwlock = RwLockStore(Arc::new(RwLock::new(team(size))));
+        let actor = runtime.block_on(async { ActorStore::spawn(team(size)) });
+        group.bench_with_input(BenchmarkId::new("mutex", size), &size, |b, &size| b.to_async(&runtime).iter(|| load(mutex.clone(), size)));
+        group.bench_with_input(BenchmarkId::new("rwlock", size), &size, |b, &size| b.to_async(&runtime).iter(|| load(rwlock.clone(), size)));
+        group.bench_with_input(BenchmarkId::new("actor", size), &size, |b, &size| b.to_async(&runtime).iter(|| load(actor.clone(), size)));
+    }
+    group.finish();
+}
+
+fn serialization(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    let mut group = c.benchmark_group("serialize");
+    for &size in SIZES {
+        let records = team(size);
+        // what Json() does
+        group.bench_with_input(BenchmarkId::new("to_vec", size), &records, |b, records| b.iter(|| serde_json::to_vec(records).unwrap()));
+        // the string first, then the bytes
+        group.bench_with_input(BenchmarkId::new("to_string", size), &records, |b, records| b.iter(|| serde_json::to_string(records).unwrap().into_bytes()));
+        // the same buffer every time
+        let mut buffer = Vec::new();
+        group.bench_with_input(BenchmarkId::new("to_writer_reused", size), &records, |b, records| {
+            b.iter(|| {
+                buffer.clear();
+                serde_json::to_writer(&mut buffer, records).unwrap();
+                buffer.len()
+            })
+        });
+        // how the long lists go out, collected here so the whole body is counted
+        let shared = Arc::new(records.clone());
+        group.bench_with_input(BenchmarkId::new("streamed", size), &shared, |b, shared| {
+            b.to_async(&runtime).iter(|| async {
+                let records = shared.clone();
+                let keys: Vec<usize> = (0..records.len()).collect();
+                let response = streaming::array(keys, move |i| records.get(i).cloned());
+                to_bytes(response.into_body(), usize::MAX).await.unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, lookup, insert, shared_state, serialization);
+criterion_main!(benches);
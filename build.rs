@@ -0,0 +1,32 @@
+// what GET /version and the startup banner say about this build, see src/version.rs
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok().filter(|o| o.status.success())?;
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string()).filter(|s| !s.is_empty())
+}
+
+fn main() {
+    // a source tarball without .git still builds, it just doesn't know its commit
+    let sha = output("git", &["rev-parse", "HEAD"]).unwrap_or_else(|| "unknown".to_string());
+    // SOURCE_DATE_EPOCH for reproducible builds, the same source then builds to the same binary
+    let built_at = std::env::var("SOURCE_DATE_EPOCH").ok().and_then(|s| s.parse().ok())
+        .unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()));
+    let rustc = output(&std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string()), &["--version"]).unwrap_or_default();
+    // cargo sets CARGO_FEATURE_<NAME> for each one that's on, "tokio-console" comes through as TOKIO_CONSOLE
+    let mut features: Vec<String> = std::env::vars()
+        .filter_map(|(name, _)| name.strip_prefix("CARGO_FEATURE_").map(|f| f.to_lowercase().replace('_', "-")))
+        .collect();
+    features.sort();
+
+    println!("cargo:rustc-env=BUILD_GIT_SHA={}", sha);
+    println!("cargo:rustc-env=BUILD_TIMESTAMP={}", built_at);
+    println!("cargo:rustc-env=BUILD_RUSTC={}", rustc);
+    println!("cargo:rustc-env=BUILD_FEATURES={}", features.join(","));
+    // built again for a new commit, not for every change to the source
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+}
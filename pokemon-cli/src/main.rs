@@ -0,0 +1,234 @@
+// the API from the command line, e.g. `pokemon-cli pokemon add --name Pikachu --type electric --level 12`
+use std::collections::BTreeMap;
+use std::process::exit;
+
+use pokemon_client::{Client, CreatePokemon, CreateTrainer, Pokemon, PokemonFilter, Trainer, UpdatePokemon};
+use serde::{de::DeserializeOwned, Serialize};
+
+const USAGE: &str = "\
+usage: pokemon-cli [--server URL] [--trainer ID] [--json] <command>
+
+commands:
+  pokemon list [--tag TAG] [--favorite true|false] [--generation I] [--region kanto]
+  pokemon get <id>
+  pokemon add --name NAME [--type TYPE] [--secondary-type TYPE] --level LEVEL [--visibility private|unlisted|public]
+  pokemon update <id> [--name NAME] [--type TYPE] [--level LEVEL] [--visibility private|unlisted|public]
+  pokemon delete <id>
+  trainer list
+  trainer get <id>
+  trainer add --name NAME [--team 1,2,3] [--email ADDRESS]
+  trainer team <id> <pokemon ids, e.g. 1,2,3>
+
+--server is POKEMON_SERVER if it's set, http://localhost:3000 otherwise
+--trainer sends X-Trainer-Id, to see that trainer's private Pokemon and to set their team
+--json prints what the server sent instead of a table";
+
+enum Command{
+    ListPokemon(PokemonFilter),
+    GetPokemon(u32),
+    AddPokemon(CreatePokemon),
+    UpdatePokemon(u32, UpdatePokemon),
+    DeletePokemon(u32),
+    ListTrainers,
+    GetTrainer(u32),
+    AddTrainer(CreateTrainer),
+    SetTeam(u32, Vec<u32>),
+}
+
+struct Options{
+    server: String,
+    trainer: Option<u32>,
+    json: bool,
+}
+
+#[tokio::main]
+async fn main() {
+    let (options, command) = parse(std::env::args().skip(1).collect()).unwrap_or_else(|e| {
+        eprintln!("{}\n\n{}", e, USAGE);
+        exit(2);
+    });
+    let mut client = Client::new(options.server);
+    if let Some(trainer_id) = options.trainer {
+        client = client.as_trainer(trainer_id);
+    }
+    if let Err(e) = run(&client, command, options.json).await {
+        eprintln!("error: {}", e);
+        exit(1);
+    }
+}
+
+async fn run(client: &Client, command: Command, json: bool) -> Result<(), pokemon_client::Error> {
+    match command {
+        Command::ListPokemon(filter) => show(&client.list_pokemon(&filter).await?, json, |list| pokemon_table(list)),
+        Command::GetPokemon(id) => show(&client.get_pokemon(id).await?, json, |p| pokemon_table(std::slice::from_ref(p))),
+        Command::AddPokemon(pokemon) => show(&client.create_pokemon(&pokemon).await?, json, |p| pokemon_table(std::slice::from_ref(p))),
+        Command::UpdatePokemon(id, changes) => {
+            show(&client.update_pokemon(id, &changes).await?, json, |p| pokemon_table(std::slice::from_ref(p)))
+        }
+        Command::DeletePokemon(id) => {
+            client.delete_pokemon(id).await?;
+            if !json {
+                println!("deleted pokemon {}", id);
+            }
+        }
+        Command::ListTrainers => show(&client.list_trainers().await?, json, |list| trainer_table(list)),
+        Command::GetTrainer(id) => show(&client.get_trainer(id).await?, json, |t| trainer_table(std::slice::from_ref(t))),
+        Command::AddTrainer(trainer) => show(&client.create_trainer(&trainer).await?, json, |t| trainer_table(std::slice::from_ref(t))),
+        Command::SetTeam(id, team) => show(&client.set_team(id, &team).await?, json, |t| trainer_table(std::slice::from_ref(t))),
+    }
+    Ok(())
+}
+
+fn show<T: Serialize>(value: &T, json: bool, table: impl Fn(&T)) {
+    if json {
+        println!("{}", serde_json::to_string_pretty(value).expect("API types always serialize"));
+    } else {
+        table(value);
+    }
+}
+
+fn pokemon_table(pokemon: &[Pokemon]) {
+    let rows = pokemon.iter().map(|p| {
+        let types = match &p.secondary_type {
+            Some(secondary) => format!("{}/{}", p.poke_type, secondary),
+            None => p.poke_type.clone(),
+        };
+        let hp = format!("{}/{}", p.current_hp, p.max_hp);
+        vec![p.id.to_string(), p.name.clone(), p.species.clone(), types, p.level.to_string(), hp]
+    });
+    print_table(&["ID", "NAME", "SPECIES", "TYPE", "LEVEL", "HP"], rows.collect());
+}
+
+fn trainer_table(trainers: &[Trainer]) {
+    let ids = |ids: &[u32]| ids.iter().map(u32::to_string).collect::<Vec<_>>().join(",");
+    let rows = trainers.iter().map(|t| {
+        vec![t.id.to_string(), t.name.clone(), ids(&t.team), ids(&t.pc_box), t.badges.len().to_string(), t.money.to_string()]
+    });
+    print_table(&["ID", "NAME", "TEAM", "BOX", "BADGES", "MONEY"], rows.collect());
+}
+
+// every column as wide as its widest cell
+fn print_table(headers: &[&str], rows: Vec<Vec<String>>) {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let line = |cells: Vec<&str>| {
+        let padded: Vec<String> = cells.iter().zip(&widths).map(|(cell, width)| format!("{:<width$}", cell, width = width)).collect();
+        println!("{}", padded.join("  ").trim_end());
+    };
+    line(headers.to_vec());
+    for row in &rows {
+        line(row.iter().map(String::as_str).collect());
+    }
+}
+
+// the global options can go anywhere, everything after the command and its arguments is --flag value pairs
+fn parse(args: Vec<String>) -> Result<(Options, Command), String> {
+    let mut options = Options {
+        server: std::env::var("POKEMON_SERVER").unwrap_or_else(|_| "http://localhost:3000".to_string()),
+        trainer: None,
+        json: false,
+    };
+    let mut words = Vec::new();
+    let mut flags = BTreeMap::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let Some(flag) = arg.strip_prefix("--") else {
+            words.push(arg);
+            continue;
+        };
+        if matches!(flag, "json" | "help") {
+            flags.insert(flag.to_string(), String::new());
+            continue;
+        }
+        let value = args.next().ok_or_else(|| format!("--{} needs a value", flag))?;
+        flags.insert(flag.to_string(), value);
+    }
+    if flags.contains_key("help") {
+        println!("{}", USAGE);
+        exit(0);
+    }
+    let mut flags = Flags(flags);
+    if let Some(server) = flags.take("server") {
+        options.server = server;
+    }
+    options.trainer = flags.number("trainer")?;
+    options.json = flags.take("json").is_some();
+
+    let words: Vec<&str> = words.iter().map(String::as_str).collect();
+    let command = match words.as_slice() {
+        ["pokemon", "list"] => Command::ListPokemon(PokemonFilter {
+            tag: flags.take("tag"),
+            favorite: flags.parsed("favorite")?,
+            generation: flags.parsed("generation")?,
+            region: flags.parsed("region")?,
+            ..PokemonFilter::default()
+        }),
+        ["pokemon", "get", id] => Command::GetPokemon(id_from(id)?),
+        ["pokemon", "add"] => Command::AddPokemon(CreatePokemon {
+            name: flags.take("name").ok_or("pokemon add needs --name")?,
+            poke_type: flags.take("type"),
+            secondary_type: flags.take("secondary-type"),
+            level: flags.number("level")?.ok_or("pokemon add needs --level")?,
+            visibility: flags.parsed("visibility")?,
+            ..CreatePokemon::default()
+        }),
+        ["pokemon", "update", id] => Command::UpdatePokemon(id_from(id)?, UpdatePokemon {
+            name: flags.take("name"),
+            poke_type: flags.take("type"),
+            level: flags.number("level")?,
+            visibility: flags.parsed("visibility")?,
+            ..UpdatePokemon::default()
+        }),
+        ["pokemon", "delete", id] => Command::DeletePokemon(id_from(id)?),
+        ["trainer", "list"] => Command::ListTrainers,
+        ["trainer", "get", id] => Command::GetTrainer(id_from(id)?),
+        ["trainer", "add"] => Command::AddTrainer(CreateTrainer {
+            name: flags.take("name").ok_or("trainer add needs --name")?,
+            team: flags.take("team").map(|team| ids_from(&team)).transpose()?.unwrap_or_default(),
+            email: flags.take("email"),
+        }),
+        ["trainer", "team", id, team] => Command::SetTeam(id_from(id)?, ids_from(team)?),
+        [] => return Err("no command given".to_string()),
+        _ => return Err(format!("unknown command `{}`", words.join(" "))),
+    };
+    if let Some(flag) = flags.0.keys().next() {
+        return Err(format!("--{} doesn't go with `{}`", flag, words.join(" ")));
+    }
+    Ok((options, command))
+}
+
+// taken out as they're used, so anything left over wasn't meant for the command
+struct Flags(BTreeMap<String, String>);
+
+impl Flags{
+    fn take(&mut self, name: &str) -> Option<String> {
+        self.0.remove(name)
+    }
+
+    fn number(&mut self, name: &str) -> Result<Option<u32>, String> {
+        self.take(name).map(|v| v.parse().map_err(|_| format!("--{} has to be a number, not {:?}", name, v))).transpose()
+    }
+
+    // anything the API takes as a JSON string, written the same way, e.g. --region kanto
+    fn parsed<T: DeserializeOwned>(&mut self, name: &str) -> Result<Option<T>, String> {
+        self.take(name)
+            .map(|v| {
+                let json = v.parse::<serde_json::Value>().unwrap_or(serde_json::Value::String(v.clone()));
+                serde_json::from_value(json).map_err(|e| format!("--{} {:?}: {}", name, v, e))
+            })
+            .transpose()
+    }
+}
+
+fn id_from(text: &str) -> Result<u32, String> {
+    text.parse().map_err(|_| format!("{:?} isn't an ID", text))
+}
+
+// "1,2,3", or "" for nobody
+fn ids_from(text: &str) -> Result<Vec<u32>, String> {
+    text.split(',').filter(|id| !id.trim().is_empty()).map(|id| id_from(id.trim())).collect()
+}
@@ -0,0 +1,68 @@
+use std::process::{Command, Output};
+
+use rust_hello_http::AppBuilder;
+
+// a fresh server on a free port, offline and with nothing in it
+async fn server() -> String {
+    let app = AppBuilder::new().build();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app.router).await.unwrap() });
+    format!("http://{}", addr)
+}
+
+// runs off the runtime's threads so the server above keeps answering
+async fn cli(server: &str, args: &[&str]) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_pokemon-cli"));
+    command.arg("--server").arg(server).args(args).env_remove("POKEMON_SERVER");
+    tokio::task::spawn_blocking(move || command.output().unwrap()).await.unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn add_list_and_delete() {
+    let server = server().await;
+    let added = stdout(&cli(&server, &["pokemon", "add", "--name", "Pikachu", "--type", "electric", "--level", "12"]).await);
+    assert!(added.starts_with("ID"));
+    assert!(added.contains("Pikachu") && added.contains("electric"));
+
+    let table = stdout(&cli(&server, &["pokemon", "list"]).await);
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0].split_whitespace().collect::<Vec<_>>(), ["ID", "NAME", "SPECIES", "TYPE", "LEVEL", "HP"]);
+    assert_eq!(lines[1].split_whitespace().take(5).collect::<Vec<_>>(), ["1", "Pikachu", "Pikachu", "electric", "12"]);
+
+    let json: serde_json::Value = serde_json::from_str(&stdout(&cli(&server, &["--json", "pokemon", "list"]).await)).unwrap();
+    assert_eq!(json[0]["level"], 12);
+
+    assert_eq!(stdout(&cli(&server, &["pokemon", "delete", "1"]).await), "deleted pokemon 1\n");
+    assert_eq!(stdout(&cli(&server, &["pokemon", "list"]).await).lines().count(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn trainers() {
+    let server = server().await;
+    stdout(&cli(&server, &["pokemon", "add", "--name", "Eevee", "--type", "normal", "--level", "5"]).await);
+    stdout(&cli(&server, &["trainer", "add", "--name", "Ash"]).await);
+    // only Ash can change Ash's team
+    assert_eq!(cli(&server, &["trainer", "team", "1", "1"]).await.status.code(), Some(1));
+    let team = stdout(&cli(&server, &["--trainer", "1", "trainer", "team", "1", "1"]).await);
+    assert_eq!(team.lines().nth(1).unwrap().split_whitespace().take(3).collect::<Vec<_>>(), ["1", "Ash", "1"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn usage_and_api_errors() {
+    let server = server().await;
+    let usage = cli(&server, &["pokemon", "add", "--name", "Pikachu"]).await;
+    assert_eq!(usage.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&usage.stderr).contains("needs --level"));
+    assert_eq!(cli(&server, &["pokemon", "fly"]).await.status.code(), Some(2));
+
+    let missing = cli(&server, &["pokemon", "get", "42"]).await;
+    assert_eq!(missing.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&missing.stderr).starts_with("error: 404"));
+}
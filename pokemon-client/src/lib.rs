@@ -0,0 +1,256 @@
+// a typed client for the Pokemon API, so Rust programs don't have to build the requests by hand
+// the request and response types are the server's own, from pokemon-models
+use reqwest::{Method, RequestBuilder, Response};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+
+use pokemon_models::models::TrainerAction;
+use pokemon_models::validation::Validate;
+
+pub use pokemon_models::models::{
+    BattleFilter, BattleRecord, BattleSummary, ChallengeGym, CreateGym, CreatePokemon, CreateTrainer, Gym, GymChallengeResult,
+    GymPokemon, Pokemon, PokemonFilter, ProposeTrade, SetTeam, StartBattle, Trade, TradeStatus, Trainer, UpdatePokemon,
+};
+pub use reqwest::StatusCode;
+
+#[derive(Debug)]
+pub enum Error{
+    // the server couldn't be reached, or sent back something that isn't what the endpoint returns
+    Http(reqwest::Error),
+    // caught before it was sent, the server would have answered 400 with this message
+    Invalid(String),
+    // the server said no, with the message and extra fields from its {"error": ...} body
+    Api{
+        status: StatusCode,
+        message: String,
+        details: Map<String, Value>,
+    },
+}
+
+impl Error{
+    // None when it never got an answer
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Error::Http(e) => e.status(),
+            Error::Invalid(_) => None,
+            Error::Api { status, .. } => Some(*status),
+        }
+    }
+}
+
+impl std::fmt::Display for Error{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Http(e) => write!(f, "{}", e),
+            Error::Invalid(message) => write!(f, "{}", message),
+            Error::Api { status, message, .. } => write!(f, "{}: {}", status, message),
+        }
+    }
+}
+
+impl std::error::Error for Error{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Http(e) => Some(e),
+            Error::Invalid(_) | Error::Api { .. } => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error{
+    fn from(e: reqwest::Error) -> Self {
+        Error::Http(e)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Client{
+    base_url: String,
+    http: reqwest::Client,
+    // sent as X-Trainer-Id, which decides what private Pokemon can be seen
+    trainer_id: Option<u32>,
+    admin_token: Option<String>,
+}
+
+impl Client{
+    // e.g. "http://localhost:3000"
+    pub fn new(base_url: impl Into<String>) -> Client {
+        Client::with_http(base_url, reqwest::Client::new())
+    }
+
+    // for a reqwest client with its own timeouts, proxy and so on
+    pub fn with_http(base_url: impl Into<String>, http: reqwest::Client) -> Client {
+        let base_url = base_url.into().trim_end_matches('/').to_string();
+        Client { base_url, http, trainer_id: None, admin_token: None }
+    }
+
+    // every request is made as this trainer
+    pub fn as_trainer(mut self, trainer_id: u32) -> Client {
+        self.trainer_id = Some(trainer_id);
+        self
+    }
+
+    // for the /admin endpoints
+    pub fn admin_token(mut self, token: impl Into<String>) -> Client {
+        self.admin_token = Some(token.into());
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    // anything there isn't a method for yet, with the trainer and admin token already set
+    pub fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let mut request = self.http.request(method, format!("{}{}", self.base_url, path));
+        if let Some(trainer_id) = self.trainer_id {
+            request = request.header("x-trainer-id", trainer_id);
+        }
+        if let Some(token) = &self.admin_token {
+            request = request.bearer_auth(token);
+        }
+        request
+    }
+
+    // the health check, any answer at all means the server is up
+    pub async fn health(&self) -> Result<String, Error> {
+        Ok(check(self.request(Method::GET, "/").send().await?).await?.text().await?)
+    }
+
+    // pokemon
+
+    pub async fn list_pokemon(&self, filter: &PokemonFilter) -> Result<Vec<Pokemon>, Error> {
+        json(self.request(Method::GET, "/pokemon").query(filter)).await
+    }
+
+    pub async fn get_pokemon(&self, id: u32) -> Result<Pokemon, Error> {
+        json(self.request(Method::GET, &format!("/pokemon/{}", id))).await
+    }
+
+    pub async fn create_pokemon(&self, pokemon: &CreatePokemon) -> Result<Pokemon, Error> {
+        pokemon.validate().map_err(Error::Invalid)?;
+        self.send(Method::POST, "/pokemon", pokemon).await
+    }
+
+    // only the fields that are set are changed
+    pub async fn update_pokemon(&self, id: u32, changes: &UpdatePokemon) -> Result<Pokemon, Error> {
+        changes.validate().map_err(Error::Invalid)?;
+        self.send(Method::PUT, &format!("/pokemon/{}", id), changes).await
+    }
+
+    // it goes to the trash, where it can be restored for a while
+    pub async fn delete_pokemon(&self, id: u32) -> Result<(), Error> {
+        check(self.request(Method::DELETE, &format!("/pokemon/{}", id)).send().await?).await?;
+        Ok(())
+    }
+
+    // trainers
+
+    pub async fn list_trainers(&self) -> Result<Vec<Trainer>, Error> {
+        json(self.request(Method::GET, "/trainers")).await
+    }
+
+    pub async fn get_trainer(&self, id: u32) -> Result<Trainer, Error> {
+        json(self.request(Method::GET, &format!("/trainers/{}", id))).await
+    }
+
+    pub async fn create_trainer(&self, trainer: &CreateTrainer) -> Result<Trainer, Error> {
+        trainer.validate().map_err(Error::Invalid)?;
+        self.send(Method::POST, "/trainers", trainer).await
+    }
+
+    // anyone left off the team goes to the box
+    pub async fn set_team(&self, trainer_id: u32, team: &[u32]) -> Result<Trainer, Error> {
+        let team = SetTeam { team: team.to_vec() };
+        team.validate().map_err(Error::Invalid)?;
+        self.send(Method::PUT, &format!("/trainers/{}/team", trainer_id), &team).await
+    }
+
+    // battles
+
+    pub async fn start_battle(&self, battle: &StartBattle) -> Result<BattleRecord, Error> {
+        battle.validate().map_err(Error::Invalid)?;
+        self.send(Method::POST, "/battles", battle).await
+    }
+
+    pub async fn list_battles(&self, filter: &BattleFilter) -> Result<Vec<BattleSummary>, Error> {
+        json(self.request(Method::GET, "/battles").query(filter)).await
+    }
+
+    pub async fn get_battle(&self, id: u32) -> Result<BattleRecord, Error> {
+        json(self.request(Method::GET, &format!("/battles/{}", id))).await
+    }
+
+    // gyms
+
+    pub async fn list_gyms(&self) -> Result<Vec<Gym>, Error> {
+        json(self.request(Method::GET, "/gyms")).await
+    }
+
+    pub async fn get_gym(&self, id: u32) -> Result<Gym, Error> {
+        json(self.request(Method::GET, &format!("/gyms/{}", id))).await
+    }
+
+    pub async fn create_gym(&self, gym: &CreateGym) -> Result<Gym, Error> {
+        gym.validate().map_err(Error::Invalid)?;
+        self.send(Method::POST, "/gyms", gym).await
+    }
+
+    pub async fn challenge_gym(&self, gym_id: u32, challenge: &ChallengeGym) -> Result<GymChallengeResult, Error> {
+        self.send(Method::POST, &format!("/gyms/{}/challenge", gym_id), challenge).await
+    }
+
+    // trades
+
+    pub async fn propose_trade(&self, trade: &ProposeTrade) -> Result<Trade, Error> {
+        self.send(Method::POST, "/trades", trade).await
+    }
+
+    pub async fn get_trade(&self, id: u32) -> Result<Trade, Error> {
+        json(self.request(Method::GET, &format!("/trades/{}", id))).await
+    }
+
+    // trainer_id is whoever's acting, only to_trainer can accept or decline and only from_trainer can cancel
+    pub async fn accept_trade(&self, id: u32, trainer_id: u32) -> Result<Trade, Error> {
+        self.trade_action(id, "accept", trainer_id).await
+    }
+
+    pub async fn decline_trade(&self, id: u32, trainer_id: u32) -> Result<Trade, Error> {
+        self.trade_action(id, "decline", trainer_id).await
+    }
+
+    pub async fn cancel_trade(&self, id: u32, trainer_id: u32) -> Result<Trade, Error> {
+        self.trade_action(id, "cancel", trainer_id).await
+    }
+
+    async fn trade_action(&self, id: u32, action: &str, trainer_id: u32) -> Result<Trade, Error> {
+        let body = TrainerAction { trainer_id };
+        self.send(Method::POST, &format!("/trades/{}/{}", id, action), &body).await
+    }
+
+    async fn send<T: DeserializeOwned>(&self, method: Method, path: &str, body: &impl Serialize) -> Result<T, Error> {
+        json(self.request(method, path).json(body)).await
+    }
+}
+
+async fn json<T: DeserializeOwned>(request: RequestBuilder) -> Result<T, Error> {
+    Ok(check(request.send().await?).await?.json().await?)
+}
+
+// turns an error status into Error::Api, with the server's message when it sent one
+async fn check(response: Response) -> Result<Response, Error> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body: Value = response.json().await.unwrap_or_default();
+    let Value::Object(mut details) = body else {
+        let message = status.canonical_reason().unwrap_or("error").to_string();
+        return Err(Error::Api { status, message, details: Map::new() });
+    };
+    let message = match details.remove("error") {
+        Some(Value::String(message)) => message,
+        _ => status.canonical_reason().unwrap_or("error").to_string(),
+    };
+    Err(Error::Api { status, message, details })
+}
@@ -0,0 +1,105 @@
+use pokemon_client::{
+    ChallengeGym, Client, CreateGym, CreatePokemon, CreateTrainer, Error, GymPokemon, PokemonFilter, ProposeTrade, StartBattle,
+    StatusCode, TradeStatus, UpdatePokemon,
+};
+use rust_hello_http::{models::Visibility, AppBuilder};
+
+// a fresh server on a free port, offline and with nothing in it
+async fn server() -> Client {
+    let app = AppBuilder::new().admin_token(Some("test-token".to_string())).build();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app.router).await.unwrap() });
+    Client::new(format!("http://{}/", addr))
+}
+
+fn pokemon(name: &str, poke_type: &str, level: u32) -> CreatePokemon {
+    CreatePokemon { name: name.to_string(), poke_type: Some(poke_type.to_string()), level, ..CreatePokemon::default() }
+}
+
+#[tokio::test]
+async fn pokemon_crud() {
+    let client = server().await;
+    assert!(!client.health().await.unwrap().is_empty());
+
+    let pikachu = client.create_pokemon(&pokemon("Pikachu", "electric", 12)).await.unwrap();
+    assert_eq!(pikachu.species, "Pikachu");
+    assert_eq!(client.get_pokemon(pikachu.id).await.unwrap().level, 12);
+
+    let changes = UpdatePokemon { level: Some(30), ..UpdatePokemon::default() };
+    assert_eq!(client.update_pokemon(pikachu.id, &changes).await.unwrap().level, 30);
+    assert_eq!(client.list_pokemon(&PokemonFilter::default()).await.unwrap().len(), 1);
+    let starters = PokemonFilter { tag: Some("starter".to_string()), ..PokemonFilter::default() };
+    assert!(client.list_pokemon(&starters).await.unwrap().is_empty());
+
+    client.delete_pokemon(pikachu.id).await.unwrap();
+    let error = client.get_pokemon(pikachu.id).await.unwrap_err();
+    assert_eq!(error.status(), Some(StatusCode::NOT_FOUND));
+}
+
+#[tokio::test]
+async fn errors_carry_the_servers_message() {
+    let client = server().await;
+    let missingno = CreatePokemon { name: "MissingNo".to_string(), level: 5, ..CreatePokemon::default() };
+    let error = client.create_pokemon(&missingno).await.unwrap_err();
+    let Error::Api { status, message, .. } = error else { panic!("expected an API error, got {}", error) };
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(message.contains("poke_type is required"), "{}", message);
+
+    // checked before it's sent, with the message the server would give
+    let error = client.create_pokemon(&pokemon("Pikachu", "electric", 0)).await.unwrap_err();
+    assert!(matches!(&error, Error::Invalid(message) if message == "level must be 1 to 100"), "{}", error);
+    let error = client.set_team(1, &[1, 1]).await.unwrap_err();
+    assert!(matches!(&error, Error::Invalid(message) if message.contains("twice")), "{}", error);
+
+    // a 404 without a body still gets a message
+    let error = client.delete_pokemon(99).await.unwrap_err();
+    assert_eq!(error.to_string(), "404 Not Found: Not Found");
+}
+
+#[tokio::test]
+async fn private_pokemon_need_the_trainer() {
+    let client = server().await;
+    let ash = client.create_trainer(&CreateTrainer { name: "Ash".to_string(), ..CreateTrainer::default() }).await.unwrap();
+    let mut caught = pokemon("Eevee", "normal", 5);
+    caught.visibility = Some(Visibility::Private);
+    caught.original_trainer = Some(ash.id);
+    let eevee = client.create_pokemon(&caught).await.unwrap();
+    let as_ash = client.clone().as_trainer(ash.id);
+    assert_eq!(client.set_team(ash.id, &[eevee.id]).await.unwrap_err().status(), Some(StatusCode::FORBIDDEN));
+    as_ash.set_team(ash.id, &[eevee.id]).await.unwrap();
+
+    assert!(client.list_pokemon(&PokemonFilter::default()).await.unwrap().is_empty());
+    assert_eq!(as_ash.list_pokemon(&PokemonFilter::default()).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn trainers_battles_gyms_and_trades() {
+    let client = server().await;
+    let blastoise = client.create_pokemon(&pokemon("Blastoise", "water", 100)).await.unwrap();
+    let pidgey = client.create_pokemon(&pokemon("Pidgey", "normal", 5)).await.unwrap();
+    let ash = client.create_trainer(&CreateTrainer { name: "Ash".to_string(), team: vec![blastoise.id], email: None }).await.unwrap();
+    let gary = client.create_trainer(&CreateTrainer { name: "Gary".to_string(), team: vec![pidgey.id], email: None }).await.unwrap();
+    assert_eq!(client.list_trainers().await.unwrap().len(), 2);
+
+    let battle = client.clone().as_trainer(ash.id).start_battle(&StartBattle { side_a: vec![blastoise.id], side_b: vec![pidgey.id], seed: Some(7) }).await.unwrap();
+    assert_eq!(client.get_battle(battle.id).await.unwrap().outcome.turns, battle.outcome.turns);
+    assert_eq!(client.list_battles(&Default::default()).await.unwrap().len(), 1);
+
+    let gym = CreateGym {
+        name: "Pewter Gym".to_string(),
+        leader: "Brock".to_string(),
+        badge: "Boulder Badge".to_string(),
+        team: vec![GymPokemon { name: "Geodude".to_string(), level: 5 }],
+    };
+    let gym = client.create_gym(&gym).await.unwrap();
+    let result = client.clone().as_trainer(ash.id).challenge_gym(gym.id, &ChallengeGym { trainer_id: ash.id, seed: Some(1) }).await.unwrap();
+    assert!(result.won);
+    assert_eq!(client.get_trainer(ash.id).await.unwrap().badges, ["Boulder Badge"]);
+
+    let offer = ProposeTrade { from_trainer: ash.id, to_trainer: gary.id, offered: blastoise.id, requested: pidgey.id };
+    let trade = client.clone().as_trainer(ash.id).propose_trade(&offer).await.unwrap();
+    assert_eq!(client.clone().as_trainer(ash.id).accept_trade(trade.id, ash.id).await.unwrap_err().status(), Some(StatusCode::FORBIDDEN));
+    assert_eq!(client.clone().as_trainer(gary.id).accept_trade(trade.id, gary.id).await.unwrap().status, TradeStatus::Accepted);
+    assert_eq!(client.get_trainer(gary.id).await.unwrap().team, [blastoise.id]);
+}
@@ -0,0 +1,61 @@
+// what a battle's result looks like, the simulator itself is in the server
+use serde::{Deserialize, Serialize};
+
+// same limit as a trainer's party
+pub const MAX_TEAM_SIZE: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Side{
+    SideA,
+    SideB,
+}
+
+impl Side{
+    pub fn label(self) -> &'static str {
+        match self {
+            Side::SideA => "Side A",
+            Side::SideB => "Side B",
+        }
+    }
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn other(self) -> Side {
+        match self {
+            Side::SideA => Side::SideB,
+            Side::SideB => Side::SideA,
+        }
+    }
+}
+
+pub const SIDES: [Side; 2] = [Side::SideA, Side::SideB];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnLog{
+    pub turn: u32,
+    pub events: Vec<String>,
+}
+
+// how each Pokemon came out of the battle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CombatantResult{
+    pub id: u32,
+    pub name: String,
+    pub remaining_hp: u32,
+    pub max_hp: u32,
+    pub fainted: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BattleOutcome{
+    // None means a draw
+    pub winner: Option<Side>,
+    pub turns: u32,
+    pub side_a: Vec<CombatantResult>,
+    pub side_b: Vec<CombatantResult>,
+    // turn 0 is the opening send-outs
+    pub log: Vec<TurnLog>,
+}
@@ -0,0 +1,67 @@
+// wild encounters the way clients see them, and the odds of a ball holding
+// the encounter tables and the rolls are in the server
+use serde::{Deserialize, Serialize};
+
+use crate::models::Pokemon;
+use crate::species;
+use crate::status::StatusCondition;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EncounterStatus{
+    Active,
+    Caught,
+    Fled,
+    Fainted,
+}
+
+// a wild Pokemon that showed up, it doesn't belong to anyone until caught
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WildEncounter{
+    pub id: u32,
+    pub region: String,
+    pub area: String,
+    pub status: EncounterStatus,
+    // id 0 until it's caught
+    pub pokemon: Pokemon,
+}
+
+// "poke_ball", "great_ball"... in JSON
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Ball{
+    #[default]
+    #[serde(rename = "poke_ball")]
+    Poke,
+    #[serde(rename = "great_ball")]
+    Great,
+    #[serde(rename = "ultra_ball")]
+    Ultra,
+    #[serde(rename = "master_ball")]
+    Master,
+}
+
+impl Ball{
+    fn modifier(self) -> f64 {
+        match self {
+            Ball::Poke => 1.0,
+            Ball::Great => 1.5,
+            Ball::Ultra => 2.0,
+            Ball::Master => 255.0,
+        }
+    }
+}
+
+// chance from 0 to 1 that the ball holds, gen 3/4 style:
+// lower HP, a status condition and a better ball all help, and some species are just harder
+pub fn catch_chance(pokemon: &Pokemon, ball: Ball) -> f64 {
+    let max_hp = pokemon.max_hp.max(1) as f64;
+    let hp = pokemon.current_hp as f64;
+    let rate = species::find_or_unknown(&pokemon.species).catch_rate as f64;
+    let status = match pokemon.status {
+        Some(StatusCondition::Sleep | StatusCondition::Freeze) => 2.0,
+        Some(_) => 1.5,
+        None => 1.0,
+    };
+    let a = (3.0 * max_hp - 2.0 * hp) * rate * ball.modifier() / (3.0 * max_hp) * status;
+    (a / 255.0).min(1.0)
+}
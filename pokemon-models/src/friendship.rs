@@ -0,0 +1,11 @@
+// 0 to 255, how attached a Pokemon is, the job that raises it over time is in the server
+pub const MAX_FRIENDSHIP: u32 = 255;
+
+// where new Pokemon start, hatched ones are already more attached
+pub const BASE_FRIENDSHIP: u32 = 70;
+pub const HATCHED_FRIENDSHIP: u32 = 120;
+
+// how much things change it
+pub const LEVEL_UP: i32 = 5;
+pub const HEALED: i32 = 2;
+pub const FAINTED: i32 = -5;
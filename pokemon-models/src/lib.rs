@@ -0,0 +1,14 @@
+// everything a request or response of the API is made of, shared by the server, pokemon-client and the CLI
+// along with the game rules those types can't do without, like stats from the species table
+// the server's modules of the same name re-export these and add the parts that need its state
+pub mod battle;
+pub mod encounters;
+pub mod friendship;
+pub mod market;
+pub mod models;
+pub mod regions;
+pub mod species;
+pub mod status;
+pub mod types;
+#[cfg(feature = "validation")]
+pub mod validation;
@@ -0,0 +1,19 @@
+// the prices, listings are in the server
+use crate::encounters::Ball;
+
+// what every new trainer starts out with
+pub const STARTING_MONEY: u32 = 3000;
+pub const STARTING_BALLS: u32 = 5;
+
+// beating a gym pays this much per level of the leader's strongest Pokemon
+pub const GYM_PRIZE_PER_LEVEL: u32 = 100;
+
+// the shop only sells balls, Master Balls can't be bought
+pub fn price(ball: Ball) -> Option<u32> {
+    match ball {
+        Ball::Poke => Some(200),
+        Ball::Great => Some(600),
+        Ball::Ultra => Some(800),
+        Ball::Master => None,
+    }
+}
@@ -0,0 +1,1150 @@
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::battle::{BattleOutcome, CombatantResult, Side, MAX_TEAM_SIZE};
+use crate::encounters::{Ball, WildEncounter};
+use crate::friendship;
+use crate::market;
+use crate::regions::{Generation, Region};
+use crate::species::{self, BaseStats};
+use crate::status::{self, StatusCondition};
+use crate::types::PokeType;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pokemon{
+    pub id: u32,
+    // what it's called, the nickname if it has one, otherwise the species
+    // kept for clients from before nicknames, set_species and set_nickname keep it up to date
+    pub name: String,
+    pub species: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nickname: Option<String>,
+    pub poke_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secondary_type: Option<String>,
+    // rare alternate coloring, only rolled for wild Pokemon
+    #[serde(default)]
+    pub shiny: bool,
+    pub level: u32,
+    // total exp earned so far, always at least the threshold for the current level
+    pub exp: u32,
+    pub stats: Stats,
+    // individual values, 0 to 31 per stat - wild and hatched Pokemon roll them, others start at 0
+    #[serde(default)]
+    pub ivs: Stats,
+    // only for species the species table doesn't have, looked up on pokeapi when created
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_stats: Option<BaseStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sprite_url: Option<String>,
+    // always metric here, ?units=imperial converts them on the way out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height_m: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight_kg: Option<f64>,
+    // where the species comes from, e.g. "I" and "kanto"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generation: Option<Generation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<Region>,
+    pub current_hp: u32,
+    // same as stats.hp, repeated so clients can render an HP bar from two fields
+    pub max_hp: u32,
+    // 0 to 255, some species only evolve once it's high enough
+    #[serde(default)]
+    pub friendship: u32,
+    // at 0 HP the Pokemon can't battle or gain exp until healed
+    #[serde(default)]
+    pub fainted: bool,
+    #[serde(default)]
+    pub status: Option<StatusCondition>,
+    // turns left before waking up, only set while asleep
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sleep_turns: Option<u32>,
+    // up to 4 moves, learned by leveling up or taught
+    #[serde(default)]
+    pub moves: Vec<String>,
+    // notable things that happened to this Pokemon, oldest first
+    #[serde(default)]
+    pub history: Vec<HistoryEvent>,
+    // who besides the owner can see it
+    #[serde(default)]
+    pub visibility: Visibility,
+    // lowercase and sorted, see tags.rs
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub favorite: bool,
+    #[serde(default)]
+    pub capture: Capture,
+}
+
+// unix seconds
+pub fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+// how it was first obtained, set when it's created, caught or hatched and never changed after
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Capture{
+    // stays the same through trades, like in the games
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_trainer: Option<u32>,
+    // so it still reads well if the trainer is gone
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_trainer_name: Option<String>,
+    // unix seconds
+    pub captured_at: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    // only for caught Pokemon
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ball: Option<Ball>,
+}
+
+impl Capture{
+    // right now, by nobody in particular
+    pub fn now() -> Capture {
+        Capture { captured_at: unix_time(), ..Capture::default() }
+    }
+
+    pub fn by(trainer: &Trainer) -> Capture {
+        Capture { original_trainer: Some(trainer.id), original_trainer_name: Some(trainer.name.clone()), ..Capture::now() }
+    }
+
+    // for a server with a clock of its own
+    pub fn at(mut self, captured_at: u64) -> Capture {
+        self.captured_at = captured_at;
+        self
+    }
+}
+
+// only matters for Pokemon a trainer owns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Visibility{
+    // just the owner
+    Private,
+    // anyone who has the ID, but it stays out of lists and the gallery
+    Unlisted,
+    // anyone, and it shows up in the gallery
+    #[default]
+    Public,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum HistoryEvent{
+    Evolved{
+        from: String,
+        to: String,
+        level: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        item: Option<String>,
+    },
+    // trainer names, so the history still reads well if a trainer is gone
+    Traded{
+        from: String,
+        to: String,
+    },
+    // bought on the market
+    Sold{
+        from: String,
+        to: String,
+        price: u32,
+    },
+    // another record of the same Pokemon was folded into this one
+    Merged{
+        from: u32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Stats{
+    pub hp: u32,
+    pub attack: u32,
+    pub defense: u32,
+    pub sp_attack: u32,
+    pub sp_defense: u32,
+    pub speed: u32,
+}
+
+impl Stats{
+    // hp, attack, defense, sp_attack, sp_defense, speed
+    pub fn as_array(&self) -> [u32; 6] {
+        [self.hp, self.attack, self.defense, self.sp_attack, self.sp_defense, self.speed]
+    }
+
+    pub fn from_array([hp, attack, defense, sp_attack, sp_defense, speed]: [u32; 6]) -> Stats {
+        Stats { hp, attack, defense, sp_attack, sp_defense, speed }
+    }
+}
+
+impl Pokemon{
+    // a fresh Pokemon at the given level, with exp, stats, HP and moves filled in from the species data
+    pub fn new(id: u32, species: String, poke_type: String, secondary_type: Option<String>, level: u32) -> Pokemon {
+        let mut pokemon = Pokemon {
+            id,
+            name: species.clone(),
+            species,
+            nickname: None,
+            poke_type,
+            secondary_type,
+            shiny: false,
+            level,
+            exp: 0,
+            stats: Stats::default(),
+            ivs: Stats::default(),
+            base_stats: None,
+            sprite_url: None,
+            height_m: None,
+            weight_kg: None,
+            generation: None,
+            region: None,
+            current_hp: 0,
+            max_hp: 0,
+            friendship: friendship::BASE_FRIENDSHIP,
+            fainted: false,
+            status: None,
+            sleep_turns: None,
+            moves: Vec::new(),
+            history: Vec::new(),
+            visibility: Visibility::default(),
+            tags: Vec::new(),
+            favorite: false,
+            capture: Capture::now(),
+        };
+        pokemon.set_level(level);
+        pokemon.moves = species::find_or_unknown(&pokemon.species).starting_moves(level);
+        pokemon
+    }
+
+    // a different species, the nickname stays
+    pub fn set_species(&mut self, species: String) {
+        self.species = species;
+        self.name = self.nickname.clone().unwrap_or_else(|| self.species.clone());
+        self.refresh_stats();
+    }
+
+    // None goes back to the species name
+    pub fn set_nickname(&mut self, nickname: Option<String>) {
+        self.nickname = nickname;
+        self.name = self.nickname.clone().unwrap_or_else(|| self.species.clone());
+    }
+
+    // recompute stats after level or species changed
+    pub fn refresh_stats(&mut self) {
+        let base = self.base_stats.unwrap_or(species::find_or_unknown(&self.species).base_stats);
+        self.stats = species::calc_stats(&base, &self.ivs, self.level);
+
+        // like the games, gained max HP is also added to current HP (but doesn't revive)
+        let old_max = self.max_hp;
+        self.max_hp = self.stats.hp;
+        if !self.fainted {
+            self.current_hp = (self.current_hp + self.max_hp.saturating_sub(old_max)).min(self.max_hp);
+        }
+    }
+
+    // returns the damage actually dealt, which stops at 0 HP
+    pub fn take_damage(&mut self, amount: u32) -> u32 {
+        let dealt = amount.min(self.current_hp);
+        self.current_hp -= dealt;
+        if self.current_hp == 0 && !self.fainted {
+            self.fainted = true;
+            self.change_friendship(friendship::FAINTED);
+            // fainting wipes any status condition
+            status::cure(self);
+        }
+        dealt
+    }
+
+    // returns the HP actually restored, which stops at max HP
+    pub fn restore_hp(&mut self, amount: u32) -> u32 {
+        let restored = amount.min(self.max_hp - self.current_hp);
+        self.current_hp += restored;
+        restored
+    }
+
+    // Pokemon Center: full HP, no status and back on its feet
+    pub fn heal(&mut self) {
+        self.current_hp = self.max_hp;
+        self.fainted = false;
+        status::cure(self);
+        self.change_friendship(friendship::HEALED);
+    }
+
+    pub fn change_friendship(&mut self, delta: i32) {
+        self.friendship = self.friendship.saturating_add_signed(delta).min(friendship::MAX_FRIENDSHIP);
+    }
+
+    // jump straight to a level, resetting exp to the start of that level
+    pub fn set_level(&mut self, level: u32) {
+        let species = species::find_or_unknown(&self.species);
+        self.level = level;
+        self.exp = species.growth_rate.exp_for_level(level);
+        self.refresh_stats();
+    }
+
+    // add exp and level up for every threshold crossed, returns levels gained
+    // plus the new moves - learned straight away if there's a free slot, offered otherwise
+    pub fn gain_exp(&mut self, amount: u32) -> (u32, Vec<String>, Vec<String>) {
+        let growth = species::find_or_unknown(&self.species).growth_rate;
+        // exp stops counting once at max level
+        let cap = growth.exp_for_level(species::MAX_LEVEL);
+        self.exp = self.exp.saturating_add(amount).min(cap);
+
+        let new_level = growth.level_for_exp(self.exp);
+        let gained = new_level.saturating_sub(self.level);
+        let mut learned = Vec::new();
+        let mut offered = Vec::new();
+        if gained > 0 {
+            let species = species::find_or_unknown(&self.species);
+            for level in self.level + 1..=new_level {
+                for m in species.moves_at(level) {
+                    if self.knows_move(m) || offered.iter().any(|o| o == m) {
+                        continue;
+                    }
+                    if self.moves.len() < species::MAX_MOVES {
+                        self.moves.push(m.to_string());
+                        learned.push(m.to_string());
+                    } else {
+                        offered.push(m.to_string());
+                    }
+                }
+            }
+            self.level = new_level;
+            self.refresh_stats();
+            self.change_friendship(friendship::LEVEL_UP * gained as i32);
+        }
+        (gained, learned, offered)
+    }
+
+    // primary + secondary type, Err names the first one that isn't a real type
+    pub fn types(&self) -> Result<Vec<PokeType>, String> {
+        std::iter::once(&self.poke_type)
+            .chain(self.secondary_type.as_ref())
+            .map(|t| t.parse())
+            .collect()
+    }
+
+    pub fn knows_move(&self, move_name: &str) -> bool {
+        self.moves.iter().any(|m| m.eq_ignore_ascii_case(move_name))
+    }
+
+    // turn into the evolved species, keeping level and exp
+    pub fn evolve_into(&mut self, into: &species::Species, item: Option<String>) {
+        let (primary, secondary) = into.types;
+        self.history.push(HistoryEvent::Evolved {
+            from: self.species.clone(),
+            to: into.name.to_string(),
+            level: self.level,
+            item,
+        });
+        self.poke_type = primary.to_string();
+        self.secondary_type = secondary.map(str::to_string);
+        self.set_species(into.name.to_string());
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CreatePokemon{
+    // the species, "species" works too
+    #[serde(alias = "species")]
+    pub name: String,
+    // can be left out when pokeapi lookups are on, the species' types are used
+    #[serde(default)]
+    pub poke_type: Option<String>,
+    #[serde(default)]
+    pub secondary_type: Option<String>,
+    pub level: u32,
+    // public unless given
+    #[serde(default)]
+    pub visibility: Option<Visibility>,
+    // filled in from pokeapi when left out
+    #[serde(default)]
+    pub height_m: Option<f64>,
+    #[serde(default)]
+    pub weight_kg: Option<f64>,
+    // either one fills in the other where there's only one answer
+    #[serde(default)]
+    pub generation: Option<Generation>,
+    #[serde(default)]
+    pub region: Option<Region>,
+    // for the capture record, which can't be changed later
+    #[serde(default)]
+    pub original_trainer: Option<u32>,
+    #[serde(default)]
+    pub location: Option<String>,
+}
+
+// name is the species, as it was before nicknames
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UpdatePokemon{
+    pub name: Option<String>,
+    pub poke_type: Option<String>,
+    pub secondary_type: Option<String>,
+    pub level: Option<u32>,
+    pub visibility: Option<Visibility>,
+    pub height_m: Option<f64>,
+    pub weight_kg: Option<f64>,
+    pub generation: Option<Generation>,
+    pub region: Option<Region>,
+}
+
+// GET /pokemon?generation=I&region=kanto&tag=starter&favorite=true&original_trainer=1&ball=great_ball&location=..., all optional
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PokemonFilter{
+    pub generation: Option<Generation>,
+    pub region: Option<Region>,
+    pub tag: Option<String>,
+    pub favorite: Option<bool>,
+    pub original_trainer: Option<u32>,
+    pub ball: Option<Ball>,
+    // case doesn't matter
+    pub location: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddTags{
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RenameTag{
+    pub to: String,
+}
+
+// `merge` is folded into `keep` and deleted
+#[derive(Debug, Deserialize)]
+pub struct MergePokemon{
+    pub keep: u32,
+    pub merge: u32,
+}
+
+// every tag in `from` becomes `into`
+#[derive(Debug, Deserialize)]
+pub struct MergeTags{
+    pub from: Vec<String>,
+    pub into: String,
+}
+
+// how many Pokemon a rename or merge touched
+#[derive(Debug, Serialize)]
+pub struct TagsChanged{
+    pub tag: String,
+    pub pokemon_changed: usize,
+}
+
+// showdown for one Pokemon (the default and only choice), json or ndjson for a full dump
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery{
+    pub format: Option<String>,
+}
+
+// fail unless given
+// what to do when an imported record has the same ID as one that's already here
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Strategy{
+    // keep what's here
+    Skip,
+    // replace it with the imported one
+    Overwrite,
+    // the fields the import has win, the ones it leaves out are kept
+    Merge,
+    // import nothing if there's any conflict
+    #[default]
+    Fail,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportQuery{
+    pub strategy: Option<Strategy>,
+}
+
+// POST /admin/generate?count=10000&seed=42, 100 and a random seed if left out
+#[derive(Debug, Deserialize)]
+pub struct GenerateQuery{
+    pub count: Option<usize>,
+    pub seed: Option<u64>,
+}
+
+// GET /admin/runtime?window_ms=1000, how long to watch the runtime's workers for
+#[derive(Debug, Deserialize)]
+pub struct RuntimeQuery{
+    pub window_ms: Option<u64>,
+}
+
+// GET /debug/pprof/profile?seconds=30&format=flamegraph, 10 seconds of pprof protobuf if left out
+#[derive(Debug, Deserialize)]
+pub struct ProfileQuery{
+    pub seconds: Option<u64>,
+    pub format: Option<String>,
+}
+
+// null or "" takes the nickname off
+#[derive(Debug, Deserialize)]
+pub struct SetNickname{
+    pub nickname: Option<String>,
+}
+
+// leave the token out to get one
+#[derive(Debug, Deserialize)]
+pub struct ConfirmRelease{
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+// seconds until the link stops working, defaults to a day
+#[derive(Debug, Deserialize)]
+pub struct ShareQuery{
+    pub expires_in: Option<u64>,
+}
+
+// a public Pokemon and who it belongs to, for GET /gallery
+#[derive(Debug, Serialize)]
+pub struct GalleryEntry{
+    pub trainer_id: u32,
+    pub trainer_name: String,
+    pub pokemon: Pokemon,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportedPokemon{
+    pub pokemon: Pokemon,
+    // parts of the set the model has no place for, e.g. "item: Light Ball"
+    pub not_kept: Vec<String>,
+}
+
+// what the HTML forms at /web/pokemon post, an empty field counts as left out
+#[derive(Debug, Deserialize)]
+pub struct PokemonForm{
+    pub name: String,
+    #[serde(default)]
+    pub poke_type: String,
+    #[serde(default)]
+    pub secondary_type: String,
+    pub level: u32,
+}
+
+fn non_empty(text: String) -> Option<String> {
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+impl From<PokemonForm> for CreatePokemon{
+    fn from(form: PokemonForm) -> Self {
+        CreatePokemon {
+            name: form.name.trim().to_string(),
+            poke_type: non_empty(form.poke_type),
+            secondary_type: non_empty(form.secondary_type),
+            level: form.level,
+            visibility: None,
+            height_m: None,
+            weight_kg: None,
+            generation: None,
+            region: None,
+            original_trainer: None,
+            location: None,
+        }
+    }
+}
+
+impl From<PokemonForm> for UpdatePokemon{
+    fn from(form: PokemonForm) -> Self {
+        UpdatePokemon {
+            name: non_empty(form.name),
+            poke_type: non_empty(form.poke_type),
+            secondary_type: non_empty(form.secondary_type),
+            level: Some(form.level),
+            visibility: None,
+            height_m: None,
+            weight_kg: None,
+            generation: None,
+            region: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Damage{
+    pub amount: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApplyStatus{
+    pub condition: StatusCondition,
+}
+
+// without a condition anything is cured (Full Heal), with one it has to match (Antidote, Burn Heal...)
+#[derive(Debug, Default, Deserialize)]
+pub struct CureStatus{
+    pub condition: Option<StatusCondition>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GainExp{
+    pub amount: u32,
+}
+
+// what changed after gaining exp, so clients can show a level-up screen
+#[derive(Debug, Serialize)]
+pub struct ExpGainResult{
+    pub exp_gained: u32,
+    pub levels_gained: u32,
+    pub previous_level: u32,
+    pub previous_stats: Stats,
+    // moves picked up into free slots
+    pub learned_moves: Vec<String>,
+    // moves that need a slot freed up first - use POST /pokemon/{id}/teach
+    pub offered_moves: Vec<String>,
+    pub pokemon: Pokemon,
+}
+
+// both fields are optional - a level-based evolution needs neither
+#[derive(Debug, Default, Deserialize)]
+pub struct EvolvePokemon{
+    // item used on the Pokemon, e.g. "Thunder Stone"
+    pub item: Option<String>,
+    // pick a branch when a species has several evolutions (Eevee)
+    pub into: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TeachMove{
+    #[serde(rename = "move")]
+    pub move_name: String,
+    // move to replace when all 4 slots are taken
+    pub forget: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Matchup{
+    pub attacking: PokeType,
+    pub defending: PokeType,
+    pub multiplier: f32,
+    pub effectiveness: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TypeMultiplier{
+    #[serde(rename = "type")]
+    pub poke_type: PokeType,
+    pub multiplier: f32,
+}
+
+// how every attacking type fares against a Pokemon, grouped like a Pokedex page
+#[derive(Debug, Serialize)]
+pub struct TypeProfile{
+    pub id: u32,
+    pub types: Vec<PokeType>,
+    pub weaknesses: Vec<TypeMultiplier>,
+    pub resistances: Vec<TypeMultiplier>,
+    pub immunities: Vec<PokeType>,
+}
+
+// each side is a list of Pokemon IDs, a single Pokemon is just a team of one
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StartBattle{
+    pub side_a: Vec<u32>,
+    pub side_b: Vec<u32>,
+    // same seed + same Pokemon = same battle, handy for reproducing results
+    pub seed: Option<u64>,
+}
+
+// a finished battle kept around as a replay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BattleRecord{
+    pub id: u32,
+    // replaying with the same seed and Pokemon gives the same battle
+    pub seed: u64,
+    #[serde(flatten)]
+    pub outcome: BattleOutcome,
+}
+
+impl BattleRecord{
+    pub fn involves(&self, pokemon_id: u32) -> bool {
+        self.outcome.side_a.iter().chain(&self.outcome.side_b).any(|c| c.id == pokemon_id)
+    }
+
+    pub fn summary(&self) -> BattleSummary {
+        let ids = |side: &[CombatantResult]| side.iter().map(|c| c.id).collect();
+        BattleSummary {
+            id: self.id,
+            side_a: ids(&self.outcome.side_a),
+            side_b: ids(&self.outcome.side_b),
+            winner: self.outcome.winner,
+            turns: self.outcome.turns,
+        }
+    }
+}
+
+// list view of a battle, the log can get long
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BattleSummary{
+    pub id: u32,
+    pub side_a: Vec<u32>,
+    pub side_b: Vec<u32>,
+    pub winner: Option<Side>,
+    pub turns: u32,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BattleFilter{
+    // only battles this Pokemon took part in
+    pub pokemon: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JoinSession{
+    pub side: Side,
+}
+
+// someone who brings a team of Pokemon to battles
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trainer{
+    pub id: u32,
+    pub name: String,
+    // up to 6 Pokemon IDs, a Pokemon can only be on one trainer's team
+    pub team: Vec<u32>,
+    // the rest of the trainer's Pokemon, e.g. catches made with a full team
+    #[serde(rename = "box")]
+    pub pc_box: Vec<u32>,
+    // badges won from gym leaders, in the order they were earned
+    pub badges: Vec<String>,
+    pub money: u32,
+    // balls on hand, each throw uses one up
+    pub bag: BTreeMap<Ball, u32>,
+    // for trade offers and the like, only mailed once the link sent to it has been opened
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: bool,
+    // accounts at Google, GitHub and the like that log in as this trainer
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub logins: Vec<Login>,
+    // other trainers this one has let see or change their Pokemon
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub permissions: Vec<Permission>,
+}
+
+// one account at a login provider, subject is its ID there
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Login{
+    pub provider: String,
+    pub subject: String,
+}
+
+// write lets them do anything to it a trainer can do short of releasing it, read just lets them see it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Access{
+    Read,
+    Write,
+}
+
+// given by a Pokemon's owner, on that one Pokemon or on every Pokemon in their box
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Permission{
+    pub trainer_id: u32,
+    pub access: Access,
+    // None for the box
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pokemon_id: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GrantPermission{
+    pub trainer_id: u32,
+    pub access: Access,
+}
+
+impl Trainer{
+    pub fn new(id: u32, name: String, team: Vec<u32>) -> Trainer {
+        Trainer {
+            id,
+            name,
+            team,
+            pc_box: Vec::new(),
+            badges: Vec::new(),
+            money: market::STARTING_MONEY,
+            bag: BTreeMap::from([(Ball::Poke, market::STARTING_BALLS)]),
+            email: None,
+            email_verified: false,
+            logins: Vec::new(),
+            permissions: Vec::new(),
+        }
+    }
+
+    pub fn owns(&self, pokemon_id: u32) -> bool {
+        self.team.contains(&pokemon_id) || self.pc_box.contains(&pokemon_id)
+    }
+
+    // the trainer as someone else sees them, the email address and login accounts are only for the trainer
+    pub fn seen_by(&self, viewer: Option<u32>) -> Trainer {
+        let mut trainer = self.clone();
+        if viewer != Some(self.id) {
+            trainer.email = None;
+            trainer.logins.clear();
+        }
+        trainer
+    }
+
+    // a new Pokemon joins the team, or the box once the team is full
+    pub fn receive(&mut self, pokemon_id: u32) {
+        if self.team.len() < MAX_TEAM_SIZE {
+            self.team.push(pokemon_id);
+        } else {
+            self.pc_box.push(pokemon_id);
+        }
+    }
+
+    // the Pokemon leaves the team or box, e.g. when it's sold
+    pub fn release(&mut self, pokemon_id: u32) {
+        self.team.retain(|id| *id != pokemon_id);
+        self.pc_box.retain(|id| *id != pokemon_id);
+        self.permissions.retain(|p| p.pokemon_id != Some(pokemon_id));
+    }
+
+    // one permission per trainer per Pokemon (or the box), a new one replaces the old
+    pub fn grant(&mut self, permission: Permission) {
+        self.revoke(permission.trainer_id, permission.pokemon_id);
+        self.permissions.push(permission);
+    }
+
+    pub fn revoke(&mut self, trainer_id: u32, pokemon_id: Option<u32>) -> bool {
+        let before = self.permissions.len();
+        self.permissions.retain(|p| p.trainer_id != trainer_id || p.pokemon_id != pokemon_id);
+        self.permissions.len() != before
+    }
+
+    // the most another trainer has been given on one of this trainer's Pokemon, the box's only counts while it's in the box
+    pub fn granted(&self, trainer_id: u32, pokemon_id: u32) -> Option<Access> {
+        let in_box = self.pc_box.contains(&pokemon_id);
+        self.permissions.iter()
+            .filter(|p| p.trainer_id == trainer_id)
+            .filter(|p| p.pokemon_id == Some(pokemon_id) || (p.pokemon_id.is_none() && in_box))
+            .map(|p| p.access)
+            .max()
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CreateTrainer{
+    pub name: String,
+    #[serde(default)]
+    pub team: Vec<u32>,
+    // gets a link to confirm it's theirs
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+// where a login provider sends the browser back to, with a code or why there isn't one
+#[derive(Debug, Deserialize)]
+pub struct LoginCallback{
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignInOutcome{
+    // the account already logs in as this trainer
+    Existing,
+    // a trainer with the same verified email, the account logs in as them from now on
+    Linked,
+    Created,
+}
+
+// what finishing a login gives back, the trainer and the session that proves it's them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignIn{
+    pub trainer: Trainer,
+    pub outcome: SignInOutcome,
+    pub session: Session,
+}
+
+// sent back as `Authorization: Bearer <token>`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session{
+    pub token: String,
+    pub trainer_id: u32,
+    // unix seconds
+    pub expires_at: u64,
+}
+
+// POST /auth/password, for a trainer who has set one
+#[derive(Debug, Deserialize)]
+pub struct PasswordLogin{
+    pub trainer_id: u32,
+    pub password: String,
+}
+
+// GET /trainers/{id}/verify-email?token=..., the link in the email
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmail{
+    pub token: String,
+}
+
+// PUT /trainers/{id}/password, the current one is needed to change it once there is one
+#[derive(Debug, Deserialize)]
+pub struct SetPassword{
+    pub password: String,
+    #[serde(default)]
+    pub current_password: Option<String>,
+}
+
+// POST /trainers/forgot-password
+#[derive(Debug, Deserialize)]
+pub struct ForgotPassword{
+    pub email: String,
+}
+
+// POST /trainers/reset-password, with the token from the email
+#[derive(Debug, Deserialize)]
+pub struct ResetPassword{
+    pub token: String,
+    pub password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetTeam{
+    pub team: Vec<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JoinQueue{
+    pub trainer_id: u32,
+}
+
+// simulated rounds are played on the spot, interactive ones open a battle session per match
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TournamentMode{
+    #[default]
+    Simulated,
+    Interactive,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTournament{
+    pub name: String,
+    #[serde(default)]
+    pub mode: TournamentMode,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterEntrant{
+    pub trainer_id: u32,
+}
+
+// a leader's Pokemon, described by species and level rather than stored in the team
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GymPokemon{
+    pub name: String,
+    pub level: u32,
+}
+
+impl GymPokemon{
+    // gym Pokemon aren't stored anywhere, so they all get id 0
+    pub fn to_pokemon(&self) -> Pokemon {
+        let species = species::find_or_unknown(&self.name);
+        let (primary, secondary) = species.types;
+        Pokemon::new(0, species.name.to_string(), primary.to_string(), secondary.map(str::to_string), self.level)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Gym{
+    pub id: u32,
+    pub name: String,
+    pub leader: String,
+    pub badge: String,
+    pub team: Vec<GymPokemon>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateGym{
+    pub name: String,
+    pub leader: String,
+    pub badge: String,
+    pub team: Vec<GymPokemon>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChallengeGym{
+    pub trainer_id: u32,
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GymChallengeResult{
+    pub won: bool,
+    // only on the first win, a badge is earned once
+    pub badge_awarded: Option<String>,
+    // prize money, paid on every win
+    pub prize: u32,
+    pub trainer: Trainer,
+    pub battle: BattleRecord,
+}
+
+// all optional, a type matches either of a Pokemon's types
+#[derive(Debug, Deserialize)]
+pub struct RandomQuery{
+    pub poke_type: Option<String>,
+    pub min_level: Option<u32>,
+    pub max_level: Option<u32>,
+}
+
+// metric defaults to count
+#[derive(Debug, Deserialize)]
+pub struct AggregateQuery{
+    pub group_by: String,
+    pub metric: Option<String>,
+}
+
+// both default to the Kanto tall grass
+#[derive(Debug, Deserialize)]
+pub struct EncounterQuery{
+    pub region: Option<String>,
+    pub area: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AttackWild{
+    pub pokemon_id: u32,
+    #[serde(rename = "move")]
+    pub move_name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AttackResult{
+    pub events: Vec<String>,
+    pub pokemon: Pokemon,
+    pub encounter: WildEncounter,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CatchPokemon{
+    pub trainer_id: u32,
+    #[serde(default)]
+    pub ball: Ball,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CatchResult{
+    pub caught: bool,
+    pub shakes: u32,
+    // the wild Pokemon ran off after breaking free
+    pub fled: bool,
+    pub catch_chance: f64,
+    pub encounter: WildEncounter,
+    pub trainer: Trainer,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BreedPokemon{
+    pub parent_a: u32,
+    pub parent_b: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WalkEgg{
+    pub steps: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DaycarePokemon{
+    pub pokemon_id: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TradeStatus{
+    Pending,
+    Accepted,
+    Declined,
+    Cancelled,
+}
+
+// from_trainer offers one of their Pokemon for one of to_trainer's
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trade{
+    pub id: u32,
+    pub from_trainer: u32,
+    pub to_trainer: u32,
+    pub offered: u32,
+    pub requested: u32,
+    pub status: TradeStatus,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProposeTrade{
+    pub from_trainer: u32,
+    pub to_trainer: u32,
+    pub offered: u32,
+    pub requested: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind{
+    TradeOffer,
+    BattleResult,
+    LevelUp,
+}
+
+// something that happened to one of a trainer's Pokemon or trades, waiting in their inbox
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification{
+    pub id: u32,
+    pub trainer_id: u32,
+    pub kind: NotificationKind,
+    pub message: String,
+    // what it's about, whichever applies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trade_id: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub battle_id: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pokemon_id: Option<u32>,
+    // unix seconds
+    pub created_at: u64,
+    pub read: bool,
+}
+
+// ?unread=true for only the ones not read yet
+#[derive(Debug, Deserialize)]
+pub struct NotificationQuery{
+    pub unread: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UnreadCount{
+    pub unread: usize,
+}
+
+// which trainer is acting, e.g. only to_trainer can accept a trade and only the seller can withdraw a listing
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrainerAction{
+    pub trainer_id: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BuyItem{
+    pub trainer_id: u32,
+    pub item: Ball,
+    // 1 if left out
+    pub quantity: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListPokemon{
+    pub trainer_id: u32,
+    pub pokemon_id: u32,
+    pub price: u32,
+}
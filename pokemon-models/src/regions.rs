@@ -0,0 +1,120 @@
+use std::fmt;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+const NUMERALS: [&str; 9] = ["I", "II", "III", "IV", "V", "VI", "VII", "VIII", "IX"];
+
+// I to IX, written as a roman numeral, requests can send the number too
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Generation(u8);
+
+impl Generation{
+    pub fn new(number: u64) -> Option<Generation> {
+        (1..=NUMERALS.len() as u64).contains(&number).then_some(Generation(number as u8))
+    }
+
+    // "IV", "iv" or "4"
+    pub fn parse(text: &str) -> Option<Generation> {
+        let text = text.trim();
+        match text.parse::<u64>() {
+            Ok(number) => Generation::new(number),
+            Err(_) => NUMERALS.iter()
+                .position(|n| n.eq_ignore_ascii_case(text))
+                .map(|i| Generation(i as u8 + 1)),
+        }
+    }
+
+    // the region a generation's games are set in, None for VIII which has two
+    pub fn home_region(self) -> Option<Region> {
+        let mut regions = Region::ALL.iter().filter(|r| r.generation() == self);
+        match (regions.next(), regions.next()) {
+            (Some(region), None) => Some(*region),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Generation{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(NUMERALS[self.0 as usize - 1])
+    }
+}
+
+impl Serialize for Generation{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Generation{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct GenerationVisitor;
+
+        impl de::Visitor<'_> for GenerationVisitor{
+            type Value = Generation;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a generation from I to IX")
+            }
+
+            fn visit_u64<E: de::Error>(self, number: u64) -> Result<Generation, E> {
+                Generation::new(number).ok_or_else(|| E::custom(format!("there's no generation {}, it goes I to IX", number)))
+            }
+
+            fn visit_str<E: de::Error>(self, text: &str) -> Result<Generation, E> {
+                Generation::parse(text).ok_or_else(|| E::custom(format!("there's no generation {}, it goes I to IX", text)))
+            }
+        }
+
+        deserializer.deserialize_any(GenerationVisitor)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Region{
+    Kanto,
+    Johto,
+    Hoenn,
+    Sinnoh,
+    Unova,
+    Kalos,
+    Alola,
+    Galar,
+    Hisui,
+    Paldea,
+}
+
+impl Region{
+    pub const ALL: [Region; 10] = [
+        Region::Kanto, Region::Johto, Region::Hoenn, Region::Sinnoh, Region::Unova,
+        Region::Kalos, Region::Alola, Region::Galar, Region::Hisui, Region::Paldea,
+    ];
+
+    // the generation that introduced it
+    pub fn generation(self) -> Generation {
+        Generation(match self {
+            Region::Kanto => 1,
+            Region::Johto => 2,
+            Region::Hoenn => 3,
+            Region::Sinnoh => 4,
+            Region::Unova => 5,
+            Region::Kalos => 6,
+            Region::Alola => 7,
+            Region::Galar | Region::Hisui => 8,
+            Region::Paldea => 9,
+        })
+    }
+}
+
+// both filled in from whichever one was given, or an error if they don't go together
+pub fn resolve(generation: Option<Generation>, region: Option<Region>) -> Result<(Option<Generation>, Option<Region>), String> {
+    match (generation, region) {
+        (Some(generation), Some(region)) if region.generation() != generation => {
+            Err(format!("{:?} is a generation {} region, not {}", region, region.generation(), generation))
+        }
+        (None, Some(region)) => Ok((Some(region.generation()), Some(region))),
+        (Some(generation), None) => Ok((Some(generation), generation.home_region())),
+        other => Ok(other),
+    }
+}
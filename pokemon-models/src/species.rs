@@ -0,0 +1,316 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::models::Stats;
+
+// highest level a Pokemon can reach
+pub const MAX_LEVEL: u32 = 100;
+
+// how fast a species levels up - each curve maps a level to the total exp needed to reach it
+#[derive(Debug, Clone, Copy)]
+pub enum GrowthRate{
+    Fast,
+    MediumFast,
+    MediumSlow,
+    Slow,
+}
+
+impl GrowthRate{
+    pub fn exp_for_level(self, level: u32) -> u32 {
+        let n = level.clamp(1, MAX_LEVEL) as i64;
+        let exp = match self {
+            GrowthRate::Fast => 4 * n * n * n / 5,
+            GrowthRate::MediumFast => n * n * n,
+            // this curve dips below zero at level 1 so it gets clamped
+            GrowthRate::MediumSlow => 6 * n * n * n / 5 - 15 * n * n + 100 * n - 140,
+            GrowthRate::Slow => 5 * n * n * n / 4,
+        };
+        exp.max(0) as u32
+    }
+
+    // highest level whose threshold is covered by the given exp
+    pub fn level_for_exp(self, exp: u32) -> u32 {
+        let mut level = 1;
+        while level < MAX_LEVEL && self.exp_for_level(level + 1) <= exp {
+            level += 1;
+        }
+        level
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct BaseStats{
+    pub hp: u32,
+    pub attack: u32,
+    pub defense: u32,
+    pub sp_attack: u32,
+    pub sp_defense: u32,
+    pub speed: u32,
+}
+
+const fn base(hp: u32, attack: u32, defense: u32, sp_attack: u32, sp_defense: u32, speed: u32) -> BaseStats {
+    BaseStats { hp, attack, defense, sp_attack, sp_defense, speed }
+}
+
+#[derive(Debug)]
+pub struct Species{
+    pub name: &'static str,
+    // primary and optional secondary type
+    pub types: (&'static str, Option<&'static str>),
+    pub base_stats: BaseStats,
+    pub growth_rate: GrowthRate,
+    // 3 for legendaries up to 255 for the likes of Pidgey, higher is easier to catch
+    pub catch_rate: u32,
+    // two Pokemon sharing a group can breed, Undiscovered never can
+    pub egg_groups: &'static [EggGroup],
+    pub evolutions: &'static [Evolution],
+    // (level, move) pairs learned by leveling up
+    pub learnset: &'static [(u32, &'static str)],
+    // TM groups this species is compatible with
+    pub tms: &'static [&'static [&'static str]],
+}
+
+// most Pokemon know this many moves at most
+pub const MAX_MOVES: usize = 4;
+
+impl Species{
+    // moves learned exactly when reaching this level
+    pub fn moves_at(&self, level: u32) -> impl Iterator<Item = &'static str> + '_ {
+        self.learnset.iter().filter(move |(l, _)| *l == level).map(|(_, m)| *m)
+    }
+
+    // like in the games, a fresh Pokemon knows the latest level-up moves it qualifies for
+    pub fn starting_moves(&self, level: u32) -> Vec<String> {
+        let mut moves: Vec<String> = Vec::new();
+        for (_, m) in self.learnset.iter().filter(|(l, _)| *l <= level) {
+            if !moves.iter().any(|known| known == m) {
+                moves.push(m.to_string());
+            }
+        }
+        let skip = moves.len().saturating_sub(MAX_MOVES);
+        moves.split_off(skip)
+    }
+
+    pub fn can_learn(&self, move_name: &str, level: u32) -> bool {
+        let by_level = self.learnset.iter().any(|(l, m)| *l <= level && m.eq_ignore_ascii_case(move_name));
+        let by_tm = self.tms.iter().flat_map(|group| group.iter()).any(|m| m.eq_ignore_ascii_case(move_name));
+        by_level || by_tm
+    }
+
+    // canonical spelling of a move this species knows about
+    pub fn move_name(&self, move_name: &str) -> Option<&'static str> {
+        self.learnset.iter().map(|(_, m)| m)
+            .chain(self.tms.iter().flat_map(|group| group.iter()))
+            .find(|m| m.eq_ignore_ascii_case(move_name))
+            .copied()
+    }
+}
+
+// what it takes to evolve into another species
+#[derive(Debug, Clone, Copy)]
+pub enum EvolutionTrigger{
+    Level(u32),
+    // using an item like a Thunder Stone (trade evolutions use a Linking Cord)
+    Item(&'static str),
+    // friendship at or above this value, see friendship.rs
+    Friendship(u32),
+}
+
+#[derive(Debug)]
+pub struct Evolution{
+    pub into: &'static str,
+    pub trigger: EvolutionTrigger,
+}
+
+impl Evolution{
+    pub fn is_met(&self, level: u32, friendship: u32, item: Option<&str>) -> bool {
+        match self.trigger {
+            EvolutionTrigger::Level(min_level) => level >= min_level,
+            EvolutionTrigger::Item(needed) => item.is_some_and(|i| i.eq_ignore_ascii_case(needed)),
+            EvolutionTrigger::Friendship(min_friendship) => friendship >= min_friendship,
+        }
+    }
+
+    // human readable requirement for error messages
+    pub fn requirement(&self) -> String {
+        match self.trigger {
+            EvolutionTrigger::Level(min_level) => format!("reach level {}", min_level),
+            EvolutionTrigger::Item(needed) => format!("use a {}", needed),
+            EvolutionTrigger::Friendship(min_friendship) => format!("reach {} friendship", min_friendship),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EggGroup{
+    Monster,
+    Water1,
+    Water2,
+    Bug,
+    Flying,
+    Field,
+    Fairy,
+    Grass,
+    HumanLike,
+    Mineral,
+    Amorphous,
+    Dragon,
+    Undiscovered,
+}
+
+// anything not in the table below still gets sensible numbers
+pub const UNKNOWN_SPECIES: Species = Species { name: "Unknown", types: ("Normal", None), base_stats: base(50, 50, 50, 50, 50, 50), growth_rate: GrowthRate::MediumFast, catch_rate: 45, egg_groups: &[Undiscovered], evolutions: &[], learnset: &[], tms: &[] };
+
+use GrowthRate::*;
+use EvolutionTrigger::*;
+use EggGroup::*;
+
+// level-up learnsets, shared across an evolution line where the games mostly agree
+const BULBASAUR_MOVES: &[(u32, &str)] = &[(1, "Tackle"), (3, "Growl"), (7, "Vine Whip"), (9, "Leech Seed"), (13, "Poison Powder"), (13, "Sleep Powder"), (19, "Razor Leaf"), (28, "Sludge Bomb"), (36, "Solar Beam")];
+const CHARMANDER_MOVES: &[(u32, &str)] = &[(1, "Scratch"), (1, "Growl"), (7, "Ember"), (10, "Smokescreen"), (12, "Dragon Breath"), (17, "Fire Fang"), (28, "Slash"), (32, "Flamethrower"), (39, "Fire Spin"), (46, "Flare Blitz")];
+const SQUIRTLE_MOVES: &[(u32, &str)] = &[(1, "Tackle"), (4, "Tail Whip"), (7, "Water Gun"), (10, "Withdraw"), (13, "Bite"), (16, "Water Pulse"), (25, "Aqua Tail"), (42, "Hydro Pump")];
+const CATERPIE_MOVES: &[(u32, &str)] = &[(1, "Tackle"), (1, "String Shot"), (7, "Harden"), (9, "Bug Bite"), (10, "Confusion"), (12, "Poison Powder"), (12, "Stun Spore"), (12, "Sleep Powder"), (16, "Psybeam"), (18, "Gust"), (34, "Bug Buzz")];
+const PIDGEY_MOVES: &[(u32, &str)] = &[(1, "Tackle"), (5, "Sand Attack"), (9, "Gust"), (13, "Quick Attack"), (21, "Wing Attack"), (33, "Agility"), (37, "Air Slash"), (49, "Hurricane")];
+const RATTATA_MOVES: &[(u32, &str)] = &[(1, "Tackle"), (1, "Tail Whip"), (4, "Quick Attack"), (7, "Focus Energy"), (10, "Bite"), (16, "Hyper Fang"), (22, "Crunch"), (31, "Double-Edge")];
+const PIKACHU_MOVES: &[(u32, &str)] = &[(1, "Thunder Shock"), (1, "Growl"), (5, "Tail Whip"), (8, "Thunder Wave"), (10, "Quick Attack"), (13, "Electro Ball"), (20, "Spark"), (26, "Thunderbolt"), (29, "Agility"), (42, "Thunder")];
+const JIGGLYPUFF_MOVES: &[(u32, &str)] = &[(1, "Sing"), (3, "Pound"), (5, "Defense Curl"), (9, "Rollout"), (24, "Body Slam"), (29, "Rest"), (45, "Double-Edge")];
+const ABRA_MOVES: &[(u32, &str)] = &[(1, "Teleport"), (16, "Confusion"), (18, "Disable"), (21, "Psybeam"), (28, "Recover"), (36, "Psychic")];
+const MACHOP_MOVES: &[(u32, &str)] = &[(1, "Low Kick"), (1, "Leer"), (3, "Focus Energy"), (7, "Karate Chop"), (13, "Seismic Toss"), (19, "Revenge"), (39, "Cross Chop"), (45, "Dynamic Punch")];
+const GEODUDE_MOVES: &[(u32, &str)] = &[(1, "Tackle"), (1, "Defense Curl"), (10, "Rock Throw"), (16, "Rollout"), (22, "Rock Slide"), (36, "Earthquake"), (42, "Stone Edge")];
+const GASTLY_MOVES: &[(u32, &str)] = &[(1, "Lick"), (1, "Hypnosis"), (15, "Night Shade"), (19, "Confuse Ray"), (22, "Hex"), (33, "Shadow Ball"), (40, "Dark Pulse")];
+const ONIX_MOVES: &[(u32, &str)] = &[(1, "Tackle"), (1, "Harden"), (4, "Bind"), (9, "Rock Throw"), (25, "Rock Slide"), (36, "Iron Tail"), (46, "Stone Edge")];
+const MAGIKARP_MOVES: &[(u32, &str)] = &[(1, "Splash"), (15, "Tackle"), (30, "Flail")];
+const GYARADOS_MOVES: &[(u32, &str)] = &[(1, "Splash"), (15, "Tackle"), (20, "Bite"), (21, "Twister"), (24, "Ice Fang"), (30, "Aqua Tail"), (41, "Hydro Pump"), (47, "Hyper Beam")];
+const EEVEE_MOVES: &[(u32, &str)] = &[(1, "Tackle"), (1, "Tail Whip"), (5, "Sand Attack"), (10, "Quick Attack"), (17, "Bite"), (23, "Take Down"), (45, "Double-Edge")];
+const VAPOREON_MOVES: &[(u32, &str)] = &[(1, "Tackle"), (1, "Tail Whip"), (1, "Water Gun"), (5, "Sand Attack"), (10, "Quick Attack"), (20, "Water Pulse"), (25, "Aurora Beam"), (45, "Hydro Pump")];
+const JOLTEON_MOVES: &[(u32, &str)] = &[(1, "Tackle"), (1, "Tail Whip"), (1, "Thunder Shock"), (5, "Sand Attack"), (10, "Quick Attack"), (20, "Double Kick"), (25, "Thunder Fang"), (45, "Thunder")];
+const FLAREON_MOVES: &[(u32, &str)] = &[(1, "Tackle"), (1, "Tail Whip"), (1, "Ember"), (5, "Sand Attack"), (10, "Quick Attack"), (20, "Bite"), (25, "Fire Fang"), (45, "Flare Blitz")];
+const SNORLAX_MOVES: &[(u32, &str)] = &[(1, "Tackle"), (4, "Defense Curl"), (9, "Amnesia"), (12, "Lick"), (25, "Body Slam"), (28, "Rest"), (41, "Crunch"), (50, "Hyper Beam")];
+const ARTICUNO_MOVES: &[(u32, &str)] = &[(1, "Gust"), (1, "Powder Snow"), (15, "Ice Shard"), (22, "Agility"), (36, "Ice Beam"), (50, "Blizzard")];
+const ZAPDOS_MOVES: &[(u32, &str)] = &[(1, "Peck"), (1, "Thunder Shock"), (8, "Thunder Wave"), (22, "Agility"), (36, "Drill Peck"), (43, "Thunderbolt"), (50, "Thunder")];
+const MOLTRES_MOVES: &[(u32, &str)] = &[(1, "Wing Attack"), (1, "Ember"), (8, "Fire Spin"), (22, "Agility"), (36, "Flamethrower"), (43, "Air Slash"), (50, "Fire Blast")];
+const DRATINI_MOVES: &[(u32, &str)] = &[(1, "Wrap"), (1, "Leer"), (5, "Thunder Wave"), (11, "Twister"), (15, "Dragon Breath"), (25, "Agility"), (31, "Aqua Tail"), (35, "Dragon Rush"), (45, "Outrage"), (55, "Wing Attack")];
+const ESPEON_MOVES: &[(u32, &str)] = &[(1, "Tackle"), (1, "Tail Whip"), (1, "Confusion"), (5, "Sand Attack"), (10, "Quick Attack"), (20, "Swift"), (25, "Psybeam"), (45, "Psychic")];
+const UMBREON_MOVES: &[(u32, &str)] = &[(1, "Tackle"), (1, "Tail Whip"), (5, "Sand Attack"), (10, "Quick Attack"), (17, "Bite"), (20, "Confuse Ray"), (35, "Crunch"), (45, "Dark Pulse")];
+const MEWTWO_MOVES: &[(u32, &str)] = &[(1, "Confusion"), (1, "Disable"), (8, "Swift"), (15, "Psycho Cut"), (36, "Recover"), (43, "Psychic"), (50, "Aura Sphere"), (64, "Psystrike")];
+const MEW_MOVES: &[(u32, &str)] = &[(1, "Pound"), (10, "Mega Punch"), (30, "Ancient Power"), (40, "Psychic"), (50, "Aura Sphere")];
+
+// TM compatibility by type, everyone gets the common ones
+const COMMON_TMS: &[&str] = &["Toxic", "Protect", "Rest", "Substitute", "Return"];
+const NORMAL_TMS: &[&str] = &["Hyper Beam", "Body Slam"];
+const FIRE_TMS: &[&str] = &["Flamethrower", "Fire Blast", "Will-O-Wisp"];
+const WATER_TMS: &[&str] = &["Surf", "Ice Beam"];
+const GRASS_TMS: &[&str] = &["Solar Beam", "Energy Ball", "Giga Drain"];
+const ELECTRIC_TMS: &[&str] = &["Thunderbolt", "Thunder", "Thunder Wave"];
+const ICE_TMS: &[&str] = &["Ice Beam", "Blizzard"];
+const FIGHTING_TMS: &[&str] = &["Brick Break", "Focus Blast"];
+const POISON_TMS: &[&str] = &["Sludge Bomb"];
+const GROUND_TMS: &[&str] = &["Earthquake", "Dig"];
+const FLYING_TMS: &[&str] = &["Aerial Ace", "Fly"];
+const PSYCHIC_TMS: &[&str] = &["Psychic", "Calm Mind", "Shadow Ball"];
+const BUG_TMS: &[&str] = &["X-Scissor"];
+const ROCK_TMS: &[&str] = &["Rock Slide", "Stone Edge"];
+const GHOST_TMS: &[&str] = &["Shadow Ball", "Dark Pulse"];
+const DARK_TMS: &[&str] = &["Dark Pulse"];
+const DRAGON_TMS: &[&str] = &["Dragon Claw", "Dragon Pulse"];
+const FAIRY_TMS: &[&str] = &["Dazzling Gleam"];
+
+pub static SPECIES: &[Species] = &[
+    Species { name: "Bulbasaur", types: ("Grass", Some("Poison")), base_stats: base(45, 49, 49, 65, 65, 45), growth_rate: MediumSlow, catch_rate: 45, egg_groups: &[Monster, Grass], evolutions: &[Evolution { into: "Ivysaur", trigger: Level(16) }], learnset: BULBASAUR_MOVES, tms: &[COMMON_TMS, GRASS_TMS, POISON_TMS] },
+    Species { name: "Ivysaur", types: ("Grass", Some("Poison")), base_stats: base(60, 62, 63, 80, 80, 60), growth_rate: MediumSlow, catch_rate: 45, egg_groups: &[Monster, Grass], evolutions: &[Evolution { into: "Venusaur", trigger: Level(32) }], learnset: BULBASAUR_MOVES, tms: &[COMMON_TMS, GRASS_TMS, POISON_TMS] },
+    Species { name: "Venusaur", types: ("Grass", Some("Poison")), base_stats: base(80, 82, 83, 100, 100, 80), growth_rate: MediumSlow, catch_rate: 45, egg_groups: &[Monster, Grass], evolutions: &[], learnset: BULBASAUR_MOVES, tms: &[COMMON_TMS, GRASS_TMS, POISON_TMS] },
+    Species { name: "Charmander", types: ("Fire", None), base_stats: base(39, 52, 43, 60, 50, 65), growth_rate: MediumSlow, catch_rate: 45, egg_groups: &[Monster, Dragon], evolutions: &[Evolution { into: "Charmeleon", trigger: Level(16) }], learnset: CHARMANDER_MOVES, tms: &[COMMON_TMS, FIRE_TMS] },
+    Species { name: "Charmeleon", types: ("Fire", None), base_stats: base(58, 64, 58, 80, 65, 80), growth_rate: MediumSlow, catch_rate: 45, egg_groups: &[Monster, Dragon], evolutions: &[Evolution { into: "Charizard", trigger: Level(36) }], learnset: CHARMANDER_MOVES, tms: &[COMMON_TMS, FIRE_TMS] },
+    Species { name: "Charizard", types: ("Fire", Some("Flying")), base_stats: base(78, 84, 78, 109, 85, 100), growth_rate: MediumSlow, catch_rate: 45, egg_groups: &[Monster, Dragon], evolutions: &[], learnset: CHARMANDER_MOVES, tms: &[COMMON_TMS, FIRE_TMS, FLYING_TMS] },
+    Species { name: "Squirtle", types: ("Water", None), base_stats: base(44, 48, 65, 50, 64, 43), growth_rate: MediumSlow, catch_rate: 45, egg_groups: &[Monster, Water1], evolutions: &[Evolution { into: "Wartortle", trigger: Level(16) }], learnset: SQUIRTLE_MOVES, tms: &[COMMON_TMS, WATER_TMS] },
+    Species { name: "Wartortle", types: ("Water", None), base_stats: base(59, 63, 80, 65, 80, 58), growth_rate: MediumSlow, catch_rate: 45, egg_groups: &[Monster, Water1], evolutions: &[Evolution { into: "Blastoise", trigger: Level(36) }], learnset: SQUIRTLE_MOVES, tms: &[COMMON_TMS, WATER_TMS] },
+    Species { name: "Blastoise", types: ("Water", None), base_stats: base(79, 83, 100, 85, 105, 78), growth_rate: MediumSlow, catch_rate: 45, egg_groups: &[Monster, Water1], evolutions: &[], learnset: SQUIRTLE_MOVES, tms: &[COMMON_TMS, WATER_TMS] },
+    Species { name: "Caterpie", types: ("Bug", None), base_stats: base(45, 30, 35, 20, 20, 45), growth_rate: MediumFast, catch_rate: 255, egg_groups: &[Bug], evolutions: &[Evolution { into: "Metapod", trigger: Level(7) }], learnset: CATERPIE_MOVES, tms: &[COMMON_TMS, BUG_TMS] },
+    Species { name: "Metapod", types: ("Bug", None), base_stats: base(50, 20, 55, 25, 25, 30), growth_rate: MediumFast, catch_rate: 120, egg_groups: &[Bug], evolutions: &[Evolution { into: "Butterfree", trigger: Level(10) }], learnset: CATERPIE_MOVES, tms: &[COMMON_TMS, BUG_TMS] },
+    Species { name: "Butterfree", types: ("Bug", Some("Flying")), base_stats: base(60, 45, 50, 90, 80, 70), growth_rate: MediumFast, catch_rate: 45, egg_groups: &[Bug], evolutions: &[], learnset: CATERPIE_MOVES, tms: &[COMMON_TMS, BUG_TMS, FLYING_TMS] },
+    Species { name: "Pidgey", types: ("Normal", Some("Flying")), base_stats: base(40, 45, 40, 35, 35, 56), growth_rate: MediumSlow, catch_rate: 255, egg_groups: &[Flying], evolutions: &[Evolution { into: "Pidgeotto", trigger: Level(18) }], learnset: PIDGEY_MOVES, tms: &[COMMON_TMS, NORMAL_TMS, FLYING_TMS] },
+    Species { name: "Pidgeotto", types: ("Normal", Some("Flying")), base_stats: base(63, 60, 55, 50, 50, 71), growth_rate: MediumSlow, catch_rate: 120, egg_groups: &[Flying], evolutions: &[Evolution { into: "Pidgeot", trigger: Level(36) }], learnset: PIDGEY_MOVES, tms: &[COMMON_TMS, NORMAL_TMS, FLYING_TMS] },
+    Species { name: "Pidgeot", types: ("Normal", Some("Flying")), base_stats: base(83, 80, 75, 70, 70, 101), growth_rate: MediumSlow, catch_rate: 45, egg_groups: &[Flying], evolutions: &[], learnset: PIDGEY_MOVES, tms: &[COMMON_TMS, NORMAL_TMS, FLYING_TMS] },
+    Species { name: "Rattata", types: ("Normal", None), base_stats: base(30, 56, 35, 25, 35, 72), growth_rate: MediumFast, catch_rate: 255, egg_groups: &[Field], evolutions: &[Evolution { into: "Raticate", trigger: Level(20) }], learnset: RATTATA_MOVES, tms: &[COMMON_TMS, NORMAL_TMS] },
+    Species { name: "Raticate", types: ("Normal", None), base_stats: base(55, 81, 60, 50, 70, 97), growth_rate: MediumFast, catch_rate: 127, egg_groups: &[Field], evolutions: &[], learnset: RATTATA_MOVES, tms: &[COMMON_TMS, NORMAL_TMS] },
+    Species { name: "Pichu", types: ("Electric", None), base_stats: base(20, 40, 15, 35, 35, 60), growth_rate: MediumFast, catch_rate: 190, egg_groups: &[Undiscovered], evolutions: &[Evolution { into: "Pikachu", trigger: Friendship(220) }], learnset: PIKACHU_MOVES, tms: &[COMMON_TMS, ELECTRIC_TMS] },
+    Species { name: "Pikachu", types: ("Electric", None), base_stats: base(35, 55, 40, 50, 50, 90), growth_rate: MediumFast, catch_rate: 190, egg_groups: &[Field, Fairy], evolutions: &[Evolution { into: "Raichu", trigger: Item("Thunder Stone") }], learnset: PIKACHU_MOVES, tms: &[COMMON_TMS, ELECTRIC_TMS] },
+    Species { name: "Raichu", types: ("Electric", None), base_stats: base(60, 90, 55, 90, 80, 110), growth_rate: MediumFast, catch_rate: 75, egg_groups: &[Field, Fairy], evolutions: &[], learnset: PIKACHU_MOVES, tms: &[COMMON_TMS, ELECTRIC_TMS] },
+    Species { name: "Igglybuff", types: ("Normal", Some("Fairy")), base_stats: base(90, 30, 15, 40, 20, 15), growth_rate: Fast, catch_rate: 170, egg_groups: &[Undiscovered], evolutions: &[Evolution { into: "Jigglypuff", trigger: Friendship(220) }], learnset: JIGGLYPUFF_MOVES, tms: &[COMMON_TMS, NORMAL_TMS, FAIRY_TMS] },
+    Species { name: "Jigglypuff", types: ("Normal", Some("Fairy")), base_stats: base(115, 45, 20, 45, 25, 20), growth_rate: Fast, catch_rate: 170, egg_groups: &[Fairy], evolutions: &[Evolution { into: "Wigglytuff", trigger: Item("Moon Stone") }], learnset: JIGGLYPUFF_MOVES, tms: &[COMMON_TMS, NORMAL_TMS, FAIRY_TMS] },
+    Species { name: "Wigglytuff", types: ("Normal", Some("Fairy")), base_stats: base(140, 70, 45, 85, 50, 45), growth_rate: Fast, catch_rate: 50, egg_groups: &[Fairy], evolutions: &[], learnset: JIGGLYPUFF_MOVES, tms: &[COMMON_TMS, NORMAL_TMS, FAIRY_TMS] },
+    Species { name: "Abra", types: ("Psychic", None), base_stats: base(25, 20, 15, 105, 55, 90), growth_rate: MediumSlow, catch_rate: 200, egg_groups: &[HumanLike], evolutions: &[Evolution { into: "Kadabra", trigger: Level(16) }], learnset: ABRA_MOVES, tms: &[COMMON_TMS, PSYCHIC_TMS] },
+    Species { name: "Kadabra", types: ("Psychic", None), base_stats: base(40, 35, 30, 120, 70, 105), growth_rate: MediumSlow, catch_rate: 100, egg_groups: &[HumanLike], evolutions: &[Evolution { into: "Alakazam", trigger: Item("Linking Cord") }], learnset: ABRA_MOVES, tms: &[COMMON_TMS, PSYCHIC_TMS] },
+    Species { name: "Alakazam", types: ("Psychic", None), base_stats: base(55, 50, 45, 135, 95, 120), growth_rate: MediumSlow, catch_rate: 50, egg_groups: &[HumanLike], evolutions: &[], learnset: ABRA_MOVES, tms: &[COMMON_TMS, PSYCHIC_TMS] },
+    Species { name: "Machop", types: ("Fighting", None), base_stats: base(70, 80, 50, 35, 35, 35), growth_rate: MediumSlow, catch_rate: 180, egg_groups: &[HumanLike], evolutions: &[Evolution { into: "Machoke", trigger: Level(28) }], learnset: MACHOP_MOVES, tms: &[COMMON_TMS, FIGHTING_TMS] },
+    Species { name: "Machoke", types: ("Fighting", None), base_stats: base(80, 100, 70, 50, 60, 45), growth_rate: MediumSlow, catch_rate: 90, egg_groups: &[HumanLike], evolutions: &[Evolution { into: "Machamp", trigger: Item("Linking Cord") }], learnset: MACHOP_MOVES, tms: &[COMMON_TMS, FIGHTING_TMS] },
+    Species { name: "Machamp", types: ("Fighting", None), base_stats: base(90, 130, 80, 65, 85, 55), growth_rate: MediumSlow, catch_rate: 45, egg_groups: &[HumanLike], evolutions: &[], learnset: MACHOP_MOVES, tms: &[COMMON_TMS, FIGHTING_TMS] },
+    Species { name: "Geodude", types: ("Rock", Some("Ground")), base_stats: base(40, 80, 100, 30, 30, 20), growth_rate: MediumSlow, catch_rate: 255, egg_groups: &[Mineral], evolutions: &[Evolution { into: "Graveler", trigger: Level(25) }], learnset: GEODUDE_MOVES, tms: &[COMMON_TMS, ROCK_TMS, GROUND_TMS] },
+    Species { name: "Graveler", types: ("Rock", Some("Ground")), base_stats: base(55, 95, 115, 45, 45, 35), growth_rate: MediumSlow, catch_rate: 120, egg_groups: &[Mineral], evolutions: &[Evolution { into: "Golem", trigger: Item("Linking Cord") }], learnset: GEODUDE_MOVES, tms: &[COMMON_TMS, ROCK_TMS, GROUND_TMS] },
+    Species { name: "Golem", types: ("Rock", Some("Ground")), base_stats: base(80, 120, 130, 55, 65, 45), growth_rate: MediumSlow, catch_rate: 45, egg_groups: &[Mineral], evolutions: &[], learnset: GEODUDE_MOVES, tms: &[COMMON_TMS, ROCK_TMS, GROUND_TMS] },
+    Species { name: "Gastly", types: ("Ghost", Some("Poison")), base_stats: base(30, 35, 30, 100, 35, 80), growth_rate: MediumSlow, catch_rate: 190, egg_groups: &[Amorphous], evolutions: &[Evolution { into: "Haunter", trigger: Level(25) }], learnset: GASTLY_MOVES, tms: &[COMMON_TMS, GHOST_TMS, POISON_TMS] },
+    Species { name: "Haunter", types: ("Ghost", Some("Poison")), base_stats: base(45, 50, 45, 115, 55, 95), growth_rate: MediumSlow, catch_rate: 90, egg_groups: &[Amorphous], evolutions: &[Evolution { into: "Gengar", trigger: Item("Linking Cord") }], learnset: GASTLY_MOVES, tms: &[COMMON_TMS, GHOST_TMS, POISON_TMS] },
+    Species { name: "Gengar", types: ("Ghost", Some("Poison")), base_stats: base(60, 65, 60, 130, 75, 110), growth_rate: MediumSlow, catch_rate: 45, egg_groups: &[Amorphous], evolutions: &[], learnset: GASTLY_MOVES, tms: &[COMMON_TMS, GHOST_TMS, POISON_TMS] },
+    Species { name: "Onix", types: ("Rock", Some("Ground")), base_stats: base(35, 45, 160, 30, 45, 70), growth_rate: MediumFast, catch_rate: 45, egg_groups: &[Mineral], evolutions: &[], learnset: ONIX_MOVES, tms: &[COMMON_TMS, ROCK_TMS, GROUND_TMS] },
+    Species { name: "Magikarp", types: ("Water", None), base_stats: base(20, 10, 55, 15, 20, 80), growth_rate: Slow, catch_rate: 255, egg_groups: &[Water2, Dragon], evolutions: &[Evolution { into: "Gyarados", trigger: Level(20) }], learnset: MAGIKARP_MOVES, tms: &[] },
+    Species { name: "Gyarados", types: ("Water", Some("Flying")), base_stats: base(95, 125, 79, 60, 100, 81), growth_rate: Slow, catch_rate: 45, egg_groups: &[Water2, Dragon], evolutions: &[], learnset: GYARADOS_MOVES, tms: &[COMMON_TMS, WATER_TMS, FLYING_TMS] },
+    Species { name: "Eevee", types: ("Normal", None), base_stats: base(55, 55, 50, 45, 65, 55), growth_rate: MediumFast, catch_rate: 45, egg_groups: &[Field], evolutions: &[Evolution { into: "Vaporeon", trigger: Item("Water Stone") }, Evolution { into: "Jolteon", trigger: Item("Thunder Stone") }, Evolution { into: "Flareon", trigger: Item("Fire Stone") }, Evolution { into: "Espeon", trigger: Friendship(220) }, Evolution { into: "Umbreon", trigger: Friendship(220) }], learnset: EEVEE_MOVES, tms: &[COMMON_TMS, NORMAL_TMS] },
+    Species { name: "Vaporeon", types: ("Water", None), base_stats: base(130, 65, 60, 110, 95, 65), growth_rate: MediumFast, catch_rate: 45, egg_groups: &[Field], evolutions: &[], learnset: VAPOREON_MOVES, tms: &[COMMON_TMS, WATER_TMS] },
+    Species { name: "Jolteon", types: ("Electric", None), base_stats: base(65, 65, 60, 110, 95, 130), growth_rate: MediumFast, catch_rate: 45, egg_groups: &[Field], evolutions: &[], learnset: JOLTEON_MOVES, tms: &[COMMON_TMS, ELECTRIC_TMS] },
+    Species { name: "Flareon", types: ("Fire", None), base_stats: base(65, 130, 60, 95, 110, 65), growth_rate: MediumFast, catch_rate: 45, egg_groups: &[Field], evolutions: &[], learnset: FLAREON_MOVES, tms: &[COMMON_TMS, FIRE_TMS] },
+    Species { name: "Espeon", types: ("Psychic", None), base_stats: base(65, 65, 60, 130, 95, 110), growth_rate: MediumFast, catch_rate: 45, egg_groups: &[Field], evolutions: &[], learnset: ESPEON_MOVES, tms: &[COMMON_TMS, PSYCHIC_TMS] },
+    Species { name: "Umbreon", types: ("Dark", None), base_stats: base(95, 65, 110, 60, 130, 65), growth_rate: MediumFast, catch_rate: 45, egg_groups: &[Field], evolutions: &[], learnset: UMBREON_MOVES, tms: &[COMMON_TMS, DARK_TMS] },
+    Species { name: "Snorlax", types: ("Normal", None), base_stats: base(160, 110, 65, 65, 110, 30), growth_rate: Slow, catch_rate: 25, egg_groups: &[Monster], evolutions: &[], learnset: SNORLAX_MOVES, tms: &[COMMON_TMS, NORMAL_TMS] },
+    Species { name: "Articuno", types: ("Ice", Some("Flying")), base_stats: base(90, 85, 100, 95, 125, 85), growth_rate: Slow, catch_rate: 3, egg_groups: &[Undiscovered], evolutions: &[], learnset: ARTICUNO_MOVES, tms: &[COMMON_TMS, ICE_TMS, FLYING_TMS] },
+    Species { name: "Zapdos", types: ("Electric", Some("Flying")), base_stats: base(90, 90, 85, 125, 90, 100), growth_rate: Slow, catch_rate: 3, egg_groups: &[Undiscovered], evolutions: &[], learnset: ZAPDOS_MOVES, tms: &[COMMON_TMS, ELECTRIC_TMS, FLYING_TMS] },
+    Species { name: "Moltres", types: ("Fire", Some("Flying")), base_stats: base(90, 100, 90, 125, 85, 90), growth_rate: Slow, catch_rate: 3, egg_groups: &[Undiscovered], evolutions: &[], learnset: MOLTRES_MOVES, tms: &[COMMON_TMS, FIRE_TMS, FLYING_TMS] },
+    Species { name: "Dratini", types: ("Dragon", None), base_stats: base(41, 64, 45, 50, 50, 50), growth_rate: Slow, catch_rate: 45, egg_groups: &[Water1, Dragon], evolutions: &[Evolution { into: "Dragonair", trigger: Level(30) }], learnset: DRATINI_MOVES, tms: &[COMMON_TMS, DRAGON_TMS] },
+    Species { name: "Dragonair", types: ("Dragon", None), base_stats: base(61, 84, 65, 70, 70, 70), growth_rate: Slow, catch_rate: 45, egg_groups: &[Water1, Dragon], evolutions: &[Evolution { into: "Dragonite", trigger: Level(55) }], learnset: DRATINI_MOVES, tms: &[COMMON_TMS, DRAGON_TMS] },
+    Species { name: "Dragonite", types: ("Dragon", Some("Flying")), base_stats: base(91, 134, 95, 100, 100, 80), growth_rate: Slow, catch_rate: 45, egg_groups: &[Water1, Dragon], evolutions: &[], learnset: DRATINI_MOVES, tms: &[COMMON_TMS, DRAGON_TMS, FLYING_TMS] },
+    Species { name: "Mewtwo", types: ("Psychic", None), base_stats: base(106, 110, 90, 154, 90, 130), growth_rate: Slow, catch_rate: 3, egg_groups: &[Undiscovered], evolutions: &[], learnset: MEWTWO_MOVES, tms: &[COMMON_TMS, PSYCHIC_TMS] },
+    Species { name: "Mew", types: ("Psychic", None), base_stats: base(100, 100, 100, 100, 100, 100), growth_rate: MediumSlow, catch_rate: 45, egg_groups: &[Undiscovered], evolutions: &[], learnset: MEW_MOVES, tms: &[COMMON_TMS, PSYCHIC_TMS] },
+];
+
+// case-insensitive so "pikachu" and "Pikachu" are the same species
+// legendaries and mythicals, including ones the table above doesn't have yet
+const LEGENDARIES: &[&str] = &["Articuno", "Zapdos", "Moltres", "Mewtwo", "Mew", "Raikou", "Entei", "Suicune", "Lugia", "Ho-Oh", "Celebi"];
+
+pub fn is_legendary(name: &str) -> bool {
+    LEGENDARIES.iter().any(|l| l.eq_ignore_ascii_case(name))
+}
+
+pub fn find(name: &str) -> Option<&'static Species> {
+    SPECIES.iter().find(|s| s.name.eq_ignore_ascii_case(name))
+}
+
+pub fn find_or_unknown(name: &str) -> &'static Species {
+    find(name).unwrap_or(&UNKNOWN_SPECIES)
+}
+
+// simplified main-series formula (IVs but no EVs/natures yet)
+pub fn calc_stats(base: &BaseStats, ivs: &Stats, level: u32) -> Stats {
+    let other = |b: u32, iv: u32| (2 * b + iv) * level / 100 + 5;
+    Stats {
+        hp: (2 * base.hp + ivs.hp) * level / 100 + level + 10,
+        attack: other(base.attack, ivs.attack),
+        defense: other(base.defense, ivs.defense),
+        sp_attack: other(base.sp_attack, ivs.sp_attack),
+        sp_defense: other(base.sp_defense, ivs.sp_defense),
+        speed: other(base.speed, ivs.speed),
+    }
+}
+
+// individual values go from 0 to 31 per stat
+pub const MAX_IV: u32 = 31;
+
+pub fn random_ivs(rng: &mut impl Rng) -> Stats {
+    Stats::from_array(std::array::from_fn(|_| rng.random_range(0..=MAX_IV)))
+}
+
+// first stage of an evolution line, which is what hatches from an egg
+pub fn base_form(species: &'static Species) -> &'static Species {
+    match SPECIES.iter().find(|s| s.evolutions.iter().any(|e| e.into == species.name)) {
+        Some(previous) => base_form(previous),
+        None => species,
+    }
+}
@@ -0,0 +1,128 @@
+// status conditions and what they do each turn, the job that ticks them outside of battle is in the server
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::models::Pokemon;
+
+// non-volatile status conditions - a Pokemon can only have one at a time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusCondition{
+    Poison,
+    Burn,
+    Paralysis,
+    Sleep,
+    Freeze,
+}
+
+impl StatusCondition{
+    // types that can never get this condition
+    fn immune_types(self) -> &'static [&'static str] {
+        match self {
+            StatusCondition::Poison => &["Poison", "Steel"],
+            StatusCondition::Burn => &["Fire"],
+            StatusCondition::Paralysis => &["Electric"],
+            StatusCondition::Freeze => &["Ice"],
+            StatusCondition::Sleep => &[],
+        }
+    }
+
+    pub fn is_immune(self, pokemon: &Pokemon) -> bool {
+        let immune = self.immune_types();
+        let is_type = |t: &str| immune.iter().any(|i| i.eq_ignore_ascii_case(t));
+        is_type(&pokemon.poke_type) || pokemon.secondary_type.as_deref().is_some_and(is_type)
+    }
+
+    // "poisoned", "burned"... for log messages
+    pub fn adjective(self) -> &'static str {
+        match self {
+            StatusCondition::Poison => "poisoned",
+            StatusCondition::Burn => "burned",
+            StatusCondition::Paralysis => "paralyzed",
+            StatusCondition::Sleep => "asleep",
+            StatusCondition::Freeze => "frozen",
+        }
+    }
+}
+
+// chance to thaw out each turn, 20% like the games
+const THAW_CHANCE: f64 = 0.2;
+
+// paralyzed Pokemon can't move 25% of the time
+const FULL_PARALYSIS_CHANCE: f64 = 0.25;
+
+// put a status on the Pokemon, Err explains why it didn't stick
+pub fn apply(pokemon: &mut Pokemon, condition: StatusCondition, rng: &mut impl Rng) -> Result<(), String> {
+    if pokemon.fainted {
+        return Err(format!("{} has fainted", pokemon.name));
+    }
+    if let Some(current) = pokemon.status {
+        return Err(format!("{} is already {}", pokemon.name, current.adjective()));
+    }
+    if condition.is_immune(pokemon) {
+        return Err(format!("{} can't be {}", pokemon.name, condition.adjective()));
+    }
+
+    pokemon.status = Some(condition);
+    // sleep lasts 1-3 turns
+    pokemon.sleep_turns = (condition == StatusCondition::Sleep).then(|| rng.random_range(1..=3));
+    Ok(())
+}
+
+pub fn cure(pokemon: &mut Pokemon) {
+    pokemon.status = None;
+    pokemon.sleep_turns = None;
+}
+
+// over-time damage at the end of a turn (or tick), returns what happened for the log
+// outside of battle poison and burn leave the Pokemon at 1 HP instead of fainting it
+pub fn end_of_turn(pokemon: &mut Pokemon, can_faint: bool) -> Option<String> {
+    if pokemon.fainted {
+        return None;
+    }
+
+    let (fraction, cause) = match pokemon.status? {
+        StatusCondition::Poison => (8, "poison"),
+        StatusCondition::Burn => (16, "its burn"),
+        _ => return None,
+    };
+    let mut amount = (pokemon.max_hp / fraction).max(1);
+    if !can_faint {
+        amount = amount.min(pokemon.current_hp.saturating_sub(1));
+        if amount == 0 {
+            return None;
+        }
+    }
+    pokemon.take_damage(amount);
+    Some(format!("{} was hurt by {} ({} HP)", pokemon.name, cause, amount))
+}
+
+// checked whenever the Pokemon tries to move
+// Ok means it can act (maybe with a message like waking up), Err says why it can't
+pub fn before_move(pokemon: &mut Pokemon, rng: &mut impl Rng) -> Result<Option<String>, String> {
+    let name = pokemon.name.clone();
+    match pokemon.status {
+        Some(StatusCondition::Sleep) => {
+            let turns = pokemon.sleep_turns.unwrap_or(0).saturating_sub(1);
+            if turns == 0 {
+                cure(pokemon);
+                Ok(Some(format!("{} woke up!", name)))
+            } else {
+                pokemon.sleep_turns = Some(turns);
+                Err(format!("{} is fast asleep", name))
+            }
+        }
+        Some(StatusCondition::Freeze) => {
+            if rng.random_bool(THAW_CHANCE) {
+                cure(pokemon);
+                Ok(Some(format!("{} thawed out!", name)))
+            } else {
+                Err(format!("{} is frozen solid", name))
+            }
+        }
+        Some(StatusCondition::Paralysis) if rng.random_bool(FULL_PARALYSIS_CHANCE) => {
+            Err(format!("{} is fully paralyzed", name))
+        }
+        _ => Ok(None),
+    }
+}
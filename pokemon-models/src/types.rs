@@ -0,0 +1,107 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PokeType{
+    Normal,
+    Fire,
+    Water,
+    Electric,
+    Grass,
+    Ice,
+    Fighting,
+    Poison,
+    Ground,
+    Flying,
+    Psychic,
+    Bug,
+    Rock,
+    Ghost,
+    Dragon,
+    Dark,
+    Steel,
+    Fairy,
+}
+
+use PokeType::*;
+
+pub const ALL_TYPES: [PokeType; 18] = [
+    Normal, Fire, Water, Electric, Grass, Ice, Fighting, Poison, Ground,
+    Flying, Psychic, Bug, Rock, Ghost, Dragon, Dark, Steel, Fairy,
+];
+
+impl fmt::Display for PokeType{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+// case-insensitive so "fire" and "Fire" both work in URLs
+impl FromStr for PokeType{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ALL_TYPES.iter()
+            .find(|t| t.to_string().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| format!("unknown type {}", s))
+    }
+}
+
+// (super effective against, not very effective against, no effect on)
+fn chart(attacking: PokeType) -> (&'static [PokeType], &'static [PokeType], &'static [PokeType]) {
+    match attacking {
+        Normal => (&[], &[Rock, Steel], &[Ghost]),
+        Fire => (&[Grass, Ice, Bug, Steel], &[Fire, Water, Rock, Dragon], &[]),
+        Water => (&[Fire, Ground, Rock], &[Water, Grass, Dragon], &[]),
+        Electric => (&[Water, Flying], &[Electric, Grass, Dragon], &[Ground]),
+        Grass => (&[Water, Ground, Rock], &[Fire, Grass, Poison, Flying, Bug, Dragon, Steel], &[]),
+        Ice => (&[Grass, Ground, Flying, Dragon], &[Fire, Water, Ice, Steel], &[]),
+        Fighting => (&[Normal, Ice, Rock, Dark, Steel], &[Poison, Flying, Psychic, Bug, Fairy], &[Ghost]),
+        Poison => (&[Grass, Fairy], &[Poison, Ground, Rock, Ghost], &[Steel]),
+        Ground => (&[Fire, Electric, Poison, Rock, Steel], &[Grass, Bug], &[Flying]),
+        Flying => (&[Grass, Fighting, Bug], &[Electric, Rock, Steel], &[]),
+        Psychic => (&[Fighting, Poison], &[Psychic, Steel], &[Dark]),
+        Bug => (&[Grass, Psychic, Dark], &[Fire, Fighting, Poison, Flying, Ghost, Steel, Fairy], &[]),
+        Rock => (&[Fire, Ice, Flying, Bug], &[Fighting, Ground, Steel], &[]),
+        Ghost => (&[Psychic, Ghost], &[Dark], &[Normal]),
+        Dragon => (&[Dragon], &[Steel], &[Fairy]),
+        Dark => (&[Psychic, Ghost], &[Fighting, Dark, Fairy], &[]),
+        Steel => (&[Ice, Rock, Fairy], &[Fire, Water, Electric, Steel], &[]),
+        Fairy => (&[Fighting, Dragon, Dark], &[Fire, Poison, Steel], &[]),
+    }
+}
+
+// damage multiplier of one attacking type against one defending type
+pub fn effectiveness(attacking: PokeType, defending: PokeType) -> f32 {
+    let (strong, weak, immune) = chart(attacking);
+    if immune.contains(&defending) {
+        0.0
+    } else if strong.contains(&defending) {
+        2.0
+    } else if weak.contains(&defending) {
+        0.5
+    } else {
+        1.0
+    }
+}
+
+// dual types multiply, so Ground vs Grass/Poison is 0.5 * 2 = 1
+pub fn effectiveness_against(attacking: PokeType, defending: &[PokeType]) -> f32 {
+    defending.iter().map(|d| effectiveness(attacking, *d)).product()
+}
+
+// the label the games print after an attack
+pub fn describe(multiplier: f32) -> &'static str {
+    if multiplier == 0.0 {
+        "no_effect"
+    } else if multiplier > 1.0 {
+        "super_effective"
+    } else if multiplier < 1.0 {
+        "not_very_effective"
+    } else {
+        "normal"
+    }
+}
@@ -0,0 +1,96 @@
+use crate::battle::MAX_TEAM_SIZE;
+use crate::models::{CreateGym, CreatePokemon, CreateTrainer, SetTeam, StartBattle, UpdatePokemon};
+use crate::species::{self, MAX_LEVEL};
+
+// what can be checked about a request on its own, without looking anything up
+// the server runs these before anything else and answers a 400 with the message,
+// so a client that runs them first finds out the same thing without a round trip
+pub trait Validate{
+    fn validate(&self) -> Result<(), String>;
+}
+
+// levels outside 1-100 don't exist in the games
+pub fn level(level: u32) -> Result<(), String> {
+    if (1..=MAX_LEVEL).contains(&level) {
+        Ok(())
+    } else {
+        Err(format!("level must be 1 to {}", MAX_LEVEL))
+    }
+}
+
+// a trainer's team: up to a full party, and nobody on it twice
+pub fn team(ids: &[u32]) -> Result<(), String> {
+    if ids.len() > MAX_TEAM_SIZE {
+        return Err(format!("a team has at most {} pokemon", MAX_TEAM_SIZE));
+    }
+    match ids.iter().enumerate().find(|(i, id)| ids[..*i].contains(id)) {
+        Some((_, id)) => Err(format!("pokemon {} is on the team twice", id)),
+        None => Ok(()),
+    }
+}
+
+impl Validate for CreatePokemon{
+    fn validate(&self) -> Result<(), String> {
+        level(self.level)
+    }
+}
+
+impl Validate for UpdatePokemon{
+    fn validate(&self) -> Result<(), String> {
+        self.level.map_or(Ok(()), level)
+    }
+}
+
+// just enough to catch typos, whether it exists is up to the verification link
+pub fn email(address: &str) -> Result<(), String> {
+    let valid = address.split_once('@').is_some_and(|(user, domain)| {
+        !user.is_empty() && domain.contains('.') && !domain.starts_with('.') && !domain.ends_with('.')
+    });
+    if valid && !address.chars().any(char::is_whitespace) {
+        Ok(())
+    } else {
+        Err(format!("{} isn't an email address", address))
+    }
+}
+
+impl Validate for CreateTrainer{
+    fn validate(&self) -> Result<(), String> {
+        team(&self.team)?;
+        self.email.as_deref().map_or(Ok(()), email)
+    }
+}
+
+impl Validate for SetTeam{
+    fn validate(&self) -> Result<(), String> {
+        team(&self.team)
+    }
+}
+
+impl Validate for StartBattle{
+    fn validate(&self) -> Result<(), String> {
+        for side in [&self.side_a, &self.side_b] {
+            if side.is_empty() || side.len() > MAX_TEAM_SIZE {
+                return Err(format!("each side needs 1 to {} pokemon", MAX_TEAM_SIZE));
+            }
+        }
+        if self.side_a.iter().any(|id| self.side_b.contains(id)) {
+            return Err("a pokemon can't battle itself".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Validate for CreateGym{
+    fn validate(&self) -> Result<(), String> {
+        if self.team.is_empty() || self.team.len() > MAX_TEAM_SIZE {
+            return Err(format!("a gym leader needs 1 to {} pokemon", MAX_TEAM_SIZE));
+        }
+        for member in &self.team {
+            if species::find(&member.name).is_none() {
+                return Err(format!("unknown species {}", member.name));
+            }
+            level(member.level).map_err(|e| format!("{}'s {}", member.name, e))?;
+        }
+        Ok(())
+    }
+}
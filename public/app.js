@@ -0,0 +1,130 @@
+// a tiny single page app on top of the JSON API
+// /ui/ lists the team, /ui/pokemon/{id} shows one Pokemon
+
+const app = document.getElementById("app");
+
+async function api(method, path, body) {
+  const response = await fetch(path, {
+    method,
+    headers: body ? { "content-type": "application/json" } : {},
+    body: body ? JSON.stringify(body) : undefined,
+  });
+  if (!response.ok) {
+    const text = await response.text();
+    let message = `${response.status} ${response.statusText}`;
+    try {
+      message = JSON.parse(text).error || message;
+    } catch (_) {}
+    throw new Error(message);
+  }
+  return response.status === 204 ? null : response.json();
+}
+
+function escape(text) {
+  const div = document.createElement("div");
+  div.textContent = String(text ?? "");
+  return div.innerHTML;
+}
+
+function showError(error) {
+  const p = document.createElement("p");
+  p.className = "error";
+  p.textContent = error.message;
+  app.prepend(p);
+}
+
+async function listPage() {
+  const team = await api("GET", "/pokemon");
+  const rows = team.map(p => `
+    <tr>
+      <td>${p.id}</td>
+      <td><a href="/ui/pokemon/${p.id}" data-link>${escape(p.name)}</a></td>
+      <td>${escape([p.poke_type, p.secondary_type].filter(Boolean).join(" / "))}</td>
+      <td>${p.level}</td>
+      <td>${p.current_hp}/${p.max_hp}</td>
+    </tr>`).join("");
+  app.innerHTML = `
+    <h2>Team</h2>
+    <table>
+      <thead><tr><th>ID</th><th>Name</th><th>Type</th><th>Level</th><th>HP</th></tr></thead>
+      <tbody>${rows || `<tr><td colspan="5">No Pokemon yet</td></tr>`}</tbody>
+    </table>
+    <h2>Add a Pokemon</h2>
+    <form id="create">
+      <input name="name" placeholder="Name" required>
+      <input name="poke_type" placeholder="Type">
+      <input name="secondary_type" placeholder="Second type">
+      <input name="level" type="number" min="1" max="100" value="5" required>
+      <button>Add</button>
+    </form>`;
+  document.getElementById("create").addEventListener("submit", async event => {
+    event.preventDefault();
+    const form = new FormData(event.target);
+    try {
+      await api("POST", "/pokemon", {
+        name: form.get("name"),
+        poke_type: form.get("poke_type") || undefined,
+        secondary_type: form.get("secondary_type") || undefined,
+        level: Number(form.get("level")),
+      });
+      render();
+    } catch (error) {
+      showError(error);
+    }
+  });
+}
+
+async function detailPage(id) {
+  const p = await api("GET", `/pokemon/${id}`);
+  const stats = Object.entries(p.stats).map(([stat, value]) => `<tr><th>${stat}</th><td>${value}</td></tr>`).join("");
+  app.innerHTML = `
+    <h2>${escape(p.name)} <small>#${p.id}</small></h2>
+    ${p.sprite_url ? `<img class="sprite" src="${escape(p.sprite_url)}" alt="">` : ""}
+    <p>Level ${p.level} ${escape(p.poke_type)}${p.secondary_type ? " / " + escape(p.secondary_type) : ""},
+      HP ${p.current_hp}/${p.max_hp}${p.status ? ", " + escape(p.status) : ""}</p>
+    <table>${stats}</table>
+    <p>Moves: ${p.moves.map(escape).join(", ") || "none"}</p>
+    <form id="exp">
+      <input name="amount" type="number" min="1" value="100" required>
+      <button>Gain exp</button>
+    </form>
+    <button id="heal">Heal</button>
+    <button id="delete">Delete</button>`;
+  document.getElementById("exp").addEventListener("submit", async event => {
+    event.preventDefault();
+    const amount = Number(new FormData(event.target).get("amount"));
+    try {
+      await api("POST", `/pokemon/${id}/gain-exp`, { amount });
+      render();
+    } catch (error) {
+      showError(error);
+    }
+  });
+  document.getElementById("heal").addEventListener("click", () => api("POST", `/pokemon/${id}/heal`).then(render, showError));
+  document.getElementById("delete").addEventListener("click", () => api("DELETE", `/pokemon/${id}`).then(() => navigate("/ui/"), showError));
+}
+
+async function render() {
+  const match = location.pathname.match(/^\/ui\/pokemon\/(\d+)/);
+  try {
+    await (match ? detailPage(match[1]) : listPage());
+  } catch (error) {
+    app.innerHTML = "";
+    showError(error);
+  }
+}
+
+function navigate(path) {
+  history.pushState(null, "", path);
+  render();
+}
+
+document.addEventListener("click", event => {
+  const link = event.target.closest("a[data-link]");
+  if (link) {
+    event.preventDefault();
+    navigate(link.getAttribute("href"));
+  }
+});
+window.addEventListener("popstate", render);
+render();
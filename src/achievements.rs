@@ -0,0 +1,120 @@
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+
+use crate::species;
+use crate::state::SharedState;
+
+// things that happen to a trainer, the handlers report them and the rules below decide what they earn
+#[derive(Debug, Clone, Copy)]
+pub enum Event<'a>{
+    // a wild Pokemon of this species was caught
+    Caught(&'a str),
+    // got one some other way: hatched, traded for, bought or evolved
+    Obtained(&'a str),
+    // a gym or tournament battle
+    BattleWon,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Achievement{
+    pub id: String,
+    pub name: String,
+    pub description: String,
+}
+
+// everything the rules look at, kept up to date as events come in
+#[derive(Debug, Clone, Serialize)]
+pub struct Progress{
+    pub trainer_id: u32,
+    pub catches: u32,
+    pub battles_won: u32,
+    // every species the trainer has had, like a pokedex
+    pub pokedex: BTreeSet<String>,
+    // in the order they were earned
+    pub earned: Vec<Achievement>,
+}
+
+impl Progress{
+    pub fn new(trainer_id: u32) -> Progress {
+        Progress { trainer_id, catches: 0, battles_won: 0, pokedex: BTreeSet::new(), earned: Vec::new() }
+    }
+
+    fn has(&self, id: &str) -> bool {
+        self.earned.iter().any(|a| a.id == id)
+    }
+}
+
+struct Rule{
+    id: &'static str,
+    name: &'static str,
+    description: &'static str,
+    met: fn(&Progress) -> bool,
+}
+
+const RULES: &[Rule] = &[
+    Rule { id: "first_catch", name: "First Catch", description: "Catch a wild pokemon", met: |p| p.catches >= 1 },
+    Rule { id: "ten_catches", name: "Collector", description: "Catch 10 wild pokemon", met: |p| p.catches >= 10 },
+    Rule { id: "first_win", name: "First Victory", description: "Win a gym or tournament battle", met: |p| p.battles_won >= 1 },
+    Rule { id: "hundred_wins", name: "Centurion", description: "Win 100 gym or tournament battles", met: |p| p.battles_won >= 100 },
+];
+
+// every type that has species, e.g. "Fire"
+fn types() -> BTreeSet<&'static str> {
+    species::SPECIES.iter()
+        .flat_map(|s| [Some(s.types.0), s.types.1])
+        .flatten()
+        .collect()
+}
+
+// one per type: have every species of that type in the pokedex
+fn type_masters(progress: &Progress) -> Vec<Achievement> {
+    types().into_iter()
+        .filter(|t| {
+            species::SPECIES.iter()
+                .filter(|s| s.types.0 == *t || s.types.1 == Some(*t))
+                .all(|s| progress.pokedex.contains(s.name))
+        })
+        .map(|t| Achievement {
+            id: format!("{}_master", t.to_lowercase()),
+            name: format!("{} Master", t),
+            description: format!("Have every {} type pokemon in your pokedex", t),
+        })
+        .collect()
+}
+
+// apply the event, then award anything it unlocked
+fn apply(progress: &mut Progress, event: Event) {
+    match event {
+        Event::Caught(species) => {
+            progress.catches += 1;
+            progress.pokedex.insert(species.to_string());
+        }
+        Event::Obtained(species) => {
+            progress.pokedex.insert(species.to_string());
+        }
+        Event::BattleWon => progress.battles_won += 1,
+    }
+
+    let unlocked: Vec<Achievement> = RULES.iter()
+        .filter(|r| (r.met)(progress))
+        .map(|r| Achievement { id: r.id.to_string(), name: r.name.to_string(), description: r.description.to_string() })
+        .chain(type_masters(progress))
+        .filter(|a| !progress.has(&a.id))
+        .collect();
+    progress.earned.extend(unlocked);
+}
+
+pub fn record(state: &SharedState, trainer_id: u32, event: Event) {
+    let mut achievements = state.achievements.lock().unwrap();
+    let index = match achievements.iter().position(|p| p.trainer_id == trainer_id) {
+        Some(index) => index,
+        None => {
+            achievements.push(Progress::new(trainer_id));
+            achievements.len() - 1
+        }
+    };
+    apply(&mut achievements[index], event);
+    drop(achievements);
+    state.plugins.on_event(trainer_id, event);
+}
@@ -0,0 +1,442 @@
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::achievements::Progress;
+use crate::breeding::Egg;
+use crate::daycare::DaycareStay;
+use crate::error::ApiError;
+use crate::market::Listing;
+use crate::models::{BattleRecord, Gym, Pokemon, Trade, Trainer};
+pub use crate::models::Strategy;
+use crate::sprites;
+use crate::state::SharedState;
+use crate::tournament::Tournament;
+use crate::trash::TrashedPokemon;
+
+// bumped whenever a dump's shape changes in a way an import has to know about
+pub const SCHEMA_VERSION: u32 = 1;
+
+// biggest dump POST /admin/import takes
+pub const MAX_IMPORT_SIZE: usize = 64 * 1024 * 1024;
+
+// ADMIN_TOKEN, which has nothing to do with trainers so it can't be guessed from one
+pub fn token_from_env() -> Option<String> {
+    crate::secrets::var("ADMIN_TOKEN").ok().filter(|t| !t.trim().is_empty())
+}
+
+// takes the same time however much of the token is right
+fn same_token(given: &[u8], expected: &[u8]) -> bool {
+    given.len() == expected.len() && given.iter().zip(expected).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+// middleware for the /admin routes: Authorization: Bearer <ADMIN_TOKEN>, and off altogether without one
+pub async fn require_token(State(state): State<SharedState>, request: Request, next: Next) -> Response {
+    let Some(expected) = &state.admin_token else {
+        return ApiError::forbidden("the admin API is off, set ADMIN_TOKEN to turn it on").into_response();
+    };
+    let given = request.headers().get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    if !given.is_some_and(|g| same_token(g.trim().as_bytes(), expected.as_bytes())) {
+        let mut response = ApiError::new(StatusCode::UNAUTHORIZED, "the admin API needs Authorization: Bearer <ADMIN_TOKEN>").into_response();
+        response.headers_mut().insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+        return response;
+    }
+    next.run(request).await
+}
+
+// everything the server has in memory, for backups or copying one server's data onto another
+#[derive(Debug, Serialize)]
+pub struct Dump{
+    pub schema_version: u32,
+    // unix seconds
+    pub exported_at: u64,
+    pub pokemon: Vec<Pokemon>,
+    pub trainers: Vec<Trainer>,
+    pub gyms: Vec<Gym>,
+    pub trades: Vec<Trade>,
+    pub market: Vec<Listing>,
+    pub battles: Vec<BattleRecord>,
+    pub tournaments: Vec<Tournament>,
+    pub eggs: Vec<Egg>,
+    pub daycare: Vec<DaycareStay>,
+    pub trash: Vec<TrashedPokemon>,
+    pub achievements: Vec<Progress>,
+}
+
+// all at once under the locks, so the dump is consistent
+pub fn snapshot(state: &SharedState) -> Dump {
+    let tournaments = state.tournaments.lock().unwrap();
+    let eggs = state.eggs.lock().unwrap();
+    let daycare = state.daycare.lock().unwrap();
+    let trades = state.trades.lock().unwrap();
+    let market = state.market.lock().unwrap();
+    let trash = state.trash.lock().unwrap();
+    let team = state.team.lock().unwrap();
+    let trainers = state.trainers.lock().unwrap();
+    let achievements = state.achievements.lock().unwrap();
+    Dump {
+        schema_version: SCHEMA_VERSION,
+        exported_at: state.time.now(),
+        pokemon: team.clone(),
+        trainers: trainers.clone(),
+        gyms: state.gyms.lock().unwrap().clone(),
+        trades: trades.clone(),
+        market: market.clone(),
+        battles: state.battles.lock().unwrap().clone(),
+        tournaments: tournaments.clone(),
+        eggs: eggs.clone(),
+        daycare: daycare.clone(),
+        trash: trash.clone(),
+        achievements: achievements.clone(),
+    }
+}
+
+// the snapshot as JSON with its ETag, stamped with the last export's time if nothing has changed since
+// so the same data always exports to the same bytes, and a download cut off halfway can be resumed
+pub fn export_json(state: &SharedState) -> serde_json::Result<(Dump, Vec<u8>, String)> {
+    let mut dump = snapshot(state);
+    let now = dump.exported_at;
+    let mut last = state.last_export.lock().unwrap();
+    if let Some((at, etag)) = last.clone() {
+        dump.exported_at = at;
+        let json = serde_json::to_vec(&dump)?;
+        if sprites::etag(&json) == etag {
+            return Ok((dump, json, etag));
+        }
+        dump.exported_at = now;
+    }
+    let json = serde_json::to_vec(&dump)?;
+    let etag = sprites::etag(&json);
+    *last = Some((now, etag.clone()));
+    Ok((dump, json, etag))
+}
+
+// one line of NDJSON, e.g. {"type":"pokemon","data":{...}}
+#[derive(Serialize)]
+struct Line<'a, T>{
+    #[serde(rename = "type")]
+    kind: &'a str,
+    data: T,
+}
+
+fn line<T: Serialize>(kind: &str, data: T) -> Result<String, Infallible> {
+    // nothing in there can fail to serialize
+    let mut text = serde_json::to_string(&Line { kind, data }).unwrap_or_default();
+    text.push('\n');
+    Ok(text)
+}
+
+impl Dump{
+    // a header line, then one line per record, each serialized as the body gets to it
+    pub fn into_ndjson(self) -> impl Iterator<Item = Result<String, Infallible>> + Send {
+        #[derive(Serialize)]
+        struct Header{
+            schema_version: u32,
+            exported_at: u64,
+        }
+        let header = Header { schema_version: self.schema_version, exported_at: self.exported_at };
+        // same order and names as RECORD_TYPES
+        std::iter::once(line("header", header))
+            .chain(self.pokemon.into_iter().map(|r| line("pokemon", r)))
+            .chain(self.trainers.into_iter().map(|r| line("trainer", r)))
+            .chain(self.gyms.into_iter().map(|r| line("gym", r)))
+            .chain(self.trades.into_iter().map(|r| line("trade", r)))
+            .chain(self.market.into_iter().map(|r| line("listing", r)))
+            .chain(self.battles.into_iter().map(|r| line("battle", r)))
+            .chain(self.tournaments.into_iter().map(|r| line("tournament", r)))
+            .chain(self.eggs.into_iter().map(|r| line("egg", r)))
+            .chain(self.daycare.into_iter().map(|r| line("daycare", r)))
+            .chain(self.trash.into_iter().map(|r| line("trash", r)))
+            .chain(self.achievements.into_iter().map(|r| line("achievements", r)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome{
+    Created,
+    Skipped,
+    Overwritten,
+    Merged,
+    // only with the fail strategy, and then nothing was imported
+    Conflict,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecordReport{
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub id: u32,
+    pub outcome: Outcome,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportReport{
+    pub strategy: Strategy,
+    // false if the fail strategy found a conflict
+    pub applied: bool,
+    pub records: Vec<RecordReport>,
+    // record types in the dump that can't be imported yet, and how many of each
+    pub ignored: BTreeMap<String, usize>,
+}
+
+// the kinds of record an import can bring in, the rest of a dump only makes sense alongside them
+pub trait Record: Serialize + DeserializeOwned{
+    const KIND: &'static str;
+    fn id(&self) -> u32;
+    fn set_id(&mut self, id: u32);
+}
+
+impl Record for Pokemon{
+    const KIND: &'static str = "pokemon";
+    fn id(&self) -> u32 { self.id }
+    fn set_id(&mut self, id: u32) { self.id = id; }
+}
+
+impl Record for Trainer{
+    const KIND: &'static str = "trainer";
+    fn id(&self) -> u32 { self.id }
+    fn set_id(&mut self, id: u32) { self.id = id; }
+}
+
+impl Record for Gym{
+    const KIND: &'static str = "gym";
+    fn id(&self) -> u32 { self.id }
+    fn set_id(&mut self, id: u32) { self.id = id; }
+}
+
+// (key in a JSON dump, type in an NDJSON one) for each kind of record
+const RECORD_TYPES: &[(&str, &str)] = &[
+    ("pokemon", "pokemon"),
+    ("trainers", "trainer"),
+    ("gyms", "gym"),
+    ("trades", "trade"),
+    ("market", "listing"),
+    ("battles", "battle"),
+    ("tournaments", "tournament"),
+    ("eggs", "egg"),
+    ("daycare", "daycare"),
+    ("trash", "trash"),
+    ("achievements", "achievements"),
+];
+
+// a dump split up by record type, still as JSON so a merge knows which fields were actually sent
+// keyed the way a JSON dump is, whichever format it came in
+#[derive(Debug, Default)]
+pub struct Incoming{
+    schema_version: Option<u64>,
+    records: BTreeMap<String, Vec<Value>>,
+}
+
+impl Incoming{
+    fn add(&mut self, kind: &str, record: Value) {
+        let key = RECORD_TYPES.iter().find(|(key, ndjson)| *key == kind || *ndjson == kind).map_or(kind, |(key, _)| key);
+        self.records.entry(key.to_string()).or_default().push(record);
+    }
+
+    fn take(&mut self, key: &str) -> Vec<Value> {
+        self.records.remove(key).unwrap_or_default()
+    }
+
+    // what GET /admin/export gives back
+    pub fn from_json(bytes: &[u8]) -> Result<Incoming, String> {
+        Incoming::from_value(serde_json::from_slice(bytes).map_err(|e| format!("that isn't a JSON dump: {}", e))?)
+    }
+
+    fn from_value(dump: Value) -> Result<Incoming, String> {
+        let Value::Object(fields) = dump else {
+            return Err("a dump should be a JSON object".to_string());
+        };
+        let mut incoming = Incoming::default();
+        for (key, value) in fields {
+            match (key.as_str(), value) {
+                ("schema_version", value) => incoming.schema_version = value.as_u64(),
+                ("exported_at", _) => {}
+                (key, Value::Array(records)) => records.into_iter().for_each(|r| incoming.add(key, r)),
+                (key, _) => return Err(format!("{} should be a list of records", key)),
+            }
+        }
+        Ok(incoming)
+    }
+
+    // what GET /admin/export?format=ndjson gives back
+    pub fn from_ndjson(bytes: &[u8]) -> Result<Incoming, String> {
+        #[derive(Deserialize)]
+        struct Line{
+            #[serde(rename = "type")]
+            kind: String,
+            data: Value,
+        }
+        let text = std::str::from_utf8(bytes).map_err(|_| "NDJSON should be UTF-8".to_string())?;
+        let mut incoming = Incoming::default();
+        for (number, text) in text.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+            let line: Line = serde_json::from_str(text).map_err(|e| format!("line {} isn't a dump record: {}", number + 1, e))?;
+            if line.kind == "header" {
+                incoming.schema_version = line.data.get("schema_version").and_then(Value::as_u64);
+            } else {
+                incoming.add(&line.kind, line.data);
+            }
+        }
+        Ok(incoming)
+    }
+
+    // either format, NDJSON is the one whose first line is a record on its own
+    pub fn parse(bytes: &[u8]) -> Result<Incoming, String> {
+        let first_line = bytes.split(|b| *b == b'\n').next().unwrap_or_default();
+        let is_ndjson = serde_json::from_slice::<Value>(first_line).is_ok_and(|v| v.get("type").is_some() && v.get("data").is_some());
+        if is_ndjson { Incoming::from_ndjson(bytes) } else { Incoming::from_json(bytes) }
+    }
+
+    // the live data, as if it had just been exported
+    pub fn live(state: &SharedState) -> Incoming {
+        let dump = serde_json::to_value(snapshot(state)).unwrap_or_default();
+        Incoming::from_value(dump).unwrap_or_default()
+    }
+}
+
+// the imported fields on top of the existing record
+fn merge_fields<T: Record>(existing: &T, incoming: &Value) -> Result<T, String> {
+    let mut merged = serde_json::to_value(existing).map_err(|e| e.to_string())?;
+    if let (Value::Object(fields), Value::Object(updates)) = (&mut merged, incoming) {
+        fields.extend(updates.iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+    serde_json::from_value(merged).map_err(|e| format!("{} {} can't be merged: {}", T::KIND, existing.id(), e))
+}
+
+// works out what happens to each record without changing anything
+// a merge only needs the ID and the fields that change, everything else needs the whole record
+fn plan<T: Record>(existing: &[T], incoming: &[Value], strategy: Strategy) -> Result<Vec<(T, Outcome)>, String> {
+    incoming.iter().enumerate()
+        .map(|(index, value)| {
+            let id = value.get("id").and_then(Value::as_u64)
+                .ok_or_else(|| format!("{} record {} has no id", T::KIND, index + 1))?;
+            let current = existing.iter().find(|r| r.id() as u64 == id);
+            if let (Some(current), Strategy::Merge) = (current, strategy) {
+                return Ok((merge_fields(current, value)?, Outcome::Merged));
+            }
+            let record: T = serde_json::from_value(value.clone())
+                .map_err(|e| format!("{} record {} isn't valid: {}", T::KIND, index + 1, e))?;
+            let outcome = match (current, strategy) {
+                (None, _) => Outcome::Created,
+                (Some(_), Strategy::Skip) => Outcome::Skipped,
+                (Some(_), Strategy::Fail) => Outcome::Conflict,
+                (Some(_), _) => Outcome::Overwritten,
+            };
+            Ok((record, outcome))
+        })
+        .collect()
+}
+
+fn report<T: Record>(planned: &[(T, Outcome)]) -> impl Iterator<Item = RecordReport> + '_ {
+    planned.iter().map(|(record, outcome)| RecordReport { kind: T::KIND, id: record.id(), outcome: *outcome })
+}
+
+// the collections are kept in ID order, new IDs come from the last one
+fn apply<T: Record>(existing: &mut Vec<T>, planned: Vec<(T, Outcome)>) {
+    for (record, outcome) in planned {
+        match (existing.binary_search_by_key(&record.id(), Record::id), outcome) {
+            (_, Outcome::Skipped | Outcome::Conflict) => {}
+            (Ok(index), _) => existing[index] = record,
+            (Err(index), _) => existing.insert(index, record),
+        }
+    }
+}
+
+// Err is for a dump that can't be read at all, a conflict under the fail strategy comes back as applied: false
+pub fn import(state: &SharedState, mut incoming: Incoming, strategy: Strategy) -> Result<ImportReport, String> {
+    let version = incoming.schema_version.unwrap_or(1);
+    if version > SCHEMA_VERSION as u64 {
+        return Err(format!("that dump is schema version {}, this server only knows up to {}", version, SCHEMA_VERSION));
+    }
+
+    let mut team = state.team.lock().unwrap();
+    let mut trainers = state.trainers.lock().unwrap();
+    let mut gyms = state.gyms.lock().unwrap();
+    let pokemon = plan(&team, &incoming.take("pokemon"), strategy)?;
+    let trainer_records = plan(&trainers, &incoming.take("trainers"), strategy)?;
+    let gym_records = plan(&gyms, &incoming.take("gyms"), strategy)?;
+    let ignored = incoming.records.iter()
+        .filter(|(_, records)| !records.is_empty())
+        .map(|(key, records)| (key.clone(), records.len()))
+        .collect();
+
+    let mut records: Vec<RecordReport> = report(&pokemon).chain(report(&trainer_records)).chain(report(&gym_records)).collect();
+    let applied = !records.iter().any(|r| r.outcome == Outcome::Conflict);
+    if applied {
+        apply(&mut team, pokemon);
+        apply(&mut trainers, trainer_records);
+        apply(&mut gyms, gym_records);
+    } else {
+        // nothing changed, so only the clashes are worth reporting
+        records.retain(|r| r.outcome == Outcome::Conflict);
+    }
+    Ok(ImportReport { strategy, applied, records, ignored })
+}
+
+// one kind of record, compared by ID
+#[derive(Debug, Default, Serialize)]
+pub struct KindDiff{
+    pub added: Vec<u64>,
+    pub removed: Vec<u64>,
+    pub changed: Vec<ChangedRecord>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangedRecord{
+    pub id: u64,
+    // top-level fields that differ
+    pub fields: Vec<String>,
+}
+
+// most records have an id, the ones that hang off something else go by that
+fn record_id(record: &Value) -> Option<u64> {
+    ["id", "pokemon_id", "trainer_id"].iter()
+        .find_map(|key| record.get(key).and_then(Value::as_u64))
+        .or_else(|| record.get("pokemon")?.get("id")?.as_u64())
+}
+
+fn by_id(records: Vec<Value>) -> BTreeMap<u64, Value> {
+    records.into_iter().filter_map(|r| Some((record_id(&r)?, r))).collect()
+}
+
+// what changes going from `before` to `after`, only the kinds that differ are in there
+pub fn diff(mut before: Incoming, mut after: Incoming) -> BTreeMap<String, KindDiff> {
+    let kinds: Vec<String> = before.records.keys().chain(after.records.keys()).cloned().collect();
+    let mut diffs = BTreeMap::new();
+    for kind in kinds {
+        let (old, new) = (by_id(before.take(&kind)), by_id(after.take(&kind)));
+        let mut diff = KindDiff::default();
+        for (id, record) in &new {
+            match old.get(id) {
+                None => diff.added.push(*id),
+                Some(previous) if previous != record => {
+                    let empty = serde_json::Map::new();
+                    let (a, b) = (previous.as_object().unwrap_or(&empty), record.as_object().unwrap_or(&empty));
+                    let mut fields: Vec<String> = a.keys().chain(b.keys())
+                        .filter(|k| a.get(*k) != b.get(*k))
+                        .cloned()
+                        .collect();
+                    fields.sort();
+                    fields.dedup();
+                    diff.changed.push(ChangedRecord { id: *id, fields });
+                }
+                Some(_) => {}
+            }
+        }
+        diff.removed = old.keys().filter(|id| !new.contains_key(id)).copied().collect();
+        if !(diff.added.is_empty() && diff.removed.is_empty() && diff.changed.is_empty()) {
+            diffs.insert(kind, diff);
+        }
+    }
+    diffs
+}
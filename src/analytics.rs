@@ -0,0 +1,179 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::models::Pokemon;
+use crate::species::MAX_LEVEL;
+
+// histogram buckets are this many levels wide: 1-10, 11-20...
+const BUCKET_SIZE: u32 = 10;
+
+#[derive(Debug, Serialize)]
+pub struct LevelBucket{
+    pub min_level: u32,
+    pub max_level: u32,
+    pub count: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TeamStats{
+    pub count: u32,
+    // a dual-type Pokemon counts towards both of its types
+    pub by_type: BTreeMap<String, u32>,
+    // None while there are no Pokemon
+    pub avg_level: Option<f64>,
+    pub min_level: Option<u32>,
+    pub max_level: Option<u32>,
+    pub level_distribution: Vec<LevelBucket>,
+}
+
+pub fn team_stats(team: &[Pokemon]) -> TeamStats {
+    let mut by_type = BTreeMap::new();
+    for pokemon in team {
+        for t in std::iter::once(&pokemon.poke_type).chain(&pokemon.secondary_type) {
+            *by_type.entry(t.clone()).or_default() += 1;
+        }
+    }
+
+    let levels = || team.iter().map(|p| p.level);
+    let level_distribution = (0..MAX_LEVEL.div_ceil(BUCKET_SIZE))
+        .map(|i| {
+            let (min_level, max_level) = (i * BUCKET_SIZE + 1, (i + 1) * BUCKET_SIZE);
+            let count = levels().filter(|l| (min_level..=max_level).contains(l)).count() as u32;
+            LevelBucket { min_level, max_level, count }
+        })
+        .collect();
+
+    TeamStats {
+        count: team.len() as u32,
+        by_type,
+        avg_level: (!team.is_empty()).then(|| levels().sum::<u32>() as f64 / team.len() as f64),
+        min_level: levels().min(),
+        max_level: levels().max(),
+        level_distribution,
+    }
+}
+
+// fields /pokemon/aggregate can group by
+#[derive(Debug, Clone, Copy)]
+pub enum GroupBy{
+    PokeType,
+    SecondaryType,
+    Name,
+    Species,
+    Level,
+    Shiny,
+    Status,
+}
+
+impl GroupBy{
+    pub const NAMES: &'static str = "poke_type, secondary_type, name, species, level, shiny, status";
+
+    pub fn parse(name: &str) -> Option<GroupBy> {
+        match name {
+            "poke_type" => Some(GroupBy::PokeType),
+            "secondary_type" => Some(GroupBy::SecondaryType),
+            "name" => Some(GroupBy::Name),
+            "species" => Some(GroupBy::Species),
+            "level" => Some(GroupBy::Level),
+            "shiny" => Some(GroupBy::Shiny),
+            "status" => Some(GroupBy::Status),
+            _ => None,
+        }
+    }
+
+    fn key(self, pokemon: &Pokemon) -> Value {
+        match self {
+            GroupBy::PokeType => json!(pokemon.poke_type),
+            GroupBy::SecondaryType => json!(pokemon.secondary_type),
+            GroupBy::Name => json!(pokemon.name),
+            GroupBy::Species => json!(pokemon.species),
+            GroupBy::Level => json!(pokemon.level),
+            GroupBy::Shiny => json!(pokemon.shiny),
+            GroupBy::Status => json!(pokemon.status),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Field{
+    Level,
+    Exp,
+    Friendship,
+}
+
+impl Field{
+    fn value(self, pokemon: &Pokemon) -> u32 {
+        match self {
+            Field::Level => pokemon.level,
+            Field::Exp => pokemon.exp,
+            Field::Friendship => pokemon.friendship,
+        }
+    }
+}
+
+// "count", or avg/min/max of a field, e.g. "avg_level"
+#[derive(Debug, Clone, Copy)]
+pub enum Metric{
+    Count,
+    Avg(Field),
+    Min(Field),
+    Max(Field),
+}
+
+impl Metric{
+    pub const NAMES: &'static str = "count, or avg_, min_ or max_ followed by level, exp or friendship";
+
+    pub fn parse(name: &str) -> Option<Metric> {
+        if name == "count" {
+            return Some(Metric::Count);
+        }
+        let (function, field) = name.split_once('_')?;
+        let field = match field {
+            "level" => Field::Level,
+            "exp" => Field::Exp,
+            "friendship" => Field::Friendship,
+            _ => return None,
+        };
+        match function {
+            "avg" => Some(Metric::Avg(field)),
+            "min" => Some(Metric::Min(field)),
+            "max" => Some(Metric::Max(field)),
+            _ => None,
+        }
+    }
+
+    // groups are never empty, so there's always a value
+    fn compute(self, group: &[&Pokemon]) -> Value {
+        let values = |field: Field| group.iter().map(move |p| field.value(p));
+        match self {
+            Metric::Count => json!(group.len()),
+            Metric::Avg(field) => json!(values(field).map(f64::from).sum::<f64>() / group.len() as f64),
+            Metric::Min(field) => json!(values(field).min()),
+            Metric::Max(field) => json!(values(field).max()),
+        }
+    }
+}
+
+// a small table: one row per group as [key, value], in key order
+#[derive(Debug, Serialize)]
+pub struct Aggregate{
+    pub columns: [String; 2],
+    pub rows: Vec<[Value; 2]>,
+}
+
+// levels sort as numbers, everything else by its JSON text
+type SortKey = (Option<u64>, String);
+
+pub fn aggregate(team: &[Pokemon], group_by: GroupBy, metric: Metric, names: [String; 2]) -> Aggregate {
+    let mut groups: BTreeMap<SortKey, (Value, Vec<&Pokemon>)> = BTreeMap::new();
+    for pokemon in team {
+        let key = group_by.key(pokemon);
+        groups.entry((key.as_u64(), key.to_string())).or_insert_with(|| (key, Vec::new())).1.push(pokemon);
+    }
+    Aggregate {
+        columns: names,
+        rows: groups.into_values().map(|(key, group)| [key, metric.compute(&group)]).collect(),
+    }
+}
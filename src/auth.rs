@@ -0,0 +1,163 @@
+use std::time::Duration;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::models::Session;
+use crate::sharing::hex;
+
+// how long a login lasts before logging in again
+pub const DEFAULT_LIFETIME: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+// what logins hand out, an EdDSA-signed JWT sent back as `Authorization: Bearer <token>`
+#[derive(Debug)]
+pub struct Auth{
+    // newest first, it signs new sessions and the rest still check the ones they signed
+    keys: Vec<SigningKey>,
+    lifetime: Duration,
+    // false stops X-Trainer-Id from saying who a request is from, so only a session does
+    trust_header: bool,
+}
+
+#[derive(Debug)]
+pub struct SigningKey{
+    // the start of the public key's SHA-256, the `kid` on each token
+    pub id: String,
+    pair: Ed25519KeyPair,
+}
+
+impl SigningKey{
+    pub fn from_seed(seed: &[u8; 32]) -> SigningKey {
+        let pair = Ed25519KeyPair::from_seed_unchecked(seed).expect("any 32 bytes are a seed");
+        SigningKey { id: hex(&Sha256::digest(pair.public_key().as_ref()))[..8].to_string(), pair }
+    }
+
+    // the public half, as GET /.well-known/jwks.json shows it
+    pub fn jwk(&self) -> Value {
+        json!({ "kty": "OKP", "crv": "Ed25519", "use": "sig", "alg": "EdDSA", "kid": self.id, "x": URL_SAFE_NO_PAD.encode(self.pair.public_key()) })
+    }
+}
+
+impl Default for Auth{
+    // a random key, so sessions end when the server restarts
+    fn default() -> Self {
+        Auth {
+            keys: vec![SigningKey::from_seed(&rand::random())],
+            lifetime: DEFAULT_LIFETIME,
+            trust_header: true,
+        }
+    }
+}
+
+// 32 byte seeds as 64 hex characters, e.g. from `openssl rand -hex 32`
+// one per line or separated by commas, newest first, # comments and blank lines are skipped
+pub fn parse_keys(text: &str) -> Result<Vec<SigningKey>, String> {
+    let keys = text.lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .flat_map(|line| line.split(','))
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .map(|key| {
+            let bytes: Option<Vec<u8>> = (0..key.len()).step_by(2)
+                .map(|i| key.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+                .collect();
+            bytes.and_then(|b| b.try_into().ok())
+                .map(|seed| SigningKey::from_seed(&seed))
+                .ok_or_else(|| "a session key is 64 hex characters, `openssl rand -hex 32` makes one".to_string())
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if keys.is_empty() {
+        return Err("there are no keys in it".to_string());
+    }
+    Ok(keys)
+}
+
+fn encode(value: &Value) -> String {
+    URL_SAFE_NO_PAD.encode(value.to_string())
+}
+
+impl Auth{
+    // SESSION_KEYS keeps sessions working across restarts, SESSION_LIFETIME is in seconds
+    // TRUST_TRAINER_HEADER=false makes the session the only way to say who's asking
+    pub fn from_env() -> Result<Auth, String> {
+        let mut auth = Auth::default();
+        if let Ok(keys) = crate::secrets::var("SESSION_KEYS") {
+            auth.keys = parse_keys(&keys).map_err(|e| format!("SESSION_KEYS: {}", e))?;
+        }
+        if let Ok(secs) = std::env::var("SESSION_LIFETIME") {
+            auth.lifetime = Duration::from_secs(secs.parse().map_err(|_| format!("SESSION_LIFETIME should be a number of seconds, not {:?}", secs))?);
+        }
+        if let Ok(trust) = std::env::var("TRUST_TRAINER_HEADER") {
+            auth.trust_header = match trust.as_str() {
+                "true" | "1" => true,
+                "false" | "0" => false,
+                _ => return Err(format!("TRUST_TRAINER_HEADER should be true or false, not {:?}", trust)),
+            };
+        }
+        Ok(auth)
+    }
+
+    pub fn keys(mut self, keys: Vec<SigningKey>) -> Auth {
+        assert!(!keys.is_empty(), "sessions need a key to be signed with");
+        self.keys = keys;
+        self
+    }
+
+    pub fn lifetime(mut self, lifetime: Duration) -> Auth {
+        self.lifetime = lifetime;
+        self
+    }
+
+    pub fn trust_header(mut self, trust: bool) -> Auth {
+        self.trust_header = trust;
+        self
+    }
+
+    pub fn trusts_header(&self) -> bool {
+        self.trust_header
+    }
+
+    // every key that's still checked, for anyone who'd rather check a session themselves
+    pub fn jwks(&self) -> Value {
+        json!({ "keys": self.keys.iter().map(SigningKey::jwk).collect::<Vec<_>>() })
+    }
+
+    pub fn issue(&self, trainer_id: u32, now: u64) -> Session {
+        let key = &self.keys[0];
+        let expires_at = now + self.lifetime.as_secs();
+        let signed = format!(
+            "{}.{}",
+            encode(&json!({ "alg": "EdDSA", "typ": "JWT", "kid": key.id })),
+            encode(&json!({ "sub": trainer_id.to_string(), "iat": now, "exp": expires_at })),
+        );
+        let signature = URL_SAFE_NO_PAD.encode(key.pair.sign(signed.as_bytes()));
+        Session { token: format!("{}.{}", signed, signature), trainer_id, expires_at }
+    }
+
+    // the trainer it's for, if one of the keys signed it and it hasn't expired
+    pub fn verify(&self, token: &str, now: u64) -> Result<u32, String> {
+        let invalid = || "that session isn't valid, log in again".to_string();
+        let decode = |part: &str| URL_SAFE_NO_PAD.decode(part).ok();
+        let (signed, signature) = token.rsplit_once('.').ok_or_else(invalid)?;
+        let (header, payload) = signed.split_once('.').ok_or_else(invalid)?;
+        let header: Value = decode(header).and_then(|h| serde_json::from_slice(&h).ok()).ok_or_else(invalid)?;
+        if header["alg"] != "EdDSA" {
+            return Err(invalid());
+        }
+        // a key taken out of SESSION_KEYS ends the sessions it signed
+        let key = self.keys.iter().find(|k| header["kid"] == k.id.as_str()).ok_or_else(invalid)?;
+        let signature = decode(signature).ok_or_else(invalid)?;
+        UnparsedPublicKey::new(&ED25519, key.pair.public_key().as_ref())
+            .verify(signed.as_bytes(), &signature)
+            .map_err(|_| invalid())?;
+
+        let claims: Value = decode(payload).and_then(|c| serde_json::from_slice(&c).ok()).ok_or_else(invalid)?;
+        if claims["exp"].as_u64().is_none_or(|exp| exp <= now) {
+            return Err("that session has expired, log in again".to_string());
+        }
+        claims["sub"].as_str().and_then(|s| s.parse().ok()).ok_or_else(invalid)
+    }
+}
@@ -0,0 +1,230 @@
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::io::AsyncReadExt;
+
+use crate::admin;
+use crate::encryption::{self, Keys};
+use crate::notify::Notice;
+use crate::s3::S3Sink;
+use crate::state::SharedState;
+
+// a dump from GET /admin/export, saved as <dir>/snapshot-<unix seconds>.json
+// or snapshot-<unix seconds>.json.enc when there's a key to encrypt it with
+#[derive(Debug, Clone, Serialize)]
+pub struct Snapshot{
+    pub file: String,
+    pub taken_at: u64,
+    pub size: u64,
+    // the ID of the key it's encrypted with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_id: Option<String>,
+    // where the copy went when S3 is set up, or why it didn't get there
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uploaded_to: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upload_error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Backups{
+    dir: PathBuf,
+    // zero turns the background snapshots off, POST /admin/snapshots still works
+    interval: Duration,
+    // the newest this many are kept, older ones are deleted
+    keep: usize,
+    // every snapshot is copied here too, retention there is up to the bucket's own lifecycle rules
+    s3: Option<S3Sink>,
+    // encrypts them on disk and in S3 alike, None leaves them as plain JSON
+    keys: Option<Keys>,
+}
+
+impl Default for Backups{
+    fn default() -> Self {
+        Backups { dir: PathBuf::from("snapshots"), interval: Duration::from_secs(60 * 60), keep: 24, s3: None, keys: None }
+    }
+}
+
+// what POST /admin/snapshots/rekey did
+#[derive(Debug, Clone, Serialize)]
+pub struct Rekey{
+    pub key_id: String,
+    // plain ones and ones encrypted with an older key, now encrypted with key_id
+    pub rekeyed: Vec<String>,
+    pub unchanged: usize,
+    // e.g. "snapshot-1791953720.json.enc: it's encrypted with key 1a2b3c4d, which isn't one of the keys"
+    pub failed: Vec<String>,
+}
+
+impl Backups{
+    // the default schedule, into `dir`
+    pub fn in_dir(dir: impl Into<PathBuf>) -> Backups {
+        Backups { dir: dir.into(), ..Backups::default() }
+    }
+
+    // encrypted from now on, snapshots already on disk stay as they are until they're rekeyed
+    pub fn encrypted(mut self, keys: Keys) -> Backups {
+        self.keys = Some(keys);
+        self
+    }
+
+    // SNAPSHOT_DIR, SNAPSHOT_INTERVAL_SECS and SNAPSHOT_KEEP, hourly into ./snapshots keeping a day's worth otherwise
+    // and SNAPSHOT_KEYS or SNAPSHOT_KEY_FILE to encrypt them
+    pub fn from_env() -> Result<Backups, String> {
+        let mut backups = Backups::default();
+        if let Ok(dir) = std::env::var("SNAPSHOT_DIR") {
+            backups.dir = PathBuf::from(dir);
+        }
+        if let Some(secs) = std::env::var("SNAPSHOT_INTERVAL_SECS").ok().and_then(|s| s.parse().ok()) {
+            backups.interval = Duration::from_secs(secs);
+        }
+        if let Some(keep) = std::env::var("SNAPSHOT_KEEP").ok().and_then(|s| s.parse().ok()) {
+            // always at least the one just taken
+            backups.keep = usize::max(keep, 1);
+        }
+        backups.s3 = S3Sink::from_env();
+        backups.keys = Keys::from_env()?;
+        Ok(backups)
+    }
+
+    fn snapshot_at(file: &str) -> Option<u64> {
+        let file = file.strip_suffix(".enc").unwrap_or(file);
+        file.strip_prefix("snapshot-")?.strip_suffix(".json")?.parse().ok()
+    }
+
+    async fn keyring(&self) -> io::Result<Option<encryption::Keyring>> {
+        let Some(keys) = &self.keys else { return Ok(None) };
+        keys.load().await.map(Some).map_err(io::Error::other)
+    }
+
+    // written next to it first, so a crash never leaves half a snapshot under the real name
+    async fn write(&self, file: &str, bytes: &[u8]) -> io::Result<()> {
+        let partial = self.dir.join(format!("{}.partial", file));
+        tokio::fs::write(&partial, bytes).await?;
+        tokio::fs::rename(&partial, self.dir.join(file)).await
+    }
+
+    // just enough of it to tell which key it needs
+    async fn key_id(&self, file: &str) -> io::Result<Option<String>> {
+        if !file.ends_with(".enc") {
+            return Ok(None);
+        }
+        let mut header = Vec::with_capacity(encryption::HEADER_LEN);
+        tokio::fs::File::open(self.dir.join(file)).await?.take(encryption::HEADER_LEN as u64).read_to_end(&mut header).await?;
+        Ok(encryption::key_id(&header))
+    }
+
+    // how often the scheduler takes one, zero when SNAPSHOT_INTERVAL_SECS turned it off
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    // writes a fresh snapshot, then deletes the ones past `keep`
+    // an S3 upload that fails doesn't fail the snapshot, it's still on disk
+    pub async fn take(&self, state: &SharedState) -> io::Result<Snapshot> {
+        let dump = admin::snapshot(state);
+        let json = serde_json::to_vec(&dump)?;
+        let (file, bytes, key_id) = match self.keyring().await? {
+            Some(keyring) => (format!("snapshot-{}.json.enc", dump.exported_at), keyring.encrypt(&json), Some(keyring.current().id.clone())),
+            None => (format!("snapshot-{}.json", dump.exported_at), json, None),
+        };
+        tokio::fs::create_dir_all(&self.dir).await?;
+        self.write(&file, &bytes).await?;
+        self.prune().await?;
+
+        let mut snapshot = Snapshot { file, taken_at: dump.exported_at, size: bytes.len() as u64, key_id, uploaded_to: None, upload_error: None };
+        if let Some(s3) = &self.s3 {
+            match s3.upload(&snapshot.file, bytes).await {
+                Ok(location) => snapshot.uploaded_to = Some(location),
+                Err(e) => snapshot.upload_error = Some(e),
+            }
+        }
+        Ok(snapshot)
+    }
+
+    // newest first
+    pub async fn list(&self) -> io::Result<Vec<Snapshot>> {
+        let mut snapshots = Vec::new();
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(snapshots),
+            Err(e) => return Err(e),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let file = entry.file_name().to_string_lossy().into_owned();
+            if let Some(taken_at) = Backups::snapshot_at(&file) {
+                let size = entry.metadata().await?.len();
+                let key_id = self.key_id(&file).await?;
+                snapshots.push(Snapshot { file, taken_at, size, key_id, uploaded_to: None, upload_error: None });
+            }
+        }
+        snapshots.sort_by_key(|s| std::cmp::Reverse(s.taken_at));
+        Ok(snapshots)
+    }
+
+    // as plain JSON whether or not it's encrypted on disk, ready for POST /admin/import
+    pub async fn read(&self, file: &str) -> io::Result<Vec<u8>> {
+        // only names list() would give, never a path out of the directory
+        if Backups::snapshot_at(file).is_none() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("{} isn't a snapshot", file)));
+        }
+        let bytes = tokio::fs::read(self.dir.join(file)).await?;
+        if !file.ends_with(".enc") {
+            return Ok(bytes);
+        }
+        let keyring = self.keyring().await?
+            .ok_or_else(|| io::Error::other("it's encrypted and there's no SNAPSHOT_KEYS or SNAPSHOT_KEY_FILE to decrypt it with"))?;
+        keyring.decrypt(&bytes).map_err(io::Error::other)
+    }
+
+    // encrypts every snapshot on disk with the current key, after a rotation or once encryption's been turned on
+    // copies already in S3 are left as they were
+    pub async fn rekey(&self) -> Result<Rekey, String> {
+        let keyring = self.keyring().await.map_err(|e| e.to_string())?
+            .ok_or("snapshots aren't encrypted, set SNAPSHOT_KEYS or SNAPSHOT_KEY_FILE first")?;
+        let key_id = keyring.current().id.clone();
+        let mut rekey = Rekey { key_id: key_id.clone(), rekeyed: Vec::new(), unchanged: 0, failed: Vec::new() };
+        for snapshot in self.list().await.map_err(|e| format!("couldn't list the snapshots: {}", e))? {
+            if snapshot.key_id.as_ref() == Some(&key_id) {
+                rekey.unchanged += 1;
+                continue;
+            }
+            let file = format!("snapshot-{}.json.enc", snapshot.taken_at);
+            let rewritten = async {
+                let json = self.read(&snapshot.file).await?;
+                self.write(&file, &keyring.encrypt(&json)).await?;
+                // a plain one is replaced by its encrypted copy
+                if snapshot.file != file {
+                    tokio::fs::remove_file(self.dir.join(&snapshot.file)).await?;
+                }
+                io::Result::Ok(())
+            };
+            match rewritten.await {
+                Ok(()) => rekey.rekeyed.push(file),
+                Err(e) => rekey.failed.push(format!("{}: {}", snapshot.file, e)),
+            }
+        }
+        Ok(rekey)
+    }
+
+    async fn prune(&self) -> io::Result<()> {
+        for old in self.list().await?.into_iter().skip(self.keep) {
+            tokio::fs::remove_file(self.dir.join(old.file)).await?;
+        }
+        Ok(())
+    }
+}
+
+// a scheduled snapshot, a failure is printed and posted to the webhook as well
+pub async fn scheduled_snapshot(state: &SharedState) -> Result<(), String> {
+    let error = match state.backups.take(state).await {
+        Ok(Snapshot { file, upload_error: Some(e), .. }) => format!("couldn't upload {} to S3: {}", file, e),
+        Ok(_) => return Ok(()),
+        Err(e) => format!("couldn't take a snapshot: {}", e),
+    };
+    eprintln!("{}", error);
+    state.notifier.notify(Notice::BackupFailed { error: error.clone() });
+    Err(error)
+}
@@ -0,0 +1,618 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::models::Pokemon;
+use crate::moves::{self, Category, MoveData, MoveEffect, Stat, STRUGGLE};
+use crate::scripting::Scripts;
+use crate::status::{self, StatusCondition};
+use crate::types::{self, PokeType};
+
+pub use pokemon_models::battle::*;
+
+// battles that drag on this long are called a draw
+pub const MAX_TURNS: u32 = 200;
+
+// stat stages go from -6 to +6
+const MAX_STAGE: i8 = 6;
+
+const CRIT_CHANCE: f64 = 1.0 / 24.0;
+
+// the AI picks a random move this often instead of the best one
+const RANDOM_MOVE_CHANCE: f64 = 0.1;
+
+// a Pokemon plus everything that only matters for the length of a battle
+struct Combatant{
+    pokemon: Pokemon,
+    types: Vec<PokeType>,
+    // indexed by Stat
+    stages: [i8; 6],
+}
+
+impl Combatant{
+    fn new(pokemon: Pokemon) -> Self {
+        // made-up types fight as Normal
+        let types = pokemon.types().unwrap_or_else(|_| vec![PokeType::Normal]);
+        Combatant { pokemon, types, stages: [0; 6] }
+    }
+
+    fn name(&self) -> &str {
+        &self.pokemon.name
+    }
+
+    fn stat(&self, stat: Stat) -> f64 {
+        let stats = &self.pokemon.stats;
+        let value = match stat {
+            Stat::Attack => stats.attack,
+            Stat::Defense => stats.defense,
+            Stat::SpAttack => stats.sp_attack,
+            Stat::SpDefense => stats.sp_defense,
+            Stat::Speed => stats.speed,
+            Stat::Accuracy => 1,
+        };
+        value as f64 * stage_multiplier(self.stages[stat as usize])
+    }
+
+    // paralysis halves speed
+    fn speed(&self) -> f64 {
+        let speed = self.stat(Stat::Speed);
+        if self.pokemon.status == Some(StatusCondition::Paralysis) { speed / 2.0 } else { speed }
+    }
+
+    // known moves we have data for, Struggle if there are none
+    fn usable_moves(&self) -> Vec<&'static MoveData> {
+        let known: Vec<_> = self.pokemon.moves.iter().filter_map(|m| moves::find(m)).collect();
+        if known.is_empty() { vec![&STRUGGLE] } else { known }
+    }
+
+    fn result(&self) -> CombatantResult {
+        CombatantResult {
+            id: self.pokemon.id,
+            name: self.pokemon.name.clone(),
+            remaining_hp: self.pokemon.current_hp,
+            max_hp: self.pokemon.max_hp,
+            fainted: self.pokemon.fainted,
+        }
+    }
+}
+
+struct Team{
+    members: Vec<Combatant>,
+    active: usize,
+}
+
+impl Team{
+    fn new(members: Vec<Pokemon>) -> Self {
+        Team { members: members.into_iter().map(Combatant::new).collect(), active: 0 }
+    }
+
+    fn active(&self) -> &Combatant {
+        &self.members[self.active]
+    }
+
+    fn active_mut(&mut self) -> &mut Combatant {
+        &mut self.members[self.active]
+    }
+
+    fn next_able(&self) -> Option<usize> {
+        self.members.iter().position(|c| !c.pokemon.fainted)
+    }
+}
+
+// +1 is 1.5x, -1 is 2/3x and so on; accuracy uses thirds instead of halves
+fn stage_multiplier(stage: i8) -> f64 {
+    let stage = stage as f64;
+    if stage >= 0.0 { (2.0 + stage) / 2.0 } else { 2.0 / (2.0 - stage) }
+}
+
+fn accuracy_multiplier(stage: i8) -> f64 {
+    let stage = stage as f64;
+    if stage >= 0.0 { (3.0 + stage) / 3.0 } else { 3.0 / (3.0 - stage) }
+}
+
+fn stat_label(stat: Stat) -> &'static str {
+    match stat {
+        Stat::Attack => "Attack",
+        Stat::Defense => "Defense",
+        Stat::SpAttack => "Sp. Atk",
+        Stat::SpDefense => "Sp. Def",
+        Stat::Speed => "Speed",
+        Stat::Accuracy => "accuracy",
+    }
+}
+
+fn roll(rng: &mut impl Rng, percent: u8) -> bool {
+    rng.random_range(0..100) < percent as u32
+}
+
+// a battle in progress, driven one turn at a time
+// the Pokemon passed in are copies so nothing is saved; fainted ones sit out
+pub struct Battle{
+    teams: [Team; 2],
+    turn: u32,
+    log: Vec<TurnLog>,
+    // the operator's damage and after_battle_turn hooks, if any
+    scripts: Option<Arc<Scripts>>,
+}
+
+impl Battle{
+    pub fn new(side_a: Vec<Pokemon>, side_b: Vec<Pokemon>) -> Battle {
+        let mut teams = [Team::new(side_a), Team::new(side_b)];
+
+        let mut opening = Vec::new();
+        for (side, team) in SIDES.iter().zip(teams.iter_mut()) {
+            if let Some(first) = team.next_able() {
+                team.active = first;
+                opening.push(format!("{} sends out {}!", side.label(), team.active().name()));
+            }
+        }
+        Battle { teams, turn: 0, log: vec![TurnLog { turn: 0, events: opening }], scripts: None }
+    }
+
+    pub fn with_scripts(mut self, scripts: Arc<Scripts>) -> Battle {
+        self.scripts = Some(scripts);
+        self
+    }
+
+    // turn 0: who got sent out first
+    pub fn opening(&self) -> &TurnLog {
+        &self.log[0]
+    }
+
+    // Some once the battle is over, holding the winner (None for a draw)
+    // a team with nobody able to battle loses straight away
+    pub fn result(&self) -> Option<Option<Side>> {
+        let able: Vec<bool> = self.teams.iter().map(|t| t.next_able().is_some()).collect();
+        match (able[0], able[1]) {
+            (true, false) => Some(Some(Side::SideA)),
+            (false, true) => Some(Some(Side::SideB)),
+            (false, false) => Some(None),
+            _ if self.turn >= MAX_TURNS => Some(None),
+            _ => None,
+        }
+    }
+
+    pub fn active_name(&self, side: Side) -> &str {
+        self.teams[side.index()].active().name()
+    }
+
+    // the active Pokemon as it stands right now, HP and status included
+    pub fn active_pokemon(&self, side: Side) -> &Pokemon {
+        &self.teams[side.index()].active().pokemon
+    }
+
+    pub fn active_moves(&self, side: Side) -> Vec<&'static MoveData> {
+        self.teams[side.index()].active().usable_moves()
+    }
+
+    // one of the active Pokemon's usable moves by name
+    pub fn find_move(&self, side: Side, move_name: &str) -> Option<&'static MoveData> {
+        self.active_moves(side).into_iter().find(|m| m.name.eq_ignore_ascii_case(move_name))
+    }
+
+    pub fn ai_move(&self, side: Side, rng: &mut impl Rng) -> &'static MoveData {
+        let i = side.index();
+        choose_move(self.teams[i].active(), self.teams[1 - i].active(), rng)
+    }
+
+    pub fn play_turn(&mut self, choices: [&'static MoveData; 2], rng: &mut impl Rng) -> &TurnLog {
+        self.turn += 1;
+        let mut events = Vec::new();
+
+        for attacker in turn_order(&self.teams, &choices, rng) {
+            let (user, target) = actives(&mut self.teams, attacker);
+            // whoever fainted earlier in the turn doesn't get to act (or be hit)
+            if user.pokemon.fainted || target.pokemon.fainted {
+                continue;
+            }
+            take_action(user, target, choices[attacker], self.scripts.as_deref(), rng, &mut events);
+        }
+
+        for team in self.teams.iter_mut() {
+            let active = team.active_mut();
+            if let Some(message) = status::end_of_turn(&mut active.pokemon, true) {
+                events.push(message);
+                if active.pokemon.fainted {
+                    events.push(format!("{} fainted!", active.name()));
+                }
+            }
+        }
+
+        // fainted Pokemon get replaced by the next one in line
+        for (side, team) in SIDES.iter().zip(self.teams.iter_mut()) {
+            if team.active().pokemon.fainted {
+                if let Some(next) = team.next_able() {
+                    team.active = next;
+                    events.push(format!("{} sends out {}!", side.label(), team.active().name()));
+                }
+            }
+        }
+
+        if let Some(scripts) = &self.scripts {
+            let actives = [&self.teams[0].active().pokemon, &self.teams[1].active().pokemon];
+            let extra = scripts.after_battle_turn(self.turn, &events, actives);
+            events.extend(extra);
+        }
+
+        self.log.push(TurnLog { turn: self.turn, events });
+        self.log.last().unwrap()
+    }
+
+    // note why the battle ended early (forfeit, timeout...) in the log
+    pub fn note(&mut self, message: String) -> &TurnLog {
+        self.log.push(TurnLog { turn: self.turn, events: vec![message] });
+        self.log.last().unwrap()
+    }
+
+    pub fn into_outcome(self, winner: Option<Side>) -> BattleOutcome {
+        let [a, b] = self.teams;
+        BattleOutcome {
+            winner,
+            turns: self.turn,
+            side_a: a.members.iter().map(Combatant::result).collect(),
+            side_b: b.members.iter().map(Combatant::result).collect(),
+            log: self.log,
+        }
+    }
+}
+
+// run a whole battle with the AI picking moves for both sides
+pub fn run(mut battle: Battle, rng: &mut impl Rng) -> BattleOutcome {
+    loop {
+        if let Some(winner) = battle.result() {
+            return battle.into_outcome(winner);
+        }
+        let choices = [battle.ai_move(Side::SideA, rng), battle.ai_move(Side::SideB, rng)];
+        battle.play_turn(choices, rng);
+    }
+}
+
+// (acting side's active, other side's active)
+fn actives(teams: &mut [Team; 2], attacker: usize) -> (&mut Combatant, &mut Combatant) {
+    let [a, b] = teams;
+    if attacker == 0 { (a.active_mut(), b.active_mut()) } else { (b.active_mut(), a.active_mut()) }
+}
+
+// higher priority first, then the faster Pokemon, speed ties are a coin flip
+fn turn_order(teams: &[Team; 2], choices: &[&MoveData; 2], rng: &mut impl Rng) -> [usize; 2] {
+    let a_first = match choices[0].priority.cmp(&choices[1].priority) {
+        std::cmp::Ordering::Greater => true,
+        std::cmp::Ordering::Less => false,
+        std::cmp::Ordering::Equal => {
+            let (speed_a, speed_b) = (teams[0].active().speed(), teams[1].active().speed());
+            if speed_a == speed_b { rng.random_bool(0.5) } else { speed_a > speed_b }
+        }
+    };
+    if a_first { [0, 1] } else { [1, 0] }
+}
+
+// simple AI: go for the move that looks best against the current target
+fn choose_move(user: &Combatant, target: &Combatant, rng: &mut impl Rng) -> &'static MoveData {
+    let options = user.usable_moves();
+    if rng.random_bool(RANDOM_MOVE_CHANCE) {
+        return options[rng.random_range(0..options.len())];
+    }
+    options.into_iter()
+        .max_by(|a, b| score_move(a, user, target).total_cmp(&score_move(b, user, target)))
+        .unwrap_or(&STRUGGLE)
+}
+
+fn score_move(mv: &MoveData, user: &Combatant, target: &Combatant) -> f64 {
+    let accuracy = mv.accuracy.map_or(1.0, |a| a as f64 / 100.0);
+    let score = match (mv.category, mv.effect) {
+        (Category::Status, MoveEffect::Inflict(condition, _)) => {
+            if target.pokemon.status.is_none() && !condition.is_immune(&target.pokemon) { 70.0 } else { 0.0 }
+        }
+        (Category::Status, MoveEffect::Heal | MoveEffect::Rest) => {
+            if user.pokemon.current_hp * 2 < user.pokemon.max_hp { 80.0 } else { 0.0 }
+        }
+        (Category::Status, MoveEffect::Boost(changes)) => {
+            if changes.iter().all(|(stat, _)| user.stages[*stat as usize] < 2) { 30.0 } else { 0.0 }
+        }
+        (Category::Status, MoveEffect::Lower(stat, _, _)) => {
+            if target.stages[stat as usize] > -2 { 20.0 } else { 0.0 }
+        }
+        (Category::Status, _) => 0.0,
+        (_, MoveEffect::LevelDamage) => user.pokemon.level as f64,
+        _ => {
+            let stab = if user.types.contains(&mv.poke_type) { 1.5 } else { 1.0 };
+            mv.power as f64 * stab * types::effectiveness_against(mv.poke_type, &target.types) as f64
+        }
+    };
+    score * accuracy
+}
+
+fn take_action(user: &mut Combatant, target: &mut Combatant, mv: &MoveData, scripts: Option<&Scripts>, rng: &mut impl Rng, events: &mut Vec<String>) {
+    match status::before_move(&mut user.pokemon, rng) {
+        Ok(Some(message)) => events.push(message),
+        Ok(None) => {}
+        Err(message) => {
+            events.push(message);
+            return;
+        }
+    }
+
+    events.push(format!("{} used {}!", user.name(), mv.name));
+
+    if let Some(accuracy) = mv.accuracy {
+        let chance = accuracy as f64 / 100.0 * accuracy_multiplier(user.stages[Stat::Accuracy as usize]);
+        if !rng.random_bool(chance.min(1.0)) {
+            events.push(format!("{}'s attack missed!", user.name()));
+            return;
+        }
+    }
+
+    if mv.category == Category::Status {
+        use_status_move(user, target, mv, rng, events);
+    } else {
+        use_damaging_move(user, target, mv, scripts, rng, events);
+    }
+}
+
+fn use_damaging_move(user: &mut Combatant, target: &mut Combatant, mv: &MoveData, scripts: Option<&Scripts>, rng: &mut impl Rng, events: &mut Vec<String>) {
+    let multiplier = types::effectiveness_against(mv.poke_type, &target.types) as f64;
+    if multiplier == 0.0 {
+        events.push(format!("It doesn't affect {}...", target.name()));
+        return;
+    }
+
+    let damage = if let MoveEffect::LevelDamage = mv.effect {
+        user.pokemon.level
+    } else {
+        calc_damage(user, target, mv, multiplier, rng, events)
+    };
+    let damage = match scripts {
+        Some(scripts) => scripts.damage(&user.pokemon, &target.pokemon, mv, multiplier, damage),
+        None => damage,
+    };
+    let dealt = target.pokemon.take_damage(damage);
+    events.push(format!("{} took {} damage ({}/{} HP)",
+        target.name(), dealt, target.pokemon.current_hp, target.pokemon.max_hp));
+
+    match mv.effect {
+        // secondary effects fail silently
+        MoveEffect::Inflict(condition, chance)
+            if !target.pokemon.fainted && roll(rng, chance) && status::apply(&mut target.pokemon, condition, rng).is_ok() => {
+            events.push(format!("{} is {}!", target.name(), condition.adjective()));
+        }
+        MoveEffect::Lower(stat, stages, chance) if !target.pokemon.fainted && roll(rng, chance) => {
+            change_stage(target, stat, -stages, events);
+        }
+        MoveEffect::Recoil(divisor) => {
+            let recoil = (dealt / divisor).max(1);
+            user.pokemon.take_damage(recoil);
+            events.push(format!("{} is damaged by recoil ({} HP)", user.name(), recoil));
+        }
+        MoveEffect::Drain => {
+            let restored = user.pokemon.restore_hp((dealt / 2).max(1));
+            if restored > 0 {
+                events.push(format!("{} drained {} HP", user.name(), restored));
+            }
+        }
+        _ => {}
+    }
+
+    if target.pokemon.fainted {
+        events.push(format!("{} fainted!", target.name()));
+    }
+    if user.pokemon.fainted {
+        events.push(format!("{} fainted!", user.name()));
+    }
+}
+
+// simplified main-series damage formula
+fn calc_damage(user: &Combatant, target: &Combatant, mv: &MoveData, multiplier: f64, rng: &mut impl Rng, events: &mut Vec<String>) -> u32 {
+    let (attack, defense) = match mv.category {
+        Category::Physical => (user.stat(Stat::Attack), target.stat(Stat::Defense)),
+        _ => (user.stat(Stat::SpAttack), target.stat(Stat::SpDefense)),
+    };
+    let level = user.pokemon.level as f64;
+    let mut damage = (2.0 * level / 5.0 + 2.0) * mv.power as f64 * attack / defense / 50.0 + 2.0;
+
+    if user.types.contains(&mv.poke_type) {
+        damage *= 1.5;
+    }
+    damage *= multiplier;
+    if rng.random_bool(CRIT_CHANCE) {
+        damage *= 1.5;
+        events.push("A critical hit!".to_string());
+    }
+    if multiplier > 1.0 {
+        events.push("It's super effective!".to_string());
+    } else if multiplier < 1.0 {
+        events.push("It's not very effective...".to_string());
+    }
+    // burned Pokemon hit for half with physical moves
+    if mv.category == Category::Physical && user.pokemon.status == Some(StatusCondition::Burn) {
+        damage *= 0.5;
+    }
+    damage *= rng.random_range(0.85..=1.0);
+
+    (damage as u32).max(1)
+}
+
+fn use_status_move(user: &mut Combatant, target: &mut Combatant, mv: &MoveData, rng: &mut impl Rng, events: &mut Vec<String>) {
+    match mv.effect {
+        MoveEffect::Inflict(condition, _) => {
+            // Ground types shrug off Thunder Wave
+            if mv.poke_type == PokeType::Electric && types::effectiveness_against(mv.poke_type, &target.types) == 0.0 {
+                events.push(format!("It doesn't affect {}...", target.name()));
+            } else if status::apply(&mut target.pokemon, condition, rng).is_ok() {
+                events.push(format!("{} is {}!", target.name(), condition.adjective()));
+            } else {
+                events.push("But it failed!".to_string());
+            }
+        }
+        MoveEffect::Boost(changes) => {
+            for (stat, stages) in changes {
+                change_stage(user, *stat, *stages, events);
+            }
+        }
+        MoveEffect::Lower(stat, stages, _) => change_stage(target, stat, -stages, events),
+        MoveEffect::Heal => {
+            let restored = user.pokemon.restore_hp(user.pokemon.max_hp / 2);
+            if restored == 0 {
+                events.push("But it failed!".to_string());
+            } else {
+                events.push(format!("{} restored {} HP", user.name(), restored));
+            }
+        }
+        MoveEffect::Rest => {
+            if user.pokemon.current_hp == user.pokemon.max_hp {
+                events.push("But it failed!".to_string());
+            } else {
+                user.pokemon.heal();
+                user.pokemon.status = Some(StatusCondition::Sleep);
+                user.pokemon.sleep_turns = Some(2);
+                events.push(format!("{} slept and became healthy!", user.name()));
+            }
+        }
+        _ => events.push("But nothing happened!".to_string()),
+    }
+}
+
+fn change_stage(combatant: &mut Combatant, stat: Stat, stages: i8, events: &mut Vec<String>) {
+    let current = combatant.stages[stat as usize];
+    let updated = (current + stages).clamp(-MAX_STAGE, MAX_STAGE);
+    let label = stat_label(stat);
+    let name = combatant.name().to_string();
+
+    if updated == current {
+        let direction = if stages > 0 { "higher" } else { "lower" };
+        events.push(format!("{}'s {} won't go any {}!", name, label, direction));
+        return;
+    }
+    combatant.stages[stat as usize] = updated;
+    let change = match stages {
+        s if s >= 2 => "sharply rose",
+        s if s > 0 => "rose",
+        s if s <= -2 => "harshly fell",
+        _ => "fell",
+    };
+    events.push(format!("{}'s {} {}!", name, label, change));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn pokemon(id: u32, name: &str, poke_type: &str, level: u32) -> Pokemon {
+        Pokemon::new(id, name.to_string(), poke_type.to_string(), None, level)
+    }
+
+    fn simulate(side_a: Vec<Pokemon>, side_b: Vec<Pokemon>, rng: &mut impl Rng) -> BattleOutcome {
+        run(Battle::new(side_a, side_b), rng)
+    }
+
+    fn all_events(outcome: &BattleOutcome) -> Vec<String> {
+        outcome.log.iter().flat_map(|t| t.events.clone()).collect()
+    }
+
+    #[test]
+    fn stage_multipliers_match_the_games() {
+        assert_eq!(stage_multiplier(0), 1.0);
+        assert_eq!(stage_multiplier(1), 1.5);
+        assert_eq!(stage_multiplier(6), 4.0);
+        assert_eq!(stage_multiplier(-2), 0.5);
+        assert_eq!(accuracy_multiplier(-3), 0.5);
+    }
+
+    #[test]
+    fn stronger_pokemon_wins() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let outcome = simulate(
+            vec![pokemon(1, "Mewtwo", "Psychic", 70)],
+            vec![pokemon(2, "Magikarp", "Water", 5)],
+            &mut rng,
+        );
+        assert_eq!(outcome.winner, Some(Side::SideA));
+        assert!(outcome.side_b[0].fainted);
+        assert!(!outcome.side_a[0].fainted);
+    }
+
+    #[test]
+    fn same_seed_same_battle() {
+        let run = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            simulate(
+                vec![pokemon(1, "Pikachu", "Electric", 30)],
+                vec![pokemon(2, "Squirtle", "Water", 30)],
+                &mut rng,
+            )
+        };
+        assert_eq!(all_events(&run(42)), all_events(&run(42)));
+    }
+
+    #[test]
+    fn faster_pokemon_moves_first() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let outcome = simulate(
+            vec![pokemon(1, "Geodude", "Rock", 20)],
+            vec![pokemon(2, "Jolteon", "Electric", 20)],
+            &mut rng,
+        );
+        let first = &outcome.log[1].events[0];
+        assert!(first.starts_with("Jolteon"), "unexpected first event: {}", first);
+    }
+
+    #[test]
+    fn fainted_pokemon_sit_out() {
+        let mut fainted = pokemon(2, "Rattata", "Normal", 10);
+        fainted.take_damage(fainted.max_hp);
+        let mut rng = StdRng::seed_from_u64(3);
+        let outcome = simulate(
+            vec![pokemon(1, "Pidgey", "Normal", 10)],
+            vec![fainted, pokemon(3, "Caterpie", "Bug", 10)],
+            &mut rng,
+        );
+        assert_eq!(outcome.log[0].events[1], "Side B sends out Caterpie!");
+        let events = all_events(&outcome);
+        assert!(!events.iter().any(|e| e.contains("Rattata")));
+    }
+
+    #[test]
+    fn team_with_nobody_able_loses_immediately() {
+        let mut fainted = pokemon(2, "Rattata", "Normal", 10);
+        fainted.take_damage(fainted.max_hp);
+        let mut rng = StdRng::seed_from_u64(3);
+        let outcome = simulate(vec![pokemon(1, "Pidgey", "Normal", 10)], vec![fainted], &mut rng);
+        assert_eq!(outcome.winner, Some(Side::SideA));
+        assert_eq!(outcome.turns, 0);
+    }
+
+    #[test]
+    fn splash_wars_end_in_a_draw() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let outcome = simulate(
+            vec![pokemon(1, "Magikarp", "Water", 5)],
+            vec![pokemon(2, "Magikarp", "Water", 5)],
+            &mut rng,
+        );
+        assert_eq!(outcome.winner, None);
+        assert_eq!(outcome.turns, MAX_TURNS);
+    }
+
+    #[test]
+    fn ground_types_ignore_electric_moves() {
+        let mut pikachu = pokemon(1, "Pikachu", "Electric", 30);
+        pikachu.moves = vec!["Thunderbolt".to_string()];
+        let mut rng = StdRng::seed_from_u64(9);
+        let geodude = Pokemon::new(2, "Geodude".to_string(), "Rock".to_string(), Some("Ground".to_string()), 30);
+        let outcome = simulate(vec![pikachu], vec![geodude], &mut rng);
+        let events = all_events(&outcome);
+        assert!(events.iter().any(|e| e == "It doesn't affect Geodude..."));
+        assert_eq!(outcome.winner, Some(Side::SideB));
+    }
+
+    #[test]
+    fn pokemon_without_moves_struggle() {
+        let mut a = pokemon(1, "Mew", "Psychic", 50);
+        a.moves.clear();
+        let mut rng = StdRng::seed_from_u64(11);
+        let outcome = simulate(vec![a], vec![pokemon(2, "Caterpie", "Bug", 5)], &mut rng);
+        let events = all_events(&outcome);
+        assert!(events.iter().any(|e| e == "Mew used Struggle!"));
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::encounters::{self, Ball};
+use crate::models::{CatchPokemon, EncounterQuery, Pokemon, RandomQuery};
+use crate::state::SharedState;
+use crate::viewer::Viewer;
+
+const DEFAULT_URL: &str = "https://api.telegram.org";
+
+// how long one getUpdates waits for a message before asking again
+const POLL_SECS: u64 = 30;
+
+// after Telegram couldn't be reached, so a network blip doesn't turn into a tight loop
+const RETRY_AFTER: Duration = Duration::from_secs(5);
+
+const HELP: &str = "\
+/team [trainer id] - a trainer's team, yours once you've picked one
+/random [type] - any one Pokemon, e.g. /random fire
+/wild [region] [area] - a wild Pokemon appears, kanto grass unless you say otherwise
+/catch <encounter id> [ball] - throw a poke_ball, great_ball, ultra_ball or master_ball at it
+/trainer <id> - who you are in this chat, to catch and to see your private Pokemon";
+
+#[derive(Debug)]
+pub struct Telegram{
+    client: reqwest::Client,
+    // https://api.telegram.org/bot<token>, every method goes after it
+    base_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Updates{
+    ok: bool,
+    #[serde(default)]
+    result: Vec<Update>,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Update{
+    update_id: i64,
+    #[serde(default)]
+    message: Option<Message>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Message{
+    chat: Chat,
+    // photos and stickers don't have any
+    #[serde(default)]
+    text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Chat{
+    id: i64,
+}
+
+impl Telegram{
+    pub fn new(token: &str, api_url: &str) -> Telegram {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(POLL_SECS + 10))
+            .build()
+            .expect("a client with only a timeout always builds");
+        Telegram { client, base_url: format!("{}/bot{}", api_url.trim_end_matches('/'), token) }
+    }
+
+    // only with TELEGRAM_BOT_TOKEN, TELEGRAM_API_URL points it somewhere else (e.g. a local Bot API server)
+    pub fn from_env() -> Option<Telegram> {
+        let token = crate::secrets::var("TELEGRAM_BOT_TOKEN").ok().filter(|t| !t.is_empty())?;
+        let api_url = std::env::var("TELEGRAM_API_URL").unwrap_or_else(|_| DEFAULT_URL.to_string());
+        Some(Telegram::new(&token, &api_url))
+    }
+
+    async fn updates(&self, offset: i64) -> Result<Vec<Update>, String> {
+        let url = format!("{}/getUpdates?offset={}&timeout={}", self.base_url, offset, POLL_SECS);
+        let updates: Updates = self.client.get(url).send().await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| e.without_url().to_string())?
+            .json().await
+            .map_err(|e| e.without_url().to_string())?;
+        if !updates.ok {
+            return Err(updates.description.unwrap_or_else(|| "getUpdates failed".to_string()));
+        }
+        Ok(updates.result)
+    }
+
+    async fn send(&self, chat_id: i64, text: &str) -> Result<(), reqwest::Error> {
+        self.client.post(format!("{}/sendMessage", self.base_url))
+            .json(&json!({ "chat_id": chat_id, "text": text }))
+            .send().await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+// long-polls for messages and answers each one, straight from the state rather than over HTTP
+pub fn spawn(state: SharedState, telegram: Telegram) {
+    tokio::spawn(async move {
+        // chat -> the trainer it picked with /trainer, forgotten on restart
+        let mut trainers: HashMap<i64, u32> = HashMap::new();
+        let mut offset = 0;
+        loop {
+            let updates = match telegram.updates(offset).await {
+                Ok(updates) => updates,
+                Err(e) => {
+                    eprintln!("telegram: couldn't get updates: {}", e);
+                    tokio::time::sleep(RETRY_AFTER).await;
+                    continue;
+                }
+            };
+            for update in updates {
+                // acknowledged by asking for what comes after it, even if answering fails
+                offset = offset.max(update.update_id + 1);
+                let Some(Message { chat, text: Some(text) }) = update.message else { continue };
+                let Some(reply) = respond(&state, trainers.entry(chat.id).or_default(), &text) else { continue };
+                if let Err(e) = telegram.send(chat.id, &reply).await {
+                    eprintln!("telegram: couldn't answer chat {}: {}", chat.id, e.without_url());
+                }
+            }
+        }
+    });
+}
+
+// the answer to one message, None for anything that isn't a command
+// trainer is who the chat said it is, 0 until it says
+pub fn respond(state: &SharedState, trainer: &mut u32, text: &str) -> Option<String> {
+    let mut words = text.split_whitespace();
+    let command = words.next()?.strip_prefix('/')?;
+    // "/team@SomeBot" in group chats
+    let command = command.split('@').next().unwrap_or_default();
+    let args: Vec<&str> = words.collect();
+    let viewer = Viewer(Some(*trainer).filter(|id| *id != 0));
+    Some(match (command, args.as_slice()) {
+        ("start" | "help", _) => HELP.to_string(),
+        ("trainer", [id]) => match id.parse::<u32>().ok().and_then(|id| find_trainer(state, id)) {
+            Some((id, name)) => {
+                *trainer = id;
+                format!("you're {} now", name)
+            }
+            None => format!("there's no trainer {}", id),
+        },
+        ("team", []) if viewer.0.is_none() => "whose? /team <trainer id>, or pick yours with /trainer <id>".to_string(),
+        ("team", []) => team(state, viewer, *trainer),
+        ("team", [id]) => match id.parse() {
+            Ok(id) => team(state, viewer, id),
+            Err(_) => format!("{} isn't a trainer ID", id),
+        },
+        ("random", args) => {
+            let query = RandomQuery { poke_type: args.first().map(|t| t.to_string()), min_level: None, max_level: None };
+            match state.random_pokemon(viewer, &query) {
+                Some(pokemon) => line(&pokemon),
+                None => "no pokemon match".to_string(),
+            }
+        }
+        ("wild", args) => {
+            let query = EncounterQuery { region: args.first().map(|r| r.to_string()), area: args.get(1).map(|a| a.to_string()) };
+            match encounters::appear(state, query) {
+                Ok(encounter) => format!(
+                    "a wild {} (lv {}) appeared in {} {}! /catch {}",
+                    encounter.pokemon.name, encounter.pokemon.level, encounter.region, encounter.area, encounter.id,
+                ),
+                Err(e) => e.message,
+            }
+        }
+        ("catch", [..]) if viewer.0.is_none() => "pick your trainer first with /trainer <id>".to_string(),
+        ("catch", [id, rest @ ..]) if rest.len() <= 1 => {
+            let Ok(id) = id.parse() else { return Some(format!("{} isn't an encounter ID", id)) };
+            let ball = match rest.first() {
+                Some(ball) => match serde_json::from_value::<Ball>(json!(ball)) {
+                    Ok(ball) => ball,
+                    Err(_) => return Some(format!("{} isn't a ball, try poke_ball, great_ball, ultra_ball or master_ball", ball)),
+                },
+                None => Ball::default(),
+            };
+            match encounters::catch(state, id, &CatchPokemon { trainer_id: *trainer, ball }) {
+                Ok(result) if result.caught => format!("gotcha! {} was caught", line(&result.encounter.pokemon)),
+                Ok(result) if result.fled => format!("the wild {} broke free and fled", result.encounter.pokemon.name),
+                Ok(result) => format!("the wild {} broke free after {} shakes, try again", result.encounter.pokemon.name, result.shakes),
+                Err(e) => e.message,
+            }
+        }
+        _ => format!("don't know /{} like that\n\n{}", command, HELP),
+    })
+}
+
+fn find_trainer(state: &SharedState, id: u32) -> Option<(u32, String)> {
+    let trainers = state.trainers.lock().unwrap();
+    trainers.iter().find(|t| t.id == id).map(|t| (t.id, t.name.clone()))
+}
+
+// leaves out what the chat's trainer couldn't open over HTTP either
+fn team(state: &SharedState, viewer: Viewer, trainer_id: u32) -> String {
+    let Some((name, ids)) = state.trainers.lock().unwrap().iter()
+        .find(|t| t.id == trainer_id)
+        .map(|t| (t.name.clone(), t.team.clone()))
+    else {
+        return format!("there's no trainer {}", trainer_id);
+    };
+    let lines: Vec<String> = ids.iter().filter_map(|id| state.visible_pokemon(viewer, *id)).map(|p| line(&p)).collect();
+    if lines.is_empty() {
+        return format!("{}'s team is empty", name);
+    }
+    format!("{}'s team:\n{}", name, lines.join("\n"))
+}
+
+// #25 Pikachu lv 12 electric, 30/30 HP
+fn line(pokemon: &Pokemon) -> String {
+    let types = match &pokemon.secondary_type {
+        Some(secondary) => format!("{}/{}", pokemon.poke_type, secondary),
+        None => pokemon.poke_type.clone(),
+    };
+    format!("#{} {} lv {} {}, {}/{} HP", pokemon.id, pokemon.name, pokemon.level, types, pokemon.current_hp, pokemon.max_hp)
+}
@@ -0,0 +1,125 @@
+use std::time::Duration;
+
+use rand::{seq::SliceRandom, Rng};
+use serde::Serialize;
+
+use crate::achievements::{self, Event};
+use crate::friendship;
+use crate::models::{Capture, Pokemon, Stats};
+use crate::species::{self, EggGroup};
+use crate::state::SharedState;
+
+// steps of walking before an egg hatches
+pub const EGG_STEPS: u32 = 2560;
+
+// eggs get some steps in on their own, like carrying them around
+const STEPS_PER_TICK: u32 = 256;
+pub const TICK_INTERVAL: Duration = Duration::from_secs(30);
+
+// how many IVs come from the parents, the rest are rolled fresh
+const INHERITED_IVS: usize = 3;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Egg{
+    pub id: u32,
+    // what will hatch: the first stage of parent_a's line
+    pub species: String,
+    pub parents: [u32; 2],
+    // the egg goes to whoever owns parent_a, if anyone
+    pub trainer_id: Option<u32>,
+    pub steps_remaining: u32,
+    pub ivs: Stats,
+    pub hatched_into: Option<u32>,
+}
+
+// Err says why the two can't breed
+pub fn check_compatible(a: &Pokemon, b: &Pokemon) -> Result<(), String> {
+    let groups = |p: &Pokemon| species::find_or_unknown(&p.species).egg_groups;
+    for p in [a, b] {
+        if groups(p).contains(&EggGroup::Undiscovered) {
+            return Err(format!("{} can't breed", p.name));
+        }
+    }
+    if !groups(a).iter().any(|g| groups(b).contains(g)) {
+        return Err(format!("{} and {} don't share an egg group", a.name, b.name));
+    }
+    Ok(())
+}
+
+// a few stats are passed down from a random parent each, the rest are new
+fn inherit_ivs(a: &Stats, b: &Stats, rng: &mut impl Rng) -> Stats {
+    let parents = [a.as_array(), b.as_array()];
+    let mut ivs = species::random_ivs(rng).as_array();
+    let mut stats: Vec<usize> = (0..ivs.len()).collect();
+    stats.shuffle(rng);
+    for stat in stats.into_iter().take(INHERITED_IVS) {
+        ivs[stat] = parents[rng.random_range(0..2)][stat];
+    }
+    Stats::from_array(ivs)
+}
+
+pub fn lay(id: u32, a: &Pokemon, b: &Pokemon, trainer_id: Option<u32>, rng: &mut impl Rng) -> Egg {
+    let species = species::base_form(species::find_or_unknown(&a.species));
+    Egg {
+        id,
+        species: species.name.to_string(),
+        parents: [a.id, b.id],
+        trainer_id,
+        steps_remaining: EGG_STEPS,
+        ivs: inherit_ivs(&a.ivs, &b.ivs, rng),
+        hatched_into: None,
+    }
+}
+
+fn hatch(egg: &Egg, id: u32) -> Pokemon {
+    let species = species::find_or_unknown(&egg.species);
+    let (primary, secondary) = species.types;
+    let mut pokemon = Pokemon::new(id, species.name.to_string(), primary.to_string(), secondary.map(str::to_string), 1);
+    pokemon.ivs = egg.ivs;
+    pokemon.friendship = friendship::HATCHED_FRIENDSHIP;
+    pokemon.capture.location = Some("hatched from an egg".to_string());
+    pokemon.refresh_stats();
+    pokemon
+}
+
+// count down the steps, at 0 the egg hatches into a level 1 Pokemon for its trainer
+pub fn advance(state: &SharedState, egg: &mut Egg, steps: u32) {
+    if egg.hatched_into.is_some() {
+        return;
+    }
+    egg.steps_remaining = egg.steps_remaining.saturating_sub(steps);
+    if egg.steps_remaining > 0 {
+        return;
+    }
+
+    let mut team = state.team.lock().unwrap();
+    let mut trainers = state.trainers.lock().unwrap();
+    let trainer = trainers.iter_mut().find(|t| Some(t.id) == egg.trainer_id);
+    // it waits at 0 steps until the trainer has room again
+    if trainer.as_ref().is_some_and(|t| state.quotas.check_receive(t).is_err()) {
+        return;
+    }
+    let new_id = team.last().map_or(1, |p| p.id + 1);
+    let mut hatched = hatch(egg, new_id);
+    hatched.capture.captured_at = state.time.now();
+    team.push(hatched);
+    egg.hatched_into = Some(new_id);
+
+    if let Some(trainer) = trainer {
+        if let Some(hatched) = team.last_mut() {
+            hatched.capture = Capture { location: hatched.capture.location.take(), ..Capture::by(trainer).at(state.time.now()) };
+        }
+        state.quotas.receive(trainer, new_id);
+        achievements::record(state, trainer.id, Event::Obtained(&egg.species));
+    }
+    state.responses.invalidate("pokemon", None);
+    state.responses.invalidate("trainers", None);
+}
+
+// every egg gets a few steps closer to hatching
+pub fn incubate(state: &SharedState) {
+    let mut eggs = state.eggs.lock().unwrap();
+    for egg in eggs.iter_mut() {
+        advance(state, egg, STEPS_PER_TICK);
+    }
+}
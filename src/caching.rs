@@ -0,0 +1,192 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use axum::body::{to_bytes, Body, HttpBody};
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+// lists and records change all the time, a few seconds is enough to take the edge off a page polling them
+pub const DEFAULT_TTL: u64 = 5;
+// the type chart and the shop are built in, they only change with a new release
+pub const DEFAULT_STATIC_TTL: u64 = 24 * 60 * 60;
+
+// routes whose answer only changes when the server is upgraded
+const STATIC_ROUTES: &[&str] = &["/types/{attacking}/vs/{defending}", "/shop"];
+// GETs that do something, or are random, so a copy is never right
+const NO_STORE_ROUTES: &[&str] = &["/encounters", "/pokemon/random", "/trainers/{id}/verify-email", "/auth/{provider}/login", "/auth/{provider}/callback", "/jobs/{id}", "/admin/jobs/{id}", "/debug/pprof/profile", "/metrics"];
+
+// bigger responses aren't looked at, they just get Cache-Control
+const MAX_TRACKED_SIZE: usize = 8 * 1024 * 1024;
+// past this many URLs the ones seen so far are forgotten, and get a fresh Last-Modified next time
+const MAX_TRACKED: usize = 10_000;
+
+// Cache-Control, Last-Modified and Vary on what the API reads
+// nothing records when a record last changed, so Last-Modified is when the response was last seen to be different
+#[derive(Debug)]
+pub struct CachePolicy{
+    on: bool,
+    ttl: u64,
+    static_ttl: u64,
+    // the static routes were last modified when the server started
+    started: SystemTime,
+    // URL and the headers it varies on -> hash of the last body, and when that body was first seen
+    seen: Mutex<HashMap<u64, (u64, SystemTime)>>,
+}
+
+impl Default for CachePolicy{
+    fn default() -> Self {
+        CachePolicy::new(true)
+    }
+}
+
+impl CachePolicy{
+    fn new(on: bool) -> CachePolicy {
+        CachePolicy {
+            on,
+            ttl: DEFAULT_TTL,
+            static_ttl: DEFAULT_STATIC_TTL,
+            started: to_second(SystemTime::now()),
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // no caching headers at all
+    pub fn off() -> CachePolicy {
+        CachePolicy::new(false)
+    }
+
+    // CACHE=off turns it off, CACHE_TTL and CACHE_STATIC_TTL are max-age in seconds for everything else and for the built in data
+    pub fn from_env() -> Result<CachePolicy, String> {
+        if std::env::var("CACHE").is_ok_and(|v| matches!(v.to_lowercase().as_str(), "off" | "false" | "0")) {
+            return Ok(CachePolicy::off());
+        }
+        let secs = |name: &str, default: u64| match std::env::var(name) {
+            Ok(secs) => secs.parse().map_err(|_| format!("{} should be a number of seconds, not {:?}", name, secs)),
+            Err(_) => Ok(default),
+        };
+        Ok(CachePolicy::default().ttl(secs("CACHE_TTL", DEFAULT_TTL)?).static_ttl(secs("CACHE_STATIC_TTL", DEFAULT_STATIC_TTL)?))
+    }
+
+    // 0 makes clients revalidate every time
+    pub fn ttl(mut self, secs: u64) -> CachePolicy {
+        self.ttl = secs;
+        self
+    }
+
+    pub fn static_ttl(mut self, secs: u64) -> CachePolicy {
+        self.static_ttl = secs;
+        self
+    }
+
+    // when this body was first seen at this key, a change is always at least a second after the last one
+    // so a copy from the second before never looks current
+    fn last_modified(&self, key: u64, body: &[u8]) -> SystemTime {
+        let mut hasher = DefaultHasher::new();
+        body.hash(&mut hasher);
+        let hash = hasher.finish();
+        let now = to_second(SystemTime::now());
+
+        let mut seen = self.seen.lock().unwrap();
+        if seen.len() >= MAX_TRACKED {
+            seen.clear();
+        }
+        match seen.get(&key) {
+            Some((last, at)) if *last == hash => *at,
+            previous => {
+                let at = previous.map_or(now, |(_, at)| now.max(*at + Duration::from_secs(1)));
+                seen.insert(key, (hash, at));
+                at
+            }
+        }
+    }
+}
+
+fn to_second(time: SystemTime) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()))
+}
+
+// the URL and whatever else changes the response, the language and who's asking
+fn key(request: &Request) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    request.uri().hash(&mut hasher);
+    for name in [header::ACCEPT_LANGUAGE.as_str(), "x-trainer-id", header::AUTHORIZATION.as_str()] {
+        request.headers().get(name).map(HeaderValue::as_bytes).hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+fn not_modified_since(request: &HeaderMap, last_modified: SystemTime) -> bool {
+    // If-None-Match wins when there's both
+    if request.contains_key(header::IF_NONE_MATCH) {
+        return false;
+    }
+    request.get(header::IF_MODIFIED_SINCE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| httpdate::parse_http_date(v).ok())
+        .is_some_and(|since| last_modified <= since)
+}
+
+fn set(headers: &mut HeaderMap, cache_control: String, last_modified: Option<SystemTime>) {
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_str(&cache_control).expect("a valid Cache-Control"));
+    if let Some(at) = last_modified {
+        headers.insert(header::LAST_MODIFIED, HeaderValue::from_str(&httpdate::fmt_http_date(at)).expect("a valid date"));
+    }
+    headers.append(header::VARY, HeaderValue::from_static("x-trainer-id"));
+    headers.append(header::VARY, HeaderValue::from_static("authorization"));
+}
+
+// a 304 with the same caching headers and no body
+fn not_modified(mut response: Response) -> Response {
+    let mut headers = std::mem::take(response.headers_mut());
+    headers.remove(header::CONTENT_LENGTH);
+    headers.remove(header::CONTENT_TYPE);
+    (StatusCode::NOT_MODIFIED, headers).into_response()
+}
+
+// only successful GETs the handler didn't already say how to cache
+pub async fn apply(State(policy): State<Arc<CachePolicy>>, request: Request, next: Next) -> Response {
+    if !policy.on || request.method() != Method::GET {
+        return next.run(request).await;
+    }
+    let route = request.extensions().get::<MatchedPath>().map(|p| p.as_str().to_string()).unwrap_or_default();
+    // a trainer's own view of things, or an admin's, isn't for shared caches
+    let private = request.headers().contains_key("x-trainer-id") || request.headers().contains_key(header::AUTHORIZATION);
+    let visibility = if private { "private" } else { "public" };
+    let (key, headers) = (key(&request), request.headers().clone());
+    let response = next.run(request).await;
+
+    let handled = response.headers().contains_key(header::CACHE_CONTROL) || response.headers().contains_key(header::LAST_MODIFIED);
+    if response.status() != StatusCode::OK || handled {
+        return response;
+    }
+    let mut response = response;
+    // the /web forms carry a CSRF token, and some GETs change things
+    if NO_STORE_ROUTES.contains(&route.as_str()) || route.starts_with("/web") || response.headers().contains_key(header::SET_COOKIE) {
+        set(response.headers_mut(), "no-store".to_string(), None);
+        return response;
+    }
+    if STATIC_ROUTES.contains(&route.as_str()) {
+        set(response.headers_mut(), format!("{}, max-age={}", visibility, policy.static_ttl), Some(policy.started));
+        return if not_modified_since(&headers, policy.started) { not_modified(response) } else { response };
+    }
+
+    // streamed, or too big to hash
+    let small = response.body().size_hint().exact().is_some_and(|len| len as usize <= MAX_TRACKED_SIZE);
+    if !small {
+        set(response.headers_mut(), format!("{}, max-age={}", visibility, policy.ttl), None);
+        return response;
+    }
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = to_bytes(body, MAX_TRACKED_SIZE).await else {
+        return Response::from_parts(parts, Body::empty());
+    };
+    let last_modified = policy.last_modified(key, &bytes);
+    set(&mut parts.headers, format!("{}, max-age={}", visibility, policy.ttl), Some(last_modified));
+    let response = Response::from_parts(parts, Body::from(bytes));
+    if not_modified_since(&headers, last_modified) { not_modified(response) } else { response }
+}
@@ -0,0 +1,137 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use axum::body::{Body, Bytes};
+use axum::extract::{Json, Request, State};
+use axum::http::{HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ApiError;
+use crate::state::SharedState;
+
+// more than this is taken to be a typo
+pub const MAX_LATENCY: Duration = Duration::from_secs(60);
+
+// what goes wrong and how often, every percentage is 0 to 100 and it's all off by default
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChaosSettings{
+    // how long the delayed requests wait before they're handled
+    pub latency_ms: u64,
+    pub latency_percent: f64,
+    // 500s instead of the handler's answer
+    pub error_percent: f64,
+    // the connection closed without an answer
+    pub drop_percent: f64,
+    // only paths starting with one of these, every path when it's empty
+    pub paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChaosStats{
+    #[serde(flatten)]
+    pub settings: ChaosSettings,
+    pub delayed: u64,
+    pub errors: u64,
+    pub dropped: u64,
+}
+
+// only built with --features chaos, switched at runtime through PUT /admin/chaos
+#[derive(Debug, Default)]
+pub struct Chaos{
+    settings: Mutex<ChaosSettings>,
+    delayed: AtomicU64,
+    errors: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl Chaos{
+    pub fn set(&self, settings: ChaosSettings) -> Result<(), ApiError> {
+        for (name, percent) in [("latency_percent", settings.latency_percent), ("error_percent", settings.error_percent), ("drop_percent", settings.drop_percent)] {
+            if !(0.0..=100.0).contains(&percent) {
+                return Err(ApiError::unprocessable(format!("{} should be 0 to 100, not {}", name, percent)));
+            }
+        }
+        if Duration::from_millis(settings.latency_ms) > MAX_LATENCY {
+            return Err(ApiError::unprocessable(format!("latency_ms can be up to {}", MAX_LATENCY.as_millis())));
+        }
+        *self.settings.lock().unwrap() = settings;
+        Ok(())
+    }
+
+    pub fn stats(&self) -> ChaosStats {
+        ChaosStats {
+            settings: self.settings.lock().unwrap().clone(),
+            delayed: self.delayed.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn roll(rng: &mut impl Rng, percent: f64) -> bool {
+    percent > 0.0 && rng.random::<f64>() * 100.0 < percent
+}
+
+// a body that fails straight away, hyper closes the connection before it's written a byte
+fn dropped() -> Response {
+    let failed = futures_util::stream::once(async { Err::<Bytes, _>(std::io::Error::new(std::io::ErrorKind::ConnectionAborted, "dropped by chaos mode")) });
+    Response::new(Body::from_stream(failed))
+}
+
+// middleware for the whole app, the admin API is left alone so it can be switched off again
+pub async fn inject(State(state): State<SharedState>, request: Request, next: Next) -> Response {
+    let path = request.uri().path();
+    let settings = state.chaos.settings.lock().unwrap().clone();
+    // nothing to roll for, and no draw from a seeded sequence the requests themselves should get
+    let off = [settings.drop_percent, settings.latency_percent, settings.error_percent].iter().all(|p| *p <= 0.0);
+    if off || path == "/admin" || path.starts_with("/admin/") || !(settings.paths.is_empty() || settings.paths.iter().any(|p| path.starts_with(p.as_str()))) {
+        return next.run(request).await;
+    }
+    let chaos = &state.chaos;
+    // RANDOM_SEED makes it go wrong the same way every time
+    let mut rng = state.randomness.rng();
+    if roll(&mut rng, settings.drop_percent) {
+        chaos.dropped.fetch_add(1, Ordering::Relaxed);
+        return dropped();
+    }
+    if roll(&mut rng, settings.latency_percent) {
+        chaos.delayed.fetch_add(1, Ordering::Relaxed);
+        tokio::time::sleep(Duration::from_millis(settings.latency_ms)).await;
+    }
+    if roll(&mut rng, settings.error_percent) {
+        chaos.errors.fetch_add(1, Ordering::Relaxed);
+        let mut response = ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "injected by chaos mode").into_response();
+        response.headers_mut().insert("x-chaos", HeaderValue::from_static("error"));
+        return response;
+    }
+    next.run(request).await
+}
+
+// under /admin, behind its token
+pub fn admin_routes() -> Router<SharedState> {
+    Router::new().route("/chaos", get(get_chaos).put(set_chaos).delete(stop_chaos))
+}
+
+// READ - what's being injected and how much has been so far
+async fn get_chaos(State(state): State<SharedState>) -> Json<ChaosStats> {
+    Json(state.chaos.stats())
+}
+
+// UPDATE - fields left out are 0
+async fn set_chaos(State(state): State<SharedState>, Json(settings): Json<ChaosSettings>) -> Result<Json<ChaosStats>, ApiError> {
+    state.chaos.set(settings)?;
+    Ok(Json(state.chaos.stats()))
+}
+
+// DELETE - everything back to normal, the counts are kept
+async fn stop_chaos(State(state): State<SharedState>) -> StatusCode {
+    *state.chaos.settings.lock().unwrap() = ChaosSettings::default();
+    StatusCode::NO_CONTENT
+}
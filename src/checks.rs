@@ -0,0 +1,216 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use crate::admin::Record;
+use crate::battle::MAX_TEAM_SIZE;
+use crate::market::ListingStatus;
+use crate::models::TradeStatus;
+use crate::species;
+use crate::state::SharedState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Issue{
+    DuplicateId,
+    OutOfOrder,
+    // a trainer, daycare stay, trade or listing pointing at a Pokemon that isn't there
+    DanglingReference,
+    // the same Pokemon on more than one trainer
+    SharedOwnership,
+    // the same Pokemon twice on one trainer
+    ListedTwice,
+    TeamTooBig,
+    InvalidLevel,
+    InvalidExp,
+    InvalidHp,
+}
+
+// one broken invariant, with what POST /admin/repair does about it
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding{
+    pub issue: Issue,
+    pub detail: String,
+    // None if it needs a human to decide, e.g. which of two trainers really owns a Pokemon
+    pub fix: Option<String>,
+    // only ever true in a repair report
+    pub fixed: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CheckReport{
+    pub ok: bool,
+    pub findings: Vec<Finding>,
+}
+
+// collects findings, only applying the fixes when repairing
+struct Scan{
+    repair: bool,
+    findings: Vec<Finding>,
+}
+
+impl Scan{
+    // true if the caller should go ahead and apply `fix`
+    fn found(&mut self, issue: Issue, detail: String, fix: Option<String>) -> bool {
+        let fixed = self.repair && fix.is_some();
+        self.findings.push(Finding { issue, detail, fix, fixed });
+        fixed
+    }
+
+    fn unfixable(&mut self, issue: Issue, detail: String) {
+        self.found(issue, detail, None);
+    }
+}
+
+// later records with an ID that's already taken get a fresh one, the first keeps it along with every reference to it
+fn unique_ids<T: Record>(scan: &mut Scan, records: &mut [T]) {
+    let mut seen = BTreeSet::new();
+    let mut next_id = records.iter().map(Record::id).max().unwrap_or(0) + 1;
+    for record in records.iter_mut() {
+        let id = record.id();
+        if seen.insert(id) {
+            continue;
+        }
+        let detail = format!("more than one {} with id {}", T::KIND, id);
+        if scan.found(Issue::DuplicateId, detail, Some(format!("the later one becomes {} {}", T::KIND, next_id))) {
+            record.set_id(next_id);
+            next_id += 1;
+        }
+    }
+}
+
+fn in_order<T: Record>(scan: &mut Scan, records: &mut [T]) {
+    if records.windows(2).all(|pair| pair[0].id() < pair[1].id()) {
+        return;
+    }
+    if scan.found(Issue::OutOfOrder, format!("{} records aren't in id order", T::KIND), Some("sort them by id".to_string())) {
+        records.sort_by_key(Record::id);
+    }
+}
+
+fn run(state: &SharedState, repair: bool) -> Vec<Finding> {
+    let mut scan = Scan { repair, findings: Vec::new() };
+    let mut daycare = state.daycare.lock().unwrap();
+    let mut trades = state.trades.lock().unwrap();
+    let mut market = state.market.lock().unwrap();
+    let mut team = state.team.lock().unwrap();
+    let mut trainers = state.trainers.lock().unwrap();
+
+    unique_ids(&mut scan, &mut team);
+    in_order(&mut scan, &mut team);
+    unique_ids(&mut scan, &mut trainers);
+    in_order(&mut scan, &mut trainers);
+    {
+        let mut gyms = state.gyms.lock().unwrap();
+        unique_ids(&mut scan, &mut gyms);
+        in_order(&mut scan, &mut gyms);
+    }
+
+    for pokemon in team.iter_mut() {
+        let growth = species::find_or_unknown(&pokemon.species).growth_rate;
+        if !(1..=species::MAX_LEVEL).contains(&pokemon.level) {
+            let level = pokemon.level.clamp(1, species::MAX_LEVEL);
+            let detail = format!("pokemon {} is level {}", pokemon.id, pokemon.level);
+            if scan.found(Issue::InvalidLevel, detail, Some(format!("set it to level {}", level))) {
+                pokemon.set_level(level);
+            }
+        }
+        let (floor, ceiling) = (growth.exp_for_level(pokemon.level), growth.exp_for_level(pokemon.level + 1));
+        if pokemon.exp < floor || (pokemon.level < species::MAX_LEVEL && pokemon.exp >= ceiling) {
+            let detail = format!("pokemon {} has {} exp, level {} needs {} to {}", pokemon.id, pokemon.exp, pokemon.level, floor, ceiling.saturating_sub(1));
+            if scan.found(Issue::InvalidExp, detail, Some(format!("set it to {}", floor))) {
+                pokemon.exp = floor;
+            }
+        }
+        if pokemon.current_hp > pokemon.max_hp {
+            let detail = format!("pokemon {} has {}/{} HP", pokemon.id, pokemon.current_hp, pokemon.max_hp);
+            if scan.found(Issue::InvalidHp, detail, Some(format!("lower it to {}", pokemon.max_hp))) {
+                pokemon.current_hp = pokemon.max_hp;
+            }
+        }
+    }
+
+    let exists: BTreeSet<u32> = team.iter().map(|p| p.id).collect();
+    let mut owners: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+    for trainer in trainers.iter_mut() {
+        let mut seen = BTreeSet::new();
+        for (slot, ids) in [("team", &mut trainer.team), ("box", &mut trainer.pc_box)] {
+            let mut kept = Vec::with_capacity(ids.len());
+            for &id in ids.iter() {
+                let keep = if !exists.contains(&id) {
+                    let detail = format!("trainer {}'s {} has pokemon {}, which doesn't exist", trainer.id, slot, id);
+                    !scan.found(Issue::DanglingReference, detail, Some(format!("take it off the {}", slot)))
+                } else if !seen.insert(id) {
+                    let detail = format!("trainer {} has pokemon {} more than once", trainer.id, id);
+                    !scan.found(Issue::ListedTwice, detail, Some(format!("drop the extra one from the {}", slot)))
+                } else {
+                    true
+                };
+                if keep {
+                    kept.push(id);
+                }
+            }
+            *ids = kept;
+        }
+        if trainer.team.len() > MAX_TEAM_SIZE {
+            let detail = format!("trainer {} has {} Pokemon on their team", trainer.id, trainer.team.len());
+            if scan.found(Issue::TeamTooBig, detail, Some(format!("move all but the first {} to the box", MAX_TEAM_SIZE))) {
+                let extra = trainer.team.split_off(MAX_TEAM_SIZE);
+                trainer.pc_box.splice(0..0, extra);
+            }
+        }
+        for &id in seen.iter() {
+            owners.entry(id).or_default().push(trainer.id);
+        }
+    }
+    for (pokemon_id, trainer_ids) in owners.into_iter().filter(|(_, t)| t.len() > 1) {
+        scan.unfixable(Issue::SharedOwnership, format!("pokemon {} belongs to trainers {:?}", pokemon_id, trainer_ids));
+    }
+
+    let mut kept = Vec::with_capacity(daycare.len());
+    for stay in daycare.drain(..) {
+        if exists.contains(&stay.pokemon_id) {
+            kept.push(stay);
+            continue;
+        }
+        let detail = format!("pokemon {} is in the daycare but doesn't exist", stay.pokemon_id);
+        if !scan.found(Issue::DanglingReference, detail, Some("end the stay".to_string())) {
+            kept.push(stay);
+        }
+    }
+    *daycare = kept;
+
+    for trade in trades.iter_mut().filter(|t| t.status == TradeStatus::Pending) {
+        for id in [trade.offered, trade.requested].into_iter().filter(|id| !exists.contains(id)) {
+            let detail = format!("pending trade {} is for pokemon {}, which doesn't exist", trade.id, id);
+            if scan.found(Issue::DanglingReference, detail, Some("cancel the trade".to_string())) {
+                trade.status = TradeStatus::Cancelled;
+            }
+        }
+    }
+
+    for listing in market.iter_mut().filter(|l| l.status == ListingStatus::Listed && !exists.contains(&l.pokemon_id)) {
+        let detail = format!("listing {} is for pokemon {}, which doesn't exist", listing.id, listing.pokemon_id);
+        if scan.found(Issue::DanglingReference, detail, Some("withdraw the listing".to_string())) {
+            listing.status = ListingStatus::Withdrawn;
+        }
+    }
+
+    scan.findings
+}
+
+// only looks, nothing changes
+pub fn check(state: &SharedState) -> CheckReport {
+    let findings = run(state, false);
+    CheckReport { ok: findings.is_empty(), findings }
+}
+
+// fixes everything that has a fix, ok is whether anything's left that didn't
+pub fn repair(state: &SharedState) -> CheckReport {
+    let findings = run(state, true);
+    // from the console too, where the cache never sees a write go by
+    if findings.iter().any(|f| f.fixed) {
+        state.responses.clear();
+    }
+    CheckReport { ok: findings.iter().all(|f| f.fixed), findings }
+}
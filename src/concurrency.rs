@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use tokio::sync::Semaphore;
+
+use crate::error::ApiError;
+
+// what Retry-After says when a request is turned away, the ones ahead of it are usually done by then
+pub const DEFAULT_RETRY_AFTER: u64 = 1;
+
+// how many requests are handled at once, anything over is turned away straight off rather than queued
+#[derive(Debug)]
+pub struct ConcurrencyLimits{
+    // every request, None for no cap
+    global: Option<Arc<Semaphore>>,
+    // route pattern as it's declared, e.g. "/pokemon/{id}/export" -> its own cap on top of the global one
+    routes: HashMap<String, Arc<Semaphore>>,
+    retry_after: u64,
+}
+
+impl Default for ConcurrencyLimits{
+    // no limits
+    fn default() -> Self {
+        ConcurrencyLimits { global: None, routes: HashMap::new(), retry_after: DEFAULT_RETRY_AFTER }
+    }
+}
+
+impl ConcurrencyLimits{
+    // MAX_IN_FLIGHT caps everything, ROUTE_CONCURRENCY caps routes, e.g. "/admin/export=1,/battles=4"
+    pub fn from_env() -> Result<ConcurrencyLimits, String> {
+        let mut limits = ConcurrencyLimits::default();
+        if let Ok(max) = std::env::var("MAX_IN_FLIGHT") {
+            limits = limits.global(max.parse().map_err(|_| format!("MAX_IN_FLIGHT should be a number, not {:?}", max))?);
+        }
+        if let Ok(routes) = std::env::var("ROUTE_CONCURRENCY") {
+            for pair in routes.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+                let (path, max) = pair.rsplit_once('=')
+                    .and_then(|(path, max)| Some((path.trim(), max.trim().parse().ok()?)))
+                    .ok_or_else(|| format!("ROUTE_CONCURRENCY entries look like /battles=4, not {:?}", pair))?;
+                limits = limits.route(path, max);
+            }
+        }
+        Ok(limits)
+    }
+
+    pub fn global(mut self, max: usize) -> ConcurrencyLimits {
+        self.global = Some(Arc::new(Semaphore::new(max)));
+        self
+    }
+
+    // the path as the route is declared, with {placeholders}
+    pub fn route(mut self, path: &str, max: usize) -> ConcurrencyLimits {
+        self.routes.insert(path.to_string(), Arc::new(Semaphore::new(max)));
+        self
+    }
+
+    pub fn retry_after(mut self, secs: u64) -> ConcurrencyLimits {
+        self.retry_after = secs;
+        self
+    }
+
+    fn shed(&self, what: &str) -> Response {
+        let mut response = ApiError::new(StatusCode::SERVICE_UNAVAILABLE, format!("{} is busy, try again shortly", what)).into_response();
+        response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(self.retry_after));
+        response
+    }
+}
+
+// holds a permit from each semaphore that applies for as long as the request runs
+pub async fn enforce(State(limits): State<Arc<ConcurrencyLimits>>, request: Request, next: Next) -> Response {
+    let _global = match &limits.global {
+        Some(global) => match global.clone().try_acquire_owned() {
+            Ok(permit) => Some(permit),
+            Err(_) => return limits.shed("the server"),
+        },
+        None => None,
+    };
+    let route = request.extensions().get::<MatchedPath>().and_then(|path| limits.routes.get_key_value(path.as_str()));
+    let _route = match route {
+        Some((path, semaphore)) => match semaphore.clone().try_acquire_owned() {
+            Ok(permit) => Some(permit),
+            Err(_) => return limits.shed(path),
+        },
+        None => None,
+    };
+    next.run(request).await
+}
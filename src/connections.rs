@@ -0,0 +1,175 @@
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use axum::serve::{ListenerExt, TapIo};
+use serde::Serialize;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::state::SharedState;
+
+// hitting the cap is printed at most this often, with how many times it happened since
+const WARN_EVERY: Duration = Duration::from_secs(60);
+
+// open TCP connections, so one client holding thousands open can't use up every file descriptor
+// once the cap is reached nothing more is accepted until one closes, the rest wait in the OS backlog
+#[derive(Debug)]
+pub struct Connections{
+    // None for no cap
+    max: Option<usize>,
+    permits: Arc<Semaphore>,
+    open: AtomicUsize,
+    // how many times a connection had to wait for the cap
+    cap_hits: AtomicU64,
+    warned: Mutex<(Option<Instant>, u64)>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionStats{
+    pub open: usize,
+    pub max: Option<usize>,
+    pub cap_hits: u64,
+}
+
+impl Default for Connections{
+    fn default() -> Self {
+        Connections::new(None)
+    }
+}
+
+impl Connections{
+    fn new(max: Option<usize>) -> Connections {
+        Connections {
+            max,
+            permits: Arc::new(Semaphore::new(max.unwrap_or(0))),
+            open: AtomicUsize::new(0),
+            cap_hits: AtomicU64::new(0),
+            warned: Mutex::new((None, 0)),
+        }
+    }
+
+    pub fn limit(max: usize) -> Connections {
+        Connections::new(Some(max))
+    }
+
+    // MAX_CONNECTIONS, no cap otherwise
+    pub fn from_env() -> Result<Connections, String> {
+        match std::env::var("MAX_CONNECTIONS") {
+            Ok(max) => Ok(Connections::limit(max.parse().map_err(|_| format!("MAX_CONNECTIONS should be a number, not {:?}", max))?)),
+            Err(_) => Ok(Connections::default()),
+        }
+    }
+
+    pub fn stats(&self) -> ConnectionStats {
+        ConnectionStats { open: self.open.load(Ordering::Relaxed), max: self.max, cap_hits: self.cap_hits.load(Ordering::Relaxed) }
+    }
+
+    // a permit for one more connection, waiting for one to close if it's full
+    async fn admit(&self) -> Option<OwnedSemaphorePermit> {
+        let max = self.max?;
+        if let Ok(permit) = self.permits.clone().try_acquire_owned() {
+            return Some(permit);
+        }
+        self.cap_hits.fetch_add(1, Ordering::Relaxed);
+        self.warn(max);
+        self.permits.clone().acquire_owned().await.ok()
+    }
+
+    fn warn(&self, max: usize) {
+        let mut warned = self.warned.lock().unwrap();
+        warned.1 += 1;
+        if warned.0.is_none_or(|at| at.elapsed() >= WARN_EVERY) {
+            eprintln!("connections: at the cap of {}, waited for one to close {} times", max, warned.1);
+            *warned = (Some(Instant::now()), 0);
+        }
+    }
+}
+
+// a TcpListener that stops accepting at the cap
+pub struct Listener{
+    inner: TcpListener,
+    state: SharedState,
+}
+
+// what the cap is counting, giving the permit back when it's closed
+pub struct Connection{
+    stream: TcpStream,
+    state: SharedState,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl Drop for Connection{
+    fn drop(&mut self) {
+        self.state.connections.open.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl axum::serve::Listener for Listener{
+    type Io = Connection;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Connection, SocketAddr) {
+        let permit = self.state.connections.admit().await;
+        loop {
+            match self.inner.accept().await {
+                Ok((stream, addr)) => {
+                    self.state.connections.open.fetch_add(1, Ordering::Relaxed);
+                    return (Connection { stream, state: self.state.clone(), _permit: permit }, addr);
+                }
+                // the client gave up before it was accepted, on to the next one
+                Err(e) if matches!(e.kind(), io::ErrorKind::ConnectionAborted | io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionRefused) => {}
+                // most likely out of file descriptors anyway, give some a chance to close
+                Err(e) => {
+                    eprintln!("connections: couldn't accept: {}", e);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            }
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+}
+
+// ready for axum::serve, with the peer address for into_make_service_with_connect_info::<SocketAddr>
+pub fn listen(listener: TcpListener, state: SharedState) -> TapIo<Listener, fn(&mut Connection)> {
+    // tapping it does nothing, but axum only knows how to get a SocketAddr out of a TcpListener or a tapped listener
+    Listener { inner: listener, state }.tap_io(untouched as fn(&mut Connection))
+}
+
+fn untouched(_: &mut Connection) {}
+
+impl AsyncRead for Connection{
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Connection{
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(mut self: Pin<&mut Self>, cx: &mut Context<'_>, bufs: &[io::IoSlice<'_>]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.stream.is_write_vectored()
+    }
+}
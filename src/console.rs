@@ -0,0 +1,305 @@
+use std::io;
+use std::net::SocketAddr;
+
+use serde::Serialize;
+use serde_json::Value;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+
+use crate::checks;
+use crate::modes::{Mode, ModeSettings};
+use crate::state::SharedState;
+
+const HELP: &str = "\
+status                         how much of everything there is, and the mode
+find <kind> [field<op>value]   everything of a kind that matches, one per line, e.g. find pokemon level>=30 poke_type=fire
+show <kind> <id>               one of them in full
+snapshot                       take a snapshot now
+snapshots                      the snapshots on disk, newest first
+rekey                          encrypt every snapshot with the current key
+check                          look for broken data
+repair                         fix what check finds that can safely be fixed
+mode [name] [message]          show the mode, or switch to normal, read_only or maintenance
+toggle <name>                  into read_only or maintenance, or back to normal if it's already in it
+quit                           close the console, the server keeps running
+
+kinds: pokemon, trainers, battles, gyms, trades, market, eggs, trash
+ops: = != > < >= <=, fields can be nested like capture.ball, = on a list means it contains the value";
+
+// what one line typed into the console does, and whether that was the last one
+pub struct Reply{
+    pub output: String,
+    pub quit: bool,
+}
+
+impl Reply{
+    fn text(output: impl Into<String>) -> Reply {
+        Reply { output: output.into(), quit: false }
+    }
+
+    fn json(value: &impl Serialize) -> Reply {
+        Reply::text(serde_json::to_string_pretty(value).expect("state always serializes"))
+    }
+}
+
+// CONSOLE_ADDR: a loopback address like 127.0.0.1:7000, a Unix socket path, or "stdin"
+// there's no token, being able to connect at all is the check, so it never listens anywhere but this machine
+pub async fn spawn(state: SharedState, addr: &str) -> io::Result<String> {
+    if addr == "stdin" {
+        tokio::spawn(async move {
+            let _ = session(&state, BufReader::new(tokio::io::stdin()), tokio::io::stdout()).await;
+        });
+        return Ok("stdin".to_string());
+    }
+    if addr.contains('/') {
+        return spawn_unix(state, addr);
+    }
+    let socket: SocketAddr = addr.parse().map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, format!("{} isn't an address", addr)))?;
+    if !socket.ip().is_loopback() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} isn't a loopback address, the console is local only", addr)));
+    }
+    let listener = tokio::net::TcpListener::bind(socket).await?;
+    let bound = listener.local_addr()?;
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let state = state.clone();
+            tokio::spawn(async move {
+                let (reader, writer) = stream.into_split();
+                let _ = session(&state, BufReader::new(reader), writer).await;
+            });
+        }
+    });
+    Ok(bound.to_string())
+}
+
+// only the user the server runs as can connect
+#[cfg(unix)]
+fn spawn_unix(state: SharedState, path: &str) -> io::Result<String> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    // left behind by a server that didn't shut down cleanly
+    if std::fs::metadata(path).is_ok_and(|m| m.file_type().is_socket()) {
+        std::fs::remove_file(path)?;
+    }
+    let listener = tokio::net::UnixListener::bind(path)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let state = state.clone();
+            tokio::spawn(async move {
+                let (reader, writer) = stream.into_split();
+                let _ = session(&state, BufReader::new(reader), writer).await;
+            });
+        }
+    });
+    Ok(path.to_string())
+}
+
+#[cfg(not(unix))]
+fn spawn_unix(_state: SharedState, path: &str) -> io::Result<String> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, format!("{} looks like a Unix socket, which this platform doesn't have", path)))
+}
+
+// a prompt, a line, its reply, until quit or the other end goes away
+pub async fn session(state: &SharedState, reader: impl AsyncBufRead + Unpin, mut writer: impl AsyncWrite + Unpin) -> io::Result<()> {
+    writer.write_all(b"pokemon admin console, `help` for the commands\n> ").await?;
+    writer.flush().await?;
+    let mut lines = reader.lines();
+    while let Some(line) = lines.next_line().await? {
+        let reply = execute(state, &line).await;
+        writer.write_all(reply.output.as_bytes()).await?;
+        if reply.quit {
+            writer.write_all(b"\n").await?;
+            return writer.flush().await;
+        }
+        writer.write_all(if reply.output.is_empty() { b"> " } else { b"\n> " }).await?;
+        writer.flush().await?;
+    }
+    Ok(())
+}
+
+pub async fn execute(state: &SharedState, line: &str) -> Reply {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    match words.as_slice() {
+        [] => Reply::text(""),
+        ["help"] => Reply::text(HELP),
+        ["quit" | "exit"] => Reply { output: "bye".to_string(), quit: true },
+        ["status"] => status(state),
+        ["find", kind, conditions @ ..] => find(state, kind, conditions),
+        ["show", kind, id] => show(state, kind, id),
+        ["snapshot"] => match state.backups.take(state).await {
+            Ok(snapshot) => Reply::json(&snapshot),
+            Err(e) => Reply::text(format!("couldn't take a snapshot: {}", e)),
+        },
+        ["snapshots"] => match state.backups.list().await {
+            Ok(snapshots) => Reply::json(&snapshots),
+            Err(e) => Reply::text(format!("couldn't list the snapshots: {}", e)),
+        },
+        ["rekey"] => match state.backups.rekey().await {
+            Ok(rekey) => Reply::json(&rekey),
+            Err(e) => Reply::text(e),
+        },
+        ["check"] => Reply::json(&checks::check(state)),
+        ["repair"] => Reply::json(&checks::repair(state)),
+        ["mode"] => Reply::json(&state.modes.get()),
+        ["mode", name, message @ ..] => match mode_from(name) {
+            Ok(mode) => {
+                let message = (!message.is_empty()).then(|| message.join(" "));
+                state.modes.set(ModeSettings { mode, message, ..ModeSettings::default() });
+                Reply::json(&state.modes.get())
+            }
+            Err(e) => Reply::text(e),
+        },
+        ["toggle", name] => match mode_from(name) {
+            Ok(mode) => {
+                state.modes.toggle(mode);
+                Reply::json(&state.modes.get())
+            }
+            Err(e) => Reply::text(e),
+        },
+        [command, ..] => Reply::text(format!("unknown command {:?}, `help` lists them", command)),
+    }
+}
+
+fn mode_from(name: &str) -> Result<Mode, String> {
+    serde_json::from_value(Value::String(name.to_string()))
+        .map_err(|_| format!("unknown mode {:?}, it's normal, read_only or maintenance", name))
+}
+
+fn status(state: &SharedState) -> Reply {
+    let counts = ["pokemon", "trainers", "battles", "gyms", "trades", "market", "eggs", "trash"]
+        .map(|kind| format!("{:<9}{}", kind, collection(state, kind).map_or(0, |items| items.len())));
+    let settings = state.modes.get();
+    let mode = match settings.message {
+        Some(message) => format!("mode     {:?} ({})", settings.mode, message),
+        None => format!("mode     {:?}", settings.mode),
+    };
+    let stats = state.connections.stats();
+    let connections = match stats.max {
+        Some(max) => format!("conns    {} of {}, full {} times", stats.open, max, stats.cap_hits),
+        None => format!("conns    {}", stats.open),
+    };
+    Reply::text(format!("{}\n{}\n{}", counts.join("\n"), mode, connections))
+}
+
+// everything of a kind as JSON, copied out so no lock is held while it's searched
+fn collection(state: &SharedState, kind: &str) -> Option<Vec<Value>> {
+    fn values<T: Serialize>(items: &[T]) -> Vec<Value> {
+        items.iter().map(|item| serde_json::to_value(item).expect("state always serializes")).collect()
+    }
+    Some(match kind {
+        "pokemon" => values(&state.team.lock().unwrap()),
+        "trainers" => values(&state.trainers.lock().unwrap()),
+        "battles" => values(&state.battles.lock().unwrap()),
+        "gyms" => values(&state.gyms.lock().unwrap()),
+        "trades" => values(&state.trades.lock().unwrap()),
+        "market" => values(&state.market.lock().unwrap()),
+        "eggs" => values(&state.eggs.lock().unwrap()),
+        "trash" => values(&state.trash.lock().unwrap()),
+        _ => return None,
+    })
+}
+
+fn unknown_kind(kind: &str) -> Reply {
+    Reply::text(format!("unknown kind {:?}, `help` lists them", kind))
+}
+
+fn show(state: &SharedState, kind: &str, id: &str) -> Reply {
+    let Some(items) = collection(state, kind) else { return unknown_kind(kind) };
+    let Ok(id) = id.parse::<u64>() else { return Reply::text(format!("{:?} isn't an ID", id)) };
+    match items.iter().find(|item| item["id"].as_u64() == Some(id)) {
+        Some(item) => Reply::json(item),
+        None => Reply::text(format!("no {} with ID {}", kind, id)),
+    }
+}
+
+fn find(state: &SharedState, kind: &str, conditions: &[&str]) -> Reply {
+    let Some(items) = collection(state, kind) else { return unknown_kind(kind) };
+    let conditions: Vec<Condition> = match conditions.iter().map(|c| Condition::parse(c)).collect() {
+        Ok(conditions) => conditions,
+        Err(e) => return Reply::text(e),
+    };
+    let found: Vec<String> = items.iter()
+        .filter(|item| conditions.iter().all(|c| c.matches(item)))
+        .map(Value::to_string)
+        .collect();
+    let summary = match found.len() {
+        1 => "1 match".to_string(),
+        n => format!("{} matches", n),
+    };
+    if found.is_empty() {
+        return Reply::text(summary);
+    }
+    Reply::text(format!("{}\n{}", found.join("\n"), summary))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op{
+    Eq,
+    Ne,
+    Gt,
+    Lt,
+    Ge,
+    Le,
+}
+
+// e.g. level>=30, a field of the JSON compared with a value
+struct Condition{
+    path: Vec<String>,
+    op: Op,
+    value: String,
+}
+
+impl Condition{
+    fn parse(text: &str) -> Result<Condition, String> {
+        // the two-character ones first, so >= isn't read as > with "=30"
+        let ops = [(">=", Op::Ge), ("<=", Op::Le), ("!=", Op::Ne), ("=", Op::Eq), (">", Op::Gt), ("<", Op::Lt)];
+        let (at, symbol, op) = ops.iter()
+            .filter_map(|(symbol, op)| text.find(symbol).map(|at| (at, *symbol, *op)))
+            .min_by_key(|(at, symbol, _)| (*at, std::cmp::Reverse(symbol.len())))
+            .ok_or_else(|| format!("{:?} isn't a condition, they look like level>=30", text))?;
+        let field = &text[..at];
+        if field.is_empty() {
+            return Err(format!("{:?} doesn't say which field", text));
+        }
+        Ok(Condition {
+            path: field.split('.').map(str::to_string).collect(),
+            op,
+            value: text[at + symbol.len()..].to_string(),
+        })
+    }
+
+    fn matches(&self, item: &Value) -> bool {
+        let field = self.path.iter().fold(item, |value, key| &value[key.as_str()]);
+        match field {
+            // a list has the value in it, or doesn't
+            Value::Array(values) if matches!(self.op, Op::Eq | Op::Ne) => {
+                values.iter().any(|v| self.compare(v) == Some(std::cmp::Ordering::Equal)) == (self.op == Op::Eq)
+            }
+            _ => match self.compare(field) {
+                Some(ordering) => match self.op {
+                    Op::Eq => ordering.is_eq(),
+                    Op::Ne => ordering.is_ne(),
+                    Op::Gt => ordering.is_gt(),
+                    Op::Lt => ordering.is_lt(),
+                    Op::Ge => ordering.is_ge(),
+                    Op::Le => ordering.is_le(),
+                },
+                // an object, or a list with something other than = or !=
+                None => self.op == Op::Ne,
+            },
+        }
+    }
+
+    // numbers as numbers, everything else as text ignoring case, null matches "null"
+    fn compare(&self, field: &Value) -> Option<std::cmp::Ordering> {
+        if let (Some(a), Ok(b)) = (field.as_f64(), self.value.parse::<f64>()) {
+            return a.partial_cmp(&b);
+        }
+        let text = match field {
+            Value::String(s) => s.to_lowercase(),
+            Value::Object(_) | Value::Array(_) => return None,
+            other => other.to_string(),
+        };
+        Some(text.cmp(&self.value.to_lowercase()))
+    }
+}
@@ -0,0 +1,85 @@
+use axum::body::{self, Body};
+use axum::extract::{FromRequest, Request};
+use axum::http::{header, HeaderMap, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Form;
+use serde::Deserialize;
+
+use crate::sharing::hex;
+use crate::web;
+
+// double submit: the same random token in a cookie and in every form, another site can post the form but can't read the cookie
+pub const COOKIE: &str = "csrf";
+pub const FIELD: &str = "csrf_token";
+
+// the pages' forms are a few short fields
+const MAX_FORM_SIZE: usize = 64 * 1024;
+
+// what the pages put in their forms, in the request extensions for the /web handlers
+#[derive(Debug, Clone)]
+pub struct CsrfToken(pub String);
+
+#[derive(Debug, Deserialize)]
+struct Submitted{
+    #[serde(default)]
+    csrf_token: String,
+}
+
+fn from_cookie(headers: &HeaderMap) -> Option<String> {
+    headers.get_all(header::COOKIE).iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == COOKIE)
+        .map(|(_, value)| value.to_string())
+        .filter(|value| value.len() == 64 && value.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+// constant time, so the token can't be guessed byte by byte
+fn same(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn rejected() -> Response {
+    (StatusCode::FORBIDDEN, web::error_page("Form expired", "that form has expired, reload the page and try again")).into_response()
+}
+
+// for the /web pages: hands out the cookie, and turns away any form that doesn't carry it back
+pub async fn protect(request: Request, next: Next) -> Response {
+    let cookie = from_cookie(request.headers());
+    let fresh = cookie.is_none();
+    let token = cookie.unwrap_or_else(|| hex(&rand::random::<[u8; 32]>()));
+
+    let mut request = if matches!(*request.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+        request
+    } else {
+        if fresh {
+            return rejected();
+        }
+        let (parts, body) = request.into_parts();
+        let Ok(bytes) = body::to_bytes(body, MAX_FORM_SIZE).await else {
+            return (StatusCode::PAYLOAD_TOO_LARGE, web::error_page("Too big", "that form is too big")).into_response();
+        };
+        // read from a copy, the handler gets the whole body as it came
+        let mut copy = Request::post("/").body(Body::from(bytes.clone())).expect("a bare POST always builds");
+        if let Some(content_type) = parts.headers.get(header::CONTENT_TYPE) {
+            copy.headers_mut().insert(header::CONTENT_TYPE, content_type.clone());
+        }
+        let submitted = Form::<Submitted>::from_request(copy, &()).await.map_or(String::new(), |Form(s)| s.csrf_token);
+        if !same(&submitted, &token) {
+            return rejected();
+        }
+        Request::from_parts(parts, Body::from(bytes))
+    };
+    request.extensions_mut().insert(CsrfToken(token.clone()));
+
+    let mut response = next.run(request).await;
+    if fresh {
+        let cookie = format!("{}={}; Path=/web; HttpOnly; SameSite=Strict", COOKIE, token);
+        if let Ok(value) = cookie.parse() {
+            response.headers_mut().append(header::SET_COOKIE, value);
+        }
+    }
+    response
+}
@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::inbox;
+use crate::models::{ExpGainResult, Pokemon, Stats};
+use crate::state::SharedState;
+
+// 1 exp per second, like 1 exp per step in the games
+const EXP_PER_SECOND: u32 = 1;
+
+// how often the daycare pays out, withdrawing pays the time since the last payout
+pub const TICK_INTERVAL: Duration = Duration::from_secs(30);
+
+// a Pokemon left at the daycare, it levels up on its own while it's there
+#[derive(Debug, Clone, Serialize)]
+pub struct DaycareStay{
+    pub pokemon_id: u32,
+    pub name: String,
+    pub deposited_level: u32,
+    // running totals since the deposit
+    pub exp_gained: u32,
+    pub levels_gained: u32,
+    pub learned_moves: Vec<String>,
+    pub offered_moves: Vec<String>,
+    #[serde(skip)]
+    deposited_stats: Stats,
+    // unix seconds
+    #[serde(skip)]
+    last_paid: u64,
+}
+
+impl DaycareStay{
+    pub fn new(pokemon: &Pokemon, now: u64) -> DaycareStay {
+        DaycareStay {
+            pokemon_id: pokemon.id,
+            name: pokemon.name.clone(),
+            deposited_level: pokemon.level,
+            exp_gained: 0,
+            levels_gained: 0,
+            learned_moves: Vec::new(),
+            offered_moves: Vec::new(),
+            deposited_stats: pokemon.stats,
+            last_paid: now,
+        }
+    }
+
+    // exp for the seconds since the last payout
+    // returns the levels this payout was worth
+    pub fn pay(&mut self, pokemon: &mut Pokemon, now: u64) -> u32 {
+        let secs = now.saturating_sub(self.last_paid);
+        self.last_paid += secs;
+
+        let previous_exp = pokemon.exp;
+        let (levels, learned, offered) = pokemon.gain_exp(secs as u32 * EXP_PER_SECOND);
+        self.exp_gained += pokemon.exp - previous_exp;
+        self.levels_gained += levels;
+        self.learned_moves.extend(learned);
+        for m in offered {
+            if !self.offered_moves.contains(&m) {
+                self.offered_moves.push(m);
+            }
+        }
+        levels
+    }
+
+    // everything that happened during the stay, same shape as POST /pokemon/{id}/gain-exp
+    pub fn summary(self, pokemon: Pokemon) -> ExpGainResult {
+        ExpGainResult {
+            exp_gained: self.exp_gained,
+            levels_gained: self.levels_gained,
+            previous_level: self.deposited_level,
+            previous_stats: self.deposited_stats,
+            learned_moves: self.learned_moves,
+            offered_moves: self.offered_moves,
+            pokemon,
+        }
+    }
+}
+
+// pays every stay for the time since the last payout
+pub fn tick(state: &SharedState) {
+    let now = state.time.now();
+    let mut grown = Vec::new();
+    {
+        let mut daycare = state.daycare.lock().unwrap();
+        let mut team = state.team.lock().unwrap();
+        for stay in daycare.iter_mut() {
+            if let Some(pokemon) = team.iter_mut().find(|p| p.id == stay.pokemon_id) {
+                if stay.pay(pokemon, now) > 0 {
+                    grown.push((pokemon.id, pokemon.name.clone(), pokemon.level));
+                }
+                state.responses.invalidate("pokemon", Some(pokemon.id));
+            }
+        }
+    }
+    // picking it up says how it went, so only these ones while it's away
+    for (id, name, level) in grown {
+        inbox::level_up(state, id, &name, level);
+    }
+}
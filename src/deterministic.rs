@@ -0,0 +1,150 @@
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+
+use crate::models::unix_time;
+
+// where the state gets the time for the records it stamps, in unix seconds
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> u64;
+}
+
+// hands out a generator for each battle, encounter or roll
+pub trait RngProvider: Debug + Send + Sync {
+    fn rng(&self) -> StdRng;
+}
+
+#[derive(Debug)]
+pub struct SystemClock;
+
+impl Clock for SystemClock{
+    fn now(&self) -> u64 {
+        unix_time()
+    }
+}
+
+// stays at the same second until it's moved along
+#[derive(Debug)]
+pub struct FrozenClock{
+    at: AtomicU64,
+}
+
+impl FrozenClock{
+    pub fn at(time: u64) -> FrozenClock {
+        FrozenClock { at: AtomicU64::new(time) }
+    }
+
+    pub fn set(&self, time: u64) {
+        self.at.store(time, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, secs: u64) {
+        self.at.fetch_add(secs, Ordering::SeqCst);
+    }
+}
+
+impl Clock for FrozenClock{
+    fn now(&self) -> u64 {
+        self.at.load(Ordering::SeqCst)
+    }
+}
+
+// a fresh one from the OS every time
+#[derive(Debug)]
+pub struct OsRng;
+
+impl RngProvider for OsRng{
+    fn rng(&self) -> StdRng {
+        StdRng::from_rng(&mut rand::rng())
+    }
+}
+
+// every generator comes from one seeded sequence, so the same requests in the same order roll the same
+#[derive(Debug)]
+pub struct SeededRng{
+    sequence: Mutex<StdRng>,
+}
+
+impl SeededRng{
+    pub fn new(seed: u64) -> SeededRng {
+        SeededRng { sequence: Mutex::new(StdRng::seed_from_u64(seed)) }
+    }
+}
+
+impl RngProvider for SeededRng{
+    fn rng(&self) -> StdRng {
+        StdRng::seed_from_u64(self.sequence.lock().unwrap().next_u64())
+    }
+}
+
+// the state's clock, the system's unless FROZEN_TIME stops it
+#[derive(Debug, Clone)]
+pub struct Time(Arc<dyn Clock>);
+
+impl Default for Time{
+    fn default() -> Self {
+        Time(Arc::new(SystemClock))
+    }
+}
+
+impl Time{
+    pub fn new(clock: Arc<dyn Clock>) -> Time {
+        Time(clock)
+    }
+
+    pub fn frozen(at: u64) -> Time {
+        Time(Arc::new(FrozenClock::at(at)))
+    }
+
+    // FROZEN_TIME, in unix seconds
+    pub fn from_env() -> Result<Time, String> {
+        match std::env::var("FROZEN_TIME") {
+            Ok(at) => Ok(Time::frozen(at.parse().map_err(|_| format!("FROZEN_TIME should be unix seconds, not {:?}", at))?)),
+            Err(_) => Ok(Time::default()),
+        }
+    }
+
+    pub fn now(&self) -> u64 {
+        self.0.now()
+    }
+}
+
+// the state's randomness, from the OS unless RANDOM_SEED fixes it
+#[derive(Debug, Clone)]
+pub struct Randomness(Arc<dyn RngProvider>);
+
+impl Default for Randomness{
+    fn default() -> Self {
+        Randomness(Arc::new(OsRng))
+    }
+}
+
+impl Randomness{
+    pub fn new(provider: Arc<dyn RngProvider>) -> Randomness {
+        Randomness(provider)
+    }
+
+    pub fn seeded(seed: u64) -> Randomness {
+        Randomness(Arc::new(SeededRng::new(seed)))
+    }
+
+    // RANDOM_SEED
+    pub fn from_env() -> Result<Randomness, String> {
+        match std::env::var("RANDOM_SEED") {
+            Ok(seed) => Ok(Randomness::seeded(seed.parse().map_err(|_| format!("RANDOM_SEED should be a number, not {:?}", seed))?)),
+            Err(_) => Ok(Randomness::default()),
+        }
+    }
+
+    pub fn rng(&self) -> StdRng {
+        self.0.rng()
+    }
+
+    // for the battles that keep their seed, so a replay can run them again
+    pub fn seed(&self) -> u64 {
+        self.rng().next_u64()
+    }
+}
@@ -0,0 +1,52 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::models::{HistoryEvent, Pokemon};
+use crate::tags;
+
+// Pokemon that look like the same one added twice
+#[derive(Debug, Serialize)]
+pub struct DuplicateGroup{
+    pub species: String,
+    pub level: u32,
+    pub nickname: Option<String>,
+    pub pokemon: Vec<u32>,
+}
+
+// same species, level and nickname, oldest ID first
+pub fn groups(team: &[Pokemon]) -> Vec<DuplicateGroup> {
+    let mut groups: BTreeMap<(&str, u32, Option<&str>), Vec<u32>> = BTreeMap::new();
+    for p in team {
+        groups.entry((&p.species, p.level, p.nickname.as_deref())).or_default().push(p.id);
+    }
+    groups.into_iter()
+        .filter(|(_, ids)| ids.len() > 1)
+        .map(|((species, level, nickname), pokemon)| DuplicateGroup {
+            species: species.to_string(),
+            level,
+            nickname: nickname.map(str::to_string),
+            pokemon,
+        })
+        .collect()
+}
+
+// folds `other` into `keep`: stats and moves stay keep's, but nothing from either history or tags is lost
+// the older capture record wins, since that's when the Pokemon was really caught
+pub fn absorb(keep: &Pokemon, other: &Pokemon) -> Result<Pokemon, String> {
+    if keep.species != other.species {
+        return Err(format!("{} and {} aren't the same species", keep.name, other.name));
+    }
+    let mut merged = keep.clone();
+    tags::add(&mut merged, &other.tags)?;
+    merged.favorite |= other.favorite;
+    if merged.nickname.is_none() {
+        merged.set_nickname(other.nickname.clone());
+    }
+    if other.capture.captured_at < merged.capture.captured_at {
+        merged.capture = other.capture.clone();
+    }
+    merged.history.extend(other.history.iter().cloned());
+    merged.history.push(HistoryEvent::Merged { from: other.id });
+    Ok(merged)
+}
@@ -0,0 +1,223 @@
+use std::collections::BTreeMap;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::achievements::{self, Event};
+use crate::error::ApiError;
+use crate::models::{Capture, CatchPokemon, CatchResult, EncounterQuery, Pokemon};
+use crate::notify;
+use crate::species;
+use crate::state::SharedState;
+
+pub use pokemon_models::encounters::*;
+
+// 1 in 4096, same as the modern games
+pub const SHINY_ODDS: u32 = 4096;
+
+// chance a wild Pokemon runs off after breaking out of a ball
+const FLEE_CHANCE: f64 = 0.1;
+
+// one possible wild Pokemon in an area, picked with probability weight / total weight
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncounterSlot{
+    pub species: String,
+    pub min_level: u32,
+    pub max_level: u32,
+    pub weight: u32,
+}
+
+// region -> area -> slots, e.g. kanto -> grass -> Pidgey, Rattata...
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct EncounterTable(pub BTreeMap<String, BTreeMap<String, Vec<EncounterSlot>>>);
+
+// (species, min level, max level, weight)
+type Slot = (&'static str, u32, u32, u32);
+
+const KANTO: &[(&str, &[Slot])] = &[
+    ("grass", &[
+        ("Pidgey", 2, 5, 30),
+        ("Rattata", 2, 4, 30),
+        ("Caterpie", 3, 5, 15),
+        ("Jigglypuff", 3, 7, 10),
+        ("Pikachu", 3, 5, 5),
+        ("Abra", 6, 9, 5),
+        ("Bulbasaur", 5, 5, 3),
+        ("Eevee", 10, 15, 2),
+    ]),
+    ("cave", &[
+        ("Geodude", 7, 12, 45),
+        ("Machop", 8, 12, 30),
+        ("Onix", 10, 14, 20),
+        ("Mewtwo", 70, 70, 1),
+    ]),
+    ("water", &[
+        ("Magikarp", 5, 15, 70),
+        ("Squirtle", 5, 10, 10),
+        ("Dratini", 10, 15, 10),
+        ("Gyarados", 15, 25, 5),
+    ]),
+    ("tower", &[
+        ("Gastly", 15, 25, 80),
+        ("Haunter", 20, 25, 20),
+    ]),
+];
+
+impl Default for EncounterTable{
+    fn default() -> Self {
+        let areas = KANTO.iter()
+            .map(|(area, slots)| {
+                let slots = slots.iter()
+                    .map(|(name, min_level, max_level, weight)| EncounterSlot {
+                        species: name.to_string(),
+                        min_level: *min_level,
+                        max_level: *max_level,
+                        weight: *weight,
+                    })
+                    .collect();
+                (area.to_string(), slots)
+            })
+            .collect();
+        EncounterTable(BTreeMap::from([("kanto".to_string(), areas)]))
+    }
+}
+
+impl EncounterTable{
+    // the built-in table, or the JSON file named by ENCOUNTER_TABLE
+    pub fn from_env() -> Result<EncounterTable, String> {
+        let Ok(path) = std::env::var("ENCOUNTER_TABLE") else {
+            return Ok(EncounterTable::default());
+        };
+        let text = std::fs::read_to_string(&path).map_err(|e| format!("can't read {}: {}", path, e))?;
+        let table: EncounterTable = serde_json::from_str(&text).map_err(|e| format!("{} isn't a valid encounter table: {}", path, e))?;
+        table.validate()?;
+        Ok(table)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for (region, areas) in &self.0 {
+            for (area, slots) in areas {
+                if slots.iter().all(|s| s.weight == 0) {
+                    return Err(format!("{}/{} has nothing to encounter", region, area));
+                }
+                for slot in slots {
+                    if species::find(&slot.species).is_none() {
+                        return Err(format!("{}/{}: unknown species {}", region, area, slot.species));
+                    }
+                    if slot.min_level == 0 || slot.min_level > slot.max_level || slot.max_level > species::MAX_LEVEL {
+                        return Err(format!("{}/{}: bad level range for {}", region, area, slot.species));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn slots(&self, region: &str, area: &str) -> Option<&[EncounterSlot]> {
+        self.0.get(region)?.get(area).map(Vec::as_slice)
+    }
+}
+
+pub struct Throw{
+    pub caught: bool,
+    // 0 to 3, like the wobbles before it breaks out
+    pub shakes: u32,
+    pub fled: bool,
+}
+
+// four checks, each passed with the fourth root of the catch chance, all four is a catch
+pub fn throw(chance: f64, rng: &mut impl Rng) -> Throw {
+    let per_check = chance.powf(0.25);
+    let passed = (0..4).take_while(|_| rng.random_bool(per_check)).count() as u32;
+    let caught = passed == 4;
+    Throw { caught, shakes: passed.min(3), fled: !caught && rng.random_bool(FLEE_CHANCE) }
+}
+
+// pick a slot by weight, then a level in its range and roll for shininess and IVs
+pub fn generate(slots: &[EncounterSlot], rng: &mut impl Rng) -> Pokemon {
+    let total: u32 = slots.iter().map(|s| s.weight).sum();
+    let mut roll = rng.random_range(0..total);
+    let slot = slots.iter()
+        .find(|s| {
+            if roll < s.weight {
+                return true;
+            }
+            roll -= s.weight;
+            false
+        })
+        .expect("roll is below the total weight");
+
+    let species = species::find_or_unknown(&slot.species);
+    let (primary, secondary) = species.types;
+    let level = rng.random_range(slot.min_level..=slot.max_level);
+    let mut pokemon = Pokemon::new(0, species.name.to_string(), primary.to_string(), secondary.map(str::to_string), level);
+    pokemon.shiny = rng.random_ratio(1, SHINY_ODDS);
+    pokemon.ivs = species::random_ivs(rng);
+    pokemon.refresh_stats();
+    pokemon
+}
+
+// a new wild Pokemon in region/area, kanto grass unless given
+pub fn appear(state: &SharedState, query: EncounterQuery) -> Result<WildEncounter, ApiError> {
+    let region = query.region.unwrap_or_else(|| "kanto".to_string()).to_lowercase();
+    let area = query.area.unwrap_or_else(|| "grass".to_string()).to_lowercase();
+    let slots = state.encounter_table.slots(&region, &area)
+        .ok_or_else(|| ApiError::not_found(format!("no wild pokemon in {} {}", region, area)))?;
+    let mut pokemon = generate(slots, &mut state.randomness.rng());
+    pokemon.capture.captured_at = state.time.now();
+
+    let mut encounters = state.encounters.lock().unwrap();
+    let new_id = encounters.last().map_or(1, |e| e.id + 1);
+    let encounter = WildEncounter { id: new_id, region, area, status: EncounterStatus::Active, pokemon };
+    encounters.push(encounter.clone());
+    Ok(encounter)
+}
+
+// a catch joins the trainer's team, or their box once the team is full, a miss might make it flee
+pub fn catch(state: &SharedState, id: u32, payload: &CatchPokemon) -> Result<CatchResult, ApiError> {
+    let mut encounters = state.encounters.lock().unwrap();
+    let encounter = encounters.iter_mut().find(|e| e.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("encounter {} not found", id)))?;
+    if encounter.status != EncounterStatus::Active {
+        return Err(ApiError::conflict(format!("the wild {} is gone", encounter.pokemon.name)));
+    }
+
+    let mut team = state.team.lock().unwrap();
+    let mut trainers = state.trainers.lock().unwrap();
+    let trainer = trainers.iter_mut().find(|t| t.id == payload.trainer_id)
+        .ok_or_else(|| ApiError::not_found(format!("trainer {} not found", payload.trainer_id)))?;
+    state.quotas.check_receive(trainer)?;
+    // the ball is used up whether it holds or not
+    match trainer.bag.get_mut(&payload.ball) {
+        Some(left) if *left > 0 => *left -= 1,
+        _ => return Err(ApiError::conflict(format!("{} has no {:?} Balls left", trainer.name, payload.ball))),
+    }
+
+    let catch_chance = catch_chance(&encounter.pokemon, payload.ball);
+    let throw = throw(catch_chance, &mut state.randomness.rng());
+    if throw.caught {
+        encounter.pokemon.id = team.last().map_or(1, |p| p.id + 1);
+        encounter.pokemon.capture = Capture {
+            location: Some(format!("{}/{}", encounter.region, encounter.area)),
+            ball: Some(payload.ball),
+            ..Capture::by(trainer).at(state.time.now())
+        };
+        encounter.status = EncounterStatus::Caught;
+        team.push(encounter.pokemon.clone());
+        state.quotas.receive(trainer, encounter.pokemon.id);
+        achievements::record(state, trainer.id, Event::Caught(&encounter.pokemon.species));
+        notify::new_pokemon(state, &encounter.pokemon, "caught");
+    } else if throw.fled {
+        encounter.status = EncounterStatus::Fled;
+    }
+
+    Ok(CatchResult {
+        caught: throw.caught,
+        shakes: throw.shakes,
+        fled: throw.fled,
+        catch_chance,
+        encounter: encounter.clone(),
+        trainer: trainer.clone(),
+    })
+}
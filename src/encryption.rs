@@ -0,0 +1,141 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use sha2::{Digest, Sha256};
+
+use crate::sharing::hex;
+
+// what an encrypted file starts with: these, the ID of the key it's encrypted with, then the nonce
+const MAGIC: &[u8] = b"RHE1";
+const KEY_ID_LEN: usize = 8;
+// how many bytes key_id needs to see
+pub const HEADER_LEN: usize = MAGIC.len() + KEY_ID_LEN + NONCE_LEN;
+
+// 32 bytes for AES-256-GCM, given as 64 hex characters, e.g. from `openssl rand -hex 32`
+#[derive(Clone)]
+pub struct Key{
+    // the start of its SHA-256, so a file says which key it needs without giving the key away
+    pub id: String,
+    bytes: [u8; 32],
+}
+
+// never the key itself, it'd end up in logs
+impl fmt::Debug for Key{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Key({})", self.id)
+    }
+}
+
+impl Key{
+    pub fn parse(text: &str) -> Result<Key, String> {
+        let text = text.trim();
+        let bytes: Option<Vec<u8>> = (0..text.len()).step_by(2)
+            .map(|i| text.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+            .collect();
+        let bytes: [u8; 32] = bytes.and_then(|b| b.try_into().ok())
+            .ok_or("a key is 64 hex characters, `openssl rand -hex 32` makes one")?;
+        let id = hex(&Sha256::digest(bytes))[..KEY_ID_LEN].to_string();
+        Ok(Key { id, bytes })
+    }
+
+    fn aead(&self) -> LessSafeKey {
+        LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &self.bytes).expect("32 bytes is an AES-256 key"))
+    }
+}
+
+// the first key encrypts, all of them decrypt, so files from before a rotation can still be read
+#[derive(Debug, Clone)]
+pub struct Keyring{
+    keys: Vec<Key>,
+}
+
+impl Keyring{
+    // one key per line or separated by commas, newest first, # comments and blank lines are skipped
+    pub fn parse(text: &str) -> Result<Keyring, String> {
+        let keys = text.lines()
+            .map(|line| line.split('#').next().unwrap_or_default())
+            .flat_map(|line| line.split(','))
+            .filter(|key| !key.trim().is_empty())
+            .map(Key::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.is_empty() {
+            return Err("there are no keys in it".to_string());
+        }
+        Ok(Keyring { keys })
+    }
+
+    pub fn current(&self) -> &Key {
+        &self.keys[0]
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> Vec<u8> {
+        let key = self.current();
+        let nonce = rand::random::<[u8; NONCE_LEN]>();
+        let mut header = MAGIC.to_vec();
+        header.extend_from_slice(key.id.as_bytes());
+        header.extend_from_slice(&nonce);
+        let mut sealed = plaintext.to_vec();
+        // the header is authenticated too, so the key ID in it can't be swapped
+        key.aead().seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(&header), &mut sealed)
+            .expect("AES-GCM can seal anything under 64GB");
+        header.append(&mut sealed);
+        header
+    }
+
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        let id = key_id(data).ok_or("it isn't encrypted")?;
+        let key = self.keys.iter().find(|k| k.id == id)
+            .ok_or_else(|| format!("it's encrypted with key {}, which isn't one of the keys", id))?;
+        let (header, sealed) = data.split_at(HEADER_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(&header[MAGIC.len() + KEY_ID_LEN..]).expect("the header ends with a nonce");
+        let mut opened = sealed.to_vec();
+        let plaintext = key.aead().open_in_place(nonce, Aad::from(header), &mut opened)
+            .map_err(|_| format!("it doesn't decrypt with key {}, the file has been changed or cut short", id))?;
+        Ok(plaintext.to_vec())
+    }
+}
+
+// the key an encrypted file needs, None when it isn't one
+pub fn key_id(data: &[u8]) -> Option<String> {
+    if data.len() < HEADER_LEN || !data.starts_with(MAGIC) {
+        return None;
+    }
+    let id = std::str::from_utf8(&data[MAGIC.len()..MAGIC.len() + KEY_ID_LEN]).ok()?;
+    Some(id.to_string())
+}
+
+// SNAPSHOT_KEYS are fixed for as long as the server runs
+// SNAPSHOT_KEY_FILE is read again whenever it's needed, so a rotation doesn't need a restart
+#[derive(Debug, Clone)]
+pub enum Keys{
+    Fixed(Keyring),
+    File(PathBuf),
+}
+
+impl Keys{
+    // SNAPSHOT_KEYS or SNAPSHOT_KEY_FILE, not both
+    pub fn from_env() -> Result<Option<Keys>, String> {
+        match (crate::secrets::var("SNAPSHOT_KEYS"), std::env::var("SNAPSHOT_KEY_FILE")) {
+            (Ok(_), Ok(_)) => Err("set SNAPSHOT_KEYS or SNAPSHOT_KEY_FILE, not both".to_string()),
+            (Ok(keys), _) => Keyring::parse(&keys).map(|k| Some(Keys::Fixed(k))).map_err(|e| format!("SNAPSHOT_KEYS: {}", e)),
+            (_, Ok(file)) => {
+                // a bad file stops the server now rather than at the first snapshot
+                let text = std::fs::read_to_string(&file).map_err(|e| format!("SNAPSHOT_KEY_FILE: {}", e))?;
+                Keyring::parse(&text).map_err(|e| format!("SNAPSHOT_KEY_FILE: {}", e))?;
+                Ok(Some(Keys::File(PathBuf::from(file))))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    pub async fn load(&self) -> Result<Keyring, String> {
+        match self {
+            Keys::Fixed(keyring) => Ok(keyring.clone()),
+            Keys::File(path) => {
+                let text = tokio::fs::read_to_string(path).await.map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+                Keyring::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+            }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::{json, Map, Value};
+
+// error with a message for the client, rendered as {"error": "..."}
+#[derive(Debug)]
+pub struct ApiError{
+    pub status: StatusCode,
+    pub message: String,
+    // extra fields next to "error", e.g. the usage and limit of a quota
+    pub details: Map<String, Value>,
+}
+
+impl ApiError{
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        ApiError { status, message: message.into(), details: Map::new() }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::FORBIDDEN, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, message)
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::CONFLICT, message)
+    }
+
+    // request was understood but the game rules don't allow it
+    pub fn unprocessable(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNPROCESSABLE_ENTITY, message)
+    }
+
+    // anything but an object is ignored
+    pub fn with_details(mut self, details: Value) -> Self {
+        if let Value::Object(details) = details {
+            self.details.extend(details);
+        }
+        self
+    }
+}
+
+// the message of a 5xx, left on the response for error reporting to pick up
+#[derive(Debug, Clone)]
+pub struct ServerError(pub String);
+
+// lets handlers keep using `?` on plain status codes
+impl From<StatusCode> for ApiError{
+    fn from(status: StatusCode) -> Self {
+        let message = status.canonical_reason().unwrap_or("error");
+        ApiError::new(status, message)
+    }
+}
+
+impl IntoResponse for ApiError{
+    fn into_response(self) -> Response {
+        let mut body = Map::from_iter([("error".to_string(), json!(self.message))]);
+        body.extend(self.details);
+        let server_error = self.status.is_server_error().then_some(ServerError(self.message));
+        let mut response = (self.status, Json(Value::Object(body))).into_response();
+        if let Some(server_error) = server_error {
+            response.extensions_mut().insert(server_error);
+        }
+        response
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use axum::extract::{MatchedPath, Request, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+
+use crate::error::ApiError;
+use crate::state::SharedState;
+
+// a group of experimental endpoints that can be turned off without a new build
+#[derive(Debug)]
+pub struct Flag{
+    pub name: &'static str,
+    pub description: &'static str,
+    // routes as they're declared, each one and everything under it
+    pub routes: &'static [&'static str],
+}
+
+pub const FLAGS: &[Flag] = &[
+    Flag {
+        name: "battles",
+        description: "Battles, live battle sessions and matchmaking",
+        routes: &["/battles", "/battle-sessions", "/matchmaking"],
+    },
+    Flag {
+        name: "trading",
+        description: "Trade offers between trainers",
+        routes: &["/trades", "/trainers/{id}/trades"],
+    },
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FlagStatus{
+    pub name: &'static str,
+    pub description: &'static str,
+    pub enabled: bool,
+    pub routes: &'static [&'static str],
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetFlag{
+    pub enabled: bool,
+}
+
+// which flags are on, every one of them unless FEATURE_FLAGS says otherwise
+// switched at runtime through PUT /admin/flags/{name}, and back to FEATURE_FLAGS on restart
+#[derive(Debug)]
+pub struct Flags{
+    enabled: Mutex<BTreeMap<&'static str, bool>>,
+}
+
+impl Default for Flags{
+    // all on
+    fn default() -> Self {
+        Flags { enabled: Mutex::new(FLAGS.iter().map(|f| (f.name, true)).collect()) }
+    }
+}
+
+fn known(name: &str) -> Result<&'static Flag, String> {
+    FLAGS.iter().find(|f| f.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("unknown feature flag {:?}, it's one of {}", name, FLAGS.iter().map(|f| f.name).collect::<Vec<_>>().join(", ")))
+}
+
+impl Flags{
+    // e.g. "battles=off,trading=on", anything left out stays on
+    pub fn parse(spec: &str) -> Result<Flags, String> {
+        let flags = Flags::default();
+        for pair in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (name, value) = pair.split_once('=')
+                .ok_or_else(|| format!("FEATURE_FLAGS entries look like battles=off, not {:?}", pair))?;
+            let enabled = match value.trim() {
+                "on" => true,
+                "off" => false,
+                other => return Err(format!("feature flags are on or off, not {:?}", other)),
+            };
+            flags.set(name.trim(), enabled)?;
+        }
+        Ok(flags)
+    }
+
+    // FEATURE_FLAGS
+    pub fn from_env() -> Result<Flags, String> {
+        std::env::var("FEATURE_FLAGS").map_or_else(|_| Ok(Flags::default()), |spec| Flags::parse(&spec))
+    }
+
+    pub fn set(&self, name: &str, enabled: bool) -> Result<FlagStatus, String> {
+        let flag = known(name)?;
+        self.enabled.lock().unwrap().insert(flag.name, enabled);
+        Ok(FlagStatus { name: flag.name, description: flag.description, enabled, routes: flag.routes })
+    }
+
+    pub fn statuses(&self) -> Vec<FlagStatus> {
+        let enabled = self.enabled.lock().unwrap();
+        FLAGS.iter()
+            .map(|f| FlagStatus { name: f.name, description: f.description, enabled: enabled[f.name], routes: f.routes })
+            .collect()
+    }
+
+    // the flag that's off and covers this route, if there is one
+    fn blocking(&self, route: &str) -> Option<&'static Flag> {
+        let enabled = self.enabled.lock().unwrap();
+        FLAGS.iter()
+            .filter(|f| !enabled[f.name])
+            .find(|f| f.routes.iter().any(|r| route == *r || route.strip_prefix(r).is_some_and(|rest| rest.starts_with('/'))))
+    }
+}
+
+// middleware for the whole app, a route behind a flag that's off is a 404 like one that was never built
+pub async fn enforce(State(state): State<SharedState>, request: Request, next: Next) -> Response {
+    let flag = request.extensions().get::<MatchedPath>().and_then(|path| state.flags.blocking(path.as_str()));
+    match flag {
+        Some(flag) => ApiError::not_found(format!("{} is turned off on this server", flag.description.to_lowercase())).into_response(),
+        None => next.run(request).await,
+    }
+}
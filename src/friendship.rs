@@ -0,0 +1,22 @@
+use std::time::Duration;
+
+use crate::state::SharedState;
+
+pub use pokemon_models::friendship::*;
+
+const TIME_ON_TEAM: i32 = 1;
+
+// Pokemon on a trainer's team slowly warm up to them, like walking around together
+pub const TICK_INTERVAL: Duration = Duration::from_secs(60);
+
+pub fn tick(state: &SharedState) {
+    let on_teams: Vec<u32> = state.trainers.lock().unwrap().iter()
+        .flat_map(|t| t.team.iter().copied())
+        .collect();
+    let mut team = state.team.lock().unwrap();
+    for pokemon in team.iter_mut().filter(|p| on_teams.contains(&p.id) && !p.fainted) {
+        pokemon.change_friendship(TIME_ON_TEAM);
+    }
+    drop(team);
+    state.responses.invalidate("pokemon", None);
+}
@@ -0,0 +1,100 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Serialize;
+
+use crate::encounters::SHINY_ODDS;
+use crate::models::{Capture, Pokemon};
+use crate::species::{self, EvolutionTrigger, Species, SPECIES};
+use crate::state::SharedState;
+
+// every generated Pokemon gets this tag, so they're easy to find and clean up after a load test
+pub const TAG: &str = "generated";
+
+pub const DEFAULT_COUNT: usize = 100;
+pub const MAX_COUNT: usize = 100_000;
+
+const PROGRESS_EVERY: usize = 1000;
+
+// lowest level a first-stage Pokemon shows up at
+const MIN_LEVEL: u32 = 2;
+
+// evolutions that don't need a level still usually happen a bit after the previous stage is around
+const NON_LEVEL_EVOLUTION_GAP: u32 = 5;
+
+#[derive(Debug, Serialize)]
+pub struct Generated{
+    pub created: usize,
+    // None when count was 0
+    pub first_id: Option<u32>,
+    pub last_id: Option<u32>,
+    // pass it back as ?seed= for the same Pokemon again
+    pub seed: u64,
+}
+
+// the lowest level a species can plausibly be at, e.g. 36 for a Charizard
+fn min_level(species: &'static Species) -> u32 {
+    let previous = SPECIES.iter()
+        .find_map(|s| s.evolutions.iter().find(|e| e.into == species.name).map(|e| (s, e.trigger)));
+    match previous {
+        None => MIN_LEVEL,
+        Some((previous, EvolutionTrigger::Level(level))) => u32::max(level, min_level(previous)),
+        Some((previous, _)) => min_level(previous) + NON_LEVEL_EVOLUTION_GAP,
+    }
+}
+
+// common species are common: the easier something is to catch, the more often it's picked
+fn pick_species(rng: &mut impl Rng) -> &'static Species {
+    let total: u32 = SPECIES.iter().map(|s| s.catch_rate).sum();
+    let mut roll = rng.random_range(0..total);
+    SPECIES.iter()
+        .find(|s| {
+            if roll < s.catch_rate {
+                return true;
+            }
+            roll -= s.catch_rate;
+            false
+        })
+        .expect("roll is below the total weight")
+}
+
+// anywhere from its minimum up to 100, squared so most of them end up near the bottom
+fn pick_level(species: &'static Species, rng: &mut impl Rng) -> u32 {
+    let min = min_level(species).min(species::MAX_LEVEL);
+    let spread = (species::MAX_LEVEL - min) as f64;
+    min + (rng.random::<f64>().powi(2) * spread).round() as u32
+}
+
+pub fn random_pokemon(id: u32, rng: &mut impl Rng) -> Pokemon {
+    let species = pick_species(rng);
+    let (primary, secondary) = species.types;
+    let level = pick_level(species, rng);
+    let mut pokemon = Pokemon::new(id, species.name.to_string(), primary.to_string(), secondary.map(str::to_string), level);
+    pokemon.shiny = rng.random_ratio(1, SHINY_ODDS);
+    pokemon.ivs = species::random_ivs(rng);
+    pokemon.refresh_stats();
+    pokemon.current_hp = pokemon.max_hp;
+    pokemon.friendship = rng.random_range(0..=255);
+    pokemon.tags = vec![TAG.to_string()];
+    pokemon.capture = Capture { location: Some("generated".to_string()), ..Capture::now() };
+    pokemon
+}
+
+// adds `count` random Pokemon owned by nobody, IDs carry on after the current last one
+// the same seed always gives the same Pokemon, whatever's already there
+// progress hears how many are done every PROGRESS_EVERY, for a background job to pass on
+pub fn generate(state: &SharedState, count: usize, seed: u64, mut progress: impl FnMut(usize)) -> Generated {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut team = state.team.lock().unwrap();
+    team.reserve(count);
+    let first_id = team.last().map_or(1, |p| p.id + 1);
+    let now = state.time.now();
+    for (done, id) in (first_id..first_id + count as u32).enumerate() {
+        let mut pokemon = random_pokemon(id, &mut rng);
+        pokemon.capture.captured_at = now;
+        team.push(pokemon);
+        if (done + 1) % PROGRESS_EVERY == 0 {
+            progress(done + 1);
+        }
+    }
+    let ids = (count > 0).then(|| (first_id, first_id + count as u32 - 1));
+    Generated { created: count, first_id: ids.map(|(first, _)| first), last_id: ids.map(|(_, last)| last), seed }
+}
@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::http::header::{self, HeaderName, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+
+// the pages at /web style themselves inline, and sprites can be anywhere on https
+pub const DEFAULT_CSP: &str =
+    "default-src 'self'; img-src 'self' data: https:; style-src 'self' 'unsafe-inline'; frame-ancestors 'none'; base-uri 'self'; form-action 'self'";
+
+// a year, once a browser has seen it over https
+pub const DEFAULT_HSTS_MAX_AGE: u64 = 365 * 24 * 60 * 60;
+
+#[derive(Debug, Clone)]
+pub struct SecurityHeaders{
+    on: bool,
+    // only on HTML responses, the JSON API doesn't have anything for it to restrict
+    csp: Option<HeaderValue>,
+    // Strict-Transport-Security, only when the server is reached over https
+    hsts: Option<HeaderValue>,
+}
+
+impl Default for SecurityHeaders{
+    // on, without HSTS since nothing says there's TLS in front of it
+    fn default() -> Self {
+        SecurityHeaders { on: true, csp: Some(HeaderValue::from_static(DEFAULT_CSP)), hsts: None }
+    }
+}
+
+impl SecurityHeaders{
+    // nothing is added at all
+    pub fn off() -> SecurityHeaders {
+        SecurityHeaders { on: false, csp: None, hsts: None }
+    }
+
+    // SECURITY_HEADERS=off turns them all off, CONTENT_SECURITY_POLICY replaces the default one ("" for none)
+    // HSTS goes out when PUBLIC_URL is https, for HSTS_MAX_AGE seconds (0 for none)
+    pub fn from_env() -> Result<SecurityHeaders, String> {
+        if std::env::var("SECURITY_HEADERS").is_ok_and(|v| matches!(v.to_lowercase().as_str(), "off" | "false" | "0")) {
+            return Ok(SecurityHeaders::off());
+        }
+        let mut headers = SecurityHeaders::default();
+        if let Ok(csp) = std::env::var("CONTENT_SECURITY_POLICY") {
+            headers = headers.csp(Some(&csp).filter(|c| !c.is_empty()).map(String::as_str))?;
+        }
+        if std::env::var("PUBLIC_URL").is_ok_and(|url| url.starts_with("https://")) {
+            let max_age = match std::env::var("HSTS_MAX_AGE") {
+                Ok(secs) => secs.parse().map_err(|_| format!("HSTS_MAX_AGE should be a number of seconds, not {:?}", secs))?,
+                Err(_) => DEFAULT_HSTS_MAX_AGE,
+            };
+            headers = headers.hsts((max_age > 0).then_some(max_age));
+        }
+        Ok(headers)
+    }
+
+    pub fn csp(mut self, policy: Option<&str>) -> Result<SecurityHeaders, String> {
+        self.csp = policy
+            .map(|p| HeaderValue::from_str(p).map_err(|_| format!("{:?} can't go in a Content-Security-Policy header", p)))
+            .transpose()?;
+        Ok(self)
+    }
+
+    // seconds, None to leave it out
+    pub fn hsts(mut self, max_age: Option<u64>) -> SecurityHeaders {
+        self.hsts = max_age.map(|secs| {
+            HeaderValue::from_str(&format!("max-age={}; includeSubDomains", secs)).expect("a number makes a valid header")
+        });
+        self
+    }
+}
+
+// fills in whatever the handler didn't set itself
+pub async fn add(State(headers): State<Arc<SecurityHeaders>>, request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+    if !headers.on {
+        return response;
+    }
+    let html = response.headers().get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/html"));
+    let fixed = [
+        (header::X_CONTENT_TYPE_OPTIONS, Some(HeaderValue::from_static("nosniff"))),
+        (header::X_FRAME_OPTIONS, Some(HeaderValue::from_static("DENY"))),
+        (header::REFERRER_POLICY, Some(HeaderValue::from_static("strict-origin-when-cross-origin"))),
+        (header::STRICT_TRANSPORT_SECURITY, headers.hsts.clone()),
+        (header::CONTENT_SECURITY_POLICY, headers.csp.clone().filter(|_| html)),
+    ];
+    let response_headers = response.headers_mut();
+    for (name, value) in fixed {
+        if let Some(value) = value {
+            response_headers.entry::<HeaderName>(name).or_insert(value);
+        }
+    }
+    response
+}
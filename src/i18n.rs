@@ -0,0 +1,239 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use serde_json::Value;
+
+// languages there are translations for, English is what the code is written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang{
+    En,
+    Ja,
+    Fr,
+    De,
+}
+
+impl Lang{
+    fn parse(tag: &str) -> Option<Lang> {
+        match tag {
+            "en" => Some(Lang::En),
+            "ja" => Some(Lang::Ja),
+            "fr" => Some(Lang::Fr),
+            "de" => Some(Lang::De),
+            _ => None,
+        }
+    }
+
+    fn code(self) -> &'static str {
+        match self {
+            Lang::En => "en",
+            Lang::Ja => "ja",
+            Lang::Fr => "fr",
+            Lang::De => "de",
+        }
+    }
+
+    // column in the tables below
+    fn index(self) -> Option<usize> {
+        match self {
+            Lang::En => None,
+            Lang::Ja => Some(0),
+            Lang::Fr => Some(1),
+            Lang::De => Some(2),
+        }
+    }
+
+    // the best language the client asked for, e.g. "fr-CA, de;q=0.8, *;q=0.1"
+    // goes by q-value, each tag falls back to its base language (fr-CA -> fr), and anything we don't have -> English
+    pub fn from_accept_language(header: &str) -> Lang {
+        let mut wanted: Vec<(f32, &str)> = header.split(',')
+            .filter_map(|part| {
+                let mut pieces = part.trim().split(';');
+                let tag = pieces.next()?.trim();
+                let q = pieces
+                    .find_map(|p| p.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.parse().ok())?;
+                (!tag.is_empty() && q > 0.0).then_some((q, tag))
+            })
+            .collect();
+        // stable, so equal q-values keep the client's order
+        wanted.sort_by(|a, b| b.0.total_cmp(&a.0));
+        wanted.iter()
+            .find_map(|(_, tag)| {
+                let tag = tag.to_ascii_lowercase();
+                Lang::parse(&tag).or_else(|| Lang::parse(tag.split('-').next()?))
+            })
+            .unwrap_or(Lang::En)
+    }
+}
+
+// English species name -> Japanese, French, German
+const SPECIES: &[(&str, [&str; 3])] = &[
+    ("Bulbasaur", ["フシギダネ", "Bulbizarre", "Bisasam"]),
+    ("Ivysaur", ["フシギソウ", "Herbizarre", "Bisaknosp"]),
+    ("Venusaur", ["フシギバナ", "Florizarre", "Bisaflor"]),
+    ("Charmander", ["ヒトカゲ", "Salamèche", "Glumanda"]),
+    ("Charmeleon", ["リザード", "Reptincel", "Glutexo"]),
+    ("Charizard", ["リザードン", "Dracaufeu", "Glurak"]),
+    ("Squirtle", ["ゼニガメ", "Carapuce", "Schiggy"]),
+    ("Wartortle", ["カメール", "Carabaffe", "Schillok"]),
+    ("Blastoise", ["カメックス", "Tortank", "Turtok"]),
+    ("Caterpie", ["キャタピー", "Chenipan", "Raupy"]),
+    ("Metapod", ["トランセル", "Chrysacier", "Safcon"]),
+    ("Butterfree", ["バタフリー", "Papilusion", "Smettbo"]),
+    ("Pidgey", ["ポッポ", "Roucool", "Taubsi"]),
+    ("Pidgeotto", ["ピジョン", "Roucoups", "Tauboga"]),
+    ("Pidgeot", ["ピジョット", "Roucarnage", "Tauboss"]),
+    ("Rattata", ["コラッタ", "Rattata", "Rattfratz"]),
+    ("Raticate", ["ラッタ", "Rattatac", "Rattikarl"]),
+    ("Pichu", ["ピチュー", "Pichu", "Pichu"]),
+    ("Pikachu", ["ピカチュウ", "Pikachu", "Pikachu"]),
+    ("Raichu", ["ライチュウ", "Raichu", "Raichu"]),
+    ("Igglybuff", ["ププリン", "Toudoudou", "Fluffeluff"]),
+    ("Jigglypuff", ["プリン", "Rondoudou", "Pummeluff"]),
+    ("Wigglytuff", ["プクリン", "Grodoudou", "Knuddeluff"]),
+    ("Abra", ["ケーシィ", "Abra", "Abra"]),
+    ("Kadabra", ["ユンゲラー", "Kadabra", "Kadabra"]),
+    ("Alakazam", ["フーディン", "Alakazam", "Simsala"]),
+    ("Machop", ["ワンリキー", "Machoc", "Machollo"]),
+    ("Machoke", ["ゴーリキー", "Machopeur", "Maschock"]),
+    ("Machamp", ["カイリキー", "Mackogneur", "Machomei"]),
+    ("Geodude", ["イシツブテ", "Racaillou", "Kleinstein"]),
+    ("Graveler", ["ゴローン", "Gravalanch", "Georok"]),
+    ("Golem", ["ゴローニャ", "Grolem", "Geowaz"]),
+    ("Gastly", ["ゴース", "Fantominus", "Nebulak"]),
+    ("Haunter", ["ゴースト", "Spectrum", "Alpollo"]),
+    ("Gengar", ["ゲンガー", "Ectoplasma", "Gengar"]),
+    ("Onix", ["イワーク", "Onix", "Onix"]),
+    ("Magikarp", ["コイキング", "Magicarpe", "Karpador"]),
+    ("Gyarados", ["ギャラドス", "Léviator", "Garados"]),
+    ("Eevee", ["イーブイ", "Évoli", "Evoli"]),
+    ("Vaporeon", ["シャワーズ", "Aquali", "Aquana"]),
+    ("Jolteon", ["サンダース", "Voltali", "Blitza"]),
+    ("Flareon", ["ブースター", "Pyroli", "Flamara"]),
+    ("Espeon", ["エーフィ", "Mentali", "Psiana"]),
+    ("Umbreon", ["ブラッキー", "Noctali", "Nachtara"]),
+    ("Snorlax", ["カビゴン", "Ronflex", "Relaxo"]),
+    ("Articuno", ["フリーザー", "Artikodin", "Arktos"]),
+    ("Zapdos", ["サンダー", "Électhor", "Zapdos"]),
+    ("Moltres", ["ファイヤー", "Sulfura", "Lavados"]),
+    ("Dratini", ["ミニリュウ", "Minidraco", "Dratini"]),
+    ("Dragonair", ["ハクリュー", "Draco", "Dragonir"]),
+    ("Dragonite", ["カイリュー", "Dracolosse", "Dragoran"]),
+    ("Mewtwo", ["ミュウツー", "Mewtwo", "Mewtu"]),
+    ("Mew", ["ミュウ", "Mew", "Mew"]),
+];
+
+// the most common error messages, {} is filled in from the English message
+const MESSAGES: &[(&str, [&str; 3])] = &[
+    ("pokemon {} not found", ["ポケモン {} が見つかりません", "pokémon {} introuvable", "Pokémon {} nicht gefunden"]),
+    ("trainer {} not found", ["トレーナー {} が見つかりません", "dresseur {} introuvable", "Trainer {} nicht gefunden"]),
+    ("gym {} not found", ["ジム {} が見つかりません", "arène {} introuvable", "Arena {} nicht gefunden"]),
+    ("tournament {} not found", ["大会 {} が見つかりません", "tournoi {} introuvable", "Turnier {} nicht gefunden"]),
+    ("{} has fainted and can't gain exp", ["{} はひんしのため経験値をもらえません", "{} est K.O. et ne peut pas gagner d'expérience", "{} ist besiegt und kann keine Erfahrung sammeln"]),
+    ("{} has already fainted", ["{} はすでにひんしです", "{} est déjà K.O.", "{} ist bereits besiegt"]),
+    ("{} already knows {}", ["{} はすでに {} をおぼえています", "{} connaît déjà {}", "{} beherrscht {} bereits"]),
+    ("{} can't learn {}", ["{} は {} をおぼえられません", "{} ne peut pas apprendre {}", "{} kann {} nicht erlernen"]),
+    ("no pokemon match", ["条件に合うポケモンがいません", "aucun pokémon ne correspond", "kein Pokémon passt"]),
+    ("that share link has expired", ["この共有リンクは期限切れです", "ce lien de partage a expiré", "dieser Freigabelink ist abgelaufen"]),
+    ("that share link isn't valid", ["この共有リンクは無効です", "ce lien de partage n'est pas valide", "dieser Freigabelink ist ungültig"]),
+    ("pick a different nickname", ["別のニックネームを選んでください", "choisissez un autre surnom", "wähle einen anderen Spitznamen"]),
+    ("Bad Request", ["不正なリクエスト", "Requête invalide", "Ungültige Anfrage"]),
+    ("Not Found", ["見つかりません", "Introuvable", "Nicht gefunden"]),
+];
+
+// the English name if there's no translation
+pub fn species_name(lang: Lang, english: &str) -> &str {
+    let Some(index) = lang.index() else { return english };
+    SPECIES.iter()
+        .find(|(en, _)| *en == english)
+        .map_or(english, |(_, names)| names[index])
+}
+
+// the {} parts of `message` if it has the template's shape, e.g. "pokemon 7 not found" -> ["7"]
+fn fill_ins<'a>(template: &str, message: &'a str) -> Option<Vec<&'a str>> {
+    let mut parts = template.split("{}");
+    let mut rest = message.strip_prefix(parts.next()?)?;
+    let mut values = Vec::new();
+    for part in parts {
+        let end = if part.is_empty() { rest.len() } else { rest.find(part)? };
+        values.push(&rest[..end]);
+        rest = &rest[end + part.len()..];
+    }
+    rest.is_empty().then_some(values)
+}
+
+// English if the message isn't in the table, species names in it are translated too
+pub fn message(lang: Lang, english: &str) -> String {
+    let Some(index) = lang.index() else { return english.to_string() };
+    for (template, translations) in MESSAGES {
+        let Some(values) = fill_ins(template, english) else { continue };
+        let mut translated = translations[index].to_string();
+        for value in values {
+            translated = translated.replacen("{}", species_name(lang, value), 1);
+        }
+        return translated;
+    }
+    english.to_string()
+}
+
+// anything that looks like a Pokemon gets a translated species_name, and its name too if it isn't a nickname
+// "species" itself stays English since that's what requests send back
+fn localize(lang: Lang, value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            if let Some(Value::String(species)) = fields.get("species") {
+                let translated = species_name(lang, species).to_string();
+                if fields.get("nickname").is_none_or(Value::is_null) {
+                    fields.insert("name".to_string(), Value::String(translated.clone()));
+                }
+                fields.insert("species_name".to_string(), Value::String(translated));
+            }
+            if let Some(Value::String(error)) = fields.get("error") {
+                let translated = message(lang, error);
+                fields.insert("error".to_string(), Value::String(translated));
+            }
+            fields.values_mut().for_each(|v| localize(lang, v));
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| localize(lang, v)),
+        _ => {}
+    }
+}
+
+// middleware that rewrites JSON responses into the language from Accept-Language
+// admin dumps are left alone, they have to come back in exactly as they went out
+pub async fn localize_responses(request: Request, next: Next) -> Response {
+    if request.uri().path().starts_with("/admin/") {
+        return next.run(request).await;
+    }
+    let lang = request.headers().get(header::ACCEPT_LANGUAGE)
+        .and_then(|v| v.to_str().ok())
+        .map_or(Lang::En, Lang::from_accept_language);
+    let mut response = next.run(request).await;
+    response.headers_mut().append(header::VARY, HeaderValue::from_static("accept-language"));
+
+    let is_json = response.headers().get(header::CONTENT_TYPE)
+        .is_some_and(|v| v.as_bytes().starts_with(b"application/json"));
+    if !is_json {
+        return response;
+    }
+    response.headers_mut().insert(header::CONTENT_LANGUAGE, HeaderValue::from_static(lang.code()));
+    if lang == Lang::En {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = to_bytes(body, usize::MAX).await else {
+        return Response::from_parts(parts, Body::empty());
+    };
+    let Ok(mut value) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    localize(lang, &mut value);
+    let localized = serde_json::to_vec(&value).unwrap_or_else(|_| bytes.to_vec());
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(localized))
+}
@@ -0,0 +1,83 @@
+use std::collections::BTreeSet;
+
+use crate::battle::{CombatantResult, Side};
+use crate::models::{BattleRecord, Notification, NotificationKind, Trade};
+use crate::state::AppState;
+
+// per trainer, past this the oldest ones go
+pub const MAX_PER_TRAINER: usize = 100;
+
+fn notification(trainer_id: u32, kind: NotificationKind, message: String) -> Notification {
+    Notification {
+        id: 0,
+        trainer_id,
+        kind,
+        message,
+        trade_id: None,
+        battle_id: None,
+        pokemon_id: None,
+        // stamped by push
+        created_at: 0,
+        read: false,
+    }
+}
+
+// takes the inbox lock, which comes after every other one
+fn push(state: &AppState, mut notification: Notification) {
+    let mut inbox = state.inbox.lock().unwrap();
+    notification.id = inbox.last().map_or(1, |n| n.id + 1);
+    notification.created_at = state.time.now();
+    let trainer_id = notification.trainer_id;
+    inbox.push(notification);
+    let count = inbox.iter().filter(|n| n.trainer_id == trainer_id).count();
+    if count > MAX_PER_TRAINER {
+        let mut extra = count - MAX_PER_TRAINER;
+        inbox.retain(|n| {
+            let drop = extra > 0 && n.trainer_id == trainer_id;
+            if drop {
+                extra -= 1;
+            }
+            !drop
+        });
+    }
+}
+
+// to the trainer who was offered it
+pub fn trade_offer(state: &AppState, trade: &Trade, from_name: &str) {
+    let message = format!("{} offered you Pokemon {} for your Pokemon {}", from_name, trade.offered, trade.requested);
+    push(state, Notification { trade_id: Some(trade.id), ..notification(trade.to_trainer, NotificationKind::TradeOffer, message) });
+}
+
+// to everyone who owns a Pokemon that fought, gym leaders' don't belong to anyone
+pub fn battle_result(state: &AppState, record: &BattleRecord) {
+    let (side_a, side_b) = {
+        let trainers = state.trainers.lock().unwrap();
+        let owners = |side: &[CombatantResult]| -> BTreeSet<u32> {
+            side.iter().filter_map(|c| trainers.iter().find(|t| t.owns(c.id)).map(|t| t.id)).collect()
+        };
+        (owners(&record.outcome.side_a), owners(&record.outcome.side_b))
+    };
+    let (id, turns) = (record.id, record.outcome.turns);
+    for trainer_id in side_a.union(&side_b) {
+        let side = match (side_a.contains(trainer_id), side_b.contains(trainer_id)) {
+            (true, false) => Some(Side::SideA),
+            (false, true) => Some(Side::SideB),
+            _ => None,
+        };
+        let message = match (record.outcome.winner, side) {
+            (None, _) => format!("battle {} was a draw after {} turns", id, turns),
+            // their Pokemon were on both sides
+            (Some(winner), None) => format!("battle {} between your own Pokemon is over, {} won in {} turns", id, winner.label(), turns),
+            (Some(winner), Some(side)) if winner == side => format!("you won battle {} in {} turns", id, turns),
+            (Some(_), Some(_)) => format!("you lost battle {} after {} turns", id, turns),
+        };
+        push(state, Notification { battle_id: Some(id), ..notification(*trainer_id, NotificationKind::BattleResult, message) });
+    }
+}
+
+// a Pokemon that grew at the daycare, to whoever owns it
+pub fn level_up(state: &AppState, pokemon_id: u32, name: &str, level: u32) {
+    let Some(owner) = state.trainers.lock().unwrap().iter().find(|t| t.owns(pokemon_id)).map(|t| t.id) else { return };
+    let message = format!("{} grew to level {} at the daycare", name, level);
+    push(state, Notification { pokemon_id: Some(pokemon_id), ..notification(owner, NotificationKind::LevelUp, message) });
+}
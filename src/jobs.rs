@@ -0,0 +1,154 @@
+use std::sync::Mutex;
+
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Json, Response};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::error::ApiError;
+use crate::state::SharedState;
+
+// finished jobs past this many are forgotten, oldest first
+pub const MAX_KEPT: usize = 1000;
+
+// long operations run in the background when the client asks with Prefer: respond-async
+// it gets 202 and the job straight away, and asks GET /jobs/{id} how it's going
+#[derive(Debug, Default)]
+pub struct Jobs{
+    // oldest first
+    jobs: Mutex<Vec<Job>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus{
+    // waiting for a blocking thread
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct JobProgress{
+    pub done: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Job{
+    pub id: u32,
+    // "import", "generate" or "tournament-round"
+    pub kind: &'static str,
+    pub status: JobStatus,
+    // None for the kinds that can't tell how far they've got
+    pub progress: Option<JobProgress>,
+    // the body the request would have answered with, once it's done
+    pub result: Option<Value>,
+    // the error it would have answered with and its status code, once it's failed
+    pub error: Option<Value>,
+    // unix seconds
+    pub created_at: u64,
+    pub finished_at: Option<u64>,
+    // started through the admin API, so it's only shown there
+    #[serde(skip)]
+    pub admin: bool,
+}
+
+impl Jobs{
+    pub fn get(&self, id: u32) -> Option<Job> {
+        self.jobs.lock().unwrap().iter().find(|j| j.id == id).cloned()
+    }
+
+    fn update(&self, id: u32, change: impl FnOnce(&mut Job)) {
+        if let Some(job) = self.jobs.lock().unwrap().iter_mut().find(|j| j.id == id) {
+            change(job);
+        }
+    }
+
+    fn add(&self, kind: &'static str, admin: bool, now: u64) -> Job {
+        let mut jobs = self.jobs.lock().unwrap();
+        let finished = jobs.iter().filter(|j| j.finished_at.is_some()).count();
+        if finished >= MAX_KEPT {
+            let oldest = jobs.iter().position(|j| j.finished_at.is_some()).expect("at least one is finished");
+            jobs.remove(oldest);
+        }
+        let id = jobs.last().map_or(1, |j| j.id + 1);
+        let job = Job {
+            id,
+            kind,
+            status: JobStatus::Queued,
+            progress: None,
+            result: None,
+            error: None,
+            created_at: now,
+            finished_at: None,
+            admin,
+        };
+        jobs.push(job.clone());
+        job
+    }
+}
+
+// handed to the work, so it can say how far it's got
+pub struct Progress{
+    state: SharedState,
+    id: u32,
+}
+
+impl Progress{
+    pub fn set(&self, done: usize, total: usize) {
+        self.state.jobs.update(self.id, |job| job.progress = Some(JobProgress { done, total }));
+    }
+}
+
+// Prefer: respond-async, possibly among other preferences
+pub fn wanted(headers: &HeaderMap) -> bool {
+    headers.get_all("prefer").iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|preference| preference.split(';').next().unwrap_or_default().trim().eq_ignore_ascii_case("respond-async"))
+}
+
+// runs the work on a blocking thread and answers 202 with the job, Location says where to ask after it
+pub fn start<T: Serialize>(
+    state: &SharedState,
+    kind: &'static str,
+    admin: bool,
+    work: impl FnOnce(&Progress) -> Result<T, ApiError> + Send + 'static,
+) -> Response {
+    let job = state.jobs.add(kind, admin, state.time.now());
+    let progress = Progress { state: state.clone(), id: job.id };
+    tokio::spawn(async move {
+        let (state, id) = (progress.state.clone(), progress.id);
+        let ran = tokio::task::spawn_blocking(move || {
+            progress.state.jobs.update(progress.id, |job| job.status = JobStatus::Running);
+            work(&progress).map(|result| serde_json::to_value(result).expect("results always serialize"))
+        }).await;
+        let outcome = ran.unwrap_or_else(|e| Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("that stopped halfway: {}", e))));
+        state.jobs.update(id, |job| {
+            job.finished_at = Some(state.time.now());
+            match outcome {
+                Ok(result) => {
+                    job.status = JobStatus::Done;
+                    job.result = Some(result);
+                }
+                Err(e) => {
+                    job.status = JobStatus::Failed;
+                    let mut error = json!({ "status": e.status.as_u16(), "error": e.message });
+                    error.as_object_mut().expect("an object").extend(e.details);
+                    job.error = Some(error);
+                }
+            }
+        });
+        // the reads cached while it ran can be out of date now, and it's not a request the cache would see
+        state.responses.clear();
+    });
+
+    let location = if admin { format!("/admin/jobs/{}", job.id) } else { format!("/jobs/{}", job.id) };
+    let headers = [
+        (header::LOCATION, HeaderValue::from_str(&location).expect("digits are a valid header")),
+        (header::HeaderName::from_static("preference-applied"), HeaderValue::from_static("respond-async")),
+    ];
+    (StatusCode::ACCEPTED, headers, Json(job)).into_response()
+}
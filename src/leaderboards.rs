@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::state::SharedState;
+
+// how many places each board keeps
+const TOP: usize = 10;
+
+// the boards are rebuilt in the background, so a request only copies out the top entries
+pub const TICK_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric{
+    HighestLevel,
+    BattlesWon,
+    Collection,
+}
+
+impl Metric{
+    pub fn parse(name: &str) -> Option<Metric> {
+        match name {
+            "highest_level" => Some(Metric::HighestLevel),
+            "battles_won" => Some(Metric::BattlesWon),
+            "collection" => Some(Metric::Collection),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Entry{
+    pub rank: u32,
+    // a pokemon ID on highest_level, a trainer ID on the others
+    pub id: u32,
+    pub name: String,
+    pub value: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Board{
+    pub metric: String,
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Leaderboards{
+    highest_level: Vec<Entry>,
+    battles_won: Vec<Entry>,
+    collection: Vec<Entry>,
+}
+
+impl Leaderboards{
+    pub fn get(&self, metric: Metric) -> &[Entry] {
+        match metric {
+            Metric::HighestLevel => &self.highest_level,
+            Metric::BattlesWon => &self.battles_won,
+            Metric::Collection => &self.collection,
+        }
+    }
+}
+
+// best first, ties go to the lower ID (the one that got there first)
+fn top(mut scores: Vec<(u32, String, u32)>) -> Vec<Entry> {
+    scores.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)));
+    scores.into_iter()
+        .take(TOP)
+        .enumerate()
+        .map(|(i, (id, name, value))| Entry { rank: i as u32 + 1, id, name, value })
+        .collect()
+}
+
+// battles won comes from the counters achievements already keep, the rest from the collections
+fn build(state: &SharedState) -> Leaderboards {
+    let highest_level = {
+        let team = state.team.lock().unwrap();
+        top(team.iter().map(|p| (p.id, p.name.clone(), p.level)).collect())
+    };
+    let trainers: Vec<(u32, String, u32)> = state.trainers.lock().unwrap().iter()
+        .map(|t| (t.id, t.name.clone(), (t.team.len() + t.pc_box.len()) as u32))
+        .collect();
+    let battles_won = {
+        let achievements = state.achievements.lock().unwrap();
+        let won = |id: u32| achievements.iter().find(|p| p.trainer_id == id).map_or(0, |p| p.battles_won);
+        top(trainers.iter().map(|(id, name, _)| (*id, name.clone(), won(*id))).filter(|t| t.2 > 0).collect())
+    };
+    let collection = top(trainers);
+    Leaderboards { highest_level, battles_won, collection }
+}
+
+pub fn rebuild(state: &SharedState) {
+    let boards = build(state);
+    *state.leaderboards.lock().unwrap() = boards;
+}
@@ -0,0 +1,3229 @@
+pub mod achievements;
+pub mod admin;
+pub mod auth;
+pub mod backups;
+#[cfg(feature = "telegram")]
+pub mod bot;
+pub mod analytics;
+pub mod battle;
+pub mod breeding;
+pub mod caching;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+pub mod checks;
+pub mod concurrency;
+pub mod connections;
+pub mod console;
+pub mod csrf;
+pub mod daycare;
+pub mod deterministic;
+pub mod duplicates;
+pub mod encounters;
+pub mod encryption;
+pub mod error;
+pub mod flags;
+pub mod friendship;
+pub mod generator;
+pub mod headers;
+pub mod i18n;
+pub mod inbox;
+pub mod jobs;
+pub mod leaderboards;
+pub mod mail;
+pub mod market;
+pub mod matchmaking;
+pub mod memory;
+pub mod modes;
+pub mod moves;
+pub mod nickname;
+pub mod notify;
+pub mod oidc;
+pub mod passwords;
+pub mod plugins;
+#[cfg(feature = "pprof")]
+pub mod profiling;
+pub mod pokeapi;
+pub mod proxy;
+pub mod quotas;
+pub mod ranges;
+pub mod ratelimit;
+pub mod readiness;
+pub mod recording;
+pub mod redis;
+pub mod releases;
+pub mod responses;
+pub mod runtime;
+pub mod rules;
+pub mod s3;
+pub mod scheduler;
+pub mod scripting;
+pub mod secrets;
+pub mod seed;
+#[cfg(feature = "sentry")]
+pub mod sentry;
+pub mod session;
+pub mod sharing;
+pub mod showdown;
+pub mod slo;
+pub mod sprites;
+pub mod state;
+pub mod status;
+pub mod streaming;
+pub mod tags;
+pub mod tournament;
+pub mod trash;
+pub mod units;
+pub mod version;
+pub mod viewer;
+pub mod web;
+
+// the types clients share, and the game rules they need, live in pokemon-models
+pub use pokemon_models::{models, regions, species, types};
+use pokemon_models::validation::{self, Validate};
+
+use axum::{
+    error_handling::HandleErrorLayer,
+    middleware,
+    routing::{get, post, put, delete},
+    BoxError, Router,
+};
+
+use std::collections::BTreeMap;
+use std::future::{ready, Ready};
+use std::sync::Arc;
+use std::path::PathBuf;
+use std::time::Duration;
+use maud::Markup;
+use tower_http::decompression::RequestDecompressionLayer;
+use tower_http::services::{ServeDir, ServeFile};
+use error::ApiError;
+use admin::{Incoming, KindDiff};
+use backups::Snapshot;
+use checks::CheckReport;
+use modes::ModeSettings;
+use seed::SeedReport;
+use jobs::Job;
+use models::{Pokemon, Capture, Visibility, CreatePokemon, UpdatePokemon, PokemonForm, ExportQuery, ImportQuery, GenerateQuery, RuntimeQuery, ImportedPokemon, ShareQuery, ConfirmRelease, PokemonFilter, AddTags, RenameTag, MergeTags, MergePokemon, TagsChanged, SetNickname, GalleryEntry, Damage, ApplyStatus, CureStatus, GainExp, ExpGainResult, EvolvePokemon, TeachMove, StartBattle, BattleRecord, BattleSummary, BattleFilter, JoinSession, Trainer, CreateTrainer, LoginCallback, SignIn, SignInOutcome, SetTeam, JoinQueue, CreateTournament, RegisterEntrant, Gym, CreateGym, ChallengeGym, GymChallengeResult, AggregateQuery, RandomQuery, EncounterQuery, AttackWild, AttackResult, CatchPokemon, CatchResult, BreedPokemon, WalkEgg, DaycarePokemon, Trade, TradeStatus, ProposeTrade, TrainerAction, Permission, GrantPermission, VerifyEmail, SetPassword, ForgotPassword, ResetPassword, PasswordLogin, Notification, NotificationQuery, UnreadCount, HistoryEvent, BuyItem, ListPokemon, Matchup, TypeMultiplier, TypeProfile};
+use types::PokeType;
+use session::BattleSession;
+use csrf::CsrfToken;
+use scripting::HookError;
+use breeding::Egg;
+use daycare::DaycareStay;
+use duplicates::DuplicateGroup;
+use encounters::{EncounterStatus, EncounterTable, WildEncounter};
+use analytics::{Aggregate, GroupBy, TeamStats};
+use leaderboards::{Board, Metric};
+use market::{Listing, ListingStatus, ShopItem};
+use achievements::{Event, Progress};
+use matchmaking::{Ticket, TicketStatus};
+use tournament::{Bracket, Tournament, TournamentStatus};
+use trash::TrashedPokemon;
+use sharing::Share;
+use viewer::Viewer;
+use rand::{rngs::StdRng, SeedableRng};
+
+// shared state: each collection protected by its own Mutex
+use state::{AppState, SharedState};
+
+// everything the server needs, put together from the environment by the binary
+// or by hand to embed the API in another axum service or test it in-process
+pub struct AppBuilder{
+    state: AppState,
+    // the Accept-Language and ?units=imperial middleware
+    localize: bool,
+    convert_units: bool,
+    // served at /ui, None leaves it out
+    ui_dir: Option<PathBuf>,
+    // the admin API as its own router instead of under /admin on the main one
+    separate_admin: bool,
+    security_headers: Arc<headers::SecurityHeaders>,
+    proxies: Arc<proxy::TrustedProxies>,
+    rate_limits: Arc<ratelimit::RateLimits>,
+    concurrency: Arc<concurrency::ConcurrencyLimits>,
+    cache_policy: Arc<caching::CachePolicy>,
+}
+
+impl Default for AppBuilder{
+    fn default() -> Self {
+        AppBuilder {
+            state: AppState::default(),
+            localize: true,
+            convert_units: true,
+            ui_dir: Some(PathBuf::from("public")),
+            separate_admin: false,
+            security_headers: Arc::default(),
+            proxies: Arc::default(),
+            rate_limits: Arc::default(),
+            concurrency: Arc::default(),
+            cache_policy: Arc::default(),
+        }
+    }
+}
+
+// what build() hands back, nothing is listening or running in the background yet
+pub struct App{
+    pub state: SharedState,
+    pub router: Router,
+    // only with separate_admin(true)
+    pub admin: Option<Router>,
+}
+
+impl AppBuilder{
+    // an empty in-memory app with nothing from the environment: offline, no admin token, no snapshots
+    pub fn new() -> AppBuilder {
+        AppBuilder::default()
+    }
+
+    // how the binary configures itself, see the README for every variable
+    pub fn from_env() -> Result<AppBuilder, String> {
+        secrets::check()?;
+        let state = AppState {
+            encounter_table: EncounterTable::from_env()?,
+            pokeapi: pokeapi::PokeApi::from_env(),
+            redis: redis::Redis::from_env()?,
+            sprites: sprites::SpriteStore::from_env(),
+            sharing: sharing::Sharing::from_env(),
+            backups: backups::Backups::from_env()?,
+            admin_token: admin::token_from_env(),
+            quotas: quotas::Quotas::from_env(),
+            rules: rules::Rules::from_env()?,
+            scripts: Arc::new(scripting::Scripts::from_env()?),
+            plugins: plugins::Plugins::from_env(),
+            notifier: notify::Notifier::from_env()?,
+            mailer: mail::Mailer::from_env()?,
+            logins: oidc::Logins::from_env()?,
+            auth: auth::Auth::from_env()?,
+            connections: connections::Connections::from_env()?,
+            responses: responses::ResponseCache::from_env()?,
+            scheduler: scheduler::Scheduler::from_env()?,
+            memory: memory::Memory::from_env()?,
+            flags: flags::Flags::from_env()?,
+            slos: slo::Slos::from_env()?,
+            recorder: recording::Recorder::from_env()?,
+            time: deterministic::Time::from_env()?,
+            randomness: deterministic::Randomness::from_env()?,
+            #[cfg(feature = "sentry")]
+            sentry: sentry::Sentry::from_env()?,
+            ..AppState::default()
+        };
+        Ok(AppBuilder {
+            state,
+            security_headers: Arc::new(headers::SecurityHeaders::from_env()?),
+            proxies: Arc::new(proxy::TrustedProxies::from_env()?),
+            rate_limits: Arc::new(ratelimit::RateLimits::from_env()?),
+            concurrency: Arc::new(concurrency::ConcurrencyLimits::from_env()?),
+            cache_policy: Arc::new(caching::CachePolicy::from_env()?),
+            ..AppBuilder::default()
+        })
+    }
+
+    // storage: where sprites and snapshots go, and whether species are looked up on pokeapi
+    pub fn sprites(mut self, sprites: sprites::SpriteStore) -> AppBuilder {
+        self.state.sprites = sprites;
+        self
+    }
+
+    // Google, GitHub, Keycloak and the like that trainers can log in with
+    pub fn logins(mut self, logins: oidc::Logins) -> AppBuilder {
+        self.state.logins = logins;
+        self
+    }
+
+    pub fn auth(mut self, auth: auth::Auth) -> AppBuilder {
+        self.state.auth = auth;
+        self
+    }
+
+    pub fn backups(mut self, backups: backups::Backups) -> AppBuilder {
+        self.state.backups = backups;
+        self
+    }
+
+    pub fn pokeapi(mut self, pokeapi: Option<pokeapi::PokeApi>) -> AppBuilder {
+        self.state.pokeapi = pokeapi;
+        self
+    }
+
+    // shared between instances, in front of pokeapi
+    pub fn redis(mut self, redis: Option<redis::Redis>) -> AppBuilder {
+        self.state.redis = redis;
+        self
+    }
+
+    pub fn encounter_table(mut self, table: EncounterTable) -> AppBuilder {
+        self.state.encounter_table = table;
+        self
+    }
+
+    // auth: None turns the admin API off
+    pub fn admin_token(mut self, token: Option<String>) -> AppBuilder {
+        self.state.admin_token = token;
+        self
+    }
+
+    pub fn sharing(mut self, sharing: sharing::Sharing) -> AppBuilder {
+        self.state.sharing = sharing;
+        self
+    }
+
+    pub fn quotas(mut self, quotas: quotas::Quotas) -> AppBuilder {
+        self.state.quotas = quotas;
+        self
+    }
+
+    pub fn rules(mut self, rules: rules::Rules) -> AppBuilder {
+        self.state.rules = rules;
+        self
+    }
+
+    pub fn scripts(mut self, scripts: scripting::Scripts) -> AppBuilder {
+        self.state.scripts = Arc::new(scripts);
+        self
+    }
+
+    pub fn plugin(mut self, plugin: impl plugins::Plugin) -> AppBuilder {
+        self.state.plugins.register(plugin);
+        self
+    }
+
+    pub fn notifier(mut self, notifier: notify::Notifier) -> AppBuilder {
+        self.state.notifier = notifier;
+        self
+    }
+
+    pub fn mailer(mut self, mailer: mail::Mailer) -> AppBuilder {
+        self.state.mailer = mailer;
+        self
+    }
+
+    pub fn response_cache(mut self, cache: responses::ResponseCache) -> AppBuilder {
+        self.state.responses = cache;
+        self
+    }
+
+    // only counted when served with connections::listen
+    pub fn connections(mut self, connections: connections::Connections) -> AppBuilder {
+        self.state.connections = connections;
+        self
+    }
+
+    pub fn scheduler(mut self, scheduler: scheduler::Scheduler) -> AppBuilder {
+        self.state.scheduler = scheduler;
+        self
+    }
+
+    // the thresholds the memory task warns at
+    pub fn memory(mut self, memory: memory::Memory) -> AppBuilder {
+        self.state.memory = memory;
+        self
+    }
+
+    // which experimental endpoints are on to begin with, all of them unless told otherwise
+    pub fn flags(mut self, flags: flags::Flags) -> AppBuilder {
+        self.state.flags = flags;
+        self
+    }
+
+    // latency targets per route, without any the latencies are still tracked
+    pub fn slos(mut self, slos: slo::Slos) -> AppBuilder {
+        self.state.slos = slos;
+        self
+    }
+
+    // a frozen clock and a seed, for tests and demos that come out the same every time
+    pub fn time(mut self, time: deterministic::Time) -> AppBuilder {
+        self.state.time = time;
+        self
+    }
+
+    pub fn randomness(mut self, randomness: deterministic::Randomness) -> AppBuilder {
+        self.state.randomness = randomness;
+        self
+    }
+
+    // where the API's traffic is recorded, off by default
+    pub fn recorder(mut self, recorder: recording::Recorder) -> AppBuilder {
+        self.state.recorder = recorder;
+        self
+    }
+
+    // where panics and 5xx responses are reported, only built with --features sentry
+    #[cfg(feature = "sentry")]
+    pub fn sentry(mut self, sentry: sentry::Sentry) -> AppBuilder {
+        self.state.sentry = sentry;
+        self
+    }
+
+    // middleware
+    pub fn localize(mut self, on: bool) -> AppBuilder {
+        self.localize = on;
+        self
+    }
+
+    pub fn convert_units(mut self, on: bool) -> AppBuilder {
+        self.convert_units = on;
+        self
+    }
+
+    pub fn ui_dir(mut self, dir: Option<PathBuf>) -> AppBuilder {
+        self.ui_dir = dir;
+        self
+    }
+
+    pub fn separate_admin(mut self, on: bool) -> AppBuilder {
+        self.separate_admin = on;
+        self
+    }
+
+    pub fn security_headers(mut self, headers: headers::SecurityHeaders) -> AppBuilder {
+        self.security_headers = Arc::new(headers);
+        self
+    }
+
+    pub fn trusted_proxies(mut self, proxies: proxy::TrustedProxies) -> AppBuilder {
+        self.proxies = Arc::new(proxies);
+        self
+    }
+
+    pub fn rate_limits(mut self, limits: ratelimit::RateLimits) -> AppBuilder {
+        self.rate_limits = Arc::new(limits);
+        self
+    }
+
+    pub fn concurrency_limits(mut self, limits: concurrency::ConcurrencyLimits) -> AppBuilder {
+        self.concurrency = Arc::new(limits);
+        self
+    }
+
+    pub fn cache_policy(mut self, policy: caching::CachePolicy) -> AppBuilder {
+        self.cache_policy = Arc::new(policy);
+        self
+    }
+
+    pub fn build(mut self) -> App {
+        let state: SharedState = Arc::new(std::mem::take(&mut self.state));
+        let (router, admin) = self.routers(&state);
+        App { state, router, admin }
+    }
+
+    // the app around a state that's already shared, the state set on the builder isn't used
+    fn routers(&self, state: &SharedState) -> (Router, Option<Router>) {
+        let (mut api, admin) = routes(state);
+        if !self.separate_admin {
+            api = api.nest("/admin", admin.clone());
+        }
+        if let Some(dir) = &self.ui_dir {
+            // the browser UI, unknown paths get index.html so the page can do its own routing
+            api = api.nest_service("/ui", ServeDir::new(dir).fallback(ServeFile::new(dir.join("index.html"))));
+        }
+        if self.localize {
+            // species names and error messages in the client's language
+            api = api.layer(middleware::from_fn(i18n::localize_responses));
+        }
+        if self.convert_units {
+            // ?units=imperial on anything that's read
+            api = api.layer(middleware::from_fn(units::convert_responses));
+        }
+        let router = api
+            // hot reads from memory, on the body as it goes out after the two above
+            .layer(middleware::from_fn_with_state(state.clone(), responses::serve))
+            // Cache-Control and Last-Modified, from the body as it goes out after the two above
+            .layer(middleware::from_fn_with_state(self.cache_policy.clone(), caching::apply))
+            // FEATURE_FLAGS, battles and trading can be switched off here without a new build
+            .layer(middleware::from_fn_with_state(state.clone(), flags::enforce))
+            // maintenance and read-only modes, checked before anything else
+            .layer(middleware::from_fn_with_state(state.clone(), modes::enforce))
+            // per caller and tier, once the real client is known
+            .layer(middleware::from_fn_with_state((self.rate_limits.clone(), state.clone()), ratelimit::enforce))
+            // a 503 straight away once too much is in flight, before anything else gets to work on it
+            .layer(middleware::from_fn_with_state(self.concurrency.clone(), concurrency::enforce))
+            // nosniff and co. on every response, even a 503 from the modes
+            .layer(middleware::from_fn_with_state(self.security_headers.clone(), headers::add))
+            // per route latency, for everything above
+            .layer(middleware::from_fn_with_state(state.clone(), slo::track));
+        // SENTRY_DSN, outside chaos mode so the 500s it makes on purpose aren't reported
+        #[cfg(feature = "sentry")]
+        let router = router.layer(middleware::from_fn_with_state(state.clone(), sentry::report));
+        // only built with --features chaos, inside the access log so what it does shows up there
+        #[cfg(feature = "chaos")]
+        let router = router.layer(middleware::from_fn_with_state(state.clone(), chaos::inject));
+        let router = router
+            // RECORD_TRAFFIC, what the client sent and got back, chaos and all
+            .layer(middleware::from_fn_with_state(state.clone(), recording::record))
+            // the real client behind any trusted proxies, and the access log
+            .layer(middleware::from_fn_with_state(self.proxies.clone(), proxy::resolve))
+            .with_state(state.clone());
+        let admin = self.separate_admin.then(|| {
+            let admin = Router::new().nest("/admin", admin);
+            #[cfg(feature = "sentry")]
+            let admin = admin.layer(middleware::from_fn_with_state(state.clone(), sentry::report));
+            admin
+                // imports and repairs still clear the cached reads
+                .layer(middleware::from_fn_with_state(state.clone(), responses::serve))
+                .layer(middleware::from_fn_with_state(self.security_headers.clone(), headers::add))
+                .layer(middleware::from_fn_with_state(self.proxies.clone(), proxy::resolve))
+                .with_state(state.clone())
+        });
+        (router, admin)
+    }
+}
+
+impl App{
+    // plugins' on_startup, then everything that runs on a timer, needs a tokio runtime
+    pub fn spawn_jobs(&self) {
+        let state = &self.state;
+        state.plugins.on_startup(state);
+
+        // poison, the daycare, snapshots and co. keep ticking in the background
+        scheduler::spawn(state);
+        modes::spawn_signal_listener(state.clone());
+        notify::spawn_notifier(state.clone());
+        mail::spawn_mailer(state.clone());
+        #[cfg(feature = "sentry")]
+        sentry::spawn_reporter(state.clone());
+    }
+}
+
+// the whole API for an existing state with the default middleware, admin under /admin
+pub fn app(state: SharedState) -> Router {
+    AppBuilder::default().routers(&state).0
+}
+
+// Content-Encoding: gzip or deflate on the uploads, anything else is 415
+// the route's DefaultBodyLimit counts the bytes they decompress to, so a small bomb is still cut off at the limit
+type Decompressed = (HandleErrorLayer<fn(BoxError) -> Ready<ApiError>, ()>, RequestDecompressionLayer);
+
+fn decompressed() -> Decompressed {
+    // the layer's errors are the handler's, and the handlers don't fail that way
+    let failed: fn(BoxError) -> Ready<ApiError> = |e| ready(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+    (HandleErrorLayer::new(failed), RequestDecompressionLayer::new())
+}
+
+// CPU-heavy work on tokio's blocking threads, so a big import or a long scripted battle doesn't hold up the requests sharing its worker
+async fn blocking<T: Send + 'static>(work: impl FnOnce() -> T + Send + 'static) -> Result<T, ApiError> {
+    tokio::task::spawn_blocking(work).await
+        .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("that stopped halfway: {}", e)))
+}
+
+// (everything else, the admin API) before any middleware
+fn routes(state: &SharedState) -> (Router<SharedState>, Router<SharedState>) {
+    let api = Router::new()
+        .route("/", get(root))
+        .route("/version", get(get_version))
+        .route("/readyz", get(get_ready))
+        .route("/openapi.json", get(get_openapi))
+        .route("/pokemon", get(get_all_pokemon))
+        .route("/pokemon", post(create_pokemon))
+        .route("/pokemon/stats", get(get_pokemon_stats))
+        .route("/pokemon/aggregate", get(aggregate_pokemon))
+        .route("/pokemon/random", get(random_pokemon))
+        .route("/pokemon/import", post(import_pokemon)
+            .layer(DefaultBodyLimit::max(showdown::MAX_PASTE_SIZE))
+            .layer(decompressed()))
+        .route("/pokemon/duplicates", get(get_duplicates))
+        .route("/pokemon/merge", post(merge_pokemon))
+        .route("/gallery", get(get_gallery))
+        .route("/pokemon/{id}", get(get_pokemon_by_id))
+        .route("/pokemon/{id}", put(update_pokemon))
+        .route("/pokemon/{id}", delete(delete_pokemon))
+        .route("/pokemon/{id}/gain-exp", post(gain_exp))
+        .route("/pokemon/{id}/evolve", post(evolve_pokemon))
+        .route("/pokemon/{id}/teach", post(teach_move))
+        .route("/pokemon/{id}/clone", post(clone_pokemon))
+        .route("/pokemon/{id}/release", post(release_pokemon))
+        .route("/pokemon/{id}/nickname", post(set_nickname))
+        .route("/pokemon/{id}/tags", post(add_tags))
+        .route("/pokemon/{id}/tags/{tag}", delete(remove_tag))
+        .route("/pokemon/{id}/favorite", post(favorite_pokemon).delete(unfavorite_pokemon))
+        .route("/pokemon/{id}/permissions", post(grant_pokemon_permission))
+        .route("/pokemon/{id}/permissions/{trainer_id}", delete(revoke_pokemon_permission))
+        .route("/tags", get(get_tags))
+        .route("/tags/merge", post(merge_tags))
+        .route("/tags/{tag}/rename", post(rename_tag))
+        .route("/pokemon/{id}/damage", post(damage_pokemon))
+        .route("/pokemon/{id}/heal", post(heal_pokemon))
+        .route("/pokemon/{id}/status", post(apply_status))
+        .route("/pokemon/{id}/cure", post(cure_status))
+        .route("/pokemon/{id}/weaknesses", get(get_weaknesses))
+        .route("/pokemon/{id}/export", get(export_pokemon))
+        .route("/pokemon/{id}/qr", get(pokemon_qr))
+        .route("/pokemon/{id}/share", post(share_pokemon))
+        .route("/pokemon/{id}/shares", get(get_pokemon_shares).delete(revoke_pokemon_shares))
+        .route("/shares/{token}", get(get_share).delete(revoke_share))
+        .route("/shares/{token}/redeem", post(redeem_share))
+        .route("/pokemon/{id}/sprite", put(upload_sprite).get(get_sprite)
+            // room for the multipart framing around the image
+            .layer(DefaultBodyLimit::max(sprites::MAX_SIZE + 64 * 1024)))
+        .route("/types/{attacking}/vs/{defending}", get(get_matchup))
+        .route("/battles", post(start_battle))
+        .route("/battles", get(get_all_battles))
+        .route("/battles/{id}", get(get_battle_by_id))
+        .route("/battle-sessions", post(create_battle_session))
+        .route("/battle-sessions/{id}", get(get_battle_session))
+        .route("/battle-sessions/{id}/ws", get(join_battle_session))
+        .route("/auth/providers", get(get_login_providers))
+        .route("/auth/password", post(password_login))
+        .route("/.well-known/jwks.json", get(get_session_keys))
+        .route("/auth/{provider}/login", get(start_login))
+        .route("/auth/{provider}/callback", get(finish_login))
+        .route("/trainers", post(create_trainer))
+        .route("/trainers", get(get_all_trainers))
+        .route("/trainers/{id}", get(get_trainer_by_id))
+        .route("/trainers/{id}/team", put(set_trainer_team))
+        .route("/trainers/{id}/box/permissions", post(grant_box_permission))
+        .route("/trainers/{id}/box/permissions/{trainer_id}", delete(revoke_box_permission))
+        .route("/trainers/{id}/verify-email", get(verify_trainer_email).post(resend_verification))
+        .route("/trainers/{id}/password", put(set_password))
+        .route("/trainers/forgot-password", post(forgot_password))
+        .route("/trainers/reset-password", post(reset_password))
+        .route("/trainers/{id}/trades", get(get_trainer_trades))
+        .route("/trainers/{id}/notifications", get(get_notifications))
+        .route("/trainers/{id}/notifications/unread-count", get(get_unread_count))
+        .route("/trainers/{id}/notifications/read", post(read_all_notifications))
+        .route("/trainers/{id}/notifications/{notification_id}/read", post(read_notification))
+        .route("/trainers/{id}/achievements", get(get_trainer_achievements))
+        .route("/trades", post(propose_trade))
+        .route("/trades/{id}", get(get_trade_by_id))
+        .route("/trades/{id}/accept", post(accept_trade))
+        .route("/trades/{id}/decline", post(decline_trade))
+        .route("/trades/{id}/cancel", post(cancel_trade))
+        .route("/leaderboards/{metric}", get(get_leaderboard))
+        .route("/shop", get(get_shop))
+        .route("/shop/buy", post(buy_item))
+        .route("/market", post(list_pokemon).get(get_market))
+        .route("/market/{id}", get(get_listing))
+        .route("/market/{id}/buy", post(buy_listing))
+        .route("/market/{id}/withdraw", post(withdraw_listing))
+        .route("/matchmaking/join", post(join_matchmaking))
+        .route("/matchmaking/{id}", get(get_ticket))
+        .route("/matchmaking/{id}", delete(leave_matchmaking))
+        .route("/tournaments", post(create_tournament))
+        .route("/tournaments", get(get_all_tournaments))
+        .route("/tournaments/{id}", get(get_tournament_by_id))
+        .route("/tournaments/{id}/register", post(register_for_tournament))
+        .route("/tournaments/{id}/start", post(start_tournament))
+        .route("/tournaments/{id}/rounds", post(play_tournament_round))
+        .route("/tournaments/{id}/bracket", get(get_bracket))
+        .route("/gyms", post(create_gym))
+        .route("/gyms", get(get_all_gyms))
+        .route("/gyms/{id}", get(get_gym_by_id))
+        .route("/gyms/{id}/challenge", post(challenge_gym))
+        .route("/encounters", get(wild_encounter))
+        .route("/encounters/{id}", get(get_encounter))
+        .route("/encounters/{id}/attack", post(attack_encounter))
+        .route("/encounters/{id}/catch", post(catch_encounter))
+        .route("/daycare", get(get_daycare))
+        .route("/daycare/deposit", post(deposit_pokemon))
+        .route("/daycare/withdraw", post(withdraw_pokemon))
+        .route("/daycare/breed", post(breed_pokemon))
+        .route("/eggs", get(get_all_eggs))
+        .route("/eggs/{id}", get(get_egg_by_id))
+        .route("/eggs/{id}/walk", post(walk_egg))
+        .route("/trash", get(get_trash).delete(empty_trash))
+        .route("/trash/{id}/restore", post(restore_pokemon))
+        .route("/jobs/{id}", get(get_job))
+        .merge(web_routes())
+        .merge(memory::routes(state))
+        .merge(state.plugins.extra_routes());
+    // only built with --features pprof
+    #[cfg(feature = "pprof")]
+    let api = api.merge(profiling::routes(state));
+
+    // backups and the like, behind their own token
+    let admin = Router::new()
+        .route("/export", get(export_dump))
+        .route("/import", post(import_dump)
+            .layer(DefaultBodyLimit::max(admin::MAX_IMPORT_SIZE))
+            .layer(decompressed()))
+        .route("/snapshots", get(get_snapshots).post(take_snapshot))
+        .route("/snapshots/rekey", post(rekey_snapshots))
+        .route("/snapshots/{file}", get(get_snapshot))
+        .route("/diff", post(diff_dumps)
+            .layer(DefaultBodyLimit::max(2 * admin::MAX_IMPORT_SIZE))
+            .layer(decompressed()))
+        .route("/mode", get(get_mode).put(set_mode))
+        .route("/flags", get(get_flags))
+        .route("/flags/{name}", put(set_flag))
+        .route("/connections", get(get_connections))
+        .route("/tasks", get(get_tasks))
+        .route("/runtime", get(get_runtime))
+        .route("/memory", get(get_memory))
+        .route("/slo", get(get_slo))
+        .route("/cache", get(get_response_cache).delete(clear_response_cache))
+        .route("/seed", post(seed_data))
+        .route("/generate", post(generate_pokemon))
+        .route("/jobs/{id}", get(get_admin_job))
+        .route("/check", get(check_data))
+        .route("/repair", post(repair_data));
+    // only built with --features chaos
+    #[cfg(feature = "chaos")]
+    let admin = admin.merge(chaos::admin_routes());
+    let admin = admin.route_layer(middleware::from_fn_with_state(state.clone(), admin::require_token));
+    (api, admin)
+}
+
+// the HTML pages, every form carries a CSRF token
+fn web_routes() -> Router<SharedState> {
+    Router::new()
+        .route("/web/pokemon", get(web_list).post(web_create))
+        .route("/web/pokemon/{id}", get(web_edit).post(web_update))
+        .route("/web/pokemon/{id}/delete", post(web_delete))
+        .route_layer(middleware::from_fn(csrf::protect))
+}
+
+// health check main page function - not part of CRUD
+async fn root() -> &'static str {
+    "Pokemon Team API - Try GET /pokemon"
+}
+
+// READ - whether it's fit to be sent traffic, 503 and what's wrong if not
+async fn get_ready(State(state): State<SharedState>) -> (StatusCode, Json<readiness::Readiness>) {
+    let readiness = readiness::check(&state).await;
+    let status = if readiness.ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(readiness))
+}
+
+// READ - which build is running, to check a deploy went out
+async fn get_version() -> Json<version::BuildInfo> {
+    Json(version::info())
+}
+
+// the API as OpenAPI 3.1, tests/golden.rs checks every answer it records against it
+async fn get_openapi() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "application/json")], include_str!("openapi.json"))
+}
+
+use axum::{
+    body::Body,
+    extract::{State, Json, Form, Path, Query, DefaultBodyLimit, Multipart, ws::WebSocketUpgrade},
+    Extension,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+};
+
+// CREATE - Add a new Pokemon
+// declare we have extractor for state + destructure
+async fn create_pokemon(
+    State(state): State<SharedState>,
+    Json(payload): Json<CreatePokemon>,
+    ) -> Result<(StatusCode, Json<Pokemon>), ApiError>{
+    let new_pokemon = add_pokemon(&state, payload).await?;
+    Ok((StatusCode::CREATED, Json(new_pokemon)))
+}
+
+// shared by the JSON and HTML form handlers
+async fn add_pokemon(state: &SharedState, payload: CreatePokemon) -> Result<Pokemon, ApiError> {
+    let pokemon = new_pokemon(state, payload).await?;
+    let mut added = add_all(state, vec![pokemon])?;
+    Ok(added.remove(0))
+}
+
+// everything filled in but the ID, it isn't on the team until add_all has checked it
+async fn new_pokemon(state: &SharedState, payload: CreatePokemon) -> Result<Pokemon, ApiError> {
+    payload.validate().map_err(ApiError::bad_request)?;
+    payload.height_m.map(units::check_height).transpose().map_err(ApiError::bad_request)?;
+    payload.weight_kg.map(units::check_weight).transpose().map_err(ApiError::bad_request)?;
+    let (generation, region) = regions::resolve(payload.generation, payload.region).map_err(ApiError::bad_request)?;
+    let mut capture = match payload.original_trainer {
+        Some(trainer_id) => state.trainers.lock().unwrap().iter().find(|t| t.id == trainer_id).map(Capture::by)
+            .ok_or_else(|| ApiError::not_found(format!("trainer {} not found", trainer_id)))?,
+        None => Capture::now(),
+    }.at(state.time.now());
+    capture.location = payload.location.map(|l| l.trim().to_string()).filter(|l| !l.is_empty());
+
+    // look the species up before locking, the lookup is cached after the first time
+    let info = match &state.pokeapi {
+        Some(api) => api.lookup(&payload.name, state.redis.as_ref()).await,
+        None => None,
+    };
+    let (poke_type, secondary_type) = match (payload.poke_type, &info) {
+        (Some(poke_type), _) => (poke_type, payload.secondary_type),
+        (None, Some(info)) => info.types.clone(),
+        (None, None) => return Err(ApiError::bad_request(format!("poke_type is required, pokeapi doesn't know {}", payload.name))),
+    };
+
+    // fills in exp, stats and moves from the species data
+    let mut new_pokemon = Pokemon::new(0, payload.name, poke_type, secondary_type, payload.level);
+    new_pokemon.visibility = payload.visibility.unwrap_or_default();
+    new_pokemon.height_m = payload.height_m.or(info.as_ref().map(|i| i.height_m));
+    new_pokemon.weight_kg = payload.weight_kg.or(info.as_ref().map(|i| i.weight_kg));
+    new_pokemon.generation = generation;
+    new_pokemon.region = region;
+    new_pokemon.capture = capture;
+    if let Some(info) = info {
+        new_pokemon.sprite_url = info.sprite_url;
+        // species we have data for keep their own base stats
+        if species::find(&new_pokemon.species).is_none() {
+            new_pokemon.base_stats = Some(info.base_stats);
+            new_pokemon.refresh_stats();
+        }
+    }
+    Ok(new_pokemon)
+}
+
+// the scripts and rules see each one just as it'll be stored, with the next IDs
+// then they're all added, or none of them are if any one is refused
+fn add_all(state: &SharedState, pokemon: Vec<Pokemon>) -> Result<Vec<Pokemon>, ApiError> {
+    let mut team = state.team.lock().unwrap();
+    let first_id = team.last().map_or(1, |p| p.id + 1);
+    let checked = pokemon.into_iter().zip(first_id..)
+        .map(|(mut new_pokemon, new_id)| {
+            new_pokemon.id = new_id;
+            let mut new_pokemon = state.scripts.before_create(new_pokemon).map_err(|e| match e {
+                HookError::Refused(message) => ApiError::unprocessable(message),
+                HookError::Failed(message) => ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("before_create hook: {}", message)),
+            })?;
+            // whatever the scripts did, it's still the next ID and a real level
+            new_pokemon.id = new_id;
+            validation::level(new_pokemon.level).map_err(|e| ApiError::unprocessable(format!("before_create hook: {}", e)))?;
+            new_pokemon.refresh_stats();
+            state.rules.check_pokemon(&new_pokemon)?;
+            Ok(new_pokemon)
+        })
+        .collect::<Result<Vec<_>, ApiError>>()?;
+
+    // solves the problem of sending one copy to vec and the other back as payload
+    for new_pokemon in &checked {
+        team.push(new_pokemon.clone());
+        notify::new_pokemon(state, new_pokemon, "created");
+    }
+    Ok(checked)
+}
+
+// READ - get all pokemons
+// ?generation= and ?region= narrow it down, e.g. for a per-region view
+// a long list is streamed a chunk at a time instead of being copied out whole
+async fn get_all_pokemon(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Query(filter): Query<PokemonFilter>)
+    -> Response {
+    let tag = filter.tag.as_deref().map(|t| t.trim().to_lowercase());
+    let location = filter.location.as_deref().map(str::trim);
+    let positions = state.listed_positions(viewer, |p| {
+        filter.generation.is_none_or(|g| p.generation == Some(g))
+            && filter.region.is_none_or(|r| p.region == Some(r))
+            && tag.as_ref().is_none_or(|tag| p.tags.contains(tag))
+            && filter.favorite.is_none_or(|f| p.favorite == f)
+            && filter.original_trainer.is_none_or(|t| p.capture.original_trainer == Some(t))
+            && filter.ball.is_none_or(|b| p.capture.ball == Some(b))
+            && location.is_none_or(|location| p.capture.location.as_ref().is_some_and(|l| l.eq_ignore_ascii_case(location)))
+    });
+    if positions.len() > streaming::STREAM_OVER {
+        return streaming::array(positions, move |(position, id)| state.pokemon_at(position, id));
+    }
+    let team: Vec<Pokemon> = positions.into_iter().filter_map(|(position, id)| state.pokemon_at(position, id)).collect();
+    Json(team).into_response()
+}
+
+// READ - public Pokemon across all trainers, trainer by trainer
+async fn get_gallery(State(state): State<SharedState>) -> Json<Vec<GalleryEntry>> {
+    let team = state.team.lock().unwrap();
+    let trainers = state.trainers.lock().unwrap();
+    let entries = trainers.iter()
+        .flat_map(|t| t.team.iter().chain(t.pc_box.iter()).map(move |id| (t, *id)))
+        .filter_map(|(t, id)| team.iter().find(|p| p.id == id).map(|p| (t, p)))
+        .filter(|(_, p)| p.visibility == Visibility::Public)
+        .map(|(t, p)| GalleryEntry { trainer_id: t.id, trainer_name: t.name.clone(), pokemon: p.clone() })
+        .collect();
+    Json(entries)
+}
+
+// READ - counts and level numbers for the whole collection, for dashboards
+async fn get_pokemon_stats(State(state): State<SharedState>, viewer: Viewer) -> Json<TeamStats> {
+    Json(analytics::team_stats(&state.listed_team(viewer)))
+}
+
+// READ - one metric per group, e.g. ?group_by=poke_type&metric=avg_level
+async fn aggregate_pokemon(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Query(query): Query<AggregateQuery>)
+    -> Result<Json<Aggregate>, ApiError> {
+    let group_by = GroupBy::parse(&query.group_by)
+        .ok_or_else(|| ApiError::bad_request(format!("can't group by {}, try {}", query.group_by, GroupBy::NAMES)))?;
+    let metric_name = query.metric.unwrap_or_else(|| "count".to_string());
+    let metric = analytics::Metric::parse(&metric_name)
+        .ok_or_else(|| ApiError::bad_request(format!("unknown metric {}, try {}", metric_name, analytics::Metric::NAMES)))?;
+
+    let team = state.listed_team(viewer);
+    Ok(Json(analytics::aggregate(&team, group_by, metric, [query.group_by, metric_name])))
+}
+
+// READ - any one Pokemon, each equally likely, e.g. for a pokemon of the day
+async fn random_pokemon(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Query(query): Query<RandomQuery>)
+    -> Result<Json<Pokemon>, ApiError> {
+    state.random_pokemon(viewer, &query)
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("no pokemon match"))
+}
+
+// READ - Get one Pokemon by ID
+// return result since we might not find any id matching
+// private Pokemon are a 404 for everyone but their owner, so nobody can tell they exist
+async fn get_pokemon_by_id(State(state): State<SharedState>, viewer: Viewer, Path(id): Path<u32>) -> Result<Json<Pokemon>, StatusCode> {
+    if let Some(pokemon) = state.visible_pokemon(viewer, id) {
+        Ok(Json(pokemon))
+    } else{
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
+async fn update_pokemon(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    Json(payload): Json<UpdatePokemon>)
+    -> Result<Json<Pokemon>, ApiError> {
+    state.writable_pokemon(viewer, id)?;
+    edit_pokemon(&state, id, payload).map(Json)
+}
+
+fn edit_pokemon(state: &SharedState, id: u32, payload: UpdatePokemon) -> Result<Pokemon, ApiError> {
+    // update require mutable mutexguard (roleplaying as vec)
+    let mut team = state.team.lock().unwrap();
+    let index = team.iter().position(|p| p.id == id).ok_or(StatusCode::NOT_FOUND)?;
+    // changed on a copy first, so a broken rule leaves it as it was
+    let mut updated = team[index].clone();
+    apply_update(&mut updated, payload)?;
+    state.rules.check_pokemon(&updated)?;
+    let trainers = state.trainers.lock().unwrap();
+    if let Some(trainer) = trainers.iter().find(|t| t.team.contains(&id)) {
+        let members: Vec<&Pokemon> = trainer.team.iter()
+            .filter_map(|member| if *member == id { Some(&updated) } else { team.iter().find(|p| p.id == *member) })
+            .collect();
+        state.rules.check_team(&members)?;
+    }
+    drop(trainers);
+    team[index] = updated.clone();
+    Ok(updated)
+}
+
+// nothing changes unless the whole update is valid
+fn apply_update(pokemon: &mut Pokemon, payload: UpdatePokemon) -> Result<(), ApiError> {
+    payload.validate().map_err(ApiError::bad_request)?;
+    payload.height_m.map_or(Ok(()), units::check_height).map_err(ApiError::bad_request)?;
+    payload.weight_kg.map_or(Ok(()), units::check_weight).map_err(ApiError::bad_request)?;
+    let origin = regions::resolve(payload.generation, payload.region).map_err(ApiError::bad_request)?;
+
+    // any way we can reduce LOC here since we're just testing if not null
+    if let Some(name) = payload.name{
+        pokemon.set_species(name);
+    }
+    if let Some(poke_type) = payload.poke_type{
+        pokemon.poke_type = poke_type;
+    }
+    if let Some(secondary_type) = payload.secondary_type{
+        pokemon.secondary_type = Some(secondary_type);
+    }
+    if let Some(level) = payload.level{
+        pokemon.set_level(level);
+    }
+    if let Some(visibility) = payload.visibility{
+        pokemon.visibility = visibility;
+    }
+    if let Some(height_m) = payload.height_m{
+        pokemon.height_m = Some(height_m);
+    }
+    if let Some(weight_kg) = payload.weight_kg{
+        pokemon.weight_kg = Some(weight_kg);
+    }
+    if origin != (None, None){
+        (pokemon.generation, pokemon.region) = origin;
+    }
+    Ok(())
+}
+
+// CLONE - a copy with a new ID and nobody's, the body is optional and works like PUT on the copy
+// handy for setting up battles, e.g. the same Pokemon at a few levels
+async fn clone_pokemon(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    payload: Option<Json<UpdatePokemon>>)
+    -> Result<(StatusCode, Json<Pokemon>), ApiError> {
+    let mut copy = state.visible_pokemon(viewer, id)
+        .ok_or_else(|| ApiError::not_found(format!("pokemon {} not found", id)))?;
+    // it's a new Pokemon, not another record of this one
+    copy.history.clear();
+    copy.capture = Capture::now().at(state.time.now());
+    copy.favorite = false;
+    if let Some(Json(payload)) = payload {
+        apply_update(&mut copy, payload)?;
+    }
+    // in the same way as a new one, so the scripts and the webhook see it too
+    let mut added = add_all(&state, vec![copy])?;
+    Ok((StatusCode::CREATED, Json(added.remove(0))))
+}
+
+// Pokemon that belong to a trainer have to go through POST /pokemon/{id}/release instead
+async fn delete_pokemon(State(state): State<SharedState>, viewer: Viewer, Path(id): Path<u32>) -> Result<StatusCode, ApiError> {
+    if let Some(owner) = owner_of(&state, id) {
+        // anyone else is told they can't, rather than how
+        state.writable_pokemon(viewer, id)?;
+        return Err(ApiError::conflict(format!("pokemon {} belongs to {}, release it instead", id, owner.name)));
+    }
+    if remove_pokemon(&state, id).await {
+        Ok(StatusCode::NO_CONTENT)  // 204 - Successfully deleted
+    } else {
+        Ok(StatusCode::NOT_FOUND)   // 404 - Pokemon wasn't there
+    }
+}
+
+fn owner_of(state: &SharedState, id: u32) -> Option<Trainer> {
+    state.trainers.lock().unwrap().iter().find(|t| t.owns(id)).cloned()
+}
+
+// RELEASE - two calls so a level 100 can't go with one slip
+// without a token: 202 with a token that works for 5 minutes, with it: the Pokemon is gone for good
+async fn release_pokemon(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    payload: Option<Json<ConfirmRelease>>)
+    -> Result<Response, ApiError> {
+    let pokemon = state.writable_pokemon(viewer, id)?;
+    let owner = owner_of(&state, id);
+    // write access isn't enough for this one
+    if let Some(owner) = owner.as_ref().filter(|o| viewer.0 != Some(o.id)) {
+        return Err(ApiError::forbidden(format!("only {} can release {}", owner.name, pokemon.name)));
+    }
+
+    let Some(Json(ConfirmRelease { token: Some(token) })) = payload else {
+        let release = state.releases.request(&pokemon, owner.map(|t| t.id), state.time.now());
+        return Ok((StatusCode::ACCEPTED, Json(release)).into_response());
+    };
+    let release = state.releases.confirm(id, &token, state.time.now()).map_err(ApiError::forbidden)?;
+    // e.g. traded away in between
+    if release.trainer_id != owner.map(|t| t.id) {
+        return Err(ApiError::conflict(format!("{} changed hands, ask for a new token", pokemon.name)));
+    }
+    remove_pokemon(&state, id).await;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+// false if there was no such Pokemon, otherwise it goes in the trash until it's restored or purged
+async fn remove_pokemon(state: &SharedState, id: u32) -> bool {
+    let deleted = {
+        let mut trash = state.trash.lock().unwrap();
+        let mut team = state.team.lock().unwrap();
+        let mut trainers = state.trainers.lock().unwrap();
+        match team.iter().position(|p| p.id == id) {
+            Some(index) => {
+                let pokemon = team.remove(index);
+                // a deleted Pokemon leaves its trainer's team or box too
+                let owner = trainers.iter_mut().find(|t| t.owns(id));
+                let trainer_id = owner.map(|t| {
+                    t.release(id);
+                    t.id
+                });
+                trash.push(TrashedPokemon::new(pokemon, trainer_id, state.time.now()));
+                true
+            }
+            None => false,
+        }
+    };
+
+    if deleted {
+        // the ID can be reused while it's in the trash, so the sprite can't stay behind
+        state.sprites.remove(id).await;
+        state.sharing.revoke_all(id);
+        state.releases.forget(id);
+    }
+    deleted
+}
+
+// GET - likely duplicates, Pokemon with the same species, level and nickname
+async fn get_duplicates(State(state): State<SharedState>, viewer: Viewer) -> Json<Vec<DuplicateGroup>> {
+    Json(duplicates::groups(&state.listed_team(viewer)))
+}
+
+// MERGE - two records of the same Pokemon become one, the trainer who had `merge` gets `keep` in its place
+async fn merge_pokemon(State(state): State<SharedState>, viewer: Viewer, Json(payload): Json<MergePokemon>) -> Result<Json<Pokemon>, ApiError> {
+    let MergePokemon { keep, merge } = payload;
+    if keep == merge {
+        return Err(ApiError::bad_request("a pokemon can't be merged with itself"));
+    }
+    state.writable_pokemon(viewer, keep)?;
+    state.writable_pokemon(viewer, merge)?;
+    let merged = {
+        let daycare = state.daycare.lock().unwrap();
+        let trades = state.trades.lock().unwrap();
+        let market = state.market.lock().unwrap();
+        // these would be left pointing at a Pokemon that's gone
+        if daycare.iter().any(|s| s.pokemon_id == merge) {
+            return Err(ApiError::conflict(format!("pokemon {} is at the daycare", merge)));
+        }
+        if trades.iter().any(|t| t.status == TradeStatus::Pending && (t.offered == merge || t.requested == merge)) {
+            return Err(ApiError::conflict(format!("pokemon {} is in a pending trade", merge)));
+        }
+        if market.iter().any(|l| l.status == ListingStatus::Listed && l.pokemon_id == merge) {
+            return Err(ApiError::conflict(format!("pokemon {} is listed on the market", merge)));
+        }
+
+        let mut team = state.team.lock().unwrap();
+        let mut trainers = state.trainers.lock().unwrap();
+        let find = |id: u32| team.iter().position(|p| p.id == id)
+            .ok_or_else(|| ApiError::not_found(format!("pokemon {} not found", id)));
+        let (keep_index, merge_index) = (find(keep)?, find(merge)?);
+        let owner = |id: u32| trainers.iter().position(|t| t.owns(id));
+        let (keep_owner, merge_owner) = (owner(keep), owner(merge));
+        if keep_owner.is_some() && merge_owner.is_some() && keep_owner != merge_owner {
+            return Err(ApiError::conflict(format!("pokemon {} and {} belong to different trainers", keep, merge)));
+        }
+        let merged = duplicates::absorb(&team[keep_index], &team[merge_index]).map_err(ApiError::unprocessable)?;
+
+        team[keep_index] = merged.clone();
+        team.remove(merge_index);
+        if let Some(trainer) = merge_owner.map(|i| &mut trainers[i]) {
+            if keep_owner.is_some() {
+                trainer.release(merge);
+            } else {
+                // same slot in the team or box
+                for id in trainer.team.iter_mut().chain(trainer.pc_box.iter_mut()).filter(|id| **id == merge) {
+                    *id = keep;
+                }
+            }
+        }
+        merged
+    };
+    state.sprites.remove(merge).await;
+    state.sharing.revoke_all(merge);
+    Ok(Json(merged))
+}
+
+// GET - deleted Pokemon, oldest first, they're purged after 30 days
+async fn get_trash(State(state): State<SharedState>, viewer: Viewer) -> Json<Vec<TrashedPokemon>> {
+    let trash = state.trash.lock().unwrap();
+    Json(trash.iter().filter(|t| viewer.can_list(&t.pokemon, t.trainer_id)).cloned().collect())
+}
+
+// RESTORE - back in the team, and back to its trainer if they're still around
+// it keeps its ID unless a new Pokemon took it in the meantime
+async fn restore_pokemon(State(state): State<SharedState>, viewer: Viewer, Path(id): Path<u32>) -> Result<Json<Pokemon>, ApiError> {
+    let mut trash = state.trash.lock().unwrap();
+    let index = trash.iter().position(|t| t.pokemon.id == id && viewer.can_open(&t.pokemon, t.trainer_id))
+        .ok_or_else(|| ApiError::not_found(format!("pokemon {} isn't in the trash", id)))?;
+    let mut team = state.team.lock().unwrap();
+    let mut trainers = state.trainers.lock().unwrap();
+    let trainer_id = trash[index].trainer_id;
+    if let Some(trainer) = trainers.iter().find(|t| Some(t.id) == trainer_id) {
+        state.quotas.check_receive(trainer)?;
+    }
+    let TrashedPokemon { mut pokemon, .. } = trash.remove(index);
+
+    // the team is kept in ID order, new IDs come from the last one
+    match team.binary_search_by_key(&pokemon.id, |p| p.id) {
+        Ok(_) => {
+            pokemon.id = team.last().map_or(1, |p| p.id + 1);
+            team.push(pokemon.clone());
+        }
+        Err(index) => team.insert(index, pokemon.clone()),
+    }
+    if let Some(trainer) = trainers.iter_mut().find(|t| Some(t.id) == trainer_id) {
+        state.quotas.receive(trainer, pokemon.id);
+    }
+    Ok(Json(pokemon))
+}
+
+// DELETE - the viewer's trash, for good, other trainers' deleted Pokemon stay
+async fn empty_trash(State(state): State<SharedState>, viewer: Viewer) -> StatusCode {
+    trash::purge_for(&state, viewer);
+    StatusCode::NO_CONTENT
+}
+
+// EXPORT - everything, as one JSON document or as NDJSON (?format=ndjson) with one record per line
+// the schema version is in the X-Schema-Version header as well as the dump itself
+// the JSON takes Range requests so a big download can resume, NDJSON is streamed and always comes whole
+async fn export_dump(State(state): State<SharedState>, Query(query): Query<ExportQuery>, headers: HeaderMap) -> Result<Response, ApiError> {
+    let (exported_at, content_type, extension, body) = match query.format.as_deref().unwrap_or("json") {
+        "json" => {
+            let (dump, json, etag) = blocking(move || admin::export_json(&state)).await?
+                .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("couldn't write the dump: {}", e)))?;
+            (dump.exported_at, "application/json", "json", ([(header::ETAG, etag.clone())], ranges::respond(&headers, &etag, json)).into_response())
+        }
+        // written out line by line as it's sent, so big dumps don't sit in memory twice
+        "ndjson" => {
+            let dump = admin::snapshot(&state);
+            (dump.exported_at, "application/x-ndjson", "ndjson", Body::from_stream(futures_util::stream::iter(dump.into_ndjson())).into_response())
+        }
+        other => return Err(ApiError::bad_request(format!("can't export as {}, only json or ndjson", other))),
+    };
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"pokemon-dump-{}.{}\"", exported_at, extension)),
+            (header::HeaderName::from_static("x-schema-version"), admin::SCHEMA_VERSION.to_string()),
+        ],
+        body,
+    ).into_response())
+}
+
+// IMPORT - a dump from GET /admin/export, JSON or NDJSON going by Content-Type
+// ?strategy= says what happens when a record's ID is already taken, the report says what happened to each one
+async fn import_dump(
+    State(state): State<SharedState>,
+    Query(query): Query<ImportQuery>,
+    headers: HeaderMap,
+    body: axum::body::Bytes)
+    -> Result<Response, ApiError> {
+    let is_ndjson = headers.get(header::CONTENT_TYPE).is_some_and(|v| v.as_bytes().starts_with(b"application/x-ndjson"));
+    let background = jobs::wanted(&headers);
+    let job_state = state.clone();
+    let work = move || {
+        let incoming = if is_ndjson { Incoming::from_ndjson(&body) } else { Incoming::from_json(&body) }
+            .map_err(ApiError::bad_request)?;
+        admin::import(&job_state, incoming, query.strategy.unwrap_or_default()).map_err(ApiError::unprocessable)
+    };
+    if background {
+        return Ok(jobs::start(&state, "import", true, move |_| work()));
+    }
+    let report = blocking(work).await??;
+    let status = if report.applied { StatusCode::OK } else { StatusCode::CONFLICT };
+    Ok((status, Json(report)).into_response())
+}
+
+// CREATE - the bundled demo data, anything already seeded is left alone
+async fn seed_data(State(state): State<SharedState>) -> (StatusCode, Json<SeedReport>) {
+    let report = seed::load(&state);
+    let status = if report.created_anything() { StatusCode::CREATED } else { StatusCode::OK };
+    (status, Json(report))
+}
+
+// CREATE - a pile of random Pokemon for load testing
+async fn generate_pokemon(State(state): State<SharedState>, Query(query): Query<GenerateQuery>, headers: HeaderMap) -> Result<Response, ApiError> {
+    let count = query.count.unwrap_or(generator::DEFAULT_COUNT);
+    if count > generator::MAX_COUNT {
+        return Err(ApiError::bad_request(format!("count can be up to {}", generator::MAX_COUNT)));
+    }
+    let seed = query.seed.unwrap_or_else(|| state.randomness.seed());
+    if jobs::wanted(&headers) {
+        let job_state = state.clone();
+        return Ok(jobs::start(&state, "generate", true, move |progress| {
+            Ok::<_, ApiError>(generator::generate(&job_state, count, seed, |done| progress.set(done, count)))
+        }));
+    }
+    let generated = blocking(move || generator::generate(&state, count, seed, |_| {})).await?;
+    Ok((StatusCode::CREATED, Json(generated)).into_response())
+}
+
+// READ - a background job started with Prefer: respond-async through the admin API, or any other
+async fn get_admin_job(State(state): State<SharedState>, Path(id): Path<u32>) -> Result<Json<Job>, ApiError> {
+    state.jobs.get(id).map(Json).ok_or_else(|| ApiError::not_found(format!("job {} not found", id)))
+}
+
+// READ - anything in the data that shouldn't be possible, and what repair would do about it
+async fn check_data(State(state): State<SharedState>) -> Json<CheckReport> {
+    Json(checks::check(&state))
+}
+
+// UPDATE - fix whatever check finds that can safely be fixed
+async fn repair_data(State(state): State<SharedState>) -> Json<CheckReport> {
+    Json(checks::repair(&state))
+}
+
+// DIFF - two dumps as "before" and "after" in a multipart form, JSON or NDJSON
+// with only "after" it's compared against the live data, e.g. to see what restoring a snapshot would change
+async fn diff_dumps(State(state): State<SharedState>, mut multipart: Multipart) -> Result<Json<BTreeMap<String, KindDiff>>, ApiError> {
+    let multipart_error = |e: axum::extract::multipart::MultipartError| ApiError::new(e.status(), e.body_text());
+    let (mut before, mut after) = (None, None);
+    while let Some(field) = multipart.next_field().await.map_err(multipart_error)? {
+        let slot = match field.name() {
+            Some("before") => &mut before,
+            Some("after") => &mut after,
+            _ => continue,
+        };
+        *slot = Some(field.bytes().await.map_err(multipart_error)?);
+    }
+    let after = after.ok_or_else(|| ApiError::bad_request("no after field in the form"))?;
+    let diff = blocking(move || {
+        let parse = |name: &str, bytes: &[u8]| Incoming::parse(bytes).map_err(|e| ApiError::bad_request(format!("{}: {}", name, e)));
+        let before = match before {
+            Some(bytes) => parse("before", &bytes)?,
+            None => Incoming::live(&state),
+        };
+        Ok::<_, ApiError>(admin::diff(before, parse("after", &after)?))
+    }).await??;
+    Ok(Json(diff))
+}
+
+// GET - how the response cache is doing
+async fn get_response_cache(State(state): State<SharedState>) -> Json<responses::CacheStats> {
+    Json(state.responses.stats())
+}
+
+// DELETE - drops every cached response, the counts are kept
+async fn clear_response_cache(State(state): State<SharedState>) -> StatusCode {
+    state.responses.clear();
+    StatusCode::NO_CONTENT
+}
+
+// GET - open TCP connections, the cap and how often it's been hit
+async fn get_connections(State(state): State<SharedState>) -> Json<connections::ConnectionStats> {
+    Json(state.connections.stats())
+}
+
+// READ - every scheduled task, how often it runs and how its last run went
+async fn get_tasks(State(state): State<SharedState>) -> Json<Vec<scheduler::TaskStatus>> {
+    Json(state.scheduler.statuses())
+}
+
+// READ - tokio's workers and queues, watched over ?window_ms= (250 by default)
+async fn get_runtime(Query(query): Query<RuntimeQuery>) -> Result<Json<runtime::RuntimeStats>, ApiError> {
+    let window = query.window_ms.map_or(runtime::DEFAULT_WINDOW, Duration::from_millis);
+    if window > runtime::MAX_WINDOW {
+        return Err(ApiError::bad_request(format!("window_ms can be up to {}", runtime::MAX_WINDOW.as_millis())));
+    }
+    Ok(Json(runtime::sample(window).await))
+}
+
+// READ - how many records each collection holds and roughly how much memory they take up
+async fn get_memory(State(state): State<SharedState>) -> Json<memory::MemoryStats> {
+    Json(memory::stats(&state))
+}
+
+// READ - rolling p50/p95/p99 for every route and how each is doing against its target
+async fn get_slo(State(state): State<SharedState>) -> Json<slo::SloReport> {
+    Json(state.slos.report())
+}
+
+// GET - normal, read_only or maintenance
+async fn get_mode(State(state): State<SharedState>) -> Json<ModeSettings> {
+    Json(state.modes.get())
+}
+
+// MODE - switch it while the server's running, the health check and /admin keep working whatever it is
+async fn set_mode(State(state): State<SharedState>, Json(payload): Json<ModeSettings>) -> Json<ModeSettings> {
+    state.modes.set(payload);
+    Json(state.modes.get())
+}
+
+// GET - every feature flag and whether it's on
+async fn get_flags(State(state): State<SharedState>) -> Json<Vec<flags::FlagStatus>> {
+    Json(state.flags.statuses())
+}
+
+// UPDATE - turn a flag on or off until the next restart
+async fn set_flag(State(state): State<SharedState>, Path(name): Path<String>, Json(payload): Json<flags::SetFlag>) -> Result<Json<flags::FlagStatus>, ApiError> {
+    state.flags.set(&name, payload.enabled)
+        .map(Json)
+        .map_err(ApiError::not_found)
+}
+
+// GET - snapshots on disk, newest first
+async fn get_snapshots(State(state): State<SharedState>) -> Result<Json<Vec<Snapshot>>, ApiError> {
+    state.backups.list().await
+        .map(Json)
+        .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("couldn't list the snapshots: {}", e)))
+}
+
+// SNAPSHOT - right now rather than waiting for the next scheduled one
+async fn take_snapshot(State(state): State<SharedState>) -> Result<(StatusCode, Json<Snapshot>), ApiError> {
+    let snapshot = state.backups.take(&state).await
+        .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("couldn't take a snapshot: {}", e)))?;
+    Ok((StatusCode::CREATED, Json(snapshot)))
+}
+
+// READ - one snapshot as plain JSON, decrypted if it was encrypted
+async fn get_snapshot(State(state): State<SharedState>, Path(file): Path<String>) -> Result<Response, ApiError> {
+    match state.backups.read(&file).await {
+        Ok(json) => Ok(([(header::CONTENT_TYPE, "application/json")], json).into_response()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(ApiError::not_found(format!("snapshot {} not found", file))),
+        Err(e) => Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("couldn't read {}: {}", file, e))),
+    }
+}
+
+// REKEY - every snapshot on disk encrypted with the current key, after a rotation
+async fn rekey_snapshots(State(state): State<SharedState>) -> Result<Json<backups::Rekey>, ApiError> {
+    state.backups.rekey().await.map(Json).map_err(ApiError::conflict)
+}
+
+// EXPORT - as Showdown team text, ready to paste into the teambuilder
+async fn export_pokemon(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    Query(query): Query<ExportQuery>)
+    -> Result<Response, ApiError> {
+    let format = query.format.unwrap_or_else(|| "showdown".to_string());
+    if format != "showdown" {
+        return Err(ApiError::bad_request(format!("can't export as {}, only showdown", format)));
+    }
+    let pokemon = state.visible_pokemon(viewer, id)
+        .ok_or_else(|| ApiError::not_found(format!("pokemon {} not found", id)))?;
+    Ok(([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], showdown::export(&pokemon)).into_response())
+}
+
+// IMPORT - a Showdown paste of up to 6 sets as the plain text body, each one becomes a new Pokemon
+// nothing is created unless the whole paste is valid
+async fn import_pokemon(State(state): State<SharedState>, body: String) -> Result<(StatusCode, Json<Vec<ImportedPokemon>>), ApiError> {
+    let sets = showdown::parse(&body).map_err(ApiError::unprocessable)?;
+
+    let mut built = Vec::new();
+    let mut not_kept = Vec::new();
+    for set in sets {
+        let (primary, secondary) = species::find_or_unknown(set.species).types;
+        let payload = CreatePokemon {
+            name: set.species.to_string(),
+            poke_type: Some(primary.to_string()),
+            secondary_type: secondary.map(str::to_string),
+            level: set.level,
+            visibility: None,
+            height_m: None,
+            weight_kg: None,
+            generation: None,
+            region: None,
+            original_trainer: None,
+            location: None,
+        };
+        let mut pokemon = new_pokemon(&state, payload).await?;
+        pokemon.ivs = set.ivs;
+        pokemon.shiny = set.shiny;
+        pokemon.set_nickname(set.nickname.clone());
+        if !set.moves.is_empty() {
+            pokemon.moves = set.moves.clone();
+        }
+        pokemon.refresh_stats();
+        pokemon.current_hp = pokemon.max_hp;
+        built.push(pokemon);
+        not_kept.push(set.not_kept());
+    }
+    let imported = add_all(&state, built)?.into_iter().zip(not_kept)
+        .map(|(pokemon, not_kept)| ImportedPokemon { pokemon, not_kept })
+        .collect();
+    Ok((StatusCode::CREATED, Json(imported)))
+}
+
+// WEB - the team as an HTML page, with a form to add one
+// the pages go by the viewer like the API does, without a session or X-Trainer-Id that's what anyone can see
+async fn web_list(State(state): State<SharedState>, viewer: Viewer, Extension(token): Extension<CsrfToken>) -> Markup {
+    web::list_page(&state.listed_team(viewer), None, &token)
+}
+
+// WEB - the add form posts here, back to the list either way
+async fn web_create(State(state): State<SharedState>, viewer: Viewer, Extension(token): Extension<CsrfToken>, Form(form): Form<PokemonForm>) -> Response {
+    match add_pokemon(&state, form.into()).await {
+        Ok(_) => Redirect::to("/web/pokemon").into_response(),
+        Err(e) => (e.status, web::list_page(&state.listed_team(viewer), Some(&e.message), &token)).into_response(),
+    }
+}
+
+// WEB - one Pokemon with a form to edit it
+async fn web_edit(State(state): State<SharedState>, viewer: Viewer, Extension(token): Extension<CsrfToken>, Path(id): Path<u32>) -> Response {
+    match state.visible_pokemon(viewer, id) {
+        Some(pokemon) => web::edit_page(&pokemon, None, &token).into_response(),
+        None => (StatusCode::NOT_FOUND, web::not_found_page(&format!("pokemon {} not found", id))).into_response(),
+    }
+}
+
+async fn web_update(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Extension(token): Extension<CsrfToken>,
+    Path(id): Path<u32>,
+    Form(form): Form<PokemonForm>)
+    -> Response {
+    match state.writable_pokemon(viewer, id).and_then(|_| edit_pokemon(&state, id, form.into())) {
+        Ok(_) => Redirect::to(&format!("/web/pokemon/{}", id)).into_response(),
+        Err(e) if e.status == StatusCode::NOT_FOUND => (StatusCode::NOT_FOUND, web::not_found_page(&format!("pokemon {} not found", id))).into_response(),
+        Err(e) => {
+            let team = state.team.lock().unwrap();
+            let Some(pokemon) = team.iter().find(|p| p.id == id) else {
+                return e.into_response();
+            };
+            (e.status, web::edit_page(pokemon, Some(&e.message), &token)).into_response()
+        }
+    }
+}
+
+async fn web_delete(State(state): State<SharedState>, viewer: Viewer, Extension(token): Extension<CsrfToken>, Path(id): Path<u32>) -> Response {
+    if state.visible_pokemon(viewer, id).is_none() {
+        return (StatusCode::NOT_FOUND, web::not_found_page(&format!("pokemon {} not found", id))).into_response();
+    }
+    if let Some(owner) = owner_of(&state, id) {
+        let team = state.team.lock().unwrap();
+        if let Some(pokemon) = team.iter().find(|p| p.id == id) {
+            let message = format!("{} belongs to {}, release it with POST /pokemon/{}/release", pokemon.name, owner.name, id);
+            return (StatusCode::CONFLICT, web::edit_page(pokemon, Some(&message), &token)).into_response();
+        }
+    }
+    remove_pokemon(&state, id).await;
+    Redirect::to("/web/pokemon").into_response()
+}
+
+// UPLOAD - a png, jpeg, gif or webp image in the "sprite" field of a multipart form, up to 1 MB
+async fn upload_sprite(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    mut multipart: Multipart)
+    -> Result<Json<Pokemon>, ApiError> {
+    state.writable_pokemon(viewer, id)?;
+
+    let multipart_error = |e: axum::extract::multipart::MultipartError| ApiError::new(e.status(), e.body_text());
+    let mut upload = None;
+    while let Some(field) = multipart.next_field().await.map_err(multipart_error)? {
+        if field.name() == Some("sprite") {
+            let declared = field.content_type().map(str::to_string);
+            upload = Some((declared, field.bytes().await.map_err(multipart_error)?));
+            break;
+        }
+    }
+    let Some((declared, bytes)) = upload else {
+        return Err(ApiError::bad_request("no sprite field in the form"));
+    };
+
+    if bytes.len() > sprites::MAX_SIZE {
+        return Err(ApiError::new(StatusCode::PAYLOAD_TOO_LARGE, format!("sprites can be up to {} bytes", sprites::MAX_SIZE)));
+    }
+    let Some(content_type) = sprites::sniff(&bytes) else {
+        return Err(ApiError::new(StatusCode::UNSUPPORTED_MEDIA_TYPE, "sprites have to be png, jpeg, gif or webp images"));
+    };
+    if let Some(declared) = declared.filter(|d| d != content_type) {
+        return Err(ApiError::new(StatusCode::UNSUPPORTED_MEDIA_TYPE, format!("sent as {} but it's really {}", declared, content_type)));
+    }
+    state.sprites.save(id, content_type, &bytes).await
+        .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("couldn't save the sprite: {}", e)))?;
+
+    let mut team = state.team.lock().unwrap();
+    let pokemon = team.iter_mut().find(|p| p.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("pokemon {} not found", id)))?;
+    pokemon.sprite_url = Some(format!("/pokemon/{}/sprite", id));
+    Ok(Json(pokemon.clone()))
+}
+
+// READ - the uploaded sprite, clients can keep it for 5 minutes and revalidate with the ETag after that
+// Range requests get just part of it
+async fn get_sprite(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    headers: HeaderMap)
+    -> Result<Response, ApiError> {
+    check_pokemon(&state, viewer, id)?;
+    let Some((content_type, bytes)) = state.sprites.load(id).await else {
+        return Err(ApiError::not_found(format!("pokemon {} has no sprite", id)));
+    };
+    let etag = sprites::etag(&bytes);
+    let cache_headers = [(header::ETAG, etag.clone()), (header::CACHE_CONTROL, "public, max-age=300".to_string())];
+    if headers.get(header::IF_NONE_MATCH).is_some_and(|v| v.as_bytes() == etag.as_bytes()) {
+        return Ok((StatusCode::NOT_MODIFIED, cache_headers).into_response());
+    }
+    Ok((cache_headers, [(header::CONTENT_TYPE, content_type)], ranges::respond(&headers, &etag, bytes)).into_response())
+}
+
+fn check_pokemon(state: &SharedState, viewer: Viewer, id: u32) -> Result<(), ApiError> {
+    if state.visible_pokemon(viewer, id).is_some() {
+        Ok(())
+    } else {
+        Err(ApiError::not_found(format!("pokemon {} not found", id)))
+    }
+}
+
+// SHARE - a signed link anyone can open to see this Pokemon without logging in, until it expires or is revoked
+async fn share_pokemon(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    Query(query): Query<ShareQuery>)
+    -> Result<(StatusCode, Json<Share>), ApiError> {
+    state.writable_pokemon(viewer, id)?;
+    let lifetime = query.expires_in.map_or(sharing::DEFAULT_LIFETIME, Duration::from_secs);
+    if lifetime.is_zero() || lifetime > sharing::MAX_LIFETIME {
+        return Err(ApiError::bad_request(format!("expires_in must be 1 to {} seconds", sharing::MAX_LIFETIME.as_secs())));
+    }
+    Ok((StatusCode::CREATED, Json(state.sharing.share(id, lifetime, state.time.now()))))
+}
+
+// SHARE - the same kind of link as a QR code, it can also be redeemed once for a copy
+async fn pokemon_qr(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>)
+    -> Result<Response, ApiError> {
+    state.writable_pokemon(viewer, id)?;
+    let share = state.sharing.share(id, sharing::DEFAULT_LIFETIME, state.time.now());
+    let png = sharing::qr_png(&share.url)
+        .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("couldn't draw the QR code: {}", e)))?;
+    // every request signs a fresh link, so don't let anything cache it
+    Ok(([(header::CONTENT_TYPE, "image/png"), (header::CACHE_CONTROL, "no-store")], png).into_response())
+}
+
+// READ - the links for this Pokemon that still work
+async fn get_pokemon_shares(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>)
+    -> Result<Json<Vec<Share>>, ApiError> {
+    state.writable_pokemon(viewer, id)?;
+    Ok(Json(state.sharing.outstanding(id, state.time.now())))
+}
+
+// REVOKE - every outstanding link for this Pokemon stops working
+async fn revoke_pokemon_shares(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>)
+    -> Result<StatusCode, ApiError> {
+    state.writable_pokemon(viewer, id)?;
+    state.sharing.revoke_all(id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// REVOKE - just this link
+async fn revoke_share(
+    State(state): State<SharedState>,
+    Path(token): Path<String>)
+    -> StatusCode {
+    if state.sharing.revoke(&token) {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+// PERMISSIONS - the owner lets another trainer see (read) or change (write) one of their Pokemon
+// X-Trainer-Id has to be the owner's, giving the same trainer another permission on it replaces the old one
+async fn grant_pokemon_permission(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    Json(payload): Json<GrantPermission>)
+    -> Result<(StatusCode, Json<Permission>), ApiError> {
+    let owner = pokemon_owner(&state, viewer, id)?;
+    let mut trainers = state.trainers.lock().unwrap();
+    grant_permission(&mut trainers, owner, payload, Some(id)).map(|p| (StatusCode::CREATED, Json(p)))
+}
+
+async fn revoke_pokemon_permission(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path((id, trainer_id)): Path<(u32, u32)>)
+    -> Result<StatusCode, ApiError> {
+    let owner = pokemon_owner(&state, viewer, id)?;
+    let mut trainers = state.trainers.lock().unwrap();
+    revoke_permission(&mut trainers, owner, trainer_id, Some(id))
+}
+
+// PERMISSIONS - the same for every Pokemon in the trainer's box, as long as it's there
+async fn grant_box_permission(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    Json(payload): Json<GrantPermission>)
+    -> Result<(StatusCode, Json<Permission>), ApiError> {
+    let mut trainers = state.trainers.lock().unwrap();
+    box_owner(&trainers, viewer, id)?;
+    grant_permission(&mut trainers, id, payload, None).map(|p| (StatusCode::CREATED, Json(p)))
+}
+
+async fn revoke_box_permission(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path((id, trainer_id)): Path<(u32, u32)>)
+    -> Result<StatusCode, ApiError> {
+    let mut trainers = state.trainers.lock().unwrap();
+    box_owner(&trainers, viewer, id)?;
+    revoke_permission(&mut trainers, id, trainer_id, None)
+}
+
+// the ID of the trainer who owns the Pokemon, as long as they're the one asking
+fn pokemon_owner(state: &SharedState, viewer: Viewer, id: u32) -> Result<u32, ApiError> {
+    let pokemon = state.visible_pokemon(viewer, id)
+        .ok_or_else(|| ApiError::not_found(format!("pokemon {} not found", id)))?;
+    let owner = owner_of(state, id)
+        .ok_or_else(|| ApiError::conflict(format!("nobody owns {}, anyone can already see and change it", pokemon.name)))?;
+    if viewer.0 != Some(owner.id) {
+        return Err(ApiError::forbidden(format!("only {} can say who else can use {}", owner.name, pokemon.name)));
+    }
+    Ok(owner.id)
+}
+
+fn box_owner(trainers: &[Trainer], viewer: Viewer, id: u32) -> Result<(), ApiError> {
+    let trainer = trainers.iter().find(|t| t.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("trainer {} not found", id)))?;
+    if viewer.0 != Some(id) {
+        return Err(ApiError::forbidden(format!("only {} can say who else can use their box", trainer.name)));
+    }
+    Ok(())
+}
+
+fn grant_permission(trainers: &mut [Trainer], owner: u32, payload: GrantPermission, pokemon_id: Option<u32>) -> Result<Permission, ApiError> {
+    if payload.trainer_id == owner {
+        return Err(ApiError::bad_request("trainers don't need permission for their own Pokemon"));
+    }
+    if !trainers.iter().any(|t| t.id == payload.trainer_id) {
+        return Err(ApiError::not_found(format!("trainer {} not found", payload.trainer_id)));
+    }
+    let permission = Permission { trainer_id: payload.trainer_id, access: payload.access, pokemon_id };
+    let owner = trainers.iter_mut().find(|t| t.id == owner)
+        .ok_or_else(|| ApiError::not_found(format!("trainer {} not found", owner)))?;
+    owner.grant(permission.clone());
+    Ok(permission)
+}
+
+fn revoke_permission(trainers: &mut [Trainer], owner: u32, trainer_id: u32, pokemon_id: Option<u32>) -> Result<StatusCode, ApiError> {
+    let owner = trainers.iter_mut().find(|t| t.id == owner)
+        .ok_or_else(|| ApiError::not_found(format!("trainer {} not found", owner)))?;
+    if owner.revoke(trainer_id, pokemon_id) {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::not_found(format!("trainer {} has no permission there", trainer_id)))
+    }
+}
+
+// the Pokemon a share link points at
+fn shared_pokemon(state: &SharedState, token: &str) -> Result<Pokemon, ApiError> {
+    let id = state.sharing.verify(token, state.time.now()).map_err(ApiError::forbidden)?;
+    state.team.lock().unwrap().iter().find(|p| p.id == id).cloned()
+        .ok_or_else(|| ApiError::not_found(format!("pokemon {} isn't around anymore", id)))
+}
+
+// READ - what the share link is for, before redeeming it
+async fn get_share(
+    State(state): State<SharedState>,
+    Path(token): Path<String>)
+    -> Result<Json<Pokemon>, ApiError> {
+    shared_pokemon(&state, &token).map(Json)
+}
+
+// REDEEM - a copy of the shared Pokemon goes into the trainer's box, the original stays where it is
+async fn redeem_share(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(token): Path<String>,
+    Json(payload): Json<TrainerAction>)
+    -> Result<(StatusCode, Json<Pokemon>), ApiError> {
+    viewer.require(payload.trainer_id)?;
+    let shared = shared_pokemon(&state, &token)?;
+
+    let mut team = state.team.lock().unwrap();
+    let mut trainers = state.trainers.lock().unwrap();
+    let trainer = trainers.iter_mut().find(|t| t.id == payload.trainer_id)
+        .ok_or_else(|| ApiError::not_found(format!("trainer {} not found", payload.trainer_id)))?;
+    if trainer.owns(shared.id) {
+        return Err(ApiError::conflict(format!("{} already has {}", trainer.name, shared.name)));
+    }
+    state.quotas.check(trainer, trainer.team.len(), trainer.pc_box.len() + 1)?;
+    if !state.sharing.redeem(&token) {
+        return Err(ApiError::conflict("that share link has already been redeemed"));
+    }
+
+    let new_id = team.last().map_or(1, |p| p.id + 1);
+    let copy = Pokemon { id: new_id, ..shared };
+    trainer.pc_box.push(new_id);
+    team.push(copy.clone());
+    achievements::record(&state, trainer.id, Event::Obtained(&copy.species));
+
+    Ok((StatusCode::CREATED, Json(copy)))
+}
+
+// NICKNAME - up to 12 characters, checked for rude words, null or "" goes back to the species name
+async fn set_nickname(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    Json(payload): Json<SetNickname>)
+    -> Result<Json<Pokemon>, ApiError> {
+    let nickname = match payload.nickname.filter(|n| !n.trim().is_empty()) {
+        Some(nickname) => Some(nickname::validate(&nickname).map_err(ApiError::unprocessable)?),
+        None => None,
+    };
+
+    state.writable_pokemon(viewer, id)?;
+    let mut team = state.team.lock().unwrap();
+    let pokemon = team.iter_mut().find(|p| p.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("pokemon {} not found", id)))?;
+    pokemon.set_nickname(nickname);
+    Ok(Json(pokemon.clone()))
+}
+
+// TAGS - any labels the trainer likes, e.g. "starter" or "shiny hunt", up to 20 per Pokemon
+async fn add_tags(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    Json(payload): Json<AddTags>)
+    -> Result<Json<Pokemon>, ApiError> {
+    let new_tags = payload.tags.iter()
+        .map(|t| tags::normalize(t))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(ApiError::bad_request)?;
+
+    state.writable_pokemon(viewer, id)?;
+    let mut team = state.team.lock().unwrap();
+    let pokemon = team.iter_mut().find(|p| p.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("pokemon {} not found", id)))?;
+    tags::add(pokemon, &new_tags).map_err(ApiError::unprocessable)?;
+    Ok(Json(pokemon.clone()))
+}
+
+async fn remove_tag(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path((id, tag)): Path<(u32, String)>)
+    -> Result<Json<Pokemon>, ApiError> {
+    let tag = tag.trim().to_lowercase();
+    state.writable_pokemon(viewer, id)?;
+    let mut team = state.team.lock().unwrap();
+    let pokemon = team.iter_mut().find(|p| p.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("pokemon {} not found", id)))?;
+    if !pokemon.tags.contains(&tag) {
+        return Err(ApiError::not_found(format!("{} isn't tagged {}", pokemon.name, tag)));
+    }
+    pokemon.tags.retain(|t| *t != tag);
+    Ok(Json(pokemon.clone()))
+}
+
+fn set_favorite(state: &SharedState, id: u32, favorite: bool) -> Result<Json<Pokemon>, ApiError> {
+    let mut team = state.team.lock().unwrap();
+    let pokemon = team.iter_mut().find(|p| p.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("pokemon {} not found", id)))?;
+    pokemon.favorite = favorite;
+    Ok(Json(pokemon.clone()))
+}
+
+// FAVORITE - mark it, then find favorites with GET /pokemon?favorite=true
+async fn favorite_pokemon(State(state): State<SharedState>, viewer: Viewer, Path(id): Path<u32>) -> Result<Json<Pokemon>, ApiError> {
+    state.writable_pokemon(viewer, id)?;
+    set_favorite(&state, id, true)
+}
+
+async fn unfavorite_pokemon(State(state): State<SharedState>, viewer: Viewer, Path(id): Path<u32>) -> Result<Json<Pokemon>, ApiError> {
+    state.writable_pokemon(viewer, id)?;
+    set_favorite(&state, id, false)
+}
+
+// READ - every tag in use and how many Pokemon have it, most used first
+async fn get_tags(State(state): State<SharedState>, viewer: Viewer) -> Json<Vec<tags::TagCount>> {
+    Json(tags::counts(&state.listed_team(viewer)))
+}
+
+// RENAME - into a tag that's already in use merges the two
+// both only touch, and count, the Pokemon the viewer may change
+async fn rename_tag(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(tag): Path<String>,
+    Json(payload): Json<RenameTag>)
+    -> Result<Json<TagsChanged>, ApiError> {
+    let from = tag.trim().to_lowercase();
+    let into = tags::normalize(&payload.to).map_err(ApiError::bad_request)?;
+    let changed = merge_writable(&state, viewer, std::slice::from_ref(&from), &into);
+    if changed == 0 {
+        return Err(ApiError::not_found(format!("no pokemon are tagged {}", from)));
+    }
+    Ok(Json(TagsChanged { tag: into, pokemon_changed: changed }))
+}
+
+// MERGE - several tags become one, e.g. "fav" and "favourite" into "favorite"
+async fn merge_tags(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Json(payload): Json<MergeTags>)
+    -> Result<Json<TagsChanged>, ApiError> {
+    if payload.from.is_empty() {
+        return Err(ApiError::bad_request("from needs at least one tag"));
+    }
+    let from: Vec<String> = payload.from.iter().map(|t| t.trim().to_lowercase()).collect();
+    let into = tags::normalize(&payload.into).map_err(ApiError::bad_request)?;
+    let changed = merge_writable(&state, viewer, &from, &into);
+    Ok(Json(TagsChanged { tag: into, pokemon_changed: changed }))
+}
+
+fn merge_writable(state: &SharedState, viewer: Viewer, from: &[String], into: &str) -> usize {
+    let mut team = state.team.lock().unwrap();
+    let trainers = state.trainers.lock().unwrap();
+    tags::merge(&mut team, from, into, |p| viewer.can_write(p.id, trainers.iter().find(|t| t.owns(p.id))))
+}
+
+// LEVEL UP - add exp and level up for every threshold crossed
+async fn gain_exp(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    Json(payload): Json<GainExp>)
+    -> Result<Json<ExpGainResult>, ApiError> {
+    if payload.amount == 0 {
+        return Err(ApiError::bad_request("amount must be greater than 0"));
+    }
+
+    state.writable_pokemon(viewer, id)?;
+    let mut team = state.team.lock().unwrap();
+    let pokemon = team.iter_mut().find(|p| p.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("pokemon {} not found", id)))?;
+    if pokemon.fainted {
+        return Err(ApiError::conflict(format!("{} has fainted and can't gain exp", pokemon.name)));
+    }
+
+    let previous_level = pokemon.level;
+    let previous_stats = pokemon.stats;
+    let previous_exp = pokemon.exp;
+    let (levels_gained, learned_moves, offered_moves) = pokemon.gain_exp(payload.amount);
+
+    Ok(Json(ExpGainResult {
+        // can be less than requested once the level cap is hit
+        exp_gained: pokemon.exp - previous_exp,
+        levels_gained,
+        previous_level,
+        previous_stats,
+        learned_moves,
+        offered_moves,
+        pokemon: pokemon.clone(),
+    }))
+}
+
+// EVOLVE - check the species' evolution conditions and transform the Pokemon
+async fn evolve_pokemon(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    payload: Option<Json<EvolvePokemon>>)
+    -> Result<Json<Pokemon>, ApiError> {
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+
+    state.writable_pokemon(viewer, id)?;
+    let mut team = state.team.lock().unwrap();
+    let pokemon = team.iter_mut().find(|p| p.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("pokemon {} not found", id)))?;
+
+    let current = species::find(&pokemon.species)
+        .ok_or_else(|| ApiError::unprocessable(format!("no evolution data for {}", pokemon.species)))?;
+    if current.evolutions.is_empty() {
+        return Err(ApiError::unprocessable(format!("{} does not evolve", current.name)));
+    }
+
+    // narrow down to the requested branch if there is one
+    let candidates: Vec<_> = current.evolutions.iter()
+        .filter(|e| payload.into.as_deref().is_none_or(|into| e.into.eq_ignore_ascii_case(into)))
+        .collect();
+    if candidates.is_empty() {
+        return Err(ApiError::unprocessable(format!(
+            "{} can't evolve into {}", current.name, payload.into.unwrap_or_default())));
+    }
+
+    let Some(evolution) = candidates.iter().find(|e| e.is_met(pokemon.level, pokemon.friendship, payload.item.as_deref())) else {
+        let needs: Vec<String> = candidates.iter()
+            .map(|e| format!("{} to evolve into {}", e.requirement(), e.into))
+            .collect();
+        return Err(ApiError::unprocessable(format!("{} needs to {}", current.name, needs.join(" or "))));
+    };
+
+    let into = species::find(evolution.into).expect("evolution target missing from species table");
+    // only record the item if it was what triggered the evolution
+    let item = match evolution.trigger {
+        species::EvolutionTrigger::Item(needed) => Some(needed.to_string()),
+        species::EvolutionTrigger::Level(_) | species::EvolutionTrigger::Friendship(_) => None,
+    };
+    pokemon.evolve_into(into, item);
+    if let Some(owner) = state.trainers.lock().unwrap().iter().find(|t| t.owns(id)) {
+        achievements::record(&state, owner.id, Event::Obtained(&pokemon.species));
+    }
+
+    Ok(Json(pokemon.clone()))
+}
+
+// TEACH - learn a level-up or TM move, replacing one if all slots are taken
+async fn teach_move(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    Json(payload): Json<TeachMove>)
+    -> Result<Json<Pokemon>, ApiError> {
+    state.writable_pokemon(viewer, id)?;
+    let mut team = state.team.lock().unwrap();
+    let pokemon = team.iter_mut().find(|p| p.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("pokemon {} not found", id)))?;
+
+    let species = species::find_or_unknown(&pokemon.species);
+    let move_name = match species.move_name(&payload.move_name) {
+        Some(m) if species.can_learn(m, pokemon.level) => m,
+        _ => return Err(ApiError::unprocessable(format!("{} can't learn {}", pokemon.name, payload.move_name))),
+    };
+    if pokemon.knows_move(move_name) {
+        return Err(ApiError::conflict(format!("{} already knows {}", pokemon.name, move_name)));
+    }
+
+    match payload.forget {
+        Some(forget) => {
+            let slot = pokemon.moves.iter().position(|m| m.eq_ignore_ascii_case(&forget))
+                .ok_or_else(|| ApiError::unprocessable(format!("{} doesn't know {}", pokemon.name, forget)))?;
+            pokemon.moves[slot] = move_name.to_string();
+        }
+        None if pokemon.moves.len() >= species::MAX_MOVES => {
+            return Err(ApiError::conflict(format!(
+                "{} already knows {} moves ({}) - pass \"forget\" to replace one",
+                pokemon.name, species::MAX_MOVES, pokemon.moves.join(", "))));
+        }
+        None => pokemon.moves.push(move_name.to_string()),
+    }
+
+    Ok(Json(pokemon.clone()))
+}
+
+// DAMAGE - knock off some HP, fainting at 0
+async fn damage_pokemon(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    Json(payload): Json<Damage>)
+    -> Result<Json<Pokemon>, ApiError> {
+    if payload.amount == 0 {
+        return Err(ApiError::bad_request("amount must be greater than 0"));
+    }
+
+    state.writable_pokemon(viewer, id)?;
+    let mut team = state.team.lock().unwrap();
+    let pokemon = team.iter_mut().find(|p| p.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("pokemon {} not found", id)))?;
+    if pokemon.fainted {
+        return Err(ApiError::conflict(format!("{} has already fainted", pokemon.name)));
+    }
+
+    pokemon.take_damage(payload.amount);
+    Ok(Json(pokemon.clone()))
+}
+
+// HEAL - Pokemon Center semantics, always succeeds even at full HP
+async fn heal_pokemon(State(state): State<SharedState>, viewer: Viewer, Path(id): Path<u32>) -> Result<Json<Pokemon>, ApiError> {
+    state.writable_pokemon(viewer, id)?;
+    let mut team = state.team.lock().unwrap();
+    let pokemon = team.iter_mut().find(|p| p.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("pokemon {} not found", id)))?;
+
+    pokemon.heal();
+    Ok(Json(pokemon.clone()))
+}
+
+// STATUS - poison, burn, paralyze, sleep or freeze a Pokemon
+async fn apply_status(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    Json(payload): Json<ApplyStatus>)
+    -> Result<Json<Pokemon>, ApiError> {
+    state.writable_pokemon(viewer, id)?;
+    let mut team = state.team.lock().unwrap();
+    let pokemon = team.iter_mut().find(|p| p.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("pokemon {} not found", id)))?;
+
+    // one status at a time, cure the current one first
+    if let Some(current) = pokemon.status {
+        return Err(ApiError::conflict(format!("{} is already {}", pokemon.name, current.adjective())));
+    }
+    status::apply(pokemon, payload.condition, &mut state.randomness.rng()).map_err(ApiError::unprocessable)?;
+    Ok(Json(pokemon.clone()))
+}
+
+// CURE - remove the status condition, optionally only a specific one
+async fn cure_status(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    payload: Option<Json<CureStatus>>)
+    -> Result<Json<Pokemon>, ApiError> {
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+
+    state.writable_pokemon(viewer, id)?;
+    let mut team = state.team.lock().unwrap();
+    let pokemon = team.iter_mut().find(|p| p.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("pokemon {} not found", id)))?;
+
+    let Some(current) = pokemon.status else {
+        return Err(ApiError::conflict(format!("{} has no status condition", pokemon.name)));
+    };
+    if let Some(wanted) = payload.condition {
+        if wanted != current {
+            return Err(ApiError::unprocessable(format!("{} is {}, not {}",
+                pokemon.name, current.adjective(), wanted.adjective())));
+        }
+    }
+
+    status::cure(pokemon);
+    Ok(Json(pokemon.clone()))
+}
+
+// TYPES - how effective one type is against another
+async fn get_matchup(Path((attacking, defending)): Path<(String, String)>) -> Result<Json<Matchup>, ApiError> {
+    let attacking: PokeType = attacking.parse().map_err(ApiError::not_found)?;
+    let defending: PokeType = defending.parse().map_err(ApiError::not_found)?;
+    let multiplier = types::effectiveness(attacking, defending);
+
+    Ok(Json(Matchup { attacking, defending, multiplier, effectiveness: types::describe(multiplier) }))
+}
+
+// TYPES - weaknesses/resistances of a Pokemon from its (possibly dual) typing
+async fn get_weaknesses(State(state): State<SharedState>, viewer: Viewer, Path(id): Path<u32>) -> Result<Json<TypeProfile>, ApiError> {
+    let pokemon = state.visible_pokemon(viewer, id)
+        .ok_or_else(|| ApiError::not_found(format!("pokemon {} not found", id)))?;
+    let defending = pokemon.types().map_err(ApiError::unprocessable)?;
+
+    let mut profile = TypeProfile {
+        id,
+        types: defending.clone(),
+        weaknesses: Vec::new(),
+        resistances: Vec::new(),
+        immunities: Vec::new(),
+    };
+    for attacking in types::ALL_TYPES {
+        let multiplier = types::effectiveness_against(attacking, &defending);
+        if multiplier == 0.0 {
+            profile.immunities.push(attacking);
+        } else if multiplier > 1.0 {
+            profile.weaknesses.push(TypeMultiplier { poke_type: attacking, multiplier });
+        } else if multiplier < 1.0 {
+            profile.resistances.push(TypeMultiplier { poke_type: attacking, multiplier });
+        }
+    }
+    // 4x weaknesses first, 0.25x resistances first
+    profile.weaknesses.sort_by(|a, b| b.multiplier.total_cmp(&a.multiplier));
+    profile.resistances.sort_by(|a, b| a.multiplier.total_cmp(&b.multiplier));
+
+    Ok(Json(profile))
+}
+
+// copies of both sides' Pokemon, checking the usual battle rules
+// side_a is the viewer's and has to be theirs to use, side_b only has to be visible to them
+fn pick_sides(state: &SharedState, viewer: Viewer, sides: &StartBattle) -> Result<(Vec<Pokemon>, Vec<Pokemon>), ApiError> {
+    sides.validate().map_err(ApiError::bad_request)?;
+    let (side_a, side_b) = (state.pick_writable_team(viewer, &sides.side_a)?, state.pick_team(viewer, &sides.side_b)?);
+
+    for side in [&side_a, &side_b] {
+        if side.iter().all(|p| p.fainted) {
+            return Err(ApiError::unprocessable("every pokemon on a side has fainted - heal them first"));
+        }
+    }
+    Ok((side_a, side_b))
+}
+
+// BATTLE - simulate a battle between two teams and keep the log as a replay
+// stored Pokemon aren't changed
+async fn start_battle(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Json(payload): Json<StartBattle>)
+    -> Result<(StatusCode, Json<BattleRecord>), ApiError> {
+    // copies, so the lock isn't held while simulating
+    let (side_a, side_b) = pick_sides(&state, viewer, &payload)?;
+
+    // always settle on a seed so the replay can be reproduced later
+    let seed = payload.seed.unwrap_or_else(|| state.randomness.seed());
+    let record = blocking(move || {
+        let outcome = battle::run(battle::Battle::new(side_a, side_b).with_scripts(state.scripts.clone()), &mut StdRng::seed_from_u64(seed));
+        state.record_battle(seed, outcome)
+    }).await?;
+
+    Ok((StatusCode::CREATED, Json(record)))
+}
+
+// READ - past battles without the turn-by-turn log, optionally only ones a Pokemon took part in
+async fn get_all_battles(
+    State(state): State<SharedState>,
+    Query(filter): Query<BattleFilter>)
+    -> Response {
+    let battles = state.battles.lock().unwrap();
+    if battles.len() > streaming::STREAM_OVER {
+        let positions: Vec<(usize, u32)> = battles.iter().enumerate()
+            .filter(|(_, b)| filter.pokemon.is_none_or(|id| b.involves(id)))
+            .map(|(i, b)| (i, b.id))
+            .collect();
+        drop(battles);
+        // battles are only ever added, so they stay where they are
+        return streaming::array(positions, move |(position, id)| {
+            state.battles.lock().unwrap().get(position).filter(|b| b.id == id).map(BattleRecord::summary)
+        });
+    }
+    let summaries: Vec<BattleSummary> = battles.iter()
+        .filter(|b| filter.pokemon.is_none_or(|id| b.involves(id)))
+        .map(BattleRecord::summary)
+        .collect();
+    Json(summaries).into_response()
+}
+
+// READ - one battle with the full log, for rendering a replay
+async fn get_battle_by_id(State(state): State<SharedState>, Path(id): Path<u32>) -> Result<Json<BattleRecord>, ApiError> {
+    let battles = state.battles.lock().unwrap();
+    battles.iter().find(|b| b.id == id)
+        .cloned()
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("battle {} not found", id)))
+}
+
+// BATTLE SESSION - set up an interactive battle, players then connect over WebSocket
+async fn create_battle_session(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Json(payload): Json<StartBattle>)
+    -> Result<(StatusCode, Json<BattleSession>), ApiError> {
+    let (side_a, side_b) = pick_sides(&state, viewer, &payload)?;
+    let session = session::create(&state, side_a, side_b);
+    Ok((StatusCode::CREATED, Json(session)))
+}
+
+async fn get_battle_session(State(state): State<SharedState>, Path(id): Path<u32>) -> Result<Json<BattleSession>, ApiError> {
+    let sessions = state.sessions.lock().unwrap();
+    sessions.iter().find(|s| s.id == id)
+        .cloned()
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("battle session {} not found", id)))
+}
+
+// BATTLE SESSION - GET /battle-sessions/{id}/ws?side=side_a upgrades to a WebSocket for that side
+async fn join_battle_session(
+    State(state): State<SharedState>,
+    Path(id): Path<u32>,
+    Query(join): Query<JoinSession>,
+    ws: WebSocketUpgrade)
+    -> Result<Response, ApiError> {
+    let commands = {
+        let sessions = state.sessions.lock().unwrap();
+        let session = sessions.iter().find(|s| s.id == id)
+            .ok_or_else(|| ApiError::not_found(format!("battle session {} not found", id)))?;
+        if session.connected.contains(&join.side) {
+            return Err(ApiError::conflict(format!("{} already has a player", join.side.label())));
+        }
+        session.commands.clone()
+            .ok_or_else(|| ApiError::conflict("this battle session is over"))?
+    };
+
+    Ok(ws.on_upgrade(move |socket| session::run_connection(socket, state, id, join.side, commands)))
+}
+
+// a trainer's team must be real Pokemon that no other trainer owns
+fn check_team(team: &[Pokemon], trainers: &[Trainer], rules: &rules::Rules, trainer_id: Option<u32>, ids: &[u32]) -> Result<(), ApiError> {
+    validation::team(ids).map_err(ApiError::bad_request)?;
+    for id in ids {
+        if !team.iter().any(|p| p.id == *id) {
+            return Err(ApiError::not_found(format!("pokemon {} not found", id)));
+        }
+        if let Some(owner) = trainers.iter().find(|t| Some(t.id) != trainer_id && t.owns(*id)) {
+            return Err(ApiError::conflict(format!("pokemon {} already belongs to {}", id, owner.name)));
+        }
+    }
+    let members: Vec<&Pokemon> = ids.iter().filter_map(|id| team.iter().find(|p| p.id == *id)).collect();
+    rules.check_team(&members)?;
+    Ok(())
+}
+
+// CREATE - register a trainer, optionally with a starting team
+async fn create_trainer(
+    State(state): State<SharedState>,
+    Json(payload): Json<CreateTrainer>)
+    -> Result<(StatusCode, Json<Trainer>), ApiError> {
+    payload.validate().map_err(ApiError::bad_request)?;
+    let team = state.team.lock().unwrap();
+    let mut trainers = state.trainers.lock().unwrap();
+    check_team(&team, &trainers, &state.rules, None, &payload.team)?;
+
+    let new_id = trainers.last().map_or(1, |t| t.id + 1);
+    let mut trainer = Trainer::new(new_id, payload.name, payload.team);
+    trainer.email = payload.email.map(|e| e.trim().to_string());
+    state.quotas.check(&trainer, trainer.team.len(), 0)?;
+    trainers.push(trainer.clone());
+    mail::send_verification(&state, &trainer);
+
+    Ok((StatusCode::CREATED, Json(trainer)))
+}
+
+// READ - the names that go in /auth/{provider}/login
+async fn get_login_providers(State(state): State<SharedState>) -> Json<Vec<String>> {
+    Json(state.logins.names().into_iter().map(str::to_string).collect())
+}
+
+// READ - the public keys sessions are checked with, the old ones too until they're dropped from SESSION_KEYS
+async fn get_session_keys(State(state): State<SharedState>) -> Json<serde_json::Value> {
+    Json(state.auth.jwks())
+}
+
+// LOGIN - off to the provider, which sends the browser back to the callback
+async fn start_login(State(state): State<SharedState>, Path(provider): Path<String>) -> Result<Redirect, ApiError> {
+    let redirect_uri = oidc::redirect_uri(state.sharing.public_url(), &provider);
+    let url = state.logins.start(&provider, &redirect_uri, state.time.now()).await?;
+    Ok(Redirect::to(&url))
+}
+
+// CALLBACK - the trainer the account logs in as, made the first time if there isn't one
+async fn finish_login(
+    State(state): State<SharedState>,
+    Path(provider): Path<String>,
+    Query(callback): Query<LoginCallback>)
+    -> Result<(StatusCode, Json<SignIn>), ApiError> {
+    let redirect_uri = oidc::redirect_uri(state.sharing.public_url(), &provider);
+    let identity = state.logins.finish(&provider, &callback, &redirect_uri, state.time.now()).await?;
+    let sign_in = oidc::sign_in(&state, identity)?;
+    let status = if sign_in.outcome == SignInOutcome::Created { StatusCode::CREATED } else { StatusCode::OK };
+    Ok((status, Json(sign_in)))
+}
+
+// LOGIN - a session for a trainer with a password, the same answer for a wrong password as for no such trainer
+async fn password_login(State(state): State<SharedState>, Json(payload): Json<PasswordLogin>) -> Result<Json<SignIn>, ApiError> {
+    if !state.passwords.check(payload.trainer_id, &payload.password) {
+        return Err(ApiError::new(StatusCode::UNAUTHORIZED, "that trainer ID and password don't match"));
+    }
+    let trainers = state.trainers.lock().unwrap();
+    let trainer = trainers.iter().find(|t| t.id == payload.trainer_id).cloned()
+        .ok_or_else(|| ApiError::new(StatusCode::UNAUTHORIZED, "that trainer ID and password don't match"))?;
+    let session = state.auth.issue(trainer.id, state.time.now());
+    Ok(Json(SignIn { trainer, outcome: SignInOutcome::Existing, session }))
+}
+
+// VERIFY - the link from the email sent when the trainer was created
+// each link works once, and not at all for an address the trainer has since changed
+async fn verify_trainer_email(
+    State(state): State<SharedState>,
+    Path(id): Path<u32>,
+    Query(query): Query<VerifyEmail>)
+    -> Result<Json<Trainer>, ApiError> {
+    let mut trainers = state.trainers.lock().unwrap();
+    let trainer = trainers.iter_mut().find(|t| t.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("trainer {} not found", id)))?;
+    match mail::verify(&state, trainer, &query.token) {
+        Ok(()) => {}
+        Err(mail::TokenError::Expired) => {
+            return Err(ApiError::new(StatusCode::GONE, "that verification link has expired, POST to it for a new one"));
+        }
+        Err(mail::TokenError::Invalid) => return Err(ApiError::forbidden("that verification link isn't valid")),
+    }
+    trainer.email_verified = true;
+    // a GET that changes the trainer, so the cache doesn't see it as a write
+    state.responses.invalidate("trainers", Some(id));
+    Ok(Json(trainer.clone()))
+}
+
+// VERIFY - send a fresh verification link, the old one stops working
+async fn resend_verification(State(state): State<SharedState>, Path(id): Path<u32>) -> Result<StatusCode, ApiError> {
+    let trainers = state.trainers.lock().unwrap();
+    let trainer = trainers.iter().find(|t| t.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("trainer {} not found", id)))?;
+    if trainer.email.is_none() {
+        return Err(ApiError::unprocessable(format!("trainer {} doesn't have an email address", id)));
+    }
+    if trainer.email_verified {
+        return Err(ApiError::conflict(format!("trainer {}'s email is already verified", id)));
+    }
+    mail::send_verification(&state, trainer);
+    Ok(StatusCode::ACCEPTED)
+}
+
+// UPDATE - a trainer's password, the first one is set by the trainer themselves and changing it needs the current one
+async fn set_password(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    Json(payload): Json<SetPassword>)
+    -> Result<StatusCode, ApiError> {
+    if !state.trainers.lock().unwrap().iter().any(|t| t.id == id) {
+        return Err(ApiError::not_found(format!("trainer {} not found", id)));
+    }
+    passwords::check_length(&payload.password).map_err(ApiError::bad_request)?;
+    match payload.current_password {
+        Some(current) if !state.passwords.check(id, &current) => {
+            return Err(ApiError::forbidden(format!("that isn't trainer {}'s current password", id)));
+        }
+        Some(_) => {}
+        None if state.passwords.is_set(id) => {
+            return Err(ApiError::forbidden(format!("trainer {} already has a password, send current_password to change it", id)));
+        }
+        None if viewer.0 != Some(id) => {
+            return Err(ApiError::forbidden(format!("only trainer {} can set their first password", id)));
+        }
+        None => {}
+    }
+    state.passwords.set(id, &payload.password);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// RESET - mail a reset token to whoever has this verified address, the answer is the same either way so it can't be used to find trainers
+async fn forgot_password(State(state): State<SharedState>, Json(payload): Json<ForgotPassword>) -> StatusCode {
+    let trainers = state.trainers.lock().unwrap();
+    let email = payload.email.trim();
+    if let Some(trainer) = trainers.iter().find(|t| t.email_verified && t.email.as_deref().is_some_and(|e| e.eq_ignore_ascii_case(email))) {
+        mail::send_password_reset(&state, trainer);
+    }
+    StatusCode::ACCEPTED
+}
+
+// RESET - a new password with the token from the email, which is used up either way
+async fn reset_password(State(state): State<SharedState>, Json(payload): Json<ResetPassword>) -> Result<StatusCode, ApiError> {
+    passwords::check_length(&payload.password).map_err(ApiError::bad_request)?;
+    let trainers = state.trainers.lock().unwrap();
+    let id = match mail::redeem_reset(&state, &trainers, &payload.token) {
+        Ok(id) => id,
+        Err(mail::TokenError::Expired) => {
+            return Err(ApiError::new(StatusCode::GONE, "that reset token has expired, ask for a new one at /trainers/forgot-password"));
+        }
+        Err(mail::TokenError::Invalid) => return Err(ApiError::forbidden("that reset token isn't valid")),
+    };
+    state.passwords.set(id, &payload.password);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// READ - all trainers, each one's email and logins only to themselves
+async fn get_all_trainers(State(state): State<SharedState>, viewer: Viewer) -> Json<Vec<Trainer>> {
+    let trainers = state.trainers.lock().unwrap();
+    Json(trainers.iter().map(|t| t.seen_by(viewer.0)).collect())
+}
+
+// READ - one trainer by ID
+async fn get_trainer_by_id(State(state): State<SharedState>, viewer: Viewer, Path(id): Path<u32>) -> Result<Json<Trainer>, ApiError> {
+    let trainers = state.trainers.lock().unwrap();
+    trainers.iter().find(|t| t.id == id)
+        .map(|t| Json(t.seen_by(viewer.0)))
+        .ok_or_else(|| ApiError::not_found(format!("trainer {} not found", id)))
+}
+
+// UPDATE - swap out a trainer's whole team
+// Pokemon can come out of the trainer's box, the ones left off the team go into it
+// only by the trainer, with Pokemon they may change
+async fn set_trainer_team(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    Json(payload): Json<SetTeam>)
+    -> Result<Json<Trainer>, ApiError> {
+    if !state.trainers.lock().unwrap().iter().any(|t| t.id == id) {
+        return Err(ApiError::not_found(format!("trainer {} not found", id)));
+    }
+    viewer.require(id)?;
+    for pokemon_id in &payload.team {
+        state.writable_pokemon(viewer, *pokemon_id)?;
+    }
+    let team = state.team.lock().unwrap();
+    let mut trainers = state.trainers.lock().unwrap();
+    if !trainers.iter().any(|t| t.id == id) {
+        return Err(ApiError::not_found(format!("trainer {} not found", id)));
+    }
+    check_team(&team, &trainers, &state.rules, Some(id), &payload.team)?;
+
+    let trainer = trainers.iter_mut().find(|t| t.id == id).unwrap();
+    // everything else the trainer has ends up in the box, including Pokemon they're only now claiming
+    let claimed = payload.team.iter().filter(|p| !trainer.owns(**p)).count();
+    let owned = trainer.team.len() + trainer.pc_box.len() + claimed;
+    state.quotas.check(trainer, payload.team.len(), owned - payload.team.len())?;
+    let left_out: Vec<u32> = trainer.team.iter().copied().filter(|p| !payload.team.contains(p)).collect();
+    trainer.pc_box.retain(|p| !payload.team.contains(p));
+    trainer.pc_box.extend(left_out);
+    trainer.team = payload.team;
+    Ok(Json(trainer.clone()))
+}
+
+// MATCHMAKING - queue a trainer's team, the matchmaker pairs it with a similarly-leveled one
+// and starts a battle session, poll the ticket to find out which one
+async fn join_matchmaking(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Json(payload): Json<JoinQueue>)
+    -> Result<(StatusCode, Json<Ticket>), ApiError> {
+    viewer.require(payload.trainer_id)?;
+    let team_ids = {
+        let trainers = state.trainers.lock().unwrap();
+        let trainer = trainers.iter().find(|t| t.id == payload.trainer_id)
+            .ok_or_else(|| ApiError::not_found(format!("trainer {} not found", payload.trainer_id)))?;
+        trainer.team.clone()
+    };
+    let team = state.pick_team(viewer, &team_ids)?;
+    if !matchmaking::battle_ready(&team) {
+        return Err(ApiError::unprocessable("the trainer needs at least one pokemon that hasn't fainted"));
+    }
+
+    let mut tickets = state.tickets.lock().unwrap();
+    if tickets.iter().any(|t| t.trainer_id == payload.trainer_id && t.status == TicketStatus::Waiting) {
+        return Err(ApiError::conflict(format!("trainer {} is already in the queue", payload.trainer_id)));
+    }
+    let new_id = tickets.last().map_or(1, |t| t.id + 1);
+    let ticket = Ticket {
+        id: new_id,
+        trainer_id: payload.trainer_id,
+        team: team_ids,
+        team_level: matchmaking::average_level(&team),
+        status: TicketStatus::Waiting,
+        session_id: None,
+        side: None,
+        queued_at: state.time.now(),
+    };
+    tickets.push(ticket.clone());
+
+    // 202 - the match itself happens in the background
+    Ok((StatusCode::ACCEPTED, Json(ticket)))
+}
+
+// READ - check on a queue ticket
+async fn get_ticket(State(state): State<SharedState>, Path(id): Path<u32>) -> Result<Json<Ticket>, ApiError> {
+    let tickets = state.tickets.lock().unwrap();
+    tickets.iter().find(|t| t.id == id)
+        .cloned()
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("ticket {} not found", id)))
+}
+
+// DELETE - leave the queue
+async fn leave_matchmaking(State(state): State<SharedState>, Path(id): Path<u32>) -> Result<StatusCode, ApiError> {
+    let mut tickets = state.tickets.lock().unwrap();
+    let ticket = tickets.iter_mut().find(|t| t.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("ticket {} not found", id)))?;
+    if ticket.status != TicketStatus::Waiting {
+        return Err(ApiError::conflict("this ticket isn't in the queue anymore"));
+    }
+    ticket.status = TicketStatus::Cancelled;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// CREATE - a tournament open for registration
+async fn create_tournament(
+    State(state): State<SharedState>,
+    Json(payload): Json<CreateTournament>)
+    -> (StatusCode, Json<Tournament>) {
+    let mut tournaments = state.tournaments.lock().unwrap();
+    let new_id = tournaments.last().map_or(1, |t| t.id + 1);
+    let tournament = Tournament::new(new_id, payload.name, payload.mode);
+    tournaments.push(tournament.clone());
+    (StatusCode::CREATED, Json(tournament))
+}
+
+// READ - all tournaments, without their brackets
+async fn get_all_tournaments(State(state): State<SharedState>) -> Json<Vec<Tournament>> {
+    let tournaments = state.tournaments.lock().unwrap();
+    Json(tournaments.clone())
+}
+
+async fn get_tournament_by_id(State(state): State<SharedState>, Path(id): Path<u32>) -> Result<Json<Tournament>, ApiError> {
+    let tournaments = state.tournaments.lock().unwrap();
+    tournaments.iter().find(|t| t.id == id)
+        .cloned()
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("tournament {} not found", id)))
+}
+
+// TOURNAMENT - sign a trainer up, seeds go by registration order
+async fn register_for_tournament(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    Json(payload): Json<RegisterEntrant>)
+    -> Result<Json<Tournament>, ApiError> {
+    viewer.require(payload.trainer_id)?;
+    if !state.trainers.lock().unwrap().iter().any(|t| t.id == payload.trainer_id) {
+        return Err(ApiError::not_found(format!("trainer {} not found", payload.trainer_id)));
+    }
+
+    let mut tournaments = state.tournaments.lock().unwrap();
+    let tournament = tournaments.iter_mut().find(|t| t.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("tournament {} not found", id)))?;
+    if tournament.status != TournamentStatus::Registering {
+        return Err(ApiError::conflict("registration for this tournament is closed"));
+    }
+    if tournament.entrants.contains(&payload.trainer_id) {
+        return Err(ApiError::conflict(format!("trainer {} is already registered", payload.trainer_id)));
+    }
+    tournament.entrants.push(payload.trainer_id);
+    Ok(Json(tournament.clone()))
+}
+
+// TOURNAMENT - close registration and draw the bracket
+async fn start_tournament(State(state): State<SharedState>, Path(id): Path<u32>) -> Result<Json<Bracket>, ApiError> {
+    let mut tournaments = state.tournaments.lock().unwrap();
+    let tournament = tournaments.iter_mut().find(|t| t.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("tournament {} not found", id)))?;
+    if tournament.status != TournamentStatus::Registering {
+        return Err(ApiError::conflict("this tournament has already started"));
+    }
+    if tournament.entrants.len() < 2 {
+        return Err(ApiError::unprocessable("a tournament needs at least 2 trainers"));
+    }
+    tournament.start();
+    Ok(Json(tournament.bracket()))
+}
+
+// TOURNAMENT - play the current round
+// simulated tournaments battle right away, interactive ones open a session per match
+// and pick up the results the next time this is called
+async fn play_tournament_round(State(state): State<SharedState>, Path(id): Path<u32>, headers: HeaderMap) -> Result<Response, ApiError> {
+    // a whole round of battles
+    let play = move |state: &SharedState, progress: &dyn Fn(usize, usize)| {
+        let mut tournaments = state.tournaments.lock().unwrap();
+        let tournament = playable(&mut tournaments, id)?;
+        tournament::play_round(state, tournament, progress);
+        Ok::<_, ApiError>(tournament.bracket())
+    };
+    if jobs::wanted(&headers) {
+        // a round that can't be played is still refused straight away
+        playable(&mut state.tournaments.lock().unwrap(), id)?;
+        let job_state = state.clone();
+        return Ok(jobs::start(&state, "tournament-round", false, move |progress| play(&job_state, &|played, of| progress.set(played, of))));
+    }
+    let bracket = blocking(move || play(&state, &|_, _| {})).await??;
+    Ok(Json(bracket).into_response())
+}
+
+// the tournament with this ID, if it's one a round can be played in
+fn playable(tournaments: &mut [Tournament], id: u32) -> Result<&mut Tournament, ApiError> {
+    let tournament = tournaments.iter_mut().find(|t| t.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("tournament {} not found", id)))?;
+    match tournament.status {
+        TournamentStatus::Registering => Err(ApiError::conflict("this tournament hasn't started yet")),
+        TournamentStatus::Finished => Err(ApiError::conflict("this tournament is over")),
+        TournamentStatus::InProgress => Ok(tournament),
+    }
+}
+
+// READ - how a background job started with Prefer: respond-async is getting on
+async fn get_job(State(state): State<SharedState>, Path(id): Path<u32>) -> Result<Json<Job>, ApiError> {
+    state.jobs.get(id).filter(|job| !job.admin).map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("job {} not found", id)))
+}
+
+// READ - rounds, results and standings
+async fn get_bracket(State(state): State<SharedState>, Path(id): Path<u32>) -> Result<Json<Bracket>, ApiError> {
+    let tournaments = state.tournaments.lock().unwrap();
+    tournaments.iter().find(|t| t.id == id)
+        .map(|t| Json(t.bracket()))
+        .ok_or_else(|| ApiError::not_found(format!("tournament {} not found", id)))
+}
+
+// CREATE - a gym with its leader's team
+async fn create_gym(
+    State(state): State<SharedState>,
+    Json(payload): Json<CreateGym>)
+    -> Result<(StatusCode, Json<Gym>), ApiError> {
+    payload.validate().map_err(ApiError::bad_request)?;
+
+    let mut gyms = state.gyms.lock().unwrap();
+    let new_id = gyms.last().map_or(1, |g| g.id + 1);
+    let gym = Gym { id: new_id, name: payload.name, leader: payload.leader, badge: payload.badge, team: payload.team };
+    gyms.push(gym.clone());
+    Ok((StatusCode::CREATED, Json(gym)))
+}
+
+// READ - all gyms
+async fn get_all_gyms(State(state): State<SharedState>) -> Json<Vec<Gym>> {
+    let gyms = state.gyms.lock().unwrap();
+    Json(gyms.clone())
+}
+
+async fn get_gym_by_id(State(state): State<SharedState>, Path(id): Path<u32>) -> Result<Json<Gym>, ApiError> {
+    let gyms = state.gyms.lock().unwrap();
+    gyms.iter().find(|g| g.id == id)
+        .cloned()
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("gym {} not found", id)))
+}
+
+// GYM - battle the leader, winning earns the gym's badge
+// the trainer's team is side_a, the leader's side_b
+async fn challenge_gym(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    Json(payload): Json<ChallengeGym>)
+    -> Result<Json<GymChallengeResult>, ApiError> {
+    viewer.require(payload.trainer_id)?;
+    let gym = state.gyms.lock().unwrap().iter().find(|g| g.id == id).cloned()
+        .ok_or_else(|| ApiError::not_found(format!("gym {} not found", id)))?;
+    let team_ids = state.trainers.lock().unwrap().iter().find(|t| t.id == payload.trainer_id)
+        .map(|t| t.team.clone())
+        .ok_or_else(|| ApiError::not_found(format!("trainer {} not found", payload.trainer_id)))?;
+    let team = state.pick_team(viewer, &team_ids)?;
+    if !matchmaking::battle_ready(&team) {
+        return Err(ApiError::unprocessable("the trainer needs at least one pokemon that hasn't fainted"));
+    }
+
+    let leader_team = gym.team.iter().map(|p| p.to_pokemon()).collect();
+    let seed = payload.seed.unwrap_or_else(|| state.randomness.seed());
+    let scripts = state.scripts.clone();
+    let outcome = blocking(move || battle::run(battle::Battle::new(team, leader_team).with_scripts(scripts), &mut StdRng::seed_from_u64(seed))).await?;
+    let battle = state.record_battle(seed, outcome);
+    let won = battle.outcome.winner == Some(battle::Side::SideA);
+
+    let mut trainers = state.trainers.lock().unwrap();
+    let trainer = trainers.iter_mut().find(|t| t.id == payload.trainer_id)
+        .ok_or_else(|| ApiError::not_found(format!("trainer {} not found", payload.trainer_id)))?;
+    let badge_awarded = (won && !trainer.badges.contains(&gym.badge)).then(|| gym.badge.clone());
+    if let Some(badge) = &badge_awarded {
+        trainer.badges.push(badge.clone());
+    }
+    let prize = if won {
+        gym.team.iter().map(|p| p.level).max().unwrap_or(0) * market::GYM_PRIZE_PER_LEVEL
+    } else {
+        0
+    };
+    trainer.money = trainer.money.saturating_add(prize);
+    if won {
+        achievements::record(&state, trainer.id, Event::BattleWon);
+    }
+
+    Ok(Json(GymChallengeResult { won, badge_awarded, prize, trainer: trainer.clone(), battle }))
+}
+
+// ENCOUNTER - a random wild Pokemon from the encounter table, try catching it with POST /encounters/{id}/catch
+async fn wild_encounter(
+    State(state): State<SharedState>,
+    Query(query): Query<EncounterQuery>)
+    -> Result<Json<WildEncounter>, ApiError> {
+    encounters::appear(&state, query).map(Json)
+}
+
+async fn get_encounter(State(state): State<SharedState>, Path(id): Path<u32>) -> Result<Json<WildEncounter>, ApiError> {
+    let encounters = state.encounters.lock().unwrap();
+    encounters.iter().find(|e| e.id == id)
+        .cloned()
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("encounter {} not found", id)))
+}
+
+// ENCOUNTER - one of your Pokemon attacks the wild one to wear it down before throwing a ball
+// plays a single battle turn, so the wild Pokemon hits back
+async fn attack_encounter(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    Json(payload): Json<AttackWild>)
+    -> Result<Json<AttackResult>, ApiError> {
+    state.writable_pokemon(viewer, payload.pokemon_id)?;
+    let mut encounters = state.encounters.lock().unwrap();
+    let encounter = encounters.iter_mut().find(|e| e.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("encounter {} not found", id)))?;
+    if encounter.status != EncounterStatus::Active {
+        return Err(ApiError::conflict(format!("the wild {} is gone", encounter.pokemon.name)));
+    }
+
+    let mut team = state.team.lock().unwrap();
+    let pokemon = team.iter_mut().find(|p| p.id == payload.pokemon_id)
+        .ok_or_else(|| ApiError::not_found(format!("pokemon {} not found", payload.pokemon_id)))?;
+    if pokemon.fainted {
+        return Err(ApiError::unprocessable(format!("{} has fainted and can't battle", pokemon.name)));
+    }
+
+    let mut battle = battle::Battle::new(vec![pokemon.clone()], vec![encounter.pokemon.clone()]).with_scripts(state.scripts.clone());
+    let chosen = battle.find_move(battle::Side::SideA, &payload.move_name)
+        .ok_or_else(|| ApiError::unprocessable(format!("{} can't use {}", pokemon.name, payload.move_name)))?;
+    let mut rng = state.randomness.rng();
+    let wild_move = battle.ai_move(battle::Side::SideB, &mut rng);
+    let events = battle.play_turn([chosen, wild_move], &mut rng).events.clone();
+
+    // HP and status carry over for both, the battle worked on copies
+    *pokemon = battle.active_pokemon(battle::Side::SideA).clone();
+    encounter.pokemon = battle.active_pokemon(battle::Side::SideB).clone();
+    if encounter.pokemon.fainted {
+        encounter.status = EncounterStatus::Fainted;
+    }
+
+    Ok(Json(AttackResult { events, pokemon: pokemon.clone(), encounter: encounter.clone() }))
+}
+
+// CATCH - throw a ball at a wild Pokemon
+// a catch joins the trainer's team, or their box once the team is full, a miss might make it flee
+async fn catch_encounter(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    Json(payload): Json<CatchPokemon>)
+    -> Result<Json<CatchResult>, ApiError> {
+    viewer.require(payload.trainer_id)?;
+    encounters::catch(&state, id, &payload).map(Json)
+}
+
+// BREED - two compatible Pokemon produce an egg of parent_a's line, it hatches after enough steps
+async fn breed_pokemon(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Json(payload): Json<BreedPokemon>)
+    -> Result<(StatusCode, Json<Egg>), ApiError> {
+    if payload.parent_a == payload.parent_b {
+        return Err(ApiError::bad_request("a pokemon can't breed with itself"));
+    }
+    let parents = state.pick_writable_team(viewer, &[payload.parent_a, payload.parent_b])?;
+    let (a, b) = (&parents[0], &parents[1]);
+    breeding::check_compatible(a, b).map_err(ApiError::unprocessable)?;
+    let trainer_id = {
+        let trainers = state.trainers.lock().unwrap();
+        let trainer = trainers.iter().find(|t| t.owns(a.id));
+        // it'd only have to wait at 0 steps until there's room, better to say so now
+        trainer.map(|t| state.quotas.check_receive(t)).transpose()?;
+        trainer.map(|t| t.id)
+    };
+
+    let mut eggs = state.eggs.lock().unwrap();
+    let new_id = eggs.last().map_or(1, |e| e.id + 1);
+    let egg = breeding::lay(new_id, a, b, trainer_id, &mut state.randomness.rng());
+    eggs.push(egg.clone());
+    Ok((StatusCode::CREATED, Json(egg)))
+}
+
+// READ - all eggs, hatched ones say what they hatched into
+async fn get_all_eggs(State(state): State<SharedState>) -> Json<Vec<Egg>> {
+    let eggs = state.eggs.lock().unwrap();
+    Json(eggs.clone())
+}
+
+async fn get_egg_by_id(State(state): State<SharedState>, Path(id): Path<u32>) -> Result<Json<Egg>, ApiError> {
+    let eggs = state.eggs.lock().unwrap();
+    eggs.iter().find(|e| e.id == id)
+        .cloned()
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("egg {} not found", id)))
+}
+
+// BREED - walk an egg closer to hatching, eggs also pick up steps on their own over time
+async fn walk_egg(
+    State(state): State<SharedState>,
+    Path(id): Path<u32>,
+    Json(payload): Json<WalkEgg>)
+    -> Result<Json<Egg>, ApiError> {
+    if payload.steps == 0 {
+        return Err(ApiError::bad_request("steps must be greater than 0"));
+    }
+    let mut eggs = state.eggs.lock().unwrap();
+    let egg = eggs.iter_mut().find(|e| e.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("egg {} not found", id)))?;
+    if egg.hatched_into.is_some() {
+        return Err(ApiError::conflict("this egg has already hatched"));
+    }
+    breeding::advance(&state, egg, payload.steps);
+    Ok(Json(egg.clone()))
+}
+
+// DAYCARE - leave a Pokemon to gain exp over time
+async fn deposit_pokemon(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Json(payload): Json<DaycarePokemon>)
+    -> Result<(StatusCode, Json<DaycareStay>), ApiError> {
+    state.writable_pokemon(viewer, payload.pokemon_id)?;
+    let mut daycare = state.daycare.lock().unwrap();
+    if daycare.iter().any(|s| s.pokemon_id == payload.pokemon_id) {
+        return Err(ApiError::conflict(format!("pokemon {} is already at the daycare", payload.pokemon_id)));
+    }
+    let team = state.team.lock().unwrap();
+    let pokemon = team.iter().find(|p| p.id == payload.pokemon_id)
+        .ok_or_else(|| ApiError::not_found(format!("pokemon {} not found", payload.pokemon_id)))?;
+    if pokemon.fainted {
+        return Err(ApiError::conflict(format!("{} has fainted and can't gain exp", pokemon.name)));
+    }
+
+    let stay = DaycareStay::new(pokemon, state.time.now());
+    daycare.push(stay.clone());
+    Ok((StatusCode::CREATED, Json(stay)))
+}
+
+// READ - who's at the daycare and how they're doing
+async fn get_daycare(State(state): State<SharedState>) -> Json<Vec<DaycareStay>> {
+    let daycare = state.daycare.lock().unwrap();
+    Json(daycare.clone())
+}
+
+// DAYCARE - pick a Pokemon back up, paying out the exp since the last tick
+async fn withdraw_pokemon(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Json(payload): Json<DaycarePokemon>)
+    -> Result<Json<ExpGainResult>, ApiError> {
+    state.writable_pokemon(viewer, payload.pokemon_id)?;
+    let mut daycare = state.daycare.lock().unwrap();
+    let index = daycare.iter().position(|s| s.pokemon_id == payload.pokemon_id)
+        .ok_or_else(|| ApiError::not_found(format!("pokemon {} isn't at the daycare", payload.pokemon_id)))?;
+    let mut stay = daycare.remove(index);
+
+    let mut team = state.team.lock().unwrap();
+    let pokemon = team.iter_mut().find(|p| p.id == payload.pokemon_id)
+        .ok_or_else(|| ApiError::not_found(format!("pokemon {} not found", payload.pokemon_id)))?;
+    stay.pay(pokemon, state.time.now());
+    Ok(Json(stay.summary(pokemon.clone())))
+}
+
+// both sides still have to own what they're trading
+fn check_trade(trainers: &[Trainer], trade: &ProposeTrade) -> Result<(), ApiError> {
+    for (trainer_id, pokemon_id) in [(trade.from_trainer, trade.offered), (trade.to_trainer, trade.requested)] {
+        let trainer = trainers.iter().find(|t| t.id == trainer_id)
+            .ok_or_else(|| ApiError::not_found(format!("trainer {} not found", trainer_id)))?;
+        if !trainer.owns(pokemon_id) {
+            return Err(ApiError::conflict(format!("{} doesn't own pokemon {}", trainer.name, pokemon_id)));
+        }
+    }
+    Ok(())
+}
+
+// TRADE - offer one of your Pokemon for one of another trainer's
+async fn propose_trade(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Json(payload): Json<ProposeTrade>)
+    -> Result<(StatusCode, Json<Trade>), ApiError> {
+    viewer.require(payload.from_trainer)?;
+    if payload.from_trainer == payload.to_trainer {
+        return Err(ApiError::bad_request("a trainer can't trade with themselves"));
+    }
+    let mut trades = state.trades.lock().unwrap();
+    check_trade(&state.trainers.lock().unwrap(), &payload)?;
+
+    let new_id = trades.last().map_or(1, |t| t.id + 1);
+    let trade = Trade {
+        id: new_id,
+        from_trainer: payload.from_trainer,
+        to_trainer: payload.to_trainer,
+        offered: payload.offered,
+        requested: payload.requested,
+        status: TradeStatus::Pending,
+    };
+    trades.push(trade.clone());
+    {
+        let trainers = state.trainers.lock().unwrap();
+        let from_name = trainers.iter().find(|t| t.id == trade.from_trainer).map(|t| t.name.clone()).unwrap_or_default();
+        if let Some(to) = trainers.iter().find(|t| t.id == trade.to_trainer) {
+            mail::send_trade_offer(&state, to, &from_name, &trade);
+        }
+        inbox::trade_offer(&state, &trade, &from_name);
+    }
+    Ok((StatusCode::CREATED, Json(trade)))
+}
+
+async fn get_trade_by_id(State(state): State<SharedState>, Path(id): Path<u32>) -> Result<Json<Trade>, ApiError> {
+    let trades = state.trades.lock().unwrap();
+    trades.iter().find(|t| t.id == id)
+        .cloned()
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("trade {} not found", id)))
+}
+
+// READ - every trade a trainer offered or was offered, oldest first
+async fn get_trainer_trades(State(state): State<SharedState>, Path(id): Path<u32>) -> Result<Json<Vec<Trade>>, ApiError> {
+    let trades = state.trades.lock().unwrap();
+    if !state.trainers.lock().unwrap().iter().any(|t| t.id == id) {
+        return Err(ApiError::not_found(format!("trainer {} not found", id)));
+    }
+    Ok(Json(trades.iter().filter(|t| t.from_trainer == id || t.to_trainer == id).cloned().collect()))
+}
+
+// a trainer's notifications are only for them, X-Trainer-Id has to say it's them
+fn inbox_owner(state: &SharedState, viewer: Viewer, id: u32) -> Result<(), ApiError> {
+    if !state.trainers.lock().unwrap().iter().any(|t| t.id == id) {
+        return Err(ApiError::not_found(format!("trainer {} not found", id)));
+    }
+    if viewer.0 != Some(id) {
+        return Err(ApiError::forbidden(format!("only trainer {} can see their notifications", id)));
+    }
+    Ok(())
+}
+
+// READ - a trainer's notifications, newest first, ?unread=true for only the unread ones
+async fn get_notifications(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    Query(query): Query<NotificationQuery>)
+    -> Result<Json<Vec<Notification>>, ApiError> {
+    inbox_owner(&state, viewer, id)?;
+    let inbox = state.inbox.lock().unwrap();
+    Ok(Json(inbox.iter().rev()
+        .filter(|n| n.trainer_id == id)
+        .filter(|n| !query.unread.unwrap_or(false) || !n.read)
+        .cloned()
+        .collect()))
+}
+
+// READ - how many haven't been read yet, cheap enough to poll for a badge
+async fn get_unread_count(State(state): State<SharedState>, viewer: Viewer, Path(id): Path<u32>) -> Result<Json<UnreadCount>, ApiError> {
+    inbox_owner(&state, viewer, id)?;
+    let inbox = state.inbox.lock().unwrap();
+    Ok(Json(UnreadCount { unread: inbox.iter().filter(|n| n.trainer_id == id && !n.read).count() }))
+}
+
+// UPDATE - mark one notification read, doing it again is fine
+async fn read_notification(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path((id, notification_id)): Path<(u32, u32)>)
+    -> Result<Json<Notification>, ApiError> {
+    inbox_owner(&state, viewer, id)?;
+    let mut inbox = state.inbox.lock().unwrap();
+    let notification = inbox.iter_mut().find(|n| n.id == notification_id && n.trainer_id == id)
+        .ok_or_else(|| ApiError::not_found(format!("notification {} not found", notification_id)))?;
+    notification.read = true;
+    Ok(Json(notification.clone()))
+}
+
+// UPDATE - mark all of a trainer's notifications read
+async fn read_all_notifications(State(state): State<SharedState>, viewer: Viewer, Path(id): Path<u32>) -> Result<StatusCode, ApiError> {
+    inbox_owner(&state, viewer, id)?;
+    let mut inbox = state.inbox.lock().unwrap();
+    inbox.iter_mut().filter(|n| n.trainer_id == id).for_each(|n| n.read = true);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// the pending trade, if this trainer is the one allowed to act on it
+fn pending_trade(trades: &mut [Trade], id: u32, trainer_id: u32, allowed: u32) -> Result<&mut Trade, ApiError> {
+    let trade = trades.iter_mut().find(|t| t.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("trade {} not found", id)))?;
+    if trainer_id != allowed {
+        return Err(ApiError::forbidden(format!("trainer {} can't do that to trade {}", trainer_id, id)));
+    }
+    if trade.status != TradeStatus::Pending {
+        return Err(ApiError::conflict(format!("trade {} is no longer pending", id)));
+    }
+    Ok(trade)
+}
+
+// TRADE - to_trainer accepts, both Pokemon change hands in one go
+// each one takes the other's place on the team (or in the box)
+async fn accept_trade(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    Json(payload): Json<TrainerAction>)
+    -> Result<Json<Trade>, ApiError> {
+    viewer.require(payload.trainer_id)?;
+    let mut trades = state.trades.lock().unwrap();
+    let to_trainer = trades.iter().find(|t| t.id == id).map_or(0, |t| t.to_trainer);
+    let trade = pending_trade(&mut trades, id, payload.trainer_id, to_trainer)?;
+
+    let mut team = state.team.lock().unwrap();
+    let mut trainers = state.trainers.lock().unwrap();
+    let proposal = ProposeTrade { from_trainer: trade.from_trainer, to_trainer: trade.to_trainer, offered: trade.offered, requested: trade.requested };
+    check_trade(&trainers, &proposal)?;
+
+    let mut names = [String::new(), String::new()];
+    for trainer in trainers.iter_mut() {
+        let (give, take, slot) = if trainer.id == trade.from_trainer {
+            (trade.offered, trade.requested, 0)
+        } else if trainer.id == trade.to_trainer {
+            (trade.requested, trade.offered, 1)
+        } else {
+            continue;
+        };
+        for owned in trainer.team.iter_mut().chain(trainer.pc_box.iter_mut()) {
+            if *owned == give {
+                *owned = take;
+            }
+        }
+        // whoever the old owner let use it has to ask the new one
+        trainer.permissions.retain(|p| p.pokemon_id != Some(give));
+        names[slot] = trainer.name.clone();
+    }
+
+    let [from_name, to_name] = names;
+    for pokemon in team.iter_mut() {
+        let (from, to) = if pokemon.id == trade.offered {
+            (&from_name, &to_name)
+        } else if pokemon.id == trade.requested {
+            (&to_name, &from_name)
+        } else {
+            continue;
+        };
+        pokemon.history.push(HistoryEvent::Traded { from: from.clone(), to: to.clone() });
+    }
+    for (trainer_id, pokemon_id) in [(trade.from_trainer, trade.requested), (trade.to_trainer, trade.offered)] {
+        if let Some(pokemon) = team.iter().find(|p| p.id == pokemon_id) {
+            achievements::record(&state, trainer_id, Event::Obtained(&pokemon.species));
+        }
+    }
+
+    trade.status = TradeStatus::Accepted;
+    state.notifier.notify(notify::Notice::TradeCompleted { trade: trade.clone(), from_name, to_name });
+    Ok(Json(trade.clone()))
+}
+
+// TRADE - to_trainer turns it down
+async fn decline_trade(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    Json(payload): Json<TrainerAction>)
+    -> Result<Json<Trade>, ApiError> {
+    viewer.require(payload.trainer_id)?;
+    let mut trades = state.trades.lock().unwrap();
+    let to_trainer = trades.iter().find(|t| t.id == id).map_or(0, |t| t.to_trainer);
+    let trade = pending_trade(&mut trades, id, payload.trainer_id, to_trainer)?;
+    trade.status = TradeStatus::Declined;
+    Ok(Json(trade.clone()))
+}
+
+// TRADE - from_trainer takes the offer back
+async fn cancel_trade(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    Json(payload): Json<TrainerAction>)
+    -> Result<Json<Trade>, ApiError> {
+    viewer.require(payload.trainer_id)?;
+    let mut trades = state.trades.lock().unwrap();
+    let from_trainer = trades.iter().find(|t| t.id == id).map_or(0, |t| t.from_trainer);
+    let trade = pending_trade(&mut trades, id, payload.trainer_id, from_trainer)?;
+    trade.status = TradeStatus::Cancelled;
+    Ok(Json(trade.clone()))
+}
+
+// READ - what the shop sells and for how much
+async fn get_shop() -> Json<Vec<ShopItem>> {
+    Json(market::catalog())
+}
+
+// BUY - balls from the shop, paid for out of the trainer's money
+async fn buy_item(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Json(payload): Json<BuyItem>)
+    -> Result<Json<Trainer>, ApiError> {
+    viewer.require(payload.trainer_id)?;
+    let quantity = payload.quantity.unwrap_or(1);
+    if quantity == 0 {
+        return Err(ApiError::bad_request("quantity must be at least 1"));
+    }
+    let price = market::price(payload.item)
+        .ok_or_else(|| ApiError::unprocessable(format!("{:?} Balls aren't for sale", payload.item)))?;
+    let cost = price.checked_mul(quantity)
+        .ok_or_else(|| ApiError::bad_request("that's more than anyone could pay for"))?;
+
+    let mut trainers = state.trainers.lock().unwrap();
+    let trainer = trainers.iter_mut().find(|t| t.id == payload.trainer_id)
+        .ok_or_else(|| ApiError::not_found(format!("trainer {} not found", payload.trainer_id)))?;
+    if trainer.money < cost {
+        return Err(ApiError::conflict(format!("that costs {} but {} only has {}", cost, trainer.name, trainer.money)));
+    }
+    trainer.money -= cost;
+    let balls = trainer.bag.entry(payload.item).or_default();
+    *balls = balls.saturating_add(quantity);
+    Ok(Json(trainer.clone()))
+}
+
+// SELL - put one of your Pokemon on the market for a fixed price
+async fn list_pokemon(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Json(payload): Json<ListPokemon>)
+    -> Result<(StatusCode, Json<Listing>), ApiError> {
+    viewer.require(payload.trainer_id)?;
+    if payload.price == 0 {
+        return Err(ApiError::bad_request("price must be at least 1"));
+    }
+    let mut market = state.market.lock().unwrap();
+    let team = state.team.lock().unwrap();
+    let trainers = state.trainers.lock().unwrap();
+    let trainer = trainers.iter().find(|t| t.id == payload.trainer_id)
+        .ok_or_else(|| ApiError::not_found(format!("trainer {} not found", payload.trainer_id)))?;
+    if !trainer.owns(payload.pokemon_id) {
+        return Err(ApiError::conflict(format!("{} doesn't own pokemon {}", trainer.name, payload.pokemon_id)));
+    }
+    if market.iter().any(|l| l.pokemon_id == payload.pokemon_id && l.status == ListingStatus::Listed) {
+        return Err(ApiError::conflict(format!("pokemon {} is already on the market", payload.pokemon_id)));
+    }
+    let pokemon = team.iter().find(|p| p.id == payload.pokemon_id)
+        .ok_or_else(|| ApiError::not_found(format!("pokemon {} not found", payload.pokemon_id)))?;
+
+    let new_id = market.last().map_or(1, |l| l.id + 1);
+    let listing = Listing {
+        id: new_id,
+        seller: trainer.id,
+        pokemon_id: pokemon.id,
+        name: pokemon.name.clone(),
+        level: pokemon.level,
+        price: payload.price,
+        status: ListingStatus::Listed,
+        buyer: None,
+    };
+    market.push(listing.clone());
+    Ok((StatusCode::CREATED, Json(listing)))
+}
+
+// READ - everything still for sale
+async fn get_market(State(state): State<SharedState>) -> Json<Vec<Listing>> {
+    let market = state.market.lock().unwrap();
+    Json(market.iter().filter(|l| l.status == ListingStatus::Listed).cloned().collect())
+}
+
+async fn get_listing(State(state): State<SharedState>, Path(id): Path<u32>) -> Result<Json<Listing>, ApiError> {
+    let market = state.market.lock().unwrap();
+    market.iter().find(|l| l.id == id)
+        .cloned()
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("listing {} not found", id)))
+}
+
+// BUY - pay the asking price, the money and the Pokemon change hands in one go
+async fn buy_listing(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    Json(payload): Json<TrainerAction>)
+    -> Result<Json<Listing>, ApiError> {
+    viewer.require(payload.trainer_id)?;
+    let mut market = state.market.lock().unwrap();
+    let mut team = state.team.lock().unwrap();
+    let mut trainers = state.trainers.lock().unwrap();
+    let listing = market.iter_mut().find(|l| l.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("listing {} not found", id)))?;
+    if listing.status != ListingStatus::Listed {
+        return Err(ApiError::conflict(format!("listing {} is no longer for sale", id)));
+    }
+    if listing.seller == payload.trainer_id {
+        return Err(ApiError::bad_request("a trainer can't buy their own pokemon"));
+    }
+
+    let buyer = trainers.iter().position(|t| t.id == payload.trainer_id)
+        .ok_or_else(|| ApiError::not_found(format!("trainer {} not found", payload.trainer_id)))?;
+    let seller = trainers.iter().position(|t| t.id == listing.seller && t.owns(listing.pokemon_id))
+        .ok_or_else(|| ApiError::conflict(format!("the seller doesn't have pokemon {} anymore", listing.pokemon_id)))?;
+    state.quotas.check_receive(&trainers[buyer])?;
+    if trainers[buyer].money < listing.price {
+        return Err(ApiError::conflict(format!("{} can't afford {}", trainers[buyer].name, listing.price)));
+    }
+
+    trainers[buyer].money -= listing.price;
+    state.quotas.receive(&mut trainers[buyer], listing.pokemon_id);
+    trainers[seller].money = trainers[seller].money.saturating_add(listing.price);
+    trainers[seller].release(listing.pokemon_id);
+    if let Some(pokemon) = team.iter_mut().find(|p| p.id == listing.pokemon_id) {
+        pokemon.history.push(HistoryEvent::Sold {
+            from: trainers[seller].name.clone(),
+            to: trainers[buyer].name.clone(),
+            price: listing.price,
+        });
+        achievements::record(&state, payload.trainer_id, Event::Obtained(&pokemon.species));
+    }
+
+    listing.status = ListingStatus::Sold;
+    listing.buyer = Some(payload.trainer_id);
+    Ok(Json(listing.clone()))
+}
+
+// WITHDRAW - the seller takes their Pokemon off the market
+async fn withdraw_listing(
+    State(state): State<SharedState>,
+    viewer: Viewer,
+    Path(id): Path<u32>,
+    Json(payload): Json<TrainerAction>)
+    -> Result<Json<Listing>, ApiError> {
+    viewer.require(payload.trainer_id)?;
+    let mut market = state.market.lock().unwrap();
+    let listing = market.iter_mut().find(|l| l.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("listing {} not found", id)))?;
+    if listing.seller != payload.trainer_id {
+        return Err(ApiError::forbidden(format!("trainer {} can't withdraw listing {}", payload.trainer_id, id)));
+    }
+    if listing.status != ListingStatus::Listed {
+        return Err(ApiError::conflict(format!("listing {} is no longer for sale", id)));
+    }
+    listing.status = ListingStatus::Withdrawn;
+    Ok(Json(listing.clone()))
+}
+
+// READ - what a trainer has earned so far, and the counts the rules go by
+async fn get_trainer_achievements(State(state): State<SharedState>, Path(id): Path<u32>) -> Result<Json<Progress>, ApiError> {
+    if !state.trainers.lock().unwrap().iter().any(|t| t.id == id) {
+        return Err(ApiError::not_found(format!("trainer {} not found", id)));
+    }
+    let achievements = state.achievements.lock().unwrap();
+    let progress = achievements.iter().find(|p| p.trainer_id == id).cloned();
+    Ok(Json(progress.unwrap_or_else(|| Progress::new(id))))
+}
+
+// READ - the top 10 for highest_level, battles_won or collection, refreshed every 10 seconds
+async fn get_leaderboard(State(state): State<SharedState>, Path(metric): Path<String>) -> Result<Json<Board>, ApiError> {
+    let Some(parsed) = Metric::parse(&metric) else {
+        return Err(ApiError::not_found(format!("no leaderboard for {}, try highest_level, battles_won or collection", metric)));
+    };
+    let entries = state.leaderboards.lock().unwrap().get(parsed).to_vec();
+    Ok(Json(Board { metric, entries }))
+}
@@ -1,132 +1,140 @@
-mod models;
+use std::net::SocketAddr;
 
-use axum::{
-    routing::{get, post, put, delete},
-    Router,
-};
+use rust_hello_http::recording::{self, Recorder};
+use rust_hello_http::runtime::RuntimeConfig;
+use rust_hello_http::{connections, console, memory, secrets, seed, version, AppBuilder};
 
-use std::sync::{Arc, Mutex};
-use models::{Pokemon, CreatePokemon, UpdatePokemon};
+// counts allocations for GET /admin/memory and /metrics
+#[global_allocator]
+static ALLOCATOR: memory::CountingAllocator = memory::CountingAllocator;
 
-// shared state: a list of Pokemon protected by a Mutex
-type SharedState = Arc<Mutex<Vec<Pokemon>>>;
+// WORKER_THREADS and co. pick the runtime's threads, so it's built by hand rather than with #[tokio::main]
+fn main() {
+    let config = RuntimeConfig::from_env().unwrap_or_else(|e| panic!("{}", e));
+    let runtime = config.build().unwrap_or_else(|e| panic!("can't start the tokio runtime: {}", e));
+    runtime.block_on(serve());
+}
 
-#[tokio::main]
-async fn main() {
-    // start with an empty team
-    let state: SharedState = Arc::new(Mutex::new(Vec::new()));
-
-    // build app with a router
-    let app = Router::new()
-        .route("/", get(root))
-        .route("/pokemon", get(get_all_pokemon))
-        .route("/pokemon", post(create_pokemon))
-        .route("/pokemon/{id}", get(get_pokemon_by_id))
-        .route("/pokemon/{id}", put(update_pokemon))
-        .route("/pokemon/{id}", delete(delete_pokemon))
-        .with_state(state);
+async fn serve() {
+    // from Vault or SOPS, before anything reads a secret
+    let loaded = secrets::load().await.unwrap_or_else(|e| panic!("{}", e));
 
-    // run server on localhost:3000
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:3000").await.unwrap();
+    // `cargo run -- replay traffic.jsonl` sends a recording to a fresh server, and says what came back different
+    if std::env::args().nth(1).as_deref() == Some("replay") {
+        std::process::exit(replay(std::env::args().nth(2), std::env::args().nth(3)).await);
+    }
 
-    println!("Server running on http://127.0.0.1:3000");
-    println!("Try: curl http://localhost:3000/pokemon");
+    println!("{}", version::banner());
+    if !loaded.is_empty() {
+        println!("Secrets loaded: {}", loaded.join(", "));
+    }
 
-    axum::serve(listener, app).await.unwrap();
-}
+    // only built with --features tokio-console
+    #[cfg(feature = "tokio-console")]
+    rust_hello_http::runtime::start_console();
 
-// health check main page function - not part of CRUD
-async fn root() -> &'static str {
-    "Pokemon Team API - Try GET /pokemon"
-}
+    // start with an empty team
+    let builder = AppBuilder::from_env().unwrap_or_else(|e| panic!("{}", e));
+    // on the main port unless ADMIN_ADDR puts it somewhere else
+    let admin_addr = std::env::var("ADMIN_ADDR").ok();
+    let app = builder.separate_admin(admin_addr.is_some()).build();
+    let state = &app.state;
+    if !state.scripts.files().is_empty() {
+        println!("Loaded scripts: {}", state.scripts.files().join(", "));
+    }
+    if !state.plugins.names().is_empty() {
+        println!("Plugins: {}", state.plugins.names().join(", "));
+    }
 
-use axum::{
-    extract::{State, Json, Path},
-    http::StatusCode
-};
-
-// CREATE - Add a new Pokemon
-// declare we have extractor for state + destructure
-async fn create_pokemon(
-    State(state): State<SharedState>,
-    Json(payload): Json<CreatePokemon>,
-    ) -> (StatusCode, Json<Pokemon>){
-    let mut team = state.lock().unwrap();
-
-    // create new id for indexing
-    let new_id: u32 = if let Some(last_pokemon) = team.last(){
-        last_pokemon.id + 1
-    } else {
-        1
-    };
+    // `cargo run -- seed` starts with the demo data already loaded
+    match std::env::args().nth(1).as_deref() {
+        None => {}
+        Some("seed") => {
+            let report = seed::load(state);
+            println!("Seeded {} Pokemon and {} trainers", report.pokemon_created, report.trainers_created);
+        }
+        Some(other) => {
+            eprintln!("unknown command {:?}, it's `seed` or `replay`", other);
+            std::process::exit(2);
+        }
+    }
 
-    let new_pokemon: Pokemon = Pokemon{
-        id: new_id,
-        name: payload.name,
-        poke_type: payload.poke_type,
-        level: payload.level,
-    };
+    if let (Some(addr), Some(admin_app)) = (admin_addr, app.admin.clone()) {
+        let listener = tokio::net::TcpListener::bind(&addr).await
+            .unwrap_or_else(|e| panic!("can't listen on ADMIN_ADDR {}: {}", addr, e));
+        println!("Admin API on http://{}/admin", addr);
+        let listener = connections::listen(listener, state.clone());
+        tokio::spawn(async move { axum::serve(listener, admin_app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap() });
+    }
 
-    // solves the problem of sending one copy to vec and the other back as payload    
-    team.push(new_pokemon.clone());
+    // a console on this machine only, to look around and flip modes without going through HTTP
+    if let Ok(addr) = std::env::var("CONSOLE_ADDR") {
+        let on = console::spawn(state.clone(), &addr).await
+            .unwrap_or_else(|e| panic!("can't start the console on CONSOLE_ADDR {}: {}", addr, e));
+        println!("Admin console on {}", on);
+    }
 
-    (StatusCode::CREATED, Json(new_pokemon))
-}
+    // only built with --features telegram
+    #[cfg(feature = "telegram")]
+    if let Some(telegram) = rust_hello_http::bot::Telegram::from_env() {
+        rust_hello_http::bot::spawn(state.clone(), telegram);
+        println!("Telegram bot on");
+    }
 
-// READ - get all pokemons
-async fn get_all_pokemon(State(state): State<SharedState>) -> Json<Vec<Pokemon>>{
-    let team = state.lock().unwrap();
-    // can't move vector out of mutex so we clone
-    Json(team.clone())
-}
+    app.spawn_jobs();
 
-// READ - Get one Pokemon by ID
-// return result since we might not find any id matching
-async fn get_pokemon_by_id(State(state): State<SharedState>, Path(id): Path<u32>) -> Result<Json<Pokemon>, StatusCode> {
-    let team = state.lock().unwrap();
+    // run server on localhost:3000
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:3000").await.unwrap();
+    // MAX_CONNECTIONS counts these and the admin port's together
+    let listener = connections::listen(listener, state.clone());
 
-    if let Some(pokemon) = team.iter().find(|p| p.id == id) {
-        Ok(Json(pokemon.clone()))
-    } else{
-        Err(StatusCode::NOT_FOUND)
-    }
+    println!("Server running on http://127.0.0.1:3000");
+    println!("Try: curl http://localhost:3000/pokemon");
+
+    // the peer address, for finding the real client behind TRUSTED_PROXIES
+    axum::serve(listener, app.router.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
 }
 
-async fn update_pokemon(
-    State(state): State<SharedState>,
-    Path(id): Path<u32>,
-    Json(payload): Json<UpdatePokemon>)
-    -> Result<Json<Pokemon>, StatusCode> {
-    // update require mutable mutexguard (roleplaying as vec)
-    let mut team = state.lock().unwrap();
-
-    if let Some(pokemon) = team.iter_mut().find(|p| p.id == id){
-        // any way we can reduce LOC here since we're just testing if not null
-        if let Some(name) = payload.name{
-            pokemon.name = name;
+// against the server at `against` if there is one, with ADMIN_TOKEN for the admin requests
+// the exit code is 1 if anything came back different
+async fn replay(path: Option<String>, against: Option<String>) -> i32 {
+    let Some(path) = path else {
+        eprintln!("usage: rust-hello-http replay <recording.jsonl> [url]");
+        return 2;
+    };
+    let exchanges = match recording::read(&path) {
+        Ok(exchanges) => exchanges,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
         }
-        if let Some(poke_type) = payload.poke_type{
-            pokemon.poke_type = poke_type;
+    };
+    let (url, token) = match against {
+        Some(url) => (url, secrets::var("ADMIN_TOKEN").ok()),
+        None => {
+            // configured like the real one, but nothing's recorded and there's nothing on a timer
+            let token = format!("{:032x}", rand::random::<u128>());
+            let builder = AppBuilder::from_env().unwrap_or_else(|e| panic!("{}", e));
+            let app = builder.recorder(Recorder::default()).admin_token(Some(token.clone())).build();
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("a free port");
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            tokio::spawn(async move { axum::serve(listener, app.router.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap() });
+            (url, Some(token))
         }
-        if let Some(level) = payload.level{
-            pokemon.level = level;
+    };
+    let report = match recording::replay(&url, &exchanges, token.as_deref()).await {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    for mismatch in &report.mismatches {
+        println!("#{} {} {}: expected {}, got {}", mismatch.exchange, mismatch.method, mismatch.uri, mismatch.expected_status, mismatch.status);
+        for field in &mismatch.differences {
+            println!("    {} is different", field);
         }
-
-        Ok(Json(pokemon.clone()))
-    } else{
-        Err(StatusCode::NOT_FOUND)
     }
+    println!("Replayed {} requests: {} matched, {} different, {} skipped", report.replayed, report.matched, report.mismatches.len(), report.skipped);
+    i32::from(!report.mismatches.is_empty())
 }
-async fn delete_pokemon(State(state): State<SharedState>, Path(id): Path<u32>) -> StatusCode {
-    let mut team = state.lock().unwrap();
-    let original_len = team.len();
-
-    // retain = keep item that satisfies the following condition
-    team.retain(|p| p.id != id);
-    
-    if team.len() < original_len {
-        StatusCode::NO_CONTENT  // 204 - Successfully deleted
-    } else {
-        StatusCode::NOT_FOUND   // 404 - Pokemon wasn't there
-    }
-}
\ No newline at end of file
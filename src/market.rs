@@ -0,0 +1,39 @@
+use serde::Serialize;
+
+use crate::encounters::Ball;
+
+pub use pokemon_models::market::*;
+
+#[derive(Debug, Serialize)]
+pub struct ShopItem{
+    pub item: Ball,
+    pub price: u32,
+}
+
+pub fn catalog() -> Vec<ShopItem> {
+    [Ball::Poke, Ball::Great, Ball::Ultra, Ball::Master].into_iter()
+        .filter_map(|item| Some(ShopItem { item, price: price(item)? }))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListingStatus{
+    Listed,
+    Sold,
+    Withdrawn,
+}
+
+// a Pokemon a trainer put up for sale, anyone else can buy it for the asking price
+#[derive(Debug, Clone, Serialize)]
+pub struct Listing{
+    pub id: u32,
+    pub seller: u32,
+    pub pokemon_id: u32,
+    // what it was when listed, so buyers can browse without looking each one up
+    pub name: String,
+    pub level: u32,
+    pub price: u32,
+    pub status: ListingStatus,
+    pub buyer: Option<u32>,
+}
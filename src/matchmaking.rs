@@ -0,0 +1,117 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::battle::Side;
+use crate::models::Pokemon;
+use crate::session;
+use crate::state::SharedState;
+use crate::viewer::Viewer;
+
+// how far apart two teams' average levels can be right after joining
+const LEVEL_RANGE: f32 = 5.0;
+
+// the range widens by a level every few seconds spent waiting, up to a limit
+const RANGE_STEP: Duration = Duration::from_secs(5);
+const MAX_LEVEL_RANGE: f32 = 20.0;
+
+// give up on finding an opponent after this long
+pub const QUEUE_TIMEOUT: Duration = Duration::from_secs(120);
+
+pub const MATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TicketStatus{
+    Waiting,
+    Matched,
+    TimedOut,
+    Cancelled,
+}
+
+// a trainer's place in the queue, polled until it's matched
+#[derive(Debug, Clone, Serialize)]
+pub struct Ticket{
+    pub id: u32,
+    pub trainer_id: u32,
+    pub team: Vec<u32>,
+    // average level of the team when it joined
+    pub team_level: f32,
+    pub status: TicketStatus,
+    // once matched: the battle session to connect to and which side to take
+    pub session_id: Option<u32>,
+    pub side: Option<Side>,
+    // unix seconds
+    #[serde(skip)]
+    pub queued_at: u64,
+}
+
+impl Ticket{
+    // level difference this ticket accepts, so nobody waits forever for a perfect match
+    fn level_range(&self, now: u64) -> f32 {
+        let steps = now.saturating_sub(self.queued_at) / RANGE_STEP.as_secs();
+        (LEVEL_RANGE + steps as f32).min(MAX_LEVEL_RANGE)
+    }
+}
+
+pub fn average_level(team: &[Pokemon]) -> f32 {
+    team.iter().map(|p| p.level as f32).sum::<f32>() / team.len().max(1) as f32
+}
+
+// a team that can still battle
+pub fn battle_ready(team: &[Pokemon]) -> bool {
+    !team.is_empty() && team.iter().any(|p| !p.fainted)
+}
+
+// pairs waiting tickets and expires old ones, every MATCH_INTERVAL
+pub fn run_matchmaking(state: &SharedState, now: u64) {
+    let mut tickets = state.tickets.lock().unwrap();
+
+    for ticket in tickets.iter_mut() {
+        if ticket.status == TicketStatus::Waiting && now.saturating_sub(ticket.queued_at) >= QUEUE_TIMEOUT.as_secs() {
+            ticket.status = TicketStatus::TimedOut;
+        }
+    }
+
+    // oldest first, each one takes the closest match both sides are happy with
+    let waiting: Vec<usize> = (0..tickets.len())
+        .filter(|i| tickets[*i].status == TicketStatus::Waiting)
+        .collect();
+    let mut taken: Vec<usize> = Vec::new();
+    for (n, &i) in waiting.iter().enumerate() {
+        if taken.contains(&i) {
+            continue;
+        }
+        let gap = |j: usize| (tickets[i].team_level - tickets[j].team_level).abs();
+        let partner = waiting[n + 1..].iter()
+            .copied()
+            .filter(|j| !taken.contains(j))
+            .filter(|j| gap(*j) <= tickets[i].level_range(now).min(tickets[*j].level_range(now)))
+            .min_by(|a, b| gap(*a).total_cmp(&gap(*b)));
+        let Some(j) = partner else { continue };
+
+        // teams may have changed since joining, a team that can't battle anymore drops out
+        let team = |ticket: &Ticket| state.pick_team(Viewer(Some(ticket.trainer_id)), &ticket.team).ok().filter(|t| battle_ready(t));
+        let (side_a, side_b) = (team(&tickets[i]), team(&tickets[j]));
+        match (side_a, side_b) {
+            (Some(side_a), Some(side_b)) => {
+                let session = session::create(state, side_a, side_b);
+                for (index, side) in [(i, Side::SideA), (j, Side::SideB)] {
+                    let ticket = &mut tickets[index];
+                    ticket.status = TicketStatus::Matched;
+                    ticket.session_id = Some(session.id);
+                    ticket.side = Some(side);
+                }
+                taken.extend([i, j]);
+            }
+            (side_a, side_b) => {
+                for (index, team) in [(i, side_a), (j, side_b)] {
+                    if team.is_none() {
+                        tickets[index].status = TicketStatus::Cancelled;
+                        taken.push(index);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use axum::extract::State;
+use axum::http::header;
+use axum::middleware;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use serde::Serialize;
+
+use crate::admin;
+use crate::state::SharedState;
+
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+// records serialized to guess how much each collection owns beyond the records themselves
+const SAMPLE: usize = 32;
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+static IN_USE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static DEALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+// the system allocator, counting what goes through it
+// the binary installs it with #[global_allocator], without it there are no allocator stats
+pub struct CountingAllocator;
+
+fn allocated(size: usize) {
+    INSTALLED.store(true, Ordering::Relaxed);
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    let in_use = IN_USE.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(in_use, Ordering::Relaxed);
+}
+
+fn freed(size: usize) {
+    DEALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    IN_USE.fetch_sub(size, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for CountingAllocator{
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        freed(layout.size());
+    }
+
+    // counted as freeing the old block and allocating the new one
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = System.realloc(ptr, layout, new_size);
+        if !new.is_null() {
+            freed(layout.size());
+            allocated(new_size);
+        }
+        new
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AllocatorStats{
+    // bytes handed out and not given back yet
+    pub in_use_bytes: usize,
+    pub peak_bytes: usize,
+    pub allocations: u64,
+    pub deallocations: u64,
+}
+
+// None unless the CountingAllocator is the global allocator
+pub fn allocator() -> Option<AllocatorStats> {
+    INSTALLED.load(Ordering::Relaxed).then(|| AllocatorStats {
+        in_use_bytes: IN_USE.load(Ordering::Relaxed),
+        peak_bytes: PEAK.load(Ordering::Relaxed),
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+        deallocations: DEALLOCATIONS.load(Ordering::Relaxed),
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CollectionSize{
+    pub name: &'static str,
+    pub records: usize,
+    // the records plus a guess at their strings and lists, from the size of a few as JSON
+    pub approx_bytes: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryStats{
+    pub records: usize,
+    pub store_bytes: usize,
+    pub collections: Vec<CollectionSize>,
+    pub allocator: Option<AllocatorStats>,
+    pub warn_bytes: Option<usize>,
+    pub warn_records: Option<usize>,
+    // past either threshold
+    pub over: bool,
+}
+
+// MEMORY_WARN_MB and MEMORY_WARN_RECORDS, the memory task warns once they're crossed
+#[derive(Debug, Default)]
+pub struct Memory{
+    warn_bytes: Option<usize>,
+    warn_records: Option<usize>,
+    // so crossing is said once, not every check
+    over: Mutex<bool>,
+}
+
+impl Memory{
+    pub fn warn_at(bytes: Option<usize>, records: Option<usize>) -> Memory {
+        Memory { warn_bytes: bytes, warn_records: records, ..Memory::default() }
+    }
+
+    pub fn from_env() -> Result<Memory, String> {
+        let number = |key: &str| match std::env::var(key) {
+            Ok(value) => value.parse::<usize>().map(Some).map_err(|_| format!("{} should be a number, not {:?}", key, value)),
+            Err(_) => Ok(None),
+        };
+        Ok(Memory::warn_at(number("MEMORY_WARN_MB")?.map(|mb| mb * 1024 * 1024), number("MEMORY_WARN_RECORDS")?))
+    }
+}
+
+fn size<T: Serialize>(name: &'static str, records: &Mutex<Vec<T>>) -> CollectionSize {
+    let records = records.lock().unwrap();
+    let step = (records.len() / SAMPLE).max(1);
+    let sampled: Vec<usize> = records.iter().step_by(step).take(SAMPLE)
+        .map(|r| serde_json::to_vec(r).map_or(0, |json| json.len()))
+        .collect();
+    let owned = sampled.iter().sum::<usize>() / sampled.len().max(1) * records.len();
+    CollectionSize { name, records: records.len(), approx_bytes: records.capacity() * std::mem::size_of::<T>() + owned }
+}
+
+pub fn stats(state: &SharedState) -> MemoryStats {
+    // one lock at a time
+    let collections = vec![
+        size("pokemon", &state.team),
+        size("trainers", &state.trainers),
+        size("gyms", &state.gyms),
+        size("trades", &state.trades),
+        size("achievements", &state.achievements),
+        size("market", &state.market),
+        size("battles", &state.battles),
+        size("sessions", &state.sessions),
+        size("tickets", &state.tickets),
+        size("tournaments", &state.tournaments),
+        size("encounters", &state.encounters),
+        size("eggs", &state.eggs),
+        size("daycare", &state.daycare),
+        size("trash", &state.trash),
+        size("inbox", &state.inbox),
+    ];
+    let records = collections.iter().map(|c| c.records).sum();
+    let store_bytes = collections.iter().map(|c| c.approx_bytes).sum();
+    let allocator = allocator();
+    // the real heap when it's counted, the guess otherwise
+    let bytes = allocator.as_ref().map_or(store_bytes, |a| a.in_use_bytes);
+    let memory = &state.memory;
+    let over = memory.warn_bytes.is_some_and(|max| bytes >= max) || memory.warn_records.is_some_and(|max| records >= max);
+    MemoryStats { records, store_bytes, collections, allocator, warn_bytes: memory.warn_bytes, warn_records: memory.warn_records, over }
+}
+
+// the scheduler's memory task, an error while over a threshold so it shows in GET /admin/tasks
+pub fn check(state: &SharedState) -> Result<(), String> {
+    let stats = stats(state);
+    let mut was_over = state.memory.over.lock().unwrap();
+    if !stats.over {
+        if *was_over {
+            eprintln!("memory: back under the thresholds, {} records", stats.records);
+        }
+        *was_over = false;
+        return Ok(());
+    }
+    let heap = stats.allocator.as_ref().map_or(stats.store_bytes, |a| a.in_use_bytes);
+    let message = format!("over the thresholds: {} records and {} MB", stats.records, heap / (1024 * 1024));
+    if !*was_over {
+        eprintln!("memory: {}", message);
+    }
+    *was_over = true;
+    Err(message)
+}
+
+// Prometheus' text format
+pub fn prometheus(stats: &MemoryStats) -> String {
+    let mut out = String::new();
+    let mut metric = |name: &str, help: &str, kind: &str, values: Vec<(String, String)>| {
+        let _ = writeln!(out, "# HELP pokemon_api_{} {}\n# TYPE pokemon_api_{} {}", name, help, name, kind);
+        for (labels, value) in values {
+            let _ = writeln!(out, "pokemon_api_{}{} {}", name, labels, value);
+        }
+    };
+    let by_collection = |value: fn(&CollectionSize) -> usize| {
+        stats.collections.iter().map(|c| (format!("{{collection=\"{}\"}}", c.name), value(c).to_string())).collect()
+    };
+    metric("records", "Records kept in memory.", "gauge", by_collection(|c| c.records));
+    metric("store_bytes", "Approximate bytes the records take up.", "gauge", by_collection(|c| c.approx_bytes));
+    metric("memory_over_threshold", "1 past MEMORY_WARN_MB or MEMORY_WARN_RECORDS.", "gauge", vec![(String::new(), (stats.over as u8).to_string())]);
+    if let Some(allocator) = &stats.allocator {
+        metric("heap_in_use_bytes", "Bytes allocated and not freed yet.", "gauge", vec![(String::new(), allocator.in_use_bytes.to_string())]);
+        metric("heap_peak_bytes", "The most bytes in use at once.", "gauge", vec![(String::new(), allocator.peak_bytes.to_string())]);
+        metric("allocations_total", "Allocations since the server started.", "counter", vec![(String::new(), allocator.allocations.to_string())]);
+        metric("deallocations_total", "Deallocations since the server started.", "counter", vec![(String::new(), allocator.deallocations.to_string())]);
+    }
+    out
+}
+
+// behind the admin token like the admin API
+pub fn routes(state: &SharedState) -> Router<SharedState> {
+    Router::new()
+        .route("/metrics", get(get_metrics))
+        .route_layer(middleware::from_fn_with_state(state.clone(), admin::require_token))
+}
+
+// READ - the memory stats for Prometheus to scrape
+async fn get_metrics(State(state): State<SharedState>) -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], prometheus(&stats(&state)))
+}
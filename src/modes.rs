@@ -0,0 +1,124 @@
+use std::sync::Mutex;
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::ApiError;
+use crate::state::SharedState;
+
+// what clients are told to wait unless the operator says otherwise
+pub const DEFAULT_RETRY_AFTER: u64 = 300;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode{
+    #[default]
+    Normal,
+    // reads work, anything that would change something gets 403
+    ReadOnly,
+    // 503 for everything but the health check
+    Maintenance,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModeSettings{
+    pub mode: Mode,
+    // seconds, for the Retry-After header in maintenance mode
+    #[serde(default = "default_retry_after")]
+    pub retry_after: u64,
+    // shown to clients instead of the default message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+fn default_retry_after() -> u64 {
+    DEFAULT_RETRY_AFTER
+}
+
+impl Default for ModeSettings{
+    fn default() -> Self {
+        ModeSettings { mode: Mode::Normal, retry_after: DEFAULT_RETRY_AFTER, message: None }
+    }
+}
+
+// the current mode, switched at runtime through PUT /admin/mode, a signal or the console
+#[derive(Debug, Default)]
+pub struct Modes{
+    current: Mutex<ModeSettings>,
+}
+
+impl Modes{
+    pub fn get(&self) -> ModeSettings {
+        self.current.lock().unwrap().clone()
+    }
+
+    pub fn set(&self, settings: ModeSettings) {
+        *self.current.lock().unwrap() = settings;
+    }
+
+    // into `mode`, or back to normal if it's already in it
+    pub fn toggle(&self, mode: Mode) -> Mode {
+        let mut current = self.current.lock().unwrap();
+        current.mode = if current.mode == mode { Mode::Normal } else { mode };
+        current.message = None;
+        current.mode
+    }
+}
+
+// the health checks and the admin API always get through, so the mode can be switched back
+// /readyz says maintenance itself, with a 503 of its own
+fn exempt(request: &Request) -> bool {
+    let path = request.uri().path();
+    path == "/" || path == "/readyz" || path == "/admin" || path.starts_with("/admin/")
+}
+
+fn is_read(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+// middleware for the whole app
+pub async fn enforce(State(state): State<SharedState>, request: Request, next: Next) -> Response {
+    let settings = state.modes.get();
+    if exempt(&request) {
+        return next.run(request).await;
+    }
+    match settings.mode {
+        Mode::Normal => next.run(request).await,
+        Mode::ReadOnly if is_read(request.method()) => next.run(request).await,
+        Mode::ReadOnly => {
+            let message = settings.message.unwrap_or_else(|| "the server is read-only right now, try again later".to_string());
+            ApiError::forbidden(message).into_response()
+        }
+        Mode::Maintenance => {
+            let message = settings.message.unwrap_or_else(|| "the server is down for maintenance".to_string());
+            let mut response = ApiError::new(StatusCode::SERVICE_UNAVAILABLE, message).into_response();
+            response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(settings.retry_after));
+            response
+        }
+    }
+}
+
+// kill -USR1 toggles maintenance mode, kill -USR2 toggles read-only
+#[cfg(unix)]
+pub fn spawn_signal_listener(state: SharedState) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    for (kind, mode) in [(SignalKind::user_defined1(), Mode::Maintenance), (SignalKind::user_defined2(), Mode::ReadOnly)] {
+        let Ok(mut signals) = signal(kind) else { continue };
+        let state = state.clone();
+        tokio::spawn(async move {
+            while signals.recv().await.is_some() {
+                let now = state.modes.toggle(mode);
+                println!("mode is now {:?}", now);
+            }
+        });
+    }
+}
+
+#[cfg(not(unix))]
+pub fn spawn_signal_listener(_state: SharedState) {}
@@ -0,0 +1,193 @@
+use crate::status::StatusCondition;
+use crate::types::PokeType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category{
+    Physical,
+    Special,
+    Status,
+}
+
+// stats that can be raised or lowered during a battle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stat{
+    Attack,
+    Defense,
+    SpAttack,
+    SpDefense,
+    Speed,
+    Accuracy,
+}
+
+// what a move does besides (or instead of) dealing damage
+#[derive(Debug, Clone, Copy)]
+pub enum MoveEffect{
+    NoEffect,
+    // (condition, % chance)
+    Inflict(StatusCondition, u8),
+    // raise the user's stats by the given stages
+    Boost(&'static [(Stat, i8)]),
+    // (stat, stages, % chance) lowered on the target
+    Lower(Stat, i8, u8),
+    // user takes 1/n of the damage dealt
+    Recoil(u32),
+    // user heals half the damage dealt
+    Drain,
+    // user heals half its max HP
+    Heal,
+    // full HP then sleep for 2 turns
+    Rest,
+    // damage equal to the user's level
+    LevelDamage,
+}
+
+#[derive(Debug)]
+pub struct MoveData{
+    pub name: &'static str,
+    pub poke_type: PokeType,
+    pub category: Category,
+    pub power: u32,
+    // None never misses
+    pub accuracy: Option<u8>,
+    // higher goes first regardless of speed
+    pub priority: i8,
+    pub effect: MoveEffect,
+}
+
+// used when a Pokemon has nothing else it can use
+pub const STRUGGLE: MoveData = MoveData { name: "Struggle", poke_type: PokeType::Normal, category: Category::Physical, power: 50, accuracy: None, priority: 0, effect: MoveEffect::Recoil(4) };
+
+use Category::*;
+use MoveEffect::*;
+use PokeType::*;
+use Stat::*;
+
+pub static MOVES: &[MoveData] = &[
+    MoveData { name: "Aerial Ace", poke_type: Flying, category: Physical, power: 60, accuracy: None, priority: 0, effect: NoEffect },
+    MoveData { name: "Agility", poke_type: Psychic, category: Status, power: 0, accuracy: None, priority: 0, effect: Boost(&[(Speed, 2)]) },
+    MoveData { name: "Air Slash", poke_type: Flying, category: Special, power: 75, accuracy: Some(95), priority: 0, effect: NoEffect },
+    MoveData { name: "Amnesia", poke_type: Psychic, category: Status, power: 0, accuracy: None, priority: 0, effect: Boost(&[(SpDefense, 2)]) },
+    MoveData { name: "Ancient Power", poke_type: Rock, category: Special, power: 60, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Aqua Tail", poke_type: Water, category: Physical, power: 90, accuracy: Some(90), priority: 0, effect: NoEffect },
+    MoveData { name: "Aura Sphere", poke_type: Fighting, category: Special, power: 80, accuracy: None, priority: 0, effect: NoEffect },
+    MoveData { name: "Aurora Beam", poke_type: Ice, category: Special, power: 65, accuracy: Some(100), priority: 0, effect: Lower(Attack, 1, 10) },
+    MoveData { name: "Bind", poke_type: Normal, category: Physical, power: 15, accuracy: Some(85), priority: 0, effect: NoEffect },
+    MoveData { name: "Bite", poke_type: Dark, category: Physical, power: 60, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Blizzard", poke_type: Ice, category: Special, power: 110, accuracy: Some(70), priority: 0, effect: Inflict(StatusCondition::Freeze, 10) },
+    MoveData { name: "Body Slam", poke_type: Normal, category: Physical, power: 85, accuracy: Some(100), priority: 0, effect: Inflict(StatusCondition::Paralysis, 30) },
+    MoveData { name: "Brick Break", poke_type: Fighting, category: Physical, power: 75, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Bug Bite", poke_type: Bug, category: Physical, power: 60, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Bug Buzz", poke_type: Bug, category: Special, power: 90, accuracy: Some(100), priority: 0, effect: Lower(SpDefense, 1, 10) },
+    MoveData { name: "Calm Mind", poke_type: Psychic, category: Status, power: 0, accuracy: None, priority: 0, effect: Boost(&[(SpAttack, 1), (SpDefense, 1)]) },
+    MoveData { name: "Confuse Ray", poke_type: Ghost, category: Status, power: 0, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Confusion", poke_type: Psychic, category: Special, power: 50, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Cross Chop", poke_type: Fighting, category: Physical, power: 100, accuracy: Some(80), priority: 0, effect: NoEffect },
+    MoveData { name: "Crunch", poke_type: Dark, category: Physical, power: 80, accuracy: Some(100), priority: 0, effect: Lower(Defense, 1, 20) },
+    MoveData { name: "Dark Pulse", poke_type: Dark, category: Special, power: 80, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Dazzling Gleam", poke_type: Fairy, category: Special, power: 80, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Defense Curl", poke_type: Normal, category: Status, power: 0, accuracy: None, priority: 0, effect: Boost(&[(Defense, 1)]) },
+    MoveData { name: "Dig", poke_type: Ground, category: Physical, power: 80, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Disable", poke_type: Normal, category: Status, power: 0, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Double Kick", poke_type: Fighting, category: Physical, power: 60, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Double-Edge", poke_type: Normal, category: Physical, power: 120, accuracy: Some(100), priority: 0, effect: Recoil(3) },
+    MoveData { name: "Dragon Breath", poke_type: Dragon, category: Special, power: 60, accuracy: Some(100), priority: 0, effect: Inflict(StatusCondition::Paralysis, 30) },
+    MoveData { name: "Dragon Claw", poke_type: Dragon, category: Physical, power: 80, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Dragon Pulse", poke_type: Dragon, category: Special, power: 85, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Dragon Rush", poke_type: Dragon, category: Physical, power: 100, accuracy: Some(75), priority: 0, effect: NoEffect },
+    MoveData { name: "Drill Peck", poke_type: Flying, category: Physical, power: 80, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Dynamic Punch", poke_type: Fighting, category: Physical, power: 100, accuracy: Some(50), priority: 0, effect: NoEffect },
+    MoveData { name: "Earthquake", poke_type: Ground, category: Physical, power: 100, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Electro Ball", poke_type: Electric, category: Special, power: 60, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Ember", poke_type: Fire, category: Special, power: 40, accuracy: Some(100), priority: 0, effect: Inflict(StatusCondition::Burn, 10) },
+    MoveData { name: "Energy Ball", poke_type: Grass, category: Special, power: 90, accuracy: Some(100), priority: 0, effect: Lower(SpDefense, 1, 10) },
+    MoveData { name: "Fire Blast", poke_type: Fire, category: Special, power: 110, accuracy: Some(85), priority: 0, effect: Inflict(StatusCondition::Burn, 10) },
+    MoveData { name: "Fire Fang", poke_type: Fire, category: Physical, power: 65, accuracy: Some(95), priority: 0, effect: Inflict(StatusCondition::Burn, 10) },
+    MoveData { name: "Fire Spin", poke_type: Fire, category: Special, power: 35, accuracy: Some(85), priority: 0, effect: NoEffect },
+    MoveData { name: "Flail", poke_type: Normal, category: Physical, power: 40, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Flamethrower", poke_type: Fire, category: Special, power: 90, accuracy: Some(100), priority: 0, effect: Inflict(StatusCondition::Burn, 10) },
+    MoveData { name: "Flare Blitz", poke_type: Fire, category: Physical, power: 120, accuracy: Some(100), priority: 0, effect: Recoil(3) },
+    MoveData { name: "Fly", poke_type: Flying, category: Physical, power: 90, accuracy: Some(95), priority: 0, effect: NoEffect },
+    MoveData { name: "Focus Blast", poke_type: Fighting, category: Special, power: 120, accuracy: Some(70), priority: 0, effect: Lower(SpDefense, 1, 10) },
+    MoveData { name: "Focus Energy", poke_type: Normal, category: Status, power: 0, accuracy: None, priority: 0, effect: NoEffect },
+    MoveData { name: "Giga Drain", poke_type: Grass, category: Special, power: 75, accuracy: Some(100), priority: 0, effect: Drain },
+    MoveData { name: "Growl", poke_type: Normal, category: Status, power: 0, accuracy: Some(100), priority: 0, effect: Lower(Attack, 1, 100) },
+    MoveData { name: "Gust", poke_type: Flying, category: Special, power: 40, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Harden", poke_type: Normal, category: Status, power: 0, accuracy: None, priority: 0, effect: Boost(&[(Defense, 1)]) },
+    MoveData { name: "Hex", poke_type: Ghost, category: Special, power: 65, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Hurricane", poke_type: Flying, category: Special, power: 110, accuracy: Some(70), priority: 0, effect: NoEffect },
+    MoveData { name: "Hydro Pump", poke_type: Water, category: Special, power: 110, accuracy: Some(80), priority: 0, effect: NoEffect },
+    MoveData { name: "Hyper Beam", poke_type: Normal, category: Special, power: 150, accuracy: Some(90), priority: 0, effect: NoEffect },
+    MoveData { name: "Hyper Fang", poke_type: Normal, category: Physical, power: 80, accuracy: Some(90), priority: 0, effect: NoEffect },
+    MoveData { name: "Hypnosis", poke_type: Psychic, category: Status, power: 0, accuracy: Some(60), priority: 0, effect: Inflict(StatusCondition::Sleep, 100) },
+    MoveData { name: "Ice Beam", poke_type: Ice, category: Special, power: 90, accuracy: Some(100), priority: 0, effect: Inflict(StatusCondition::Freeze, 10) },
+    MoveData { name: "Ice Fang", poke_type: Ice, category: Physical, power: 65, accuracy: Some(95), priority: 0, effect: Inflict(StatusCondition::Freeze, 10) },
+    MoveData { name: "Ice Shard", poke_type: Ice, category: Physical, power: 40, accuracy: Some(100), priority: 1, effect: NoEffect },
+    MoveData { name: "Iron Tail", poke_type: Steel, category: Physical, power: 100, accuracy: Some(75), priority: 0, effect: Lower(Defense, 1, 30) },
+    MoveData { name: "Karate Chop", poke_type: Fighting, category: Physical, power: 50, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Leech Seed", poke_type: Grass, category: Status, power: 0, accuracy: Some(90), priority: 0, effect: NoEffect },
+    MoveData { name: "Leer", poke_type: Normal, category: Status, power: 0, accuracy: Some(100), priority: 0, effect: Lower(Defense, 1, 100) },
+    MoveData { name: "Lick", poke_type: Ghost, category: Physical, power: 30, accuracy: Some(100), priority: 0, effect: Inflict(StatusCondition::Paralysis, 30) },
+    MoveData { name: "Low Kick", poke_type: Fighting, category: Physical, power: 60, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Mega Punch", poke_type: Normal, category: Physical, power: 80, accuracy: Some(85), priority: 0, effect: NoEffect },
+    MoveData { name: "Night Shade", poke_type: Ghost, category: Special, power: 0, accuracy: Some(100), priority: 0, effect: LevelDamage },
+    MoveData { name: "Outrage", poke_type: Dragon, category: Physical, power: 120, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Peck", poke_type: Flying, category: Physical, power: 35, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Poison Powder", poke_type: Poison, category: Status, power: 0, accuracy: Some(75), priority: 0, effect: Inflict(StatusCondition::Poison, 100) },
+    MoveData { name: "Pound", poke_type: Normal, category: Physical, power: 40, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Powder Snow", poke_type: Ice, category: Special, power: 40, accuracy: Some(100), priority: 0, effect: Inflict(StatusCondition::Freeze, 10) },
+    MoveData { name: "Protect", poke_type: Normal, category: Status, power: 0, accuracy: None, priority: 4, effect: NoEffect },
+    MoveData { name: "Psybeam", poke_type: Psychic, category: Special, power: 65, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Psychic", poke_type: Psychic, category: Special, power: 90, accuracy: Some(100), priority: 0, effect: Lower(SpDefense, 1, 10) },
+    MoveData { name: "Psycho Cut", poke_type: Psychic, category: Physical, power: 70, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Psystrike", poke_type: Psychic, category: Special, power: 100, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Quick Attack", poke_type: Normal, category: Physical, power: 40, accuracy: Some(100), priority: 1, effect: NoEffect },
+    MoveData { name: "Razor Leaf", poke_type: Grass, category: Physical, power: 55, accuracy: Some(95), priority: 0, effect: NoEffect },
+    MoveData { name: "Recover", poke_type: Normal, category: Status, power: 0, accuracy: None, priority: 0, effect: Heal },
+    MoveData { name: "Rest", poke_type: Psychic, category: Status, power: 0, accuracy: None, priority: 0, effect: Rest },
+    MoveData { name: "Return", poke_type: Normal, category: Physical, power: 102, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Revenge", poke_type: Fighting, category: Physical, power: 60, accuracy: Some(100), priority: -4, effect: NoEffect },
+    MoveData { name: "Rock Slide", poke_type: Rock, category: Physical, power: 75, accuracy: Some(90), priority: 0, effect: NoEffect },
+    MoveData { name: "Rock Throw", poke_type: Rock, category: Physical, power: 50, accuracy: Some(90), priority: 0, effect: NoEffect },
+    MoveData { name: "Rollout", poke_type: Rock, category: Physical, power: 30, accuracy: Some(90), priority: 0, effect: NoEffect },
+    MoveData { name: "Sand Attack", poke_type: Ground, category: Status, power: 0, accuracy: Some(100), priority: 0, effect: Lower(Accuracy, 1, 100) },
+    MoveData { name: "Scratch", poke_type: Normal, category: Physical, power: 40, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Seismic Toss", poke_type: Fighting, category: Physical, power: 0, accuracy: Some(100), priority: 0, effect: LevelDamage },
+    MoveData { name: "Shadow Ball", poke_type: Ghost, category: Special, power: 80, accuracy: Some(100), priority: 0, effect: Lower(SpDefense, 1, 20) },
+    MoveData { name: "Sing", poke_type: Normal, category: Status, power: 0, accuracy: Some(55), priority: 0, effect: Inflict(StatusCondition::Sleep, 100) },
+    MoveData { name: "Slash", poke_type: Normal, category: Physical, power: 70, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Sleep Powder", poke_type: Grass, category: Status, power: 0, accuracy: Some(75), priority: 0, effect: Inflict(StatusCondition::Sleep, 100) },
+    MoveData { name: "Sludge Bomb", poke_type: Poison, category: Special, power: 90, accuracy: Some(100), priority: 0, effect: Inflict(StatusCondition::Poison, 30) },
+    MoveData { name: "Smokescreen", poke_type: Normal, category: Status, power: 0, accuracy: Some(100), priority: 0, effect: Lower(Accuracy, 1, 100) },
+    MoveData { name: "Solar Beam", poke_type: Grass, category: Special, power: 120, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Spark", poke_type: Electric, category: Physical, power: 65, accuracy: Some(100), priority: 0, effect: Inflict(StatusCondition::Paralysis, 30) },
+    MoveData { name: "Splash", poke_type: Normal, category: Status, power: 0, accuracy: None, priority: 0, effect: NoEffect },
+    MoveData { name: "Stone Edge", poke_type: Rock, category: Physical, power: 100, accuracy: Some(80), priority: 0, effect: NoEffect },
+    MoveData { name: "String Shot", poke_type: Bug, category: Status, power: 0, accuracy: Some(95), priority: 0, effect: Lower(Speed, 2, 100) },
+    MoveData { name: "Stun Spore", poke_type: Grass, category: Status, power: 0, accuracy: Some(75), priority: 0, effect: Inflict(StatusCondition::Paralysis, 100) },
+    MoveData { name: "Substitute", poke_type: Normal, category: Status, power: 0, accuracy: None, priority: 0, effect: NoEffect },
+    MoveData { name: "Surf", poke_type: Water, category: Special, power: 90, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Swift", poke_type: Normal, category: Special, power: 60, accuracy: None, priority: 0, effect: NoEffect },
+    MoveData { name: "Tackle", poke_type: Normal, category: Physical, power: 40, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Tail Whip", poke_type: Normal, category: Status, power: 0, accuracy: Some(100), priority: 0, effect: Lower(Defense, 1, 100) },
+    MoveData { name: "Take Down", poke_type: Normal, category: Physical, power: 90, accuracy: Some(85), priority: 0, effect: Recoil(4) },
+    MoveData { name: "Teleport", poke_type: Psychic, category: Status, power: 0, accuracy: None, priority: 0, effect: NoEffect },
+    MoveData { name: "Thunder Fang", poke_type: Electric, category: Physical, power: 65, accuracy: Some(95), priority: 0, effect: Inflict(StatusCondition::Paralysis, 10) },
+    MoveData { name: "Thunder Shock", poke_type: Electric, category: Special, power: 40, accuracy: Some(100), priority: 0, effect: Inflict(StatusCondition::Paralysis, 10) },
+    MoveData { name: "Thunder Wave", poke_type: Electric, category: Status, power: 0, accuracy: Some(90), priority: 0, effect: Inflict(StatusCondition::Paralysis, 100) },
+    MoveData { name: "Thunder", poke_type: Electric, category: Special, power: 110, accuracy: Some(70), priority: 0, effect: Inflict(StatusCondition::Paralysis, 30) },
+    MoveData { name: "Thunderbolt", poke_type: Electric, category: Special, power: 90, accuracy: Some(100), priority: 0, effect: Inflict(StatusCondition::Paralysis, 10) },
+    MoveData { name: "Toxic", poke_type: Poison, category: Status, power: 0, accuracy: Some(90), priority: 0, effect: Inflict(StatusCondition::Poison, 100) },
+    MoveData { name: "Twister", poke_type: Dragon, category: Special, power: 40, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Vine Whip", poke_type: Grass, category: Physical, power: 45, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Water Gun", poke_type: Water, category: Special, power: 40, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Water Pulse", poke_type: Water, category: Special, power: 60, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Will-O-Wisp", poke_type: Fire, category: Status, power: 0, accuracy: Some(85), priority: 0, effect: Inflict(StatusCondition::Burn, 100) },
+    MoveData { name: "Wing Attack", poke_type: Flying, category: Physical, power: 60, accuracy: Some(100), priority: 0, effect: NoEffect },
+    MoveData { name: "Withdraw", poke_type: Water, category: Status, power: 0, accuracy: None, priority: 0, effect: Boost(&[(Defense, 1)]) },
+    MoveData { name: "Wrap", poke_type: Normal, category: Physical, power: 15, accuracy: Some(90), priority: 0, effect: NoEffect },
+    MoveData { name: "X-Scissor", poke_type: Bug, category: Physical, power: 80, accuracy: Some(100), priority: 0, effect: NoEffect },
+];
+
+pub fn find(name: &str) -> Option<&'static MoveData> {
+    MOVES.iter().find(|m| m.name.eq_ignore_ascii_case(name))
+}
@@ -0,0 +1,44 @@
+// the games' own limit since gen 6
+pub const MAX_LENGTH: usize = 12;
+
+// checked against the nickname with spaces, punctuation and look-alike digits taken out, so "F.u_c k" and "sh1t" don't get through
+// kept to words that rarely turn up inside harmless names, so Cassius and Peacock are fine
+const BLOCKED: &[&str] = &[
+    "bastard", "bitch", "cunt", "fag", "fuck", "nigg", "penis", "porn", "shit", "slut", "twat", "wank", "whore",
+];
+
+// "Sh1t!" -> "shit"
+fn normalize(text: &str) -> String {
+    text.chars()
+        .filter_map(|c| match c.to_ascii_lowercase() {
+            '0' => Some('o'),
+            '1' | '!' | '|' => Some('i'),
+            '3' => Some('e'),
+            '4' | '@' => Some('a'),
+            '5' | '$' => Some('s'),
+            '7' => Some('t'),
+            c if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+// the trimmed nickname, or why it can't be used
+pub fn validate(nickname: &str) -> Result<String, String> {
+    let nickname = nickname.trim();
+    let length = nickname.chars().count();
+    if length > MAX_LENGTH {
+        return Err(format!("nicknames can be up to {} characters, that one is {}", MAX_LENGTH, length));
+    }
+    if nickname.chars().any(char::is_control) {
+        return Err("nicknames can't have control characters".to_string());
+    }
+    let normalized = normalize(nickname);
+    if normalized.is_empty() {
+        return Err("nicknames need at least one letter or number".to_string());
+    }
+    if BLOCKED.iter().any(|word| normalized.contains(word)) {
+        return Err("pick a different nickname".to_string());
+    }
+    Ok(nickname.to_string())
+}
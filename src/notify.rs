@@ -0,0 +1,309 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+use tokio::sync::mpsc;
+
+use crate::models::{Pokemon, Trade};
+use crate::species;
+use crate::state::SharedState;
+
+// over this many in RATE_WINDOW and the rest are dropped, then counted in the next one that gets through
+pub const DEFAULT_RATE_LIMIT: usize = 10;
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+// waiting to be posted, anything past this is dropped rather than holding up a handler
+const QUEUE_SIZE: usize = 100;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+// a message the webhook didn't take is tried again by each retry sweep, up to this many times in all
+pub const MAX_ATTEMPTS: u32 = 5;
+// how often the scheduler runs the retry sweep
+pub const RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+// something worth telling a channel about
+#[derive(Debug, Clone)]
+pub enum Notice{
+    // a legendary joined the collection, made through the API or caught in the wild
+    LegendaryCreated { pokemon: Box<Pokemon>, how: &'static str },
+    TradeCompleted { trade: Trade, from_name: String, to_name: String },
+    // a scheduled snapshot, or its copy to S3
+    BackupFailed { error: String },
+}
+
+impl Notice{
+    pub const KINDS: [&'static str; 3] = ["legendary_created", "trade_completed", "backup_failed"];
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Notice::LegendaryCreated { .. } => "legendary_created",
+            Notice::TradeCompleted { .. } => "trade_completed",
+            Notice::BackupFailed { .. } => "backup_failed",
+        }
+    }
+
+    // what a template can put in {braces}
+    pub fn fields(&self) -> BTreeMap<&'static str, String> {
+        match self {
+            Notice::LegendaryCreated { pokemon, how } => BTreeMap::from([
+                ("id", pokemon.id.to_string()),
+                ("name", pokemon.name.clone()),
+                ("species", pokemon.species.clone()),
+                ("level", pokemon.level.to_string()),
+                ("how", how.to_string()),
+            ]),
+            Notice::TradeCompleted { trade, from_name, to_name } => BTreeMap::from([
+                ("trade_id", trade.id.to_string()),
+                ("from_trainer", from_name.clone()),
+                ("to_trainer", to_name.clone()),
+                ("offered", trade.offered.to_string()),
+                ("requested", trade.requested.to_string()),
+            ]),
+            Notice::BackupFailed { error } => BTreeMap::from([("error", error.clone())]),
+        }
+    }
+
+    fn default_template(kind: &str) -> &'static str {
+        match kind {
+            "legendary_created" => "A legendary {species} was {how}! #{id} {name}, level {level}",
+            "trade_completed" => "Trade {trade_id} done: {from_trainer} gave #{offered} to {to_trainer} for #{requested}",
+            _ => "Backup failed: {error}",
+        }
+    }
+}
+
+// Slack wants {"text": ...}, Discord wants {"content": ...}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format{
+    Slack,
+    Discord,
+}
+
+impl Format{
+    // Discord's webhooks are all on discord.com, anything else is taken to be Slack or something compatible
+    pub fn for_url(url: &str) -> Format {
+        if url.contains("discord.com/") || url.contains("discordapp.com/") { Format::Discord } else { Format::Slack }
+    }
+}
+
+#[derive(Debug)]
+pub struct Notifier{
+    webhook_url: Option<String>,
+    format: Format,
+    // the kinds that get posted, every one of them unless NOTIFY_EVENTS says otherwise
+    events: BTreeSet<String>,
+    // kind -> template, overriding the default one
+    templates: BTreeMap<String, String>,
+    rate_limit: usize,
+    limiter: Mutex<Limiter>,
+    queue: mpsc::Sender<String>,
+    // taken by the job that does the posting
+    receiver: Mutex<Option<mpsc::Receiver<String>>>,
+    // messages the webhook didn't take and how many times they've been tried, oldest first
+    failed: Mutex<VecDeque<(String, u32)>>,
+}
+
+#[derive(Debug, Default)]
+struct Limiter{
+    sent: VecDeque<Instant>,
+    dropped: usize,
+}
+
+impl Default for Notifier{
+    // off, it only posts once it has a webhook
+    fn default() -> Self {
+        let (queue, receiver) = mpsc::channel(QUEUE_SIZE);
+        Notifier {
+            webhook_url: None,
+            format: Format::Slack,
+            events: Notice::KINDS.iter().map(|k| k.to_string()).collect(),
+            templates: BTreeMap::new(),
+            rate_limit: DEFAULT_RATE_LIMIT,
+            limiter: Mutex::default(),
+            queue,
+            receiver: Mutex::new(Some(receiver)),
+            failed: Mutex::default(),
+        }
+    }
+}
+
+impl Notifier{
+    pub fn webhook(url: impl Into<String>) -> Notifier {
+        let url = url.into();
+        Notifier { format: Format::for_url(&url), webhook_url: Some(url), ..Notifier::default() }
+    }
+
+    // NOTIFY_WEBHOOK_URL turns it on, NOTIFY_EVENTS picks the kinds (comma separated),
+    // NOTIFY_TEMPLATE_<KIND> changes a message and NOTIFY_RATE_LIMIT is how many a minute
+    pub fn from_env() -> Result<Notifier, String> {
+        let Some(url) = crate::secrets::var("NOTIFY_WEBHOOK_URL").ok().filter(|u| !u.is_empty()) else {
+            return Ok(Notifier::default());
+        };
+        let mut notifier = Notifier::webhook(url);
+        if let Ok(events) = std::env::var("NOTIFY_EVENTS") {
+            notifier = notifier.events(&events.split(',').map(str::trim).filter(|e| !e.is_empty()).collect::<Vec<_>>())?;
+        }
+        for kind in Notice::KINDS {
+            if let Ok(template) = std::env::var(format!("NOTIFY_TEMPLATE_{}", kind.to_uppercase())) {
+                notifier = notifier.template(kind, template)?;
+            }
+        }
+        if let Ok(limit) = std::env::var("NOTIFY_RATE_LIMIT") {
+            let limit = limit.parse().map_err(|_| format!("NOTIFY_RATE_LIMIT should be a number, not {:?}", limit))?;
+            notifier = notifier.rate_limit(limit);
+        }
+        Ok(notifier)
+    }
+
+    pub fn events(mut self, kinds: &[&str]) -> Result<Notifier, String> {
+        self.events = kinds.iter().map(|kind| known_kind(kind).map(str::to_string)).collect::<Result<_, _>>()?;
+        Ok(self)
+    }
+
+    pub fn template(mut self, kind: &str, template: impl Into<String>) -> Result<Notifier, String> {
+        let kind = known_kind(kind)?;
+        self.templates.insert(kind.to_string(), template.into());
+        Ok(self)
+    }
+
+    // a minute's worth, 0 for no limit
+    pub fn rate_limit(mut self, per_minute: usize) -> Notifier {
+        self.rate_limit = per_minute;
+        self
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.webhook_url.is_some()
+    }
+
+    // the message for a notice, {field}s filled in and anything unknown left as it is
+    pub fn render(&self, notice: &Notice) -> String {
+        let kind = notice.kind();
+        let template = self.templates.get(kind).map_or(Notice::default_template(kind), String::as_str);
+        let fields = notice.fields();
+        // one pass, so a name with {braces} in it doesn't get filled in too
+        let (mut message, mut rest) = (String::new(), template);
+        while let Some(start) = rest.find('{') {
+            message.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            match after.find('}').and_then(|end| fields.get(&after[..end]).map(|value| (value, end))) {
+                Some((value, end)) => {
+                    message.push_str(value);
+                    rest = &after[end + 1..];
+                }
+                None => {
+                    message.push('{');
+                    rest = after;
+                }
+            }
+        }
+        message.push_str(rest);
+        message
+    }
+
+    // queues the message for the posting job, safe to call with locks held
+    pub fn notify(&self, notice: Notice) {
+        if !self.is_on() || !self.events.contains(notice.kind()) {
+            return;
+        }
+        let mut message = self.render(&notice);
+        {
+            let mut limiter = self.limiter.lock().unwrap();
+            let now = Instant::now();
+            while limiter.sent.front().is_some_and(|at| now.duration_since(*at) >= RATE_WINDOW) {
+                limiter.sent.pop_front();
+            }
+            if self.rate_limit > 0 && limiter.sent.len() >= self.rate_limit {
+                limiter.dropped += 1;
+                return;
+            }
+            limiter.sent.push_back(now);
+            if limiter.dropped > 0 {
+                message.push_str(&format!("\n({} more notifications were dropped by the rate limit)", limiter.dropped));
+                limiter.dropped = 0;
+            }
+        }
+        if self.queue.try_send(message).is_err() {
+            eprintln!("notify: the queue is full, dropped a {} notification", notice.kind());
+        }
+    }
+
+    // how many are waiting for the next retry sweep
+    pub fn failed(&self) -> usize {
+        self.failed.lock().unwrap().len()
+    }
+
+    fn failed_to_post(&self, message: String, attempts: u32) {
+        if attempts >= MAX_ATTEMPTS {
+            eprintln!("notify: gave up on a notification after {} attempts", attempts);
+            return;
+        }
+        let mut failed = self.failed.lock().unwrap();
+        // the same cap as the queue, the oldest go first
+        if failed.len() >= QUEUE_SIZE {
+            failed.pop_front();
+        }
+        failed.push_back((message, attempts));
+    }
+}
+
+async fn post(client: &reqwest::Client, url: &str, format: Format, message: &str) -> Result<(), reqwest::Error> {
+    let body = match format {
+        Format::Slack => json!({ "text": message }),
+        Format::Discord => json!({ "content": message }),
+    };
+    client.post(url).json(&body).send().await.and_then(|r| r.error_for_status()).map(|_| ())
+}
+
+// tries what the webhook didn't take again, the scheduler runs it every RETRY_INTERVAL
+pub async fn retry_failed(state: &SharedState) -> Result<(), String> {
+    let notifier = &state.notifier;
+    let Some(url) = &notifier.webhook_url else { return Ok(()) };
+    let failed: Vec<(String, u32)> = notifier.failed.lock().unwrap().drain(..).collect();
+    if failed.is_empty() {
+        return Ok(());
+    }
+    let client = reqwest::Client::builder().timeout(TIMEOUT).build().map_err(|e| e.to_string())?;
+    let (tried, mut still_failing) = (failed.len(), 0);
+    for (message, attempts) in failed {
+        if post(&client, url, notifier.format, &message).await.is_err() {
+            still_failing += 1;
+            notifier.failed_to_post(message, attempts + 1);
+        }
+    }
+    if still_failing > 0 {
+        return Err(format!("{} of {} notifications still didn't go through", still_failing, tried));
+    }
+    Ok(())
+}
+
+fn known_kind(kind: &str) -> Result<&'static str, String> {
+    Notice::KINDS.into_iter()
+        .find(|k| k.eq_ignore_ascii_case(kind))
+        .ok_or_else(|| format!("unknown notification {:?}, it's one of {}", kind, Notice::KINDS.join(", ")))
+}
+
+// a legendary that just joined the collection
+pub fn new_pokemon(state: &SharedState, pokemon: &Pokemon, how: &'static str) {
+    if species::is_legendary(&pokemon.species) {
+        state.notifier.notify(Notice::LegendaryCreated { pokemon: Box::new(pokemon.clone()), how });
+    }
+}
+
+// posts whatever's queued, one at a time and in order
+pub fn spawn_notifier(state: SharedState) {
+    let Some(url) = state.notifier.webhook_url.clone() else { return };
+    let Some(mut receiver) = state.notifier.receiver.lock().unwrap().take() else { return };
+    let format = state.notifier.format;
+    tokio::spawn(async move {
+        let Ok(client) = reqwest::Client::builder().timeout(TIMEOUT).build() else { return };
+        while let Some(message) = receiver.recv().await {
+            if let Err(e) = post(&client, &url, format, &message).await {
+                eprintln!("notify: couldn't post to the webhook, it'll be tried again: {}", e.without_url());
+                state.notifier.failed_to_post(message, 1);
+            }
+        }
+    });
+}
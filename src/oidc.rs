@@ -0,0 +1,398 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use axum::http::{header, StatusCode};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ring::signature::{RsaPublicKeyComponents, RSA_PKCS1_2048_8192_SHA256};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::error::ApiError;
+use crate::mail;
+use crate::models::{Login, LoginCallback, SignIn, SignInOutcome, Trainer};
+use crate::secrets;
+use crate::sharing::hex;
+use crate::state::SharedState;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+// how long someone has to finish logging in at the provider
+const LOGIN_LIFETIME: u64 = 10 * 60;
+// for a provider whose clock is a little off from ours
+const LEEWAY: u64 = 60;
+
+#[derive(Debug, Clone)]
+enum Kind{
+    // endpoints from <issuer>/.well-known/openid-configuration, the identity from the signed ID token
+    Oidc { issuer: String },
+    // GitHub's logins aren't OIDC, there's no ID token so the identity comes from its user API
+    GitHub { web: String, api: String },
+}
+
+#[derive(Debug, Clone)]
+struct Endpoints{
+    authorization: String,
+    token: String,
+    // empty for GitHub
+    jwks: String,
+}
+
+// an RSA key from the provider's JWKS
+#[derive(Debug, Clone)]
+struct Jwk{
+    kid: Option<String>,
+    n: Vec<u8>,
+    e: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct Provider{
+    pub name: String,
+    kind: Kind,
+    client_id: String,
+    client_secret: String,
+    scopes: String,
+    // discovered on the first login
+    endpoints: Mutex<Option<Endpoints>>,
+    // fetched again when a token is signed with a key that isn't in here, which is how a rotation shows up
+    keys: Mutex<Vec<Jwk>>,
+}
+
+impl Provider{
+    // e.g. https://accounts.google.com, or https://<host>/realms/<realm> for Keycloak
+    pub fn oidc(name: &str, issuer: &str, client_id: &str, client_secret: &str) -> Provider {
+        let kind = Kind::Oidc { issuer: issuer.trim_end_matches('/').to_string() };
+        Provider::new(name, kind, client_id, client_secret, "openid email profile")
+    }
+
+    pub fn github(client_id: &str, client_secret: &str) -> Provider {
+        Provider::github_at("https://github.com", "https://api.github.com", client_id, client_secret)
+    }
+
+    // GitHub Enterprise, or something standing in for GitHub
+    pub fn github_at(web: &str, api: &str, client_id: &str, client_secret: &str) -> Provider {
+        let kind = Kind::GitHub { web: web.trim_end_matches('/').to_string(), api: api.trim_end_matches('/').to_string() };
+        Provider::new("github", kind, client_id, client_secret, "read:user user:email")
+    }
+
+    fn new(name: &str, kind: Kind, client_id: &str, client_secret: &str, scopes: &str) -> Provider {
+        Provider {
+            name: name.to_string(),
+            kind,
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            scopes: scopes.to_string(),
+            endpoints: Mutex::default(),
+            keys: Mutex::default(),
+        }
+    }
+
+    // space separated, what's asked for at the provider
+    pub fn scopes(mut self, scopes: &str) -> Provider {
+        self.scopes = scopes.to_string();
+        self
+    }
+
+    // the keys with this kid, or all of them for a token without one
+    fn keys_with(&self, kid: Option<&str>) -> Vec<Jwk> {
+        self.keys.lock().unwrap().iter().filter(|k| kid.is_none() || k.kid.as_deref() == kid).cloned().collect()
+    }
+}
+
+// who finished logging in, as the provider tells it
+#[derive(Debug, Clone)]
+pub struct Identity{
+    pub login: Login,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: bool,
+}
+
+// a login that's been started and not finished yet
+#[derive(Debug)]
+struct Pending{
+    provider: String,
+    nonce: String,
+    // PKCE, so a code that's intercepted on its way back is no use to anyone else
+    verifier: String,
+    started_at: u64,
+}
+
+// the providers trainers can log in with, none unless OIDC_PROVIDERS names some
+#[derive(Debug)]
+pub struct Logins{
+    providers: Vec<Provider>,
+    // by the state parameter each one was sent off with
+    pending: Mutex<HashMap<String, Pending>>,
+    client: reqwest::Client,
+}
+
+impl Default for Logins{
+    fn default() -> Self {
+        Logins {
+            providers: Vec::new(),
+            pending: Mutex::default(),
+            client: reqwest::Client::builder().timeout(TIMEOUT).build().expect("a client with a timeout"),
+        }
+    }
+}
+
+fn provider_error(message: String) -> ApiError {
+    ApiError::new(StatusCode::BAD_GATEWAY, message)
+}
+
+fn unauthorized(message: String) -> ApiError {
+    ApiError::new(StatusCode::UNAUTHORIZED, message)
+}
+
+// registered at the provider as where it sends the browser back to
+pub fn redirect_uri(public_url: &str, provider: &str) -> String {
+    format!("{}/auth/{}/callback", public_url, provider)
+}
+
+impl Logins{
+    pub fn provider(mut self, provider: Provider) -> Logins {
+        self.providers.push(provider);
+        self
+    }
+
+    // OIDC_PROVIDERS, e.g. "google,keycloak,github", and for each one OIDC_<NAME>_ISSUER, _CLIENT_ID, _CLIENT_SECRET and _SCOPES
+    // github needs no issuer
+    pub fn from_env() -> Result<Logins, String> {
+        let mut logins = Logins::default();
+        let Ok(names) = std::env::var("OIDC_PROVIDERS") else { return Ok(logins) };
+        for name in names.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            let prefix = format!("OIDC_{}", name.to_uppercase().replace('-', "_"));
+            let client_id = std::env::var(format!("{}_CLIENT_ID", prefix)).map_err(|_| format!("{} needs {}_CLIENT_ID", name, prefix))?;
+            let client_secret = secrets::var(&format!("{}_CLIENT_SECRET", prefix)).map_err(|_| format!("{} needs {}_CLIENT_SECRET", name, prefix))?;
+            let provider = match (std::env::var(format!("{}_ISSUER", prefix)), name) {
+                (Ok(issuer), _) => Provider::oidc(name, &issuer, &client_id, &client_secret),
+                (Err(_), "github") => Provider::github(&client_id, &client_secret),
+                (Err(_), _) => return Err(format!("{} needs {}_ISSUER", name, prefix)),
+            };
+            let provider = match std::env::var(format!("{}_SCOPES", prefix)) {
+                Ok(scopes) => provider.scopes(&scopes),
+                Err(_) => provider,
+            };
+            logins = logins.provider(provider);
+        }
+        Ok(logins)
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.providers.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    fn find(&self, name: &str) -> Result<&Provider, ApiError> {
+        self.providers.iter().find(|p| p.name == name)
+            .ok_or_else(|| ApiError::not_found(format!("there's no logging in with {}", name)))
+    }
+
+    async fn get_json(&self, url: &str, bearer: Option<&str>) -> Result<Value, ApiError> {
+        let mut request = self.client.get(url).header(header::ACCEPT, "application/json").header(header::USER_AGENT, "rust-hello-http");
+        if let Some(token) = bearer {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await.map_err(|e| provider_error(format!("couldn't reach {}: {}", url, e)))?;
+        if !response.status().is_success() {
+            return Err(provider_error(format!("{} said {}", url, response.status())));
+        }
+        response.json().await.map_err(|e| provider_error(format!("{} sent something that isn't JSON: {}", url, e)))
+    }
+
+    async fn endpoints(&self, provider: &Provider) -> Result<Endpoints, ApiError> {
+        if let Some(endpoints) = provider.endpoints.lock().unwrap().clone() {
+            return Ok(endpoints);
+        }
+        let endpoints = match &provider.kind {
+            Kind::GitHub { web, .. } => Endpoints {
+                authorization: format!("{}/login/oauth/authorize", web),
+                token: format!("{}/login/oauth/access_token", web),
+                jwks: String::new(),
+            },
+            Kind::Oidc { issuer } => {
+                let discovery = self.get_json(&format!("{}/.well-known/openid-configuration", issuer), None).await?;
+                let field = |name: &str| discovery[name].as_str().map(str::to_string)
+                    .ok_or_else(|| provider_error(format!("{}'s discovery document has no {}", provider.name, name)));
+                Endpoints { authorization: field("authorization_endpoint")?, token: field("token_endpoint")?, jwks: field("jwks_uri")? }
+            }
+        };
+        *provider.endpoints.lock().unwrap() = Some(endpoints.clone());
+        Ok(endpoints)
+    }
+
+    async fn fetch_keys(&self, provider: &Provider, jwks: &str) -> Result<(), ApiError> {
+        let body = self.get_json(jwks, None).await?;
+        let decode = |value: &Value| value.as_str().and_then(|v| URL_SAFE_NO_PAD.decode(v).ok());
+        let keys = body["keys"].as_array().into_iter().flatten()
+            .filter(|k| k["kty"] == "RSA" && k["use"] != "enc")
+            .filter_map(|k| Some(Jwk { kid: k["kid"].as_str().map(str::to_string), n: decode(&k["n"])?, e: decode(&k["e"])? }))
+            .collect();
+        *provider.keys.lock().unwrap() = keys;
+        Ok(())
+    }
+
+    // where to send the browser, at the provider
+    pub async fn start(&self, name: &str, redirect_uri: &str, now: u64) -> Result<String, ApiError> {
+        let provider = self.find(name)?;
+        let endpoints = self.endpoints(provider).await?;
+        let state = hex(&rand::random::<[u8; 32]>());
+        let nonce = hex(&rand::random::<[u8; 16]>());
+        let verifier = hex(&rand::random::<[u8; 32]>());
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+        let url = reqwest::Url::parse_with_params(&endpoints.authorization, [
+            ("response_type", "code"),
+            ("client_id", provider.client_id.as_str()),
+            ("redirect_uri", redirect_uri),
+            ("scope", provider.scopes.as_str()),
+            ("state", state.as_str()),
+            ("nonce", nonce.as_str()),
+            ("code_challenge", challenge.as_str()),
+            ("code_challenge_method", "S256"),
+        ]).map_err(|e| provider_error(format!("{}'s authorization endpoint isn't a URL: {}", name, e)))?;
+
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|_, p| p.started_at + LOGIN_LIFETIME >= now);
+        pending.insert(state, Pending { provider: name.to_string(), nonce, verifier, started_at: now });
+        Ok(url.to_string())
+    }
+
+    // swaps the code the provider sent back for who logged in
+    pub async fn finish(&self, name: &str, callback: &LoginCallback, redirect_uri: &str, now: u64) -> Result<Identity, ApiError> {
+        let provider = self.find(name)?;
+        if let Some(error) = &callback.error {
+            return Err(unauthorized(format!("{} said {}", name, callback.error_description.as_ref().unwrap_or(error))));
+        }
+        let (Some(code), Some(state)) = (&callback.code, &callback.state) else {
+            return Err(ApiError::bad_request("the callback needs a code and a state"));
+        };
+        // each one works once, and only for the provider it was started with
+        let pending = self.pending.lock().unwrap().remove(state)
+            .filter(|p| p.provider == name && p.started_at + LOGIN_LIFETIME >= now)
+            .ok_or_else(|| ApiError::bad_request("that login has expired or was already finished, start it again"))?;
+
+        let endpoints = self.endpoints(provider).await?;
+        let response = self.client.post(&endpoints.token)
+            .header(header::ACCEPT, "application/json")
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code.as_str()),
+                ("redirect_uri", redirect_uri),
+                ("client_id", provider.client_id.as_str()),
+                ("client_secret", provider.client_secret.as_str()),
+                ("code_verifier", pending.verifier.as_str()),
+            ])
+            .send().await
+            .map_err(|e| provider_error(format!("couldn't reach {}: {}", name, e)))?;
+        let status = response.status();
+        let body: Value = response.json().await.unwrap_or_default();
+        // GitHub says so with a 200 and an error in the body
+        if let Some(error) = body["error"].as_str() {
+            return Err(unauthorized(format!("{} wouldn't take the code: {}", name, body["error_description"].as_str().unwrap_or(error))));
+        }
+        if !status.is_success() {
+            return Err(provider_error(format!("{} said {} for the code", name, status)));
+        }
+
+        match &provider.kind {
+            Kind::Oidc { issuer } => {
+                let id_token = body["id_token"].as_str().ok_or_else(|| provider_error(format!("{} sent no ID token", name)))?;
+                let claims = self.verify(provider, &endpoints, issuer, id_token, &pending.nonce, now).await?;
+                let text = |name: &str| claims[name].as_str().map(str::to_string);
+                Ok(Identity {
+                    login: Login { provider: name.to_string(), subject: text("sub").unwrap_or_default() },
+                    name: text("name").or_else(|| text("preferred_username")),
+                    email: text("email"),
+                    // only a real true, not "true" or a claim that's missing
+                    email_verified: claims["email_verified"].as_bool() == Some(true),
+                })
+            }
+            Kind::GitHub { api, .. } => {
+                let token = body["access_token"].as_str().ok_or_else(|| provider_error(format!("{} sent no access token", name)))?;
+                let user = self.get_json(&format!("{}/user", api), Some(token)).await?;
+                let id = user["id"].as_u64().ok_or_else(|| provider_error(format!("{} sent a user without an ID", name)))?;
+                Ok(Identity {
+                    login: Login { provider: name.to_string(), subject: id.to_string() },
+                    name: user["name"].as_str().or(user["login"].as_str()).map(str::to_string),
+                    // the public one, which GitHub doesn't say is verified
+                    email: user["email"].as_str().map(str::to_string),
+                    email_verified: false,
+                })
+            }
+        }
+    }
+
+    // the claims of an RS256 ID token, once its signature, issuer, audience, expiry and nonce all check out
+    async fn verify(&self, provider: &Provider, endpoints: &Endpoints, issuer: &str, token: &str, nonce: &str, now: u64) -> Result<Value, ApiError> {
+        let invalid = |why: &str| unauthorized(format!("the ID token from {} {}", provider.name, why));
+        let decode = |part: &str| URL_SAFE_NO_PAD.decode(part).ok();
+        let (signed, signature) = token.rsplit_once('.').ok_or_else(|| invalid("isn't a JWT"))?;
+        let (header, payload) = signed.split_once('.').ok_or_else(|| invalid("isn't a JWT"))?;
+        let header: Value = decode(header).and_then(|h| serde_json::from_slice(&h).ok()).ok_or_else(|| invalid("has a header that can't be read"))?;
+        if header["alg"] != "RS256" {
+            return Err(invalid(&format!("is signed with {}, only RS256 is supported", header["alg"])));
+        }
+        let signature = decode(signature).ok_or_else(|| invalid("has a signature that can't be read"))?;
+
+        let kid = header["kid"].as_str();
+        let mut keys = provider.keys_with(kid);
+        if keys.is_empty() {
+            // the first login, or a key that's new since they were last fetched
+            self.fetch_keys(provider, &endpoints.jwks).await?;
+            keys = provider.keys_with(kid);
+        }
+        let signed_by = |k: &Jwk| RsaPublicKeyComponents { n: &k.n, e: &k.e }.verify(&RSA_PKCS1_2048_8192_SHA256, signed.as_bytes(), &signature).is_ok();
+        if !keys.iter().any(signed_by) {
+            return Err(invalid("isn't signed by any of its keys"));
+        }
+
+        let claims: Value = decode(payload).and_then(|c| serde_json::from_slice(&c).ok()).ok_or_else(|| invalid("has claims that can't be read"))?;
+        if claims["iss"].as_str().map(|i| i.trim_end_matches('/')) != Some(issuer) {
+            return Err(invalid("is from a different issuer"));
+        }
+        let audience = &claims["aud"];
+        if audience != provider.client_id.as_str() && !audience.as_array().is_some_and(|a| a.iter().any(|c| c == provider.client_id.as_str())) {
+            return Err(invalid("is for a different client"));
+        }
+        if claims["exp"].as_u64().is_none_or(|exp| exp + LEEWAY < now) {
+            return Err(invalid("has expired"));
+        }
+        if claims["nonce"].as_str() != Some(nonce) {
+            return Err(invalid("is for a different login"));
+        }
+        if claims["sub"].as_str().is_none_or(str::is_empty) {
+            return Err(invalid("doesn't say who it's for"));
+        }
+        Ok(claims)
+    }
+}
+
+// the trainer the account logs in as, linked by verified email or made on the spot the first time, with a session for them
+pub fn sign_in(state: &SharedState, identity: Identity) -> Result<SignIn, ApiError> {
+    let signed_in = |trainer: Trainer, outcome| SignIn { session: state.auth.issue(trainer.id, state.time.now()), trainer, outcome };
+    let mut trainers = state.trainers.lock().unwrap();
+    if let Some(trainer) = trainers.iter().find(|t| t.logins.contains(&identity.login)) {
+        return Ok(signed_in(trainer.clone(), SignInOutcome::Existing));
+    }
+    // only an address both sides have checked, anyone can put any address on a new account
+    let verified = identity.email.as_deref().filter(|_| identity.email_verified);
+    let same_email = |t: &&mut Trainer| t.email_verified && t.email.as_deref().zip(verified).is_some_and(|(a, b)| a.eq_ignore_ascii_case(b));
+    if let Some(trainer) = trainers.iter_mut().find(same_email) {
+        trainer.logins.push(identity.login);
+        return Ok(signed_in(trainer.clone(), SignInOutcome::Linked));
+    }
+
+    let id = trainers.last().map_or(1, |t| t.id + 1);
+    let name = identity.name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty()).unwrap_or_else(|| format!("Trainer {}", id));
+    let mut trainer = Trainer::new(id, name, Vec::new());
+    trainer.email_verified = verified.is_some();
+    trainer.email = identity.email;
+    trainer.logins.push(identity.login);
+    state.quotas.check(&trainer, 0, 0)?;
+    trainers.push(trainer.clone());
+    if !trainer.email_verified {
+        mail::send_verification(state, &trainer);
+    }
+    Ok(signed_in(trainer, SignInOutcome::Created))
+}
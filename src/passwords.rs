@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+use std::sync::Mutex;
+
+use ring::pbkdf2::{self, PBKDF2_HMAC_SHA256};
+
+// PBKDF2 rounds, slow enough that a leaked hash takes a while to guess
+const ITERATIONS: NonZeroU32 = NonZeroU32::new(100_000).expect("more than zero");
+
+pub const MIN_LENGTH: usize = 8;
+pub const MAX_LENGTH: usize = 128;
+
+// salted hashes of the passwords trainers have set, in memory like the rest of the state
+#[derive(Debug, Default)]
+pub struct Passwords{
+    hashes: Mutex<HashMap<u32, Hashed>>,
+}
+
+#[derive(Debug)]
+struct Hashed{
+    salt: [u8; 16],
+    hash: [u8; 32],
+}
+
+pub fn check_length(password: &str) -> Result<(), String> {
+    let length = password.chars().count();
+    if !(MIN_LENGTH..=MAX_LENGTH).contains(&length) {
+        return Err(format!("a password is {} to {} characters", MIN_LENGTH, MAX_LENGTH));
+    }
+    Ok(())
+}
+
+impl Passwords{
+    // replaces the old one, if there was one
+    pub fn set(&self, trainer_id: u32, password: &str) {
+        let salt = rand::random::<[u8; 16]>();
+        let mut hash = [0; 32];
+        pbkdf2::derive(PBKDF2_HMAC_SHA256, ITERATIONS, &salt, password.as_bytes(), &mut hash);
+        self.hashes.lock().unwrap().insert(trainer_id, Hashed { salt, hash });
+    }
+
+    pub fn is_set(&self, trainer_id: u32) -> bool {
+        self.hashes.lock().unwrap().contains_key(&trainer_id)
+    }
+
+    // false for a trainer without one, too
+    pub fn check(&self, trainer_id: u32, password: &str) -> bool {
+        let hashes = self.hashes.lock().unwrap();
+        hashes.get(&trainer_id).is_some_and(|h| pbkdf2::verify(PBKDF2_HMAC_SHA256, ITERATIONS, &h.salt, password.as_bytes(), &h.hash).is_ok())
+    }
+}
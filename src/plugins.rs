@@ -0,0 +1,84 @@
+use axum::Router;
+
+use crate::achievements::Event;
+use crate::state::SharedState;
+
+// a way to extend the server without touching it: new endpoints, things that react to events...
+// every hook has a default that does nothing, so a plugin only implements the ones it needs
+pub trait Plugin: Send + Sync + 'static{
+    // shown at startup
+    fn name(&self) -> &str;
+
+    // once, before the server starts listening, e.g. to spawn a background job with the state
+    fn on_startup(&self, _state: &SharedState) {}
+
+    // everything achievements hear about, right after they do
+    // this runs while the handler still holds its locks, so don't lock anything in the state here,
+    // send it to a task of your own instead
+    fn on_event(&self, _trainer_id: u32, _event: Event<'_>) {}
+
+    // merged into the app next to the built-in routes, behind the same middleware
+    fn extra_routes(&self) -> Router<SharedState> {
+        Router::new()
+    }
+}
+
+// the plugins compiled into this build, in the order they were registered
+#[derive(Default)]
+pub struct Plugins{
+    plugins: Vec<Box<dyn Plugin>>,
+}
+
+impl std::fmt::Debug for Plugins{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+impl Plugins{
+    // the built-in ones that are switched on
+    pub fn from_env() -> Plugins {
+        let mut plugins = Plugins::default();
+        if std::env::var("LOG_EVENTS").is_ok_and(|v| v == "1") {
+            plugins.register(EventLog);
+        }
+        plugins
+    }
+
+    pub fn register(&mut self, plugin: impl Plugin) {
+        self.plugins.push(Box::new(plugin));
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.plugins.iter().map(|p| p.name()).collect()
+    }
+
+    pub fn on_startup(&self, state: &SharedState) {
+        for plugin in &self.plugins {
+            plugin.on_startup(state);
+        }
+    }
+
+    pub fn on_event(&self, trainer_id: u32, event: Event<'_>) {
+        for plugin in &self.plugins {
+            plugin.on_event(trainer_id, event);
+        }
+    }
+
+    pub fn extra_routes(&self) -> Router<SharedState> {
+        self.plugins.iter().fold(Router::new(), |router, plugin| router.merge(plugin.extra_routes()))
+    }
+}
+
+// LOG_EVENTS=1 prints every event as it happens
+struct EventLog;
+
+impl Plugin for EventLog{
+    fn name(&self) -> &str {
+        "event-log"
+    }
+
+    fn on_event(&self, trainer_id: u32, event: Event<'_>) {
+        println!("trainer {}: {:?}", trainer_id, event);
+    }
+}
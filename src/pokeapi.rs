@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::redis::Redis;
+use crate::species::BaseStats;
+
+const DEFAULT_URL: &str = "https://pokeapi.co/api/v2";
+
+// a create shouldn't hang on a slow lookup, it just goes ahead without one
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+// what a lookup fills in on create
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeciesInfo{
+    pub types: (String, Option<String>),
+    pub base_stats: BaseStats,
+    pub sprite_url: Option<String>,
+    pub height_m: f64,
+    pub weight_kg: f64,
+}
+
+// just the parts of pokeapi's /pokemon/{name} response we use
+#[derive(Debug, Deserialize)]
+struct PokemonResponse{
+    // decimetres and hectograms
+    height: u32,
+    weight: u32,
+    types: Vec<TypeSlot>,
+    stats: Vec<StatSlot>,
+    sprites: Sprites,
+}
+
+#[derive(Debug, Deserialize)]
+struct TypeSlot{
+    slot: u32,
+    #[serde(rename = "type")]
+    poke_type: Named,
+}
+
+#[derive(Debug, Deserialize)]
+struct StatSlot{
+    base_stat: u32,
+    stat: Named,
+}
+
+#[derive(Debug, Deserialize)]
+struct Named{
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Sprites{
+    front_default: Option<String>,
+}
+
+// "fire" -> "Fire", to match how types are written everywhere else
+fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    chars.next().map_or_else(String::new, |first| first.to_uppercase().chain(chars).collect())
+}
+
+impl PokemonResponse{
+    fn into_info(mut self) -> SpeciesInfo {
+        self.types.sort_by_key(|t| t.slot);
+        let mut types = self.types.into_iter().map(|t| capitalize(&t.poke_type.name));
+        let stat = |name: &str| self.stats.iter().find(|s| s.stat.name == name).map_or(0, |s| s.base_stat);
+        SpeciesInfo {
+            types: (types.next().unwrap_or_else(|| "Normal".to_string()), types.next()),
+            base_stats: BaseStats {
+                hp: stat("hp"),
+                attack: stat("attack"),
+                defense: stat("defense"),
+                sp_attack: stat("special-attack"),
+                sp_defense: stat("special-defense"),
+                speed: stat("speed"),
+            },
+            sprite_url: self.sprites.front_default,
+            height_m: self.height as f64 / 10.0,
+            weight_kg: self.weight as f64 / 10.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PokeApi{
+    client: reqwest::Client,
+    base_url: String,
+    // species name -> what pokeapi said, None if it doesn't know the species
+    cache: Mutex<HashMap<String, Option<SpeciesInfo>>>,
+}
+
+impl PokeApi{
+    // on unless POKEAPI_OFFLINE is set, POKEAPI_URL points it somewhere else (e.g. a local mirror)
+    pub fn from_env() -> Option<PokeApi> {
+        if std::env::var("POKEAPI_OFFLINE").is_ok_and(|v| v != "0" && v != "false") {
+            return None;
+        }
+        let base_url = std::env::var("POKEAPI_URL").unwrap_or_else(|_| DEFAULT_URL.to_string());
+        PokeApi::new(&base_url)
+    }
+
+    // e.g. "https://pokeapi.co/api/v2", None if there's no HTTP client to be had
+    pub fn new(base_url: &str) -> Option<PokeApi> {
+        let client = reqwest::Client::builder().timeout(TIMEOUT).build().ok()?;
+        Some(PokeApi { client, base_url: base_url.trim_end_matches('/').to_string(), cache: Mutex::default() })
+    }
+
+    // Ok(None) if pokeapi doesn't know the species
+    async fn fetch(&self, key: &str) -> Result<Option<SpeciesInfo>, reqwest::Error> {
+        let url = format!("{}/pokemon/{}", self.base_url, key);
+        let response = self.client.get(&url).send().await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let body: PokemonResponse = response.error_for_status()?.json().await?;
+        Ok(Some(body.into_info()))
+    }
+
+    // None if pokeapi doesn't know the species or can't be reached
+    // misses are cached too, failed requests aren't so they get retried
+    // with `redis` it's asked after this instance's own cache and before pokeapi
+    pub async fn lookup(&self, name: &str, redis: Option<&Redis>) -> Option<SpeciesInfo> {
+        // pokeapi's names are like "mr-mime"
+        let key = name.to_lowercase().replace(' ', "-").replace(['.', '\''], "");
+        if let Some(cached) = self.cache.lock().unwrap().get(&key) {
+            return cached.clone();
+        }
+        let shared = format!("pokeapi:{}", key);
+        if let Some(info) = match redis {
+            Some(redis) => redis.cached::<Option<SpeciesInfo>>(&shared).await,
+            None => None,
+        } {
+            self.cache.lock().unwrap().insert(key, info.clone());
+            return info;
+        }
+        match self.fetch(&key).await {
+            Ok(info) => {
+                if let Some(redis) = redis {
+                    redis.cache(&shared, &info).await;
+                }
+                self.cache.lock().unwrap().insert(key, info.clone());
+                info
+            }
+            Err(e) => {
+                eprintln!("pokeapi lookup for {} failed: {}", name, e);
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use axum::extract::Query;
+use axum::http::{header, StatusCode};
+use axum::middleware;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use pprof::protos::Message;
+
+use crate::admin;
+use crate::error::ApiError;
+use crate::models::ProfileQuery;
+use crate::state::SharedState;
+
+pub const DEFAULT_SECONDS: u64 = 10;
+pub const MAX_SECONDS: u64 = 60;
+
+// samples a second, not a round number so it doesn't keep landing on the same timer
+const FREQUENCY: i32 = 99;
+// the profiler's own frames and the C library's unwinding, which only get in the way
+const BLOCKLIST: &[&str] = &["libc", "libgcc", "pthread", "vdso"];
+
+// inferno writes nothing at all for an empty profile
+const NO_SAMPLES: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" width="600" height="40"><text x="10" y="25">No samples, nothing was running on the CPU while it was profiled</text></svg>"#;
+
+// there's one profiler for the whole process
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+// gives RUNNING back however the profile ends, a client that hangs up drops the handler halfway
+struct Running;
+
+impl Drop for Running{
+    fn drop(&mut self) {
+        RUNNING.store(false, Ordering::SeqCst);
+    }
+}
+
+// only built with --features pprof, behind the admin token like the admin API
+pub fn routes(state: &SharedState) -> Router<SharedState> {
+    Router::new()
+        .route("/debug/pprof/profile", get(get_profile))
+        .route_layer(middleware::from_fn_with_state(state.clone(), admin::require_token))
+}
+
+// READ - a CPU profile of the whole server over ?seconds=, as pprof protobuf for `go tool pprof`
+// or ?format=flamegraph for an SVG to open in a browser
+async fn get_profile(Query(query): Query<ProfileQuery>) -> Result<Response, ApiError> {
+    let seconds = query.seconds.unwrap_or(DEFAULT_SECONDS);
+    if !(1..=MAX_SECONDS).contains(&seconds) {
+        return Err(ApiError::bad_request(format!("seconds can be 1 to {}", MAX_SECONDS)));
+    }
+    let flamegraph = match query.format.as_deref() {
+        None | Some("pprof") => false,
+        Some("flamegraph") => true,
+        Some(other) => return Err(ApiError::bad_request(format!("unknown format {:?}, it's pprof or flamegraph", other))),
+    };
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return Err(ApiError::conflict("a profile is already being taken, try again once it's done"));
+    }
+    let running = Running;
+
+    let guard = pprof::ProfilerGuardBuilder::default().frequency(FREQUENCY).blocklist(BLOCKLIST).build()
+        .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("couldn't start the profiler: {}", e)))?;
+    tokio::time::sleep(Duration::from_secs(seconds)).await;
+
+    // resolving the symbols is the slow part
+    let body = crate::blocking(move || {
+        let _running = running;
+        let failed = |e: pprof::Error| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("couldn't build the profile: {}", e));
+        let report = guard.report().build().map_err(failed)?;
+        drop(guard);
+        if flamegraph && report.data.is_empty() {
+            return Ok(NO_SAMPLES.as_bytes().to_vec());
+        }
+        if flamegraph {
+            let mut svg = Vec::new();
+            report.flamegraph(&mut svg).map_err(failed)?;
+            return Ok(svg);
+        }
+        report.pprof().map_err(failed)?.write_to_bytes()
+            .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("couldn't encode the profile: {}", e)))
+    }).await??;
+
+    let (content_type, file) = if flamegraph { ("image/svg+xml", "flamegraph.svg") } else { ("application/octet-stream", "profile.pb") };
+    let disposition = format!("attachment; filename=\"{}\"", file);
+    Ok(([(header::CONTENT_TYPE, content_type.to_string()), (header::CONTENT_DISPOSITION, disposition)], body).into_response())
+}
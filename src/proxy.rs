@@ -0,0 +1,136 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Instant;
+
+use axum::extract::{ConnectInfo, FromRequestParts, Request, State};
+use axum::http::request::Parts;
+use axum::http::HeaderMap;
+use axum::middleware::Next;
+use axum::response::Response;
+use ipnet::IpNet;
+
+// who's really on the other end, in the request extensions for anything that wants it
+// None when the server wasn't given the peer address, e.g. a router embedded without connect info
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+impl<S: Send + Sync> FromRequestParts<S> for ClientIp{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<ClientIp>().copied().unwrap_or_default())
+    }
+}
+
+// the proxies whose X-Forwarded-For and Forwarded headers are believed, nobody's by default
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies{
+    nets: Vec<IpNet>,
+    // one line per request on stdout
+    access_log: bool,
+}
+
+impl TrustedProxies{
+    // comma separated CIDRs, a bare address is just that one, e.g. "127.0.0.1, 10.0.0.0/8, fd00::/8"
+    pub fn parse(list: &str) -> Result<TrustedProxies, String> {
+        let nets = list.split(',')
+            .map(str::trim)
+            .filter(|net| !net.is_empty())
+            .map(|net| {
+                net.parse::<IpNet>()
+                    .or_else(|_| net.parse::<IpAddr>().map(IpNet::from))
+                    .map_err(|_| format!("{:?} isn't an IP address or CIDR range", net))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(TrustedProxies { nets, access_log: false })
+    }
+
+    // TRUSTED_PROXIES is the list, ACCESS_LOG=on prints every request with its real client
+    pub fn from_env() -> Result<TrustedProxies, String> {
+        let proxies = match std::env::var("TRUSTED_PROXIES") {
+            Ok(list) => TrustedProxies::parse(&list).map_err(|e| format!("TRUSTED_PROXIES: {}", e))?,
+            Err(_) => TrustedProxies::default(),
+        };
+        let log = std::env::var("ACCESS_LOG").is_ok_and(|v| matches!(v.to_lowercase().as_str(), "on" | "true" | "1"));
+        Ok(proxies.access_log(log))
+    }
+
+    pub fn access_log(mut self, on: bool) -> TrustedProxies {
+        self.access_log = on;
+        self
+    }
+
+    pub fn trusts(&self, ip: IpAddr) -> bool {
+        self.nets.iter().any(|net| net.contains(&ip))
+    }
+
+    // walks back from the peer through the hops the trusted proxies added, stopping at the first one that isn't trusted
+    // anything further left could've been made up by the client, so it's never looked at
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        let mut client = to_canonical(peer);
+        if !self.trusts(client) {
+            return client;
+        }
+        for hop in forwarded_for(headers).into_iter().rev() {
+            // "unknown" or an obfuscated name, the proxy is as close as it gets
+            let Some(hop) = hop else { return client };
+            client = hop;
+            if !self.trusts(client) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+// ::ffff:10.0.0.1 from a dual-stack socket is 10.0.0.1
+fn to_canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        v4 => v4,
+    }
+}
+
+// every for= in Forwarded if there's one, otherwise X-Forwarded-For, nearest the client first
+// None for a hop that isn't an address
+fn forwarded_for(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    let values = |name: &str| -> Vec<String> {
+        headers.get_all(name).iter().filter_map(|v| v.to_str().ok()).flat_map(|v| v.split(',')).map(|s| s.trim().to_string()).collect()
+    };
+    let forwarded = values("forwarded");
+    if !forwarded.is_empty() {
+        return forwarded.iter()
+            .map(|element| {
+                element.split(';')
+                    .filter_map(|pair| pair.trim().split_once('='))
+                    .find(|(name, _)| name.eq_ignore_ascii_case("for"))
+                    .and_then(|(_, value)| parse_node(value.trim_matches('"')))
+            })
+            .collect();
+    }
+    values("x-forwarded-for").iter().map(|hop| parse_node(hop)).collect()
+}
+
+// 192.0.2.1, 192.0.2.1:4711, [2001:db8::1]:4711 or 2001:db8::1
+fn parse_node(node: &str) -> Option<IpAddr> {
+    node.parse::<IpAddr>().ok()
+        .or_else(|| node.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+        .or_else(|| node.strip_prefix('[')?.strip_suffix(']')?.parse().ok())
+        .map(to_canonical)
+}
+
+// puts the ClientIp in, outside everything else so even a 503 from the modes is logged with it
+pub async fn resolve(State(proxies): State<Arc<TrustedProxies>>, mut request: Request, next: Next) -> Response {
+    let peer = request.extensions().get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(addr)| addr.ip());
+    let client = ClientIp(peer.map(|peer| proxies.client_ip(peer, request.headers())));
+    request.extensions_mut().insert(client);
+    if !proxies.access_log {
+        return next.run(request).await;
+    }
+
+    let (method, uri, started) = (request.method().clone(), request.uri().clone(), Instant::now());
+    let response = next.run(request).await;
+    let ip = client.0.map_or("-".to_string(), |ip| ip.to_string());
+    println!("{} {} {} {} {}ms", ip, method, uri, response.status().as_u16(), started.elapsed().as_millis());
+    response
+}
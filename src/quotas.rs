@@ -0,0 +1,86 @@
+use serde::Serialize;
+use serde_json::json;
+
+use crate::battle::MAX_TEAM_SIZE;
+use crate::error::ApiError;
+use crate::models::Trainer;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Quota{
+    // team and box together
+    Pokemon,
+    Box,
+    Team,
+}
+
+// per-trainer limits, checked whenever a trainer would end up with more
+// nothing a trainer already has is taken away if the limits go down
+#[derive(Debug, Clone)]
+pub struct Quotas{
+    pub pokemon: Option<usize>,
+    pub pc_box: Option<usize>,
+    // never more than a battle allows
+    pub team: usize,
+}
+
+impl Default for Quotas{
+    fn default() -> Self {
+        Quotas { pokemon: None, pc_box: None, team: MAX_TEAM_SIZE }
+    }
+}
+
+impl Quotas{
+    // QUOTA_MAX_POKEMON, QUOTA_MAX_BOX and QUOTA_MAX_TEAM, no limit but the usual 6 on a team otherwise
+    pub fn from_env() -> Quotas {
+        let var = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<usize>().ok());
+        Quotas {
+            pokemon: var("QUOTA_MAX_POKEMON"),
+            pc_box: var("QUOTA_MAX_BOX"),
+            team: var("QUOTA_MAX_TEAM").map_or(MAX_TEAM_SIZE, |team| team.clamp(1, MAX_TEAM_SIZE)),
+        }
+    }
+
+    // would the trainer be within their quotas with this many on the team and in the box
+    pub fn check(&self, trainer: &Trainer, team: usize, pc_box: usize) -> Result<(), ApiError> {
+        if team > self.team {
+            return Err(exceeded(trainer, Quota::Team, trainer.team.len(), self.team));
+        }
+        if let Some(limit) = self.pc_box.filter(|limit| pc_box > *limit) {
+            return Err(exceeded(trainer, Quota::Box, trainer.pc_box.len(), limit));
+        }
+        if let Some(limit) = self.pokemon.filter(|limit| team + pc_box > *limit) {
+            return Err(exceeded(trainer, Quota::Pokemon, trainer.team.len() + trainer.pc_box.len(), limit));
+        }
+        Ok(())
+    }
+
+    // room for one more, wherever receive would put it
+    pub fn check_receive(&self, trainer: &Trainer) -> Result<(), ApiError> {
+        let (team, pc_box) = (trainer.team.len(), trainer.pc_box.len());
+        if team < self.team {
+            self.check(trainer, team + 1, pc_box)
+        } else {
+            self.check(trainer, team, pc_box + 1)
+        }
+    }
+
+    // like Trainer::receive, but the team is full at the team quota
+    pub fn receive(&self, trainer: &mut Trainer, pokemon_id: u32) {
+        if trainer.team.len() < self.team {
+            trainer.team.push(pokemon_id);
+        } else {
+            trainer.pc_box.push(pokemon_id);
+        }
+    }
+}
+
+fn exceeded(trainer: &Trainer, quota: Quota, usage: usize, limit: usize) -> ApiError {
+    let what = match quota {
+        Quota::Pokemon => "Pokemon",
+        Quota::Box => "Pokemon in their box",
+        Quota::Team => "Pokemon on their team",
+    };
+    ApiError::forbidden(format!("{} can have at most {} {}", trainer.name, limit, what))
+        .with_details(json!({ "quota": quota, "usage": usage, "limit": limit }))
+}
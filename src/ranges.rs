@@ -0,0 +1,64 @@
+use axum::body::Bytes;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+
+// what a Range header asks of a body this long
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Wanted{
+    Whole,
+    // first..=last
+    Part(u64, u64),
+    Unsatisfiable,
+}
+
+// only a single range is served, a list of them gets the whole body, which the spec allows and is all a resume needs
+// anything that doesn't parse is ignored the same way
+fn wanted(range: &str, len: u64) -> Wanted {
+    let Some(spec) = range.trim().strip_prefix("bytes=") else { return Wanted::Whole };
+    if spec.contains(',') {
+        return Wanted::Whole;
+    }
+    let Some((first, last)) = spec.trim().split_once('-') else { return Wanted::Whole };
+    let (first, last) = (first.trim(), last.trim());
+    match (first.parse::<u64>(), last.parse::<u64>()) {
+        // the last n bytes
+        (Err(_), Ok(n)) if first.is_empty() => {
+            if n == 0 || len == 0 {
+                Wanted::Unsatisfiable
+            } else {
+                Wanted::Part(len.saturating_sub(n), len - 1)
+            }
+        }
+        // from somewhere to the end
+        (Ok(first), Err(_)) if last.is_empty() => {
+            if first >= len { Wanted::Unsatisfiable } else { Wanted::Part(first, len - 1) }
+        }
+        (Ok(first), Ok(last)) if first <= last => {
+            if first >= len { Wanted::Unsatisfiable } else { Wanted::Part(first, last.min(len - 1)) }
+        }
+        _ => Wanted::Whole,
+    }
+}
+
+// the body with Accept-Ranges, or 206 with just the part the Range header asks for
+// If-Range has to match the ETag for that, otherwise the body changed since the download started and it gets all of it again
+pub fn respond(request: &HeaderMap, etag: &str, bytes: impl Into<Bytes>) -> Response {
+    let bytes = bytes.into();
+    let len = bytes.len() as u64;
+    let fresh = request.get(header::IF_RANGE).is_none_or(|v| v.as_bytes() == etag.as_bytes());
+    let range = request.get(header::RANGE).and_then(|v| v.to_str().ok()).filter(|_| fresh);
+    let accept = (header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+
+    match range.map_or(Wanted::Whole, |range| wanted(range, len)) {
+        Wanted::Whole => ([accept], bytes).into_response(),
+        Wanted::Part(first, last) => {
+            let content_range = format!("bytes {}-{}/{}", first, last, len);
+            let part = bytes.slice(first as usize..=last as usize);
+            (StatusCode::PARTIAL_CONTENT, [accept, (header::CONTENT_RANGE, HeaderValue::from_str(&content_range).expect("digits are a valid header"))], part).into_response()
+        }
+        Wanted::Unsatisfiable => {
+            let content_range = HeaderValue::from_str(&format!("bytes */{}", len)).expect("digits are a valid header");
+            (StatusCode::RANGE_NOT_SATISFIABLE, [accept, (header::CONTENT_RANGE, content_range)]).into_response()
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::auth::Auth;
+use crate::error::ApiError;
+use crate::proxy::ClientIp;
+use crate::state::SharedState;
+use crate::viewer::bearer;
+
+// budgets are per minute, each caller's starts over on the minute from their first request
+const WINDOW: Duration = Duration::from_secs(60);
+
+// past this many callers, the ones whose minute is up are forgotten
+const PRUNE_AT: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Tier{
+    // no key and no session, counted per client IP however many trainer IDs it sends
+    Anonymous,
+    // a session from logging in, or a key given this tier
+    Free,
+    Premium,
+}
+
+impl Tier{
+    pub const ALL: [Tier; 3] = [Tier::Anonymous, Tier::Free, Tier::Premium];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Tier::Anonymous => "anonymous",
+            Tier::Free => "free",
+            Tier::Premium => "premium",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Tier> {
+        Tier::ALL.into_iter().find(|t| t.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    fn default_budget(self) -> Budget {
+        match self {
+            Tier::Anonymous => Budget { reads: 60, writes: 10 },
+            Tier::Free => Budget { reads: 300, writes: 60 },
+            Tier::Premium => Budget { reads: 1200, writes: 300 },
+        }
+    }
+}
+
+// requests a minute, 0 for no limit
+// reads are GET, HEAD and OPTIONS, writes are everything else
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Budget{
+    pub reads: u32,
+    pub writes: u32,
+}
+
+impl Budget{
+    // "300/60", reads then writes
+    fn parse(text: &str) -> Option<Budget> {
+        let (reads, writes) = text.split_once('/')?;
+        Some(Budget { reads: reads.trim().parse().ok()?, writes: writes.trim().parse().ok()? })
+    }
+}
+
+// who a budget is counted against
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Caller{
+    Key(String),
+    Trainer(u32),
+    // None when there's no peer address to go by, they all share one budget
+    Ip(Option<IpAddr>),
+}
+
+#[derive(Debug)]
+struct Window{
+    started: Instant,
+    used: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct RateLimits{
+    on: bool,
+    budgets: HashMap<Tier, Budget>,
+    // X-Api-Key -> its tier
+    keys: HashMap<String, Tier>,
+    windows: Mutex<HashMap<(Caller, bool), Window>>,
+}
+
+impl RateLimits{
+    // on, with the default budget for every tier and no keys yet
+    pub fn new() -> RateLimits {
+        RateLimits { on: true, budgets: Tier::ALL.into_iter().map(|t| (t, t.default_budget())).collect(), ..RateLimits::default() }
+    }
+
+    // RATE_LIMIT_ANONYMOUS, RATE_LIMIT_FREE and RATE_LIMIT_PREMIUM as reads/writes a minute, e.g. 300/60
+    // API_KEYS gives keys their tier, e.g. "k3y=premium,0th3r=free"
+    // off unless at least one of them is set
+    pub fn from_env() -> Result<RateLimits, String> {
+        let budgets: Vec<(Tier, String)> = Tier::ALL.into_iter()
+            .filter_map(|t| std::env::var(format!("RATE_LIMIT_{}", t.name().to_uppercase())).ok().map(|b| (t, b)))
+            .collect();
+        let keys = crate::secrets::var("API_KEYS").ok();
+        if budgets.is_empty() && keys.is_none() {
+            return Ok(RateLimits::default());
+        }
+        let mut limits = RateLimits::new();
+        for (tier, budget) in budgets {
+            let parsed = Budget::parse(&budget)
+                .ok_or_else(|| format!("RATE_LIMIT_{} should be reads/writes a minute like 300/60, not {:?}", tier.name().to_uppercase(), budget))?;
+            limits = limits.budget(tier, parsed);
+        }
+        for pair in keys.iter().flat_map(|k| k.split(',')).map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, tier) = pair.split_once('=')
+                .and_then(|(key, tier)| Some((key.trim(), Tier::parse(tier)?)))
+                .ok_or_else(|| format!("API_KEYS entries look like key=premium, not {:?}", pair))?;
+            limits = limits.key(key, tier);
+        }
+        Ok(limits)
+    }
+
+    pub fn budget(mut self, tier: Tier, budget: Budget) -> RateLimits {
+        self.budgets.insert(tier, budget);
+        self
+    }
+
+    pub fn key(mut self, key: &str, tier: Tier) -> RateLimits {
+        self.keys.insert(key.to_string(), tier);
+        self
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.on
+    }
+
+    fn caller(&self, headers: &HeaderMap, ip: ClientIp, auth: &Auth, now: u64) -> Result<(Caller, Tier), ApiError> {
+        if let Some(key) = headers.get("x-api-key") {
+            let key = key.to_str().unwrap_or_default().trim();
+            return self.keys.get(key)
+                .map(|tier| (Caller::Key(key.to_string()), *tier))
+                .ok_or_else(|| ApiError::new(StatusCode::UNAUTHORIZED, "that API key isn't known"));
+        }
+        // X-Trainer-Id costs nothing to change, so only a session someone logged in for gets a budget of its own
+        // a bad one is turned away by the handler
+        match bearer(headers).and_then(|token| auth.verify(token, now).ok()) {
+            Some(id) => Ok((Caller::Trainer(id), Tier::Free)),
+            None => Ok((Caller::Ip(ip.0), Tier::Anonymous)),
+        }
+    }
+
+    // one more request, with what's left after it or how long until there's room
+    fn take(&self, caller: Caller, write: bool, limit: u32, now: Instant) -> Result<(u32, Duration), Duration> {
+        let mut windows = self.windows.lock().unwrap();
+        if windows.len() > PRUNE_AT {
+            windows.retain(|_, w| now.duration_since(w.started) < WINDOW);
+        }
+        let window = windows.entry((caller, write)).or_insert(Window { started: now, used: 0 });
+        if now.duration_since(window.started) >= WINDOW {
+            *window = Window { started: now, used: 0 };
+        }
+        let reset = WINDOW.saturating_sub(now.duration_since(window.started));
+        if window.used >= limit {
+            return Err(reset);
+        }
+        window.used += 1;
+        Ok((limit - window.used, reset))
+    }
+}
+
+fn set_headers(headers: &mut HeaderMap, tier: Tier, limit: u32, remaining: u32, reset: Duration) {
+    // rounded up, so waiting that long is always enough
+    let reset = reset.as_secs() + u64::from(reset.subsec_nanos() > 0);
+    headers.insert("x-ratelimit-tier", HeaderValue::from_static(tier.name()));
+    headers.insert("x-ratelimit-limit", HeaderValue::from(limit));
+    headers.insert("x-ratelimit-remaining", HeaderValue::from(remaining));
+    headers.insert("x-ratelimit-reset", HeaderValue::from(reset));
+}
+
+// counts the request against the caller's budget, 429 once it's used up
+pub async fn enforce(State((limits, state)): State<(Arc<RateLimits>, SharedState)>, request: Request, next: Next) -> Response {
+    if !limits.on {
+        return next.run(request).await;
+    }
+    let ip = request.extensions().get::<ClientIp>().copied().unwrap_or_default();
+    let (caller, tier) = match limits.caller(request.headers(), ip, &state.auth, state.time.now()) {
+        Ok(caller) => caller,
+        Err(e) => return e.into_response(),
+    };
+    let write = !matches!(*request.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    let budget = limits.budgets.get(&tier).copied().unwrap_or(tier.default_budget());
+    let limit = if write { budget.writes } else { budget.reads };
+    if limit == 0 {
+        return next.run(request).await;
+    }
+
+    match limits.take(caller, write, limit, Instant::now()) {
+        Ok((remaining, reset)) => {
+            let mut response = next.run(request).await;
+            set_headers(response.headers_mut(), tier, limit, remaining, reset);
+            response
+        }
+        Err(reset) => {
+            let kind = if write { "writes" } else { "reads" };
+            let mut response = ApiError::new(StatusCode::TOO_MANY_REQUESTS, format!("the {} tier gets {} {} a minute", tier.name(), limit, kind))
+                .with_details(json!({ "tier": tier, "limit": limit }))
+                .into_response();
+            set_headers(response.headers_mut(), tier, limit, 0, reset);
+            let retry_after = response.headers()["x-ratelimit-reset"].clone();
+            response.headers_mut().insert("retry-after", retry_after);
+            response
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::modes::Mode;
+use crate::state::SharedState;
+
+// one thing GET /readyz looked at
+#[derive(Debug, Clone, Serialize)]
+pub struct Check{
+    pub name: &'static str,
+    pub ok: bool,
+    // false when the app gets by without it, like the Redis lookup cache
+    pub required: bool,
+    // e.g. "in memory", "answered in 2ms" or why it failed
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Readiness{
+    pub ready: bool,
+    pub checks: Vec<Check>,
+}
+
+// ready unless something it needs is down, or it's been put in maintenance mode
+pub async fn check(state: &SharedState) -> Readiness {
+    let settings = state.modes.get();
+    // "read_only", as PUT /admin/mode takes it, or the message the operator left
+    let mode = serde_json::to_value(settings.mode).ok().and_then(|m| m.as_str().map(str::to_string)).unwrap_or_default();
+    let mut checks = vec![
+        // everything's in memory, so it's there as long as the process is
+        Check { name: "storage", ok: true, required: true, detail: "in memory".to_string() },
+        Check { name: "mode", ok: settings.mode != Mode::Maintenance, required: true, detail: settings.message.unwrap_or(mode) },
+    ];
+    if let Some(redis) = &state.redis {
+        let started = Instant::now();
+        checks.push(match redis.ping().await {
+            Ok(()) => Check { name: "redis", ok: true, required: false, detail: format!("answered in {}ms", started.elapsed().as_millis()) },
+            // pokeapi.co is asked directly while the cache is down
+            Err(e) => Check { name: "redis", ok: false, required: false, detail: e },
+        });
+    }
+    Readiness { ready: checks.iter().all(|c| c.ok || !c.required), checks }
+}
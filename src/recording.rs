@@ -0,0 +1,283 @@
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+use axum::body::{to_bytes, Body, Bytes, HttpBody};
+use axum::extract::{Request, State};
+use axum::http::HeaderMap;
+use axum::middleware::Next;
+use axum::response::Response;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::models::unix_time;
+use crate::sharing::hex;
+use crate::state::SharedState;
+
+// bigger bodies, or ones of unknown length like uploads sent chunked, aren't kept
+pub const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+// the connection's own business, a replay sets its own
+const SKIPPED_HEADERS: &[&str] = &["host", "connection", "content-length", "transfer-encoding", "keep-alive", "upgrade"];
+// kept as "redacted" so a recording can be passed around, a replay puts its own admin token back
+const SECRET_HEADERS: &[&str] = &["authorization", "cookie", "set-cookie"];
+pub const REDACTED: &str = "redacted";
+
+// RECORD_TRAFFIC appends every request and its response here, one JSON object a line
+#[derive(Debug, Default)]
+pub struct Recorder{
+    // None when it's off
+    file: Option<Mutex<File>>,
+}
+
+// a request and the response it got, as a line of the recording
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Exchange{
+    // unix seconds
+    pub at: u64,
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedRequest{
+    pub method: String,
+    // the path and query
+    pub uri: String,
+    pub headers: BTreeMap<String, String>,
+    #[serde(flatten)]
+    pub body: RecordedBody,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedResponse{
+    pub status: u16,
+    pub headers: BTreeMap<String, String>,
+    #[serde(flatten)]
+    pub body: RecordedBody,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecordedBody{
+    // as text when it's UTF-8
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_hex: Option<String>,
+    // past MAX_BODY_SIZE or of unknown length, so it wasn't kept
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub too_big: bool,
+}
+
+impl RecordedBody{
+    fn of(bytes: &[u8]) -> RecordedBody {
+        match std::str::from_utf8(bytes) {
+            _ if bytes.is_empty() => RecordedBody::default(),
+            Ok(text) => RecordedBody { body: Some(text.to_string()), ..RecordedBody::default() },
+            Err(_) => RecordedBody { body_hex: Some(hex(bytes)), ..RecordedBody::default() },
+        }
+    }
+
+    // the bytes again, None for a body that wasn't kept
+    pub fn bytes(&self) -> Option<Vec<u8>> {
+        if self.too_big {
+            return None;
+        }
+        if let Some(text) = &self.body {
+            return Some(text.clone().into_bytes());
+        }
+        let Some(hex) = &self.body_hex else { return Some(Vec::new()) };
+        (0..hex.len()).step_by(2).map(|i| hex.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok())).collect()
+    }
+}
+
+impl Recorder{
+    // appends to the file, making it if it isn't there
+    pub fn to_file(path: impl AsRef<Path>) -> std::io::Result<Recorder> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Recorder { file: Some(Mutex::new(file)) })
+    }
+
+    // RECORD_TRAFFIC, off otherwise
+    pub fn from_env() -> Result<Recorder, String> {
+        match std::env::var("RECORD_TRAFFIC") {
+            Ok(path) => Recorder::to_file(&path).map_err(|e| format!("can't record traffic to RECORD_TRAFFIC {}: {}", path, e)),
+            Err(_) => Ok(Recorder::default()),
+        }
+    }
+
+    pub fn on(&self) -> bool {
+        self.file.is_some()
+    }
+
+    fn write(&self, exchange: &Exchange) {
+        let Some(file) = &self.file else { return };
+        let mut line = serde_json::to_vec(exchange).expect("exchanges always serialize");
+        line.push(b'\n');
+        // one short append a request, all in one write so lines from requests at the same time don't mix
+        if let Err(e) = file.lock().unwrap().write_all(&line) {
+            eprintln!("recording: couldn't write to the recording: {}", e);
+        }
+    }
+}
+
+fn recorded_headers(headers: &HeaderMap) -> BTreeMap<String, String> {
+    headers.iter()
+        .filter(|(name, _)| !SKIPPED_HEADERS.contains(&name.as_str()))
+        .map(|(name, value)| {
+            let value = if SECRET_HEADERS.contains(&name.as_str()) { REDACTED.to_string() } else { String::from_utf8_lossy(value.as_bytes()).into_owned() };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+// the whole body when it's small enough, or it's left streaming and marked too_big
+async fn take_body(body: Body) -> (Body, RecordedBody) {
+    if body.size_hint().exact().is_none_or(|len| len as usize > MAX_BODY_SIZE) {
+        return (body, RecordedBody { too_big: true, ..RecordedBody::default() });
+    }
+    match to_bytes(body, MAX_BODY_SIZE).await {
+        Ok(bytes) => (Body::from(bytes.clone()), RecordedBody::of(&bytes)),
+        Err(_) => (Body::from(Bytes::new()), RecordedBody { too_big: true, ..RecordedBody::default() }),
+    }
+}
+
+// middleware for the whole app, outside everything but the access log so it sees what the client saw
+pub async fn record(State(state): State<SharedState>, request: Request, next: Next) -> Response {
+    if !state.recorder.on() {
+        return next.run(request).await;
+    }
+    let (parts, body) = request.into_parts();
+    let (body, request_body) = take_body(body).await;
+    let recorded = RecordedRequest {
+        method: parts.method.to_string(),
+        uri: parts.uri.path_and_query().map_or("/".to_string(), |p| p.to_string()),
+        headers: recorded_headers(&parts.headers),
+        body: request_body,
+    };
+    let response = next.run(Request::from_parts(parts, body)).await;
+
+    let (parts, body) = response.into_parts();
+    let (body, response_body) = take_body(body).await;
+    let exchange = Exchange {
+        at: unix_time(),
+        request: recorded,
+        response: RecordedResponse { status: parts.status.as_u16(), headers: recorded_headers(&parts.headers), body: response_body },
+    };
+    state.recorder.write(&exchange);
+    Response::from_parts(parts, body)
+}
+
+// every line of a recording, in order
+pub fn read(path: impl AsRef<Path>) -> Result<Vec<Exchange>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("can't read the recording: {}", e))?;
+    text.lines().enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| serde_json::from_str(line).map_err(|e| format!("line {} of the recording isn't an exchange: {}", i + 1, e)))
+        .collect()
+}
+
+// how a replay went
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReplayReport{
+    pub replayed: usize,
+    pub matched: usize,
+    // bodies that weren't kept, and WebSocket upgrades
+    pub skipped: usize,
+    pub mismatches: Vec<Mismatch>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Mismatch{
+    // 1-based, counting every exchange in the recording
+    pub exchange: usize,
+    pub method: String,
+    pub uri: String,
+    pub expected_status: u16,
+    pub status: u16,
+    // JSON paths of the fields that came back different, e.g. "$.team[0].level"
+    pub differences: Vec<String>,
+}
+
+// fields that differ from one run to the next however the requests go
+fn volatile(key: &str) -> bool {
+    key.ends_with("_at") || matches!(key, "seed" | "token" | "etag" | "expires")
+}
+
+// the first few fields that differ, a small body that's gone completely wrong doesn't list everything in it
+const MAX_DIFFERENCES: usize = 20;
+
+fn differences(path: String, expected: &Value, got: &Value, out: &mut Vec<String>) {
+    if out.len() >= MAX_DIFFERENCES {
+        return;
+    }
+    match (expected, got) {
+        (Value::Object(expected), Value::Object(got)) => {
+            for key in expected.keys().chain(got.keys().filter(|k| !expected.contains_key(*k))) {
+                if volatile(key) {
+                    continue;
+                }
+                let path = format!("{}.{}", path, key);
+                match (expected.get(key), got.get(key)) {
+                    (Some(e), Some(g)) => differences(path, e, g, out),
+                    _ => out.push(path),
+                }
+            }
+        }
+        (Value::Array(expected), Value::Array(got)) if expected.len() == got.len() => {
+            for (i, (e, g)) in expected.iter().zip(got).enumerate() {
+                differences(format!("{}[{}]", path, i), e, g, out);
+            }
+        }
+        _ if expected != got => out.push(path),
+        _ => {}
+    }
+}
+
+// sends every request in the recording to base_url in order, and checks each answer against the recorded one
+// statuses have to be the same, JSON bodies too apart from timestamps and other fields that always differ
+pub async fn replay(base_url: &str, exchanges: &[Exchange], admin_token: Option<&str>) -> Result<ReplayReport, String> {
+    let client = reqwest::Client::new();
+    let mut report = ReplayReport::default();
+    for (i, exchange) in exchanges.iter().enumerate() {
+        let (request, expected) = (&exchange.request, &exchange.response);
+        let Some(body) = request.body.bytes().filter(|_| expected.status != 101) else {
+            report.skipped += 1;
+            continue;
+        };
+        let method = reqwest::Method::from_bytes(request.method.as_bytes()).map_err(|_| format!("exchange {} has a bad method {:?}", i + 1, request.method))?;
+        let mut sent = client.request(method, format!("{}{}", base_url.trim_end_matches('/'), request.uri)).body(body);
+        for (name, value) in &request.headers {
+            match (name.as_str(), value.as_str(), admin_token) {
+                ("authorization", REDACTED, Some(token)) => sent = sent.header(name, format!("Bearer {}", token)),
+                (_, REDACTED, _) => {}
+                _ => sent = sent.header(name, value),
+            }
+        }
+        let response = sent.send().await.map_err(|e| format!("exchange {} ({} {}) got no answer: {}", i + 1, request.method, request.uri, e))?;
+        let status = response.status().as_u16();
+        let got = response.bytes().await.map_err(|e| format!("exchange {} ({} {}) got no answer: {}", i + 1, request.method, request.uri, e))?;
+        report.replayed += 1;
+
+        let mut found = Vec::new();
+        let json = |bytes: &[u8]| serde_json::from_slice::<Value>(bytes).ok();
+        if let (Some(expected), Some(got)) = (expected.body.bytes().as_deref().and_then(json), json(&got)) {
+            differences("$".to_string(), &expected, &got, &mut found);
+        }
+        if status == expected.status && found.is_empty() {
+            report.matched += 1;
+        } else {
+            report.mismatches.push(Mismatch {
+                exchange: i + 1,
+                method: request.method.clone(),
+                uri: request.uri.clone(),
+                expected_status: expected.status,
+                status,
+                differences: found,
+            });
+        }
+    }
+    Ok(report)
+}
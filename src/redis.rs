@@ -0,0 +1,233 @@
+use std::time::Duration;
+
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+use crate::secrets;
+
+// a slow or missing Redis shouldn't hold a request up for long
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+// species data doesn't change, a week keeps one-off typos from piling up
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+// REDIS_URL, a pokeapi.co lookup cache shared by every instance and kept across restarts
+#[derive(Debug)]
+pub struct Redis{
+    // host:port
+    addr: String,
+    username: Option<String>,
+    password: Option<String>,
+    db: u32,
+    // in front of every key, so a few apps can share one Redis
+    prefix: String,
+    // how long anything cached is kept
+    cache_ttl: Duration,
+    // one connection, made on first use and again after it breaks
+    connection: tokio::sync::Mutex<Option<BufReader<TcpStream>>>,
+}
+
+// what Redis answered, an error reply comes back as Err instead
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reply{
+    Status(String),
+    Integer(i64),
+    Bulk(Option<Vec<u8>>),
+    Array(Option<Vec<Reply>>),
+}
+
+fn parse_url(url: &str) -> Result<(String, Option<String>, Option<String>, u32), String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("REDIS_URL isn't a URL: {}", e))?;
+    match parsed.scheme() {
+        "redis" => {}
+        "rediss" => return Err("REDIS_URL: rediss:// isn't supported, put a TLS proxy like stunnel in front and use redis://".to_string()),
+        other => return Err(format!("REDIS_URL should start with redis://, not {}://", other)),
+    }
+    let host = parsed.host_str().filter(|h| !h.is_empty()).ok_or("REDIS_URL has no host")?;
+    let addr = format!("{}:{}", host, parsed.port().unwrap_or(6379));
+    let username = Some(parsed.username().to_string()).filter(|u| !u.is_empty());
+    let password = parsed.password().map(str::to_string);
+    let db = match parsed.path().trim_matches('/') {
+        "" => 0,
+        db => db.parse().map_err(|_| format!("REDIS_URL: the database is a number, not {:?}", db))?,
+    };
+    Ok((addr, username, password, db))
+}
+
+fn encode(args: &[&[u8]]) -> Vec<u8> {
+    let mut out = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        out.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        out.extend_from_slice(arg);
+        out.extend_from_slice(b"\r\n");
+    }
+    out
+}
+
+// Ok(Err(..)) is an error reply, the outer Err is the connection going wrong
+async fn read_reply(reader: &mut BufReader<TcpStream>) -> std::io::Result<Result<Reply, String>> {
+    let broken = |what: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Redis sent {}", what));
+    let mut line = Vec::new();
+    if reader.read_until(b'\n', &mut line).await? == 0 {
+        return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "Redis closed the connection"));
+    }
+    let line = String::from_utf8_lossy(&line).trim_end_matches("\r\n").to_string();
+    let (kind, rest) = line.split_at_checked(1).ok_or_else(|| broken("an empty line"))?;
+    let number = || rest.parse::<i64>().map_err(|_| broken("a length that isn't a number"));
+    Ok(Ok(match kind {
+        "+" => Reply::Status(rest.to_string()),
+        "-" => return Ok(Err(rest.to_string())),
+        ":" => Reply::Integer(number()?),
+        "$" => match number()? {
+            -1 => Reply::Bulk(None),
+            length => {
+                let mut bytes = vec![0; length as usize + 2];
+                reader.read_exact(&mut bytes).await?;
+                bytes.truncate(length as usize);
+                Reply::Bulk(Some(bytes))
+            }
+        },
+        "*" => match number()? {
+            -1 => Reply::Array(None),
+            length => {
+                let mut items = Vec::new();
+                for _ in 0..length {
+                    match Box::pin(read_reply(reader)).await? {
+                        Ok(item) => items.push(item),
+                        Err(e) => return Ok(Err(e)),
+                    }
+                }
+                Reply::Array(Some(items))
+            }
+        },
+        _ => return Err(broken("something that isn't RESP")),
+    }))
+}
+
+impl Redis{
+    // redis://[user:password@]host[:port][/db]
+    pub fn new(url: &str) -> Result<Redis, String> {
+        let (addr, username, password, db) = parse_url(url)?;
+        Ok(Redis {
+            addr,
+            username,
+            password,
+            db,
+            prefix: "pokemon-api:".to_string(),
+            cache_ttl: DEFAULT_CACHE_TTL,
+            connection: tokio::sync::Mutex::default(),
+        })
+    }
+
+    // None without REDIS_URL, REDIS_PREFIX and REDIS_CACHE_TTL_SECS change the defaults
+    pub fn from_env() -> Result<Option<Redis>, String> {
+        let Ok(url) = secrets::var("REDIS_URL") else { return Ok(None) };
+        let mut redis = Redis::new(&url)?;
+        if let Ok(prefix) = std::env::var("REDIS_PREFIX") {
+            redis.prefix = prefix;
+        }
+        if let Ok(secs) = std::env::var("REDIS_CACHE_TTL_SECS") {
+            redis.cache_ttl = Duration::from_secs(secs.parse().map_err(|_| format!("REDIS_CACHE_TTL_SECS should be a number of seconds, not {:?}", secs))?);
+        }
+        Ok(Some(redis))
+    }
+
+    pub fn prefix(mut self, prefix: &str) -> Redis {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    pub fn cache_ttl(mut self, ttl: Duration) -> Redis {
+        self.cache_ttl = ttl;
+        self
+    }
+
+    async fn connect(&self) -> Result<BufReader<TcpStream>, String> {
+        let stream = TcpStream::connect(&self.addr).await.map_err(|e| format!("can't reach Redis at {}: {}", self.addr, e))?;
+        let mut connection = BufReader::new(stream);
+        let auth = match (&self.username, &self.password) {
+            (Some(user), Some(password)) => Some(vec![b"AUTH".as_slice(), user.as_bytes(), password.as_bytes()]),
+            (None, Some(password)) => Some(vec![b"AUTH".as_slice(), password.as_bytes()]),
+            _ => None,
+        };
+        let db = self.db.to_string();
+        let setup = auth.into_iter().chain((self.db != 0).then(|| vec![b"SELECT".as_slice(), db.as_bytes()]));
+        for args in setup {
+            let io = |e: std::io::Error| format!("Redis at {}: {}", self.addr, e);
+            connection.get_mut().write_all(&encode(&args)).await.map_err(io)?;
+            read_reply(&mut connection).await.map_err(io)?.map_err(|e| format!("Redis said no to {}: {}", String::from_utf8_lossy(args[0]), e))?;
+        }
+        Ok(connection)
+    }
+
+    // one command on the shared connection, with one retry on a fresh one if the old one had gone stale
+    pub async fn command(&self, args: &[&[u8]]) -> Result<Reply, String> {
+        let run = async {
+            let mut connection = self.connection.lock().await;
+            for attempt in 0..2 {
+                let mut open = match connection.take() {
+                    Some(open) => open,
+                    None => self.connect().await?,
+                };
+                let sent = async {
+                    open.get_mut().write_all(&encode(args)).await?;
+                    read_reply(&mut open).await
+                };
+                match sent.await {
+                    Ok(reply) => {
+                        *connection = Some(open);
+                        return reply;
+                    }
+                    Err(e) if attempt == 1 => return Err(format!("Redis at {}: {}", self.addr, e)),
+                    Err(_) => {}
+                }
+            }
+            unreachable!("the second attempt returns either way")
+        };
+        tokio::time::timeout(TIMEOUT, run).await
+            .unwrap_or_else(|_| Err(format!("Redis at {} didn't answer within {:?}", self.addr, TIMEOUT)))
+    }
+
+    pub async fn ping(&self) -> Result<(), String> {
+        match self.command(&[b"PING"]).await? {
+            Reply::Status(pong) if pong == "PONG" => Ok(()),
+            other => Err(format!("Redis answered PING with {:?}", other)),
+        }
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+        let key = format!("{}{}", self.prefix, key);
+        match self.command(&[b"GET", key.as_bytes()]).await? {
+            Reply::Bulk(value) => Ok(value),
+            other => Err(format!("Redis answered GET with {:?}", other)),
+        }
+    }
+
+    // gone after `ttl` if there is one
+    pub async fn set(&self, key: &str, value: &[u8], ttl: Option<Duration>) -> Result<(), String> {
+        let key = format!("{}{}", self.prefix, key);
+        let secs = ttl.map(|ttl| ttl.as_secs().max(1).to_string());
+        let mut args = vec![b"SET".as_slice(), key.as_bytes(), value];
+        if let Some(secs) = &secs {
+            args.extend([b"EX".as_slice(), secs.as_bytes()]);
+        }
+        self.command(&args).await.map(drop)
+    }
+
+    // what cache() put there, None if it's not there, has expired or Redis can't be reached
+    pub async fn cached<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let bytes = self.get(key).await
+            .inspect_err(|e| eprintln!("couldn't read {} from the cache: {}", key, e))
+            .ok()??;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    // as JSON for REDIS_CACHE_TTL_SECS, a failure is only printed since the caller has the value anyway
+    pub async fn cache<T: Serialize>(&self, key: &str, value: &T) {
+        let Ok(json) = serde_json::to_vec(value) else { return };
+        if let Err(e) = self.set(key, &json, Some(self.cache_ttl)).await {
+            eprintln!("couldn't cache {}: {}", key, e);
+        }
+    }
+}
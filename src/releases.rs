@@ -0,0 +1,61 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::models::Pokemon;
+
+// how long a confirmation token works for
+pub const CONFIRM_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+// the first half of a release, sending the token back does the releasing
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingRelease{
+    pub pokemon_id: u32,
+    pub name: String,
+    pub level: u32,
+    pub trainer_id: Option<u32>,
+    pub token: String,
+    pub expires_at: u64,
+}
+
+// releases waiting to be confirmed, at most one per Pokemon
+#[derive(Debug, Default)]
+pub struct Releases{
+    pending: Mutex<Vec<PendingRelease>>,
+}
+
+impl Releases{
+    // asking again replaces the earlier token
+    pub fn request(&self, pokemon: &Pokemon, trainer_id: Option<u32>, now: u64) -> PendingRelease {
+        let release = PendingRelease {
+            pokemon_id: pokemon.id,
+            name: pokemon.name.clone(),
+            level: pokemon.level,
+            trainer_id,
+            token: format!("{:032x}", rand::random::<u128>()),
+            expires_at: now + CONFIRM_WINDOW.as_secs(),
+        };
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|r| r.pokemon_id != pokemon.id);
+        pending.push(release.clone());
+        release
+    }
+
+    // a token only works once, and only for the Pokemon it was given for
+    pub fn confirm(&self, pokemon_id: u32, token: &str, now: u64) -> Result<PendingRelease, String> {
+        let mut pending = self.pending.lock().unwrap();
+        let index = pending.iter().position(|r| r.pokemon_id == pokemon_id && r.token == token)
+            .ok_or("that confirmation token isn't valid")?;
+        let release = pending.remove(index);
+        if release.expires_at < now {
+            return Err("that confirmation token has expired, ask for a new one".to_string());
+        }
+        Ok(release)
+    }
+
+    // e.g. when the Pokemon is gone some other way
+    pub fn forget(&self, pokemon_id: u32) {
+        self.pending.lock().unwrap().retain(|r| r.pokemon_id != pokemon_id);
+    }
+}
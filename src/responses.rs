@@ -0,0 +1,312 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use axum::body::{to_bytes, Body, Bytes, HttpBody};
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use serde::Serialize;
+
+use crate::state::SharedState;
+
+pub const DEFAULT_CAPACITY: usize = 1000;
+// a safety net for whatever changes records without saying so, like plugins and the console
+pub const DEFAULT_TTL: Duration = Duration::from_secs(10);
+
+// the reads that are cached, route -> the collection it shows
+const CACHED_ROUTES: &[(&str, &str)] = &[
+    ("/pokemon", "pokemon"),
+    ("/pokemon/{id}", "pokemon"),
+    ("/trainers", "trainers"),
+    ("/trainers/{id}", "trainers"),
+    ("/gyms", "gyms"),
+    ("/gyms/{id}", "gyms"),
+    ("/battles", "battles"),
+    ("/battles/{id}", "battles"),
+    ("/market", "market"),
+    ("/market/{id}", "market"),
+];
+
+// what else a write to a collection can change, a Pokemon's owner decides who can see it
+// a write anywhere not listed here clears everything
+const ALSO_CHANGES: &[(&str, &[&str])] = &[
+    ("pokemon", &["trainers"]),
+    ("trainers", &["pokemon"]),
+    ("gyms", &["trainers", "battles"]),
+];
+
+// the GETs that change something, finishing a login can make a trainer
+const WRITING_ROUTES: &[&str] = &["/auth/{provider}/callback"];
+
+// bigger responses aren't kept
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug)]
+struct Entry{
+    collection: &'static str,
+    // None for a list
+    id: Option<u32>,
+    headers: HeaderMap,
+    body: Bytes,
+    stored_at: Instant,
+    // its place in the LRU order
+    used: u64,
+}
+
+#[derive(Debug, Default)]
+struct Entries{
+    by_key: HashMap<u64, Entry>,
+    // last used -> key, oldest first
+    order: BTreeMap<u64, u64>,
+    clock: u64,
+    // bumped by every invalidation, so a response read before one isn't stored after it
+    generation: u64,
+}
+
+impl Entries{
+    fn remove(&mut self, key: u64) {
+        if let Some(entry) = self.by_key.remove(&key) {
+            self.order.remove(&entry.used);
+        }
+    }
+
+    fn touch(&mut self, key: u64) {
+        self.clock += 1;
+        let clock = self.clock;
+        if let Some(entry) = self.by_key.get_mut(&key) {
+            self.order.remove(&entry.used);
+            entry.used = clock;
+            self.order.insert(clock, key);
+        }
+    }
+}
+
+// hot GET responses kept in memory, least recently used ones go first once it's full
+// writes drop what they could have changed, see CACHED_ROUTES and ALSO_CHANGES
+#[derive(Debug)]
+pub struct ResponseCache{
+    on: bool,
+    capacity: usize,
+    ttl: Duration,
+    entries: Mutex<Entries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheStats{
+    pub on: bool,
+    pub entries: usize,
+    pub capacity: usize,
+    pub ttl_secs: u64,
+    pub hits: u64,
+    pub misses: u64,
+    // 0 when nothing has been asked for yet
+    pub hit_rate: f64,
+    // pushed out to make room, or too old
+    pub evictions: u64,
+    // dropped because a write could have changed them
+    pub invalidations: u64,
+}
+
+impl Default for ResponseCache{
+    fn default() -> Self {
+        ResponseCache::new(true)
+    }
+}
+
+impl ResponseCache{
+    fn new(on: bool) -> ResponseCache {
+        ResponseCache {
+            on,
+            capacity: DEFAULT_CAPACITY,
+            ttl: DEFAULT_TTL,
+            entries: Mutex::default(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            invalidations: AtomicU64::new(0),
+        }
+    }
+
+    pub fn off() -> ResponseCache {
+        ResponseCache::new(false)
+    }
+
+    // RESPONSE_CACHE=off turns it off, RESPONSE_CACHE_SIZE is how many responses it keeps and RESPONSE_CACHE_TTL how many seconds for
+    pub fn from_env() -> Result<ResponseCache, String> {
+        if std::env::var("RESPONSE_CACHE").is_ok_and(|v| matches!(v.to_lowercase().as_str(), "off" | "false" | "0")) {
+            return Ok(ResponseCache::off());
+        }
+        let mut cache = ResponseCache::default();
+        if let Ok(size) = std::env::var("RESPONSE_CACHE_SIZE") {
+            cache = cache.capacity(size.parse().map_err(|_| format!("RESPONSE_CACHE_SIZE should be a number, not {:?}", size))?);
+        }
+        if let Ok(secs) = std::env::var("RESPONSE_CACHE_TTL") {
+            cache = cache.ttl(Duration::from_secs(secs.parse().map_err(|_| format!("RESPONSE_CACHE_TTL should be a number of seconds, not {:?}", secs))?));
+        }
+        Ok(cache)
+    }
+
+    pub fn capacity(mut self, capacity: usize) -> ResponseCache {
+        self.capacity = capacity;
+        self
+    }
+
+    pub fn ttl(mut self, ttl: Duration) -> ResponseCache {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let (hits, misses) = (self.hits.load(Ordering::Relaxed), self.misses.load(Ordering::Relaxed));
+        CacheStats {
+            on: self.on,
+            entries: self.entries.lock().unwrap().by_key.len(),
+            capacity: self.capacity,
+            ttl_secs: self.ttl.as_secs(),
+            hits,
+            misses,
+            hit_rate: if hits + misses == 0 { 0.0 } else { hits as f64 / (hits + misses) as f64 },
+            evictions: self.evictions.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+        }
+    }
+
+    // the record with this ID and every list of the collection, or all of the collection for None
+    pub fn invalidate(&self, collection: &str, id: Option<u32>) {
+        let mut entries = self.entries.lock().unwrap();
+        let stale: Vec<u64> = entries.by_key.iter()
+            .filter(|(_, e)| e.collection == collection && (id.is_none() || e.id.is_none() || e.id == id))
+            .map(|(key, _)| *key)
+            .collect();
+        entries.generation += 1;
+        self.invalidations.fetch_add(stale.len() as u64, Ordering::Relaxed);
+        stale.into_iter().for_each(|key| entries.remove(key));
+    }
+
+    pub fn clear(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        self.invalidations.fetch_add(entries.by_key.len() as u64, Ordering::Relaxed);
+        *entries = Entries { generation: entries.generation + 1, ..Entries::default() };
+    }
+
+    // the cached response, or the generation to store the fresh one under
+    fn get(&self, key: u64) -> Result<(HeaderMap, Bytes), u64> {
+        let mut entries = self.entries.lock().unwrap();
+        let fresh = entries.by_key.get(&key).map(|e| e.stored_at.elapsed() < self.ttl);
+        match fresh {
+            Some(true) => {
+                entries.touch(key);
+                let entry = &entries.by_key[&key];
+                Ok((entry.headers.clone(), entry.body.clone()))
+            }
+            Some(false) => {
+                entries.remove(key);
+                self.evictions.fetch_add(1, Ordering::Relaxed);
+                Err(entries.generation)
+            }
+            None => Err(entries.generation),
+        }
+    }
+
+    fn put(&self, key: u64, generation: u64, collection: &'static str, id: Option<u32>, headers: HeaderMap, body: Bytes) {
+        let mut entries = self.entries.lock().unwrap();
+        if self.capacity == 0 || entries.generation != generation {
+            return;
+        }
+        entries.remove(key);
+        while entries.by_key.len() >= self.capacity {
+            let Some((_, oldest)) = entries.order.pop_first() else { break };
+            entries.by_key.remove(&oldest);
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+        entries.by_key.insert(key, Entry { collection, id, headers, body, stored_at: Instant::now(), used: 0 });
+        entries.touch(key);
+    }
+
+    // after a write to /<collection>[/<id>/...] went through
+    fn written(&self, path: &str) {
+        let mut segments = path.trim_start_matches('/').split('/');
+        let collection = segments.next().unwrap_or_default();
+        let Some((_, also)) = ALSO_CHANGES.iter().find(|(c, _)| *c == collection) else {
+            // trades, the market, the daycare, admin imports and so on move things between collections
+            return self.clear();
+        };
+        self.invalidate(collection, segments.next().and_then(|id| id.parse().ok()));
+        also.iter().for_each(|other| self.invalidate(other, None));
+    }
+}
+
+// the URL and whatever else changes the response, the language and who's asking
+fn key(request: &Request) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    request.uri().hash(&mut hasher);
+    for name in [header::ACCEPT_LANGUAGE.as_str(), "x-trainer-id", header::AUTHORIZATION.as_str()] {
+        request.headers().get(name).map(HeaderValue::as_bytes).hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+fn with_x_cache(mut response: Response, value: &'static str) -> Response {
+    response.headers_mut().insert("x-cache", HeaderValue::from_static(value));
+    response
+}
+
+// answers the cached reads from memory, and drops what a successful write could have changed
+pub async fn serve(State(state): State<SharedState>, request: Request, next: Next) -> Response {
+    let cache = &state.responses;
+    if !cache.on {
+        return next.run(request).await;
+    }
+    // they read like a GET but aren't answered like one, so they're neither cached nor count as writes
+    if matches!(*request.method(), Method::HEAD | Method::OPTIONS) {
+        return next.run(request).await;
+    }
+    let route = request.extensions().get::<MatchedPath>().map(|p| p.as_str().to_string()).unwrap_or_default();
+    if request.method() != Method::GET || WRITING_ROUTES.contains(&route.as_str()) {
+        let path = request.uri().path().to_string();
+        let response = next.run(request).await;
+        // the web forms answer a write with a redirect
+        if response.status().is_success() || response.status().is_redirection() {
+            cache.written(&path);
+        }
+        return response;
+    }
+
+    let Some(&(route, collection)) = CACHED_ROUTES.iter().find(|(r, _)| *r == route) else {
+        return next.run(request).await;
+    };
+    // the {id} is the last segment of the ones that have one
+    let id = route.ends_with("{id}").then(|| request.uri().path().rsplit('/').next().and_then(|id| id.parse().ok())).flatten();
+    let key = key(&request);
+    let generation = match cache.get(key) {
+        Ok((headers, body)) => {
+            cache.hits.fetch_add(1, Ordering::Relaxed);
+            let mut response = Response::new(Body::from(body));
+            *response.headers_mut() = headers;
+            return with_x_cache(response, "hit");
+        }
+        Err(generation) => generation,
+    };
+    cache.misses.fetch_add(1, Ordering::Relaxed);
+
+    let response = next.run(request).await;
+    let small = response.body().size_hint().exact().is_some_and(|len| len as usize <= MAX_BODY_SIZE);
+    if response.status() != StatusCode::OK || !small {
+        return with_x_cache(response, "miss");
+    }
+    let (parts, body) = response.into_parts();
+    let Ok(bytes) = to_bytes(body, MAX_BODY_SIZE).await else {
+        return Response::from_parts(parts, Body::empty());
+    };
+    cache.put(key, generation, collection, id, parts.headers.clone(), bytes.clone());
+    with_x_cache(Response::from_parts(parts, Body::from(bytes)), "miss")
+}
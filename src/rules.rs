@@ -0,0 +1,123 @@
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::error::ApiError;
+use crate::models::Pokemon;
+use crate::species;
+
+// which Pokemon a rule is about, everything it lists has to match and an empty one matches everyone
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Matcher{
+    #[serde(default)]
+    pub species: Vec<String>,
+    // either type counts
+    #[serde(default)]
+    pub types: Vec<String>,
+    #[serde(default)]
+    pub legendary: Option<bool>,
+}
+
+impl Matcher{
+    pub fn matches(&self, pokemon: &Pokemon) -> bool {
+        let has_type = |t: &String| {
+            pokemon.poke_type.eq_ignore_ascii_case(t) || pokemon.secondary_type.as_ref().is_some_and(|s| s.eq_ignore_ascii_case(t))
+        };
+        (self.species.is_empty() || self.species.iter().any(|s| s.eq_ignore_ascii_case(&pokemon.species)))
+            && (self.types.is_empty() || self.types.iter().any(has_type))
+            && self.legendary.is_none_or(|legendary| species::is_legendary(&pokemon.species) == legendary)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Check{
+    // matching Pokemon have to be in this level range, e.g. little cup
+    Level{
+        min: Option<u32>,
+        max: Option<u32>,
+    },
+    // matching Pokemon can't be created at all
+    Banned,
+    // at most this many matching Pokemon on one team
+    PerTeam{
+        max: usize,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Rule{
+    // what clients are told when they break it, e.g. "no more than 2 legendaries per team"
+    pub name: String,
+    #[serde(default)]
+    pub matching: Matcher,
+    #[serde(flatten)]
+    pub check: Check,
+}
+
+// extra validation on top of the game's own, from the JSON file named by RULES_FILE
+// checked when a Pokemon is created or updated and when a trainer's team is set
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Rules{
+    pub rules: Vec<Rule>,
+}
+
+impl Rules{
+    // none unless RULES_FILE is set
+    pub fn from_env() -> Result<Rules, String> {
+        let Ok(path) = std::env::var("RULES_FILE") else {
+            return Ok(Rules::default());
+        };
+        let text = std::fs::read_to_string(&path).map_err(|e| format!("can't read {}: {}", path, e))?;
+        let rules: Rules = serde_json::from_str(&text).map_err(|e| format!("{} isn't a valid rules file: {}", path, e))?;
+        rules.validate().map_err(|e| format!("{}: {}", path, e))?;
+        Ok(rules)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for rule in &self.rules {
+            if let Check::Level { min: Some(min), max: Some(max) } = rule.check {
+                if min > max {
+                    return Err(format!("rule {:?} has a min level above its max", rule.name));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // every rule about a single Pokemon, the first one it breaks is the error
+    pub fn check_pokemon(&self, pokemon: &Pokemon) -> Result<(), ApiError> {
+        for rule in self.rules.iter().filter(|r| r.matching.matches(pokemon)) {
+            let broken = match rule.check {
+                Check::Level { min, max } => {
+                    let too_low = min.filter(|min| pokemon.level < *min).map(|min| format!("{} is below level {}", pokemon.name, min));
+                    let too_high = max.filter(|max| pokemon.level > *max).map(|max| format!("{} is above level {}", pokemon.name, max));
+                    too_low.or(too_high)
+                }
+                Check::Banned => Some(format!("{} isn't allowed", pokemon.name)),
+                Check::PerTeam { .. } => None,
+            };
+            if let Some(reason) = broken {
+                return Err(broke(rule, reason));
+            }
+        }
+        Ok(())
+    }
+
+    // the per-team rules over a whole team
+    pub fn check_team(&self, team: &[&Pokemon]) -> Result<(), ApiError> {
+        for rule in &self.rules {
+            let Check::PerTeam { max } = rule.check else { continue };
+            let count = team.iter().filter(|p| rule.matching.matches(p)).count();
+            if count > max {
+                return Err(broke(rule, format!("the team has {} that count, the most is {}", count, max)));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn broke(rule: &Rule, reason: String) -> ApiError {
+    ApiError::unprocessable(format!("breaks the rule \"{}\": {}", rule.name, reason))
+        .with_details(json!({ "rule": rule.name }))
+}
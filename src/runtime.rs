@@ -0,0 +1,148 @@
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::runtime::{Builder, Handle, Runtime, RuntimeMetrics};
+
+pub const DEFAULT_WINDOW: Duration = Duration::from_millis(250);
+pub const MAX_WINDOW: Duration = Duration::from_secs(5);
+
+// a worker busy this much of the window without parking once is taken to be stuck
+const BLOCKED_BUSY_RATIO: f64 = 0.9;
+
+// how the binary's runtime is built, None is tokio's own default
+#[derive(Debug, Clone, Default)]
+pub struct RuntimeConfig{
+    // one per CPU by default
+    pub worker_threads: Option<usize>,
+    // spawn_blocking's pool, 512 by default
+    pub max_blocking_threads: Option<usize>,
+    // tasks a worker runs between checks for IO and timers, 61 by default
+    pub event_interval: Option<u32>,
+}
+
+impl RuntimeConfig{
+    // WORKER_THREADS, MAX_BLOCKING_THREADS and EVENT_INTERVAL
+    pub fn from_env() -> Result<RuntimeConfig, String> {
+        // tokio panics on a zero rather than saying so
+        fn number<T: std::str::FromStr + PartialEq + Default>(key: &str) -> Result<Option<T>, String> {
+            let Ok(value) = std::env::var(key) else { return Ok(None) };
+            match value.parse() {
+                Ok(n) if n != T::default() => Ok(Some(n)),
+                _ => Err(format!("{} should be a number over 0, not {:?}", key, value)),
+            }
+        }
+        Ok(RuntimeConfig {
+            worker_threads: number("WORKER_THREADS")?,
+            max_blocking_threads: number("MAX_BLOCKING_THREADS")?,
+            event_interval: number("EVENT_INTERVAL")?,
+        })
+    }
+
+    // the multi-threaded runtime #[tokio::main] would build, with these on top
+    pub fn build(&self) -> std::io::Result<Runtime> {
+        let mut builder = Builder::new_multi_thread();
+        builder.enable_all();
+        if let Some(workers) = self.worker_threads {
+            builder.worker_threads(workers);
+        }
+        if let Some(max) = self.max_blocking_threads {
+            builder.max_blocking_threads(max);
+        }
+        if let Some(interval) = self.event_interval {
+            builder.event_interval(interval);
+        }
+        builder.build()
+    }
+}
+
+// what tokio's runtime is up to, to see why the server stalls under load
+#[derive(Debug, Clone, Serialize)]
+pub struct RuntimeStats{
+    pub workers: usize,
+    pub alive_tasks: usize,
+    // spawned but not picked up by a worker yet, climbing means the workers can't keep up
+    pub global_queue_depth: usize,
+    // how long the workers were watched for
+    pub window_ms: u64,
+    // busy the whole window without parking once, most likely stuck in something that blocks
+    pub blocked_workers: usize,
+    pub worker_stats: Vec<WorkerStats>,
+    // only when built with RUSTFLAGS="--cfg tokio_unstable"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocking_pool: Option<BlockingPool>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkerStats{
+    pub worker: usize,
+    // how much of the window it was running tasks, 0 to 1
+    pub busy_ratio: f64,
+    // how many times it ran out of work and parked in the window
+    pub parks: u64,
+    pub blocked: bool,
+}
+
+// spawn_blocking's threads, where the battles and imports run
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockingPool{
+    pub threads: usize,
+    pub idle_threads: usize,
+    pub queue_depth: usize,
+}
+
+fn workers(metrics: &RuntimeMetrics) -> Vec<(Duration, u64)> {
+    (0..metrics.num_workers()).map(|w| (metrics.worker_total_busy_duration(w), metrics.worker_park_count(w))).collect()
+}
+
+#[cfg(tokio_unstable)]
+fn blocking_pool(metrics: &RuntimeMetrics) -> Option<BlockingPool> {
+    Some(BlockingPool {
+        threads: metrics.num_blocking_threads(),
+        idle_threads: metrics.num_idle_blocking_threads(),
+        queue_depth: metrics.blocking_queue_depth(),
+    })
+}
+
+#[cfg(not(tokio_unstable))]
+fn blocking_pool(_metrics: &RuntimeMetrics) -> Option<BlockingPool> {
+    None
+}
+
+// watches the workers for a while, the answer itself is late when they're all stuck
+pub async fn sample(window: Duration) -> RuntimeStats {
+    let metrics = Handle::current().metrics();
+    let before = workers(&metrics);
+    let started = tokio::time::Instant::now();
+    tokio::time::sleep(window).await;
+    // the time that really went by, a stalled runtime wakes this up late
+    let window = started.elapsed();
+    let after = workers(&metrics);
+
+    let worker_stats: Vec<WorkerStats> = before.iter().zip(&after).enumerate()
+        .map(|(worker, ((busy_before, parks_before), (busy_after, parks_after)))| {
+            let busy_ratio = ((*busy_after - *busy_before).as_secs_f64() / window.as_secs_f64()).min(1.0);
+            let parks = parks_after - parks_before;
+            WorkerStats { worker, busy_ratio, parks, blocked: parks == 0 && busy_ratio >= BLOCKED_BUSY_RATIO }
+        })
+        .collect();
+    RuntimeStats {
+        workers: metrics.num_workers(),
+        alive_tasks: metrics.num_alive_tasks(),
+        global_queue_depth: metrics.global_queue_depth(),
+        window_ms: window.as_millis() as u64,
+        blocked_workers: worker_stats.iter().filter(|w| w.blocked).count(),
+        worker_stats,
+        blocking_pool: blocking_pool(&metrics),
+    }
+}
+
+// tokio-console on 127.0.0.1:6669, or TOKIO_CONSOLE_BIND, only built with --features tokio-console
+#[cfg(feature = "tokio-console")]
+pub fn start_console() {
+    // it can't see any tasks without it, and says so by panicking
+    if cfg!(not(tokio_unstable)) {
+        eprintln!("tokio-console: off, it needs a build with RUSTFLAGS=\"--cfg tokio_unstable\"");
+        return;
+    }
+    console_subscriber::init();
+}
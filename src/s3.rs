@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+use crate::models::unix_time;
+use crate::secrets;
+use crate::sharing::hex;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+// somewhere S3-compatible to ship snapshots to: AWS, MinIO, R2, Backblaze...
+// path-style URLs (<endpoint>/<bucket>/<key>) since every provider takes those
+#[derive(Debug, Clone)]
+pub struct S3Sink{
+    client: reqwest::Client,
+    endpoint: String,
+    bucket: String,
+    region: String,
+    access_key_id: String,
+    secret_access_key: String,
+    // put in front of every key, e.g. "pokemon/" -> pokemon/snapshot-1791953720.json
+    prefix: String,
+}
+
+impl S3Sink{
+    // None unless S3_ENDPOINT, S3_BUCKET, S3_ACCESS_KEY_ID and S3_SECRET_ACCESS_KEY are all set
+    // S3_REGION defaults to us-east-1 and S3_PREFIX to snapshots/
+    pub fn from_env() -> Option<S3Sink> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let secret = |name: &str| secrets::var(name).ok().filter(|v| !v.is_empty());
+        Some(S3Sink {
+            client: reqwest::Client::builder().timeout(TIMEOUT).build().ok()?,
+            endpoint: var("S3_ENDPOINT")?.trim_end_matches('/').to_string(),
+            bucket: var("S3_BUCKET")?,
+            region: var("S3_REGION").unwrap_or_else(|| "us-east-1".to_string()),
+            access_key_id: secret("S3_ACCESS_KEY_ID")?,
+            secret_access_key: secret("S3_SECRET_ACCESS_KEY")?,
+            prefix: var("S3_PREFIX").unwrap_or_else(|| "snapshots/".to_string()),
+        })
+    }
+
+    // where `file` ends up, e.g. s3://backups/snapshots/snapshot-1791953720.json
+    pub fn location(&self, file: &str) -> String {
+        format!("s3://{}/{}{}", self.bucket, self.prefix, file)
+    }
+
+    pub async fn upload(&self, file: &str, body: Vec<u8>) -> Result<String, String> {
+        let path = format!("/{}/{}{}", self.bucket, self.prefix, file);
+        let url = reqwest::Url::parse(&format!("{}{}", self.endpoint, uri_encode(&path)))
+            .map_err(|e| format!("S3_ENDPOINT isn't a URL: {}", e))?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err("S3_ENDPOINT has no host".to_string()),
+        };
+
+        let payload_hash = hex(&Sha256::digest(&body));
+        let (amz_date, date) = timestamp(unix_time());
+        let authorization = self.authorization(url.path(), &host, &payload_hash, &amz_date, &date);
+        let response = self.client.put(url)
+            .header("host", &host)
+            .header("x-amz-content-sha256", &payload_hash)
+            .header("x-amz-date", &amz_date)
+            .header("authorization", authorization)
+            .header("content-type", "application/json")
+            .body(body)
+            .send().await
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let detail = response.text().await.unwrap_or_default();
+            return Err(format!("S3 said {}: {}", status, detail.trim()));
+        }
+        Ok(self.location(file))
+    }
+
+    // AWS Signature Version 4 for a PUT with no query string
+    fn authorization(&self, path: &str, host: &str, payload_hash: &str, amz_date: &str, date: &str) -> String {
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "PUT\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            path, host, payload_hash, amz_date, signed_headers, payload_hash,
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date, scope, hex(&Sha256::digest(canonical_request.as_bytes())),
+        );
+
+        let mut key = format!("AWS4{}", self.secret_access_key).into_bytes();
+        for part in [date, &self.region, "s3", "aws4_request"] {
+            key = hmac(&key, part);
+        }
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key_id, scope, signed_headers, hex(&hmac(&key, &string_to_sign)),
+        )
+    }
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("hmac takes keys of any size");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+// everything but unreserved characters and slashes gets %-encoded, the way SigV4 wants it
+fn uri_encode(path: &str) -> String {
+    path.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+// ("20261014T045200Z", "20261014") in UTC
+fn timestamp(unix: u64) -> (String, String) {
+    let (days, secs) = (unix / 86400, unix % 86400);
+    // days since 1970-01-01 to a calendar date, from Howard Hinnant's civil_from_days
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    let date = format!("{:04}{:02}{:02}", year, month, day);
+    (format!("{}T{:02}{:02}{:02}Z", date, secs / 3600, secs % 3600 / 60, secs % 60), date)
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use futures_util::future::BoxFuture;
+use serde::Serialize;
+use tokio::time::MissedTickBehavior;
+
+use crate::state::SharedState;
+use crate::{backups, breeding, daycare, friendship, leaderboards, matchmaking, memory, notify, status, trash};
+
+// something the server does on its own every so often
+struct Task{
+    name: &'static str,
+    // how often when TASKS doesn't say, zero for off
+    every: fn(&SharedState) -> Duration,
+    // a run as soon as the server starts, on top of one every interval after
+    at_startup: bool,
+    run: fn(SharedState) -> BoxFuture<'static, Result<(), String>>,
+}
+
+const TASKS: &[Task] = &[
+    Task { name: "status", every: |_| status::TICK_INTERVAL, at_startup: false, run: |state| Box::pin(async move { status::tick(&state); Ok(()) }) },
+    Task { name: "matchmaking", every: |_| matchmaking::MATCH_INTERVAL, at_startup: true, run: |state| Box::pin(async move { matchmaking::run_matchmaking(&state, state.time.now()); Ok(()) }) },
+    Task { name: "eggs", every: |_| breeding::TICK_INTERVAL, at_startup: false, run: |state| Box::pin(async move { breeding::incubate(&state); Ok(()) }) },
+    Task { name: "daycare", every: |_| daycare::TICK_INTERVAL, at_startup: false, run: |state| Box::pin(async move { daycare::tick(&state); Ok(()) }) },
+    Task { name: "friendship", every: |_| friendship::TICK_INTERVAL, at_startup: false, run: |state| Box::pin(async move { friendship::tick(&state); Ok(()) }) },
+    // anything restored from a snapshot that's already past its time goes straight away
+    Task { name: "trash", every: |_| trash::PURGE_INTERVAL, at_startup: true, run: |state| Box::pin(async move { trash::purge_older_than(&state, trash::ttl_from_env()); Ok(()) }) },
+    // SNAPSHOT_INTERVAL_SECS still works, it's the default here
+    Task { name: "snapshots", every: |state| state.backups.interval(), at_startup: false, run: |state| Box::pin(async move { backups::scheduled_snapshot(&state).await }) },
+    // the boards start out built
+    Task { name: "leaderboards", every: |_| leaderboards::TICK_INTERVAL, at_startup: false, run: |state| Box::pin(async move { leaderboards::rebuild(&state); Ok(()) }) },
+    // an error while it's over MEMORY_WARN_MB or MEMORY_WARN_RECORDS
+    Task { name: "memory", every: |_| memory::CHECK_INTERVAL, at_startup: false, run: |state| Box::pin(async move { memory::check(&state) }) },
+    Task { name: "webhook-retry", every: |_| notify::RETRY_INTERVAL, at_startup: false, run: |state| Box::pin(async move { notify::retry_failed(&state).await }) },
+];
+
+// how a task has been getting on, for GET /admin/tasks
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskStatus{
+    pub name: &'static str,
+    pub enabled: bool,
+    // None when it's off
+    pub interval_secs: Option<u64>,
+    pub running: bool,
+    pub runs: u64,
+    pub failures: u64,
+    // unix seconds
+    pub last_run: Option<u64>,
+    pub last_duration_ms: Option<u64>,
+    // from the last run, None once one goes through
+    pub last_error: Option<String>,
+    pub next_run: Option<u64>,
+}
+
+// runs the tasks above, each on its own interval
+#[derive(Debug, Default)]
+pub struct Scheduler{
+    // task -> how often, None for off, instead of the task's own default
+    intervals: HashMap<&'static str, Option<Duration>>,
+    statuses: Mutex<Vec<TaskStatus>>,
+}
+
+impl Scheduler{
+    // TASKS picks intervals in seconds or turns tasks off, e.g. "daycare=10,snapshots=off"
+    pub fn from_env() -> Result<Scheduler, String> {
+        let mut scheduler = Scheduler::default();
+        let Ok(tasks) = std::env::var("TASKS") else { return Ok(scheduler) };
+        for pair in tasks.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (name, every) = pair.split_once('=').ok_or_else(|| format!("TASKS wants task=seconds or task=off, not {:?}", pair))?;
+            let every = match every.trim() {
+                "off" | "false" | "0" => None,
+                secs => Some(Duration::from_secs(secs.parse().map_err(|_| format!("TASKS wants a number of seconds for {}, not {:?}", name, secs))?)),
+            };
+            scheduler = scheduler.task(name.trim(), every)?;
+        }
+        Ok(scheduler)
+    }
+
+    // None turns it off
+    pub fn task(mut self, name: &str, every: Option<Duration>) -> Result<Scheduler, String> {
+        let task = TASKS.iter().find(|t| t.name == name).ok_or_else(|| {
+            format!("unknown task {:?}, it's one of {}", name, TASKS.iter().map(|t| t.name).collect::<Vec<_>>().join(", "))
+        })?;
+        self.intervals.insert(task.name, every.filter(|every| !every.is_zero()));
+        Ok(self)
+    }
+
+    pub fn statuses(&self) -> Vec<TaskStatus> {
+        self.statuses.lock().unwrap().clone()
+    }
+
+    fn update(&self, name: &str, change: impl FnOnce(&mut TaskStatus)) {
+        if let Some(status) = self.statuses.lock().unwrap().iter_mut().find(|s| s.name == name) {
+            change(status);
+        }
+    }
+}
+
+// starts every task that isn't off, needs a tokio runtime
+pub fn spawn(state: &SharedState) {
+    for task in TASKS {
+        let every = state.scheduler.intervals.get(task.name).copied().unwrap_or_else(|| Some((task.every)(state)).filter(|e| !e.is_zero()));
+        let first = if task.at_startup { Duration::ZERO } else { every.unwrap_or_default() };
+        state.scheduler.statuses.lock().unwrap().push(TaskStatus {
+            name: task.name,
+            enabled: every.is_some(),
+            interval_secs: every.map(|e| e.as_secs()),
+            running: false,
+            runs: 0,
+            failures: 0,
+            last_run: None,
+            last_duration_ms: None,
+            last_error: None,
+            next_run: every.map(|_| state.time.now() + first.as_secs()),
+        });
+        let Some(every) = every else { continue };
+
+        let state = state.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + first, every);
+            // a run that overruns pushes the next one back, rather than a few going off at once to catch up
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                let started = Instant::now();
+                state.scheduler.update(task.name, |s| {
+                    s.running = true;
+                    s.last_run = Some(state.time.now());
+                });
+                // on its own task, so one that panics is a failure rather than the end of the loop
+                let outcome = tokio::spawn((task.run)(state.clone())).await
+                    .unwrap_or_else(|e| Err(format!("it stopped halfway: {}", e)));
+                state.scheduler.update(task.name, |s| {
+                    s.running = false;
+                    s.runs += 1;
+                    s.last_duration_ms = Some(started.elapsed().as_millis() as u64);
+                    s.next_run = Some(state.time.now() + every.as_secs());
+                    match outcome {
+                        Ok(()) => s.last_error = None,
+                        Err(e) => {
+                            s.failures += 1;
+                            s.last_error = Some(e);
+                        }
+                    }
+                });
+            }
+        });
+    }
+}
@@ -0,0 +1,161 @@
+use std::path::Path;
+
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Scope, AST};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::models::Pokemon;
+use crate::moves::MoveData;
+
+// so a runaway loop in a script can't hang a request
+const MAX_OPERATIONS: u64 = 1_000_000;
+const MAX_CALL_LEVELS: usize = 32;
+
+// a .rhai file from SCRIPTS_DIR, it defines whichever hooks it wants:
+//   fn before_create(pokemon)     return the Pokemon changed, or throw to refuse it
+//   fn damage(hit)                return the damage a move does instead of the usual
+//   fn after_battle_turn(turn)    return an array of extra lines for the battle log
+// each one with a single argument, hooks with any other number of arguments are ignored
+#[derive(Debug)]
+struct Script{
+    file: String,
+    ast: AST,
+}
+
+#[derive(Debug)]
+pub struct Scripts{
+    engine: Engine,
+    // run in file name order, each one gets what the one before returned
+    scripts: Vec<Script>,
+}
+
+impl Default for Scripts{
+    fn default() -> Self {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.set_max_call_levels(MAX_CALL_LEVELS);
+        Scripts { engine, scripts: Vec::new() }
+    }
+}
+
+impl Scripts{
+    // every *.rhai file in SCRIPTS_DIR, none if it isn't set
+    // a script that doesn't compile stops the server from starting
+    pub fn from_env() -> Result<Scripts, String> {
+        match std::env::var("SCRIPTS_DIR") {
+            Ok(dir) => Scripts::from_dir(Path::new(&dir)).map_err(|e| format!("SCRIPTS_DIR: {}", e)),
+            Err(_) => Ok(Scripts::default()),
+        }
+    }
+
+    pub fn from_dir(dir: &Path) -> Result<Scripts, String> {
+        let mut scripts = Scripts::default();
+        let entries = std::fs::read_dir(dir).map_err(|e| format!("can't read {}: {}", dir.display(), e))?;
+        let mut paths: Vec<_> = entries.filter_map(|e| e.ok()).map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "rhai"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            scripts.load(&path)?;
+        }
+        Ok(scripts)
+    }
+
+    fn load(&mut self, path: &Path) -> Result<(), String> {
+        let file = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let ast = self.engine.compile_file(path.to_path_buf()).map_err(|e| format!("{}: {}", file, e))?;
+        self.scripts.push(Script { file, ast });
+        Ok(())
+    }
+
+    pub fn files(&self) -> Vec<&str> {
+        self.scripts.iter().map(|s| s.file.as_str()).collect()
+    }
+
+    fn with_hook<'a>(&'a self, hook: &'a str) -> impl Iterator<Item = &'a Script> + 'a {
+        self.scripts.iter().filter(move |s| s.ast.iter_functions().any(|f| f.name == hook && f.params.len() == 1))
+    }
+
+    fn call(&self, script: &Script, hook: &str, arg: &impl Serialize) -> Result<Dynamic, HookError> {
+        let arg = rhai::serde::to_dynamic(arg).map_err(|e| HookError::failed(script, e))?;
+        self.engine.call_fn(&mut Scope::new(), &script.ast, hook, (arg,)).map_err(|e| HookError::from_rhai(script, *e))
+    }
+
+    // scripts can change anything about a new Pokemon, or throw to refuse it
+    // each one gets what the one before returned, returning nothing leaves it as it was
+    pub fn before_create(&self, mut pokemon: Pokemon) -> Result<Pokemon, HookError> {
+        for script in self.with_hook("before_create") {
+            let out = self.call(script, "before_create", &pokemon)?;
+            if !out.is_unit() {
+                pokemon = rhai::serde::from_dynamic(&out).map_err(|e| HookError::failed(script, e))?;
+            }
+        }
+        Ok(pokemon)
+    }
+
+    // the damage a hit does, `damage` is what the usual formula came up with
+    // a broken script is logged and skipped, a battle shouldn't die halfway
+    pub fn damage(&self, attacker: &Pokemon, defender: &Pokemon, mv: &MoveData, effectiveness: f64, mut damage: u32) -> u32 {
+        for script in self.with_hook("damage") {
+            let hit = json!({
+                "attacker": attacker,
+                "defender": defender,
+                "move": mv.name,
+                "move_type": mv.poke_type,
+                "category": format!("{:?}", mv.category).to_lowercase(),
+                "power": mv.power,
+                "effectiveness": effectiveness,
+                "damage": damage,
+            });
+            match self.call(script, "damage", &hit) {
+                Ok(out) if out.is_int() => damage = out.as_int().unwrap_or_default().max(0) as u32,
+                Ok(out) if out.is_float() => damage = out.as_float().unwrap_or_default().max(0.0) as u32,
+                Ok(out) if out.is_unit() => {}
+                Ok(_) => eprintln!("damage hook in {}: it has to return a number", script.file),
+                Err(e) => eprintln!("damage hook: {}", e.message()),
+            }
+        }
+        damage
+    }
+
+    // extra lines for the log of the turn that just ended
+    pub fn after_battle_turn(&self, turn: u32, events: &[String], actives: [&Pokemon; 2]) -> Vec<String> {
+        let mut extra = Vec::new();
+        for script in self.with_hook("after_battle_turn") {
+            let info = json!({ "turn": turn, "events": events, "side_a": actives[0], "side_b": actives[1] });
+            match self.call(script, "after_battle_turn", &info) {
+                Ok(lines) if lines.is_array() => extra.extend(lines.cast::<Array>().into_iter().map(|line| line.to_string())),
+                Ok(_) => {}
+                Err(e) => eprintln!("after_battle_turn hook: {}", e.message()),
+            }
+        }
+        extra
+    }
+}
+
+#[derive(Debug)]
+pub enum HookError{
+    // the script threw on purpose, e.g. `throw "no shinies allowed"`
+    Refused(String),
+    // anything else that went wrong in a script
+    Failed(String),
+}
+
+impl HookError{
+    fn failed(script: &Script, e: impl std::fmt::Display) -> HookError {
+        HookError::Failed(format!("{}: {}", script.file, e))
+    }
+
+    fn from_rhai(script: &Script, e: EvalAltResult) -> HookError {
+        match e {
+            EvalAltResult::ErrorRuntime(value, _) => HookError::Refused(value.to_string()),
+            e => HookError::failed(script, e),
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            HookError::Refused(message) | HookError::Failed(message) => message,
+        }
+    }
+}
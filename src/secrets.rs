@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::env::VarError;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use serde_json::Value;
+
+// the variables that can hold something secret, each one can also come from:
+// - NAME_FILE, the path of a file holding it, like Docker and Kubernetes secrets
+// - a Vault or SOPS store, loaded once at startup by load()
+pub const SECRETS: &[&str] = &[
+    "ADMIN_TOKEN",
+    "API_KEYS",
+    "NOTIFY_WEBHOOK_URL",
+    "REDIS_URL",
+    "S3_ACCESS_KEY_ID",
+    "S3_SECRET_ACCESS_KEY",
+    "SENTRY_DSN",
+    "SESSION_KEYS",
+    "SHARE_SECRET",
+    "SMTP_URL",
+    "SNAPSHOT_KEYS",
+    "TELEGRAM_BOT_TOKEN",
+];
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+// what load() got from Vault or SOPS, empty until it's run
+static STORE: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+// NAME, NAME_FILE, then the store, in that order
+// with `env` standing in for the environment, so it can be tried without touching the real one
+pub fn lookup(name: &str, env: impl Fn(&str) -> Option<String>, store: &HashMap<String, String>) -> Result<Option<String>, String> {
+    let file_var = format!("{}_FILE", name);
+    match (env(name), env(&file_var)) {
+        (Some(_), Some(_)) => Err(format!("set {} or {}, not both", name, file_var)),
+        (Some(value), None) => Ok(Some(value)),
+        (None, Some(path)) => {
+            let value = std::fs::read_to_string(&path).map_err(|e| format!("{} {}: {}", file_var, path, e))?;
+            // editors and `echo` leave a newline on the end, it's never part of the secret
+            let value = value.trim_end_matches(['\r', '\n']);
+            if value.is_empty() {
+                return Err(format!("{} {} is empty", file_var, path));
+            }
+            Ok(Some(value.to_string()))
+        }
+        (None, None) => Ok(store.get(name).cloned()),
+    }
+}
+
+fn env(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+// in place of std::env::var for anything in SECRETS
+// a file that can't be read counts as not set, check() has already stopped the server over it
+pub fn var(name: &str) -> Result<String, VarError> {
+    let store = STORE.get_or_init(HashMap::default);
+    lookup(name, env, store).ok().flatten().ok_or(VarError::NotPresent)
+}
+
+// every NAME_FILE can be read and none of them clash with a NAME, before anything's configured from them
+pub fn check() -> Result<(), String> {
+    let store = STORE.get_or_init(HashMap::default);
+    SECRETS.iter().try_for_each(|name| lookup(name, env, store).map(|_| ()))
+}
+
+// a flat object of strings, what a KV secret in Vault or a decrypted SOPS file holds
+pub fn store(value: &Value) -> Result<HashMap<String, String>, String> {
+    let object = value.as_object().ok_or("secrets are an object of names and values")?;
+    object.iter()
+        .map(|(name, value)| match value {
+            Value::String(s) => Ok((name.clone(), s.clone())),
+            _ => Err(format!("{} isn't a string", name)),
+        })
+        .collect()
+}
+
+// a KV secret, e.g. path "secret/data/pokemon" for version 2 of the engine or "secret/pokemon" for version 1
+pub async fn vault(addr: &str, token: &str, path: &str) -> Result<HashMap<String, String>, String> {
+    let client = reqwest::Client::builder().timeout(TIMEOUT).build().map_err(|e| e.to_string())?;
+    let url = format!("{}/v1/{}", addr.trim_end_matches('/'), path.trim_start_matches('/'));
+    let response = client.get(&url).header("X-Vault-Token", token).send().await
+        .map_err(|e| format!("couldn't reach Vault at {}: {}", addr, e))?;
+    if !response.status().is_success() {
+        return Err(format!("Vault said {} for {}", response.status(), path));
+    }
+    let body: Value = response.json().await.map_err(|e| format!("Vault sent something that isn't JSON: {}", e))?;
+    // version 2 wraps the secret in another data along with its metadata
+    let secret = body["data"].get("data").filter(|d| d.is_object()).unwrap_or(&body["data"]);
+    store(secret).map_err(|e| format!("Vault secret {}: {}", path, e))
+}
+
+// decrypted by the sops binary, so whatever keys it's set up with (age, PGP, KMS) work here too
+pub async fn sops(file: &str) -> Result<HashMap<String, String>, String> {
+    let output = tokio::process::Command::new("sops").args(["--decrypt", "--output-type", "json", file]).output().await
+        .map_err(|e| format!("couldn't run sops: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("sops couldn't decrypt {}: {}", file, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    let value = serde_json::from_slice(&output.stdout).map_err(|e| format!("sops sent something that isn't JSON: {}", e))?;
+    store(&value).map_err(|e| format!("{}: {}", file, e))
+}
+
+// VAULT_ADDR, VAULT_TOKEN and VAULT_SECRET_PATH, or SOPS_FILE, for anything in SECRETS that isn't set some other way
+// has to run before anything reads a secret, and only the first run counts
+// the names it loaded, never the values
+pub async fn load() -> Result<Vec<String>, String> {
+    let vault_addr = env("VAULT_ADDR").filter(|a| !a.is_empty());
+    let sops_file = env("SOPS_FILE").filter(|f| !f.is_empty());
+    let loaded = match (vault_addr, sops_file) {
+        (Some(_), Some(_)) => return Err("set VAULT_ADDR or SOPS_FILE, not both".to_string()),
+        (Some(addr), None) => {
+            // the token is a secret too, so VAULT_TOKEN_FILE works for it
+            let token = lookup("VAULT_TOKEN", env, &HashMap::new())?.ok_or("VAULT_ADDR needs VAULT_TOKEN")?;
+            let path = env("VAULT_SECRET_PATH").ok_or("VAULT_ADDR needs VAULT_SECRET_PATH")?;
+            vault(&addr, &token, &path).await?
+        }
+        (None, Some(file)) => sops(&file).await?,
+        (None, None) => HashMap::new(),
+    };
+    let mut names: Vec<String> = loaded.keys().filter(|n| SECRETS.contains(&n.as_str())).cloned().collect();
+    names.sort();
+    if STORE.set(loaded).is_err() {
+        return Err("secrets were read before they were loaded".to_string());
+    }
+    Ok(names)
+}
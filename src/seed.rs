@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Capture, Pokemon, Trainer};
+use crate::regions::Region;
+use crate::state::SharedState;
+
+// every seeded Pokemon gets this tag, it's how a second run knows what's already there
+pub const TAG: &str = "seed";
+
+// what GET /pokemon of a seeded server starts out with, see data/seed.json
+const DATASET: &str = include_str!("../data/seed.json");
+
+const DEFAULT_LEVEL: u32 = 5;
+
+#[derive(Debug, Deserialize)]
+struct Dataset{
+    // one of each, owned by nobody
+    species: Vec<SeedPokemon>,
+    // demo trainers with their teams
+    trainers: Vec<SeedTrainer>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SeedPokemon{
+    name: String,
+    poke_type: String,
+    #[serde(default)]
+    secondary_type: Option<String>,
+    #[serde(default = "default_level")]
+    level: u32,
+}
+
+fn default_level() -> u32 {
+    DEFAULT_LEVEL
+}
+
+#[derive(Debug, Deserialize)]
+struct SeedTrainer{
+    name: String,
+    team: Vec<SeedPokemon>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SeedReport{
+    pub pokemon_created: usize,
+    // already there from an earlier seed
+    pub pokemon_skipped: usize,
+    pub trainers_created: usize,
+    pub trainers_skipped: usize,
+}
+
+impl SeedReport{
+    pub fn created_anything(&self) -> bool {
+        self.pokemon_created + self.trainers_created > 0
+    }
+}
+
+fn dataset() -> Dataset {
+    serde_json::from_str(DATASET).expect("data/seed.json is valid")
+}
+
+fn seeded(id: u32, entry: SeedPokemon, capture: Capture) -> Pokemon {
+    let mut pokemon = Pokemon::new(id, entry.name, entry.poke_type, entry.secondary_type, entry.level);
+    pokemon.generation = Some(Region::Kanto.generation());
+    pokemon.region = Some(Region::Kanto);
+    pokemon.tags = vec![TAG.to_string()];
+    pokemon.capture = Capture { location: Some("seed data".to_string()), ..capture };
+    pokemon
+}
+
+// safe to run any number of times: species that already have a seeded Pokemon and
+// trainers whose name is taken are skipped, nothing that's there gets touched
+pub fn load(state: &SharedState) -> SeedReport {
+    let dataset = dataset();
+    let mut report = SeedReport::default();
+    let mut team = state.team.lock().unwrap();
+    let mut trainers = state.trainers.lock().unwrap();
+
+    for entry in dataset.species {
+        let exists = team.iter().any(|p| {
+            p.capture.original_trainer.is_none() && p.tags.iter().any(|t| t == TAG) && p.species.eq_ignore_ascii_case(&entry.name)
+        });
+        if exists {
+            report.pokemon_skipped += 1;
+            continue;
+        }
+        let id = team.last().map_or(1, |p| p.id + 1);
+        team.push(seeded(id, entry, Capture::now().at(state.time.now())));
+        report.pokemon_created += 1;
+    }
+
+    for entry in dataset.trainers {
+        if trainers.iter().any(|t| t.name.eq_ignore_ascii_case(&entry.name)) {
+            report.trainers_skipped += 1;
+            report.pokemon_skipped += entry.team.len();
+            continue;
+        }
+        let mut trainer = Trainer::new(trainers.last().map_or(1, |t| t.id + 1), entry.name, Vec::new());
+        for member in entry.team {
+            let id = team.last().map_or(1, |p| p.id + 1);
+            team.push(seeded(id, member, Capture::by(&trainer).at(state.time.now())));
+            trainer.receive(id);
+            report.pokemon_created += 1;
+        }
+        trainers.push(trainer);
+        report.trainers_created += 1;
+    }
+    report
+}
@@ -0,0 +1,320 @@
+use std::cell::RefCell;
+use std::net::IpAddr;
+use std::panic::{AssertUnwindSafe, PanicHookInfo};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::{HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use futures_util::FutureExt;
+use rand::Rng;
+use serde_json::{json, Map, Value};
+use tokio::sync::mpsc;
+
+use crate::error::{ApiError, ServerError};
+use crate::models::unix_time;
+use crate::proxy::ClientIp;
+use crate::sharing::hex;
+use crate::state::SharedState;
+
+// waiting to be sent, anything past this is dropped rather than holding up a handler
+const QUEUE_SIZE: usize = 100;
+const TIMEOUT: Duration = Duration::from_secs(10);
+const CLIENT: &str = concat!("rust-hello-http/", env!("CARGO_PKG_VERSION"));
+// a client's X-Request-Id is kept if it's no longer than this
+const MAX_REQUEST_ID: usize = 64;
+
+// where events go, out of a DSN like https://<key>@o123.ingest.sentry.io/456
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dsn{
+    pub public_key: String,
+    // the scheme and host, and any path in front of the project for a self-hosted one
+    pub base: String,
+    pub project_id: String,
+}
+
+impl Dsn{
+    pub fn parse(dsn: &str) -> Result<Dsn, String> {
+        let bad = || format!("SENTRY_DSN should look like https://<key>@<host>/<project>, not {:?}", dsn);
+        let (scheme, rest) = dsn.split_once("://").filter(|(scheme, _)| matches!(*scheme, "http" | "https")).ok_or_else(bad)?;
+        let (key, rest) = rest.split_once('@').ok_or_else(bad)?;
+        // old DSNs have a secret after the key, it isn't needed any more
+        let public_key = key.split(':').next().unwrap_or_default();
+        let (host, project_id) = rest.trim_end_matches('/').rsplit_once('/').ok_or_else(bad)?;
+        if public_key.is_empty() || host.is_empty() || project_id.is_empty() {
+            return Err(bad());
+        }
+        Ok(Dsn { public_key: public_key.to_string(), base: format!("{}://{}", scheme, host), project_id: project_id.to_string() })
+    }
+
+    pub fn envelope_url(&self) -> String {
+        format!("{}/api/{}/envelope/", self.base, self.project_id)
+    }
+
+    fn auth(&self) -> String {
+        format!("Sentry sentry_version=7, sentry_client={}, sentry_key={}", CLIENT, self.public_key)
+    }
+}
+
+// what the request that went wrong was, and who sent it
+#[derive(Debug, Clone, Default)]
+pub struct RequestContext{
+    // the client's X-Request-Id, or one made up for it, sent back on the response either way
+    pub request_id: String,
+    pub method: String,
+    pub uri: String,
+    // as it's declared, e.g. "/pokemon/{id}", None for a 404
+    pub route: Option<String>,
+    // X-Trainer-Id, taken on trust like the viewer takes it
+    pub trainer_id: Option<u32>,
+    pub ip: Option<IpAddr>,
+}
+
+impl RequestContext{
+    fn of(request: &Request) -> RequestContext {
+        let header = |name: &str| request.headers().get(name).and_then(|v| v.to_str().ok()).map(str::trim);
+        let request_id = header("x-request-id")
+            .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID)
+            .map_or_else(random_id, str::to_string);
+        RequestContext {
+            request_id,
+            method: request.method().to_string(),
+            uri: request.uri().to_string(),
+            route: request.extensions().get::<MatchedPath>().map(|path| path.as_str().to_string()),
+            trainer_id: header("x-trainer-id").and_then(|id| id.parse().ok()),
+            ip: request.extensions().get::<ClientIp>().and_then(|ClientIp(ip)| *ip),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level{
+    Error,
+    // a panic
+    Fatal,
+}
+
+#[derive(Debug, Clone)]
+pub struct Event{
+    pub level: Level,
+    // the exception's type, "panic" or the status, e.g. "500 Internal Server Error"
+    pub kind: String,
+    pub message: String,
+    // None for a panic outside a request, e.g. in a background task
+    pub request: Option<RequestContext>,
+}
+
+impl Event{
+    pub fn panic(message: String, request: Option<RequestContext>) -> Event {
+        Event { level: Level::Fatal, kind: "panic".to_string(), message, request }
+    }
+
+    pub fn server_error(status: StatusCode, message: String, request: RequestContext) -> Event {
+        Event { level: Level::Error, kind: status.to_string(), message, request: Some(request) }
+    }
+}
+
+// with SENTRY_DSN, panics and 5xx responses are sent to Sentry
+#[derive(Debug)]
+pub struct Sentry{
+    dsn: Option<Dsn>,
+    // the share of events that are sent, 0 to 1
+    sample_rate: f64,
+    environment: Option<String>,
+    queue: mpsc::Sender<String>,
+    // taken by the job that does the sending
+    receiver: Mutex<Option<mpsc::Receiver<String>>>,
+}
+
+impl Default for Sentry{
+    // off
+    fn default() -> Self {
+        let (queue, receiver) = mpsc::channel(QUEUE_SIZE);
+        Sentry { dsn: None, sample_rate: 1.0, environment: None, queue, receiver: Mutex::new(Some(receiver)) }
+    }
+}
+
+impl Sentry{
+    pub fn to(dsn: Dsn) -> Sentry {
+        Sentry { dsn: Some(dsn), ..Sentry::default() }
+    }
+
+    // SENTRY_DSN turns it on, SENTRY_SAMPLE_RATE is the share of events sent and SENTRY_ENVIRONMENT tags them
+    pub fn from_env() -> Result<Sentry, String> {
+        let Some(dsn) = crate::secrets::var("SENTRY_DSN").ok().filter(|d| !d.is_empty()) else {
+            return Ok(Sentry::default());
+        };
+        let mut sentry = Sentry::to(Dsn::parse(&dsn)?);
+        if let Ok(rate) = std::env::var("SENTRY_SAMPLE_RATE") {
+            let rate = rate.parse().ok().filter(|r| (0.0..=1.0).contains(r))
+                .ok_or_else(|| format!("SENTRY_SAMPLE_RATE should be 0 to 1, not {:?}", rate))?;
+            sentry = sentry.sample_rate(rate);
+        }
+        if let Ok(environment) = std::env::var("SENTRY_ENVIRONMENT") {
+            sentry = sentry.environment(environment);
+        }
+        Ok(sentry)
+    }
+
+    pub fn sample_rate(mut self, rate: f64) -> Sentry {
+        self.sample_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn environment(mut self, environment: impl Into<String>) -> Sentry {
+        self.environment = Some(environment.into());
+        self
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.dsn.is_some()
+    }
+
+    // queues the event for the sending job, safe from a panic hook, its ID is None when it's sampled out
+    pub fn capture(&self, event: Event) -> Option<String> {
+        if !self.is_on() || !rand::rng().random_bool(self.sample_rate) {
+            return None;
+        }
+        let id = random_id();
+        let envelope = format!("{}\n{}\n{}\n", json!({ "event_id": id }), json!({ "type": "event" }), self.payload(&id, &event));
+        if self.queue.try_send(envelope).is_err() {
+            eprintln!("sentry: the queue is full, dropped an event");
+            return None;
+        }
+        Some(id)
+    }
+
+    // the event as Sentry takes it
+    pub fn payload(&self, id: &str, event: &Event) -> Value {
+        let mut payload = Map::new();
+        payload.insert("event_id".to_string(), json!(id));
+        payload.insert("timestamp".to_string(), json!(unix_time()));
+        payload.insert("platform".to_string(), json!("other"));
+        payload.insert("logger".to_string(), json!("rust-hello-http"));
+        payload.insert("level".to_string(), json!(if event.level == Level::Fatal { "fatal" } else { "error" }));
+        payload.insert("release".to_string(), json!(concat!("rust-hello-http@", env!("CARGO_PKG_VERSION"))));
+        if let Some(environment) = &self.environment {
+            payload.insert("environment".to_string(), json!(environment));
+        }
+        payload.insert("exception".to_string(), json!({ "values": [{ "type": event.kind, "value": event.message }] }));
+        if let Some(request) = &event.request {
+            let mut tags = Map::from_iter([("request_id".to_string(), json!(request.request_id))]);
+            if let Some(route) = &request.route {
+                tags.insert("route".to_string(), json!(route));
+            }
+            payload.insert("tags".to_string(), Value::Object(tags));
+            payload.insert("request".to_string(), json!({ "method": request.method, "url": request.uri }));
+            let mut user = Map::new();
+            if let Some(trainer_id) = request.trainer_id {
+                user.insert("id".to_string(), json!(trainer_id.to_string()));
+            }
+            if let Some(ip) = request.ip {
+                user.insert("ip_address".to_string(), json!(ip.to_string()));
+            }
+            if !user.is_empty() {
+                payload.insert("user".to_string(), Value::Object(user));
+            }
+        }
+        Value::Object(payload)
+    }
+}
+
+// 32 hex digits, the shape Sentry wants its event IDs in
+fn random_id() -> String {
+    hex(&rand::rng().random::<[u8; 16]>())
+}
+
+tokio::task_local! {
+    // the request being handled, so the panic hook can tell a handler's panic from anyone else's
+    static CONTEXT: RequestContext;
+}
+
+thread_local! {
+    // the last panic in a handler, for the middleware to pick up once it's caught
+    static PANICKED: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+fn panic_message(info: &PanicHookInfo) -> String {
+    let payload = info.payload();
+    let message = payload.downcast_ref::<&str>().map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "a panic without a message".to_string());
+    match info.location() {
+        Some(location) => format!("{} at {}:{}", message, location.file(), location.line()),
+        None => message,
+    }
+}
+
+// panics anywhere get reported, a handler's are left for the middleware to send with the request
+// the default hook still prints them as before
+pub fn install_panic_hook(state: &SharedState) {
+    if !state.sentry.is_on() {
+        return;
+    }
+    let state = state.clone();
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        previous(info);
+        let message = panic_message(info);
+        if CONTEXT.try_with(|_| ()).is_ok() {
+            PANICKED.with(|panicked| *panicked.borrow_mut() = Some(message));
+        } else {
+            state.sentry.capture(Event::panic(message, None));
+        }
+    }));
+}
+
+// sends whatever's queued, one at a time and in order, and installs the panic hook
+pub fn spawn_reporter(state: SharedState) {
+    let Some(dsn) = state.sentry.dsn.clone() else { return };
+    let Some(mut receiver) = state.sentry.receiver.lock().unwrap().take() else { return };
+    install_panic_hook(&state);
+    tokio::spawn(async move {
+        let Ok(client) = reqwest::Client::builder().timeout(TIMEOUT).build() else { return };
+        let (url, auth) = (dsn.envelope_url(), dsn.auth());
+        while let Some(envelope) = receiver.recv().await {
+            let sent = client.post(&url)
+                .header("x-sentry-auth", &auth)
+                .header("content-type", "application/x-sentry-envelope")
+                .body(envelope)
+                .send().await
+                .and_then(|r| r.error_for_status());
+            // not tried again, a report that didn't make it isn't worth holding on to
+            if let Err(e) = sent {
+                eprintln!("sentry: couldn't send an event: {}", e.without_url());
+            }
+        }
+    });
+}
+
+// reports 5xx responses and catches panics, which the client gets as a 500 instead of a dropped connection
+// a 503 is the server saying so on purpose, shed load or maintenance, so it isn't reported
+pub async fn report(State(state): State<SharedState>, request: Request, next: Next) -> Response {
+    if !state.sentry.is_on() {
+        return next.run(request).await;
+    }
+    let context = RequestContext::of(&request);
+    let request_id = HeaderValue::from_str(&context.request_id).ok();
+    let outcome = AssertUnwindSafe(CONTEXT.scope(context.clone(), next.run(request))).catch_unwind().await;
+    let mut response = match outcome {
+        Ok(response) => {
+            let status = response.status();
+            if status.is_server_error() && status != StatusCode::SERVICE_UNAVAILABLE {
+                let message = response.extensions().get::<ServerError>().map_or_else(|| status.to_string(), |e| e.0.clone());
+                state.sentry.capture(Event::server_error(status, message, context));
+            }
+            response
+        }
+        Err(_) => {
+            let message = PANICKED.with(|panicked| panicked.borrow_mut().take()).unwrap_or_else(|| "a handler panicked".to_string());
+            state.sentry.capture(Event::panic(message, Some(context)));
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "something went wrong, it's been reported").into_response()
+        }
+    };
+    if let Some(id) = request_id {
+        response.headers_mut().insert("x-request-id", id);
+    }
+    response
+}
@@ -0,0 +1,279 @@
+use std::time::Duration;
+
+use axum::extract::ws::{Message, WebSocket};
+use rand::{rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio::time::{timeout_at, Instant};
+
+use crate::battle::{Battle, Side, SIDES};
+use crate::models::Pokemon;
+use crate::moves::MoveData;
+use crate::state::SharedState;
+
+// how long to wait for both players to connect
+const JOIN_TIMEOUT: Duration = Duration::from_secs(300);
+
+// players who don't pick a move in time forfeit
+pub const TURN_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionStatus{
+    WaitingForPlayers,
+    InProgress,
+    Finished,
+    Cancelled,
+}
+
+// an interactive battle: two clients connect over WebSocket, one per side
+#[derive(Debug, Clone, Serialize)]
+pub struct BattleSession{
+    pub id: u32,
+    pub side_a: Vec<u32>,
+    pub side_b: Vec<u32>,
+    pub status: SessionStatus,
+    // sides with a client connected right now
+    pub connected: Vec<Side>,
+    // the stored replay once finished
+    pub battle_id: Option<u32>,
+    #[serde(skip)]
+    pub commands: Option<mpsc::Sender<Command>>,
+}
+
+// what a client sends
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage{
+    ChooseMove {
+        #[serde(rename = "move")]
+        move_name: String,
+    },
+}
+
+// what the server sends back
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage{
+    WaitingForOpponent,
+    BattleStarted { events: Vec<String> },
+    ChooseMove { active: String, moves: Vec<String>, timeout_secs: u64 },
+    MoveAccepted { #[serde(rename = "move")] move_name: String },
+    Turn { turn: u32, events: Vec<String> },
+    BattleOver { winner: Option<Side>, reason: String, battle_id: Option<u32> },
+    Error { message: String },
+}
+
+// from the connection tasks to the arbiter
+#[derive(Debug)]
+pub enum Command{
+    Join { side: Side, outgoing: mpsc::Sender<ServerMessage> },
+    Choose { side: Side, move_name: String },
+    Leave { side: Side },
+}
+
+fn set_status(state: &SharedState, id: u32, status: SessionStatus, battle_id: Option<u32>) {
+    let mut sessions = state.sessions.lock().unwrap();
+    if let Some(session) = sessions.iter_mut().find(|s| s.id == id) {
+        session.status = status;
+        session.battle_id = battle_id;
+        if matches!(status, SessionStatus::Finished | SessionStatus::Cancelled) {
+            session.commands = None;
+        }
+    }
+}
+
+fn set_connected(state: &SharedState, id: u32, side: Side, connected: bool) {
+    let mut sessions = state.sessions.lock().unwrap();
+    if let Some(session) = sessions.iter_mut().find(|s| s.id == id) {
+        session.connected.retain(|s| *s != side);
+        if connected {
+            session.connected.push(side);
+        }
+    }
+}
+
+// register a new session and start the task that referees it
+pub fn create(state: &SharedState, side_a: Vec<Pokemon>, side_b: Vec<Pokemon>) -> BattleSession {
+    let (commands, receiver) = mpsc::channel(32);
+
+    let session = {
+        let mut sessions = state.sessions.lock().unwrap();
+        let new_id = sessions.last().map_or(1, |s| s.id + 1);
+        let session = BattleSession {
+            id: new_id,
+            side_a: side_a.iter().map(|p| p.id).collect(),
+            side_b: side_b.iter().map(|p| p.id).collect(),
+            status: SessionStatus::WaitingForPlayers,
+            connected: Vec::new(),
+            battle_id: None,
+            commands: Some(commands),
+        };
+        sessions.push(session.clone());
+        session
+    };
+
+    tokio::spawn(arbitrate(session.id, state.clone(), side_a, side_b, receiver));
+    session
+}
+
+async fn send(players: &[Option<mpsc::Sender<ServerMessage>>; 2], side: Side, message: ServerMessage) {
+    if let Some(player) = &players[side.index()] {
+        let _ = player.send(message).await;
+    }
+}
+
+async fn broadcast(players: &[Option<mpsc::Sender<ServerMessage>>; 2], message: ServerMessage) {
+    for side in SIDES {
+        send(players, side, message.clone()).await;
+    }
+}
+
+// the referee: waits for both players, collects a move from each every turn and plays it out
+async fn arbitrate(id: u32, state: SharedState, side_a: Vec<Pokemon>, side_b: Vec<Pokemon>, mut commands: mpsc::Receiver<Command>) {
+    let mut players: [Option<mpsc::Sender<ServerMessage>>; 2] = [None, None];
+
+    let join_deadline = Instant::now() + JOIN_TIMEOUT;
+    while players.iter().any(Option::is_none) {
+        match timeout_at(join_deadline, commands.recv()).await {
+            Ok(Some(Command::Join { side, outgoing })) => {
+                let _ = outgoing.send(ServerMessage::WaitingForOpponent).await;
+                players[side.index()] = Some(outgoing);
+            }
+            Ok(Some(Command::Leave { side })) => players[side.index()] = None,
+            Ok(Some(Command::Choose { side, .. })) => {
+                send(&players, side, ServerMessage::Error { message: "the battle hasn't started yet".to_string() }).await;
+            }
+            Ok(None) => return,
+            Err(_) => {
+                let reason = "the other player never showed up".to_string();
+                broadcast(&players, ServerMessage::BattleOver { winner: None, reason, battle_id: None }).await;
+                set_status(&state, id, SessionStatus::Cancelled, None);
+                return;
+            }
+        }
+    }
+    set_status(&state, id, SessionStatus::InProgress, None);
+
+    let seed = state.randomness.seed();
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut battle = Battle::new(side_a, side_b).with_scripts(state.scripts.clone());
+    broadcast(&players, ServerMessage::BattleStarted { events: battle.opening().events.clone() }).await;
+
+    let (winner, reason) = loop {
+        if let Some(winner) = battle.result() {
+            break (winner, "battle finished".to_string());
+        }
+
+        for side in SIDES {
+            let moves = battle.active_moves(side).iter().map(|m| m.name.to_string()).collect();
+            let active = battle.active_name(side).to_string();
+            send(&players, side, ServerMessage::ChooseMove { active, moves, timeout_secs: TURN_TIMEOUT.as_secs() }).await;
+        }
+
+        let mut choices: [Option<&'static MoveData>; 2] = [None, None];
+        let turn_deadline = Instant::now() + TURN_TIMEOUT;
+        // sides that forfeit this turn, with the reason
+        let forfeits: Vec<(Side, &str)> = loop {
+            if choices.iter().all(Option::is_some) {
+                break Vec::new();
+            }
+            match timeout_at(turn_deadline, commands.recv()).await {
+                Ok(Some(Command::Choose { side, move_name })) => match battle.find_move(side, &move_name) {
+                    Some(mv) => {
+                        choices[side.index()] = Some(mv);
+                        send(&players, side, ServerMessage::MoveAccepted { move_name: mv.name.to_string() }).await;
+                    }
+                    None => {
+                        let message = format!("{} can't use {}", battle.active_name(side), move_name);
+                        send(&players, side, ServerMessage::Error { message }).await;
+                    }
+                },
+                Ok(Some(Command::Leave { side })) => {
+                    players[side.index()] = None;
+                    break vec![(side, "disconnected")];
+                }
+                Ok(Some(Command::Join { outgoing, .. })) => {
+                    let message = "that side is already taken".to_string();
+                    let _ = outgoing.send(ServerMessage::Error { message }).await;
+                }
+                Ok(None) => return,
+                Err(_) => {
+                    break SIDES.iter()
+                        .filter(|side| choices[side.index()].is_none())
+                        .map(|side| (*side, "ran out of time"))
+                        .collect();
+                }
+            }
+        };
+
+        if !forfeits.is_empty() {
+            let reason = forfeits.iter()
+                .map(|(side, why)| format!("{} {} and forfeits", side.label(), why))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let log = battle.note(reason.clone());
+            broadcast(&players, ServerMessage::Turn { turn: log.turn, events: log.events.clone() }).await;
+            // both AFK is a draw
+            let winner = match forfeits.as_slice() {
+                [(side, _)] => Some(side.other()),
+                _ => None,
+            };
+            break (winner, reason);
+        }
+
+        let log = battle.play_turn([choices[0].unwrap(), choices[1].unwrap()], &mut rng);
+        broadcast(&players, ServerMessage::Turn { turn: log.turn, events: log.events.clone() }).await;
+    };
+
+    let record = state.record_battle(seed, battle.into_outcome(winner));
+    broadcast(&players, ServerMessage::BattleOver { winner, reason, battle_id: Some(record.id) }).await;
+    set_status(&state, id, SessionStatus::Finished, Some(record.id));
+}
+
+// one client's socket: forwards its move choices to the arbiter and the arbiter's messages back
+pub async fn run_connection(mut socket: WebSocket, state: SharedState, id: u32, side: Side, commands: mpsc::Sender<Command>) {
+    let (outgoing, mut incoming_from_arbiter) = mpsc::channel(32);
+    if commands.send(Command::Join { side, outgoing }).await.is_err() {
+        return;
+    }
+    set_connected(&state, id, side, true);
+
+    loop {
+        tokio::select! {
+            message = incoming_from_arbiter.recv() => match message {
+                Some(message) => {
+                    let text = serde_json::to_string(&message).expect("server messages always serialize");
+                    if socket.send(Message::text(text)).await.is_err() {
+                        let _ = commands.send(Command::Leave { side }).await;
+                        break;
+                    }
+                }
+                // the arbiter is done with us
+                None => {
+                    let _ = socket.send(Message::Close(None)).await;
+                    break;
+                }
+            },
+            received = socket.recv() => match received {
+                Some(Ok(Message::Text(text))) => match serde_json::from_str::<ClientMessage>(&text) {
+                    Ok(ClientMessage::ChooseMove { move_name }) => {
+                        let _ = commands.send(Command::Choose { side, move_name }).await;
+                    }
+                    Err(e) => {
+                        let error = ServerMessage::Error { message: format!("invalid message: {}", e) };
+                        let text = serde_json::to_string(&error).expect("server messages always serialize");
+                        let _ = socket.send(Message::text(text)).await;
+                    }
+                },
+                // pings are answered automatically, binary frames are ignored
+                Some(Ok(Message::Binary(_) | Message::Ping(_) | Message::Pong(_))) => {}
+                Some(Ok(Message::Close(_)) | Err(_)) | None => {
+                    let _ = commands.send(Command::Leave { side }).await;
+                    break;
+                }
+            },
+        }
+    }
+    set_connected(&state, id, side, false);
+}
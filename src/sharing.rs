@@ -0,0 +1,188 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use qrcode::{Color, QrCode};
+use serde::Serialize;
+use sha2::Sha256;
+
+// how long a share link works for unless asked otherwise, and the longest one can last
+pub const DEFAULT_LIFETIME: Duration = Duration::from_secs(24 * 60 * 60);
+pub const MAX_LIFETIME: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+// QR code rendering: pixels per module, and the blank border scanners need
+const SCALE: u32 = 8;
+const QUIET_ZONE: u32 = 4;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// a link that shows one Pokemon to anyone who has it, until it expires or is revoked
+#[derive(Debug, Clone, Serialize)]
+pub struct Share{
+    pub token: String,
+    pub url: String,
+    pub pokemon_id: u32,
+    // unix seconds
+    pub expires_at: u64,
+}
+
+// signs share links so they can't be made up for Pokemon nobody shared
+#[derive(Debug)]
+pub struct Sharing{
+    secret: Vec<u8>,
+    // where the links point, e.g. https://pokemon.example.com
+    public_url: String,
+    // links that haven't been revoked, a signed token that isn't in here doesn't work
+    outstanding: Mutex<Vec<Share>>,
+    // tokens that have already been redeemed, each link imports one copy
+    redeemed: Mutex<HashSet<String>>,
+}
+
+impl Default for Sharing{
+    // a random secret, so links stop working when the server restarts
+    fn default() -> Self {
+        Sharing {
+            secret: rand::random::<[u8; 32]>().to_vec(),
+            public_url: "http://localhost:3000".to_string(),
+            outstanding: Mutex::default(),
+            redeemed: Mutex::default(),
+        }
+    }
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+impl Sharing{
+    // SHARE_SECRET keeps signatures the same across restarts, PUBLIC_URL is where links point
+    pub fn from_env() -> Sharing {
+        let mut sharing = Sharing::default();
+        if let Ok(secret) = crate::secrets::var("SHARE_SECRET") {
+            sharing.secret = secret.into_bytes();
+        }
+        if let Ok(url) = std::env::var("PUBLIC_URL") {
+            sharing.public_url = url.trim_end_matches('/').to_string();
+        }
+        sharing
+    }
+
+    fn mac(&self, payload: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.secret).expect("hmac takes keys of any size");
+        mac.update(payload.as_bytes());
+        mac
+    }
+
+    // a new link, the token is "<pokemon id>.<expires at>.<nonce>.<signature>"
+    pub fn share(&self, pokemon_id: u32, lifetime: Duration, now: u64) -> Share {
+        let expires_at = now + lifetime.as_secs();
+        // so two links for the same Pokemon can be revoked separately
+        let nonce: u64 = rand::random();
+        let payload = format!("{}.{}.{:016x}", pokemon_id, expires_at, nonce);
+        let token = format!("{}.{}", payload, self.sign(&payload));
+        let share = Share { url: format!("{}/shares/{}", self.public_url, token), token, pokemon_id, expires_at };
+
+        let mut outstanding = self.outstanding.lock().unwrap();
+        outstanding.retain(|s| s.expires_at >= now);
+        outstanding.push(share.clone());
+        share
+    }
+
+    // with the same secret as share links, for other links that mustn't be made up
+    pub fn sign(&self, payload: &str) -> String {
+        hex(&self.mac(payload).finalize().into_bytes())
+    }
+
+    // constant time, so the signature can't be guessed byte by byte
+    pub fn check(&self, payload: &str, signature: &str) -> bool {
+        let bytes: Option<Vec<u8>> = (0..signature.len())
+            .step_by(2)
+            .map(|i| signature.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+            .collect();
+        bytes.is_some_and(|bytes| self.mac(payload).verify_slice(&bytes).is_ok())
+    }
+
+    // where links point, without a trailing slash
+    pub fn public_url(&self) -> &str {
+        &self.public_url
+    }
+
+    // the shared Pokemon's ID, if the token is genuine, hasn't expired and hasn't been revoked
+    pub fn verify(&self, token: &str, now: u64) -> Result<u32, String> {
+        let invalid = || "that share link isn't valid".to_string();
+        let (payload, signature) = token.rsplit_once('.').ok_or_else(invalid)?;
+        if !self.check(payload, signature) {
+            return Err(invalid());
+        }
+
+        let mut parts = payload.split('.');
+        let pokemon_id: u32 = parts.next().and_then(|id| id.parse().ok()).ok_or_else(invalid)?;
+        let expires_at: u64 = parts.next().and_then(|e| e.parse().ok()).ok_or_else(invalid)?;
+        if expires_at < now {
+            return Err("that share link has expired".to_string());
+        }
+        if !self.outstanding.lock().unwrap().iter().any(|s| s.token == token) {
+            return Err("that share link has been revoked".to_string());
+        }
+        Ok(pokemon_id)
+    }
+
+    // the links for a Pokemon that still work
+    pub fn outstanding(&self, pokemon_id: u32, now: u64) -> Vec<Share> {
+        self.outstanding.lock().unwrap().iter()
+            .filter(|s| s.pokemon_id == pokemon_id && s.expires_at >= now)
+            .cloned()
+            .collect()
+    }
+
+    // false if there was no such link
+    pub fn revoke(&self, token: &str) -> bool {
+        let mut outstanding = self.outstanding.lock().unwrap();
+        let original_len = outstanding.len();
+        outstanding.retain(|s| s.token != token);
+        outstanding.len() < original_len
+    }
+
+    // how many links were revoked
+    pub fn revoke_all(&self, pokemon_id: u32) -> usize {
+        let mut outstanding = self.outstanding.lock().unwrap();
+        let original_len = outstanding.len();
+        outstanding.retain(|s| s.pokemon_id != pokemon_id);
+        original_len - outstanding.len()
+    }
+
+    // false if the token was already used
+    pub fn redeem(&self, token: &str) -> bool {
+        self.redeemed.lock().unwrap().insert(token.to_string())
+    }
+}
+
+// black modules on white, as a grayscale PNG
+pub fn qr_png(text: &str) -> Result<Vec<u8>, String> {
+    let code = QrCode::new(text.as_bytes()).map_err(|e| e.to_string())?;
+    let modules = code.width() as u32;
+    let colors = code.to_colors();
+    let size = (modules + 2 * QUIET_ZONE) * SCALE;
+
+    let mut pixels = vec![255u8; (size * size) as usize];
+    for (i, color) in colors.iter().enumerate() {
+        if *color != Color::Dark {
+            continue;
+        }
+        let (x, y) = (i as u32 % modules + QUIET_ZONE, i as u32 / modules + QUIET_ZONE);
+        for row in y * SCALE..(y + 1) * SCALE {
+            let start = (row * size + x * SCALE) as usize;
+            pixels[start..start + SCALE as usize].fill(0);
+        }
+    }
+
+    let mut png_bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png_bytes, size, size);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer.write_image_data(&pixels).map_err(|e| e.to_string())?;
+    writer.finish().map_err(|e| e.to_string())?;
+    Ok(png_bytes)
+}
@@ -0,0 +1,202 @@
+use crate::models::{Pokemon, Stats};
+use crate::moves;
+use crate::nickname;
+use crate::species::{self, MAX_IV, MAX_LEVEL, MAX_MOVES};
+
+// Showdown's own limits
+const MAX_EV: u32 = 252;
+const MAX_TOTAL_EVS: u32 = 510;
+const MAX_SETS: usize = 6;
+
+// biggest paste POST /pokemon/import takes, six sets are a couple of KB at most
+pub const MAX_PASTE_SIZE: usize = 64 * 1024;
+
+// in the order Showdown writes them
+const STAT_NAMES: [&str; 6] = ["HP", "Atk", "Def", "SpA", "SpD", "Spe"];
+
+const NATURES: &[&str] = &[
+    "Hardy", "Lonely", "Brave", "Adamant", "Naughty",
+    "Bold", "Docile", "Relaxed", "Impish", "Lax",
+    "Timid", "Hasty", "Serious", "Jolly", "Naive",
+    "Modest", "Mild", "Quiet", "Bashful", "Rash",
+    "Calm", "Gentle", "Sassy", "Careful", "Quirky",
+];
+
+// one Pokemon from a paste, the item, ability, EVs and nature are checked but there's nowhere to keep them yet
+#[derive(Debug, Clone)]
+pub struct Set{
+    pub species: &'static str,
+    pub nickname: Option<String>,
+    pub item: Option<String>,
+    pub ability: Option<String>,
+    pub level: u32,
+    pub shiny: bool,
+    pub evs: Stats,
+    pub ivs: Stats,
+    pub nature: Option<&'static str>,
+    pub moves: Vec<String>,
+}
+
+impl Set{
+    fn new(species: &'static str) -> Set {
+        Set {
+            species,
+            nickname: None,
+            item: None,
+            ability: None,
+            // Showdown's defaults for anything the paste leaves out
+            level: MAX_LEVEL,
+            shiny: false,
+            evs: Stats::default(),
+            ivs: Stats::from_array([MAX_IV; 6]),
+            nature: None,
+            moves: Vec::new(),
+        }
+    }
+
+    // the parts of the set the import couldn't keep, e.g. "item: Light Ball"
+    pub fn not_kept(&self) -> Vec<String> {
+        let evs: Vec<String> = self.evs.as_array().iter()
+            .zip(STAT_NAMES)
+            .filter(|(ev, _)| **ev > 0)
+            .map(|(ev, stat)| format!("{} {}", ev, stat))
+            .collect();
+        [
+            self.item.as_ref().map(|i| format!("item: {}", i)),
+            self.ability.as_ref().map(|a| format!("ability: {}", a)),
+            (!evs.is_empty()).then(|| format!("EVs: {}", evs.join(" / "))),
+            self.nature.map(|n| format!("nature: {}", n)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
+
+// "252 SpA / 4 SpD / 252 Spe", stats that aren't listed keep what's in `base`
+fn parse_spread(text: &str, base: Stats, max: u32) -> Result<Stats, String> {
+    let mut values = base.as_array();
+    for part in text.split('/') {
+        let (amount, stat) = part.trim().split_once(' ')
+            .ok_or_else(|| format!("\"{}\" should look like \"252 Atk\"", part.trim()))?;
+        let index = STAT_NAMES.iter().position(|s| s.eq_ignore_ascii_case(stat.trim()))
+            .ok_or_else(|| format!("unknown stat {}, try {}", stat.trim(), STAT_NAMES.join(", ")))?;
+        let amount: u32 = amount.parse().map_err(|_| format!("{} isn't a number", amount))?;
+        if amount > max {
+            return Err(format!("{} {} is over the max of {}", amount, STAT_NAMES[index], max));
+        }
+        values[index] = amount;
+    }
+    Ok(Stats::from_array(values))
+}
+
+// "Sparky (Pikachu) (M) @ Light Ball", everything but the species is optional
+fn parse_header(line: &str) -> Result<Set, String> {
+    let (name, item) = match line.split_once(" @ ") {
+        Some((name, item)) => (name.trim(), Some(item.trim().to_string())),
+        None => (line.trim(), None),
+    };
+    let name = name.strip_suffix(" (M)").or_else(|| name.strip_suffix(" (F)")).unwrap_or(name).trim();
+    let (nickname, species_name) = match name.strip_suffix(')').and_then(|n| n.rsplit_once(" (")) {
+        Some((nickname, species_name)) => (Some(nickname::validate(nickname)?), species_name.trim()),
+        None => (None, name),
+    };
+    let species = species::find(species_name).ok_or_else(|| format!("unknown species {}", species_name))?;
+    Ok(Set { nickname, item, ..Set::new(species.name) })
+}
+
+fn parse_set(lines: &[(usize, &str)]) -> Result<Set, String> {
+    let at = |number: usize, e: String| format!("line {}: {}", number, e);
+    let (first, header) = lines[0];
+    let mut set = parse_header(header).map_err(|e| at(first, e))?;
+
+    for &(number, line) in &lines[1..] {
+        if let Some(name) = line.strip_prefix("- ") {
+            let name = name.trim();
+            let mv = moves::find(name).ok_or_else(|| at(number, format!("unknown move {}", name)))?;
+            if set.moves.iter().any(|m| m == mv.name) {
+                return Err(at(number, format!("{} is in there twice", mv.name)));
+            }
+            if set.moves.len() == MAX_MOVES {
+                return Err(at(number, format!("a pokemon can only know {} moves", MAX_MOVES)));
+            }
+            set.moves.push(mv.name.to_string());
+        } else if let Some(nature) = line.strip_suffix(" Nature") {
+            let nature = NATURES.iter().find(|n| n.eq_ignore_ascii_case(nature.trim()))
+                .ok_or_else(|| at(number, format!("unknown nature {}", nature.trim())))?;
+            set.nature = Some(nature);
+        } else if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim();
+            match key.trim() {
+                "Ability" => set.ability = Some(value.to_string()),
+                "Level" => {
+                    set.level = value.parse().ok().filter(|l| (1..=MAX_LEVEL).contains(l))
+                        .ok_or_else(|| at(number, format!("level must be 1 to {}", MAX_LEVEL)))?;
+                }
+                "Shiny" => set.shiny = value.eq_ignore_ascii_case("yes"),
+                "EVs" => {
+                    set.evs = parse_spread(value, Stats::default(), MAX_EV).map_err(|e| at(number, e))?;
+                    let total: u32 = set.evs.as_array().iter().sum();
+                    if total > MAX_TOTAL_EVS {
+                        return Err(at(number, format!("{} EVs in total, the max is {}", total, MAX_TOTAL_EVS)));
+                    }
+                }
+                "IVs" => set.ivs = parse_spread(value, set.ivs, MAX_IV).map_err(|e| at(number, e))?,
+                // things newer games added that don't mean anything here
+                "Tera Type" | "Happiness" | "Dynamax Level" | "Gigantamax" | "Hidden Power" | "Pokeball" => {}
+                other => return Err(at(number, format!("don't know what {} is", other))),
+            }
+        } else {
+            return Err(at(number, format!("can't make sense of \"{}\"", line)));
+        }
+    }
+    Ok(set)
+}
+
+// a whole paste, sets are separated by blank lines
+pub fn parse(text: &str) -> Result<Vec<Set>, String> {
+    let mut blocks: Vec<Vec<(usize, &str)>> = vec![Vec::new()];
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            if !blocks.last().unwrap().is_empty() {
+                blocks.push(Vec::new());
+            }
+        } else {
+            blocks.last_mut().unwrap().push((i + 1, line));
+        }
+    }
+    blocks.retain(|b| !b.is_empty());
+
+    if blocks.is_empty() {
+        return Err("the paste is empty".to_string());
+    }
+    if blocks.len() > MAX_SETS {
+        return Err(format!("a team has at most {} pokemon, the paste has {}", MAX_SETS, blocks.len()));
+    }
+    blocks.iter().map(|b| parse_set(b)).collect()
+}
+
+// the same text format, only writing what differs from Showdown's defaults
+pub fn export(pokemon: &Pokemon) -> String {
+    let mut lines = vec![match &pokemon.nickname {
+        Some(nickname) => format!("{} ({})", nickname, pokemon.species),
+        None => pokemon.species.clone(),
+    }];
+    if pokemon.level != MAX_LEVEL {
+        lines.push(format!("Level: {}", pokemon.level));
+    }
+    if pokemon.shiny {
+        lines.push("Shiny: Yes".to_string());
+    }
+    let ivs: Vec<String> = pokemon.ivs.as_array().iter()
+        .zip(STAT_NAMES)
+        .filter(|(iv, _)| **iv != MAX_IV)
+        .map(|(iv, stat)| format!("{} {}", iv, stat))
+        .collect();
+    if !ivs.is_empty() {
+        lines.push(format!("IVs: {}", ivs.join(" / ")));
+    }
+    lines.extend(pokemon.moves.iter().map(|m| format!("- {}", m)));
+    lines.join("\n") + "\n"
+}
@@ -0,0 +1,253 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use axum::extract::{MatchedPath, Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use serde::Serialize;
+
+use crate::state::SharedState;
+
+// the last this many requests to each route are what the percentiles are over
+pub const DEFAULT_WINDOW: usize = 1000;
+// the share of requests, in percent, that have to come in under the target
+pub const DEFAULT_OBJECTIVE: f64 = 99.0;
+// a route isn't judged until it has this many, one slow first request isn't a burn
+pub const MIN_SAMPLES: usize = 20;
+
+// rolling latency per route, and a target for each route that has one
+#[derive(Debug)]
+pub struct Slos{
+    // "GET /pokemon/{id}" or just the route pattern for every method -> how long it's allowed to take
+    targets: HashMap<String, Duration>,
+    // for the routes without one of their own, None leaves them untargeted
+    default_target: Option<Duration>,
+    objective: f64,
+    window: usize,
+    // print when a route starts and stops burning through its budget
+    warn: bool,
+    routes: Mutex<HashMap<String, Window>>,
+}
+
+impl Default for Slos{
+    // latencies tracked, no targets
+    fn default() -> Self {
+        Slos {
+            targets: HashMap::new(),
+            default_target: None,
+            objective: DEFAULT_OBJECTIVE,
+            window: DEFAULT_WINDOW,
+            warn: false,
+            routes: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Window{
+    // nanoseconds, oldest first
+    samples: VecDeque<u64>,
+    // how many of the samples took longer than the target
+    slow: usize,
+    // since the server started, not just the window
+    requests: u64,
+    burning: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SloStatus{
+    Ok,
+    // more slow requests in the window than the objective allows
+    Burning,
+    // under MIN_SAMPLES so far
+    TooFew,
+    NoTarget,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteLatency{
+    // the method and the route as it's declared, e.g. "GET /pokemon/{id}"
+    pub route: String,
+    pub requests: u64,
+    // how many the percentiles are over, up to the window
+    pub samples: usize,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub target_ms: Option<f64>,
+    pub slow: usize,
+    // how fast the error budget is going, 1 spends exactly all of it, None without a target
+    pub burn_rate: Option<f64>,
+    pub status: SloStatus,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SloReport{
+    pub objective_percent: f64,
+    pub window: usize,
+    pub routes: Vec<RouteLatency>,
+}
+
+fn millis(nanos: u64) -> f64 {
+    nanos as f64 / 1_000_000.0
+}
+
+fn parse_ms(name: &str, value: &str) -> Result<Duration, String> {
+    value.trim().parse().map(Duration::from_millis).map_err(|_| format!("{} should be milliseconds, not {:?}", name, value))
+}
+
+// the nearest rank, sorted is never empty
+fn percentile(sorted: &[u64], percent: f64) -> u64 {
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+impl Slos{
+    // SLO_TARGETS sets targets in ms, e.g. "/battles=200,GET /pokemon=50", SLO_DEFAULT_MS covers the rest
+    // SLO_OBJECTIVE is the percent that has to make it, SLO_WINDOW how many requests are kept, SLO_WARN=on prints
+    pub fn from_env() -> Result<Slos, String> {
+        let mut slos = Slos::default();
+        if let Ok(targets) = std::env::var("SLO_TARGETS") {
+            for pair in targets.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+                let (route, ms) = pair.rsplit_once('=')
+                    .ok_or_else(|| format!("SLO_TARGETS entries look like /battles=200, not {:?}", pair))?;
+                slos = slos.target(route.trim(), parse_ms("SLO_TARGETS", ms)?);
+            }
+        }
+        if let Ok(ms) = std::env::var("SLO_DEFAULT_MS") {
+            slos = slos.default_target(parse_ms("SLO_DEFAULT_MS", &ms)?);
+        }
+        if let Ok(objective) = std::env::var("SLO_OBJECTIVE") {
+            let percent = objective.parse().ok().filter(|p| *p > 0.0 && *p < 100.0)
+                .ok_or_else(|| format!("SLO_OBJECTIVE should be a percent between 0 and 100, not {:?}", objective))?;
+            slos = slos.objective(percent);
+        }
+        if let Ok(window) = std::env::var("SLO_WINDOW") {
+            slos = slos.window(window.parse().ok().filter(|w| *w > 0).ok_or_else(|| format!("SLO_WINDOW should be a number over 0, not {:?}", window))?);
+        }
+        if let Ok(warn) = std::env::var("SLO_WARN") {
+            slos = slos.warn(match warn.as_str() {
+                "on" => true,
+                "off" => false,
+                _ => return Err(format!("SLO_WARN should be on or off, not {:?}", warn)),
+            });
+        }
+        Ok(slos)
+    }
+
+    // the route as it's declared with {placeholders}, with the method in front to target only that one
+    pub fn target(mut self, route: &str, target: Duration) -> Slos {
+        self.targets.insert(route.to_string(), target);
+        self
+    }
+
+    pub fn default_target(mut self, target: Duration) -> Slos {
+        self.default_target = Some(target);
+        self
+    }
+
+    // percent, e.g. 99.9
+    pub fn objective(mut self, percent: f64) -> Slos {
+        self.objective = percent;
+        self
+    }
+
+    pub fn window(mut self, requests: usize) -> Slos {
+        self.window = requests.max(1);
+        self
+    }
+
+    pub fn warn(mut self, warn: bool) -> Slos {
+        self.warn = warn;
+        self
+    }
+
+    // "GET /pokemon" -> its own target, then the pattern's for every method, then the default
+    fn target_for(&self, route: &str) -> Option<Duration> {
+        let pattern = route.split_once(' ').map_or(route, |(_, pattern)| pattern);
+        self.targets.get(route).or_else(|| self.targets.get(pattern)).copied().or(self.default_target)
+    }
+
+    // the share of slow requests that spends the whole budget
+    fn budget(&self) -> f64 {
+        1.0 - self.objective / 100.0
+    }
+
+    fn burn_rate(&self, window: &Window) -> f64 {
+        window.slow as f64 / window.samples.len() as f64 / self.budget()
+    }
+
+    fn record(&self, route: String, elapsed: Duration) {
+        let target = self.target_for(&route).map(|t| t.as_nanos() as u64);
+        let nanos = elapsed.as_nanos() as u64;
+        let mut routes = self.routes.lock().unwrap();
+        let window = routes.entry(route.clone()).or_default();
+        window.requests += 1;
+        window.samples.push_back(nanos);
+        let is_slow = |nanos: u64| target.is_some_and(|t| nanos > t);
+        if is_slow(nanos) {
+            window.slow += 1;
+        }
+        if window.samples.len() > self.window && window.samples.pop_front().is_some_and(is_slow) {
+            window.slow -= 1;
+        }
+
+        let Some(target) = target else { return };
+        let burning = window.samples.len() >= MIN_SAMPLES && self.burn_rate(window) > 1.0;
+        if self.warn && burning != window.burning {
+            if burning {
+                eprintln!("slo: {} is over its budget, {} of the last {} took longer than {}ms",
+                    route, window.slow, window.samples.len(), millis(target));
+            } else {
+                eprintln!("slo: {} is back within its budget", route);
+            }
+        }
+        window.burning = burning;
+    }
+
+    // every route that's had a request, slowest p99 first
+    pub fn report(&self) -> SloReport {
+        let routes = self.routes.lock().unwrap();
+        let mut latencies: Vec<RouteLatency> = routes.iter().map(|(route, window)| {
+            let mut sorted: Vec<u64> = window.samples.iter().copied().collect();
+            sorted.sort_unstable();
+            let target = self.target_for(route);
+            let burn_rate = target.map(|_| self.burn_rate(window));
+            let status = match burn_rate {
+                None => SloStatus::NoTarget,
+                Some(_) if sorted.len() < MIN_SAMPLES => SloStatus::TooFew,
+                Some(rate) if rate > 1.0 => SloStatus::Burning,
+                Some(_) => SloStatus::Ok,
+            };
+            RouteLatency {
+                route: route.clone(),
+                requests: window.requests,
+                samples: sorted.len(),
+                p50_ms: millis(percentile(&sorted, 50.0)),
+                p95_ms: millis(percentile(&sorted, 95.0)),
+                p99_ms: millis(percentile(&sorted, 99.0)),
+                target_ms: target.map(|t| millis(t.as_nanos() as u64)),
+                slow: window.slow,
+                burn_rate,
+                status,
+            }
+        }).collect();
+        latencies.sort_by(|a, b| b.p99_ms.total_cmp(&a.p99_ms).then_with(|| a.route.cmp(&b.route)));
+        SloReport { objective_percent: self.objective, window: self.window, routes: latencies }
+    }
+}
+
+// times everything inside it, up to when the response head is ready, a streamed body is still going after that
+// requests that didn't match a route aren't counted, or every scanner's 404s would get their own
+pub async fn track(State(state): State<SharedState>, request: Request, next: Next) -> Response {
+    let Some(path) = request.extensions().get::<MatchedPath>() else {
+        return next.run(request).await;
+    };
+    let route = format!("{} {}", request.method(), path.as_str());
+    let started = Instant::now();
+    let response = next.run(request).await;
+    state.slos.record(route, started.elapsed());
+    response
+}
@@ -0,0 +1,93 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io;
+use std::path::PathBuf;
+
+// biggest image PUT /pokemon/{id}/sprite takes
+pub const MAX_SIZE: usize = 1024 * 1024;
+
+// (content type, file extension)
+const FORMATS: &[(&str, &str)] = &[
+    ("image/png", "png"),
+    ("image/jpeg", "jpg"),
+    ("image/gif", "gif"),
+    ("image/webp", "webp"),
+];
+
+// what the bytes actually are, going by the first few, whatever the upload claims
+pub fn sniff(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+// changes whenever the image does, for If-None-Match
+pub fn etag(bytes: &[u8]) -> String {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    format!("\"{:016x}\"", hasher.finish())
+}
+
+// sprites are kept on disk as <dir>/<pokemon id>.<ext>
+#[derive(Debug, Clone)]
+pub struct SpriteStore{
+    dir: PathBuf,
+}
+
+impl Default for SpriteStore{
+    fn default() -> Self {
+        SpriteStore { dir: PathBuf::from("sprites") }
+    }
+}
+
+impl SpriteStore{
+    pub fn in_dir(dir: impl Into<PathBuf>) -> SpriteStore {
+        SpriteStore { dir: dir.into() }
+    }
+
+    // ./sprites unless SPRITE_DIR says otherwise
+    pub fn from_env() -> SpriteStore {
+        match std::env::var("SPRITE_DIR") {
+            Ok(dir) => SpriteStore { dir: PathBuf::from(dir) },
+            Err(_) => SpriteStore::default(),
+        }
+    }
+
+    fn path(&self, pokemon_id: u32, ext: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", pokemon_id, ext))
+    }
+
+    // replaces any earlier sprite, even one in a different format
+    pub async fn save(&self, pokemon_id: u32, content_type: &str, bytes: &[u8]) -> io::Result<()> {
+        let Some((_, ext)) = FORMATS.iter().find(|(t, _)| *t == content_type) else {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("can't store {}", content_type)));
+        };
+        tokio::fs::create_dir_all(&self.dir).await?;
+        self.remove(pokemon_id).await;
+        tokio::fs::write(self.path(pokemon_id, ext), bytes).await
+    }
+
+    // the content type and bytes, None if there's no sprite
+    pub async fn load(&self, pokemon_id: u32) -> Option<(&'static str, Vec<u8>)> {
+        for (content_type, ext) in FORMATS {
+            if let Ok(bytes) = tokio::fs::read(self.path(pokemon_id, ext)).await {
+                return Some((content_type, bytes));
+            }
+        }
+        None
+    }
+
+    pub async fn remove(&self, pokemon_id: u32) {
+        for (_, ext) in FORMATS {
+            // most of these won't exist
+            let _ = tokio::fs::remove_file(self.path(pokemon_id, ext)).await;
+        }
+    }
+}
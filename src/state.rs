@@ -0,0 +1,210 @@
+use std::sync::{Arc, Mutex};
+
+use rand::seq::IndexedRandom;
+
+use crate::achievements::Progress;
+use crate::auth::Auth;
+use crate::backups::Backups;
+use crate::battle::BattleOutcome;
+use crate::breeding::Egg;
+use crate::connections::Connections;
+use crate::daycare::DaycareStay;
+use crate::deterministic::{Randomness, Time};
+use crate::encounters::{EncounterTable, WildEncounter};
+use crate::error::ApiError;
+use crate::flags::Flags;
+use crate::inbox;
+use crate::jobs::Jobs;
+use crate::mail::Mailer;
+use crate::market::Listing;
+use crate::leaderboards::Leaderboards;
+use crate::matchmaking::Ticket;
+use crate::memory::Memory;
+use crate::modes::Modes;
+use crate::notify::Notifier;
+use crate::oidc::Logins;
+use crate::passwords::Passwords;
+use crate::plugins::Plugins;
+use crate::pokeapi::PokeApi;
+use crate::quotas::Quotas;
+use crate::recording::Recorder;
+use crate::redis::Redis;
+use crate::releases::Releases;
+use crate::responses::ResponseCache;
+use crate::scheduler::Scheduler;
+use crate::rules::Rules;
+use crate::scripting::Scripts;
+use crate::models::{BattleRecord, Gym, Notification, Pokemon, RandomQuery, Trade, Trainer};
+use crate::session::BattleSession;
+use crate::sharing::Sharing;
+use crate::slo::Slos;
+use crate::sprites::SpriteStore;
+use crate::tournament::Tournament;
+use crate::trash::TrashedPokemon;
+use crate::viewer::Viewer;
+
+// everything the server keeps in memory, one Mutex per collection
+// when holding more than one at a time, lock in this order: tournaments, tickets, encounters, eggs, daycare, trades, market, trash, team, trainers, achievements, leaderboards, inbox
+#[derive(Debug, Default)]
+pub struct AppState{
+    pub team: Mutex<Vec<Pokemon>>,
+    pub trainers: Mutex<Vec<Trainer>>,
+    pub gyms: Mutex<Vec<Gym>>,
+    pub trades: Mutex<Vec<Trade>>,
+    pub achievements: Mutex<Vec<Progress>>,
+    pub leaderboards: Mutex<Leaderboards>,
+    pub market: Mutex<Vec<Listing>>,
+    pub battles: Mutex<Vec<BattleRecord>>,
+    pub sessions: Mutex<Vec<BattleSession>>,
+    pub tickets: Mutex<Vec<Ticket>>,
+    pub tournaments: Mutex<Vec<Tournament>>,
+    pub encounters: Mutex<Vec<WildEncounter>>,
+    pub eggs: Mutex<Vec<Egg>>,
+    pub daycare: Mutex<Vec<DaycareStay>>,
+    // deleted Pokemon, until they're restored or purged
+    pub trash: Mutex<Vec<TrashedPokemon>>,
+    // every trainer's notifications, oldest first
+    pub inbox: Mutex<Vec<Notification>>,
+    // when the last JSON export was taken and its ETag, see admin::export_json
+    pub last_export: Mutex<Option<(u64, String)>>,
+    // read-only after startup, so no lock
+    pub encounter_table: EncounterTable,
+    // None in offline mode
+    pub pokeapi: Option<PokeApi>,
+    // REDIS_URL, None keeps the lookup cache per instance
+    pub redis: Option<Redis>,
+    pub sprites: SpriteStore,
+    pub sharing: Sharing,
+    pub releases: Releases,
+    pub backups: Backups,
+    // None turns the admin API off
+    pub admin_token: Option<String>,
+    pub modes: Modes,
+    pub quotas: Quotas,
+    pub rules: Rules,
+    pub scripts: Arc<Scripts>,
+    pub plugins: Plugins,
+    pub notifier: Notifier,
+    pub mailer: Mailer,
+    pub passwords: Passwords,
+    pub logins: Logins,
+    pub auth: Auth,
+    pub connections: Connections,
+    pub responses: ResponseCache,
+    // long operations run in the background, see jobs::start
+    pub jobs: Jobs,
+    pub scheduler: Scheduler,
+    pub memory: Memory,
+    pub flags: Flags,
+    pub slos: Slos,
+    pub recorder: Recorder,
+    // where timestamps and rolls come from, FROZEN_TIME and RANDOM_SEED pin them down
+    pub time: Time,
+    pub randomness: Randomness,
+    // only built with --features chaos
+    #[cfg(feature = "chaos")]
+    pub chaos: crate::chaos::Chaos,
+    // only built with --features sentry
+    #[cfg(feature = "sentry")]
+    pub sentry: crate::sentry::Sentry,
+}
+
+// Pokemon a trainer has been given read access to show up in their lists too
+fn listed(viewer: Viewer, pokemon: &Pokemon, trainers: &[Trainer]) -> bool {
+    let owner = trainers.iter().find(|t| t.owns(pokemon.id));
+    viewer.can_list(pokemon, owner.map(|t| t.id)) || viewer.granted(pokemon.id, owner).is_some()
+}
+
+impl AppState{
+    // copies of the given Pokemon in order, so battles don't hold the lock
+    // a 404 for any the viewer can't see, like visible_pokemon
+    pub fn pick_team(&self, viewer: Viewer, ids: &[u32]) -> Result<Vec<Pokemon>, ApiError> {
+        ids.iter()
+            .map(|id| self.visible_pokemon(viewer, *id).ok_or_else(|| ApiError::not_found(format!("pokemon {} not found", id))))
+            .collect()
+    }
+
+    // the same, with a 403 for any the viewer can't change, like writable_pokemon
+    pub fn pick_writable_team(&self, viewer: Viewer, ids: &[u32]) -> Result<Vec<Pokemon>, ApiError> {
+        ids.iter().map(|id| self.writable_pokemon(viewer, *id)).collect()
+    }
+
+    // a copy of the Pokemon if the viewer is allowed to open it, None if it's private or doesn't exist
+    pub fn visible_pokemon(&self, viewer: Viewer, id: u32) -> Option<Pokemon> {
+        let team = self.team.lock().unwrap();
+        let trainers = self.trainers.lock().unwrap();
+        let pokemon = team.iter().find(|p| p.id == id)?;
+        let owner = trainers.iter().find(|t| t.owns(id));
+        let open = viewer.can_open(pokemon, owner.map(|t| t.id)) || viewer.granted(id, owner).is_some();
+        open.then(|| pokemon.clone())
+    }
+
+    // a 404 when the viewer can't see it, like visible_pokemon, and a 403 when they can't change it
+    pub fn writable_pokemon(&self, viewer: Viewer, id: u32) -> Result<Pokemon, ApiError> {
+        let pokemon = self.visible_pokemon(viewer, id)
+            .ok_or_else(|| ApiError::not_found(format!("pokemon {} not found", id)))?;
+        let trainers = self.trainers.lock().unwrap();
+        let owner = trainers.iter().find(|t| t.owns(id));
+        match owner {
+            Some(owner) if !viewer.can_write(id, Some(owner)) => Err(ApiError::forbidden(match viewer.0 {
+                Some(trainer_id) => format!("{} hasn't let trainer {} change {}", owner.name, trainer_id, pokemon.name),
+                None => format!("{} belongs to {}, say which trainer is asking to change it", pokemon.name, owner.name),
+            })),
+            _ => Ok(pokemon),
+        }
+    }
+
+    // copies of the Pokemon that show up in lists for the viewer
+    pub fn listed_team(&self, viewer: Viewer) -> Vec<Pokemon> {
+        let team = self.team.lock().unwrap();
+        let trainers = self.trainers.lock().unwrap();
+        team.iter()
+            .filter(|p| listed(viewer, p, &trainers))
+            .cloned()
+            .collect()
+    }
+
+    // where the Pokemon the viewer would see listed are and their IDs, for lists too long to copy
+    pub fn listed_positions(&self, viewer: Viewer, keep: impl Fn(&Pokemon) -> bool) -> Vec<(usize, u32)> {
+        let team = self.team.lock().unwrap();
+        let trainers = self.trainers.lock().unwrap();
+        team.iter().enumerate()
+            .filter(|(_, p)| listed(viewer, p, &trainers) && keep(p))
+            .map(|(i, p)| (i, p.id))
+            .collect()
+    }
+
+    // a copy of the Pokemon at a position from listed_positions, or wherever it's moved to since
+    pub fn pokemon_at(&self, position: usize, id: u32) -> Option<Pokemon> {
+        let team = self.team.lock().unwrap();
+        team.get(position).filter(|p| p.id == id).or_else(|| team.iter().find(|p| p.id == id)).cloned()
+    }
+
+    // any one of the Pokemon the viewer would see listed, each equally likely
+    pub fn random_pokemon(&self, viewer: Viewer, query: &RandomQuery) -> Option<Pokemon> {
+        let has_type = |p: &Pokemon, t: &str| {
+            p.poke_type.eq_ignore_ascii_case(t) || p.secondary_type.as_deref().is_some_and(|s| s.eq_ignore_ascii_case(t))
+        };
+        let team = self.listed_team(viewer);
+        let matching: Vec<&Pokemon> = team.iter()
+            .filter(|p| query.poke_type.as_deref().is_none_or(|t| has_type(p, t)))
+            .filter(|p| query.min_level.is_none_or(|min| p.level >= min))
+            .filter(|p| query.max_level.is_none_or(|max| p.level <= max))
+            .collect();
+        matching.choose(&mut self.randomness.rng()).map(|p| (*p).clone())
+    }
+
+    // keep a finished battle as a replay
+    pub fn record_battle(&self, seed: u64, outcome: BattleOutcome) -> BattleRecord {
+        let mut battles = self.battles.lock().unwrap();
+        let new_id = battles.last().map_or(1, |b| b.id + 1);
+        let record = BattleRecord { id: new_id, seed, outcome };
+        battles.push(record.clone());
+        drop(battles);
+        self.responses.invalidate("battles", None);
+        inbox::battle_result(self, &record);
+        record
+    }
+}
+
+pub type SharedState = Arc<AppState>;
@@ -0,0 +1,21 @@
+use std::time::Duration;
+
+use crate::state::SharedState;
+
+pub use pokemon_models::status::*;
+
+// how often the scheduler applies status effects outside of battle
+pub const TICK_INTERVAL: Duration = Duration::from_secs(30);
+
+// status conditions keep ticking while Pokemon sit in the box
+pub fn tick(state: &SharedState) {
+    let mut team = state.team.lock().unwrap();
+    let mut rng = state.randomness.rng();
+    for pokemon in team.iter_mut() {
+        end_of_turn(pokemon, false);
+        // counts down sleep and rolls for thawing, same as a battle turn
+        let _ = before_move(pokemon, &mut rng);
+    }
+    drop(team);
+    state.responses.invalidate("pokemon", None);
+}
@@ -0,0 +1,61 @@
+use std::convert::Infallible;
+
+use axum::body::{Body, Bytes};
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+
+// lists longer than this are streamed, shorter ones go out as one body so the caches can keep them
+pub const STREAM_OVER: usize = 1000;
+
+// how much is serialized before it's handed to the connection
+const CHUNK_SIZE: usize = 64 * 1024;
+
+struct Chunks<K, F>{
+    keys: std::vec::IntoIter<K>,
+    fetch: F,
+    started: bool,
+    wrote_any: bool,
+    done: bool,
+}
+
+impl<K, T: Serialize, F: FnMut(K) -> Option<T>> Iterator for Chunks<K, F>{
+    type Item = Result<Bytes, Infallible>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let mut chunk = Vec::with_capacity(CHUNK_SIZE + 1024);
+        if !self.started {
+            chunk.push(b'[');
+            self.started = true;
+        }
+        while chunk.len() < CHUNK_SIZE {
+            let Some(key) = self.keys.next() else {
+                chunk.push(b']');
+                self.done = true;
+                break;
+            };
+            let Some(item) = (self.fetch)(key) else { continue };
+            if self.wrote_any {
+                chunk.push(b',');
+            }
+            serde_json::to_writer(&mut chunk, &item).expect("state always serializes");
+            self.wrote_any = true;
+        }
+        Some(Ok(Bytes::from(chunk)))
+    }
+}
+
+// a JSON array written out a chunk at a time as the body is sent, so only one chunk is ever in memory
+// each item is fetched when it's its turn, and fetch gives None for one that's gone since the keys were taken
+pub fn array<K, T, F>(keys: Vec<K>, fetch: F) -> Response
+where
+    K: Send + 'static,
+    T: Serialize,
+    F: FnMut(K) -> Option<T> + Send + 'static,
+{
+    let chunks = Chunks { keys: keys.into_iter(), fetch, started: false, wrote_any: false, done: false };
+    ([(header::CONTENT_TYPE, "application/json")], Body::from_stream(futures_util::stream::iter(chunks))).into_response()
+}
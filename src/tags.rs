@@ -0,0 +1,69 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::models::Pokemon;
+
+pub const MAX_TAGS: usize = 20;
+pub const MAX_LENGTH: usize = 30;
+
+#[derive(Debug, Serialize)]
+pub struct TagCount{
+    pub tag: String,
+    pub count: usize,
+}
+
+// tags are compared lowercase, so "Shiny Hunt" and "shiny hunt" are the same tag
+pub fn normalize(tag: &str) -> Result<String, String> {
+    let tag = tag.trim().to_lowercase();
+    if tag.is_empty() {
+        return Err("tags can't be empty".to_string());
+    }
+    if tag.chars().count() > MAX_LENGTH {
+        return Err(format!("tags can be up to {} characters", MAX_LENGTH));
+    }
+    if tag.chars().any(char::is_control) {
+        return Err("tags can't have control characters".to_string());
+    }
+    Ok(tag)
+}
+
+// adds the tags it doesn't have yet, keeping them sorted
+pub fn add(pokemon: &mut Pokemon, tags: &[String]) -> Result<(), String> {
+    let mut merged = pokemon.tags.clone();
+    merged.extend(tags.iter().cloned());
+    merged.sort();
+    merged.dedup();
+    if merged.len() > MAX_TAGS {
+        return Err(format!("a pokemon can have up to {} tags", MAX_TAGS));
+    }
+    pokemon.tags = merged;
+    Ok(())
+}
+
+// every tag in use, most used first
+pub fn counts(team: &[Pokemon]) -> Vec<TagCount> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for tag in team.iter().flat_map(|p| &p.tags) {
+        *counts.entry(tag).or_default() += 1;
+    }
+    let mut counts: Vec<TagCount> = counts.into_iter()
+        .map(|(tag, count)| TagCount { tag: tag.to_string(), count })
+        .collect();
+    // stable, so ties stay alphabetical
+    counts.sort_by_key(|c| std::cmp::Reverse(c.count));
+    counts
+}
+
+// swaps the `from` tags for `into` on every Pokemon `may_change` lets through, returns how many changed
+pub fn merge(team: &mut [Pokemon], from: &[String], into: &str, may_change: impl Fn(&Pokemon) -> bool) -> usize {
+    let mut changed = 0;
+    for pokemon in team.iter_mut().filter(|p| p.tags.iter().any(|t| from.contains(t)) && may_change(p)) {
+        pokemon.tags.retain(|t| !from.contains(t));
+        pokemon.tags.push(into.to_string());
+        pokemon.tags.sort();
+        pokemon.tags.dedup();
+        changed += 1;
+    }
+    changed
+}
@@ -0,0 +1,257 @@
+use rand::{rngs::StdRng, SeedableRng};
+use serde::Serialize;
+
+use crate::achievements::{self, Event};
+use crate::battle::{self, Side};
+use crate::matchmaking::battle_ready;
+use crate::models::Pokemon;
+pub use crate::models::TournamentMode;
+use crate::session::{self, SessionStatus};
+use crate::state::SharedState;
+use crate::viewer::Viewer;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TournamentStatus{
+    Registering,
+    InProgress,
+    Finished,
+}
+
+// one pairing in the bracket, trainer_a is always the higher seed
+#[derive(Debug, Clone, Serialize)]
+pub struct BracketMatch{
+    pub trainer_a: u32,
+    // no opponent means a bye, trainer_a goes through
+    pub trainer_b: Option<u32>,
+    pub winner: Option<u32>,
+    pub battle_id: Option<u32>,
+    // interactive mode only: where the two trainers battle
+    pub session_id: Option<u32>,
+}
+
+// single elimination, entrants are seeded in registration order
+#[derive(Debug, Clone, Serialize)]
+pub struct Tournament{
+    pub id: u32,
+    pub name: String,
+    pub mode: TournamentMode,
+    pub status: TournamentStatus,
+    // trainer IDs
+    pub entrants: Vec<u32>,
+    pub champion: Option<u32>,
+    // the bracket, see GET /tournaments/{id}/bracket
+    #[serde(skip)]
+    pub rounds: Vec<Vec<BracketMatch>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Round{
+    pub round: u32,
+    pub matches: Vec<BracketMatch>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Standing{
+    pub trainer_id: u32,
+    pub wins: u32,
+    // round the trainer lost in, None while still in it (or for the champion)
+    pub eliminated_in: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Bracket{
+    pub id: u32,
+    pub name: String,
+    pub status: TournamentStatus,
+    pub champion: Option<u32>,
+    pub rounds: Vec<Round>,
+    // best first: most wins, then who lasted longest
+    pub standings: Vec<Standing>,
+}
+
+// seed order for a bracket of the given size, so the top seeds only meet late: 1, 8, 4, 5, 2, 7, 3, 6
+fn seed_order(size: usize) -> Vec<usize> {
+    let mut order = vec![1];
+    while order.len() < size {
+        let len = order.len();
+        order = order.iter().flat_map(|s| [*s, 2 * len + 1 - s]).collect();
+    }
+    order
+}
+
+impl Tournament{
+    pub fn new(id: u32, name: String, mode: TournamentMode) -> Tournament {
+        Tournament {
+            id,
+            name,
+            mode,
+            status: TournamentStatus::Registering,
+            entrants: Vec::new(),
+            champion: None,
+            rounds: Vec::new(),
+        }
+    }
+
+    // close registration and build the first round, top seeds get the byes
+    pub fn start(&mut self) {
+        let size = self.entrants.len().next_power_of_two();
+        let seeded = |seed: usize| self.entrants.get(seed - 1).copied();
+        let first_round = seed_order(size)
+            .chunks(2)
+            .map(|pair| {
+                let (trainer_a, trainer_b) = (seeded(pair[0]).unwrap(), seeded(pair[1]));
+                BracketMatch {
+                    trainer_a,
+                    trainer_b,
+                    winner: trainer_b.is_none().then_some(trainer_a),
+                    battle_id: None,
+                    session_id: None,
+                }
+            })
+            .collect();
+        self.rounds.push(first_round);
+        self.status = TournamentStatus::InProgress;
+        self.advance();
+    }
+
+    // once every match in the current round has a winner, pair the winners up for the next one
+    fn advance(&mut self) {
+        let Some(current) = self.rounds.last() else { return };
+        let winners: Option<Vec<u32>> = current.iter().map(|m| m.winner).collect();
+        let Some(winners) = winners else { return };
+
+        if let [champion] = winners[..] {
+            self.champion = Some(champion);
+            self.status = TournamentStatus::Finished;
+            return;
+        }
+        let next_round = winners
+            .chunks(2)
+            .map(|pair| BracketMatch {
+                trainer_a: pair[0],
+                trainer_b: Some(pair[1]),
+                winner: None,
+                battle_id: None,
+                session_id: None,
+            })
+            .collect();
+        self.rounds.push(next_round);
+    }
+
+    pub fn bracket(&self) -> Bracket {
+        let mut standings: Vec<Standing> = self.entrants.iter()
+            .map(|trainer_id| Standing { trainer_id: *trainer_id, wins: 0, eliminated_in: None })
+            .collect();
+        for (round, matches) in self.rounds.iter().enumerate() {
+            for m in matches {
+                let Some(winner) = m.winner else { continue };
+                // byes don't count as wins
+                if let Some(trainer_b) = m.trainer_b {
+                    let loser = if winner == m.trainer_a { trainer_b } else { m.trainer_a };
+                    for standing in standings.iter_mut() {
+                        if standing.trainer_id == winner {
+                            standing.wins += 1;
+                        } else if standing.trainer_id == loser {
+                            standing.eliminated_in = Some(round as u32 + 1);
+                        }
+                    }
+                }
+            }
+        }
+        let lasted = |s: &Standing| s.eliminated_in.unwrap_or(u32::MAX);
+        standings.sort_by(|a, b| b.wins.cmp(&a.wins).then(lasted(b).cmp(&lasted(a))));
+
+        Bracket {
+            id: self.id,
+            name: self.name.clone(),
+            status: self.status,
+            champion: self.champion,
+            rounds: self.rounds.iter()
+                .enumerate()
+                .map(|(i, matches)| Round { round: i as u32 + 1, matches: matches.clone() })
+                .collect(),
+            standings,
+        }
+    }
+}
+
+// copies of a trainer's team, or None if it can't battle (which forfeits the match)
+fn ready_team(state: &SharedState, trainer_id: u32) -> Option<Vec<Pokemon>> {
+    let ids = {
+        let trainers = state.trainers.lock().unwrap();
+        trainers.iter().find(|t| t.id == trainer_id)?.team.clone()
+    };
+    state.pick_team(Viewer(Some(trainer_id)), &ids).ok().filter(|team| battle_ready(team))
+}
+
+// draws go to the higher seed
+fn winner_of(m: &BracketMatch, trainer_b: u32, winner: Option<Side>) -> u32 {
+    match winner {
+        Some(Side::SideB) => trainer_b,
+        _ => m.trainer_a,
+    }
+}
+
+// play every undecided match in the current round, then move on if the round is complete
+// in interactive mode this opens the sessions the first time and collects their results on later calls
+// progress hears (played, of) after each match, for a background job to pass on
+pub fn play_round(state: &SharedState, tournament: &mut Tournament, mut progress: impl FnMut(usize, usize)) {
+    let mode = tournament.mode;
+    let Some(round) = tournament.rounds.last_mut() else { return };
+
+    let total = round.iter().filter(|m| m.winner.is_none()).count();
+    for (played, m) in round.iter_mut().filter(|m| m.winner.is_none()).enumerate() {
+        progress(played, total);
+        let Some(trainer_b) = m.trainer_b else { continue };
+
+        // an interactive match that already has a session: see how it went
+        if let Some(session_id) = m.session_id {
+            let session = state.sessions.lock().unwrap().iter().find(|s| s.id == session_id).cloned();
+            match session {
+                Some(session) if session.status == SessionStatus::Finished => {
+                    let battles = state.battles.lock().unwrap();
+                    let outcome = session.battle_id.and_then(|id| battles.iter().find(|b| b.id == id));
+                    m.battle_id = session.battle_id;
+                    let winner = winner_of(m, trainer_b, outcome.and_then(|b| b.outcome.winner));
+                    m.winner = Some(winner);
+                    achievements::record(state, winner, Event::BattleWon);
+                    continue;
+                }
+                // nobody showed up, open a fresh one below
+                Some(session) if session.status == SessionStatus::Cancelled => m.session_id = None,
+                _ => continue,
+            }
+        }
+
+        let (side_a, side_b) = match (ready_team(state, m.trainer_a), ready_team(state, trainer_b)) {
+            (Some(side_a), Some(side_b)) => (side_a, side_b),
+            // a trainer who can't field a team forfeits
+            (None, Some(_)) => {
+                m.winner = Some(trainer_b);
+                continue;
+            }
+            _ => {
+                m.winner = Some(m.trainer_a);
+                continue;
+            }
+        };
+
+        match mode {
+            TournamentMode::Simulated => {
+                let seed = state.randomness.seed();
+                let outcome = battle::run(battle::Battle::new(side_a, side_b).with_scripts(state.scripts.clone()), &mut StdRng::seed_from_u64(seed));
+                let record = state.record_battle(seed, outcome);
+                m.battle_id = Some(record.id);
+                let winner = winner_of(m, trainer_b, record.outcome.winner);
+                m.winner = Some(winner);
+                achievements::record(state, winner, Event::BattleWon);
+            }
+            TournamentMode::Interactive => {
+                m.session_id = Some(session::create(state, side_a, side_b).id);
+            }
+        }
+    }
+    progress(total, total);
+    tournament.advance();
+}
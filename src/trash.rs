@@ -0,0 +1,50 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::models::Pokemon;
+use crate::state::SharedState;
+use crate::viewer::Viewer;
+
+// deleted Pokemon are kept this long before they're gone for good, TRASH_TTL_SECS changes it
+pub const DEFAULT_TTL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+pub const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+// a deleted Pokemon and who had it, so a restore can hand it back
+#[derive(Debug, Clone, Serialize)]
+pub struct TrashedPokemon{
+    pub pokemon: Pokemon,
+    pub trainer_id: Option<u32>,
+    // unix seconds
+    pub deleted_at: u64,
+}
+
+impl TrashedPokemon{
+    pub fn new(pokemon: Pokemon, trainer_id: Option<u32>, deleted_at: u64) -> TrashedPokemon {
+        TrashedPokemon { pokemon, trainer_id, deleted_at }
+    }
+}
+
+pub fn ttl_from_env() -> Duration {
+    std::env::var("TRASH_TTL_SECS").ok()
+        .and_then(|secs| secs.parse().ok())
+        .map_or(DEFAULT_TTL, Duration::from_secs)
+}
+
+// empties anything deleted more than `ttl` ago, returns how many went
+pub fn purge_older_than(state: &SharedState, ttl: Duration) -> usize {
+    let cutoff = state.time.now().saturating_sub(ttl.as_secs());
+    let mut trash = state.trash.lock().unwrap();
+    let before = trash.len();
+    trash.retain(|t| t.deleted_at > cutoff);
+    before - trash.len()
+}
+
+// empties what was the viewer's, and what nobody owned, returns how many went
+pub fn purge_for(state: &SharedState, viewer: Viewer) -> usize {
+    let mut trash = state.trash.lock().unwrap();
+    let before = trash.len();
+    trash.retain(|t| t.trainer_id.is_some() && t.trainer_id != viewer.0);
+    before - trash.len()
+}
@@ -0,0 +1,97 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Query, Request},
+    http::{header, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use crate::error::ApiError;
+
+// a bit past the extremes in the games (Joltik at 0.1 m, Eternatus at 20 m, Celesteela at 999.9 kg)
+pub const HEIGHT_RANGE_M: (f64, f64) = (0.05, 25.0);
+pub const WEIGHT_RANGE_KG: (f64, f64) = (0.05, 1000.0);
+
+const INCHES_PER_METRE: f64 = 39.3701;
+const POUNDS_PER_KG: f64 = 2.20462;
+
+// heights and weights are kept in metric and converted on the way out, like the games do
+#[derive(Debug, Deserialize)]
+struct UnitsQuery{
+    units: Option<String>,
+}
+
+fn check(value: f64, (min, max): (f64, f64), what: &str, unit: &str) -> Result<(), String> {
+    if value.is_finite() && (min..=max).contains(&value) {
+        Ok(())
+    } else {
+        Err(format!("{} must be between {} and {} {}", what, min, max, unit))
+    }
+}
+
+pub fn check_height(height_m: f64) -> Result<(), String> {
+    check(height_m, HEIGHT_RANGE_M, "height", "m")
+}
+
+pub fn check_weight(weight_kg: f64) -> Result<(), String> {
+    check(weight_kg, WEIGHT_RANGE_KG, "weight", "kg")
+}
+
+fn round(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+// height_m -> height_in and weight_kg -> weight_lb, wherever they turn up
+fn to_imperial(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            convert(fields, "height_m", "height_in", INCHES_PER_METRE);
+            convert(fields, "weight_kg", "weight_lb", POUNDS_PER_KG);
+            fields.values_mut().for_each(to_imperial);
+        }
+        Value::Array(items) => items.iter_mut().for_each(to_imperial),
+        _ => {}
+    }
+}
+
+fn convert(fields: &mut Map<String, Value>, from: &str, to: &str, factor: f64) {
+    if let Some(metric) = fields.get(from).and_then(Value::as_f64) {
+        fields.remove(from);
+        fields.insert(to.to_string(), json!(round(metric * factor)));
+    }
+}
+
+// middleware for ?units=imperial on GET requests, anything else passes straight through
+pub async fn convert_responses(request: Request, next: Next) -> Response {
+    if request.method() != Method::GET {
+        return next.run(request).await;
+    }
+    let units = Query::<UnitsQuery>::try_from_uri(request.uri()).ok().and_then(|q| q.0.units);
+    match units.as_deref() {
+        None | Some("metric") => return next.run(request).await,
+        Some("imperial") => {}
+        Some(other) => {
+            return ApiError::bad_request(format!("units can be metric or imperial, not {}", other)).into_response();
+        }
+    }
+
+    let response = next.run(request).await;
+    let is_json = response.headers().get(header::CONTENT_TYPE)
+        .is_some_and(|v| v.as_bytes().starts_with(b"application/json"));
+    if !is_json {
+        return response;
+    }
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = to_bytes(body, usize::MAX).await else {
+        return Response::from_parts(parts, Body::empty());
+    };
+    let Ok(mut value) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    to_imperial(&mut value);
+    let converted = serde_json::to_vec(&value).unwrap_or_else(|_| bytes.to_vec());
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(converted))
+}
@@ -0,0 +1,42 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use serde::Serialize;
+
+// everything is kept in memory, snapshots and their S3 copies are backups of it rather than where it lives
+pub const STORAGE: &str = "memory";
+
+// which build this is, all of it fixed at compile time by build.rs
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildInfo{
+    pub name: &'static str,
+    pub version: &'static str,
+    // "unknown" when it was built without git
+    pub git_sha: &'static str,
+    // unix seconds
+    pub built_at: u64,
+    pub rustc: &'static str,
+    // the cargo features it was built with, e.g. ["chaos", "sentry"]
+    pub features: Vec<&'static str>,
+    pub storage: &'static str,
+}
+
+pub fn info() -> BuildInfo {
+    BuildInfo {
+        name: env!("CARGO_PKG_NAME"),
+        version: env!("CARGO_PKG_VERSION"),
+        git_sha: env!("BUILD_GIT_SHA"),
+        built_at: env!("BUILD_TIMESTAMP").parse().unwrap_or_default(),
+        rustc: env!("BUILD_RUSTC"),
+        features: env!("BUILD_FEATURES").split(',').filter(|f| !f.is_empty()).collect(),
+        storage: STORAGE,
+    }
+}
+
+// the line the binary prints first, e.g. "rust-hello-http 0.1.0 (1a2b3c4d, built Wed, 14 Oct 2026 09:30:00 GMT, features: chaos)"
+pub fn banner() -> String {
+    let info = info();
+    let sha = &info.git_sha[..info.git_sha.len().min(8)];
+    let built = httpdate::fmt_http_date(UNIX_EPOCH + Duration::from_secs(info.built_at));
+    let features = if info.features.is_empty() { "none".to_string() } else { info.features.join(", ") };
+    format!("{} {} ({}, built {}, features: {}, storage: {})", info.name, info.version, sha, built, features, info.storage)
+}
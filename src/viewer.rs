@@ -0,0 +1,69 @@
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use axum::http::{header, HeaderMap, StatusCode};
+
+use crate::error::ApiError;
+use crate::models::{Access, Pokemon, Trainer, Visibility};
+use crate::state::SharedState;
+
+// the trainer making the request, None for anyone else
+// a session from logging in proves it, X-Trainer-Id is taken on trust
+// so until TRUST_TRAINER_HEADER is off, the rules below only hold for clients that don't lie about who they are
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Viewer(pub Option<u32>);
+
+// the token in `Authorization: Bearer <token>`
+pub fn bearer(headers: &HeaderMap) -> Option<&str> {
+    headers.get(header::AUTHORIZATION)?.to_str().ok()?.strip_prefix("Bearer ").map(str::trim)
+}
+
+impl FromRequestParts<SharedState> for Viewer{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &SharedState) -> Result<Self, Self::Rejection> {
+        if let Some(token) = bearer(&parts.headers) {
+            return state.auth.verify(token, state.time.now())
+                .map(|id| Viewer(Some(id)))
+                .map_err(|e| ApiError::new(StatusCode::UNAUTHORIZED, e));
+        }
+        let Some(value) = parts.headers.get("x-trainer-id").filter(|_| state.auth.trusts_header()) else {
+            return Ok(Viewer(None));
+        };
+        value.to_str().ok()
+            .and_then(|v| v.trim().parse().ok())
+            .map(|id| Viewer(Some(id)))
+            .ok_or_else(|| ApiError::bad_request("X-Trainer-Id should be a trainer ID"))
+    }
+}
+
+// owner is the trainer with the Pokemon on their team or in their box, a Pokemon nobody owns is open to everyone
+impl Viewer{
+    // opening one Pokemon by its ID
+    pub fn can_open(&self, pokemon: &Pokemon, owner: Option<u32>) -> bool {
+        owner.is_none() || owner == self.0 || pokemon.visibility != Visibility::Private
+    }
+
+    // lists, stats and random picks, which leave out unlisted Pokemon too
+    pub fn can_list(&self, pokemon: &Pokemon, owner: Option<u32>) -> bool {
+        owner.is_none() || owner == self.0 || pokemon.visibility == Visibility::Public
+    }
+
+    // what the owner has given the viewer on it, on the Pokemon itself or the box it's in
+    pub fn granted(&self, pokemon_id: u32, owner: Option<&Trainer>) -> Option<Access> {
+        owner?.granted(self.0?, pokemon_id)
+    }
+
+    // a 403 unless the request is from trainer `id`, for anything done to or as that trainer
+    pub fn require(&self, id: u32) -> Result<(), ApiError> {
+        match self.0 {
+            Some(trainer_id) if trainer_id == id => Ok(()),
+            Some(trainer_id) => Err(ApiError::forbidden(format!("only trainer {} can do that, not trainer {}", id, trainer_id))),
+            None => Err(ApiError::forbidden(format!("only trainer {} can do that, say which trainer is asking", id))),
+        }
+    }
+
+    // changing it, only the owner or a trainer they've given write access, anyone for a Pokemon nobody owns
+    pub fn can_write(&self, pokemon_id: u32, owner: Option<&Trainer>) -> bool {
+        owner.is_none_or(|o| Some(o.id) == self.0) || self.granted(pokemon_id, owner) == Some(Access::Write)
+    }
+}
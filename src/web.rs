@@ -0,0 +1,124 @@
+use maud::{html, Markup, DOCTYPE};
+
+use crate::csrf::{self, CsrfToken};
+use crate::models::Pokemon;
+
+// plain server-rendered pages for people who'd rather not use curl, the forms post back to /web/...
+fn layout(title: &str, body: Markup) -> Markup {
+    html! {
+        (DOCTYPE)
+        html lang="en" {
+            head {
+                meta charset="utf-8";
+                meta name="viewport" content="width=device-width, initial-scale=1";
+                title { (title) }
+                style { (STYLE) }
+            }
+            body {
+                header { a href="/web/pokemon" { h1 { "Pokemon Team" } } }
+                main { (body) }
+            }
+        }
+    }
+}
+
+const STYLE: &str = "
+body { font-family: system-ui, sans-serif; max-width: 48rem; margin: 0 auto; padding: 0 1rem; }
+header a { color: inherit; text-decoration: none; }
+table { width: 100%; border-collapse: collapse; }
+th, td { text-align: left; padding: 0.4rem; border-bottom: 1px solid #ddd; }
+form { display: flex; flex-wrap: wrap; gap: 0.5rem; margin: 1rem 0; }
+.error { color: #b00020; }
+";
+
+fn error_message(error: Option<&str>) -> Markup {
+    html! {
+        @if let Some(error) = error {
+            p.error { (error) }
+        }
+    }
+}
+
+fn types(pokemon: &Pokemon) -> String {
+    match &pokemon.secondary_type {
+        Some(secondary) => format!("{} / {}", pokemon.poke_type, secondary),
+        None => pokemon.poke_type.clone(),
+    }
+}
+
+// every form posts this back, see csrf.rs
+fn csrf_field(token: &CsrfToken) -> Markup {
+    html! { input type="hidden" name=(csrf::FIELD) value=(token.0); }
+}
+
+// the whole team, and a form to add one more
+pub fn list_page(team: &[Pokemon], error: Option<&str>, token: &CsrfToken) -> Markup {
+    layout("Pokemon Team", html! {
+        (error_message(error))
+        h2 { "Team" }
+        table {
+            thead { tr { th { "ID" } th { "Name" } th { "Type" } th { "Level" } th { "HP" } } }
+            tbody {
+                @for p in team {
+                    tr {
+                        td { (p.id) }
+                        td { a href={ "/web/pokemon/" (p.id) } { (p.name) } }
+                        td { (types(p)) }
+                        td { (p.level) }
+                        td { (p.current_hp) "/" (p.max_hp) }
+                    }
+                }
+                @if team.is_empty() {
+                    tr { td colspan="5" { "No Pokemon yet" } }
+                }
+            }
+        }
+        h2 { "Add a Pokemon" }
+        form method="post" action="/web/pokemon" {
+            (csrf_field(token))
+            input name="name" placeholder="Name" required;
+            input name="poke_type" placeholder="Type";
+            input name="secondary_type" placeholder="Second type";
+            input name="level" type="number" min="1" max="100" value="5" required;
+            button { "Add" }
+        }
+    })
+}
+
+// one Pokemon's details, with a form to edit it and a button to delete it
+pub fn edit_page(pokemon: &Pokemon, error: Option<&str>, token: &CsrfToken) -> Markup {
+    layout(&pokemon.name, html! {
+        (error_message(error))
+        h2 {
+            (pokemon.name) " "
+            @if pokemon.nickname.is_some() { "(" (pokemon.species) ") " }
+            small { "#" (pokemon.id) }
+        }
+        p { "Level " (pokemon.level) " " (types(pokemon)) ", HP " (pokemon.current_hp) "/" (pokemon.max_hp) }
+        p { "Moves: " (pokemon.moves.join(", ")) }
+        h3 { "Edit" }
+        form method="post" action={ "/web/pokemon/" (pokemon.id) } {
+            (csrf_field(token))
+            input name="name" value=(pokemon.species) required;
+            input name="poke_type" value=(pokemon.poke_type);
+            input name="secondary_type" placeholder="Second type" value=[pokemon.secondary_type.as_deref()];
+            input name="level" type="number" min="1" max="100" value=(pokemon.level) required;
+            button { "Save" }
+        }
+        form method="post" action={ "/web/pokemon/" (pokemon.id) "/delete" } {
+            (csrf_field(token))
+            button { "Delete" }
+        }
+    })
+}
+
+pub fn not_found_page(message: &str) -> Markup {
+    error_page("Not found", message)
+}
+
+pub fn error_page(title: &str, message: &str) -> Markup {
+    layout(title, html! {
+        p.error { (message) }
+        a href="/web/pokemon" { "Back to the team" }
+    })
+}
@@ -0,0 +1,537 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::{id, TestApp};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+use rust_hello_http::{backups::Backups, connections::Connections, console, deterministic::Time, models::Trainer, encryption::{Keyring, Keys}, flags::Flags, memory::{self, Memory}, runtime::{self, RuntimeConfig}, scheduler::{self, Scheduler}, slo::{self, Slos}};
+use serde_json::{json, Value};
+use std::io::Write;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+// like the binary, for the allocator stats
+#[global_allocator]
+static ALLOCATOR: memory::CountingAllocator = memory::CountingAllocator;
+
+#[tokio::test]
+async fn admin_needs_the_token() {
+    let app = TestApp::new();
+    let response = app.get("/admin/check").await.assert_status(StatusCode::UNAUTHORIZED);
+    assert_eq!(response.header("www-authenticate").unwrap(), "Bearer");
+    app.request(Method::GET, "/admin/check").header("authorization", "Bearer wrong").send().await
+        .assert_status(StatusCode::UNAUTHORIZED);
+    app.admin_get("/admin/check").await.assert_status(StatusCode::OK);
+
+    let off = TestApp::with(|builder| builder.admin_token(None));
+    off.admin_get("/admin/check").await.assert_error(StatusCode::FORBIDDEN, "admin API is off");
+}
+
+#[tokio::test]
+async fn export() {
+    let app = TestApp::new();
+    app.create_pokemon("Pikachu", 10).await;
+    let response = app.admin_get("/admin/export").await.assert_status(StatusCode::OK);
+    assert_eq!(response.header("x-schema-version").unwrap(), "1");
+    assert_eq!(response.header("content-type").unwrap(), "application/json");
+    let dump = response.json();
+    assert_eq!(dump["schema_version"], 1);
+    assert_eq!(dump["pokemon"].as_array().unwrap().len(), 1);
+
+    let response = app.admin_get("/admin/export?format=ndjson").await.assert_status(StatusCode::OK);
+    assert_eq!(response.header("content-type").unwrap(), "application/x-ndjson");
+    let lines: Vec<Value> = response.text().lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(lines[0]["type"], "header");
+    assert_eq!(lines[1]["type"], "pokemon");
+    app.admin_get("/admin/export?format=xml").await.assert_error(StatusCode::BAD_REQUEST, "only json or ndjson");
+}
+
+#[tokio::test]
+async fn resumable_export() {
+    let app = TestApp::new();
+    app.create_pokemon("Pikachu", 10).await;
+    let whole = app.admin_get("/admin/export").await.assert_status(StatusCode::OK);
+    assert_eq!(whole.header("accept-ranges").unwrap(), "bytes");
+    let etag = whole.header("etag").unwrap().to_str().unwrap().to_string();
+    let len = whole.body.len();
+
+    // picking up after the first 10 bytes, a second later the same data still exports the same
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let rest = app.request(Method::GET, "/admin/export").admin().header("range", "bytes=10-").header("if-range", &etag).send().await
+        .assert_status(StatusCode::PARTIAL_CONTENT);
+    assert_eq!(rest.header("content-range").unwrap().to_str().unwrap(), format!("bytes 10-{}/{}", len - 1, len));
+    assert_eq!([&whole.body[..10], &rest.body[..]].concat(), whole.body);
+    let middle = app.request(Method::GET, "/admin/export").admin().header("range", "bytes=1-3").send().await
+        .assert_status(StatusCode::PARTIAL_CONTENT);
+    assert_eq!(&middle.body[..], &whole.body[1..4]);
+    let past = app.request(Method::GET, "/admin/export").admin().header("range", &format!("bytes={}-", len)).send().await
+        .assert_status(StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(past.header("content-range").unwrap().to_str().unwrap(), format!("bytes */{}", len));
+
+    // once the data changes the old ETag no longer matches, so it starts over
+    app.create_pokemon("Eevee", 5).await;
+    let changed = app.request(Method::GET, "/admin/export").admin().header("range", "bytes=10-").header("if-range", &etag).send().await
+        .assert_status(StatusCode::OK);
+    assert_ne!(changed.header("etag").unwrap().to_str().unwrap(), etag);
+    assert!(changed.text().contains("Eevee"));
+    // several ranges at once aren't supported, they get the whole thing
+    app.request(Method::GET, "/admin/export").admin().header("range", "bytes=0-1,5-6").send().await.assert_status(StatusCode::OK);
+}
+
+#[tokio::test]
+async fn import() {
+    let from = TestApp::new();
+    from.create_pokemon("Pikachu", 10).await;
+    let dump = from.admin_get("/admin/export").await.json();
+
+    let app = TestApp::new();
+    let report = app.admin_post("/admin/import", dump.clone()).await.assert_status(StatusCode::OK).json();
+    assert_eq!(report["applied"], true);
+    assert_eq!(report["records"][0]["outcome"], "created");
+    assert_eq!(app.get("/pokemon/1").await.json()["name"], "Pikachu");
+
+    // the default strategy is fail, so nothing goes in a second time
+    let report = app.admin_post("/admin/import", dump.clone()).await.assert_status(StatusCode::CONFLICT).json();
+    assert_eq!(report["applied"], false);
+    app.admin_post("/admin/import?strategy=skip", dump.clone()).await.assert_status(StatusCode::OK);
+
+    let mut newer = dump;
+    newer["schema_version"] = json!(99);
+    app.admin_post("/admin/import", newer).await.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    app.request(Method::POST, "/admin/import").admin().text("not a dump").send().await.assert_status(StatusCode::BAD_REQUEST);
+}
+
+fn gzip(bytes: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(bytes).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[tokio::test]
+async fn compressed_uploads() {
+    let from = TestApp::new();
+    from.create_pokemon("Pikachu", 10).await;
+    let dump = from.admin_get("/admin/export").await.body;
+
+    let app = TestApp::new();
+    app.request(Method::POST, "/admin/import").admin().header("content-encoding", "gzip").body("application/json", gzip(&dump)).send().await
+        .assert_status(StatusCode::OK);
+    assert_eq!(app.get("/pokemon/1").await.json()["name"], "Pikachu");
+
+    let mut deflated = ZlibEncoder::new(Vec::new(), Compression::default());
+    deflated.write_all(b"Pikachu\n- Thunderbolt").unwrap();
+    app.request(Method::POST, "/pokemon/import").header("content-encoding", "deflate").body("text/plain", deflated.finish().unwrap()).send().await
+        .assert_status(StatusCode::CREATED);
+
+    // a few hundred bytes that decompress to 10 MB are cut off at the limit
+    let bomb = gzip(&vec![b' '; 10 * 1024 * 1024]);
+    assert!(bomb.len() < 64 * 1024);
+    app.request(Method::POST, "/pokemon/import").header("content-encoding", "gzip").body("text/plain", bomb).send().await
+        .assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+    app.request(Method::POST, "/pokemon/import").header("content-encoding", "br").text("Pikachu").send().await
+        .assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
+
+#[tokio::test]
+async fn snapshots_and_diffs() {
+    let app = TestApp::new();
+    assert_eq!(app.admin_get("/admin/snapshots").await.assert_status(StatusCode::OK).json(), json!([]));
+    let snapshot = app.admin_post("/admin/snapshots", json!({})).await.assert_status(StatusCode::CREATED).json();
+    let listed = app.admin_get("/admin/snapshots").await.json();
+    assert_eq!(listed[0]["file"], snapshot["file"]);
+
+    // an empty dump against the live data
+    let empty = app.admin_get("/admin/export").await.body;
+    app.create_pokemon("Pikachu", 10).await;
+    let diff = app.request(Method::POST, "/admin/diff").admin().multipart(&[("after", "application/json", &empty)]).send().await
+        .assert_status(StatusCode::OK)
+        .json();
+    assert_eq!(diff["pokemon"]["removed"], json!([1]));
+    app.request(Method::POST, "/admin/diff").admin().multipart(&[("before", "application/json", &empty)]).send().await
+        .assert_error(StatusCode::BAD_REQUEST, "no after field");
+    app.request(Method::POST, "/admin/diff").admin().multipart(&[("after", "application/json", b"nope")]).send().await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn encrypted_snapshots() {
+    let (a, b, c) = ("11".repeat(32), "22".repeat(32), "33".repeat(32));
+    assert!(Keyring::parse("abc").unwrap_err().contains("64 hex characters"));
+    assert!(Keyring::parse("# nothing but a comment\n").unwrap_err().contains("no keys"));
+    let keyring = Keyring::parse(&a).unwrap();
+    let mut sealed = keyring.encrypt(b"{}");
+    assert_eq!(keyring.decrypt(&sealed).unwrap(), b"{}");
+    *sealed.last_mut().unwrap() ^= 1;
+    assert!(keyring.decrypt(&sealed).unwrap_err().contains("changed"));
+
+    let dir = tempfile::tempdir().unwrap();
+    let key_file = dir.path().join("keys");
+    std::fs::write(&key_file, format!("{}\n", a)).unwrap();
+    let backups = Backups::in_dir(dir.path().join("snapshots")).encrypted(Keys::File(key_file.clone()));
+    let app = TestApp::with(|builder| builder.backups(backups).time(Time::frozen(1_700_000_000)));
+    // one from before encryption was turned on
+    std::fs::create_dir_all(dir.path().join("snapshots")).unwrap();
+    std::fs::write(dir.path().join("snapshots/snapshot-1600000000.json"), app.admin_get("/admin/export").await.body).unwrap();
+
+    app.create_pokemon("Pikachu", 10).await;
+    let snapshot = app.admin_post("/admin/snapshots", json!({})).await.assert_status(StatusCode::CREATED).json();
+    assert_eq!(snapshot["file"], "snapshot-1700000000.json.enc");
+    let first_key = snapshot["key_id"].as_str().unwrap().to_string();
+    let on_disk = std::fs::read(dir.path().join("snapshots/snapshot-1700000000.json.enc")).unwrap();
+    assert!(!String::from_utf8_lossy(&on_disk).contains("Pikachu"));
+    let dump = app.admin_get("/admin/snapshots/snapshot-1700000000.json.enc").await.assert_status(StatusCode::OK).json();
+    assert_eq!(dump["pokemon"][0]["name"], "Pikachu");
+    app.admin_get("/admin/snapshots/..%2Fkeys").await.assert_status(StatusCode::NOT_FOUND);
+
+    // a new key goes first, the old one stays to read what it encrypted
+    std::fs::write(&key_file, format!("{}\n{} # before the rotation\n", b, a)).unwrap();
+    let rekey = app.admin_post("/admin/snapshots/rekey", json!({})).await.assert_status(StatusCode::OK).json();
+    assert_ne!(rekey["key_id"], first_key.as_str());
+    assert_eq!(rekey["rekeyed"], json!(["snapshot-1700000000.json.enc", "snapshot-1600000000.json.enc"]));
+    let listed = app.admin_get("/admin/snapshots").await.json();
+    assert!(listed.as_array().unwrap().iter().all(|s| s["key_id"] == rekey["key_id"]), "{}", listed);
+    assert_eq!(app.admin_post("/admin/snapshots/rekey", json!({})).await.json()["unchanged"], 2);
+
+    // so the old key can go, but without the current one nothing can be read
+    std::fs::write(&key_file, &c).unwrap();
+    app.admin_get("/admin/snapshots/snapshot-1700000000.json.enc").await.assert_error(StatusCode::INTERNAL_SERVER_ERROR, "isn't one of the keys");
+    assert_eq!(app.admin_post("/admin/snapshots/rekey", json!({})).await.json()["failed"].as_array().unwrap().len(), 2);
+
+    TestApp::new().admin_post("/admin/snapshots/rekey", json!({})).await.assert_error(StatusCode::CONFLICT, "aren't encrypted");
+}
+
+#[tokio::test]
+async fn modes() {
+    let app = TestApp::new();
+    assert_eq!(app.admin_get("/admin/mode").await.assert_status(StatusCode::OK).json()["mode"], "normal");
+
+    app.request(Method::PUT, "/admin/mode").admin().json(json!({ "mode": "read_only" })).send().await.assert_status(StatusCode::OK);
+    app.post("/pokemon", json!({ "name": "Pikachu", "poke_type": "electric", "level": 5 })).await.assert_status(StatusCode::FORBIDDEN);
+    app.get("/pokemon").await.assert_status(StatusCode::OK);
+
+    let maintenance = json!({ "mode": "maintenance", "retry_after": 60, "message": "back soon" });
+    app.request(Method::PUT, "/admin/mode").admin().json(maintenance).send().await.assert_status(StatusCode::OK);
+    let response = app.get("/pokemon").await.assert_error(StatusCode::SERVICE_UNAVAILABLE, "back soon");
+    assert_eq!(response.header("retry-after").unwrap(), "60");
+    // the health check and the admin API keep working
+    app.get("/").await.assert_status(StatusCode::OK);
+    app.admin_get("/admin/mode").await.assert_status(StatusCode::OK);
+
+    app.request(Method::PUT, "/admin/mode").admin().json(json!({ "mode": "normal" })).send().await.assert_status(StatusCode::OK);
+    app.get("/pokemon").await.assert_status(StatusCode::OK);
+}
+
+#[tokio::test]
+async fn feature_flags() {
+    assert!(Flags::parse("battles=maybe").unwrap_err().contains("on or off"));
+    assert!(Flags::parse("gyms=off").unwrap_err().contains("battles, trading"));
+    let app = TestApp::with(|builder| builder.flags(Flags::parse("trading=off").unwrap()));
+    let flags = app.admin_get("/admin/flags").await.assert_status(StatusCode::OK).json();
+    assert_eq!(flags[0]["name"], "battles");
+    assert_eq!((flags[0]["enabled"].as_bool(), flags[1]["enabled"].as_bool()), (Some(true), Some(false)));
+
+    // turned off it's a 404, and so is everything under it
+    app.post("/trades", json!({})).await.assert_error(StatusCode::NOT_FOUND, "trade offers between trainers is turned off");
+    app.get("/trades/1").await.assert_error(StatusCode::NOT_FOUND, "turned off");
+    app.get("/trainers/1/trades").await.assert_error(StatusCode::NOT_FOUND, "turned off");
+    app.get("/battles").await.assert_status(StatusCode::OK);
+
+    let battles = app.request(Method::PUT, "/admin/flags/battles").admin().json(json!({ "enabled": false })).send().await
+        .assert_status(StatusCode::OK).json();
+    assert_eq!(battles["enabled"], false);
+    app.get("/battles").await.assert_error(StatusCode::NOT_FOUND, "turned off");
+    app.get("/matchmaking/1").await.assert_error(StatusCode::NOT_FOUND, "turned off");
+    // the rest of the API doesn't notice
+    app.get("/pokemon").await.assert_status(StatusCode::OK);
+
+    app.request(Method::PUT, "/admin/flags/trading").admin().json(json!({ "enabled": true })).send().await.assert_status(StatusCode::OK);
+    app.get("/trades/1").await.assert_error(StatusCode::NOT_FOUND, "not found");
+    app.request(Method::PUT, "/admin/flags/gyms").admin().json(json!({ "enabled": true })).send().await
+        .assert_error(StatusCode::NOT_FOUND, "unknown feature flag");
+}
+
+#[tokio::test]
+async fn seed_and_generate() {
+    let app = TestApp::new();
+    let report = app.admin_post("/admin/seed", json!({})).await.assert_status(StatusCode::CREATED).json();
+    assert_eq!(report["trainers_created"], 1);
+    // seeding twice changes nothing
+    let report = app.admin_post("/admin/seed", json!({})).await.assert_status(StatusCode::OK).json();
+    assert_eq!(report["pokemon_created"], 0);
+
+    let generated = app.admin_post("/admin/generate?count=5&seed=42", json!({})).await.assert_status(StatusCode::CREATED).json();
+    assert_eq!(generated["created"], 5);
+    assert_eq!(generated["seed"], 42);
+    let first = app.get(&format!("/pokemon/{}", generated["first_id"])).await.json();
+    assert!(first["tags"].as_array().unwrap().contains(&json!("generated")));
+    app.admin_post("/admin/generate?count=100001", json!({})).await.assert_error(StatusCode::BAD_REQUEST, "up to 100000");
+}
+
+#[tokio::test]
+async fn runtime_stats() {
+    let app = TestApp::new();
+    let stats = app.admin_get("/admin/runtime?window_ms=20").await.assert_status(StatusCode::OK).json();
+    let workers = stats["workers"].as_u64().unwrap();
+    assert!(workers >= 1);
+    assert_eq!(stats["worker_stats"].as_array().unwrap().len() as u64, workers);
+    assert!(stats["window_ms"].as_u64() >= Some(20));
+    // the one worker spent the window asleep waiting for it
+    assert_eq!(stats["blocked_workers"], 0);
+    app.admin_get("/admin/runtime?window_ms=60000").await.assert_error(StatusCode::BAD_REQUEST, "up to 5000");
+}
+
+#[test]
+fn runtime_config() {
+    let config = RuntimeConfig { worker_threads: Some(2), max_blocking_threads: Some(1), event_interval: Some(31) };
+    let runtime = config.build().unwrap();
+    let stats = runtime.block_on(runtime::sample(Duration::from_millis(10)));
+    assert_eq!(stats.workers, 2);
+    // the one blocking thread works through them in turn
+    let order = runtime.block_on(async {
+        let first = tokio::task::spawn_blocking(|| { std::thread::sleep(Duration::from_millis(50)); std::time::Instant::now() });
+        let second = tokio::task::spawn_blocking(std::time::Instant::now);
+        (first.await.unwrap(), second.await.unwrap())
+    });
+    assert!(order.0 <= order.1);
+}
+
+#[tokio::test]
+async fn memory_stats() {
+    let app = TestApp::new();
+    for name in ["Pikachu", "Eevee", "Snorlax"] {
+        app.create_pokemon(name, 10).await;
+    }
+    let stats = app.admin_get("/admin/memory").await.assert_status(StatusCode::OK).json();
+    let pokemon = stats["collections"].as_array().unwrap().iter().find(|c| c["name"] == "pokemon").unwrap();
+    assert_eq!(pokemon["records"], 3);
+    assert!(pokemon["approx_bytes"].as_u64() > Some(0));
+    assert_eq!(stats["records"], 3);
+    assert!(stats["allocator"]["in_use_bytes"].as_u64() > Some(0));
+    assert_eq!(stats["over"], false);
+
+    app.get("/metrics").await.assert_status(StatusCode::UNAUTHORIZED);
+    let metrics = app.request(Method::GET, "/metrics").admin().send().await.assert_status(StatusCode::OK);
+    assert!(metrics.header("content-type").unwrap().to_str().unwrap().starts_with("text/plain"));
+    assert!(metrics.text().contains("pokemon_api_records{collection=\"pokemon\"} 3"), "{}", metrics.text());
+    assert!(metrics.text().contains("# TYPE pokemon_api_allocations_total counter"));
+
+    let small = TestApp::with(|builder| builder.memory(Memory::warn_at(None, Some(2))));
+    small.create_pokemon("Pikachu", 10).await;
+    assert_eq!(memory::check(&small.state), Ok(()));
+    small.create_pokemon("Eevee", 10).await;
+    assert!(memory::check(&small.state).unwrap_err().contains("2 records"));
+    assert_eq!(small.admin_get("/admin/memory").await.json()["over"], true);
+}
+
+#[tokio::test]
+async fn latency_slos() {
+    // nothing is quicker than no time at all, and nothing here takes a minute
+    let slos = Slos::default().target("GET /pokemon/{id}", Duration::ZERO).target("/pokemon", Duration::from_secs(60)).objective(90.0);
+    let app = TestApp::with(|builder| builder.slos(slos));
+    let pikachu = app.create_pokemon("Pikachu", 10).await;
+    for _ in 0..slo::MIN_SAMPLES {
+        app.get(&format!("/pokemon/{}", pikachu)).await.assert_status(StatusCode::OK);
+        app.get("/pokemon").await.assert_status(StatusCode::OK);
+    }
+    app.get("/nope").await.assert_status(StatusCode::NOT_FOUND);
+
+    let report = app.admin_get("/admin/slo").await.assert_status(StatusCode::OK).json();
+    assert_eq!(report["objective_percent"], 90.0);
+    let route = |name: &str| report["routes"].as_array().unwrap().iter().find(|r| r["route"] == name).cloned()
+        .unwrap_or_else(|| panic!("no {} in {}", name, report));
+    let one = route("GET /pokemon/{id}");
+    assert_eq!((one["requests"].as_u64(), one["slow"].as_u64()), (Some(20), Some(20)));
+    assert_eq!((one["target_ms"].as_f64(), one["status"].as_str()), (Some(0.0), Some("burning")));
+    assert!((one["burn_rate"].as_f64().unwrap() - 10.0).abs() < 1e-9);
+    assert!(one["p50_ms"].as_f64() <= one["p99_ms"].as_f64());
+    let list = route("GET /pokemon");
+    assert_eq!((list["slow"].as_u64(), list["burn_rate"].as_f64(), list["status"].as_str()), (Some(0), Some(0.0), Some("ok")));
+    // the POST shares the pattern's target, but there's only been the one
+    assert_eq!(route("POST /pokemon")["status"], "too_few");
+    assert!(report["routes"].as_array().unwrap().iter().all(|r| r["route"] != "GET /nope"));
+
+    let untargeted = TestApp::new();
+    untargeted.get("/pokemon").await;
+    let report = untargeted.admin_get("/admin/slo").await.json();
+    assert_eq!((report["routes"][0]["status"].as_str(), report["routes"][0]["burn_rate"].as_f64()), (Some("no_target"), None));
+}
+
+#[tokio::test]
+async fn scheduled_tasks() {
+    let tasks = Scheduler::default()
+        .task("leaderboards", Some(Duration::from_millis(20))).unwrap()
+        .task("snapshots", None).unwrap();
+    let error = Scheduler::default().task("laundry", None).unwrap_err();
+    assert!(error.contains("unknown task") && error.contains("daycare"), "{}", error);
+    let app = TestApp::with(|builder| builder.scheduler(tasks));
+    scheduler::spawn(&app.state);
+
+    let task = |tasks: &Value, name: &str| tasks.as_array().unwrap().iter().find(|t| t["name"] == name).unwrap().clone();
+    let mut tasks = app.admin_get("/admin/tasks").await.assert_status(StatusCode::OK).json();
+    for _ in 0..100 {
+        if task(&tasks, "leaderboards")["runs"].as_u64() >= Some(2) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        tasks = app.admin_get("/admin/tasks").await.json();
+    }
+    let leaderboards = task(&tasks, "leaderboards");
+    assert!(leaderboards["runs"].as_u64() >= Some(2), "{}", leaderboards);
+    assert_eq!(leaderboards["failures"], 0);
+    assert!(leaderboards["last_run"].is_u64() && leaderboards["last_error"].is_null());
+    let snapshots = task(&tasks, "snapshots");
+    assert_eq!(snapshots["enabled"], false);
+    assert!(snapshots["next_run"].is_null());
+    // the rest are on their own defaults
+    assert_eq!(task(&tasks, "daycare")["interval_secs"], 30);
+    assert_eq!(task(&tasks, "daycare")["runs"], 0);
+}
+
+// the job once it's no longer queued or running
+async fn finished(app: &TestApp, location: &str) -> Value {
+    for _ in 0..200 {
+        let job = app.request(Method::GET, location).admin().send().await.assert_status(StatusCode::OK).json();
+        if job["status"] == "done" || job["status"] == "failed" {
+            return job;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("{} never finished", location);
+}
+
+#[tokio::test]
+async fn background_jobs() {
+    let app = TestApp::new();
+    let accepted = app.request(Method::POST, "/admin/generate?count=2500&seed=42").admin().header("prefer", "respond-async, wait=5").send().await
+        .assert_status(StatusCode::ACCEPTED);
+    assert_eq!(accepted.header("preference-applied").unwrap(), "respond-async");
+    let location = accepted.header("location").unwrap().to_str().unwrap().to_string();
+    let job = accepted.json();
+    assert_eq!(location, format!("/admin/jobs/{}", job["id"]));
+    assert_eq!(job["kind"], "generate");
+
+    let job = finished(&app, &location).await;
+    assert_eq!(job["status"], "done");
+    assert_eq!(job["result"]["created"], 2500);
+    assert_eq!(job["progress"]["done"], 2000);
+    assert!(job["finished_at"].is_u64());
+    app.get(&format!("/pokemon/{}", job["result"]["last_id"])).await.assert_status(StatusCode::OK);
+    // started through the admin API, so not for everyone
+    app.get(&format!("/jobs/{}", job["id"])).await.assert_status(StatusCode::NOT_FOUND);
+    app.admin_get("/admin/jobs/99").await.assert_status(StatusCode::NOT_FOUND);
+
+    // what would have been an error is the job's
+    let accepted = app.request(Method::POST, "/admin/import").admin().header("prefer", "respond-async").text("not json").send().await
+        .assert_status(StatusCode::ACCEPTED);
+    let job = finished(&app, accepted.header("location").unwrap().to_str().unwrap()).await;
+    assert_eq!(job["status"], "failed");
+    assert_eq!(job["error"]["status"], 400);
+    // and checks that don't need the work still answer straight away
+    app.request(Method::POST, "/admin/generate?count=100001").admin().header("prefer", "respond-async").send().await
+        .assert_error(StatusCode::BAD_REQUEST, "up to 100000");
+}
+
+#[tokio::test]
+async fn check_and_repair() {
+    let app = TestApp::new();
+    let pikachu = app.create_pokemon("Pikachu", 10).await;
+    let ash = app.create_trainer("Ash", &[pikachu]).await;
+    assert_eq!(app.admin_get("/admin/check").await.json()["ok"], true);
+
+    app.state.trainers.lock().unwrap()[0].team.push(999);
+    let report = app.admin_get("/admin/check").await.assert_status(StatusCode::OK).json();
+    assert_eq!(report["ok"], false);
+    assert_eq!(report["findings"][0]["issue"], "dangling_reference");
+    assert_eq!(report["findings"][0]["fixed"], false);
+
+    let report = app.admin_post("/admin/repair", json!({})).await.assert_status(StatusCode::OK).json();
+    assert_eq!(report["ok"], true);
+    assert_eq!(report["findings"][0]["fixed"], true);
+    assert_eq!(app.get(&format!("/trainers/{}", ash)).await.json()["team"], json!([pikachu]));
+    assert_eq!(id(&app.get("/pokemon/1").await.json()), pikachu);
+}
+
+#[tokio::test]
+async fn console_commands() {
+    let app = TestApp::new();
+    app.create_pokemon("Pikachu", 12).await;
+    app.create_pokemon("Charmander", 40).await;
+    let run = |line: &'static str| {
+        let state = app.state.clone();
+        async move { console::execute(&state, line).await.output }
+    };
+
+    assert!(run("status").await.contains("pokemon  2"));
+    let found = run("find pokemon level>=30").await;
+    assert!(found.contains("Charmander") && !found.contains("Pikachu"));
+    assert!(found.ends_with("1 match"));
+    assert!(run("find pokemon poke_type=ELECTRIC level<20").await.contains("Pikachu"));
+    assert_eq!(run("find trainers").await, "0 matches");
+    assert!(run("find pokemon level").await.contains("isn't a condition"));
+    assert_eq!(serde_json::from_str::<Value>(&run("show pokemon 2").await).unwrap()["name"], "Charmander");
+    assert!(run("show eggs 1").await.contains("no eggs with ID 1"));
+    assert!(run("find widgets").await.contains("unknown kind"));
+
+    // a repair from here drops cached reads it changed, like one through the admin API would
+    let cached = app.create_pokemon("Eevee", 5).await;
+    app.get("/pokemon").await;
+    app.state.trainers.lock().unwrap().push(Trainer::new(1, "Ash".to_string(), vec![cached, 999]));
+    assert!(run("repair").await.contains("dangling_reference"));
+    assert_eq!(app.get("/pokemon").await.header("x-cache").unwrap(), "miss");
+
+    run("mode maintenance back soon").await;
+    app.get("/pokemon").await.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+    run("toggle maintenance").await;
+    app.get("/pokemon").await.assert_status(StatusCode::OK);
+    assert!(run("mode sleepy").await.contains("unknown mode"));
+
+    assert!(run("snapshot").await.contains("snapshot-"));
+    assert_eq!(app.admin_get("/admin/snapshots").await.json().as_array().unwrap().len(), 1);
+    assert!(console::execute(&app.state, "quit").await.quit);
+}
+
+#[tokio::test]
+async fn console_socket() {
+    let app = TestApp::new();
+    assert!(console::spawn(app.state.clone(), "0.0.0.0:0").await.unwrap_err().to_string().contains("local only"));
+
+    let addr = console::spawn(app.state.clone(), "127.0.0.1:0").await.unwrap();
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"toggle read_only\nquit\n").await.unwrap();
+    let mut transcript = String::new();
+    stream.read_to_string(&mut transcript).await.unwrap();
+    assert!(transcript.starts_with("pokemon admin console"));
+    assert!(transcript.contains("\"read_only\"") && transcript.ends_with("bye\n"));
+    app.post("/pokemon", json!({"name": "Pikachu", "poke_type": "electric", "level": 5})).await
+        .assert_status(StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn connection_limit() {
+    let app = TestApp::with(|builder| builder.connections(Connections::limit(1)));
+    let addr = app.serve().await;
+    let request = b"GET /pokemon HTTP/1.1\r\nhost: localhost\r\n\r\n";
+
+    let mut first = TcpStream::connect(addr).await.unwrap();
+    first.write_all(request).await.unwrap();
+    let mut buf = [0; 1024];
+    let n = first.read(&mut buf).await.unwrap();
+    assert!(String::from_utf8_lossy(&buf[..n]).starts_with("HTTP/1.1 200"));
+
+    // the OS takes it, the server doesn't until the first one closes
+    let mut second = TcpStream::connect(addr).await.unwrap();
+    second.write_all(request).await.unwrap();
+    let waiting = tokio::time::timeout(Duration::from_millis(200), second.read(&mut buf)).await;
+    assert!(waiting.is_err());
+
+    let stats = app.admin_get("/admin/connections").await.assert_status(StatusCode::OK).json();
+    assert_eq!(stats["open"], 1);
+    assert_eq!(stats["max"], 1);
+    assert!(stats["cap_hits"].as_u64().unwrap() >= 1);
+    assert!(console::execute(&app.state, "status").await.output.contains("conns    1 of 1"));
+
+    drop(first);
+    let n = tokio::time::timeout(Duration::from_secs(5), second.read(&mut buf)).await.unwrap().unwrap();
+    assert!(String::from_utf8_lossy(&buf[..n]).starts_with("HTTP/1.1 200"));
+}
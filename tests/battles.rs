@@ -0,0 +1,200 @@
+mod common;
+
+use std::time::Duration;
+
+use axum::http::{Method, StatusCode};
+use common::{id, websocket, TestApp};
+use serde_json::json;
+
+#[tokio::test]
+async fn simulated_battles() {
+    let app = TestApp::new();
+    let charizard = app.create_pokemon("Charizard", 80).await;
+    let pidgey = app.create_pokemon("Pidgey", 5).await;
+    let battle = app.post("/battles", json!({ "side_a": [charizard], "side_b": [pidgey], "seed": 7 })).await
+        .assert_status(StatusCode::CREATED)
+        .json();
+    assert_eq!(battle["winner"], "side_a");
+    // the same seed plays out the same way
+    let again = app.post("/battles", json!({ "side_a": [charizard], "side_b": [pidgey], "seed": 7 })).await.json();
+    assert_eq!(again["log"], battle["log"]);
+
+    let all = app.get("/battles").await.assert_status(StatusCode::OK).json();
+    assert_eq!(all.as_array().unwrap().len(), 2);
+    app.get(&format!("/battles?pokemon={}", pidgey)).await.assert_status(StatusCode::OK);
+    assert_eq!(app.get("/battles?pokemon=99").await.json(), json!([]));
+    app.get(&format!("/battles/{}", id(&battle))).await.assert_status(StatusCode::OK);
+    app.get("/battles/99").await.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn battles_check_both_sides() {
+    let app = TestApp::new();
+    let pikachu = app.create_pokemon("Pikachu", 10).await;
+    let pidgey = app.create_pokemon("Pidgey", 10).await;
+    for path in ["/battles", "/battle-sessions"] {
+        app.post(path, json!({ "side_a": [], "side_b": [pidgey] })).await.assert_error(StatusCode::BAD_REQUEST, "1 to 6");
+        app.post(path, json!({ "side_a": [pikachu], "side_b": [pikachu] })).await.assert_error(StatusCode::BAD_REQUEST, "battle itself");
+        app.post(path, json!({ "side_a": [pikachu], "side_b": [99] })).await.assert_status(StatusCode::NOT_FOUND);
+    }
+    app.post(&format!("/pokemon/{}/damage", pidgey), json!({ "amount": 9999 })).await;
+    app.post("/battles", json!({ "side_a": [pikachu], "side_b": [pidgey] })).await.assert_error(StatusCode::UNPROCESSABLE_ENTITY, "fainted");
+}
+
+#[tokio::test]
+async fn battles_need_the_viewers_own_side() {
+    let app = TestApp::new();
+    let pikachu = app.create_pokemon("Pikachu", 20).await;
+    let hidden = app.create_pokemon("Eevee", 20).await;
+    let rattata = app.create_pokemon("Rattata", 20).await;
+    let ash = app.create_trainer("Ash", &[pikachu, hidden]).await;
+    let gary = app.create_trainer("Gary", &[rattata]).await;
+    app.request(Method::PUT, &format!("/pokemon/{}", hidden)).trainer(ash).json(json!({ "visibility": "private" })).send().await
+        .assert_status(StatusCode::OK);
+    let battle = |path: &str, trainer: u32, side_a: u32, side_b: u32| app.request(Method::POST, path).trainer(trainer).json(json!({ "side_a": [side_a], "side_b": [side_b] })).send();
+
+    for path in ["/battles", "/battle-sessions"] {
+        // someone else's Pokemon can be fought, not sent in
+        battle(path, gary, pikachu, rattata).await.assert_status(StatusCode::FORBIDDEN);
+        battle(path, gary, rattata, pikachu).await.assert_status(StatusCode::CREATED);
+        // and a private one isn't there at all, so its stats don't come back in the record
+        battle(path, gary, rattata, hidden).await.assert_status(StatusCode::NOT_FOUND);
+        battle(path, ash, hidden, rattata).await.assert_status(StatusCode::CREATED);
+    }
+}
+
+#[tokio::test]
+async fn battle_sessions() {
+    let app = TestApp::new();
+    let pikachu = app.create_pokemon("Pikachu", 10).await;
+    let pidgey = app.create_pokemon("Pidgey", 10).await;
+    let session = app.post("/battle-sessions", json!({ "side_a": [pikachu], "side_b": [pidgey] })).await
+        .assert_status(StatusCode::CREATED)
+        .json();
+    let session = id(&session);
+    app.get(&format!("/battle-sessions/{}", session)).await.assert_status(StatusCode::OK);
+    app.get("/battle-sessions/99").await.assert_status(StatusCode::NOT_FOUND);
+    // not a WebSocket handshake
+    app.get(&format!("/battle-sessions/{}/ws?side=side_a", session)).await.assert_status(StatusCode::BAD_REQUEST);
+
+    let addr = app.serve().await;
+    let (status, _connection) = websocket(addr, &format!("/battle-sessions/{}/ws?side=side_a", session)).await;
+    assert_eq!(status, StatusCode::SWITCHING_PROTOCOLS);
+    let (status, _) = websocket(addr, "/battle-sessions/99/ws?side=side_a").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // the first connection takes a moment to register
+    for _ in 0..50 {
+        if app.get(&format!("/battle-sessions/{}", session)).await.json()["connected"] == json!(["side_a"]) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let (status, _) = websocket(addr, &format!("/battle-sessions/{}/ws?side=side_a", session)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn matchmaking() {
+    let app = TestApp::new();
+    let pikachu = app.create_pokemon("Pikachu", 10).await;
+    let ash = app.create_trainer("Ash", &[pikachu]).await;
+    let empty = app.create_trainer("Gary", &[]).await;
+
+    let ticket = app.post_as(ash, "/matchmaking/join", json!({ "trainer_id": ash })).await.assert_status(StatusCode::ACCEPTED).json();
+    assert_eq!(ticket["status"], "waiting");
+    let ticket = id(&ticket);
+    app.post_as(ash, "/matchmaking/join", json!({ "trainer_id": ash })).await.assert_error(StatusCode::CONFLICT, "already in the queue");
+    app.post_as(99, "/matchmaking/join", json!({ "trainer_id": 99 })).await.assert_status(StatusCode::NOT_FOUND);
+    app.post_as(empty, "/matchmaking/join", json!({ "trainer_id": empty })).await.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+
+    app.get(&format!("/matchmaking/{}", ticket)).await.assert_status(StatusCode::OK);
+    app.get("/matchmaking/99").await.assert_status(StatusCode::NOT_FOUND);
+    app.delete(&format!("/matchmaking/{}", ticket)).await.assert_status(StatusCode::NO_CONTENT);
+    app.delete(&format!("/matchmaking/{}", ticket)).await.assert_status(StatusCode::CONFLICT);
+    app.delete("/matchmaking/99").await.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn tournaments() {
+    let app = TestApp::new();
+    let tournament = app.post("/tournaments", json!({ "name": "Indigo League" })).await.assert_status(StatusCode::CREATED).json();
+    assert_eq!(tournament["mode"], "simulated");
+    let tournament = id(&tournament);
+    let register = format!("/tournaments/{}/register", tournament);
+    assert_eq!(app.get("/tournaments").await.assert_status(StatusCode::OK).json().as_array().unwrap().len(), 1);
+    app.get(&format!("/tournaments/{}", tournament)).await.assert_status(StatusCode::OK);
+    app.get("/tournaments/99").await.assert_status(StatusCode::NOT_FOUND);
+
+    let mut trainers = Vec::new();
+    for (name, species) in [("Ash", "Pikachu"), ("Gary", "Eevee"), ("Misty", "Squirtle")] {
+        let pokemon = app.create_pokemon(species, 20).await;
+        trainers.push(app.create_trainer(name, &[pokemon]).await);
+    }
+    app.post_as(trainers[0], &register, json!({ "trainer_id": trainers[0] })).await.assert_status(StatusCode::OK);
+    app.post(&format!("/tournaments/{}/start", tournament), json!({})).await.assert_error(StatusCode::UNPROCESSABLE_ENTITY, "at least 2");
+    app.post(&format!("/tournaments/{}/rounds", tournament), json!({})).await.assert_error(StatusCode::CONFLICT, "hasn't started");
+    app.post_as(trainers[0], &register, json!({ "trainer_id": trainers[0] })).await.assert_error(StatusCode::CONFLICT, "already registered");
+    app.post_as(99, &register, json!({ "trainer_id": 99 })).await.assert_status(StatusCode::NOT_FOUND);
+    app.post_as(trainers[0], "/tournaments/99/register", json!({ "trainer_id": trainers[0] })).await.assert_status(StatusCode::NOT_FOUND);
+    app.post_as(trainers[1], &register, json!({ "trainer_id": trainers[1] })).await.assert_status(StatusCode::OK);
+    app.post_as(trainers[2], &register, json!({ "trainer_id": trainers[2] })).await.assert_status(StatusCode::OK);
+
+    let bracket = app.post(&format!("/tournaments/{}/start", tournament), json!({})).await.assert_status(StatusCode::OK).json();
+    assert_eq!(bracket["status"], "in_progress");
+    app.post(&format!("/tournaments/{}/start", tournament), json!({})).await.assert_status(StatusCode::CONFLICT);
+    app.post("/tournaments/99/start", json!({})).await.assert_status(StatusCode::NOT_FOUND);
+    app.post_as(trainers[2], &register, json!({ "trainer_id": trainers[2] })).await.assert_error(StatusCode::CONFLICT, "closed");
+
+    // 3 trainers is 2 rounds, the second one in the background
+    let rounds = format!("/tournaments/{}/rounds", tournament);
+    app.post(&rounds, json!({})).await.assert_status(StatusCode::OK);
+    let accepted = app.request(Method::POST, &rounds).header("prefer", "respond-async").send().await.assert_status(StatusCode::ACCEPTED);
+    let location = accepted.header("location").unwrap().to_str().unwrap().to_string();
+    let mut job = accepted.json();
+    while job["status"] == "queued" || job["status"] == "running" {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        job = app.get(&location).await.assert_status(StatusCode::OK).json();
+    }
+    assert_eq!(job["status"], "done", "{}", job);
+    assert_eq!(job["result"]["status"], "finished");
+    assert_eq!(job["progress"]["done"], job["progress"]["total"]);
+    app.request(Method::POST, &rounds).header("prefer", "respond-async").send().await.assert_error(StatusCode::CONFLICT, "over");
+    let bracket = app.get(&format!("/tournaments/{}/bracket", tournament)).await.assert_status(StatusCode::OK).json();
+    assert_eq!(bracket["status"], "finished");
+    assert!(trainers.contains(&(bracket["champion"].as_u64().unwrap() as u32)));
+    app.post(&format!("/tournaments/{}/rounds", tournament), json!({})).await.assert_error(StatusCode::CONFLICT, "over");
+    app.post("/tournaments/99/rounds", json!({})).await.assert_status(StatusCode::NOT_FOUND);
+    app.get("/tournaments/99/bracket").await.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn gyms() {
+    let app = TestApp::new();
+    let gym = json!({ "name": "Pewter Gym", "leader": "Brock", "badge": "Boulder Badge", "team": [{ "name": "Geodude", "level": 5 }] });
+    let created = app.post("/gyms", gym).await.assert_status(StatusCode::CREATED).json();
+    let gym = id(&created);
+    for team in [json!([]), json!([{ "name": "Agumon", "level": 5 }]), json!([{ "name": "Onix", "level": 0 }])] {
+        app.post("/gyms", json!({ "name": "Fake Gym", "leader": "Nobody", "badge": "None", "team": team })).await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+    assert_eq!(app.get("/gyms").await.assert_status(StatusCode::OK).json().as_array().unwrap().len(), 1);
+    app.get(&format!("/gyms/{}", gym)).await.assert_status(StatusCode::OK);
+    app.get("/gyms/99").await.assert_status(StatusCode::NOT_FOUND);
+
+    let blastoise = app.create_pokemon("Blastoise", 100).await;
+    let ash = app.create_trainer("Ash", &[blastoise]).await;
+    let empty = app.create_trainer("Gary", &[]).await;
+    let challenge = format!("/gyms/{}/challenge", gym);
+    app.post_as(empty, &challenge, json!({ "trainer_id": empty })).await.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    app.post_as(99, &challenge, json!({ "trainer_id": 99 })).await.assert_status(StatusCode::NOT_FOUND);
+    app.post_as(ash, "/gyms/99/challenge", json!({ "trainer_id": ash })).await.assert_status(StatusCode::NOT_FOUND);
+
+    let result = app.post_as(ash, &challenge, json!({ "trainer_id": ash, "seed": 1 })).await.assert_status(StatusCode::OK).json();
+    assert_eq!(result["won"], true);
+    assert_eq!(result["badge_awarded"], "Boulder Badge");
+    assert_eq!(result["trainer"]["badges"], json!(["Boulder Badge"]));
+    // only the first win earns the badge
+    let result = app.post_as(ash, &challenge, json!({ "trainer_id": ash, "seed": 1 })).await.assert_status(StatusCode::OK).json();
+    assert_eq!(result["badge_awarded"], json!(null));
+}
@@ -0,0 +1,95 @@
+// cargo test --features telegram
+#![cfg(feature = "telegram")]
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use axum::{extract::State, routing::get, routing::post, Json, Router};
+use common::TestApp;
+use rust_hello_http::bot::{self, Telegram};
+use rust_hello_http::encounters::Ball;
+use rust_hello_http::models::Visibility;
+use serde_json::{json, Value};
+
+#[tokio::test]
+async fn commands() {
+    let app = TestApp::new();
+    let pikachu = app.create_pokemon("Pikachu", 12).await;
+    let eevee = app.create_pokemon("Eevee", 5).await;
+    let ash = app.create_trainer("Ash", &[pikachu, eevee]).await;
+    app.state.team.lock().unwrap()[1].visibility = Visibility::Private;
+    app.state.trainers.lock().unwrap()[0].bag.insert(Ball::Master, 1);
+    let mut trainer = 0;
+    let mut say = |text: &str| bot::respond(&app.state, &mut trainer, text).unwrap();
+
+    assert!(say("/help").contains("/catch"));
+    let team = say(&format!("/team {}", ash));
+    assert!(team.contains("Pikachu lv 12") && !team.contains("Eevee"));
+    assert!(say("/team").starts_with("whose?"));
+    assert!(say("/catch 1").contains("pick your trainer first"));
+    assert!(say("/random water").contains("no pokemon match"));
+    assert!(say("/random electric").contains("Pikachu"));
+
+    assert_eq!(say(&format!("/trainer@PokemonBot {}", ash)), "you're Ash now");
+    assert!(say("/team").contains("Eevee"));
+    assert!(say("/wild kanto lava").contains("no wild pokemon"));
+    assert!(say("/wild").contains("appeared in kanto grass! /catch 1"));
+    assert!(say("/catch 1 frisbee").contains("isn't a ball"));
+    let thrown = say("/catch 1 master_ball");
+    assert!(thrown.starts_with("gotcha!"), "{}", thrown);
+    assert!(say("/catch 1").contains("is gone"));
+    assert!(say("/fly").starts_with("don't know /fly"));
+    assert_eq!(bot::respond(&app.state, &mut trainer, "hello there"), None);
+}
+
+// stands in for api.telegram.org: one /random message, then nothing, and keeps whatever the bot sends
+#[derive(Clone, Default)]
+struct FakeTelegram{
+    polls: Arc<Mutex<u32>>,
+    sent: Arc<Mutex<Vec<Value>>>,
+}
+
+async fn get_updates(State(fake): State<FakeTelegram>) -> Json<Value> {
+    let first = {
+        let mut polls = fake.polls.lock().unwrap();
+        *polls += 1;
+        *polls == 1
+    };
+    if !first {
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        return Json(json!({ "ok": true, "result": [] }));
+    }
+    Json(json!({ "ok": true, "result": [{ "update_id": 7, "message": { "chat": { "id": 42 }, "text": "/random" } }] }))
+}
+
+async fn send_message(State(fake): State<FakeTelegram>, Json(body): Json<Value>) -> Json<Value> {
+    fake.sent.lock().unwrap().push(body);
+    Json(json!({ "ok": true }))
+}
+
+#[tokio::test]
+async fn polls_and_answers() {
+    let app = TestApp::new();
+    app.create_pokemon("Pikachu", 12).await;
+    let fake = FakeTelegram::default();
+    let router = Router::new()
+        .route("/botsecret/getUpdates", get(get_updates))
+        .route("/botsecret/sendMessage", post(send_message))
+        .with_state(fake.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+    bot::spawn(app.state.clone(), Telegram::new("secret", &format!("http://{}/", addr)));
+    for _ in 0..100 {
+        if !fake.sent.lock().unwrap().is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    let sent = fake.sent.lock().unwrap().clone();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0]["chat_id"], 42);
+    assert!(sent[0]["text"].as_str().unwrap().contains("Pikachu"));
+}
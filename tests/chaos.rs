@@ -0,0 +1,45 @@
+// cargo test --features chaos
+#![cfg(feature = "chaos")]
+
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::TestApp;
+use serde_json::json;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+#[tokio::test]
+async fn fault_injection() {
+    let app = TestApp::new();
+    let chaos = |body: serde_json::Value| app.request(Method::PUT, "/admin/chaos").admin().json(body).send();
+    chaos(json!({ "error_percent": 120 })).await.assert_error(StatusCode::UNPROCESSABLE_ENTITY, "0 to 100");
+    chaos(json!({ "latency_ms": 600000 })).await.assert_error(StatusCode::UNPROCESSABLE_ENTITY, "up to 60000");
+
+    // every request under /pokemon fails, the rest and the admin API don't
+    chaos(json!({ "error_percent": 100, "paths": ["/pokemon"] })).await.assert_status(StatusCode::OK);
+    let failed = app.get("/pokemon").await.assert_error(StatusCode::INTERNAL_SERVER_ERROR, "chaos");
+    assert_eq!(failed.header("x-chaos").unwrap(), "error");
+    app.get("/trainers").await.assert_status(StatusCode::OK);
+    app.admin_get("/admin/chaos").await.assert_status(StatusCode::OK);
+
+    chaos(json!({ "latency_ms": 200, "latency_percent": 100 })).await.assert_status(StatusCode::OK);
+    let started = Instant::now();
+    app.get("/pokemon").await.assert_status(StatusCode::OK);
+    assert!(started.elapsed() >= Duration::from_millis(200));
+
+    // the connection closes without an answer
+    chaos(json!({ "drop_percent": 100 })).await.assert_status(StatusCode::OK);
+    let addr = app.serve().await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"GET /pokemon HTTP/1.1\r\nhost: localhost\r\n\r\n").await.unwrap();
+    let mut answer = Vec::new();
+    let _ = stream.read_to_end(&mut answer).await;
+    assert!(answer.is_empty(), "{}", String::from_utf8_lossy(&answer));
+
+    let stats = app.admin_get("/admin/chaos").await.json();
+    assert_eq!((stats["errors"].as_u64(), stats["delayed"].as_u64(), stats["dropped"].as_u64()), (Some(1), Some(1), Some(1)));
+    app.request(Method::DELETE, "/admin/chaos").admin().send().await.assert_status(StatusCode::NO_CONTENT);
+    app.get("/pokemon").await.assert_status(StatusCode::OK);
+}
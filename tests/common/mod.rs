@@ -0,0 +1,226 @@
+// shared by the integration tests: the whole app in-process with a fresh state per test,
+// nothing listens on a port unless a test calls serve(), and nothing goes out to the network
+// each test file only uses some of this
+#![allow(dead_code)]
+
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    http::{header, HeaderMap, HeaderValue, Method, Request, StatusCode},
+    Router,
+};
+use rust_hello_http::{backups::Backups, connections, species, sprites::SpriteStore, state::SharedState, AppBuilder};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use tempfile::TempDir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tower::ServiceExt;
+
+pub const ADMIN_TOKEN: &str = "test-token";
+
+const BOUNDARY: &str = "test-boundary";
+
+pub struct TestApp{
+    pub state: SharedState,
+    router: Router,
+    // sprites and snapshots go in here, it's deleted along with the app
+    _dir: TempDir,
+}
+
+impl TestApp{
+    pub fn new() -> TestApp {
+        TestApp::with(|builder| builder)
+    }
+
+    // for tests that need quotas, rules and the like on top of the defaults
+    pub fn with(configure: impl FnOnce(AppBuilder) -> AppBuilder) -> TestApp {
+        let dir = tempfile::tempdir().expect("can make a temp dir");
+        let builder = AppBuilder::new()
+            .admin_token(Some(ADMIN_TOKEN.to_string()))
+            .sprites(SpriteStore::in_dir(dir.path().join("sprites")))
+            .backups(Backups::in_dir(dir.path().join("snapshots")));
+        let app = configure(builder).build();
+        TestApp { state: app.state, router: app.router, _dir: dir }
+    }
+
+    // the app on a real port, for the few things oneshot can't do, e.g. WebSocket upgrades
+    pub async fn serve(&self) -> SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("a free port");
+        let addr = listener.local_addr().unwrap();
+        let (router, listener) = (self.router.clone(), connections::listen(listener, self.state.clone()));
+        tokio::spawn(async move { axum::serve(listener, router.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap() });
+        addr
+    }
+
+    pub fn request(&self, method: Method, path: &str) -> TestRequest<'_> {
+        TestRequest { app: self, request: Request::builder().method(method).uri(path), body: Body::empty() }
+    }
+
+    pub async fn get(&self, path: &str) -> TestResponse {
+        self.request(Method::GET, path).send().await
+    }
+
+    pub async fn post(&self, path: &str, body: Value) -> TestResponse {
+        self.request(Method::POST, path).json(body).send().await
+    }
+
+    // POST as that trainer, for anything with the trainer's ID in the body too
+    pub async fn post_as(&self, trainer_id: u32, path: &str, body: Value) -> TestResponse {
+        self.request(Method::POST, path).trainer(trainer_id).json(body).send().await
+    }
+
+    pub async fn put(&self, path: &str, body: Value) -> TestResponse {
+        self.request(Method::PUT, path).json(body).send().await
+    }
+
+    pub async fn delete(&self, path: &str) -> TestResponse {
+        self.request(Method::DELETE, path).send().await
+    }
+
+    // GET with the admin token
+    pub async fn admin_get(&self, path: &str) -> TestResponse {
+        self.request(Method::GET, path).admin().send().await
+    }
+
+    pub async fn admin_post(&self, path: &str, body: Value) -> TestResponse {
+        self.request(Method::POST, path).admin().json(body).send().await
+    }
+
+    // a Pokemon of a species the app knows, with its real types, returns its ID
+    pub async fn create_pokemon(&self, name: &str, level: u32) -> u32 {
+        let (primary, secondary) = species::find(name).expect("a known species").types;
+        let body = json!({ "name": name, "poke_type": primary, "secondary_type": secondary, "level": level });
+        id(&self.post("/pokemon", body).await.assert_status(StatusCode::CREATED).json())
+    }
+
+    pub async fn create_trainer(&self, name: &str, team: &[u32]) -> u32 {
+        id(&self.post("/trainers", json!({ "name": name, "team": team })).await.assert_status(StatusCode::CREATED).json())
+    }
+}
+
+pub struct TestRequest<'a>{
+    app: &'a TestApp,
+    request: axum::http::request::Builder,
+    body: Body,
+}
+
+impl TestRequest<'_>{
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.request = self.request.header(name, value);
+        self
+    }
+
+    pub fn admin(self) -> Self {
+        self.header("authorization", &format!("Bearer {}", ADMIN_TOKEN))
+    }
+
+    // X-Trainer-Id, for anything that depends on who's asking
+    pub fn trainer(self, trainer_id: u32) -> Self {
+        self.header("x-trainer-id", &trainer_id.to_string())
+    }
+
+    // a session token from logging in, in place of X-Trainer-Id
+    pub fn session(self, token: &str) -> Self {
+        self.header("authorization", &format!("Bearer {}", token))
+    }
+
+    pub fn json(self, body: Value) -> Self {
+        self.body("application/json", body.to_string())
+    }
+
+    pub fn text(self, body: &str) -> Self {
+        self.body("text/plain", body.to_string())
+    }
+
+    pub fn form(self, body: &str) -> Self {
+        self.body("application/x-www-form-urlencoded", body.to_string())
+    }
+
+    // a multipart form, one (name, content type, bytes) per field
+    pub fn multipart(self, fields: &[(&str, &str, &[u8])]) -> Self {
+        let mut body = Vec::new();
+        for (name, content_type, bytes) in fields {
+            body.extend_from_slice(format!("--{}\r\n", BOUNDARY).as_bytes());
+            body.extend_from_slice(format!("Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n", name, name).as_bytes());
+            body.extend_from_slice(format!("Content-Type: {}\r\n\r\n", content_type).as_bytes());
+            body.extend_from_slice(bytes);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(format!("--{}--\r\n", BOUNDARY).as_bytes());
+        self.body(&format!("multipart/form-data; boundary={}", BOUNDARY), body)
+    }
+
+    pub fn body(mut self, content_type: &str, body: impl Into<Body>) -> Self {
+        self.request = self.request.header(header::CONTENT_TYPE, content_type);
+        self.body = body.into();
+        self
+    }
+
+    pub async fn send(self) -> TestResponse {
+        let request = self.request.body(self.body).expect("a valid request");
+        let response = self.app.router.clone().oneshot(request).await.expect("the router never fails");
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = to_bytes(response.into_body(), usize::MAX).await.expect("the body can be read");
+        TestResponse { status, headers, body }
+    }
+}
+
+#[derive(Debug)]
+pub struct TestResponse{
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl TestResponse{
+    pub fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap_or_else(|e| panic!("body isn't JSON ({}): {}", e, self.text()))
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn header(&self, name: &str) -> Option<&HeaderValue> {
+        self.headers.get(name)
+    }
+
+    // shows the body when it fails, which is usually what says why
+    #[track_caller]
+    pub fn assert_status(self, status: StatusCode) -> Self {
+        assert_eq!(self.status, status, "body: {}", self.text());
+        self
+    }
+
+    // an ApiError with this status whose message mentions `message`
+    #[track_caller]
+    pub fn assert_error(self, status: StatusCode, message: &str) -> Self {
+        let response = self.assert_status(status);
+        let error = response.json()["error"].as_str().map(str::to_string).unwrap_or_default();
+        assert!(error.contains(message), "expected an error about {:?}, got {:?}", message, error);
+        response
+    }
+}
+
+// a bare WebSocket handshake against serve(), 101 means it worked and the stream is the open connection
+pub async fn websocket(addr: SocketAddr, path: &str) -> (StatusCode, TcpStream) {
+    let mut stream = TcpStream::connect(addr).await.expect("the server is listening");
+    let handshake = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: Upgrade\r\nUpgrade: websocket\r\nSec-WebSocket-Version: 13\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n",
+        path, addr);
+    stream.write_all(handshake.as_bytes()).await.unwrap();
+    // just the head, the body of an error doesn't matter here
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        let mut byte = [0];
+        stream.read_exact(&mut byte).await.expect("a response");
+        head.push(byte[0]);
+    }
+    let status = String::from_utf8_lossy(&head).split_whitespace().nth(1).and_then(|s| s.parse().ok()).expect("a status line");
+    (StatusCode::from_u16(status).unwrap(), stream)
+}
+
+pub fn id(value: &Value) -> u32 {
+    value["id"].as_u64().unwrap_or_else(|| panic!("no id in {}", value)) as u32
+}
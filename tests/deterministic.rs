@@ -0,0 +1,77 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::TestApp;
+use rust_hello_http::deterministic::{FrozenClock, Randomness, Time};
+use rust_hello_http::trash;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+// a battle and a few wild Pokemon, with nothing pinned down by the requests themselves
+async fn play(app: &TestApp) -> Value {
+    let charmander = app.create_pokemon("Charmander", 20).await;
+    let pidgey = app.create_pokemon("Pidgey", 18).await;
+    let battle = app.post("/battles", json!({ "side_a": [charmander], "side_b": [pidgey] })).await.assert_status(StatusCode::CREATED).json();
+    let mut wild = Vec::new();
+    for _ in 0..5 {
+        let encounter = app.get("/encounters").await.assert_status(StatusCode::OK).json();
+        wild.push(json!([encounter["pokemon"]["species"], encounter["pokemon"]["level"], encounter["pokemon"]["shiny"], encounter["pokemon"]["ivs"]]));
+    }
+    json!({ "seed": battle["seed"], "winner": battle["outcome"]["winner"], "wild": wild })
+}
+
+#[tokio::test]
+async fn seeded_and_frozen() {
+    let pinned = || TestApp::with(|builder| builder.randomness(Randomness::seeded(42)).time(Time::frozen(1_700_000_000)));
+    let (first, second) = (pinned(), pinned());
+    assert_eq!(play(&first).await, play(&second).await);
+    assert_ne!(play(&first).await, play(&TestApp::with(|builder| builder.randomness(Randomness::seeded(43)))).await);
+
+    let pokemon = first.get("/pokemon/1").await.json();
+    assert_eq!(pokemon["capture"]["captured_at"], 1_700_000_000);
+}
+
+#[tokio::test]
+async fn the_clock_decides_what_expires() {
+    let clock = Arc::new(FrozenClock::at(1_000));
+    let app = TestApp::with(|builder| builder.time(Time::new(clock.clone())));
+    let pikachu = app.create_pokemon("Pikachu", 10).await;
+    app.delete(&format!("/pokemon/{}", pikachu)).await.assert_status(StatusCode::NO_CONTENT);
+    let trashed = app.get("/trash").await.json();
+    assert_eq!(trashed[0]["deleted_at"], 1_000);
+
+    clock.advance(59);
+    assert_eq!(trash::purge_older_than(&app.state, Duration::from_secs(60)), 0);
+    clock.advance(1);
+    assert_eq!(trash::purge_older_than(&app.state, Duration::from_secs(60)), 1);
+
+    // a release's confirmation runs out on the same clock
+    let eevee = app.create_pokemon("Eevee", 10).await;
+    let release = app.request(Method::POST, &format!("/pokemon/{}/release", eevee)).send().await.assert_status(StatusCode::ACCEPTED).json();
+    assert_eq!(release["expires_at"], 1_060 + 5 * 60);
+    clock.advance(5 * 60 + 1);
+    app.post(&format!("/pokemon/{}/release", eevee), json!({ "token": release["token"] })).await
+        .assert_error(StatusCode::FORBIDDEN, "expired");
+}
+
+#[tokio::test]
+async fn shares_and_the_daycare_go_by_the_same_clock() {
+    let clock = Arc::new(FrozenClock::at(1_000));
+    let app = TestApp::with(|builder| builder.time(Time::new(clock.clone())));
+    let pikachu = app.create_pokemon("Pikachu", 10).await;
+    let share = app.post(&format!("/pokemon/{}/share?expires_in=60", pikachu), json!({})).await.assert_status(StatusCode::CREATED).json();
+    assert_eq!(share["expires_at"], 1_060);
+    let path = format!("/shares/{}", share["token"].as_str().unwrap());
+    clock.advance(60);
+    app.get(&path).await.assert_status(StatusCode::OK);
+    clock.advance(1);
+    app.get(&path).await.assert_error(StatusCode::FORBIDDEN, "expired");
+
+    let exp = app.get(&format!("/pokemon/{}", pikachu)).await.json()["exp"].as_u64().unwrap();
+    app.post("/daycare/deposit", json!({ "pokemon_id": pikachu })).await.assert_status(StatusCode::CREATED);
+    clock.advance(90);
+    let stay = app.post("/daycare/withdraw", json!({ "pokemon_id": pikachu })).await.assert_status(StatusCode::OK).json();
+    assert_eq!(stay["exp_gained"], 90);
+    assert_eq!(stay["pokemon"]["exp"].as_u64().unwrap(), exp + 90);
+}
@@ -0,0 +1,311 @@
+// quotas, rules, scripts, plugins and proxies, everything an operator can add on top of the defaults
+mod common;
+
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use axum::{http::{HeaderMap, HeaderName, Method, StatusCode}, routing::get, Router};
+use common::TestApp;
+use rust_hello_http::{
+    achievements::Event,
+    concurrency::ConcurrencyLimits,
+    plugins::Plugin,
+    proxy::{ClientIp, TrustedProxies},
+    quotas::Quotas,
+    ratelimit::{Budget, RateLimits, Tier},
+    rules::Rules,
+    scripting::Scripts,
+    state::SharedState,
+};
+use serde_json::json;
+
+#[tokio::test]
+async fn quotas() {
+    let app = TestApp::with(|builder| builder.quotas(Quotas { pokemon: Some(1), pc_box: None, team: 6 }));
+    let pikachu = app.create_pokemon("Pikachu", 10).await;
+    let eevee = app.create_pokemon("Eevee", 10).await;
+    let error = app.post("/trainers", json!({ "name": "Ash", "team": [pikachu, eevee] })).await
+        .assert_error(StatusCode::FORBIDDEN, "at most 1 Pokemon")
+        .json();
+    assert_eq!(error["quota"], "pokemon");
+    assert_eq!(error["limit"], 1);
+    app.create_trainer("Ash", &[pikachu]).await;
+}
+
+#[tokio::test]
+async fn rules() {
+    let rules: Rules = serde_json::from_value(json!({
+        "rules": [{ "name": "no legendaries", "matching": { "legendary": true }, "kind": "banned" }]
+    })).unwrap();
+    let app = TestApp::with(|builder| builder.rules(rules));
+    let error = app.post("/pokemon", json!({ "name": "Mewtwo", "poke_type": "psychic", "level": 70 })).await
+        .assert_error(StatusCode::UNPROCESSABLE_ENTITY, "no legendaries")
+        .json();
+    assert_eq!(error["rule"], "no legendaries");
+    app.create_pokemon("Pikachu", 10).await;
+}
+
+#[tokio::test]
+async fn scripts() {
+    let dir = tempfile::tempdir().unwrap();
+    let script = r#"
+        fn before_create(pokemon) {
+            if pokemon.species == "Ditto" {
+                throw "no Ditto on this server";
+            }
+            if pokemon.level > 50 {
+                throw "nothing over level 50";
+            }
+            pokemon.nickname = "Sparky";
+            pokemon
+        }
+    "#;
+    std::fs::write(dir.path().join("house-rules.rhai"), script).unwrap();
+    let scripts = Scripts::from_dir(dir.path()).unwrap();
+    let app = TestApp::with(|builder| builder.scripts(scripts));
+
+    app.post("/pokemon", json!({ "name": "Ditto", "poke_type": "normal", "level": 10 })).await
+        .assert_error(StatusCode::UNPROCESSABLE_ENTITY, "no Ditto");
+    let pikachu = app.create_pokemon("Pikachu", 10).await;
+    assert_eq!(app.get(&format!("/pokemon/{}", pikachu)).await.json()["nickname"], "Sparky");
+
+    // a clone is a new Pokemon to the scripts too
+    app.post(&format!("/pokemon/{}/clone", pikachu), json!({ "level": 60 })).await
+        .assert_error(StatusCode::UNPROCESSABLE_ENTITY, "nothing over level 50");
+    let copy = app.post(&format!("/pokemon/{}/clone", pikachu), json!({ "level": 20 })).await.assert_status(StatusCode::CREATED).json();
+    assert_eq!((&copy["level"], &copy["nickname"]), (&json!(20), &json!("Sparky")));
+}
+
+#[tokio::test]
+async fn scripts_see_imports_as_they_will_be_stored() {
+    let dir = tempfile::tempdir().unwrap();
+    let script = r#"
+        fn before_create(pokemon) {
+            if pokemon.nickname == "Boom" {
+                throw "no Boom on this server";
+            }
+            pokemon
+        }
+    "#;
+    std::fs::write(dir.path().join("house-rules.rhai"), script).unwrap();
+    let app = TestApp::with(|builder| builder.scripts(Scripts::from_dir(dir.path()).unwrap()));
+
+    // the nickname comes from the paste, and the Charmander before it isn't kept either
+    let paste = "Charmander\nLevel: 12\n\nBoom (Pikachu)\nLevel: 50\n- Thunderbolt\n";
+    app.request(Method::POST, "/pokemon/import").text(paste).send().await
+        .assert_error(StatusCode::UNPROCESSABLE_ENTITY, "no Boom");
+    assert_eq!(app.get("/pokemon").await.json(), json!([]));
+}
+
+#[tokio::test]
+async fn scripts_that_dont_compile_are_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("broken.rhai"), "fn before_create(pokemon {").unwrap();
+    let error = Scripts::from_dir(dir.path()).unwrap_err();
+    assert!(error.contains("broken.rhai"), "{}", error);
+}
+
+// counts battle wins and answers GET /wins
+struct Wins(Arc<AtomicUsize>);
+
+impl Plugin for Wins{
+    fn name(&self) -> &str {
+        "wins"
+    }
+
+    fn on_event(&self, _trainer_id: u32, event: Event<'_>) {
+        if let Event::BattleWon = event {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn extra_routes(&self) -> Router<SharedState> {
+        let wins = self.0.clone();
+        Router::new().route("/wins", get(move || async move { wins.load(Ordering::SeqCst).to_string() }))
+    }
+}
+
+#[tokio::test]
+async fn plugins() {
+    let wins = Arc::new(AtomicUsize::new(0));
+    let app = TestApp::with(|builder| builder.plugin(Wins(wins.clone())));
+    assert_eq!(app.get("/wins").await.assert_status(StatusCode::OK).text(), "0");
+
+    let gym = json!({ "name": "Pewter Gym", "leader": "Brock", "badge": "Boulder Badge", "team": [{ "name": "Geodude", "level": 5 }] });
+    app.post("/gyms", gym).await.assert_status(StatusCode::CREATED);
+    let blastoise = app.create_pokemon("Blastoise", 100).await;
+    let ash = app.create_trainer("Ash", &[blastoise]).await;
+    app.post_as(ash, "/gyms/1/challenge", json!({ "trainer_id": ash, "seed": 1 })).await.assert_status(StatusCode::OK);
+    assert_eq!(app.get("/wins").await.text(), "1");
+    assert_eq!(wins.load(Ordering::SeqCst), 1);
+}
+
+// says who it thinks is asking
+struct WhoAmI;
+
+impl Plugin for WhoAmI{
+    fn name(&self) -> &str {
+        "whoami"
+    }
+
+    fn extra_routes(&self) -> Router<SharedState> {
+        Router::new().route("/whoami", get(|ClientIp(ip): ClientIp| async move { ip.map_or("-".to_string(), |ip| ip.to_string()) }))
+    }
+}
+
+#[tokio::test]
+async fn client_ips_behind_trusted_proxies() {
+    let headers = |pairs: &[(&str, &str)]| {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(HeaderName::from_bytes(name.as_bytes()).unwrap(), value.parse().unwrap());
+        }
+        headers
+    };
+    let ip = |ip: &str| ip.parse::<IpAddr>().unwrap();
+    let proxies = TrustedProxies::parse("127.0.0.1, 10.0.0.0/8").unwrap();
+    assert!(TrustedProxies::parse("10.0.0.0/33").is_err());
+
+    // straight from the client, its headers mean nothing
+    let spoofed = headers(&[("x-forwarded-for", "6.6.6.6")]);
+    assert_eq!(proxies.client_ip(ip("203.0.113.9"), &spoofed), ip("203.0.113.9"));
+    // through two proxies, the made-up entry left of the real client is ignored
+    let chain = headers(&[("x-forwarded-for", "6.6.6.6, 198.51.100.7, 10.1.2.3")]);
+    assert_eq!(proxies.client_ip(ip("127.0.0.1"), &chain), ip("198.51.100.7"));
+    assert_eq!(proxies.client_ip(ip("::ffff:127.0.0.1"), &chain), ip("198.51.100.7"));
+    // Forwarded wins over X-Forwarded-For, with ports and IPv6 in brackets
+    let forwarded = headers(&[("forwarded", "for=192.0.2.60;proto=https, for=\"[2001:db8::1]:4711\""), ("x-forwarded-for", "6.6.6.6")]);
+    assert_eq!(proxies.client_ip(ip("10.0.0.1"), &forwarded), ip("2001:db8::1"));
+    // a hop that doesn't say, the proxy is as close as it gets
+    assert_eq!(proxies.client_ip(ip("10.0.0.1"), &headers(&[("forwarded", "for=unknown")])), ip("10.0.0.1"));
+
+    let app = TestApp::with(|builder| builder.trusted_proxies(proxies).plugin(WhoAmI));
+    let addr = app.serve().await;
+    let client = reqwest::Client::new();
+    let whoami = |forwarded_for: &'static str| client.get(format!("http://{}/whoami", addr)).header("x-forwarded-for", forwarded_for).send();
+    assert_eq!(whoami("198.51.100.7").await.unwrap().text().await.unwrap(), "198.51.100.7");
+    // in-process there's no peer to go by
+    assert_eq!(app.get("/whoami").await.text(), "-");
+}
+
+#[tokio::test]
+async fn rate_limit_tiers() {
+    let app = TestApp::with(|builder| builder.rate_limits(RateLimits::new()
+        .budget(Tier::Anonymous, Budget { reads: 2, writes: 1 })
+        .budget(Tier::Free, Budget { reads: 3, writes: 0 })
+        .key("k3y", Tier::Premium)));
+
+    let first = app.get("/pokemon").await.assert_status(StatusCode::OK);
+    assert_eq!(first.header("x-ratelimit-tier").unwrap(), "anonymous");
+    assert_eq!(first.header("x-ratelimit-limit").unwrap(), "2");
+    assert_eq!(first.header("x-ratelimit-remaining").unwrap(), "1");
+    app.get("/pokemon").await.assert_status(StatusCode::OK);
+    let limited = app.get("/pokemon").await.assert_error(StatusCode::TOO_MANY_REQUESTS, "anonymous tier gets 2 reads a minute");
+    assert_eq!(limited.header("x-ratelimit-remaining").unwrap(), "0");
+    assert!(limited.header("retry-after").is_some());
+    // writes have their own budget
+    app.create_pokemon("Pikachu", 5).await;
+    app.post("/pokemon", json!({ "name": "Eevee", "poke_type": "Normal", "level": 5 })).await.assert_status(StatusCode::TOO_MANY_REQUESTS);
+
+    // X-Trainer-Id doesn't get out of it, however many IDs are tried
+    for id in 1..4 {
+        app.request(Method::GET, "/pokemon").trainer(id).send().await
+            .assert_error(StatusCode::TOO_MANY_REQUESTS, "anonymous tier");
+    }
+
+    // a trainer who logged in has their own, and 0 is no limit
+    let session = app.state.auth.issue(1, app.state.time.now()).token;
+    let trainer = app.request(Method::GET, "/pokemon").session(&session).send().await.assert_status(StatusCode::OK);
+    assert_eq!(trainer.header("x-ratelimit-tier").unwrap(), "free");
+    assert_eq!(trainer.header("x-ratelimit-remaining").unwrap(), "2");
+    let write = app.request(Method::POST, "/pokemon").session(&session).json(json!({ "name": "Eevee", "poke_type": "Normal", "level": 5 })).send().await
+        .assert_status(StatusCode::CREATED);
+    assert!(write.header("x-ratelimit-limit").is_none());
+
+    let premium = app.request(Method::GET, "/pokemon").header("x-api-key", "k3y").send().await.assert_status(StatusCode::OK);
+    assert_eq!(premium.header("x-ratelimit-tier").unwrap(), "premium");
+    assert_eq!(premium.header("x-ratelimit-limit").unwrap(), "1200");
+    app.request(Method::GET, "/pokemon").header("x-api-key", "nope").send().await.assert_status(StatusCode::UNAUTHORIZED);
+
+    // off unless configured
+    assert!(TestApp::new().get("/pokemon").await.header("x-ratelimit-limit").is_none());
+}
+
+// a request that stays in flight until it's let go
+#[derive(Clone, Default)]
+struct Slow{
+    started: Arc<tokio::sync::Notify>,
+    release: Arc<tokio::sync::Notify>,
+}
+
+impl Plugin for Slow{
+    fn name(&self) -> &str {
+        "slow"
+    }
+
+    fn extra_routes(&self) -> Router<SharedState> {
+        let slow = self.clone();
+        Router::new().route("/slow", get(move || async move {
+            slow.started.notify_one();
+            slow.release.notified().await;
+            "done"
+        }))
+    }
+}
+
+#[tokio::test]
+async fn load_shedding() {
+    let slow = Slow::default();
+    let app = TestApp::with(|builder| builder
+        .plugin(slow.clone())
+        .concurrency_limits(ConcurrencyLimits::default().global(2).route("/slow", 1).retry_after(3)));
+
+    let (first, ()) = tokio::join!(app.get("/slow"), async {
+        slow.started.notified().await;
+        // the route is full, the rest of the server isn't
+        let busy = app.get("/slow").await.assert_error(StatusCode::SERVICE_UNAVAILABLE, "/slow is busy");
+        assert_eq!(busy.header("retry-after").unwrap(), "3");
+        app.get("/pokemon").await.assert_status(StatusCode::OK);
+        slow.release.notify_one();
+    });
+    first.assert_status(StatusCode::OK);
+
+    let app = TestApp::with(|builder| builder.plugin(slow.clone()).concurrency_limits(ConcurrencyLimits::default().global(1)));
+    let (first, ()) = tokio::join!(app.get("/slow"), async {
+        slow.started.notified().await;
+        app.get("/pokemon").await.assert_error(StatusCode::SERVICE_UNAVAILABLE, "the server is busy");
+        slow.release.notify_one();
+    });
+    first.assert_status(StatusCode::OK);
+    // the permit went back once it was done
+    app.get("/pokemon").await.assert_status(StatusCode::OK);
+}
+
+#[tokio::test]
+async fn battles_dont_hold_up_other_requests() {
+    let dir = tempfile::tempdir().unwrap();
+    let script = r#"
+        fn damage(hit) {
+            let n = 0;
+            while n < 100000 { n += 1; }
+            hit.damage
+        }
+    "#;
+    std::fs::write(dir.path().join("slow-damage.rhai"), script).unwrap();
+    let app = TestApp::with(|builder| builder.scripts(Scripts::from_dir(dir.path()).unwrap()));
+    let (pikachu, pidgey) = (app.create_pokemon("Pikachu", 30).await, app.create_pokemon("Pidgey", 30).await);
+
+    // on the test's one thread a battle run in the handler would finish before the list got a look in
+    let battled = std::sync::atomic::AtomicBool::new(false);
+    tokio::join!(
+        async {
+            app.post("/battles", json!({ "side_a": [pikachu], "side_b": [pidgey], "seed": 7 })).await.assert_status(StatusCode::CREATED);
+            battled.store(true, Ordering::SeqCst);
+        },
+        async {
+            app.get("/pokemon").await.assert_status(StatusCode::OK);
+            assert!(!battled.load(Ordering::SeqCst), "the list waited for the battle");
+        },
+    );
+}
@@ -0,0 +1,125 @@
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::{extract::State, routing::post, Json, Router};
+use axum::http::StatusCode;
+use common::TestApp;
+use rust_hello_http::models::{Trade, TradeStatus};
+use rust_hello_http::notify::{self, Notice, Notifier};
+use serde_json::{json, Value};
+
+type Posted = Arc<Mutex<Vec<Value>>>;
+
+// a webhook that keeps whatever's posted to it, at /slack and at /api/webhooks/... like Discord's
+// /flaky turns the first two posts away
+async fn webhook() -> (String, Posted) {
+    async fn keep(State(posted): State<Posted>, Json(body): Json<Value>) {
+        posted.lock().unwrap().push(body);
+    }
+    let posted = Posted::default();
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let router = Router::new()
+        .route("/slack", post(keep))
+        .route("/discord.com/api/webhooks/1", post(keep))
+        .route("/flaky", post(move |posted: State<Posted>, body: Json<Value>| async move {
+            if attempts.fetch_add(1, Ordering::SeqCst) < 2 {
+                return StatusCode::INTERNAL_SERVER_ERROR;
+            }
+            keep(posted, body).await;
+            StatusCode::OK
+        }))
+        .with_state(posted.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    (format!("http://{}", addr), posted)
+}
+
+async fn wait_for(posted: &Posted, count: usize) -> Vec<Value> {
+    for _ in 0..100 {
+        if posted.lock().unwrap().len() >= count {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    // long enough for anything that shouldn't have been sent to show up
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    posted.lock().unwrap().clone()
+}
+
+#[tokio::test]
+async fn legendaries_and_trades() {
+    let (url, posted) = webhook().await;
+    let app = TestApp::with(|builder| builder.notifier(Notifier::webhook(format!("{}/slack", url))));
+    notify::spawn_notifier(app.state.clone());
+
+    app.create_pokemon("Pikachu", 10).await;
+    let mewtwo = app.create_pokemon("Mewtwo", 70).await;
+    let ash = app.create_trainer("Ash", &[1]).await;
+    let gary = app.create_trainer("Gary", &[mewtwo]).await;
+    let trade = app.post_as(ash, "/trades", json!({ "from_trainer": ash, "to_trainer": gary, "offered": 1, "requested": mewtwo })).await
+        .assert_status(StatusCode::CREATED).json();
+    app.post_as(gary, &format!("/trades/{}/accept", trade["id"]), json!({ "trainer_id": gary })).await.assert_status(StatusCode::OK);
+
+    let posted = wait_for(&posted, 2).await;
+    assert_eq!(posted, [
+        json!({ "text": "A legendary Mewtwo was created! #2 Mewtwo, level 70" }),
+        json!({ "text": "Trade 1 done: Ash gave #1 to Gary for #2" }),
+    ]);
+}
+
+#[tokio::test]
+async fn templates_filters_and_rate_limit() {
+    let (url, posted) = webhook().await;
+    let notifier = Notifier::webhook(format!("{}/discord.com/api/webhooks/1", url))
+        .events(&["legendary_created"]).unwrap()
+        .template("legendary_created", "{species} {nope} {{id}}").unwrap()
+        .rate_limit(2);
+    let app = TestApp::with(|builder| builder.notifier(notifier));
+    notify::spawn_notifier(app.state.clone());
+
+    for _ in 0..4 {
+        app.create_pokemon("Mew", 5).await;
+    }
+    app.state.notifier.notify(Notice::BackupFailed { error: "disk full".to_string() });
+
+    // Discord gets "content", the third and fourth are over the limit, and backups weren't asked for
+    let posted = wait_for(&posted, 2).await;
+    assert_eq!(posted, [json!({ "content": "Mew {nope} {1}" }), json!({ "content": "Mew {nope} {2}" })]);
+
+    assert!(Notifier::default().events(&["pokemon_sneezed"]).unwrap_err().contains("unknown notification"));
+}
+
+#[tokio::test]
+async fn failed_posts_are_retried() {
+    let (url, posted) = webhook().await;
+    let app = TestApp::with(|builder| builder.notifier(Notifier::webhook(format!("{}/flaky", url))));
+    notify::spawn_notifier(app.state.clone());
+    app.state.notifier.notify(Notice::BackupFailed { error: "disk full".to_string() });
+    for _ in 0..100 {
+        if app.state.notifier.failed() == 1 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(app.state.notifier.failed(), 1);
+
+    // the scheduler runs the sweep, the second one gets it through
+    let error = notify::retry_failed(&app.state).await.unwrap_err();
+    assert_eq!(error, "1 of 1 notifications still didn't go through");
+    notify::retry_failed(&app.state).await.unwrap();
+    assert_eq!(*posted.lock().unwrap(), [json!({ "text": "Backup failed: disk full" })]);
+    assert_eq!(app.state.notifier.failed(), 0);
+}
+
+#[tokio::test]
+async fn rendering() {
+    let notifier = Notifier::webhook("http://localhost/slack");
+    let trade = Trade { id: 3, from_trainer: 1, to_trainer: 2, offered: 4, requested: 5, status: TradeStatus::Accepted };
+    let notice = Notice::TradeCompleted { trade, from_name: "{to_trainer}".to_string(), to_name: "Gary".to_string() };
+    assert_eq!(notifier.render(&notice), "Trade 3 done: {to_trainer} gave #4 to Gary for #5");
+    assert_eq!(notifier.render(&Notice::BackupFailed { error: "disk full".to_string() }), "Backup failed: disk full");
+}
@@ -0,0 +1,245 @@
+mod common;
+
+use axum::extract::State;
+use axum::http::{Method, StatusCode};
+use axum::routing::{get, post};
+use axum::{Form, Json, Router};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use common::{TestApp, TestResponse};
+use ring::rand::SystemRandom;
+use ring::rsa::PublicKeyComponents;
+use ring::signature::{RsaKeyPair, RSA_PKCS1_SHA256};
+use rust_hello_http::auth::Auth;
+use rust_hello_http::deterministic::{Clock, FrozenClock, Time};
+use rust_hello_http::models::unix_time;
+use rust_hello_http::oidc::{Logins, Provider};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// what the fake provider does next, set by each test as it goes
+#[derive(Default)]
+struct Provided{
+    issuer: String,
+    // in its JWKS, all with the same key
+    published: Vec<&'static str>,
+    jwks_fetches: u32,
+    // what its next ID token is signed as and says
+    kid: &'static str,
+    claims: Value,
+    // from the login the browser was sent off with
+    challenge: String,
+}
+
+type Fake = Arc<Mutex<Provided>>;
+
+fn key() -> RsaKeyPair {
+    RsaKeyPair::from_der(include_bytes!("oidc/provider.der")).unwrap()
+}
+
+fn encode(value: &Value) -> String {
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(value).unwrap())
+}
+
+fn id_token(kid: &str, claims: &Value) -> String {
+    let signed = format!("{}.{}", encode(&json!({ "alg": "RS256", "typ": "JWT", "kid": kid })), encode(claims));
+    let key = key();
+    let mut signature = vec![0; key.public().modulus_len()];
+    key.sign(&RSA_PKCS1_SHA256, &SystemRandom::new(), signed.as_bytes(), &mut signature).unwrap();
+    format!("{}.{}", signed, URL_SAFE_NO_PAD.encode(signature))
+}
+
+// an OIDC provider like Keycloak, with discovery, a JWKS and a token endpoint
+async fn fake_provider() -> Fake {
+    let fake = Fake::default();
+    let router = Router::new()
+        .route("/.well-known/openid-configuration", get(|State(fake): State<Fake>| async move {
+            let issuer = fake.lock().unwrap().issuer.clone();
+            Json(json!({
+                "issuer": issuer,
+                "authorization_endpoint": format!("{}/authorize", issuer),
+                "token_endpoint": format!("{}/token", issuer),
+                "jwks_uri": format!("{}/jwks", issuer),
+            }))
+        }))
+        .route("/jwks", get(|State(fake): State<Fake>| async move {
+            let mut fake = fake.lock().unwrap();
+            fake.jwks_fetches += 1;
+            let public = PublicKeyComponents::<Vec<u8>>::from(key().public());
+            let keys: Vec<Value> = fake.published.iter()
+                .map(|kid| json!({ "kty": "RSA", "use": "sig", "kid": kid, "n": URL_SAFE_NO_PAD.encode(&public.n), "e": URL_SAFE_NO_PAD.encode(&public.e) }))
+                .collect();
+            Json(json!({ "keys": keys }))
+        }))
+        .route("/token", post(|State(fake): State<Fake>, Form(form): Form<HashMap<String, String>>| async move {
+            let fake = fake.lock().unwrap();
+            let verifier = URL_SAFE_NO_PAD.encode(Sha256::digest(form["code_verifier"].as_bytes()));
+            if form["code"] != "the-code" || form["client_secret"] != "shh" || verifier != fake.challenge {
+                return (StatusCode::BAD_REQUEST, Json(json!({ "error": "invalid_grant", "error_description": "Code not valid" })));
+            }
+            (StatusCode::OK, Json(json!({ "access_token": "at", "token_type": "Bearer", "id_token": id_token(fake.kid, &fake.claims) })))
+        }))
+        .with_state(fake.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    fake.lock().unwrap().issuer = format!("http://{}/realms/pokemon", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, Router::new().nest("/realms/pokemon", router)).await.unwrap() });
+    fake
+}
+
+fn claims(fake: &Fake, subject: &str, nonce: &str) -> Value {
+    json!({
+        "iss": fake.lock().unwrap().issuer,
+        "aud": "pokemon-app",
+        "sub": subject,
+        "exp": unix_time() + 300,
+        "nonce": nonce,
+        "name": "Misty",
+        "email": "misty@example.com",
+        "email_verified": true,
+    })
+}
+
+// off to the provider and back, with whatever change to the claims it makes this time
+async fn log_in(app: &TestApp, fake: &Fake, subject: &str, change: impl FnOnce(&mut Value)) -> TestResponse {
+    let redirect = app.get("/auth/keycloak/login").await.assert_status(StatusCode::SEE_OTHER);
+    let location = reqwest::Url::parse(redirect.header("location").unwrap().to_str().unwrap()).unwrap();
+    let query: HashMap<String, String> = location.query_pairs().into_owned().collect();
+    let mut claims = claims(fake, subject, &query["nonce"]);
+    change(&mut claims);
+    {
+        let mut fake = fake.lock().unwrap();
+        fake.claims = claims;
+        fake.challenge = query["code_challenge"].clone();
+    }
+    app.get(&format!("/auth/keycloak/callback?code=the-code&state={}", query["state"])).await
+}
+
+#[tokio::test]
+async fn oidc_logins() {
+    let fake = fake_provider().await;
+    fake.lock().unwrap().published = vec!["one"];
+    fake.lock().unwrap().kid = "one";
+    let issuer = fake.lock().unwrap().issuer.clone();
+    let app = TestApp::with(|builder| builder.logins(Logins::default().provider(Provider::oidc("keycloak", &issuer, "pokemon-app", "shh"))));
+    assert_eq!(app.get("/auth/providers").await.json(), json!(["keycloak"]));
+    app.get("/auth/facebook/login").await.assert_error(StatusCode::NOT_FOUND, "no logging in with facebook");
+
+    let redirect = app.get("/auth/keycloak/login").await.assert_status(StatusCode::SEE_OTHER);
+    let location = redirect.header("location").unwrap().to_str().unwrap();
+    assert!(location.starts_with(&format!("{}/authorize?response_type=code&client_id=pokemon-app", issuer)), "{}", location);
+    assert!(location.contains("redirect_uri=http%3A%2F%2Flocalhost%3A3000%2Fauth%2Fkeycloak%2Fcallback"));
+    assert!(location.contains("code_challenge_method=S256"));
+
+    // the first time makes a trainer, after that it's the same one
+    let first = log_in(&app, &fake, "user-1", |_| {}).await.assert_status(StatusCode::CREATED).json();
+    assert_eq!(first["outcome"], "created");
+    assert_eq!((first["trainer"]["name"].as_str(), first["trainer"]["email_verified"].as_bool()), (Some("Misty"), Some(true)));
+    assert_eq!(first["trainer"]["logins"], json!([{ "provider": "keycloak", "subject": "user-1" }]));
+    let again = log_in(&app, &fake, "user-1", |c| c["name"] = json!("Someone Else")).await.assert_status(StatusCode::OK).json();
+    assert_eq!((again["outcome"].as_str(), again["trainer"]["id"].as_u64()), (Some("existing"), first["trainer"]["id"].as_u64()));
+    assert_eq!(app.get("/trainers").await.json().as_array().unwrap().len(), 1);
+
+    // another account with the trainer's verified address logs in as them too
+    let linked = log_in(&app, &fake, "user-2", |_| {}).await.assert_status(StatusCode::OK).json();
+    assert_eq!((linked["outcome"].as_str(), linked["trainer"]["id"].as_u64()), (Some("linked"), first["trainer"]["id"].as_u64()));
+    assert_eq!(linked["trainer"]["logins"].as_array().unwrap().len(), 2);
+    // an unverified one doesn't
+    let separate = log_in(&app, &fake, "user-3", |c| c["email_verified"] = json!(false)).await.assert_status(StatusCode::CREATED).json();
+    assert_eq!(separate["trainer"]["email_verified"], false);
+    // nor does one that doesn't say, or only says it as a string
+    for (subject, verified) in [("user-4", None), ("user-5", Some(json!("true")))] {
+        let separate = log_in(&app, &fake, subject, |c| match verified {
+            Some(verified) => c["email_verified"] = verified,
+            None => { c.as_object_mut().unwrap().remove("email_verified"); }
+        }).await.assert_status(StatusCode::CREATED).json();
+        assert_ne!(separate["trainer"]["id"], first["trainer"]["id"]);
+        assert_eq!(separate["trainer"]["email_verified"], false);
+    }
+
+    // the provider rotates its signing key, the new kid makes the JWKS be fetched again
+    assert_eq!(fake.lock().unwrap().jwks_fetches, 1);
+    {
+        let mut fake = fake.lock().unwrap();
+        fake.published = vec!["two", "one"];
+        fake.kid = "two";
+    }
+    log_in(&app, &fake, "user-1", |_| {}).await.assert_status(StatusCode::OK);
+    assert_eq!(fake.lock().unwrap().jwks_fetches, 2);
+
+    fake.lock().unwrap().kid = "rogue";
+    log_in(&app, &fake, "user-1", |_| {}).await.assert_error(StatusCode::UNAUTHORIZED, "isn't signed by any of its keys");
+    fake.lock().unwrap().kid = "one";
+    log_in(&app, &fake, "user-1", |c| c["exp"] = json!(unix_time() - 3600)).await.assert_error(StatusCode::UNAUTHORIZED, "has expired");
+    log_in(&app, &fake, "user-1", |c| c["aud"] = json!(["someone-else"])).await.assert_error(StatusCode::UNAUTHORIZED, "different client");
+    log_in(&app, &fake, "user-1", |c| c["iss"] = json!("https://evil.example")).await.assert_error(StatusCode::UNAUTHORIZED, "different issuer");
+    log_in(&app, &fake, "user-1", |c| c["nonce"] = json!("replayed")).await.assert_error(StatusCode::UNAUTHORIZED, "different login");
+
+    // each login finishes once
+    app.get("/auth/keycloak/callback?code=the-code&state=made-up").await.assert_error(StatusCode::BAD_REQUEST, "start it again");
+    app.get("/auth/keycloak/callback?error=access_denied&error_description=User+said+no").await
+        .assert_error(StatusCode::UNAUTHORIZED, "keycloak said User said no");
+}
+
+#[tokio::test]
+async fn logins_hand_out_sessions() {
+    let fake = fake_provider().await;
+    fake.lock().unwrap().published = vec!["one"];
+    fake.lock().unwrap().kid = "one";
+    let issuer = fake.lock().unwrap().issuer.clone();
+    let clock = Arc::new(FrozenClock::at(unix_time()));
+    let app = TestApp::with(|builder| builder
+        .logins(Logins::default().provider(Provider::oidc("keycloak", &issuer, "pokemon-app", "shh")))
+        .auth(Auth::default().trust_header(false).lifetime(Duration::from_secs(3600)))
+        .time(Time::new(clock.clone())));
+
+    let misty = log_in(&app, &fake, "user-1", |_| {}).await.assert_status(StatusCode::CREATED).json();
+    let (id, token) = (misty["trainer"]["id"].as_u64().unwrap(), misty["session"]["token"].as_str().unwrap());
+    assert_eq!((misty["session"]["trainer_id"].as_u64(), misty["session"]["expires_at"].as_u64()), (Some(id), Some(clock.now() + 3600)));
+    let inbox = format!("/trainers/{}/notifications", id);
+    app.request(Method::GET, &inbox).session(token).send().await.assert_status(StatusCode::OK);
+
+    // with the header not trusted, only the session says who's asking
+    app.request(Method::GET, &inbox).trainer(id as u32).send().await.assert_status(StatusCode::FORBIDDEN);
+    let forged = format!("{}x", token);
+    app.request(Method::GET, &inbox).session(&forged).send().await.assert_error(StatusCode::UNAUTHORIZED, "isn't valid");
+    let someone_else = log_in(&app, &fake, "user-2", |c| c["email"] = json!("brock@example.com")).await.json();
+    app.request(Method::GET, &inbox).session(someone_else["session"]["token"].as_str().unwrap()).send().await.assert_status(StatusCode::FORBIDDEN);
+
+    clock.advance(3600);
+    app.request(Method::GET, &inbox).session(token).send().await.assert_error(StatusCode::UNAUTHORIZED, "has expired");
+}
+
+#[tokio::test]
+async fn github_logins() {
+    let router = Router::new()
+        .route("/login/oauth/access_token", post(|Form(form): Form<HashMap<String, String>>| async move {
+            // a bad code is still a 200 from GitHub
+            match form["code"].as_str() {
+                "the-code" => Json(json!({ "access_token": "gho_abc", "token_type": "bearer", "scope": "read:user" })),
+                _ => Json(json!({ "error": "bad_verification_code", "error_description": "The code passed is incorrect or expired." })),
+            }
+        }))
+        .route("/user", get(|headers: axum::http::HeaderMap| async move {
+            assert_eq!(headers["authorization"], "Bearer gho_abc");
+            Json(json!({ "id": 5818, "login": "brock", "name": null, "email": "brock@example.com" }))
+        }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let github = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    let app = TestApp::with(|builder| builder.logins(Logins::default().provider(Provider::github_at(&github, &github, "id", "secret"))));
+
+    let state_of = |response: TestResponse| {
+        let location = reqwest::Url::parse(response.header("location").unwrap().to_str().unwrap()).unwrap();
+        location.query_pairs().find(|(k, _)| k == "state").unwrap().1.into_owned()
+    };
+    let state = state_of(app.get("/auth/github/login").await.assert_status(StatusCode::SEE_OTHER));
+    let brock = app.get(&format!("/auth/github/callback?code=the-code&state={}", state)).await.assert_status(StatusCode::CREATED).json();
+    assert_eq!((brock["trainer"]["name"].as_str(), brock["trainer"]["email_verified"].as_bool()), (Some("brock"), Some(false)));
+    assert_eq!(brock["trainer"]["logins"][0]["subject"], "5818");
+
+    let state = state_of(app.get("/auth/github/login").await);
+    app.get(&format!("/auth/github/callback?code=stale&state={}", state)).await.assert_error(StatusCode::UNAUTHORIZED, "incorrect or expired");
+}
@@ -0,0 +1,822 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::TestApp;
+use rust_hello_http::caching::CachePolicy;
+use rust_hello_http::headers::{self, SecurityHeaders};
+use rust_hello_http::responses::ResponseCache;
+use serde_json::json;
+
+// the smallest valid png, a single transparent pixel
+const PNG: &[u8] = &[
+    0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+    0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1f, 0x15, 0xc4,
+    0x89, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0x00, 0x01, 0x00, 0x00,
+    0x05, 0x00, 0x01, 0x0d, 0x0a, 0x2d, 0xb4, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae,
+    0x42, 0x60, 0x82,
+];
+
+#[tokio::test]
+async fn health_check() {
+    let app = TestApp::new();
+    let response = app.get("/").await.assert_status(StatusCode::OK);
+    assert!(response.text().contains("Pokemon Team API"));
+}
+
+#[tokio::test]
+async fn build_info() {
+    let app = TestApp::new();
+    let info = app.get("/version").await.assert_status(StatusCode::OK).json();
+    assert_eq!((info["name"].as_str(), info["version"].as_str()), (Some("rust-hello-http"), Some(env!("CARGO_PKG_VERSION"))));
+    let sha = info["git_sha"].as_str().unwrap();
+    assert!(sha == "unknown" || (sha.len() == 40 && sha.chars().all(|c| c.is_ascii_hexdigit())), "{}", sha);
+    assert!(info["built_at"].as_u64() > Some(1_600_000_000));
+    assert!(info["rustc"].as_str().unwrap().starts_with("rustc "));
+    assert_eq!(info["storage"], "memory");
+    // the test is built with the same features as the server
+    assert_eq!(info["features"].as_array().unwrap().iter().any(|f| f == "chaos"), cfg!(feature = "chaos"));
+    assert_eq!(info["features"].as_array().unwrap().iter().any(|f| f == "tokio-console"), cfg!(feature = "tokio-console"));
+    assert!(rust_hello_http::version::banner().starts_with(&format!("rust-hello-http {} (", env!("CARGO_PKG_VERSION"))));
+}
+
+#[tokio::test]
+async fn unknown_routes_are_404() {
+    let app = TestApp::new();
+    app.get("/nope").await.assert_status(StatusCode::NOT_FOUND);
+    app.request(Method::PATCH, "/pokemon/1").send().await.assert_status(StatusCode::METHOD_NOT_ALLOWED);
+}
+
+#[tokio::test]
+async fn create_and_read_pokemon() {
+    let app = TestApp::new();
+    let created = app.post("/pokemon", json!({ "name": "Pikachu", "poke_type": "Electric", "level": 25 })).await
+        .assert_status(StatusCode::CREATED)
+        .json();
+    assert_eq!(created["id"], 1);
+    assert_eq!(created["name"], "Pikachu");
+    assert_eq!(created["level"], 25);
+
+    let all = app.get("/pokemon").await.assert_status(StatusCode::OK).json();
+    assert_eq!(all.as_array().unwrap().len(), 1);
+    let one = app.get("/pokemon/1").await.assert_status(StatusCode::OK).json();
+    assert_eq!(one, created);
+    app.get("/pokemon/2").await.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn create_pokemon_rejects_bad_input() {
+    let app = TestApp::new();
+    app.post("/pokemon", json!({ "name": "Pikachu", "poke_type": "Electric", "level": 0 })).await
+        .assert_status(StatusCode::BAD_REQUEST);
+    app.post("/pokemon", json!({ "name": "Pikachu", "poke_type": "Electric", "level": 101 })).await
+        .assert_status(StatusCode::BAD_REQUEST);
+    // offline, so the type can't be looked up
+    app.post("/pokemon", json!({ "name": "Pikachu", "level": 5 })).await
+        .assert_error(StatusCode::BAD_REQUEST, "poke_type is required");
+    app.post("/pokemon", json!({ "name": "Pikachu", "poke_type": "Electric", "level": 5, "original_trainer": 9 })).await
+        .assert_error(StatusCode::NOT_FOUND, "trainer 9");
+    // missing fields and broken JSON are the extractor's
+    app.post("/pokemon", json!({ "name": "Pikachu" })).await.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    app.request(Method::POST, "/pokemon").body("application/json", "{").send().await.assert_status(StatusCode::BAD_REQUEST);
+    app.request(Method::POST, "/pokemon").text("hi").send().await.assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
+
+#[tokio::test]
+async fn filter_pokemon() {
+    let app = TestApp::new();
+    app.create_pokemon("Pikachu", 10).await;
+    let bulbasaur = app.create_pokemon("Bulbasaur", 20).await;
+    app.post(&format!("/pokemon/{}/favorite", bulbasaur), json!({})).await.assert_status(StatusCode::OK);
+    app.post(&format!("/pokemon/{}/tags", bulbasaur), json!({ "tags": ["Starter"] })).await.assert_status(StatusCode::OK);
+
+    let favorites = app.get("/pokemon?favorite=true").await.assert_status(StatusCode::OK).json();
+    assert_eq!(favorites.as_array().unwrap().len(), 1);
+    assert_eq!(favorites[0]["id"], bulbasaur);
+    let tagged = app.get("/pokemon?tag=starter").await.assert_status(StatusCode::OK).json();
+    assert_eq!(tagged[0]["id"], bulbasaur);
+    app.get("/pokemon?favorite=maybe").await.assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn update_pokemon() {
+    let app = TestApp::new();
+    let id = app.create_pokemon("Pikachu", 10).await;
+    let updated = app.put(&format!("/pokemon/{}", id), json!({ "level": 30 })).await.assert_status(StatusCode::OK).json();
+    assert_eq!(updated["level"], 30);
+    app.put(&format!("/pokemon/{}", id), json!({ "level": 0 })).await.assert_error(StatusCode::BAD_REQUEST, "level must be 1 to 100");
+    app.put(&format!("/pokemon/{}", id), json!({ "height_m": -1.0 })).await.assert_error(StatusCode::BAD_REQUEST, "height must be between");
+    app.put(&format!("/pokemon/{}", id), json!({ "generation": 1, "region": "hoenn" })).await.assert_error(StatusCode::BAD_REQUEST, "Hoenn is a generation III region");
+    app.put("/pokemon/99", json!({ "level": 30 })).await.assert_status(StatusCode::NOT_FOUND);
+    // nothing changed on the 400
+    assert_eq!(app.get(&format!("/pokemon/{}", id)).await.json()["level"], 30);
+}
+
+#[tokio::test]
+async fn delete_pokemon_goes_to_the_trash() {
+    let app = TestApp::new();
+    let id = app.create_pokemon("Pikachu", 10).await;
+    app.delete(&format!("/pokemon/{}", id)).await.assert_status(StatusCode::NO_CONTENT);
+    app.delete(&format!("/pokemon/{}", id)).await.assert_status(StatusCode::NOT_FOUND);
+    app.get(&format!("/pokemon/{}", id)).await.assert_status(StatusCode::NOT_FOUND);
+
+    let trash = app.get("/trash").await.assert_status(StatusCode::OK).json();
+    assert_eq!(trash[0]["pokemon"]["id"], id);
+    app.post(&format!("/trash/{}/restore", id), json!({})).await.assert_status(StatusCode::OK);
+    app.post(&format!("/trash/{}/restore", id), json!({})).await.assert_status(StatusCode::NOT_FOUND);
+    app.get(&format!("/pokemon/{}", id)).await.assert_status(StatusCode::OK);
+
+    app.delete(&format!("/pokemon/{}", id)).await.assert_status(StatusCode::NO_CONTENT);
+    app.delete("/trash").await.assert_status(StatusCode::NO_CONTENT);
+    assert_eq!(app.get("/trash").await.json(), json!([]));
+}
+
+#[tokio::test]
+async fn emptying_the_trash_leaves_other_trainers_pokemon() {
+    let app = TestApp::new();
+    let pikachu = app.create_pokemon("Pikachu", 10).await;
+    let stray = app.create_pokemon("Rattata", 3).await;
+    let ash = app.create_trainer("Ash", &[pikachu]).await;
+    let gary = app.create_trainer("Gary", &[]).await;
+    app.request(Method::PUT, &format!("/pokemon/{}", pikachu)).trainer(ash).json(json!({ "visibility": "private" })).send().await
+        .assert_status(StatusCode::OK);
+    let release = |body: serde_json::Value| app.request(Method::POST, &format!("/pokemon/{}/release", pikachu)).trainer(ash).json(body);
+    let token = release(json!({})).send().await.assert_status(StatusCode::ACCEPTED).json()["token"].as_str().unwrap().to_string();
+    release(json!({ "token": token })).send().await.assert_status(StatusCode::NO_CONTENT);
+    app.delete(&format!("/pokemon/{}", stray)).await.assert_status(StatusCode::NO_CONTENT);
+
+    app.request(Method::DELETE, "/trash").trainer(gary).send().await.assert_status(StatusCode::NO_CONTENT);
+    app.delete("/trash").await.assert_status(StatusCode::NO_CONTENT);
+    // the Rattata was nobody's, so it's gone, Ash's Pikachu isn't
+    app.post(&format!("/trash/{}/restore", stray), json!({})).await.assert_status(StatusCode::NOT_FOUND);
+    app.request(Method::POST, &format!("/trash/{}/restore", pikachu)).trainer(ash).json(json!({})).send().await
+        .assert_status(StatusCode::OK);
+    assert_eq!(app.get(&format!("/trainers/{}", ash)).await.json()["team"], json!([pikachu]));
+}
+
+#[tokio::test]
+async fn owned_pokemon_have_to_be_released() {
+    let app = TestApp::new();
+    let id = app.create_pokemon("Pikachu", 10).await;
+    let trainer = app.create_trainer("Ash", &[id]).await;
+    let other = app.create_trainer("Gary", &[]).await;
+    app.delete(&format!("/pokemon/{}", id)).await.assert_error(StatusCode::FORBIDDEN, "belongs to Ash");
+    app.request(Method::DELETE, &format!("/pokemon/{}", id)).trainer(other).send().await.assert_status(StatusCode::FORBIDDEN);
+    app.request(Method::DELETE, &format!("/pokemon/{}", id)).trainer(trainer).send().await
+        .assert_error(StatusCode::CONFLICT, "release it instead");
+
+    app.request(Method::POST, &format!("/pokemon/{}/release", id)).trainer(other).send().await
+        .assert_status(StatusCode::FORBIDDEN);
+    let release = app.request(Method::POST, &format!("/pokemon/{}/release", id)).trainer(trainer).send().await
+        .assert_status(StatusCode::ACCEPTED)
+        .json();
+    let token = release["token"].as_str().expect("a release token").to_string();
+    let confirm = |token: &str| app.request(Method::POST, &format!("/pokemon/{}/release", id)).trainer(trainer).json(json!({ "token": token }));
+    app.post(&format!("/pokemon/{}/release", id), json!({ "token": token })).await.assert_status(StatusCode::FORBIDDEN);
+    confirm("wrong").send().await.assert_status(StatusCode::FORBIDDEN);
+    confirm(&token).send().await.assert_status(StatusCode::NO_CONTENT);
+    confirm(&token).send().await.assert_status(StatusCode::NOT_FOUND);
+    assert_eq!(app.get(&format!("/trainers/{}", trainer)).await.json()["team"], json!([]));
+}
+
+#[tokio::test]
+async fn clone_pokemon() {
+    let app = TestApp::new();
+    let id = app.create_pokemon("Pikachu", 10).await;
+    let copy = app.post(&format!("/pokemon/{}/clone", id), json!({ "level": 50 })).await.assert_status(StatusCode::CREATED).json();
+    assert_eq!(copy["id"], 2);
+    assert_eq!(copy["level"], 50);
+    app.request(Method::POST, &format!("/pokemon/{}/clone", id)).send().await.assert_status(StatusCode::CREATED);
+    app.post(&format!("/pokemon/{}/clone", id), json!({ "level": 0 })).await.assert_status(StatusCode::BAD_REQUEST);
+    app.post("/pokemon/99/clone", json!({})).await.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn private_pokemon_are_hidden_from_others() {
+    let app = TestApp::new();
+    let id = app.create_pokemon("Pikachu", 10).await;
+    let owner = app.create_trainer("Ash", &[id]).await;
+    app.request(Method::PUT, &format!("/pokemon/{}", id)).trainer(owner).json(json!({ "visibility": "private" })).send().await
+        .assert_status(StatusCode::OK);
+
+    app.get(&format!("/pokemon/{}", id)).await.assert_status(StatusCode::NOT_FOUND);
+    app.request(Method::GET, &format!("/pokemon/{}", id)).trainer(owner).send().await.assert_status(StatusCode::OK);
+    assert_eq!(app.get("/pokemon").await.json(), json!([]));
+    assert_eq!(app.get("/gallery").await.assert_status(StatusCode::OK).json(), json!([]));
+    app.request(Method::GET, "/pokemon").header("x-trainer-id", "ash").send().await.assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn permissions_let_other_trainers_in() {
+    let app = TestApp::new();
+    let pikachu = app.create_pokemon("Pikachu", 10).await;
+    let onix = app.create_pokemon("Onix", 12).await;
+    let ash = app.create_trainer("Ash", &[pikachu, onix]).await;
+    let misty = app.create_trainer("Misty", &[]).await;
+    app.request(Method::PUT, &format!("/pokemon/{}", pikachu)).trainer(ash).json(json!({ "visibility": "private" })).send().await
+        .assert_status(StatusCode::OK);
+    let as_misty = |method: Method, path: String| app.request(method, &path).trainer(misty);
+
+    // only the owner hands out permissions
+    let grant = |access: &str| json!({ "trainer_id": misty, "access": access });
+    as_misty(Method::POST, format!("/pokemon/{}/permissions", onix)).json(grant("write")).send().await
+        .assert_error(StatusCode::FORBIDDEN, "only Ash");
+    app.request(Method::POST, &format!("/pokemon/{}/permissions", pikachu)).trainer(ash).json(json!({ "trainer_id": ash, "access": "read" })).send().await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    // read lets Misty see the private one, but not change it
+    as_misty(Method::GET, format!("/pokemon/{}", pikachu)).send().await.assert_status(StatusCode::NOT_FOUND);
+    let given = app.request(Method::POST, &format!("/pokemon/{}/permissions", pikachu)).trainer(ash).json(grant("read")).send().await
+        .assert_status(StatusCode::CREATED).json();
+    assert_eq!(given, json!({ "trainer_id": misty, "access": "read", "pokemon_id": pikachu }));
+    as_misty(Method::GET, format!("/pokemon/{}", pikachu)).send().await.assert_status(StatusCode::OK);
+    assert_eq!(as_misty(Method::GET, "/pokemon".to_string()).send().await.json().as_array().unwrap().len(), 2);
+    as_misty(Method::POST, format!("/pokemon/{}/nickname", pikachu)).json(json!({ "nickname": "Sparky" })).send().await
+        .assert_error(StatusCode::FORBIDDEN, "hasn't let trainer");
+    as_misty(Method::POST, format!("/pokemon/{}/heal", onix)).send().await.assert_status(StatusCode::FORBIDDEN);
+    as_misty(Method::POST, format!("/pokemon/{}/release", pikachu)).send().await.assert_error(StatusCode::FORBIDDEN, "hasn't let trainer");
+    as_misty(Method::POST, "/pokemon/merge".to_string()).json(json!({ "keep": pikachu, "merge": onix })).send().await
+        .assert_error(StatusCode::FORBIDDEN, "hasn't let trainer");
+
+    // write replaces it, and still isn't enough to release it
+    app.request(Method::POST, &format!("/pokemon/{}/permissions", pikachu)).trainer(ash).json(grant("write")).send().await
+        .assert_status(StatusCode::CREATED);
+    as_misty(Method::POST, format!("/pokemon/{}/nickname", pikachu)).json(json!({ "nickname": "Sparky" })).send().await
+        .assert_status(StatusCode::OK);
+    as_misty(Method::POST, format!("/pokemon/{}/release", pikachu)).send().await.assert_status(StatusCode::FORBIDDEN);
+    assert_eq!(app.get(&format!("/trainers/{}", ash)).await.json()["permissions"].as_array().unwrap().len(), 1);
+    app.request(Method::DELETE, &format!("/pokemon/{}/permissions/{}", pikachu, misty)).trainer(ash).send().await
+        .assert_status(StatusCode::NO_CONTENT);
+    as_misty(Method::GET, format!("/pokemon/{}", pikachu)).send().await.assert_status(StatusCode::NOT_FOUND);
+
+    // on the box, it covers whatever's in there and nothing on the team
+    app.request(Method::POST, &format!("/trainers/{}/box/permissions", ash)).trainer(ash).json(grant("write")).send().await
+        .assert_status(StatusCode::CREATED);
+    as_misty(Method::POST, format!("/pokemon/{}/heal", onix)).send().await.assert_status(StatusCode::FORBIDDEN);
+    app.request(Method::PUT, &format!("/trainers/{}/team", ash)).trainer(ash).json(json!({ "team": [pikachu] })).send().await
+        .assert_status(StatusCode::OK);
+    as_misty(Method::POST, format!("/pokemon/{}/heal", onix)).send().await.assert_status(StatusCode::OK);
+    app.request(Method::DELETE, &format!("/trainers/{}/box/permissions/{}", ash, misty)).trainer(ash).send().await
+        .assert_status(StatusCode::NO_CONTENT);
+    app.request(Method::DELETE, &format!("/trainers/{}/box/permissions/{}", ash, misty)).trainer(ash).send().await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    // nobody's Pokemon are open to everyone already, and a request that doesn't say who it's from can't change anyone's
+    let wild = app.create_pokemon("Charmander", 5).await;
+    app.request(Method::POST, &format!("/pokemon/{}/permissions", wild)).trainer(ash).json(grant("read")).send().await
+        .assert_error(StatusCode::CONFLICT, "nobody owns");
+    app.post(&format!("/pokemon/{}/heal", wild), json!({})).await.assert_status(StatusCode::OK);
+    app.post(&format!("/pokemon/{}/heal", onix), json!({})).await.assert_error(StatusCode::FORBIDDEN, "say which trainer");
+    app.put(&format!("/pokemon/{}", onix), json!({ "level": 13 })).await.assert_error(StatusCode::FORBIDDEN, "belongs to Ash");
+    app.delete(&format!("/pokemon/{}", onix)).await.assert_error(StatusCode::FORBIDDEN, "belongs to Ash");
+}
+
+#[tokio::test]
+async fn gallery_shows_public_owned_pokemon() {
+    let app = TestApp::new();
+    let id = app.create_pokemon("Pikachu", 10).await;
+    app.create_trainer("Ash", &[id]).await;
+    let gallery = app.get("/gallery").await.assert_status(StatusCode::OK).json();
+    assert_eq!(gallery[0]["trainer_name"], "Ash");
+    assert_eq!(gallery[0]["pokemon"]["id"], id);
+}
+
+#[tokio::test]
+async fn stats_aggregate_and_random() {
+    let app = TestApp::new();
+    app.get("/pokemon/random").await.assert_status(StatusCode::NOT_FOUND);
+    app.create_pokemon("Pikachu", 10).await;
+    app.create_pokemon("Charmander", 20).await;
+
+    let stats = app.get("/pokemon/stats").await.assert_status(StatusCode::OK).json();
+    assert_eq!(stats["count"], 2);
+    app.get("/pokemon/aggregate?group_by=poke_type&metric=avg_level").await.assert_status(StatusCode::OK);
+    app.get("/pokemon/aggregate?group_by=nope").await.assert_error(StatusCode::BAD_REQUEST, "can't group by");
+    app.get("/pokemon/aggregate?group_by=poke_type&metric=nope").await.assert_error(StatusCode::BAD_REQUEST, "unknown metric");
+    app.get("/pokemon/aggregate").await.assert_status(StatusCode::BAD_REQUEST);
+
+    let random = app.get("/pokemon/random?poke_type=fire").await.assert_status(StatusCode::OK).json();
+    assert_eq!(random["name"], "Charmander");
+    app.get("/pokemon/random?min_level=50").await.assert_error(StatusCode::NOT_FOUND, "no pokemon match");
+}
+
+#[tokio::test]
+async fn duplicates_and_merging() {
+    let app = TestApp::new();
+    let keep = app.create_pokemon("Pikachu", 10).await;
+    let merge = app.create_pokemon("Pikachu", 10).await;
+    let other = app.create_pokemon("Bulbasaur", 10).await;
+    let groups = app.get("/pokemon/duplicates").await.assert_status(StatusCode::OK).json();
+    assert_eq!(groups.as_array().unwrap().len(), 1);
+
+    app.post("/pokemon/merge", json!({ "keep": keep, "merge": keep })).await.assert_status(StatusCode::BAD_REQUEST);
+    app.post("/pokemon/merge", json!({ "keep": keep, "merge": 99 })).await.assert_status(StatusCode::NOT_FOUND);
+    app.post("/pokemon/merge", json!({ "keep": keep, "merge": other })).await.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    app.post("/pokemon/merge", json!({ "keep": keep, "merge": merge })).await.assert_status(StatusCode::OK);
+    app.get(&format!("/pokemon/{}", merge)).await.assert_status(StatusCode::NOT_FOUND);
+
+    let mine = app.create_pokemon("Bulbasaur", 10).await;
+    let ash = app.create_trainer("Ash", &[other]).await;
+    let gary = app.create_trainer("Gary", &[mine]).await;
+    let merge = || app.request(Method::POST, "/pokemon/merge").trainer(ash).json(json!({ "keep": other, "merge": mine }));
+    merge().send().await.assert_error(StatusCode::FORBIDDEN, "hasn't let trainer");
+    app.request(Method::POST, &format!("/pokemon/{}/permissions", mine)).trainer(gary).json(json!({ "trainer_id": ash, "access": "read" })).send().await
+        .assert_status(StatusCode::CREATED);
+    merge().send().await.assert_error(StatusCode::FORBIDDEN, "hasn't let trainer");
+    app.request(Method::POST, &format!("/pokemon/{}/permissions", mine)).trainer(gary).json(json!({ "trainer_id": ash, "access": "write" })).send().await
+        .assert_status(StatusCode::CREATED);
+    merge().send().await.assert_error(StatusCode::CONFLICT, "different trainers");
+}
+
+#[tokio::test]
+async fn nicknames() {
+    let app = TestApp::new();
+    let id = app.create_pokemon("Pikachu", 10).await;
+    let path = format!("/pokemon/{}/nickname", id);
+    let named = app.post(&path, json!({ "nickname": "Sparky" })).await.assert_status(StatusCode::OK).json();
+    assert_eq!(named["name"], "Sparky");
+    app.post(&path, json!({ "nickname": "A very long nickname" })).await.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    let reset = app.post(&path, json!({ "nickname": null })).await.assert_status(StatusCode::OK).json();
+    assert_eq!(reset["name"], "Pikachu");
+    app.post("/pokemon/99/nickname", json!({ "nickname": "Sparky" })).await.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn tags_and_favorites() {
+    let app = TestApp::new();
+    let id = app.create_pokemon("Pikachu", 10).await;
+    let tagged = app.post(&format!("/pokemon/{}/tags", id), json!({ "tags": ["Fav", "shiny hunt"] })).await
+        .assert_status(StatusCode::OK)
+        .json();
+    assert_eq!(tagged["tags"], json!(["fav", "shiny hunt"]));
+    app.post(&format!("/pokemon/{}/tags", id), json!({ "tags": [" "] })).await.assert_status(StatusCode::BAD_REQUEST);
+    let too_many: Vec<String> = (0..25).map(|i| format!("tag {}", i)).collect();
+    app.post(&format!("/pokemon/{}/tags", id), json!({ "tags": too_many })).await.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    app.post("/pokemon/99/tags", json!({ "tags": ["fav"] })).await.assert_status(StatusCode::NOT_FOUND);
+
+    let counts = app.get("/tags").await.assert_status(StatusCode::OK).json();
+    assert_eq!(counts.as_array().unwrap().len(), 2);
+    let renamed = app.post("/tags/fav/rename", json!({ "to": "favorite" })).await.assert_status(StatusCode::OK).json();
+    assert_eq!(renamed["pokemon_changed"], 1);
+    app.post("/tags/nope/rename", json!({ "to": "favorite" })).await.assert_status(StatusCode::NOT_FOUND);
+    app.post("/tags/favorite/rename", json!({ "to": "" })).await.assert_status(StatusCode::BAD_REQUEST);
+    app.post("/tags/merge", json!({ "from": ["favorite", "shiny hunt"], "into": "best" })).await.assert_status(StatusCode::OK);
+    app.post("/tags/merge", json!({ "from": [], "into": "best" })).await.assert_status(StatusCode::BAD_REQUEST);
+
+    app.delete(&format!("/pokemon/{}/tags/best", id)).await.assert_status(StatusCode::OK);
+    app.delete(&format!("/pokemon/{}/tags/best", id)).await.assert_status(StatusCode::NOT_FOUND);
+
+    let favorite = app.post(&format!("/pokemon/{}/favorite", id), json!({})).await.assert_status(StatusCode::OK).json();
+    assert_eq!(favorite["favorite"], true);
+    let unfavorite = app.delete(&format!("/pokemon/{}/favorite", id)).await.assert_status(StatusCode::OK).json();
+    assert_eq!(unfavorite["favorite"], false);
+    app.delete("/pokemon/99/favorite").await.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn renaming_tags_leaves_other_trainers_pokemon() {
+    let app = TestApp::new();
+    let pikachu = app.create_pokemon("Pikachu", 10).await;
+    let eevee = app.create_pokemon("Eevee", 10).await;
+    let ash = app.create_trainer("Ash", &[pikachu]).await;
+    let gary = app.create_trainer("Gary", &[eevee]).await;
+    for (id, trainer) in [(pikachu, ash), (eevee, gary)] {
+        app.request(Method::POST, &format!("/pokemon/{}/tags", id)).trainer(trainer).json(json!({ "tags": ["fav"] })).send().await
+            .assert_status(StatusCode::OK);
+    }
+    app.request(Method::PUT, &format!("/pokemon/{}", pikachu)).trainer(ash).json(json!({ "visibility": "private" })).send().await
+        .assert_status(StatusCode::OK);
+
+    let renamed = app.request(Method::POST, "/tags/fav/rename").trainer(gary).json(json!({ "to": "mine" })).send().await
+        .assert_status(StatusCode::OK)
+        .json();
+    assert_eq!(renamed["pokemon_changed"], 1);
+    // nobody else can touch either of them, and the count doesn't give away Ash's private Pikachu
+    app.post("/tags/fav/rename", json!({ "to": "stolen" })).await.assert_status(StatusCode::NOT_FOUND);
+    let merged = app.post("/tags/merge", json!({ "from": ["fav", "mine"], "into": "stolen" })).await.assert_status(StatusCode::OK).json();
+    assert_eq!(merged["pokemon_changed"], 0);
+    let tags_of = |id: u32, trainer: u32| app.request(Method::GET, &format!("/pokemon/{}", id)).trainer(trainer).send();
+    assert_eq!(tags_of(pikachu, ash).await.json()["tags"], json!(["fav"]));
+    assert_eq!(tags_of(eevee, gary).await.json()["tags"], json!(["mine"]));
+}
+
+#[tokio::test]
+async fn exp_and_evolution() {
+    let app = TestApp::new();
+    let id = app.create_pokemon("Charmander", 15).await;
+    app.post(&format!("/pokemon/{}/evolve", id), json!({})).await.assert_error(StatusCode::UNPROCESSABLE_ENTITY, "level 16");
+    let gained = app.post(&format!("/pokemon/{}/gain-exp", id), json!({ "amount": 5000 })).await.assert_status(StatusCode::OK).json();
+    assert!(gained["levels_gained"].as_u64().unwrap() >= 1);
+    app.post(&format!("/pokemon/{}/gain-exp", id), json!({ "amount": 0 })).await.assert_status(StatusCode::BAD_REQUEST);
+    app.post("/pokemon/99/gain-exp", json!({ "amount": 10 })).await.assert_status(StatusCode::NOT_FOUND);
+
+    let evolved = app.request(Method::POST, &format!("/pokemon/{}/evolve", id)).send().await.assert_status(StatusCode::OK).json();
+    assert_eq!(evolved["species"], "Charmeleon");
+    app.post(&format!("/pokemon/{}/evolve", id), json!({ "into": "Raichu" })).await.assert_error(StatusCode::UNPROCESSABLE_ENTITY, "can't evolve into");
+    app.post("/pokemon/99/evolve", json!({})).await.assert_status(StatusCode::NOT_FOUND);
+
+    let pikachu = app.create_pokemon("Pikachu", 5).await;
+    let raichu = app.post(&format!("/pokemon/{}/evolve", pikachu), json!({ "item": "Thunder Stone" })).await
+        .assert_status(StatusCode::OK)
+        .json();
+    assert_eq!(raichu["species"], "Raichu");
+    app.post(&format!("/pokemon/{}/evolve", pikachu), json!({})).await.assert_error(StatusCode::UNPROCESSABLE_ENTITY, "does not evolve");
+}
+
+#[tokio::test]
+async fn fainted_pokemon_cant_gain_exp() {
+    let app = TestApp::new();
+    let id = app.create_pokemon("Pikachu", 5).await;
+    app.post(&format!("/pokemon/{}/damage", id), json!({ "amount": 9999 })).await.assert_status(StatusCode::OK);
+    app.post(&format!("/pokemon/{}/gain-exp", id), json!({ "amount": 10 })).await.assert_status(StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn teach_moves() {
+    let app = TestApp::new();
+    let id = app.create_pokemon("Pikachu", 30).await;
+    let path = format!("/pokemon/{}/teach", id);
+    let moves = app.get(&format!("/pokemon/{}", id)).await.json()["moves"].clone();
+    app.post(&path, json!({ "move": "Flamethrower" })).await.assert_error(StatusCode::UNPROCESSABLE_ENTITY, "can't learn");
+    app.post(&path, json!({ "move": moves[0] })).await.assert_error(StatusCode::CONFLICT, "already knows");
+    app.post(&path, json!({ "move": "Substitute" })).await.assert_error(StatusCode::CONFLICT, "pass \"forget\"");
+    app.post(&path, json!({ "move": "Substitute", "forget": "Splash" })).await.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    let taught = app.post(&path, json!({ "move": "Substitute", "forget": moves[0] })).await.assert_status(StatusCode::OK).json();
+    assert!(taught["moves"].as_array().unwrap().contains(&json!("Substitute")));
+    app.post("/pokemon/99/teach", json!({ "move": "Substitute" })).await.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn damage_heal_and_status() {
+    let app = TestApp::new();
+    let id = app.create_pokemon("Pikachu", 20).await;
+    let hurt = app.post(&format!("/pokemon/{}/damage", id), json!({ "amount": 5 })).await.assert_status(StatusCode::OK).json();
+    assert!(hurt["current_hp"].as_u64() < hurt["max_hp"].as_u64());
+    app.post(&format!("/pokemon/{}/damage", id), json!({ "amount": 0 })).await.assert_status(StatusCode::BAD_REQUEST);
+    app.post("/pokemon/99/damage", json!({ "amount": 5 })).await.assert_status(StatusCode::NOT_FOUND);
+    app.post(&format!("/pokemon/{}/damage", id), json!({ "amount": 9999 })).await.assert_status(StatusCode::OK);
+    app.post(&format!("/pokemon/{}/damage", id), json!({ "amount": 5 })).await.assert_status(StatusCode::CONFLICT);
+
+    let healed = app.post(&format!("/pokemon/{}/heal", id), json!({})).await.assert_status(StatusCode::OK).json();
+    assert_eq!(healed["current_hp"], healed["max_hp"]);
+    app.post("/pokemon/99/heal", json!({})).await.assert_status(StatusCode::NOT_FOUND);
+
+    let path = format!("/pokemon/{}/status", id);
+    // electric types can't be paralyzed
+    app.post(&path, json!({ "condition": "paralysis" })).await.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    app.post(&path, json!({ "condition": "burn" })).await.assert_status(StatusCode::OK);
+    app.post(&path, json!({ "condition": "poison" })).await.assert_error(StatusCode::CONFLICT, "already");
+    app.post(&path, json!({ "condition": "dizzy" })).await.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    app.post("/pokemon/99/status", json!({ "condition": "burn" })).await.assert_status(StatusCode::NOT_FOUND);
+
+    let cure = format!("/pokemon/{}/cure", id);
+    app.post(&cure, json!({ "condition": "poison" })).await.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    app.request(Method::POST, &cure).send().await.assert_status(StatusCode::OK);
+    app.post(&cure, json!({})).await.assert_error(StatusCode::CONFLICT, "no status condition");
+    app.post("/pokemon/99/cure", json!({})).await.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn type_matchups_and_weaknesses() {
+    let app = TestApp::new();
+    let matchup = app.get("/types/water/vs/fire").await.assert_status(StatusCode::OK).json();
+    assert_eq!(matchup["multiplier"], 2.0);
+    app.get("/types/water/vs/plasma").await.assert_status(StatusCode::NOT_FOUND);
+
+    let id = app.create_pokemon("Bulbasaur", 5).await;
+    let profile = app.get(&format!("/pokemon/{}/weaknesses", id)).await.assert_status(StatusCode::OK).json();
+    assert!(profile["weaknesses"].as_array().unwrap().iter().any(|w| w["type"] == "Fire"));
+    app.get("/pokemon/99/weaknesses").await.assert_status(StatusCode::NOT_FOUND);
+    let odd = common::id(&app.post("/pokemon", json!({ "name": "Missingno", "poke_type": "Bird", "level": 5 })).await.json());
+    app.get(&format!("/pokemon/{}/weaknesses", odd)).await.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn showdown_import_and_export() {
+    let app = TestApp::new();
+    let paste = "Sparky (Pikachu)\nLevel: 50\n- Thunderbolt\n- Quick Attack\n\nCharmander\nLevel: 12\n";
+    let imported = app.request(Method::POST, "/pokemon/import").text(paste).send().await.assert_status(StatusCode::CREATED).json();
+    assert_eq!(imported.as_array().unwrap().len(), 2);
+    assert_eq!(imported[0]["pokemon"]["name"], "Sparky");
+    app.request(Method::POST, "/pokemon/import").text("").send().await.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+
+    let export = app.get("/pokemon/1/export").await.assert_status(StatusCode::OK);
+    assert!(export.text().starts_with("Sparky (Pikachu)"));
+    app.get("/pokemon/1/export?format=yaml").await.assert_status(StatusCode::BAD_REQUEST);
+    app.get("/pokemon/99/export").await.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn sprites() {
+    let app = TestApp::new();
+    let id = app.create_pokemon("Pikachu", 5).await;
+    let path = format!("/pokemon/{}/sprite", id);
+    app.get(&path).await.assert_error(StatusCode::NOT_FOUND, "no sprite");
+
+    let uploaded = app.request(Method::PUT, &path).multipart(&[("sprite", "image/png", PNG)]).send().await
+        .assert_status(StatusCode::OK)
+        .json();
+    assert_eq!(uploaded["sprite_url"], path);
+    let sprite = app.get(&path).await.assert_status(StatusCode::OK);
+    assert_eq!(sprite.header("content-type").unwrap(), "image/png");
+    assert_eq!(&sprite.body[..], PNG);
+    let etag = sprite.header("etag").unwrap().to_str().unwrap().to_string();
+    app.request(Method::GET, &path).header("if-none-match", &etag).send().await.assert_status(StatusCode::NOT_MODIFIED);
+    assert_eq!(sprite.header("accept-ranges").unwrap(), "bytes");
+    let part = app.request(Method::GET, &path).header("range", "bytes=-4").send().await.assert_status(StatusCode::PARTIAL_CONTENT);
+    assert_eq!(&part.body[..], &PNG[PNG.len() - 4..]);
+
+    app.request(Method::PUT, &path).multipart(&[("sprite", "image/png", b"not an image")]).send().await
+        .assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    app.request(Method::PUT, &path).multipart(&[("sprite", "image/gif", PNG)]).send().await
+        .assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    app.request(Method::PUT, &path).multipart(&[("other", "image/png", PNG)]).send().await
+        .assert_error(StatusCode::BAD_REQUEST, "no sprite field");
+    let huge = [PNG, &vec![0; 1024 * 1024]].concat();
+    app.request(Method::PUT, &path).multipart(&[("sprite", "image/png", &huge)]).send().await
+        .assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+    app.request(Method::PUT, "/pokemon/99/sprite").multipart(&[("sprite", "image/png", PNG)]).send().await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn share_links() {
+    let app = TestApp::new();
+    let id = app.create_pokemon("Pikachu", 5).await;
+    let share = app.request(Method::POST, &format!("/pokemon/{}/share", id)).send().await.assert_status(StatusCode::CREATED).json();
+    let token = share["token"].as_str().unwrap().to_string();
+    app.post(&format!("/pokemon/{}/share?expires_in=0", id), json!({})).await.assert_status(StatusCode::BAD_REQUEST);
+    app.post("/pokemon/99/share", json!({})).await.assert_status(StatusCode::NOT_FOUND);
+
+    let shares = app.get(&format!("/pokemon/{}/shares", id)).await.assert_status(StatusCode::OK).json();
+    assert_eq!(shares.as_array().unwrap().len(), 1);
+    app.get("/pokemon/99/shares").await.assert_status(StatusCode::NOT_FOUND);
+    let shared = app.get(&format!("/shares/{}", token)).await.assert_status(StatusCode::OK).json();
+    assert_eq!(shared["id"], id);
+    app.get("/shares/forged").await.assert_status(StatusCode::FORBIDDEN);
+
+    let trainer = app.create_trainer("Ash", &[]).await;
+    app.post_as(99, &format!("/shares/{}/redeem", token), json!({ "trainer_id": 99 })).await.assert_status(StatusCode::NOT_FOUND);
+    let copy = app.post_as(trainer, &format!("/shares/{}/redeem", token), json!({ "trainer_id": trainer })).await
+        .assert_status(StatusCode::CREATED)
+        .json();
+    assert_ne!(copy["id"], id);
+    app.post_as(trainer, &format!("/shares/{}/redeem", token), json!({ "trainer_id": trainer })).await.assert_status(StatusCode::CONFLICT);
+
+    app.delete(&format!("/shares/{}", token)).await.assert_status(StatusCode::NO_CONTENT);
+    app.delete(&format!("/shares/{}", token)).await.assert_status(StatusCode::NOT_FOUND);
+    app.get(&format!("/shares/{}", token)).await.assert_status(StatusCode::FORBIDDEN);
+
+    app.post(&format!("/pokemon/{}/share", id), json!({})).await.assert_status(StatusCode::CREATED);
+    app.delete(&format!("/pokemon/{}/shares", id)).await.assert_status(StatusCode::NO_CONTENT);
+    assert_eq!(app.get(&format!("/pokemon/{}/shares", id)).await.json(), json!([]));
+    app.delete("/pokemon/99/shares").await.assert_status(StatusCode::NOT_FOUND);
+
+    let qr = app.get(&format!("/pokemon/{}/qr", id)).await.assert_status(StatusCode::OK);
+    assert_eq!(qr.header("content-type").unwrap(), "image/png");
+    app.get("/pokemon/99/qr").await.assert_status(StatusCode::NOT_FOUND);
+
+    // seeing someone's Pokemon isn't enough to hand out, list or revoke its links
+    let ash = app.create_trainer("Ash", &[id]).await;
+    let gary = app.create_trainer("Gary", &[]).await;
+    for (method, path) in [(Method::POST, "share"), (Method::GET, "shares"), (Method::DELETE, "shares"), (Method::GET, "qr")] {
+        let path = format!("/pokemon/{}/{}", id, path);
+        app.request(method.clone(), &path).trainer(gary).send().await.assert_status(StatusCode::FORBIDDEN);
+        app.request(method.clone(), &path).send().await.assert_status(StatusCode::FORBIDDEN);
+        assert!(app.request(method, &path).trainer(ash).send().await.status.is_success());
+    }
+}
+
+// the cookie the pages hand out and the token that goes in their forms, what a browser would send back
+async fn csrf(app: &TestApp) -> (String, String) {
+    let page = app.get("/web/pokemon").await;
+    let cookie = page.header("set-cookie").unwrap().to_str().unwrap().split(';').next().unwrap().to_string();
+    let token = cookie.split_once('=').unwrap().1.to_string();
+    assert!(page.text().contains(&format!("name=\"csrf_token\" value=\"{}\"", token)));
+    (cookie, token)
+}
+
+#[tokio::test]
+async fn web_pages() {
+    let app = TestApp::new();
+    let list = app.get("/web/pokemon").await.assert_status(StatusCode::OK);
+    assert!(list.header("content-type").unwrap().to_str().unwrap().starts_with("text/html"));
+    let (cookie, token) = csrf(&app).await;
+    let post = |path: &str, form: &str| {
+        app.request(Method::POST, path).header("cookie", &cookie).form(&format!("csrf_token={}&{}", token, form))
+    };
+
+    post("/web/pokemon", "name=Pikachu&poke_type=Electric&level=5").send().await
+        .assert_status(StatusCode::SEE_OTHER);
+    post("/web/pokemon", "name=Pikachu&poke_type=Electric&level=500").send().await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    app.get("/web/pokemon/1").await.assert_status(StatusCode::OK);
+    app.get("/web/pokemon/99").await.assert_status(StatusCode::NOT_FOUND);
+    post("/web/pokemon/1", "name=Pikachu&poke_type=Electric&level=6").send().await
+        .assert_status(StatusCode::SEE_OTHER);
+    let page = post("/web/pokemon/1", "name=Pikachu&poke_type=Electric&level=0").send().await
+        .assert_status(StatusCode::BAD_REQUEST);
+    assert!(page.text().contains("level must be 1 to 100"));
+    post("/web/pokemon/99", "name=Pikachu&poke_type=Electric&level=6").send().await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    app.create_trainer("Ash", &[1]).await;
+    post("/web/pokemon/1/delete", "").send().await.assert_status(StatusCode::CONFLICT);
+    app.create_pokemon("Bulbasaur", 5).await;
+    post("/web/pokemon/2/delete", "").send().await.assert_status(StatusCode::SEE_OTHER);
+}
+
+#[tokio::test]
+async fn web_edits_need_write_access() {
+    let app = TestApp::new();
+    let id = app.create_pokemon("Pikachu", 10).await;
+    let ash = app.create_trainer("Ash", &[id]).await;
+    let misty = app.create_trainer("Misty", &[]).await;
+    app.request(Method::POST, &format!("/pokemon/{}/permissions", id)).trainer(ash).json(json!({ "trainer_id": misty, "access": "read" })).send().await
+        .assert_status(StatusCode::CREATED);
+    let (cookie, token) = csrf(&app).await;
+    let update = || {
+        app.request(Method::POST, &format!("/web/pokemon/{}", id)).header("cookie", &cookie)
+            .form(&format!("csrf_token={}&name=Pikachu&poke_type=Electric&level=50", token))
+    };
+
+    let page = update().trainer(misty).send().await.assert_status(StatusCode::FORBIDDEN);
+    assert!(page.text().contains("hasn't let trainer"));
+    update().send().await.assert_status(StatusCode::FORBIDDEN);
+    assert_eq!(app.get(&format!("/pokemon/{}", id)).await.json()["level"], 10);
+    update().trainer(ash).send().await.assert_status(StatusCode::SEE_OTHER);
+    assert_eq!(app.get(&format!("/pokemon/{}", id)).await.json()["level"], 50);
+}
+
+#[tokio::test]
+async fn web_pages_show_what_the_viewer_can_see() {
+    let app = TestApp::new();
+    let id = app.create_pokemon("Snorlax", 30).await;
+    let ash = app.create_trainer("Ash", &[id]).await;
+    app.request(Method::PUT, &format!("/pokemon/{}", id)).trainer(ash).json(json!({ "visibility": "private" })).send().await
+        .assert_status(StatusCode::OK);
+    let page = |path: String| app.request(Method::GET, &path);
+
+    assert!(!app.get("/web/pokemon").await.text().contains("Snorlax"));
+    assert!(page("/web/pokemon".to_string()).trainer(ash).send().await.text().contains("Snorlax"));
+    page(format!("/web/pokemon/{}", id)).send().await.assert_status(StatusCode::NOT_FOUND);
+    page(format!("/web/pokemon/{}", id)).trainer(ash).send().await.assert_status(StatusCode::OK);
+
+    // deleting it says nothing about it to anyone else
+    let (cookie, token) = csrf(&app).await;
+    let delete = || app.request(Method::POST, &format!("/web/pokemon/{}/delete", id)).header("cookie", &cookie).form(&format!("csrf_token={}", token));
+    let refused = delete().send().await.assert_status(StatusCode::NOT_FOUND);
+    assert!(!refused.text().contains("Snorlax"));
+    delete().trainer(ash).send().await.assert_status(StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn web_forms_need_the_csrf_token() {
+    let app = TestApp::new();
+    let (cookie, token) = csrf(&app).await;
+    // no cookie, as from a page on another site
+    app.request(Method::POST, "/web/pokemon").form(&format!("csrf_token={}&name=Pikachu&level=5", token)).send().await
+        .assert_status(StatusCode::FORBIDDEN);
+    // the cookie but not the field
+    app.request(Method::POST, "/web/pokemon").header("cookie", &cookie).form("name=Pikachu&level=5").send().await
+        .assert_status(StatusCode::FORBIDDEN);
+    app.request(Method::POST, "/web/pokemon").header("cookie", &cookie).form(&format!("csrf_token={}&name=Pikachu&level=5", "0".repeat(64))).send().await
+        .assert_status(StatusCode::FORBIDDEN);
+    assert!(app.get("/pokemon").await.json().as_array().unwrap().is_empty());
+
+    // a page that already has the cookie doesn't set another
+    let page = app.request(Method::GET, "/web/pokemon").header("cookie", &cookie).send().await;
+    assert!(page.header("set-cookie").is_none());
+    assert!(page.text().contains(&token));
+}
+
+#[tokio::test]
+async fn security_headers() {
+    let app = TestApp::new();
+    let page = app.get("/web/pokemon").await;
+    assert_eq!(page.header("x-content-type-options").unwrap(), "nosniff");
+    assert_eq!(page.header("x-frame-options").unwrap(), "DENY");
+    assert_eq!(page.header("content-security-policy").unwrap(), headers::DEFAULT_CSP);
+    assert!(page.header("strict-transport-security").is_none());
+    // JSON doesn't need a CSP
+    let json = app.get("/pokemon").await;
+    assert_eq!(json.header("referrer-policy").unwrap(), "strict-origin-when-cross-origin");
+    assert!(json.header("content-security-policy").is_none());
+
+    let app = TestApp::with(|builder| builder.security_headers(SecurityHeaders::default()
+        .csp(Some("default-src 'none'")).unwrap()
+        .hsts(Some(60))));
+    let page = app.get("/web/pokemon").await;
+    assert_eq!(page.header("content-security-policy").unwrap(), "default-src 'none'");
+    assert_eq!(page.header("strict-transport-security").unwrap(), "max-age=60; includeSubDomains");
+
+    let app = TestApp::with(|builder| builder.security_headers(SecurityHeaders::off()));
+    assert!(app.get("/web/pokemon").await.header("x-frame-options").is_none());
+}
+
+#[tokio::test]
+async fn caching_headers() {
+    let app = TestApp::new();
+    app.create_pokemon("Pikachu", 5).await;
+    let list = app.get("/pokemon").await.assert_status(StatusCode::OK);
+    assert_eq!(list.header("cache-control").unwrap(), "public, max-age=5");
+    assert!(list.headers.get_all("vary").iter().any(|v| v == "x-trainer-id"));
+    let last_modified = list.header("last-modified").unwrap().to_str().unwrap().to_string();
+
+    let unchanged = app.request(Method::GET, "/pokemon").header("if-modified-since", &last_modified).send().await
+        .assert_status(StatusCode::NOT_MODIFIED);
+    assert!(unchanged.body.is_empty());
+    assert_eq!(unchanged.header("last-modified").unwrap().to_str().unwrap(), last_modified);
+    // it's always at least a second later once anything has changed
+    app.create_pokemon("Eevee", 5).await;
+    let changed = app.request(Method::GET, "/pokemon").header("if-modified-since", &last_modified).send().await
+        .assert_status(StatusCode::OK);
+    assert_ne!(changed.header("last-modified").unwrap().to_str().unwrap(), last_modified);
+
+    assert_eq!(app.get("/types/fire/vs/grass").await.header("cache-control").unwrap(), "public, max-age=86400");
+    assert_eq!(app.get("/pokemon/random").await.header("cache-control").unwrap(), "no-store");
+    let mine = app.request(Method::GET, "/pokemon").trainer(1).send().await;
+    assert_eq!(mine.header("cache-control").unwrap(), "private, max-age=5");
+    // errors are never cached
+    assert!(app.get("/pokemon/99").await.header("cache-control").is_none());
+
+    let app = TestApp::with(|builder| builder.cache_policy(CachePolicy::default().ttl(0).static_ttl(60)));
+    assert_eq!(app.get("/pokemon").await.header("cache-control").unwrap(), "public, max-age=0");
+    assert_eq!(app.get("/shop").await.header("cache-control").unwrap(), "public, max-age=60");
+    let app = TestApp::with(|builder| builder.cache_policy(CachePolicy::off()));
+    assert!(app.get("/pokemon").await.header("cache-control").is_none());
+}
+
+#[tokio::test]
+async fn response_cache() {
+    let app = TestApp::new();
+    let (pikachu, eevee) = (app.create_pokemon("Pikachu", 5).await, app.create_pokemon("Eevee", 5).await);
+    let (pikachu_path, eevee_path) = (format!("/pokemon/{}", pikachu), format!("/pokemon/{}", eevee));
+    assert_eq!(app.get(&pikachu_path).await.header("x-cache").unwrap(), "miss");
+    assert_eq!(app.get(&eevee_path).await.header("x-cache").unwrap(), "miss");
+    app.get("/pokemon").await;
+    let hit = app.get(&pikachu_path).await.assert_status(StatusCode::OK);
+    assert_eq!(hit.header("x-cache").unwrap(), "hit");
+    assert_eq!(hit.json()["name"], "Pikachu");
+    // a HEAD or OPTIONS isn't a write
+    app.request(Method::HEAD, &eevee_path).send().await.assert_status(StatusCode::OK);
+    app.request(Method::OPTIONS, &eevee_path).send().await;
+    assert_eq!(app.get(&eevee_path).await.header("x-cache").unwrap(), "hit");
+    // someone else asking is cached on its own
+    assert_eq!(app.request(Method::GET, &pikachu_path).trainer(1).send().await.header("x-cache").unwrap(), "miss");
+
+    // a write drops that Pokemon and the lists, the other one stays
+    app.post(&format!("{}/nickname", pikachu_path), json!({ "nickname": "Sparky" })).await.assert_status(StatusCode::OK);
+    let fresh = app.get(&pikachu_path).await;
+    assert_eq!(fresh.header("x-cache").unwrap(), "miss");
+    assert_eq!(fresh.json()["nickname"], "Sparky");
+    assert_eq!(app.get("/pokemon").await.header("x-cache").unwrap(), "miss");
+    assert_eq!(app.get(&eevee_path).await.header("x-cache").unwrap(), "hit");
+    // falling through to a 404 isn't kept
+    app.get("/pokemon/99").await.assert_status(StatusCode::NOT_FOUND);
+    assert_eq!(app.get("/pokemon/99").await.header("x-cache").unwrap(), "miss");
+
+    let stats = app.admin_get("/admin/cache").await.assert_status(StatusCode::OK).json();
+    assert_eq!(stats["hits"], 3);
+    assert!(stats["invalidations"].as_u64().unwrap() >= 2);
+    app.request(Method::DELETE, "/admin/cache").admin().send().await.assert_status(StatusCode::NO_CONTENT);
+    assert_eq!(app.get(&eevee_path).await.header("x-cache").unwrap(), "miss");
+
+    // least recently used goes first
+    let app = TestApp::with(|builder| builder.response_cache(ResponseCache::default().capacity(2)));
+    app.create_pokemon("Pikachu", 5).await;
+    for path in ["/pokemon/1", "/pokemon", "/pokemon/1", "/trainers"] {
+        app.get(path).await;
+    }
+    assert_eq!(app.get("/pokemon/1").await.header("x-cache").unwrap(), "hit");
+    assert_eq!(app.get("/pokemon").await.header("x-cache").unwrap(), "miss");
+    assert_eq!(app.admin_get("/admin/cache").await.json()["evictions"], 2);
+}
+
+#[tokio::test]
+async fn long_lists_are_streamed() {
+    let app = TestApp::new();
+    app.admin_post("/admin/generate?count=2500&seed=7", json!({})).await.assert_status(StatusCode::CREATED);
+    let list = app.get("/pokemon").await.assert_status(StatusCode::OK);
+    assert!(list.header("content-length").is_none());
+    assert_eq!(list.header("content-type").unwrap(), "application/json");
+    let pokemon = list.json();
+    assert_eq!(pokemon.as_array().unwrap().len(), 2500);
+    assert_eq!(pokemon[2499]["id"], 2500);
+    // too long to cache
+    assert_eq!(app.get("/pokemon").await.header("x-cache").unwrap(), "miss");
+
+    // a short one still goes out whole
+    let favorites = app.get("/pokemon?favorite=true").await;
+    assert!(favorites.header("content-length").is_some());
+    assert_eq!(favorites.json(), json!([]));
+}
+
+#[tokio::test]
+async fn browser_ui_is_served() {
+    let app = TestApp::new();
+    let page = app.get("/ui/").await.assert_status(StatusCode::OK);
+    assert!(page.text().contains("<html"));
+    // the page does its own routing
+    app.get("/ui/pokemon/1").await.assert_status(StatusCode::OK);
+}
@@ -0,0 +1,38 @@
+// cargo test --features pprof
+#![cfg(feature = "pprof")]
+
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::TestApp;
+
+#[tokio::test]
+async fn cpu_profiles() {
+    let app = TestApp::new();
+    app.get("/debug/pprof/profile?seconds=1").await.assert_status(StatusCode::UNAUTHORIZED);
+    let profile = |query: &'static str| app.request(Method::GET, query).admin().send();
+    profile("/debug/pprof/profile?seconds=0").await.assert_error(StatusCode::BAD_REQUEST, "1 to 60");
+    profile("/debug/pprof/profile?format=jpeg").await.assert_error(StatusCode::BAD_REQUEST, "pprof or flamegraph");
+
+    // one at a time
+    let (first, ()) = tokio::join!(profile("/debug/pprof/profile?seconds=1"), async {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        profile("/debug/pprof/profile?seconds=1").await.assert_error(StatusCode::CONFLICT, "already");
+    });
+    let first = first.assert_status(StatusCode::OK);
+    assert_eq!(first.header("content-type").unwrap(), "application/octet-stream");
+    assert!(!first.body.is_empty());
+
+    // something for it to see
+    let busy = tokio::task::spawn_blocking(|| {
+        let started = std::time::Instant::now();
+        let mut n = 0u64;
+        while started.elapsed() < std::time::Duration::from_millis(800) {
+            n = std::hint::black_box(n.wrapping_add(1));
+        }
+    });
+    let flamegraph = profile("/debug/pprof/profile?seconds=1&format=flamegraph").await.assert_status(StatusCode::OK);
+    busy.await.unwrap();
+    assert_eq!(flamegraph.header("content-type").unwrap(), "image/svg+xml");
+    assert!(flamegraph.text().contains("<svg") && !flamegraph.text().contains("No samples"), "{}", flamegraph.text());
+}
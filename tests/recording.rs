@@ -0,0 +1,42 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::{TestApp, ADMIN_TOKEN};
+use rust_hello_http::recording::{self, Recorder};
+use serde_json::json;
+
+#[tokio::test]
+async fn record_and_replay() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("traffic.jsonl");
+    let app = TestApp::with(|builder| builder.recorder(Recorder::to_file(&path).unwrap()));
+    let pikachu = app.create_pokemon("Pikachu", 10).await;
+    app.put(&format!("/pokemon/{}", pikachu), json!({ "level": 12 })).await.assert_status(StatusCode::OK);
+    app.get(&format!("/pokemon/{}", pikachu)).await.assert_status(StatusCode::OK);
+    app.get("/pokemon/99").await.assert_status(StatusCode::NOT_FOUND);
+    app.request(Method::GET, "/admin/check").admin().send().await.assert_status(StatusCode::OK);
+
+    let exchanges = recording::read(&path).unwrap();
+    assert_eq!(exchanges.len(), 5);
+    assert_eq!((exchanges[0].request.method.as_str(), exchanges[0].request.uri.as_str(), exchanges[0].response.status), ("POST", "/pokemon", 201));
+    assert!(exchanges[0].request.body.body.as_deref().unwrap().contains("Pikachu"));
+    assert!(exchanges[2].response.body.body.as_deref().unwrap().contains("\"level\":12"));
+    // the token isn't written down
+    assert_eq!(exchanges[4].request.headers["authorization"], recording::REDACTED);
+    assert!(!std::fs::read_to_string(&path).unwrap().contains(ADMIN_TOKEN));
+
+    // the same answers from a fresh server, timestamps aside
+    let fresh = TestApp::new();
+    let url = format!("http://{}", fresh.serve().await);
+    let report = recording::replay(&url, &exchanges, Some(ADMIN_TOKEN)).await.unwrap();
+    assert_eq!((report.replayed, report.matched, report.skipped), (5, 5, 0), "{:?}", report.mismatches);
+
+    // one that no longer comes back the same
+    let mut changed = exchanges.clone();
+    changed[1].request.body.body = Some(json!({ "level": 30 }).to_string());
+    let report = recording::replay(&format!("http://{}", TestApp::new().serve().await), &changed, Some(ADMIN_TOKEN)).await.unwrap();
+    let mismatch = &report.mismatches[0];
+    assert_eq!((mismatch.exchange, mismatch.status), (2, 200));
+    assert!(mismatch.differences.contains(&"$.level".to_string()), "{:?}", mismatch.differences);
+    assert_eq!(report.mismatches.len(), 2);
+}
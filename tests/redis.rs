@@ -0,0 +1,218 @@
+mod common;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::{extract::Path, http::{Method, StatusCode}, routing::get, Json, Router};
+use common::TestApp;
+use rust_hello_http::pokeapi::PokeApi;
+use rust_hello_http::redis::{Redis, Reply};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+// enough of a Redis to be a cache, every command it's sent is kept
+#[derive(Default)]
+struct FakeRedis{
+    values: Mutex<HashMap<String, Vec<u8>>>,
+    commands: Mutex<Vec<Vec<String>>>,
+    // hangs up on anything it's sent
+    down: AtomicBool,
+}
+
+impl FakeRedis{
+    fn commands(&self) -> Vec<Vec<String>> {
+        self.commands.lock().unwrap().clone()
+    }
+
+    fn answer(&self, args: &[Vec<u8>]) -> Vec<u8> {
+        let text: Vec<String> = args.iter().map(|a| String::from_utf8_lossy(a).to_string()).collect();
+        self.commands.lock().unwrap().push(text.clone());
+        match text[0].as_str() {
+            "PING" => b"+PONG\r\n".to_vec(),
+            "AUTH" if text.last().map(String::as_str) == Some("hunter22") => b"+OK\r\n".to_vec(),
+            "AUTH" => b"-WRONGPASS invalid username-password pair\r\n".to_vec(),
+            "SELECT" => b"+OK\r\n".to_vec(),
+            "SET" => {
+                self.values.lock().unwrap().insert(text[1].clone(), args[2].clone());
+                b"+OK\r\n".to_vec()
+            }
+            "GET" => match self.values.lock().unwrap().get(&text[1]) {
+                Some(value) => [format!("${}\r\n", value.len()).as_bytes(), value, b"\r\n"].concat(),
+                None => b"$-1\r\n".to_vec(),
+            },
+            other => format!("-ERR unknown command '{}'\r\n", other).into_bytes(),
+        }
+    }
+}
+
+async fn read_command(reader: &mut BufReader<tokio::net::TcpStream>) -> Option<Vec<Vec<u8>>> {
+    let mut line = String::new();
+    reader.read_line(&mut line).await.ok().filter(|&n| n > 0)?;
+    let count: usize = line.trim_end().strip_prefix('*')?.parse().ok()?;
+    let mut args = Vec::new();
+    for _ in 0..count {
+        line.clear();
+        reader.read_line(&mut line).await.ok()?;
+        let length: usize = line.trim_end().strip_prefix('$')?.parse().ok()?;
+        let mut arg = vec![0; length + 2];
+        reader.read_exact(&mut arg).await.ok()?;
+        arg.truncate(length);
+        args.push(arg);
+    }
+    Some(args)
+}
+
+async fn fake_redis() -> (String, Arc<FakeRedis>) {
+    let fake = Arc::new(FakeRedis::default());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = fake.clone();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let fake = server.clone();
+            tokio::spawn(async move {
+                let mut reader = BufReader::new(stream);
+                while let Some(args) = read_command(&mut reader).await {
+                    if fake.down.load(Ordering::SeqCst) {
+                        return;
+                    }
+                    let reply = fake.answer(&args);
+                    if reader.get_mut().write_all(&reply).await.is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    (format!("redis://{}", addr), fake)
+}
+
+// a pokeapi that only knows Pikachu and counts how often it's asked
+async fn fake_pokeapi() -> (String, Arc<AtomicUsize>) {
+    let asked = Arc::new(AtomicUsize::new(0));
+    let counter = asked.clone();
+    let router = Router::new().route("/pokemon/{name}", get(move |Path(name): Path<String>| async move {
+        counter.fetch_add(1, Ordering::SeqCst);
+        if name != "pikachu" {
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Ok(Json(json!({
+            "height": 4,
+            "weight": 60,
+            "types": [{ "slot": 1, "type": { "name": "electric" } }],
+            "stats": [{ "base_stat": 35, "stat": { "name": "hp" } }, { "base_stat": 90, "stat": { "name": "speed" } }],
+            "sprites": { "front_default": "https://sprites.example/25.png" },
+        })))
+    }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    (format!("http://{}", addr), asked)
+}
+
+#[tokio::test]
+async fn lookups_are_shared_between_instances() {
+    let (redis_url, fake) = fake_redis().await;
+    let (pokeapi_url, asked) = fake_pokeapi().await;
+    let instance = || TestApp::with(|builder| builder
+        .pokeapi(PokeApi::new(&pokeapi_url))
+        .redis(Some(Redis::new(&redis_url).unwrap().cache_ttl(Duration::from_secs(60)))));
+    let (first, second) = (instance(), instance());
+
+    let created = first.post("/pokemon", json!({ "name": "Pikachu", "level": 5 })).await.assert_status(StatusCode::CREATED).json();
+    assert_eq!(created["poke_type"], "Electric");
+    assert_eq!(asked.load(Ordering::SeqCst), 1);
+    let set = fake.commands().into_iter().find(|c| c[0] == "SET").unwrap();
+    assert_eq!((set[1].as_str(), set[3].as_str(), set[4].as_str()), ("pokemon-api:pokeapi:pikachu", "EX", "60"));
+
+    // the second instance has never looked Pikachu up, Redis has
+    let created = second.post("/pokemon", json!({ "name": "Pikachu", "level": 5 })).await.assert_status(StatusCode::CREATED).json();
+    assert_eq!(created["poke_type"], "Electric");
+    assert_eq!(asked.load(Ordering::SeqCst), 1);
+
+    // a species pokeapi doesn't know is remembered as one
+    second.post("/pokemon", json!({ "name": "Fakemon", "level": 5 })).await.assert_status(StatusCode::BAD_REQUEST);
+    first.post("/pokemon", json!({ "name": "Fakemon", "level": 5 })).await.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(asked.load(Ordering::SeqCst), 2);
+    assert_eq!(fake.values.lock().unwrap()["pokemon-api:pokeapi:fakemon"], b"null");
+}
+
+#[tokio::test]
+async fn lookups_go_to_pokeapi_while_redis_is_down() {
+    let (redis_url, fake) = fake_redis().await;
+    let (pokeapi_url, asked) = fake_pokeapi().await;
+    fake.down.store(true, Ordering::SeqCst);
+    let app = TestApp::with(|builder| builder.pokeapi(PokeApi::new(&pokeapi_url)).redis(Some(Redis::new(&redis_url).unwrap())));
+
+    let created = app.post("/pokemon", json!({ "name": "Pikachu", "level": 5 })).await.assert_status(StatusCode::CREATED).json();
+    assert_eq!(created["poke_type"], "Electric");
+    assert_eq!(asked.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn the_url_picks_the_login_and_database() {
+    let (url, fake) = fake_redis().await;
+    let address = url.trim_start_matches("redis://");
+    let redis = Redis::new(&format!("redis://ash:hunter22@{}/3", address)).unwrap().prefix("test:");
+    redis.set("greeting", b"hi", None).await.unwrap();
+    assert_eq!(redis.get("greeting").await.unwrap(), Some(b"hi".to_vec()));
+    assert_eq!(redis.get("nothing").await.unwrap(), None);
+    assert_eq!(redis.command(&[b"FLUSHALL"]).await, Err("ERR unknown command 'FLUSHALL'".to_string()));
+    let commands = fake.commands();
+    assert_eq!(commands[..3], [vec!["AUTH", "ash", "hunter22"], vec!["SELECT", "3"], vec!["SET", "test:greeting", "hi"]]);
+    assert_eq!(redis.command(&[b"PING"]).await, Ok(Reply::Status("PONG".to_string())));
+    // the connection's kept, so it only logged in once
+    assert_eq!(fake.commands().iter().filter(|c| c[0] == "AUTH").count(), 1);
+
+    let wrong = Redis::new(&format!("redis://:nope@{}", address)).unwrap();
+    assert!(wrong.ping().await.unwrap_err().contains("WRONGPASS"));
+}
+
+#[test]
+fn bad_urls_are_refused() {
+    assert!(Redis::new("rediss://cache:6380").unwrap_err().contains("stunnel"));
+    assert!(Redis::new("http://cache").is_err());
+    assert!(Redis::new("redis://cache/zero").is_err());
+    assert!(Redis::new("redis://cache:6379/2").is_ok());
+}
+
+fn check<'a>(readiness: &'a Value, name: &str) -> &'a Value {
+    readiness["checks"].as_array().unwrap().iter().find(|c| c["name"] == name).unwrap()
+}
+
+#[tokio::test]
+async fn readiness_reports_redis_but_doesnt_need_it() {
+    let (url, fake) = fake_redis().await;
+    let app = TestApp::with(|builder| builder.redis(Some(Redis::new(&url).unwrap())));
+
+    let ready = app.get("/readyz").await.assert_status(StatusCode::OK).json();
+    assert_eq!(ready["ready"], true);
+    assert_eq!(check(&ready, "storage")["ok"], true);
+    assert_eq!(check(&ready, "redis")["ok"], true);
+    assert!(check(&ready, "redis")["detail"].as_str().unwrap().starts_with("answered in "));
+
+    fake.down.store(true, Ordering::SeqCst);
+    let ready = app.get("/readyz").await.assert_status(StatusCode::OK).json();
+    assert_eq!(ready["ready"], true);
+    assert_eq!((&check(&ready, "redis")["ok"], &check(&ready, "redis")["required"]), (&json!(false), &json!(false)));
+
+    // without REDIS_URL there's nothing to check
+    let ready = TestApp::new().get("/readyz").await.assert_status(StatusCode::OK).json();
+    assert!(ready["checks"].as_array().unwrap().iter().all(|c| c["name"] != "redis"));
+}
+
+#[tokio::test]
+async fn maintenance_mode_isnt_ready() {
+    let app = TestApp::new();
+    app.request(Method::PUT, "/admin/mode").admin().json(json!({ "mode": "maintenance", "message": "moving house" })).send().await
+        .assert_status(StatusCode::OK);
+    let ready = app.get("/readyz").await.assert_status(StatusCode::SERVICE_UNAVAILABLE).json();
+    assert_eq!(ready["ready"], false);
+    assert_eq!((&check(&ready, "mode")["ok"], &check(&ready, "mode")["detail"]), (&json!(false), &json!("moving house")));
+
+    app.request(Method::PUT, "/admin/mode").admin().json(json!({ "mode": "read_only" })).send().await.assert_status(StatusCode::OK);
+    let ready = app.get("/readyz").await.assert_status(StatusCode::OK).json();
+    assert_eq!(check(&ready, "mode")["detail"], "read_only");
+}
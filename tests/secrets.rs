@@ -0,0 +1,62 @@
+use axum::extract::Path;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use rust_hello_http::secrets::{self, lookup};
+use serde_json::json;
+use std::collections::HashMap;
+
+// an environment of its own, so the real one is left alone
+fn env(vars: &[(&str, String)]) -> impl Fn(&str) -> Option<String> {
+    let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.clone())).collect();
+    move |name| vars.get(name).cloned()
+}
+
+#[test]
+fn from_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let token = dir.path().join("admin_token");
+    std::fs::write(&token, "s3cret\n").unwrap();
+    let path = token.display().to_string();
+    let store = HashMap::from([("ADMIN_TOKEN".to_string(), "from-vault".to_string())]);
+
+    assert_eq!(lookup("ADMIN_TOKEN", env(&[("ADMIN_TOKEN_FILE", path.clone())]), &store).unwrap().as_deref(), Some("s3cret"));
+    assert_eq!(lookup("ADMIN_TOKEN", env(&[("ADMIN_TOKEN", "plain".into())]), &store).unwrap().as_deref(), Some("plain"));
+    // the store only fills in what isn't set any other way
+    assert_eq!(lookup("ADMIN_TOKEN", env(&[]), &store).unwrap().as_deref(), Some("from-vault"));
+    assert_eq!(lookup("SHARE_SECRET", env(&[]), &store).unwrap(), None);
+
+    let both = env(&[("ADMIN_TOKEN", "plain".into()), ("ADMIN_TOKEN_FILE", path)]);
+    assert!(lookup("ADMIN_TOKEN", both, &store).unwrap_err().contains("not both"));
+    let missing = env(&[("ADMIN_TOKEN_FILE", dir.path().join("nope").display().to_string())]);
+    assert!(lookup("ADMIN_TOKEN", missing, &store).unwrap_err().starts_with("ADMIN_TOKEN_FILE"));
+    std::fs::write(&token, "\n").unwrap();
+    assert!(lookup("ADMIN_TOKEN", env(&[("ADMIN_TOKEN_FILE", token.display().to_string())]), &store).unwrap_err().contains("empty"));
+
+    assert!(secrets::store(&json!({ "ADMIN_TOKEN": 42 })).unwrap_err().contains("isn't a string"));
+    assert!(secrets::store(&json!(["ADMIN_TOKEN"])).is_err());
+}
+
+#[tokio::test]
+async fn from_vault() {
+    // stands in for Vault, with a version 2 secret and a version 1 one
+    async fn read(headers: HeaderMap, Path(path): Path<String>) -> Response {
+        if headers.get("x-vault-token").is_none_or(|t| t != "root") {
+            return (StatusCode::FORBIDDEN, Json(json!({ "errors": ["permission denied"] }))).into_response();
+        }
+        match path.as_str() {
+            "secret/data/pokemon" => Json(json!({ "data": { "data": { "ADMIN_TOKEN": "v2" }, "metadata": { "version": 3 } } })).into_response(),
+            "kv/pokemon" => Json(json!({ "data": { "SHARE_SECRET": "v1" } })).into_response(),
+            _ => StatusCode::NOT_FOUND.into_response(),
+        }
+    }
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, Router::new().route("/v1/{*path}", get(read))).await.unwrap() });
+
+    assert_eq!(secrets::vault(&addr, "root", "secret/data/pokemon").await.unwrap()["ADMIN_TOKEN"], "v2");
+    assert_eq!(secrets::vault(&addr, "root", "/kv/pokemon").await.unwrap()["SHARE_SECRET"], "v1");
+    assert!(secrets::vault(&addr, "wrong", "secret/data/pokemon").await.unwrap_err().contains("403"));
+    assert!(secrets::vault(&addr, "root", "secret/data/missing").await.unwrap_err().contains("404"));
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use std::time::Duration;
+
+use axum::http::{Method, StatusCode};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use common::TestApp;
+use rust_hello_http::auth::{self, Auth};
+use rust_hello_http::models::unix_time;
+use serde_json::{json, Value};
+
+#[tokio::test]
+async fn sessions_say_who_is_asking() {
+    let app = TestApp::with(|builder| builder.auth(Auth::default().trust_header(false)));
+    let pikachu = app.create_pokemon("Pikachu", 10).await;
+    app.put(&format!("/pokemon/{}", pikachu), json!({ "visibility": "private" })).await.assert_status(StatusCode::OK);
+    let ash = common::id(&app.post("/trainers", json!({ "name": "Ash", "team": [pikachu] })).await.json());
+    let path = format!("/pokemon/{}", pikachu);
+
+    let session = app.state.auth.issue(ash, unix_time());
+    app.request(Method::GET, &path).session(&session.token).send().await.assert_status(StatusCode::OK);
+    // with the header not trusted, claiming to be ash gets nowhere
+    app.request(Method::GET, &path).trainer(ash).send().await.assert_status(StatusCode::NOT_FOUND);
+
+    let tampered = format!("{}x", session.token);
+    app.request(Method::GET, &path).session(&tampered).send().await.assert_error(StatusCode::UNAUTHORIZED, "isn't valid");
+    // a session from a key this server doesn't have
+    let elsewhere = Auth::default().issue(ash, unix_time());
+    app.request(Method::GET, &path).session(&elsewhere.token).send().await.assert_error(StatusCode::UNAUTHORIZED, "isn't valid");
+    let short = TestApp::with(|builder| builder.auth(Auth::default().lifetime(Duration::ZERO)));
+    let token = short.state.auth.issue(ash, unix_time()).token;
+    short.request(Method::GET, "/pokemon").session(&token).send().await.assert_error(StatusCode::UNAUTHORIZED, "has expired");
+}
+
+#[tokio::test]
+async fn session_keys_rotate() {
+    let old = || auth::parse_keys("11".repeat(32).as_str()).unwrap();
+    let rotated = || auth::parse_keys(&format!("{}\n{} # the old one, until its sessions run out", "22".repeat(32), "11".repeat(32))).unwrap();
+    let now = unix_time();
+    let before = Auth::default().keys(old()).issue(7, now);
+
+    // the new key signs from now on, and the old one still checks what it signed
+    let app = TestApp::with(|builder| builder.auth(Auth::default().keys(rotated())));
+    let jwks = app.get("/.well-known/jwks.json").await.assert_status(StatusCode::OK).json();
+    let kids: Vec<&str> = jwks["keys"].as_array().unwrap().iter().map(|k| k["kid"].as_str().unwrap()).collect();
+    assert_eq!(kids, [rotated()[0].id.as_str(), old()[0].id.as_str()]);
+    assert_eq!((jwks["keys"][0]["kty"].as_str(), jwks["keys"][0]["crv"].as_str()), (Some("OKP"), Some("Ed25519")));
+    assert_eq!(app.state.auth.verify(&before.token, now), Ok(7));
+    let after = app.state.auth.issue(7, now);
+    let header: Value = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(after.token.split('.').next().unwrap()).unwrap()).unwrap();
+    assert_eq!(header["kid"], kids[0]);
+
+    // once it's dropped, its sessions end
+    let dropped = Auth::default().keys(auth::parse_keys(&"22".repeat(32)).unwrap());
+    assert_eq!(dropped.verify(&after.token, now), Ok(7));
+    assert!(dropped.verify(&before.token, now).unwrap_err().contains("isn't valid"));
+    assert!(auth::parse_keys("# nothing").is_err());
+}
@@ -0,0 +1,385 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{Method, StatusCode};
+use common::{id, TestApp};
+use rust_hello_http::deterministic::{FrozenClock, Time};
+use rust_hello_http::mail::{self, Mailer};
+use serde_json::json;
+
+#[tokio::test]
+async fn create_and_read_trainers() {
+    let app = TestApp::new();
+    let pikachu = app.create_pokemon("Pikachu", 10).await;
+    let trainer = app.post("/trainers", json!({ "name": "Ash", "team": [pikachu] })).await
+        .assert_status(StatusCode::CREATED)
+        .json();
+    assert_eq!(trainer["team"], json!([pikachu]));
+    assert_eq!(trainer["money"], 3000);
+
+    let all = app.get("/trainers").await.assert_status(StatusCode::OK).json();
+    assert_eq!(all.as_array().unwrap().len(), 1);
+    app.get(&format!("/trainers/{}", id(&trainer))).await.assert_status(StatusCode::OK);
+    app.get("/trainers/99").await.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn trainer_teams_are_checked() {
+    let app = TestApp::new();
+    let pikachu = app.create_pokemon("Pikachu", 10).await;
+    app.post("/trainers", json!({ "name": "Ash", "team": [99] })).await.assert_status(StatusCode::NOT_FOUND);
+    app.post("/trainers", json!({ "name": "Ash", "team": [pikachu, pikachu] })).await.assert_error(StatusCode::BAD_REQUEST, "twice");
+    let seven: Vec<u32> = (1..=7).collect();
+    app.post("/trainers", json!({ "name": "Ash", "team": seven })).await.assert_error(StatusCode::BAD_REQUEST, "at most 6");
+    app.create_trainer("Ash", &[pikachu]).await;
+    app.post("/trainers", json!({ "name": "Gary", "team": [pikachu] })).await.assert_error(StatusCode::CONFLICT, "already belongs to Ash");
+}
+
+#[tokio::test]
+async fn set_a_trainers_team() {
+    let app = TestApp::new();
+    let pikachu = app.create_pokemon("Pikachu", 10).await;
+    let bulbasaur = app.create_pokemon("Bulbasaur", 10).await;
+    let ash = app.create_trainer("Ash", &[pikachu]).await;
+    let set_team = |trainer: u32, by: u32, team: Vec<u32>| app.request(Method::PUT, &format!("/trainers/{}/team", trainer)).trainer(by).json(json!({ "team": team }));
+    let trainer = set_team(ash, ash, vec![bulbasaur]).send().await
+        .assert_status(StatusCode::OK)
+        .json();
+    assert_eq!(trainer["team"], json!([bulbasaur]));
+    // left off the team, so into the box
+    assert_eq!(trainer["box"], json!([pikachu]));
+    app.put("/trainers/99/team", json!({ "team": [] })).await.assert_status(StatusCode::NOT_FOUND);
+    set_team(ash, ash, vec![99]).send().await.assert_status(StatusCode::NOT_FOUND);
+    let gary = app.create_trainer("Gary", &[]).await;
+    set_team(gary, gary, vec![pikachu]).send().await.assert_status(StatusCode::FORBIDDEN);
+
+    // only the trainer sets their team
+    app.put(&format!("/trainers/{}/team", ash), json!({ "team": [pikachu] })).await.assert_error(StatusCode::FORBIDDEN, "say which trainer");
+    set_team(ash, gary, vec![pikachu]).send().await.assert_error(StatusCode::FORBIDDEN, "not trainer");
+    // and moves in only what they may change, a private Pokemon of someone else's isn't even there
+    app.request(Method::PUT, &format!("/pokemon/{}", pikachu)).trainer(ash).json(json!({ "visibility": "private" })).send().await
+        .assert_status(StatusCode::OK);
+    set_team(gary, gary, vec![pikachu]).send().await.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn trades() {
+    let app = TestApp::new();
+    let pikachu = app.create_pokemon("Pikachu", 10).await;
+    let bulbasaur = app.create_pokemon("Bulbasaur", 10).await;
+    let ash = app.create_trainer("Ash", &[pikachu]).await;
+    let gary = app.create_trainer("Gary", &[bulbasaur]).await;
+    let offer = json!({ "from_trainer": ash, "to_trainer": gary, "offered": pikachu, "requested": bulbasaur });
+
+    app.post_as(ash, "/trades", json!({ "from_trainer": ash, "to_trainer": ash, "offered": pikachu, "requested": bulbasaur })).await
+        .assert_status(StatusCode::BAD_REQUEST);
+    app.post_as(ash, "/trades", json!({ "from_trainer": ash, "to_trainer": 99, "offered": pikachu, "requested": bulbasaur })).await
+        .assert_status(StatusCode::NOT_FOUND);
+    app.post_as(ash, "/trades", json!({ "from_trainer": ash, "to_trainer": gary, "offered": bulbasaur, "requested": pikachu })).await
+        .assert_error(StatusCode::CONFLICT, "doesn't own");
+
+    // the trainer in the body has to be the one asking
+    app.post_as(gary, "/trades", offer.clone()).await.assert_error(StatusCode::FORBIDDEN, "only trainer");
+    app.post("/trades", offer.clone()).await.assert_status(StatusCode::FORBIDDEN);
+    let trade = id(&app.post_as(ash, "/trades", offer.clone()).await.assert_status(StatusCode::CREATED).json());
+    app.get(&format!("/trades/{}", trade)).await.assert_status(StatusCode::OK);
+    app.get("/trades/99").await.assert_status(StatusCode::NOT_FOUND);
+    let theirs = app.get(&format!("/trainers/{}/trades", gary)).await.assert_status(StatusCode::OK).json();
+    assert_eq!(theirs.as_array().unwrap().len(), 1);
+    app.get("/trainers/99/trades").await.assert_status(StatusCode::NOT_FOUND);
+
+    // only the one it was offered to can accept, and only as themselves
+    app.post_as(ash, &format!("/trades/{}/accept", trade), json!({ "trainer_id": gary })).await.assert_error(StatusCode::FORBIDDEN, "not trainer");
+    app.post_as(ash, &format!("/trades/{}/accept", trade), json!({ "trainer_id": ash })).await.assert_status(StatusCode::FORBIDDEN);
+    app.post_as(gary, "/trades/99/accept", json!({ "trainer_id": gary })).await.assert_status(StatusCode::NOT_FOUND);
+    let accepted = app.post_as(gary, &format!("/trades/{}/accept", trade), json!({ "trainer_id": gary })).await
+        .assert_status(StatusCode::OK)
+        .json();
+    assert_eq!(accepted["status"], "accepted");
+    assert_eq!(app.get(&format!("/trainers/{}", ash)).await.json()["team"], json!([bulbasaur]));
+    app.post_as(gary, &format!("/trades/{}/accept", trade), json!({ "trainer_id": gary })).await.assert_status(StatusCode::CONFLICT);
+
+    // and back again, so there's something to turn down and take back
+    let back = json!({ "from_trainer": ash, "to_trainer": gary, "offered": bulbasaur, "requested": pikachu });
+    let declined = id(&app.post_as(ash, "/trades", back.clone()).await.json());
+    app.post_as(ash, &format!("/trades/{}/decline", declined), json!({ "trainer_id": ash })).await.assert_status(StatusCode::FORBIDDEN);
+    let trade = app.post_as(gary, &format!("/trades/{}/decline", declined), json!({ "trainer_id": gary })).await.assert_status(StatusCode::OK).json();
+    assert_eq!(trade["status"], "declined");
+    let cancelled = id(&app.post_as(ash, "/trades", back).await.json());
+    app.post_as(gary, &format!("/trades/{}/cancel", cancelled), json!({ "trainer_id": gary })).await.assert_status(StatusCode::FORBIDDEN);
+    let trade = app.post_as(ash, &format!("/trades/{}/cancel", cancelled), json!({ "trainer_id": ash })).await.assert_status(StatusCode::OK).json();
+    assert_eq!(trade["status"], "cancelled");
+    app.post_as(ash, &format!("/trades/{}/cancel", cancelled), json!({ "trainer_id": ash })).await.assert_status(StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn shop() {
+    let app = TestApp::new();
+    let shop = app.get("/shop").await.assert_status(StatusCode::OK).json();
+    assert!(shop.as_array().unwrap().iter().all(|item| item["item"] != "master_ball"));
+
+    let ash = app.create_trainer("Ash", &[]).await;
+    // nobody spends ash's money but ash
+    app.post("/shop/buy", json!({ "trainer_id": ash, "item": "great_ball", "quantity": 2 })).await
+        .assert_error(StatusCode::FORBIDDEN, "say which trainer is asking");
+    let trainer = app.post_as(ash, "/shop/buy", json!({ "trainer_id": ash, "item": "great_ball", "quantity": 2 })).await
+        .assert_status(StatusCode::OK)
+        .json();
+    assert_eq!(trainer["money"], 3000 - 2 * 600);
+    assert_eq!(trainer["bag"]["great_ball"], 2);
+    app.post_as(ash, "/shop/buy", json!({ "trainer_id": ash, "item": "great_ball", "quantity": 0 })).await.assert_status(StatusCode::BAD_REQUEST);
+    app.post_as(ash, "/shop/buy", json!({ "trainer_id": ash, "item": "master_ball" })).await.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    app.post_as(ash, "/shop/buy", json!({ "trainer_id": ash, "item": "ultra_ball", "quantity": 100 })).await.assert_status(StatusCode::CONFLICT);
+    app.post_as(ash, "/shop/buy", json!({ "trainer_id": ash, "item": "poke_ball", "quantity": u32::MAX })).await.assert_status(StatusCode::BAD_REQUEST);
+    app.post_as(99, "/shop/buy", json!({ "trainer_id": 99, "item": "poke_ball" })).await.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn market() {
+    let app = TestApp::new();
+    let pikachu = app.create_pokemon("Pikachu", 10).await;
+    let ash = app.create_trainer("Ash", &[pikachu]).await;
+    let gary = app.create_trainer("Gary", &[]).await;
+
+    app.post_as(ash, "/market", json!({ "trainer_id": ash, "pokemon_id": pikachu, "price": 0 })).await.assert_status(StatusCode::BAD_REQUEST);
+    app.post_as(99, "/market", json!({ "trainer_id": 99, "pokemon_id": pikachu, "price": 100 })).await.assert_status(StatusCode::NOT_FOUND);
+    app.post_as(gary, "/market", json!({ "trainer_id": gary, "pokemon_id": pikachu, "price": 100 })).await.assert_status(StatusCode::CONFLICT);
+    let listing = id(&app.post_as(ash, "/market", json!({ "trainer_id": ash, "pokemon_id": pikachu, "price": 100 })).await
+        .assert_status(StatusCode::CREATED)
+        .json());
+    app.post_as(ash, "/market", json!({ "trainer_id": ash, "pokemon_id": pikachu, "price": 100 })).await
+        .assert_error(StatusCode::CONFLICT, "already on the market");
+
+    assert_eq!(app.get("/market").await.assert_status(StatusCode::OK).json().as_array().unwrap().len(), 1);
+    app.get(&format!("/market/{}", listing)).await.assert_status(StatusCode::OK);
+    app.get("/market/99").await.assert_status(StatusCode::NOT_FOUND);
+
+    app.post_as(ash, &format!("/market/{}/buy", listing), json!({ "trainer_id": ash })).await.assert_status(StatusCode::BAD_REQUEST);
+    app.post_as(99, &format!("/market/{}/buy", listing), json!({ "trainer_id": 99 })).await.assert_status(StatusCode::NOT_FOUND);
+    app.post_as(gary, "/market/99/buy", json!({ "trainer_id": gary })).await.assert_status(StatusCode::NOT_FOUND);
+    let sold = app.post_as(gary, &format!("/market/{}/buy", listing), json!({ "trainer_id": gary })).await.assert_status(StatusCode::OK).json();
+    assert_eq!(sold["status"], "sold");
+    assert_eq!(app.get(&format!("/trainers/{}", gary)).await.json()["money"], 2900);
+    app.post_as(gary, &format!("/market/{}/buy", listing), json!({ "trainer_id": gary })).await.assert_status(StatusCode::CONFLICT);
+
+    let relisted = id(&app.post_as(gary, "/market", json!({ "trainer_id": gary, "pokemon_id": pikachu, "price": 5000 })).await.json());
+    app.post_as(ash, &format!("/market/{}/buy", relisted), json!({ "trainer_id": ash })).await.assert_error(StatusCode::CONFLICT, "can't afford");
+    app.post_as(ash, &format!("/market/{}/withdraw", relisted), json!({ "trainer_id": ash })).await.assert_status(StatusCode::FORBIDDEN);
+    app.post_as(gary, &format!("/market/{}/withdraw", relisted), json!({ "trainer_id": gary })).await.assert_status(StatusCode::OK);
+    app.post_as(gary, &format!("/market/{}/withdraw", relisted), json!({ "trainer_id": gary })).await.assert_status(StatusCode::CONFLICT);
+    app.post_as(gary, "/market/99/withdraw", json!({ "trainer_id": gary })).await.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn achievements_and_leaderboards() {
+    let app = TestApp::new();
+    let ash = app.create_trainer("Ash", &[]).await;
+    let progress = app.get(&format!("/trainers/{}/achievements", ash)).await.assert_status(StatusCode::OK).json();
+    assert_eq!(progress["earned"], json!([]));
+    app.get("/trainers/99/achievements").await.assert_status(StatusCode::NOT_FOUND);
+
+    for metric in ["highest_level", "battles_won", "collection"] {
+        let board = app.get(&format!("/leaderboards/{}", metric)).await.assert_status(StatusCode::OK).json();
+        assert_eq!(board["metric"], metric);
+    }
+    app.get("/leaderboards/nope").await.assert_status(StatusCode::NOT_FOUND);
+}
+
+// the mailer writes .eml files, in no particular order and decoded just enough to read the body
+async fn mail_in(dir: &std::path::Path, count: usize) -> Vec<String> {
+    for _ in 0..100 {
+        let files = std::fs::read_dir(dir).map_or(0, |entries| entries.count());
+        if files >= count {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    std::fs::read_dir(dir).unwrap()
+        .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap().replace("=\r\n", "").replace("=3D", "="))
+        .collect()
+}
+
+#[tokio::test]
+async fn email_verification_and_trade_offers() {
+    let mail = tempfile::tempdir().unwrap();
+    let app = TestApp::with(|builder| builder.mailer(Mailer::dir(mail.path())));
+    mail::spawn_mailer(app.state.clone());
+    let pikachu = app.create_pokemon("Pikachu", 10).await;
+    let eevee = app.create_pokemon("Eevee", 10).await;
+
+    app.post("/trainers", json!({ "name": "Ash", "email": "ash@pallet" })).await
+        .assert_error(StatusCode::BAD_REQUEST, "isn't an email address");
+    let ash = id(&app.post("/trainers", json!({ "name": "Ash", "team": [pikachu] })).await.json());
+    let misty = app.post("/trainers", json!({ "name": "Misty", "team": [eevee], "email": "misty@cerulean.example" })).await
+        .assert_status(StatusCode::CREATED)
+        .json();
+    assert_eq!(misty["email"], "misty@cerulean.example");
+    assert_eq!(misty["email_verified"], false);
+    let misty = id(&misty);
+
+    // the address is only shown to misty
+    assert_eq!(app.request(Method::GET, &format!("/trainers/{}", misty)).trainer(misty).send().await.json()["email"], "misty@cerulean.example");
+    assert!(app.get(&format!("/trainers/{}", misty)).await.json().get("email").is_none());
+    let listed = app.request(Method::GET, "/trainers").trainer(ash).send().await.json();
+    assert!(listed.as_array().unwrap().iter().all(|t| t.get("email").is_none()));
+
+    // not verified yet, so the offer isn't mailed
+    let offer = json!({ "from_trainer": ash, "to_trainer": misty, "offered": pikachu, "requested": eevee });
+    app.post_as(ash, "/trades", offer.clone()).await.assert_status(StatusCode::CREATED);
+    let sent = mail_in(mail.path(), 1).await;
+    assert_eq!(sent.len(), 1);
+    assert!(sent[0].contains("To: misty@cerulean.example") && sent[0].contains("Subject: Confirm your email"));
+    let link = sent[0].split_whitespace().find(|word| word.contains("/verify-email?token=")).unwrap();
+    let path = &link[link.find("/trainers/").unwrap()..];
+
+    app.get(&format!("/trainers/{}/verify-email?token=00ff", misty)).await
+        .assert_error(StatusCode::FORBIDDEN, "isn't valid");
+    app.get(&path.replace(&format!("/trainers/{}/", misty), &format!("/trainers/{}/", ash))).await
+        .assert_status(StatusCode::FORBIDDEN);
+    let verified = app.get(path).await.assert_status(StatusCode::OK).json();
+    assert_eq!(verified["email_verified"], true);
+    // once only
+    app.get(path).await.assert_status(StatusCode::FORBIDDEN);
+    app.post(&format!("/trainers/{}/verify-email", misty), json!({})).await.assert_error(StatusCode::CONFLICT, "already verified");
+    app.post(&format!("/trainers/{}/verify-email", ash), json!({})).await.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+
+    app.post_as(ash, "/trades", offer).await.assert_status(StatusCode::CREATED);
+    let sent = mail_in(mail.path(), 2).await;
+    assert_eq!(sent.len(), 2);
+    let offer = sent.iter().find(|m| m.contains("Subject: Ash sent you a trade offer")).unwrap();
+    assert!(offer.contains(&format!("Ash offered you Pokemon {} for your Pokemon {}", pikachu, eevee)));
+}
+
+#[tokio::test]
+async fn verification_links_expire() {
+    let mail = tempfile::tempdir().unwrap();
+    let app = TestApp::with(|builder| builder.mailer(Mailer::dir(mail.path()).token_lifetime(Duration::ZERO)));
+    mail::spawn_mailer(app.state.clone());
+    let misty = id(&app.post("/trainers", json!({ "name": "Misty", "email": "misty@cerulean.example" })).await.json());
+    let link = |message: &str| {
+        let link = message.split_whitespace().find(|word| word.contains("/verify-email?token=")).unwrap();
+        link[link.find("/trainers/").unwrap()..].to_string()
+    };
+
+    let first = link(&mail_in(mail.path(), 1).await[0]);
+    app.get(&first).await.assert_error(StatusCode::GONE, "expired");
+    app.post(&format!("/trainers/{}/verify-email", misty), json!({})).await.assert_status(StatusCode::ACCEPTED);
+    let sent = mail_in(mail.path(), 2).await;
+    let second = sent.iter().map(|m| link(m)).find(|l| *l != first).unwrap();
+    // the old one was used up by trying it, the new one has no time either
+    app.get(&first).await.assert_status(StatusCode::FORBIDDEN);
+    app.get(&second).await.assert_status(StatusCode::GONE);
+}
+
+#[tokio::test]
+async fn forgotten_passwords() {
+    let mail = tempfile::tempdir().unwrap();
+    let clock = Arc::new(FrozenClock::at(1_000));
+    let app = TestApp::with(|builder| builder.mailer(Mailer::dir(mail.path())).time(Time::new(clock.clone())));
+    mail::spawn_mailer(app.state.clone());
+    let misty = id(&app.post("/trainers", json!({ "name": "Misty", "email": "misty@cerulean.example" })).await.json());
+    let password = |body: serde_json::Value| app.request(Method::PUT, &format!("/trainers/{}/password", misty)).json(body);
+
+    password(json!({ "password": "togepi" })).trainer(misty).send().await.assert_error(StatusCode::BAD_REQUEST, "8 to 128");
+    password(json!({ "password": "starmie123" })).send().await.assert_error(StatusCode::FORBIDDEN, "only trainer");
+    password(json!({ "password": "starmie123" })).trainer(misty).send().await.assert_status(StatusCode::NO_CONTENT);
+    password(json!({ "password": "psyduck123" })).trainer(misty).send().await.assert_error(StatusCode::FORBIDDEN, "send current_password");
+    password(json!({ "password": "psyduck123", "current_password": "wrong one" })).send().await.assert_status(StatusCode::FORBIDDEN);
+
+    // nothing is mailed until the address is verified, and the answer doesn't say whether anyone has it
+    app.post("/trainers/forgot-password", json!({ "email": "misty@cerulean.example" })).await.assert_status(StatusCode::ACCEPTED);
+    app.post("/trainers/forgot-password", json!({ "email": "brock@pewter.example" })).await.assert_status(StatusCode::ACCEPTED);
+    let verify = mail_in(mail.path(), 1).await.remove(0);
+    let link = verify.split_whitespace().find(|word| word.contains("/verify-email?token=")).unwrap();
+    app.get(&link[link.find("/trainers/").unwrap()..]).await.assert_status(StatusCode::OK);
+
+    let token = |sent: &[String]| {
+        let reset = sent.iter().find(|m| m.contains("Subject: Reset your password")).unwrap();
+        reset.split_whitespace().find(|word| word.starts_with(&format!("{}.", misty)) && word.len() > 2).unwrap().to_string()
+    };
+    app.post("/trainers/forgot-password", json!({ "email": "Misty@cerulean.example" })).await.assert_status(StatusCode::ACCEPTED);
+    let first = token(&mail_in(mail.path(), 2).await);
+    app.post("/trainers/reset-password", json!({ "token": format!("{}.00ff", misty), "password": "psyduck123" })).await
+        .assert_error(StatusCode::FORBIDDEN, "isn't valid");
+    app.post("/trainers/reset-password", json!({ "token": first, "password": "psyduck" })).await.assert_status(StatusCode::BAD_REQUEST);
+    app.post("/trainers/reset-password", json!({ "token": first, "password": "psyduck123" })).await.assert_status(StatusCode::NO_CONTENT);
+    // once only, and the old password is gone
+    app.post("/trainers/reset-password", json!({ "token": first, "password": "golduck123" })).await.assert_status(StatusCode::FORBIDDEN);
+    password(json!({ "password": "golduck123", "current_password": "starmie123" })).send().await.assert_status(StatusCode::FORBIDDEN);
+    password(json!({ "password": "golduck123", "current_password": "psyduck123" })).send().await.assert_status(StatusCode::NO_CONTENT);
+    let login = |password: &str| app.post("/auth/password", json!({ "trainer_id": misty, "password": password }));
+    login("psyduck123").await.assert_error(StatusCode::UNAUTHORIZED, "don't match");
+    let signed_in = login("golduck123").await.assert_status(StatusCode::OK).json();
+    assert_eq!(signed_in["session"]["trainer_id"], misty);
+    app.post("/auth/password", json!({ "trainer_id": 99, "password": "golduck123" })).await.assert_status(StatusCode::UNAUTHORIZED);
+
+    // an hour, on the state's clock
+    app.post("/trainers/forgot-password", json!({ "email": "misty@cerulean.example" })).await.assert_status(StatusCode::ACCEPTED);
+    let second = token(&mail_in(mail.path(), 3).await.into_iter().filter(|m| !m.contains(&first)).collect::<Vec<_>>());
+    clock.advance(60 * 60);
+    app.post("/trainers/reset-password", json!({ "token": second, "password": "psyduck123" })).await.assert_error(StatusCode::GONE, "expired");
+}
+
+#[tokio::test]
+async fn notifications() {
+    let app = TestApp::new();
+    let pikachu = app.create_pokemon("Pikachu", 10).await;
+    let bulbasaur = app.create_pokemon("Bulbasaur", 10).await;
+    let ash = app.create_trainer("Ash", &[pikachu]).await;
+    let gary = app.create_trainer("Gary", &[bulbasaur]).await;
+    let inbox = |trainer: u32, query: &str| app.request(Method::GET, &format!("/trainers/{}/notifications{}", trainer, query)).trainer(trainer);
+
+    let trade = id(&app.post_as(ash, "/trades", json!({ "from_trainer": ash, "to_trainer": gary, "offered": pikachu, "requested": bulbasaur })).await.json());
+    app.request(Method::POST, "/battles").trainer(ash).json(json!({ "side_a": [pikachu], "side_b": [bulbasaur], "seed": 7 })).send().await
+        .assert_status(StatusCode::CREATED);
+
+    let garys = inbox(gary, "").send().await.assert_status(StatusCode::OK).json();
+    let garys = garys.as_array().unwrap();
+    assert_eq!(garys.len(), 2);
+    // newest first
+    assert_eq!(garys[0]["kind"], "battle_result");
+    assert_eq!(garys[1]["kind"], "trade_offer");
+    assert_eq!(garys[1]["trade_id"], trade);
+    assert_eq!(garys[1]["message"], format!("Ash offered you Pokemon {} for your Pokemon {}", pikachu, bulbasaur));
+    assert_eq!(inbox(ash, "").send().await.json().as_array().unwrap().len(), 1);
+
+    // only the trainer it's for
+    app.get(&format!("/trainers/{}/notifications", gary)).await.assert_status(StatusCode::FORBIDDEN);
+    app.request(Method::GET, &format!("/trainers/{}/notifications", gary)).trainer(ash).send().await.assert_status(StatusCode::FORBIDDEN);
+    app.request(Method::GET, "/trainers/99/notifications").trainer(99).send().await.assert_status(StatusCode::NOT_FOUND);
+
+    let count = |trainer: u32| app.request(Method::GET, &format!("/trainers/{}/notifications/unread-count", trainer)).trainer(trainer);
+    assert_eq!(count(gary).send().await.json()["unread"], 2);
+    let read = app.request(Method::POST, &format!("/trainers/{}/notifications/{}/read", gary, id(&garys[1]))).trainer(gary).send().await
+        .assert_status(StatusCode::OK)
+        .json();
+    assert_eq!(read["read"], true);
+    assert_eq!(count(gary).send().await.json()["unread"], 1);
+    assert_eq!(inbox(gary, "?unread=true").send().await.json().as_array().unwrap().len(), 1);
+    // someone else's notification isn't there to mark
+    app.request(Method::POST, &format!("/trainers/{}/notifications/{}/read", ash, id(&garys[1]))).trainer(ash).send().await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    app.request(Method::POST, &format!("/trainers/{}/notifications/read", gary)).trainer(gary).send().await.assert_status(StatusCode::NO_CONTENT);
+    assert_eq!(count(gary).send().await.json()["unread"], 0);
+    assert_eq!(count(ash).send().await.json()["unread"], 1);
+}
+
+#[tokio::test]
+async fn daycare_level_ups_are_notified() {
+    let app = TestApp::new();
+    let pikachu = app.create_pokemon("Pikachu", 10).await;
+    let ash = app.create_trainer("Ash", &[pikachu]).await;
+    // what the daycare job does after a payout that was worth a level
+    rust_hello_http::inbox::level_up(&app.state, pikachu, "Pikachu", 11);
+    let unowned = app.create_pokemon("Eevee", 5).await;
+    rust_hello_http::inbox::level_up(&app.state, unowned, "Eevee", 6);
+
+    let all = app.request(Method::GET, &format!("/trainers/{}/notifications", ash)).trainer(ash).send().await.json();
+    assert_eq!(all.as_array().unwrap().len(), 1);
+    assert_eq!(all[0]["kind"], "level_up");
+    assert_eq!(all[0]["pokemon_id"], pikachu);
+    assert_eq!(all[0]["message"], "Pikachu grew to level 11 at the daycare");
+}
@@ -0,0 +1,144 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::{id, TestApp};
+use rust_hello_http::encounters::Ball;
+use serde_json::json;
+
+#[tokio::test]
+async fn wild_encounters() {
+    let app = TestApp::new();
+    let encounter = app.get("/encounters").await.assert_status(StatusCode::OK).json();
+    assert_eq!(encounter["region"], "kanto");
+    assert_eq!(encounter["status"], "active");
+    app.get("/encounters?region=kanto&area=cave").await.assert_status(StatusCode::OK);
+    app.get("/encounters?region=atlantis").await.assert_error(StatusCode::NOT_FOUND, "no wild pokemon");
+    app.get(&format!("/encounters/{}", id(&encounter))).await.assert_status(StatusCode::OK);
+    app.get("/encounters/99").await.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn attack_a_wild_pokemon() {
+    let app = TestApp::new();
+    let encounter = id(&app.get("/encounters").await.json());
+    let pikachu = app.create_pokemon("Pikachu", 50).await;
+    let known = app.get(&format!("/pokemon/{}", pikachu)).await.json()["moves"][0].clone();
+    let path = format!("/encounters/{}/attack", encounter);
+    app.post(&path, json!({ "pokemon_id": pikachu, "move": "Surf" })).await.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    app.post(&path, json!({ "pokemon_id": 99, "move": known })).await.assert_status(StatusCode::NOT_FOUND);
+    app.post("/encounters/99/attack", json!({ "pokemon_id": pikachu, "move": known })).await.assert_status(StatusCode::NOT_FOUND);
+    let fainted = app.create_pokemon("Bulbasaur", 5).await;
+    app.post(&format!("/pokemon/{}/damage", fainted), json!({ "amount": 9999 })).await;
+    app.post(&path, json!({ "pokemon_id": fainted, "move": "Tackle" })).await.assert_error(StatusCode::UNPROCESSABLE_ENTITY, "fainted");
+
+    // a level 50 usually knocks it out in one go
+    let result = app.post(&path, json!({ "pokemon_id": pikachu, "move": known })).await.assert_status(StatusCode::OK).json();
+    assert!(!result["events"].as_array().unwrap().is_empty());
+    if result["encounter"]["status"] == "fainted" {
+        app.post(&path, json!({ "pokemon_id": pikachu, "move": known })).await.assert_error(StatusCode::CONFLICT, "is gone");
+    }
+}
+
+#[tokio::test]
+async fn catch_a_wild_pokemon() {
+    let app = TestApp::new();
+    let ash = app.create_trainer("Ash", &[]).await;
+    let encounter = id(&app.get("/encounters").await.json());
+    let path = format!("/encounters/{}/catch", encounter);
+    app.post_as(ash, &path, json!({ "trainer_id": ash, "ball": "great_ball" })).await.assert_error(StatusCode::CONFLICT, "no Great Balls");
+    app.post_as(99, &path, json!({ "trainer_id": 99 })).await.assert_status(StatusCode::NOT_FOUND);
+    app.post_as(ash, "/encounters/99/catch", json!({ "trainer_id": ash })).await.assert_status(StatusCode::NOT_FOUND);
+
+    // a master ball never misses
+    app.state.trainers.lock().unwrap()[0].bag.insert(Ball::Master, 1);
+    let result = app.post_as(ash, &path, json!({ "trainer_id": ash, "ball": "master_ball" })).await.assert_status(StatusCode::OK).json();
+    assert_eq!(result["caught"], true);
+    assert_eq!(result["trainer"]["team"], json!([1]));
+    app.get("/pokemon/1").await.assert_status(StatusCode::OK);
+    app.post_as(ash, &path, json!({ "trainer_id": ash })).await.assert_error(StatusCode::CONFLICT, "is gone");
+
+    let progress = app.get(&format!("/trainers/{}/achievements", ash)).await.json();
+    assert!(progress["earned"].as_array().unwrap().iter().any(|a| a["id"] == "first_catch"));
+}
+
+#[tokio::test]
+async fn breeding_and_eggs() {
+    let app = TestApp::new();
+    let pikachu = app.create_pokemon("Pikachu", 20).await;
+    let rattata = app.create_pokemon("Rattata", 20).await;
+    let bulbasaur = app.create_pokemon("Bulbasaur", 20).await;
+    let pichu = app.create_pokemon("Pichu", 20).await;
+
+    app.post("/daycare/breed", json!({ "parent_a": pikachu, "parent_b": pikachu })).await.assert_status(StatusCode::BAD_REQUEST);
+    app.post("/daycare/breed", json!({ "parent_a": pikachu, "parent_b": 99 })).await.assert_status(StatusCode::NOT_FOUND);
+    app.post("/daycare/breed", json!({ "parent_a": pikachu, "parent_b": bulbasaur })).await
+        .assert_error(StatusCode::UNPROCESSABLE_ENTITY, "egg group");
+    app.post("/daycare/breed", json!({ "parent_a": pichu, "parent_b": pikachu })).await
+        .assert_error(StatusCode::UNPROCESSABLE_ENTITY, "can't breed");
+    let egg = id(&app.post("/daycare/breed", json!({ "parent_a": pikachu, "parent_b": rattata })).await
+        .assert_status(StatusCode::CREATED)
+        .json());
+
+    assert_eq!(app.get("/eggs").await.assert_status(StatusCode::OK).json().as_array().unwrap().len(), 1);
+    app.get(&format!("/eggs/{}", egg)).await.assert_status(StatusCode::OK);
+    app.get("/eggs/99").await.assert_status(StatusCode::NOT_FOUND);
+
+    let path = format!("/eggs/{}/walk", egg);
+    app.post(&path, json!({ "steps": 0 })).await.assert_status(StatusCode::BAD_REQUEST);
+    app.post("/eggs/99/walk", json!({ "steps": 10 })).await.assert_status(StatusCode::NOT_FOUND);
+    let hatched = app.post(&path, json!({ "steps": 1_000_000 })).await.assert_status(StatusCode::OK).json();
+    let baby = hatched["hatched_into"].as_u64().expect("it hatched");
+    // the first stage of parent_a's line
+    assert_eq!(app.get(&format!("/pokemon/{}", baby)).await.json()["species"], "Pichu");
+    app.post(&path, json!({ "steps": 10 })).await.assert_error(StatusCode::CONFLICT, "already hatched");
+}
+
+#[tokio::test]
+async fn daycare() {
+    let app = TestApp::new();
+    let pikachu = app.create_pokemon("Pikachu", 20).await;
+    let stay = app.post("/daycare/deposit", json!({ "pokemon_id": pikachu })).await.assert_status(StatusCode::CREATED).json();
+    assert_eq!(stay["pokemon_id"], pikachu);
+    app.post("/daycare/deposit", json!({ "pokemon_id": pikachu })).await.assert_error(StatusCode::CONFLICT, "already at the daycare");
+    app.post("/daycare/deposit", json!({ "pokemon_id": 99 })).await.assert_status(StatusCode::NOT_FOUND);
+    assert_eq!(app.get("/daycare").await.assert_status(StatusCode::OK).json().as_array().unwrap().len(), 1);
+
+    // it'd be pointed at from the daycare
+    let other = app.create_pokemon("Pikachu", 20).await;
+    app.post("/pokemon/merge", json!({ "keep": other, "merge": pikachu })).await.assert_error(StatusCode::CONFLICT, "daycare");
+
+    let result = app.post("/daycare/withdraw", json!({ "pokemon_id": pikachu })).await.assert_status(StatusCode::OK).json();
+    assert_eq!(result["pokemon"]["id"], pikachu);
+    app.post("/daycare/withdraw", json!({ "pokemon_id": pikachu })).await.assert_status(StatusCode::NOT_FOUND);
+
+    app.post(&format!("/pokemon/{}/damage", other), json!({ "amount": 9999 })).await;
+    app.post("/daycare/deposit", json!({ "pokemon_id": other })).await.assert_error(StatusCode::CONFLICT, "fainted");
+}
+
+#[tokio::test]
+async fn other_trainers_pokemon_stay_out_of_the_wild_and_the_daycare() {
+    let app = TestApp::new();
+    let pikachu = app.create_pokemon("Pikachu", 20).await;
+    let rattata = app.create_pokemon("Rattata", 20).await;
+    let hidden = app.create_pokemon("Pikachu", 20).await;
+    let ash = app.create_trainer("Ash", &[pikachu, rattata, hidden]).await;
+    let gary = app.create_trainer("Gary", &[]).await;
+    app.request(Method::PUT, &format!("/pokemon/{}", hidden)).trainer(ash).json(json!({ "visibility": "private" })).send().await
+        .assert_status(StatusCode::OK);
+    let send = |trainer: u32, path: &str, body: serde_json::Value| app.request(Method::POST, path).trainer(trainer).json(body).send();
+
+    let attack = format!("/encounters/{}/attack", id(&app.get("/encounters").await.json()));
+    let known = app.get(&format!("/pokemon/{}", pikachu)).await.json()["moves"][0].clone();
+    send(gary, &attack, json!({ "pokemon_id": pikachu, "move": known })).await.assert_error(StatusCode::FORBIDDEN, "hasn't let trainer");
+    send(gary, &attack, json!({ "pokemon_id": hidden, "move": known })).await.assert_status(StatusCode::NOT_FOUND);
+    send(gary, "/daycare/breed", json!({ "parent_a": pikachu, "parent_b": rattata })).await.assert_status(StatusCode::FORBIDDEN);
+    send(gary, "/daycare/breed", json!({ "parent_a": hidden, "parent_b": rattata })).await.assert_status(StatusCode::NOT_FOUND);
+    send(gary, "/daycare/deposit", json!({ "pokemon_id": pikachu })).await.assert_status(StatusCode::FORBIDDEN);
+    send(gary, "/daycare/deposit", json!({ "pokemon_id": hidden })).await.assert_status(StatusCode::NOT_FOUND);
+    assert_eq!(app.get("/daycare").await.json(), json!([]));
+
+    send(ash, "/daycare/deposit", json!({ "pokemon_id": pikachu })).await.assert_status(StatusCode::CREATED);
+    send(gary, "/daycare/withdraw", json!({ "pokemon_id": pikachu })).await.assert_status(StatusCode::FORBIDDEN);
+    send(ash, "/daycare/withdraw", json!({ "pokemon_id": pikachu })).await.assert_status(StatusCode::OK);
+    send(ash, "/daycare/breed", json!({ "parent_a": pikachu, "parent_b": rattata })).await.assert_status(StatusCode::CREATED);
+}